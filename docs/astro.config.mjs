@@ -49,11 +49,29 @@ export default defineConfig({
             { label: "Environment Variables", slug: "providers/env" },
             { label: "LastPass", slug: "providers/lastpass" },
             { label: "1Password", slug: "providers/onepassword" },
+            { label: "Vault KV", slug: "providers/vault" },
+            { label: "Vault Dynamic Secrets", slug: "providers/vault-dynamic" },
+            { label: "Git Team Store", slug: "providers/gitvault" },
+            { label: "GPG", slug: "providers/gpg" },
+            { label: "Deployment Platforms", slug: "providers/paas" },
+          ],
+        },
+        {
+          label: "Integrations",
+          items: [
+            { label: "devenv and Dev Containers", slug: "integrations/devenv" },
+            { label: "Nix", slug: "integrations/nix" },
+            { label: "Ansible", slug: "integrations/ansible" },
+            { label: "Terraform", slug: "integrations/terraform" },
+            { label: "Docker", slug: "integrations/docker" },
           ],
         },
         {
           label: "SDK",
-          items: [{ label: "Rust SDK", slug: "sdk/rust" }],
+          items: [
+            { label: "Rust SDK", slug: "sdk/rust" },
+            { label: "Python SDK", slug: "sdk/python" },
+          ],
         },
         {
           label: "Reference",
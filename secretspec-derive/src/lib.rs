@@ -21,7 +21,7 @@
 
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use secretspec::{Config, Secret};
+use secretspec::{Config, Profile, Secret};
 use std::collections::{BTreeMap, HashSet};
 use syn::{LitStr, parse_macro_input};
 
@@ -319,7 +319,7 @@ fn validate_rust_identifiers(config: &Config, errors: &mut Vec<String>) {
     for (profile_name, profile_config) in &config.profiles {
         let mut profile_field_names = HashSet::new();
 
-        for secret_name in profile_config.secrets.keys() {
+        for (secret_name, _) in profile_fields(profile_config) {
             let field_name = secret_name.to_lowercase();
 
             // Check if it produces a valid Rust identifier
@@ -443,7 +443,9 @@ fn field_name_ident(name: &str) -> proc_macro2::Ident {
 ///
 /// A secret is considered optional if:
 /// - It has `required = false` in the config, OR
-/// - It has a default value specified
+/// - It has a default value specified, OR
+/// - Its requirement is conditional (`required_if` or `required_unless_set`), OR
+/// - It is limited to some `platforms`
 ///
 /// # Arguments
 ///
@@ -453,7 +455,29 @@ fn field_name_ident(name: &str) -> proc_macro2::Ident {
 ///
 /// `true` if the secret is optional, `false` if required
 fn is_secret_optional(secret_config: &Secret) -> bool {
-    !secret_config.required || secret_config.default.is_some()
+    !secret_config.required
+        || secret_config.default.is_some()
+        || secret_config.is_conditional()
+        || !secret_config.platforms.is_empty()
+}
+
+/// Returns the secrets of a profile that become struct fields, with their
+/// configuration.
+///
+/// Patterns are skipped, since the keys they match are only known at
+/// runtime, and declarations reading an item contribute their fields.
+fn profile_fields(profile_config: &Profile) -> Vec<(&str, &Secret)> {
+    profile_config
+        .secrets
+        .iter()
+        .filter(|(secret_name, _)| Secret::pattern_prefix(secret_name).is_none())
+        .flat_map(|(secret_name, secret_config)| {
+            secret_config
+                .exported_names(secret_name)
+                .into_iter()
+                .map(move |name| (name, secret_config))
+        })
+        .collect()
 }
 
 /// Determines if a field should be optional across all profiles.
@@ -479,7 +503,10 @@ fn is_secret_optional(secret_config: &Secret) -> bool {
 fn is_field_optional_across_profiles(secret_name: &str, config: &Config) -> bool {
     // Check each profile
     for profile_config in config.profiles.values() {
-        if let Some(secret_config) = profile_config.secrets.get(secret_name) {
+        if let Some((_, secret_config)) = profile_fields(profile_config)
+            .into_iter()
+            .find(|(name, _)| *name == secret_name)
+        {
             if is_secret_optional(secret_config) {
                 return true;
             }
@@ -562,16 +589,18 @@ fn analyze_field_types(config: &Config) -> BTreeMap<String, FieldInfo> {
 
     // Collect all unique secrets across all profiles
     for profile_config in config.profiles.values() {
-        for secret_name in profile_config.secrets.keys() {
-            field_info.entry(secret_name.clone()).or_insert_with(|| {
-                let is_optional = is_field_optional_across_profiles(secret_name, config);
-                let field_type = if is_optional {
-                    quote! { Option<String> }
-                } else {
-                    quote! { String }
-                };
-                FieldInfo::new(secret_name.clone(), field_type, is_optional)
-            });
+        for (secret_name, _) in profile_fields(profile_config) {
+            field_info
+                .entry(secret_name.to_string())
+                .or_insert_with(|| {
+                    let is_optional = is_field_optional_across_profiles(secret_name, config);
+                    let field_type = if is_optional {
+                        quote! { Option<String> }
+                    } else {
+                        quote! { String }
+                    };
+                    FieldInfo::new(secret_name.to_string(), field_type, is_optional)
+                });
         }
     }
 
@@ -876,19 +905,17 @@ mod secret_spec_generation {
                 .filter_map(|variant| {
                     config.profiles.get(&variant.name).map(|profile_config| {
                         let variant_ident = variant.as_ident();
-                        let fields =
-                            profile_config
-                                .secrets
-                                .iter()
-                                .map(|(secret_name, secret_config)| {
-                                    let field_name = field_name_ident(secret_name);
-                                    let field_type = if is_secret_optional(secret_config) {
-                                        quote! { Option<String> }
-                                    } else {
-                                        quote! { String }
-                                    };
-                                    quote! { #field_name: #field_type }
-                                });
+                        let fields = profile_fields(profile_config).into_iter().map(
+                            |(secret_name, secret_config)| {
+                                let field_name = field_name_ident(secret_name);
+                                let field_type = if is_secret_optional(secret_config) {
+                                    quote! { Option<String> }
+                                } else {
+                                    quote! { String }
+                                };
+                                quote! { #field_name: #field_type }
+                            },
+                        );
 
                         quote! {
                             #variant_ident {
@@ -948,19 +975,17 @@ mod secret_spec_generation {
                 .filter_map(|variant| {
                     config.profiles.get(&variant.name).map(|profile_config| {
                         let variant_ident = variant.as_ident();
-                        let assignments =
-                            profile_config
-                                .secrets
-                                .iter()
-                                .map(|(secret_name, secret_config)| {
-                                    let field_name = field_name_ident(secret_name);
-                                    generate_secret_assignment(
-                                        &field_name,
-                                        secret_name,
-                                        quote! { secrets },
-                                        is_secret_optional(secret_config),
-                                    )
-                                });
+                        let assignments = profile_fields(profile_config).into_iter().map(
+                            |(secret_name, secret_config)| {
+                                let field_name = field_name_ident(secret_name);
+                                generate_secret_assignment(
+                                    &field_name,
+                                    secret_name,
+                                    quote! { secrets },
+                                    is_secret_optional(secret_config),
+                                )
+                            },
+                        );
 
                         quote! {
                             Profile::#variant_ident => Ok(SecretSpecProfile::#variant_ident {
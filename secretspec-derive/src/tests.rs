@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod tests {
     use crate::capitalize_first;
-    use secretspec::Config;
+    use secretspec::{Config, KeysConfig, NotifyConfig, RunConfig};
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_capitalize_first() {
@@ -23,7 +24,7 @@ API_KEY = { description = "API key", required = true }
 DATABASE_URL = { description = "Database URL", required = false, default = "postgres://localhost" }
 "#;
 
-        let config: Config = toml::from_str(toml_str).unwrap();
+        let config: Config = toml_str.parse().unwrap();
         assert_eq!(config.profiles.len(), 1);
         let default_profile = &config.profiles["default"];
         assert_eq!(default_profile.secrets.len(), 2);
@@ -50,13 +51,14 @@ DATABASE_URL = { description = "Database URL", required = false, default = "post
             API_KEY = { description = "API key", required = true }
         "#;
 
-        let config: Config = toml::from_str(&format!(
+        let config: Config = format!(
             r#"[project]
 name = "test"
 revision = "1.0"
 {}"#,
             toml_str
-        ))
+        )
+        .parse()
         .unwrap();
         let api_key = &config.profiles["default"].secrets["API_KEY"];
 
@@ -86,7 +88,7 @@ SOMETIMES_REQUIRED = { description = "Sometimes required secret", required = tru
 SOMETIMES_REQUIRED = { description = "Sometimes required secret", required = false }
 "#;
 
-        let config: Config = toml::from_str(toml_str).unwrap();
+        let config: Config = toml_str.parse().unwrap();
 
         // Simulate the logic from the macro - check if secret is optional across all profiles
         let mut is_ever_optional = false;
@@ -126,7 +128,7 @@ ALWAYS_REQUIRED = { description = "Always required secret", required = true }
 ALWAYS_REQUIRED = { description = "Always required secret", required = true }
 "#;
 
-        let config: Config = toml::from_str(toml_str).unwrap();
+        let config: Config = toml_str.parse().unwrap();
         let secret_config = &config.profiles["default"].secrets["ALWAYS_REQUIRED"];
         let mut is_ever_optional = false;
 
@@ -150,7 +152,7 @@ revision = "1.0"
 HAS_DEFAULT = { description = "Secret with default", required = true, default = "some-default" }
 "#;
 
-        let config: Config = toml::from_str(toml_str).unwrap();
+        let config: Config = toml_str.parse().unwrap();
         let secret_config = &config.profiles["default"].secrets["HAS_DEFAULT"];
 
         let is_ever_optional = !secret_config.required || secret_config.default.is_some();
@@ -200,6 +202,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("API Key".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         valid_secrets.insert(
@@ -208,6 +222,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Database URL".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
 
@@ -215,6 +241,7 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         valid_profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: valid_secrets,
             },
         );
@@ -222,10 +249,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let valid_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: valid_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         validate_rust_identifiers(&valid_config, &mut errors);
@@ -242,6 +274,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Invalid name".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         invalid_secrets.insert(
@@ -250,6 +294,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Invalid name".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
 
@@ -257,6 +313,7 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         invalid_profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: invalid_secrets,
             },
         );
@@ -264,10 +321,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let invalid_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: invalid_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         errors.clear();
@@ -307,6 +369,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Function keyword".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         keyword_secrets.insert(
@@ -315,6 +389,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Struct keyword".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         keyword_secrets.insert(
@@ -323,6 +409,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Async keyword".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
 
@@ -330,6 +428,7 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         keyword_profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: keyword_secrets,
             },
         );
@@ -337,10 +436,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let keyword_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: keyword_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         validate_rust_identifiers(&keyword_config, &mut errors);
@@ -379,6 +483,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("API Key upper".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         duplicate_secrets.insert(
@@ -387,6 +503,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("API Key lower".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         duplicate_secrets.insert(
@@ -395,6 +523,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("API Key mixed".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
 
@@ -402,6 +542,7 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         duplicate_profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: duplicate_secrets,
             },
         );
@@ -409,10 +550,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let duplicate_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: duplicate_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         validate_rust_identifiers(&duplicate_config, &mut errors);
@@ -441,18 +587,21 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         valid_profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: HashMap::new(),
             },
         );
         valid_profiles.insert(
             "development".to_string(),
             Profile {
+                provider: None,
                 secrets: HashMap::new(),
             },
         );
         valid_profiles.insert(
             "production".to_string(),
             Profile {
+                provider: None,
                 secrets: HashMap::new(),
             },
         );
@@ -460,10 +609,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let valid_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: valid_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         validate_profile_identifiers(&valid_config, &mut errors);
@@ -477,12 +631,14 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         invalid_profiles.insert(
             "123invalid".to_string(),
             Profile {
+                provider: None,
                 secrets: HashMap::new(),
             },
         );
         invalid_profiles.insert(
             "invalid-name".to_string(),
             Profile {
+                provider: None,
                 secrets: HashMap::new(),
             },
         );
@@ -490,10 +646,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let invalid_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: invalid_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         errors.clear();
@@ -528,6 +689,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
             description: Some("Required".to_string()),
             required: true,
             default: None,
+            required_if: None,
+            required_unless_set: Vec::new(),
+            group: None,
+            platforms: Vec::new(),
+            transform: Vec::new(),
+            item: None,
+            fields: BTreeMap::new(),
+            ttl: None,
+            generator: None,
+            hardware_confirm: false,
+            bitwarden_item_id: None,
+            kind: None,
         };
         assert!(!is_secret_optional(&required_no_default));
 
@@ -536,6 +709,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
             description: Some("Required with default".to_string()),
             required: true,
             default: Some("default_value".to_string()),
+            required_if: None,
+            required_unless_set: Vec::new(),
+            group: None,
+            platforms: Vec::new(),
+            transform: Vec::new(),
+            item: None,
+            fields: BTreeMap::new(),
+            ttl: None,
+            generator: None,
+            hardware_confirm: false,
+            bitwarden_item_id: None,
+            kind: None,
         };
         assert!(is_secret_optional(&required_with_default));
 
@@ -544,6 +729,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
             description: Some("Not required".to_string()),
             required: false,
             default: None,
+            required_if: None,
+            required_unless_set: Vec::new(),
+            group: None,
+            platforms: Vec::new(),
+            transform: Vec::new(),
+            item: None,
+            fields: BTreeMap::new(),
+            ttl: None,
+            generator: None,
+            hardware_confirm: false,
+            bitwarden_item_id: None,
+            kind: None,
         };
         assert!(is_secret_optional(&not_required));
 
@@ -552,6 +749,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
             description: Some("Not required with default".to_string()),
             required: false,
             default: Some("default_value".to_string()),
+            required_if: None,
+            required_unless_set: Vec::new(),
+            group: None,
+            platforms: Vec::new(),
+            transform: Vec::new(),
+            item: None,
+            fields: BTreeMap::new(),
+            ttl: None,
+            generator: None,
+            hardware_confirm: false,
+            bitwarden_item_id: None,
+            kind: None,
         };
         assert!(is_secret_optional(&not_required_with_default));
     }
@@ -573,6 +782,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("API Key".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         default_secrets.insert(
@@ -581,11 +802,24 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Database URL".to_string()),
                 required: false,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: default_secrets,
             },
         );
@@ -598,6 +832,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("API Key".to_string()),
                 required: true,
                 default: Some("dev-key".to_string()),
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         dev_secrets.insert(
@@ -606,6 +852,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Database URL".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         // Note: CACHE_URL only exists in development
@@ -615,11 +873,24 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Cache URL".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         profiles.insert(
             "development".to_string(),
             Profile {
+                provider: None,
                 secrets: dev_secrets,
             },
         );
@@ -627,10 +898,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         // API_KEY is optional because it has default in development
@@ -651,6 +927,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Always required".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         let mut strict_dev = HashMap::new();
@@ -660,17 +948,31 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Always required".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         strict_profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: strict_default,
             },
         );
         strict_profiles.insert(
             "development".to_string(),
             Profile {
+                provider: None,
                 secrets: strict_dev,
             },
         );
@@ -678,10 +980,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let strict_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: strict_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         // ALWAYS_REQUIRED should not be optional
@@ -707,6 +1014,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Always required".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         default_secrets.insert(
@@ -715,6 +1034,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Optional".to_string()),
                 required: false,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         default_secrets.insert(
@@ -723,11 +1054,24 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Has default".to_string()),
                 required: true,
                 default: Some("default_value".to_string()),
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: default_secrets,
             },
         );
@@ -740,6 +1084,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Always required".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         dev_secrets.insert(
@@ -748,11 +1104,24 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Development only".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         profiles.insert(
             "development".to_string(),
             Profile {
+                provider: None,
                 secrets: dev_secrets,
             },
         );
@@ -760,10 +1129,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         let field_info = analyze_field_types(&config);
@@ -887,6 +1261,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("API Key".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         valid_secrets.insert(
@@ -895,6 +1281,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Database URL".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
 
@@ -902,12 +1300,14 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         valid_profiles.insert(
             "default".to_string(),
             Profile {
+                provider: None,
                 secrets: valid_secrets,
             },
         );
         valid_profiles.insert(
             "development".to_string(),
             Profile {
+                provider: None,
                 secrets: HashMap::new(),
             },
         );
@@ -915,10 +1315,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let valid_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: valid_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         let result = validate_config_for_codegen(&valid_config);
@@ -932,6 +1337,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Invalid name".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
         invalid_secrets.insert(
@@ -940,6 +1357,18 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
                 description: Some("Rust keyword".to_string()),
                 required: true,
                 default: None,
+                required_if: None,
+                required_unless_set: Vec::new(),
+                group: None,
+                platforms: Vec::new(),
+                transform: Vec::new(),
+                item: None,
+                fields: BTreeMap::new(),
+                ttl: None,
+                generator: None,
+                hardware_confirm: false,
+                bitwarden_item_id: None,
+                kind: None,
             },
         );
 
@@ -947,6 +1376,7 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         invalid_profiles.insert(
             "123invalid-profile".to_string(),
             Profile {
+                provider: None,
                 secrets: invalid_secrets,
             },
         );
@@ -954,10 +1384,15 @@ HAS_DEFAULT = { description = "Secret with default", required = true, default =
         let invalid_config = Config {
             project: Project {
                 name: "test".to_string(),
-                revision: "1.0".to_string(),
+                schema: 2,
                 extends: None,
             },
             profiles: invalid_profiles,
+            recipients: HashMap::new(),
+            assume_role: HashMap::new(),
+            run: RunConfig::default(),
+            notify: NotifyConfig::default(),
+            keys: KeysConfig::default(),
         };
 
         let result = validate_config_for_codegen(&invalid_config);
@@ -0,0 +1,14 @@
+"""Declarative secrets, every environment, any provider.
+
+Reads the secrets declared in ``secretspec.toml`` from the configured
+provider, the same way the ``secretspec`` CLI does::
+
+    import secretspec
+
+    secrets = secretspec.load(profile="production")
+    database_url = secrets["DATABASE_URL"]
+"""
+
+from ._secretspec import MissingSecretsError, Secrets, SecretSpecError, load
+
+__all__ = ["MissingSecretsError", "Secrets", "SecretSpecError", "load"]
@@ -0,0 +1,24 @@
+from os import PathLike
+from typing import Optional, Union
+
+_Path = Union[str, PathLike[str]]
+
+class SecretSpecError(Exception): ...
+class MissingSecretsError(SecretSpecError): ...
+
+class Secrets:
+    def __init__(
+        self,
+        path: Optional[_Path] = None,
+        provider: Optional[str] = None,
+        profile: Optional[str] = None,
+    ) -> None: ...
+    def resolve(self) -> dict[str, str]: ...
+    def get(self, name: str) -> str: ...
+    def set(self, name: str, value: str) -> None: ...
+
+def load(
+    path: Optional[_Path] = None,
+    provider: Optional[str] = None,
+    profile: Optional[str] = None,
+) -> dict[str, str]: ...
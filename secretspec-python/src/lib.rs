@@ -0,0 +1,115 @@
+//! Python bindings for SecretSpec.
+//!
+//! Built into the `secretspec` Python package with maturin. The package
+//! re-exports everything defined here; see `python/secretspec/__init__.py`.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+create_exception!(
+    secretspec,
+    SecretSpecError,
+    PyException,
+    "Raised when loading the spec or talking to the provider fails."
+);
+create_exception!(
+    secretspec,
+    MissingSecretsError,
+    SecretSpecError,
+    "Raised when required secrets are missing."
+);
+
+/// Converts a library error into the Python exception.
+fn to_py_err(e: secretspec::SecretSpecError) -> PyErr {
+    SecretSpecError::new_err(e.to_string())
+}
+
+/// The secrets declared in a `secretspec.toml`.
+///
+/// Providers and profiles are selected as by the CLI: the arguments win
+/// over `SECRETSPEC_PROVIDER` and `SECRETSPEC_PROFILE`, which win over the
+/// user configuration and the spec.
+#[pyclass(name = "Secrets", module = "secretspec", frozen)]
+struct Secrets {
+    inner: secretspec::Secrets,
+}
+
+#[pymethods]
+impl Secrets {
+    /// Loads the spec from `path`, a directory defaulting to the current one.
+    #[new]
+    #[pyo3(signature = (path = None, provider = None, profile = None))]
+    fn new(
+        path: Option<PathBuf>,
+        provider: Option<String>,
+        profile: Option<String>,
+    ) -> PyResult<Self> {
+        let mut inner =
+            secretspec::Secrets::load_from(path.unwrap_or_else(|| PathBuf::from(".")))
+                .map_err(to_py_err)?;
+        if let Some(provider) = provider {
+            inner.set_provider(provider);
+        }
+        if let Some(profile) = profile {
+            inner.set_profile(profile);
+        }
+        Ok(Self { inner })
+    }
+
+    /// Returns every secret of the profile that is set or has a default.
+    ///
+    /// Raises `MissingSecretsError` if a required secret is missing.
+    fn resolve(&self, py: Python<'_>) -> PyResult<HashMap<String, String>> {
+        let validated = py
+            .allow_threads(|| self.inner.validate())
+            .map_err(to_py_err)?
+            .map_err(|errors| MissingSecretsError::new_err(errors.to_string()))?;
+        Ok(validated
+            .resolved
+            .secrets
+            .iter()
+            .map(|(name, value)| (name.clone(), value.expose_secret().to_string()))
+            .collect())
+    }
+
+    /// Returns the value of a secret, or its default if it isn't set.
+    fn get(&self, py: Python<'_>, name: &str) -> PyResult<String> {
+        py.allow_threads(|| self.inner.get(name))
+            .map(|value| value.expose_secret().to_string())
+            .map_err(to_py_err)
+    }
+
+    /// Stores a secret in the provider.
+    fn set(&self, py: Python<'_>, name: &str, value: String) -> PyResult<()> {
+        let value = SecretString::from(value);
+        py.allow_threads(|| self.inner.set_secret(name, value))
+            .map_err(to_py_err)
+    }
+}
+
+/// Loads the spec and returns its resolved secrets.
+///
+/// Shorthand for `Secrets(path, provider, profile).resolve()`.
+#[pyfunction]
+#[pyo3(signature = (path = None, provider = None, profile = None))]
+fn load(
+    py: Python<'_>,
+    path: Option<PathBuf>,
+    provider: Option<String>,
+    profile: Option<String>,
+) -> PyResult<HashMap<String, String>> {
+    Secrets::new(path, provider, profile)?.resolve(py)
+}
+
+#[pymodule]
+fn _secretspec(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Secrets>()?;
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add("SecretSpecError", m.py().get_type::<SecretSpecError>())?;
+    m.add("MissingSecretsError", m.py().get_type::<MissingSecretsError>())?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! Criterion benchmarks for provider get/set latency.
+//!
+//! The dotenv provider is always benchmarked against a temporary file. Set
+//! `SECRETSPEC_BENCH_PROVIDERS` to a comma-separated list of provider URIs to
+//! compare others, for example:
+//!
+//! ```text
+//! SECRETSPEC_BENCH_PROVIDERS=keyring://,onepassword://Benchmarks cargo bench
+//! ```
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use secretspec::bench::BenchProvider;
+use tempfile::TempDir;
+
+/// Value sizes in bytes that every provider is measured with.
+const VALUE_SIZES: &[usize] = &[64, 4096];
+
+fn providers(dir: &TempDir) -> Vec<(String, BenchProvider)> {
+    let mut specs = vec![format!("dotenv://{}", dir.path().join(".env").display())];
+    if let Ok(extra) = std::env::var("SECRETSPEC_BENCH_PROVIDERS") {
+        specs.extend(
+            extra
+                .split(',')
+                .map(str::trim)
+                .filter(|spec| !spec.is_empty())
+                .map(str::to_string),
+        );
+    }
+
+    specs
+        .into_iter()
+        .map(|spec| {
+            let provider = BenchProvider::open(&spec)
+                .unwrap_or_else(|e| panic!("Failed to open provider '{}': {}", spec, e));
+            (provider.name().to_string(), provider)
+        })
+        .collect()
+}
+
+fn bench_providers(c: &mut Criterion) {
+    let dir = TempDir::new().expect("Failed to create temporary directory");
+    let providers = providers(&dir);
+
+    for &size in VALUE_SIZES {
+        let value = BenchProvider::value(size);
+        let key = BenchProvider::key(size);
+
+        let mut set = c.benchmark_group(format!("set/{}B", size));
+        set.throughput(Throughput::Bytes(size as u64));
+        for (name, provider) in providers.iter().filter(|(_, p)| p.allows_set()) {
+            set.bench_function(BenchmarkId::from_parameter(name), |b| {
+                b.iter(|| provider.set(&key, &value).unwrap())
+            });
+        }
+        set.finish();
+
+        let mut get = c.benchmark_group(format!("get/{}B", size));
+        get.throughput(Throughput::Bytes(size as u64));
+        for (name, provider) in &providers {
+            if provider.allows_set() {
+                provider.set(&key, &value).unwrap();
+            }
+            get.bench_function(BenchmarkId::from_parameter(name), |b| {
+                b.iter(|| provider.get(&key).unwrap())
+            });
+        }
+        get.finish();
+    }
+}
+
+criterion_group!(benches, bench_providers);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+//! Generates the gRPC service of `secretspec serve --grpc` with the `grpc`
+//! feature. The messages are defined in `src/grpc.rs` and mirror
+//! `proto/secretspec.proto`, so building needs no `protoc`.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+#[cfg(feature = "grpc")]
+fn grpc() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    let method = |name: &str, route: &str, message: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::{}Request", message))
+            .output_type(format!("crate::grpc::{}Response", message))
+            .codec_path("tonic_prost::ProstCodec")
+            .build()
+    };
+    let service = Service::builder()
+        .name("Secrets")
+        .package("secretspec.v1")
+        .method(method("get", "Get", "Get"))
+        .method(method("set", "Set", "Set"))
+        .method(method("list", "List", "List"))
+        .method(method("check", "Check", "Check"))
+        .build();
+    Builder::new().compile(&[service]);
+}
@@ -0,0 +1,689 @@
+//! Background agent holding provider sessions
+//!
+//! `secretspec agent start` runs an agent, like ssh-agent, that keeps
+//! providers in memory and serves lookups over a Unix socket. Commands find
+//! it through `SECRETSPEC_AGENT_SOCK` and send their lookups there, so a
+//! provider that was unlocked once stays unlocked across terminal sessions.
+//!
+//! - The socket lives in a directory only the user can enter, and the peer
+//!   credentials of every connection are checked against the agent's user
+//! - After the idle timeout without lookups the agent locks: it drops its
+//!   providers and their sessions, and the next lookup unlocks again
+//! - With `--biometric` the agent keeps its providers when it locks after
+//!   the idle timeout, and asks for Touch ID before serving the next lookup
+//! - Providers that work in a Nix sandbox, like `env` and `dotenv`, hold no
+//!   session and are always read directly
+//!
+//! The protocol is one JSON request and one JSON response per connection,
+//! each on a line.
+
+use crate::presence;
+use crate::provider::{
+    AccessEntry, AccessPolicy, ItemContainer, ItemMatch, Lease, Provider, SecretValue,
+};
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, DirBuilder};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+/// Environment variable pointing commands at the agent's socket.
+pub(crate) const SOCKET_ENV: &str = "SECRETSPEC_AGENT_SOCK";
+
+/// How long a connection may take to send its request or read the answer.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `start` waits for the agent to listen.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A request to the agent.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Request {
+    /// Reads a secret through the provider with this specification
+    Get {
+        provider: String,
+        project: String,
+        key: String,
+        profile: String,
+    },
+    /// Drops all providers and their sessions
+    Lock,
+    /// Stops the agent
+    Stop,
+}
+
+/// The agent's answer to a request.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Response {
+    /// The value read, if the secret exists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    /// Why the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Drop for Response {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// Returns the socket used when none is given.
+///
+/// It lives in `$XDG_RUNTIME_DIR` where available, and otherwise in a
+/// per-user directory under the temporary directory.
+pub(crate) fn default_socket() -> PathBuf {
+    let dir = directories::ProjectDirs::from("", "", "secretspec")
+        .and_then(|dirs| dirs.runtime_dir().map(Path::to_path_buf))
+        .unwrap_or_else(|| {
+            // SAFETY: geteuid has no preconditions and can't fail
+            let uid = unsafe { libc::geteuid() };
+            env::temp_dir().join(format!("secretspec-{}", uid))
+        });
+    dir.join("agent.sock")
+}
+
+/// Returns the user id of the process on the other end of a connection.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: cred and len are valid for writes of the sizes passed
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// Returns the user id of the process on the other end of a connection.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: uid and gid are valid for writes
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Providers the agent holds, and when it last served a lookup.
+struct State {
+    providers: HashMap<String, Box<dyn Provider>>,
+    last_used: Instant,
+    /// Whether the providers are kept when locking after the idle timeout
+    biometric: bool,
+    /// Whether the user must confirm their presence before the next lookup
+    gated: bool,
+}
+
+impl State {
+    /// Reads a secret, creating the provider on first use.
+    fn get(
+        &mut self,
+        spec: &str,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretString>> {
+        if self.gated {
+            presence::verify("unlock the secretspec agent")?;
+            self.gated = false;
+        }
+        self.last_used = Instant::now();
+        if !self.providers.contains_key(spec) {
+            let provider = Box::<dyn Provider>::try_from(spec.to_string())?;
+            self.providers.insert(spec.to_string(), provider);
+        }
+        self.providers[spec].get(project, key, profile)
+    }
+}
+
+/// Creates the socket's directory, only accessible by the user.
+fn create_socket_dir(socket: &Path) -> Result<()> {
+    let Some(dir) = socket.parent().filter(|dir| !dir.as_os_str().is_empty()) else {
+        return Ok(());
+    };
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    let mode = fs::metadata(dir)?.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        return Err(SecretSpecError::PermissionDenied(format!(
+            "{} is accessible by other users (mode {:o}); run 'chmod 700 {}'",
+            dir.display(),
+            mode,
+            dir.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Runs the agent in the foreground until it is stopped.
+///
+/// With `biometric`, locking after the idle timeout keeps the providers and
+/// asks the user to confirm their presence before the next lookup.
+///
+/// # Errors
+///
+/// Returns an error if another agent is already listening on `socket`, the
+/// socket can't be created, or `biometric` isn't supported
+pub(crate) fn serve(socket: &Path, idle_timeout: Duration, biometric: bool) -> Result<()> {
+    create_socket_dir(socket)?;
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "An agent is already listening on {}",
+                socket.display()
+            )));
+        }
+        // Left behind by an agent that didn't stop cleanly
+        fs::remove_file(socket)?;
+    }
+    if biometric {
+        presence::enroll()?;
+    }
+    let listener = UnixListener::bind(socket)?;
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+
+    // SAFETY: geteuid has no preconditions and can't fail
+    let uid = unsafe { libc::geteuid() };
+    let state = Arc::new(Mutex::new(State {
+        providers: HashMap::new(),
+        last_used: Instant::now(),
+        biometric,
+        gated: false,
+    }));
+
+    let idle = Arc::clone(&state);
+    thread::spawn(move || {
+        loop {
+            thread::sleep(idle_timeout.min(Duration::from_secs(1)));
+            let mut state = idle.lock().unwrap_or_else(|e| e.into_inner());
+            if !state.providers.is_empty()
+                && !state.gated
+                && state.last_used.elapsed() >= idle_timeout
+            {
+                if state.biometric {
+                    state.gated = true;
+                } else {
+                    state.providers.clear();
+                }
+            }
+        }
+    });
+
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        if peer_uid(&stream).ok() != Some(uid) {
+            continue;
+        }
+        let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+
+        let mut line = String::new();
+        if BufReader::new(&stream).read_line(&mut line).is_err() {
+            continue;
+        }
+        let request = serde_json::from_str::<Request>(&line);
+        line.zeroize();
+
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let (response, stop) = match request {
+            Ok(Request::Get {
+                provider,
+                project,
+                key,
+                profile,
+            }) => match state.get(&provider, &project, &key, &profile) {
+                Ok(value) => (
+                    Response {
+                        value: value.map(|v| v.expose_secret().to_string()),
+                        error: None,
+                    },
+                    false,
+                ),
+                Err(e) => (
+                    Response {
+                        value: None,
+                        error: Some(e.to_string()),
+                    },
+                    false,
+                ),
+            },
+            Ok(Request::Lock) => {
+                state.providers.clear();
+                state.gated = false;
+                (Response::default(), false)
+            }
+            Ok(Request::Stop) => (Response::default(), true),
+            Err(e) => (
+                Response {
+                    value: None,
+                    error: Some(format!("Invalid request: {}", e)),
+                },
+                false,
+            ),
+        };
+        drop(state);
+
+        if let Ok(mut json) = serde_json::to_string(&response) {
+            json.push('\n');
+            let _ = stream.write_all(json.as_bytes());
+            json.zeroize();
+        }
+        if stop {
+            break;
+        }
+    }
+
+    let _ = fs::remove_file(socket);
+    Ok(())
+}
+
+/// Starts the agent in the background and waits until it listens.
+///
+/// # Returns
+///
+/// The process id of the agent
+///
+/// # Errors
+///
+/// Returns an error if the agent can't be started or doesn't come up
+pub(crate) fn start(socket: &Path, idle_timeout: Duration, biometric: bool) -> Result<u32> {
+    if UnixStream::connect(socket).is_ok() {
+        return Err(SecretSpecError::ProviderOperationFailed(format!(
+            "An agent is already listening on {}",
+            socket.display()
+        )));
+    }
+    let socket_arg = socket.to_string_lossy();
+    let timeout_arg = idle_timeout.as_secs().to_string();
+    let mut args = vec![
+        "agent",
+        "start",
+        "--foreground",
+        "--socket",
+        socket_arg.as_ref(),
+        "--idle-timeout",
+        &timeout_arg,
+    ];
+    if biometric {
+        args.push("--biometric");
+    }
+    let mut child = Command::new(env::current_exe()?)
+        .args(args)
+        .env_remove(SOCKET_ENV)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Keep running when the terminal that started it closes
+        .process_group(0)
+        .spawn()?;
+
+    let started = Instant::now();
+    while started.elapsed() < START_TIMEOUT {
+        if UnixStream::connect(socket).is_ok() {
+            return Ok(child.id());
+        }
+        if let Some(status) = child.try_wait()? {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "The agent exited with {}; run 'secretspec agent start --foreground' to see why",
+                status
+            )));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    Err(SecretSpecError::ProviderOperationFailed(format!(
+        "The agent didn't start listening on {} within {} seconds",
+        socket.display(),
+        START_TIMEOUT.as_secs()
+    )))
+}
+
+/// Sends a request to the agent and reads its answer.
+fn send(socket: &Path, request: &Request) -> io::Result<Response> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut json = serde_json::to_string(request)?;
+    json.push('\n');
+    stream.write_all(json.as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response = serde_json::from_str(&line).map_err(io::Error::from);
+    line.zeroize();
+    response
+}
+
+/// Sends a request that only succeeds or fails.
+fn command(socket: &Path, request: Request) -> Result<()> {
+    let response = send(socket, &request).map_err(|e| {
+        SecretSpecError::ProviderOperationFailed(format!(
+            "No agent is listening on {}: {}",
+            socket.display(),
+            e
+        ))
+    })?;
+    match &response.error {
+        Some(error) => Err(SecretSpecError::ProviderOperationFailed(error.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Makes the agent drop its providers and their sessions.
+pub(crate) fn lock(socket: &Path) -> Result<()> {
+    command(socket, Request::Lock)
+}
+
+/// Stops the agent.
+pub(crate) fn stop(socket: &Path) -> Result<()> {
+    command(socket, Request::Stop)
+}
+
+/// Provider wrapper that reads secrets through the agent.
+///
+/// Everything other than lookups goes to the wrapped provider, as do
+/// lookups while no agent is listening.
+struct AgentProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+    /// The specification the agent creates the provider from
+    spec: String,
+    /// The agent's socket
+    socket: PathBuf,
+}
+
+/// Wraps the provider if `SECRETSPEC_AGENT_SOCK` points at an agent.
+///
+/// Providers that work in a Nix sandbox hold no session and are returned
+/// unchanged; the agent would also read them from its own environment and
+/// working directory.
+pub(crate) fn wrap(inner: Box<dyn Provider>, spec: &str) -> Box<dyn Provider> {
+    match env::var_os(SOCKET_ENV) {
+        Some(socket) if !socket.is_empty() && !inner.works_in_sandbox() => {
+            Box::new(AgentProvider {
+                inner,
+                spec: spec.to_string(),
+                socket: PathBuf::from(socket),
+            })
+        }
+        _ => inner,
+    }
+}
+
+impl Provider for AgentProvider {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    /// Reads a secret through the agent, or directly if it isn't running.
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        let request = Request::Get {
+            provider: self.spec.clone(),
+            project: project.to_string(),
+            key: key.to_string(),
+            profile: profile.to_string(),
+        };
+        let mut response = match send(&self.socket, &request) {
+            Ok(response) => response,
+            Err(_) => return self.inner.get(project, key, profile),
+        };
+        if let Some(error) = response.error.take() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Agent: {}",
+                error
+            )));
+        }
+        Ok(response.value.take().map(|value| value.into()))
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.inner.get_with_metadata(project, key, profile)
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.inner.set(project, key, value, profile)
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        self.inner
+            .compare_and_set(project, key, value, profile, expected)
+    }
+
+    fn allows_set(&self) -> bool {
+        self.inner.allows_set()
+    }
+
+    fn check_writable(&self, project: &str, profile: &str) -> Result<()> {
+        self.inner.check_writable(project, profile)
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.inner.delete(project, key, profile)
+    }
+
+    fn allows_delete(&self) -> bool {
+        self.inner.allows_delete()
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.inner.purge(project, key, profile)
+    }
+
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.inner.restore(project, key, profile)
+    }
+
+    fn allows_restore(&self) -> bool {
+        self.inner.allows_restore()
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        self.inner.list_keys(project, profile)
+    }
+
+    fn allows_list(&self) -> bool {
+        self.inner.allows_list()
+    }
+
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        self.inner.get_fields(project, item, fields, profile)
+    }
+
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        self.inner.find_items(project, key, profile)
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.inner.get_by_id(project, key, id, profile)
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        self.inner.set_by_id(project, key, id, value, profile)
+    }
+
+    fn browse(&self) -> Result<Vec<ItemContainer>> {
+        self.inner.browse()
+    }
+
+    fn max_value_size(&self) -> Option<usize> {
+        self.inner.max_value_size()
+    }
+
+    fn works_in_sandbox(&self) -> bool {
+        self.inner.works_in_sandbox()
+    }
+
+    fn doctor(&self) -> Vec<crate::doctor::Check> {
+        self.inner.doctor()
+    }
+
+    fn list_access(&self, project: &str, profile: &str) -> Result<Vec<AccessEntry>> {
+        self.inner.list_access(project, profile)
+    }
+
+    fn access_policy(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.inner.access_policy(project, profile, principal)
+    }
+
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.inner.grant(project, profile, principal)
+    }
+
+    fn leases(&self) -> Vec<Lease> {
+        self.inner.leases()
+    }
+
+    fn renew_lease(&self, lease: &Lease) -> Result<Lease> {
+        self.inner.renew_lease(lease)
+    }
+
+    fn revoke_lease(&self, lease: &Lease) -> Result<()> {
+        self.inner.revoke_lease(lease)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Waits until the agent listens on `socket`.
+    fn wait_for(socket: &Path) {
+        let started = Instant::now();
+        while UnixStream::connect(socket).is_err() {
+            assert!(started.elapsed() < START_TIMEOUT, "agent didn't start");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_agent_serves_lookups() {
+        let temp_dir = TempDir::new().unwrap();
+        let env_file = temp_dir.path().join(".env");
+        fs::write(&env_file, "API_KEY=sk-123\n").unwrap();
+        let socket = temp_dir.path().join("agent/agent.sock");
+
+        let server_socket = socket.clone();
+        let server = thread::spawn(move || serve(&server_socket, Duration::from_secs(60), false));
+        wait_for(&socket);
+        let mode = fs::metadata(socket.parent().unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        let get = |key: &str| Request::Get {
+            provider: format!("dotenv://{}", env_file.display()),
+            project: "app".to_string(),
+            key: key.to_string(),
+            profile: "default".to_string(),
+        };
+        let response = send(&socket, &get("API_KEY")).unwrap();
+        assert_eq!(response.value.as_deref(), Some("sk-123"));
+        assert!(send(&socket, &get("MISSING")).unwrap().value.is_none());
+
+        let response = send(
+            &socket,
+            &Request::Get {
+                provider: "nosuchprovider://".to_string(),
+                project: "app".to_string(),
+                key: "API_KEY".to_string(),
+                profile: "default".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(response.error.is_some());
+
+        lock(&socket).unwrap();
+        assert!(serve(&socket, Duration::from_secs(60), false).is_err());
+        stop(&socket).unwrap();
+        server.join().unwrap().unwrap();
+        assert!(!socket.exists());
+        assert!(stop(&socket).is_err());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_gated_agent_asks_for_presence() {
+        let temp_dir = TempDir::new().unwrap();
+        let env_file = temp_dir.path().join(".env");
+        fs::write(&env_file, "API_KEY=sk-123\n").unwrap();
+        let spec = format!("dotenv://{}", env_file.display());
+
+        let mut state = State {
+            providers: HashMap::new(),
+            last_used: Instant::now(),
+            biometric: true,
+            gated: false,
+        };
+        assert!(state.get(&spec, "app", "API_KEY", "default").is_ok());
+        state.gated = true;
+        // Without a presence prompt the lookup is refused and the agent stays locked
+        assert!(state.get(&spec, "app", "API_KEY", "default").is_err());
+        assert!(state.gated);
+        assert_eq!(state.providers.len(), 1);
+
+        let socket = temp_dir.path().join("agent/agent.sock");
+        assert!(serve(&socket, Duration::from_secs(60), true).is_err());
+    }
+}
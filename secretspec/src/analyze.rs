@@ -0,0 +1,304 @@
+//! Strength and reuse of stored secrets
+//!
+//! `secretspec analyze` reads the values every profile stores and reports
+//! the ones that are easy to guess: placeholders like `changeme` left from
+//! setup, values too short or too repetitive to resist guessing, and the
+//! same value stored under several secrets or in several profiles, so that
+//! rotating one leaves the others exposed. Values are compared by digest
+//! and never printed.
+
+use crate::config::SecretKind;
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Values shorter than this are reported as short.
+const MIN_LENGTH: usize = 12;
+
+/// Values with less entropy than this many bits are reported as guessable.
+const MIN_ENTROPY_BITS: f64 = 48.0;
+
+/// Placeholders and common passwords, compared in lower case without
+/// punctuation, so `Change-Me` matches `changeme`.
+const WEAK_VALUES: [&str; 28] = [
+    "changeme",
+    "changeit",
+    "changethis",
+    "password",
+    "passw0rd",
+    "password1",
+    "password123",
+    "secret",
+    "mysecret",
+    "admin",
+    "root",
+    "default",
+    "test",
+    "testing",
+    "example",
+    "sample",
+    "placeholder",
+    "dummy",
+    "todo",
+    "fixme",
+    "letmein",
+    "welcome",
+    "qwerty",
+    "guest",
+    "foobar",
+    "123456",
+    "12345678",
+    "123456789",
+];
+
+/// A secret of a profile.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    /// The profile
+    pub profile: String,
+    /// Name of the secret
+    pub secret: String,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.secret, self.profile)
+    }
+}
+
+/// A problem with a secret's value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Finding {
+    /// The value is a placeholder or a common password
+    Weak(Location),
+    /// The value is shorter than 12 characters
+    Short {
+        /// The secret
+        location: Location,
+        /// Length of the value in characters
+        length: usize,
+    },
+    /// The value repeats too few characters to resist guessing
+    LowEntropy {
+        /// The secret
+        location: Location,
+        /// Estimated entropy in bits
+        bits: f64,
+    },
+    /// The same value is stored for several secrets
+    Reused(Vec<Location>),
+    /// The password appears in known data breaches, found with
+    /// `analyze --hibp`
+    Breached {
+        /// The secret
+        location: Location,
+        /// How often the password appears in the breaches
+        occurrences: u64,
+    },
+}
+
+impl Finding {
+    /// Returns whether the finding calls for changing the value, rather than
+    /// looking at it: weak, reused and breached values.
+    pub fn is_severe(&self) -> bool {
+        matches!(
+            self,
+            Finding::Weak(_) | Finding::Reused(_) | Finding::Breached { .. }
+        )
+    }
+}
+
+/// A value to analyze.
+pub(crate) struct Sample {
+    /// The secret holding the value
+    pub location: Location,
+    /// The provider the value is stored in, or `None` for a default from
+    /// the spec, which is only checked for placeholders
+    pub provider: Option<String>,
+    /// What kind of value the secret holds
+    pub kind: Option<SecretKind>,
+    /// The value
+    pub value: SecretString,
+}
+
+/// Analyzes values, reporting weak, short and guessable values followed by
+/// reused ones.
+///
+/// Values are reused when stored under different secrets, or under the same
+/// secret in profiles with different providers. Profiles reading the same
+/// provider may share one stored item, like every profile of a `.env` file.
+pub(crate) fn analyze(samples: &[Sample]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut by_digest: HashMap<[u8; 32], Vec<&Sample>> = HashMap::new();
+    for sample in samples {
+        let value = sample.value.expose_secret();
+        if value.is_empty() {
+            continue;
+        }
+        if is_weak(value) {
+            findings.push(Finding::Weak(sample.location.clone()));
+            continue;
+        }
+        if sample.provider.is_none() {
+            continue;
+        }
+        let length = value.chars().count();
+        let bits = entropy_bits(value);
+        if length < MIN_LENGTH {
+            findings.push(Finding::Short {
+                location: sample.location.clone(),
+                length,
+            });
+        } else if bits < MIN_ENTROPY_BITS {
+            findings.push(Finding::LowEntropy {
+                location: sample.location.clone(),
+                bits,
+            });
+        }
+        by_digest
+            .entry(Sha256::digest(value.as_bytes()).into())
+            .or_default()
+            .push(sample);
+    }
+
+    let mut reused: Vec<Vec<Location>> = by_digest
+        .into_values()
+        .filter_map(|samples| {
+            // One stored item per provider and secret name
+            let mut items: BTreeMap<(&str, &str), Vec<Location>> = BTreeMap::new();
+            for sample in samples {
+                let provider = sample.provider.as_deref().unwrap_or_default();
+                items
+                    .entry((provider, &sample.location.secret))
+                    .or_default()
+                    .push(sample.location.clone());
+            }
+            (items.len() > 1).then(|| {
+                let mut locations: Vec<Location> = items.into_values().flatten().collect();
+                locations.sort();
+                locations
+            })
+        })
+        .collect();
+    reused.sort();
+    findings.extend(reused.into_iter().map(Finding::Reused));
+    findings
+}
+
+/// Returns whether a value is a placeholder, a common password or a single
+/// repeated character.
+fn is_weak(value: &str) -> bool {
+    let normalized: String = value
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let mut chars = value.chars();
+    let first = chars.next();
+    WEAK_VALUES.contains(&normalized.as_str())
+        || (value.chars().count() > 1 && chars.all(|c| Some(c) == first))
+}
+
+/// Estimates the entropy of a value in bits, from how often each of its
+/// characters occurs.
+fn entropy_bits(value: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let length = value.chars().count() as f64;
+    let per_char: f64 = counts
+        .values()
+        .map(|count| {
+            let p = *count as f64 / length;
+            -p * p.log2()
+        })
+        .sum();
+    per_char * length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(profile: &str, secret: &str, provider: Option<&str>, value: &str) -> Sample {
+        Sample {
+            location: Location {
+                profile: profile.to_string(),
+                secret: secret.to_string(),
+            },
+            provider: provider.map(str::to_string),
+            kind: None,
+            value: SecretString::from(value),
+        }
+    }
+
+    #[test]
+    fn test_is_weak() {
+        for value in ["changeme", "Change-Me", "PASSWORD", "123456", "aaaaaaaa"] {
+            assert!(is_weak(value), "{}", value);
+        }
+        for value in ["a", "correct horse battery staple", "sk_live_51H8x2"] {
+            assert!(!is_weak(value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_entropy_bits() {
+        assert_eq!(entropy_bits("abababababababab"), 16.0);
+        assert!(entropy_bits("9f86d081884c7d659a2feaa0c55ad015") > 100.0);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let key = "9f86d081884c7d659a2feaa0c55ad015";
+        let findings = analyze(&[
+            sample("default", "DB_PASSWORD", Some("keyring"), "changeme"),
+            sample("default", "LOG_LEVEL", None, "info"),
+            sample("default", "PIN", Some("keyring"), "4821"),
+            sample("default", "TOKEN", Some("keyring"), "abababababababab"),
+            sample("default", "API_KEY", Some("keyring"), key),
+            sample("default", "BACKUP_KEY", Some("keyring"), key),
+            sample("production", "API_KEY", Some("onepassword"), key),
+            // Profiles reading the same file share its values
+            sample(
+                "default",
+                "SALT",
+                Some("dotenv"),
+                "e3b0c44298fc1c149afbf4c8996fb924",
+            ),
+            sample(
+                "staging",
+                "SALT",
+                Some("dotenv"),
+                "e3b0c44298fc1c149afbf4c8996fb924",
+            ),
+        ]);
+        let location = |profile: &str, secret: &str| Location {
+            profile: profile.to_string(),
+            secret: secret.to_string(),
+        };
+        assert_eq!(
+            findings,
+            [
+                Finding::Weak(location("default", "DB_PASSWORD")),
+                Finding::Short {
+                    location: location("default", "PIN"),
+                    length: 4
+                },
+                Finding::LowEntropy {
+                    location: location("default", "TOKEN"),
+                    bits: 16.0
+                },
+                Finding::Reused(vec![
+                    location("default", "API_KEY"),
+                    location("default", "BACKUP_KEY"),
+                    location("production", "API_KEY"),
+                ]),
+            ]
+        );
+        assert!(findings[0].is_severe());
+        assert!(!findings[1].is_severe());
+    }
+}
@@ -0,0 +1,268 @@
+//! Provider benchmarks
+//!
+//! Measures how long a provider takes to store and retrieve synthetic
+//! secrets. This backs `secretspec bench`, which compares providers side by
+//! side, and the criterion benches in `benches/providers.rs`.
+//!
+//! Synthetic secrets are named `SECRETSPEC_BENCH_<n>` and stored in the
+//! `secretspec-bench` project, so repeated runs overwrite the same entries
+//! instead of accumulating new ones.
+
+use crate::provider::Provider;
+use crate::provider::chunked::ChunkedProvider;
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use std::time::{Duration, Instant};
+
+/// Project that synthetic secrets are stored in.
+pub const BENCH_PROJECT: &str = "secretspec-bench";
+
+/// Profile that synthetic secrets are stored in.
+pub const BENCH_PROFILE: &str = "default";
+
+/// Settings for a benchmark run.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Number of distinct synthetic secrets
+    pub keys: usize,
+    /// Size of each synthetic value in bytes
+    pub value_size: usize,
+    /// Number of times every secret is written and read
+    pub iterations: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            keys: 10,
+            value_size: 64,
+            iterations: 3,
+        }
+    }
+}
+
+/// Latencies measured for one kind of operation.
+#[derive(Debug, Clone, Default)]
+pub struct Latencies {
+    /// Duration of every operation, in the order they ran
+    samples: Vec<Duration>,
+}
+
+impl Latencies {
+    /// Adds a measured operation.
+    pub fn push(&mut self, sample: Duration) {
+        self.samples.push(sample);
+    }
+
+    /// Returns the number of measured operations.
+    pub fn count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns the latency below which the given share of operations finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `quantile` - A value between 0 and 1, e.g. 0.95 for the 95th percentile
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        let index = ((sorted.len() - 1) as f64 * quantile.clamp(0.0, 1.0)).round() as usize;
+        sorted[index]
+    }
+
+    /// Returns the average latency.
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.samples.len()) {
+            Ok(0) | Err(_) => Duration::ZERO,
+            Ok(n) => self.samples.iter().sum::<Duration>() / n,
+        }
+    }
+
+    /// Returns how many operations completed per second when run one after another.
+    pub fn throughput(&self) -> f64 {
+        let total: Duration = self.samples.iter().sum();
+        if total.is_zero() {
+            0.0
+        } else {
+            self.samples.len() as f64 / total.as_secs_f64()
+        }
+    }
+}
+
+/// Results of benchmarking one provider.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    /// Name of the benchmarked provider
+    pub provider: &'static str,
+    /// Latencies of writes, or `None` for read-only providers
+    pub set: Option<Latencies>,
+    /// Latencies of reads
+    pub get: Latencies,
+}
+
+/// A provider opened for benchmarking.
+pub struct BenchProvider {
+    /// The provider, wrapped for chunking like in regular use
+    provider: Box<dyn Provider>,
+}
+
+impl BenchProvider {
+    /// Opens the provider named by a provider URI.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI does not name a valid provider
+    pub fn open(spec: &str) -> Result<Self> {
+        let provider = Box::<dyn Provider>::try_from(spec)?;
+        Ok(Self {
+            provider: ChunkedProvider::wrap(provider),
+        })
+    }
+
+    /// Returns the name of the provider.
+    pub fn name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Returns whether the provider can store the synthetic secrets.
+    pub fn allows_set(&self) -> bool {
+        self.provider.allows_set()
+    }
+
+    /// Returns the name of the synthetic secret with the given index.
+    pub fn key(index: usize) -> String {
+        format!("SECRETSPEC_BENCH_{}", index)
+    }
+
+    /// Returns a synthetic value of the given size in bytes.
+    pub fn value(size: usize) -> SecretString {
+        const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+        let value: String = (0..size)
+            .map(|i| ALPHABET[i % ALPHABET.len()] as char)
+            .collect();
+        SecretString::new(value.into())
+    }
+
+    /// Stores a synthetic secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider fails to store the value
+    pub fn set(&self, key: &str, value: &SecretString) -> Result<()> {
+        self.provider.set(BENCH_PROJECT, key, value, BENCH_PROFILE)
+    }
+
+    /// Retrieves a synthetic secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider fails to retrieve the value
+    pub fn get(&self, key: &str) -> Result<Option<SecretString>> {
+        self.provider.get(BENCH_PROJECT, key, BENCH_PROFILE)
+    }
+
+    /// Writes and reads every synthetic secret, measuring each operation.
+    ///
+    /// Read-only providers are only read from, so their reads measure
+    /// lookups of secrets that don't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an operation fails or a read returns a different
+    /// value than was written
+    pub fn run(&self, options: &BenchOptions) -> Result<BenchReport> {
+        let value = Self::value(options.value_size);
+        let keys: Vec<String> = (0..options.keys).map(Self::key).collect();
+        let mut set = self.allows_set().then(Latencies::default);
+        let mut get = Latencies::default();
+
+        for _ in 0..options.iterations {
+            if let Some(set) = set.as_mut() {
+                for key in &keys {
+                    let start = Instant::now();
+                    self.set(key, &value)?;
+                    set.push(start.elapsed());
+                }
+            }
+
+            for key in &keys {
+                let start = Instant::now();
+                let stored = self.get(key)?;
+                get.push(start.elapsed());
+
+                let matches = stored
+                    .as_ref()
+                    .is_some_and(|stored| stored.expose_secret() == value.expose_secret());
+                if set.is_some() && !matches {
+                    return Err(SecretSpecError::ProviderOperationFailed(format!(
+                        "{} returned a different value for {} than was stored",
+                        self.name(),
+                        key
+                    )));
+                }
+            }
+        }
+
+        Ok(BenchReport {
+            provider: self.name(),
+            set,
+            get,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_latency_statistics() {
+        let mut latencies = Latencies::default();
+        for ms in [40, 10, 30, 20, 50] {
+            latencies.push(Duration::from_millis(ms));
+        }
+
+        assert_eq!(latencies.count(), 5);
+        assert_eq!(latencies.percentile(0.5), Duration::from_millis(30));
+        assert_eq!(latencies.percentile(0.95), Duration::from_millis(50));
+        assert_eq!(latencies.percentile(0.0), Duration::from_millis(10));
+        assert_eq!(latencies.mean(), Duration::from_millis(30));
+        assert!((latencies.throughput() - 5.0 / 0.15).abs() < 1e-9);
+
+        let empty = Latencies::default();
+        assert_eq!(empty.percentile(0.5), Duration::ZERO);
+        assert_eq!(empty.mean(), Duration::ZERO);
+        assert_eq!(empty.throughput(), 0.0);
+    }
+
+    #[test]
+    fn test_run_against_dotenv() {
+        let dir = TempDir::new().unwrap();
+        let spec = format!("dotenv://{}", dir.path().join(".env").display());
+        let provider = BenchProvider::open(&spec).unwrap();
+        let options = BenchOptions {
+            keys: 3,
+            value_size: 100,
+            iterations: 2,
+        };
+
+        let report = provider.run(&options).unwrap();
+        assert_eq!(report.provider, "dotenv");
+        assert_eq!(report.set.unwrap().count(), 6);
+        assert_eq!(report.get.count(), 6);
+        assert_eq!(
+            provider
+                .get("SECRETSPEC_BENCH_2")
+                .unwrap()
+                .unwrap()
+                .expose_secret()
+                .len(),
+            100
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Cargo credential provider protocol
+//!
+//! `secretspec cargo-credential` keeps registry tokens in the configured
+//! provider instead of `~/.cargo/credentials.toml`:
+//!
+//! ```toml
+//! # ~/.cargo/config.toml
+//! [registry]
+//! global-credential-providers = ["secretspec cargo-credential"]
+//! ```
+//!
+//! Cargo starts the command with `--cargo-plugin` appended, reads a hello
+//! line announcing the protocol versions, then writes one JSON request per
+//! line to stdin and reads one JSON response per line from stdout. A token
+//! is the secret Cargo would otherwise read from the environment:
+//! `CARGO_REGISTRY_TOKEN` for crates.io and `CARGO_REGISTRIES_<NAME>_TOKEN`
+//! for the registry `<name>`. Registries whose secret isn't declared are
+//! answered with `not-found`, so Cargo moves on to its next provider.
+
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{BufRead, Write};
+
+/// The protocol versions spoken, announced before the first request.
+const HELLO: &str = r#"{"v":[1]}"#;
+
+/// Index URLs of crates.io, which has no registry name of its own.
+const CRATES_IO: [&str; 2] = [
+    "https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+/// A request from Cargo.
+#[derive(Deserialize)]
+struct Request {
+    /// Protocol version of the request
+    v: u32,
+    registry: Registry,
+    #[serde(flatten)]
+    action: Action,
+}
+
+/// The registry a request is about.
+#[derive(Deserialize)]
+struct Registry {
+    #[serde(rename = "index-url")]
+    index_url: String,
+    /// Name of the registry in Cargo's config, absent for `--index`
+    name: Option<String>,
+}
+
+/// What Cargo asks for.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub(crate) enum Action {
+    /// Read the token of a registry
+    Get,
+    /// Store a token, given on the `cargo login` command line
+    Login { token: Option<SecretString> },
+    /// Remove the token
+    Logout,
+    /// A request added in a later version of the protocol
+    #[serde(other)]
+    Unknown,
+}
+
+impl Registry {
+    /// Returns the secret holding the registry's token, named as Cargo's
+    /// environment variable for it.
+    fn token_name(&self) -> Option<String> {
+        if self.name.as_deref() == Some("crates-io") || CRATES_IO.contains(&self.index_url.as_str())
+        {
+            return Some("CARGO_REGISTRY_TOKEN".to_string());
+        }
+        self.name.as_ref().map(|name| {
+            format!(
+                "CARGO_REGISTRIES_{}_TOKEN",
+                name.to_uppercase().replace('-', "_")
+            )
+        })
+    }
+}
+
+/// Answers Cargo's requests until it closes stdin.
+///
+/// `answer` is called with the name of the token's secret and the request,
+/// and returns the token for `get` requests.
+///
+/// # Errors
+///
+/// Returns an error if reading a request or writing a response fails.
+/// Failed requests are reported to Cargo rather than returned.
+pub(crate) fn serve(
+    input: impl BufRead,
+    mut output: impl Write,
+    mut answer: impl FnMut(&str, Action) -> Result<Option<SecretString>>,
+) -> Result<()> {
+    writeln!(output, "{}", HELLO)?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = respond(&line, &mut answer);
+        writeln!(output, "{}", response)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Returns the response to a request line.
+fn respond(
+    line: &str,
+    answer: &mut impl FnMut(&str, Action) -> Result<Option<SecretString>>,
+) -> serde_json::Value {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return other(&format!("malformed request: {}", e)),
+    };
+    if request.v != 1 {
+        return other(&format!("protocol version {} is not supported", request.v));
+    }
+    let Some(name) = request.registry.token_name() else {
+        return json!({"Err": {"kind": "url-not-supported"}});
+    };
+    let kind = match request.action {
+        Action::Get => "get",
+        Action::Login { .. } => "login",
+        Action::Logout => "logout",
+        Action::Unknown => return json!({"Err": {"kind": "operation-not-supported"}}),
+    };
+    match answer(&name, request.action) {
+        Ok(Some(token)) => json!({"Ok": {
+            "kind": kind,
+            "token": token.expose_secret(),
+            "cache": "session",
+            "operation_independent": true,
+        }}),
+        Ok(None) => json!({"Ok": {"kind": kind}}),
+        Err(SecretSpecError::SecretNotFound(_) | SecretSpecError::SecretNotDeclared { .. }) => {
+            json!({"Err": {"kind": "not-found"}})
+        }
+        Err(e) => other(&e.to_string()),
+    }
+}
+
+/// Returns an error response with a message Cargo shows.
+fn other(message: &str) -> serde_json::Value {
+    json!({"Err": {"kind": "other", "message": message}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve() {
+        let input = concat!(
+            r#"{"v":1,"registry":{"index-url":"sparse+https://index.crates.io/","name":"crates-io","headers":[]},"kind":"get","operation":"read","args":[]}"#,
+            "\n",
+            r#"{"v":1,"registry":{"index-url":"sparse+https://my-registry.example/","name":"my-registry"},"kind":"login","token":"tok-2","args":[]}"#,
+            "\n",
+            r#"{"v":1,"registry":{"index-url":"sparse+https://other.example/","name":"other"},"kind":"get","operation":"publish","name":"app","vers":"1.0.0","cksum":"abc","args":[]}"#,
+            "\n",
+            r#"{"v":1,"registry":{"index-url":"sparse+https://unnamed.example/"},"kind":"logout","args":[]}"#,
+            "\n",
+        );
+        let mut calls = Vec::new();
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, |name, action| {
+            calls.push(name.to_string());
+            match action {
+                Action::Get if name == "CARGO_REGISTRY_TOKEN" => {
+                    Ok(Some(SecretString::from("tok-1")))
+                }
+                Action::Get => Err(SecretSpecError::SecretNotFound(name.to_string())),
+                Action::Login { token } => {
+                    assert_eq!(token.unwrap().expose_secret(), "tok-2");
+                    Ok(None)
+                }
+                _ => unreachable!(),
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            calls,
+            [
+                "CARGO_REGISTRY_TOKEN",
+                "CARGO_REGISTRIES_MY_REGISTRY_TOKEN",
+                "CARGO_REGISTRIES_OTHER_TOKEN"
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                r#"{"v":[1]}"#,
+                "\n",
+                r#"{"Ok":{"cache":"session","kind":"get","operation_independent":true,"token":"tok-1"}}"#,
+                "\n",
+                r#"{"Ok":{"kind":"login"}}"#,
+                "\n",
+                r#"{"Err":{"kind":"not-found"}}"#,
+                "\n",
+                r#"{"Err":{"kind":"url-not-supported"}}"#,
+                "\n",
+            )
+        );
+    }
+}
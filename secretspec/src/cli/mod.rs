@@ -1,12 +1,42 @@
-use crate::provider::{dotenv::DotEnvProvider, providers};
-use crate::{Config, GlobalConfig, GlobalDefaults, Profile, Project, Secrets};
-use clap::{Parser, Subcommand};
+mod release;
+
+#[cfg(unix)]
+use crate::agent;
+use crate::bench::{BenchOptions, BenchProvider, Latencies};
+use crate::config;
+use crate::conformance::ConformanceSuite;
+use crate::devenv::{self, HookTarget};
+use crate::docker;
+use crate::doctor::{self, Check, Status};
+use crate::output;
+use crate::plan::{Plan, PlanFormat, PlanOperation};
+use crate::prompt;
+use crate::provider::{self, command::SystemRunner, dotenv::DotEnvProvider, providers};
+use crate::schema;
+use crate::stats::Stats;
+use crate::systemd;
+use crate::telemetry;
+use crate::terraform;
+use crate::tpm;
+use crate::usage;
+use crate::{
+    Config, EnvFinding, ExportFormat, Finding, GlobalConfig, GlobalDefaults, KeysConfig, Matrix,
+    MatrixFormat, NotifyConfig, Presence, Profile, Project, RunConfig, SecretSpecError, Secrets,
+    SpecFormat,
+};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use colored::Colorize;
 use miette::{IntoDiagnostic, Result, WrapErr, miette};
+use release::Channel;
+use secrecy::{ExposeSecret, SecretString};
 use std::collections::HashMap;
 use std::fs;
+use std::io::{self, Read};
+use std::net::SocketAddr;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Main CLI structure for the secretspec application.
 ///
@@ -20,6 +50,18 @@ struct Cli {
     /// The subcommand to execute
     #[command(subcommand)]
     command: Commands,
+    /// Project name to use instead of the one in secretspec.toml or detected
+    /// from the git remote, a package manifest or the directory
+    #[arg(long, global = true, env = "SECRETSPEC_PROJECT")]
+    project: Option<String>,
+    /// Export traces and metrics of provider calls to an OpenTelemetry
+    /// collector over OTLP/HTTP (see OTEL_EXPORTER_OTLP_ENDPOINT; without the
+    /// otlp feature, an experimental curl sink is used)
+    #[arg(long, global = true, env = "SECRETSPEC_OTEL")]
+    otel: bool,
+    /// Print without colors, as when stdout isn't a terminal or NO_COLOR is set
+    #[arg(long, global = true)]
+    no_color: bool,
 }
 
 /// Available commands for the secretspec CLI.
@@ -41,43 +83,339 @@ enum Commands {
         name: String,
         /// Value of the secret (will prompt if not provided)
         value: Option<String>,
+        /// Read the value from stdin until EOF
+        #[arg(long, conflicts_with_all = ["value", "from_file", "editor"])]
+        from_stdin: bool,
+        /// Read the value from a file
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["value", "editor"])]
+        from_file: Option<PathBuf>,
+        /// Open $EDITOR to enter a multi-line value
+        #[arg(long, conflicts_with = "value")]
+        editor: bool,
+        /// Only write if the secret is still at this revision, as shown by `list`
+        #[arg(long, value_name = "REVISION")]
+        if_revision: Option<String>,
+        /// Set the secret even if secretspec.toml doesn't declare it
+        #[arg(long)]
+        force: bool,
         /// Provider backend to use
         #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
         provider: Option<String>,
-        /// Profile to use
-        #[arg(short = 'P', long, env = "SECRETSPEC_PROFILE")]
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
         profile: Option<String>,
     },
     /// Get a secret value
     Get {
         /// Name of the secret
         name: String,
+        /// Get the secret even if secretspec.toml doesn't declare it
+        #[arg(long)]
+        force: bool,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Show step by step how a secret resolves, without printing its value
+    Explain {
+        /// Name of the secret
+        name: String,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// List the declared secrets with their revision metadata
+    List {
         /// Provider backend to use
         #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
         provider: Option<String>,
-        /// Profile to use
-        #[arg(short = 'P', long, env = "SECRETSPEC_PROFILE")]
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
         profile: Option<String>,
+        /// Only list the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
     },
     /// Run a command with secrets injected
     Run {
         /// Provider backend to use
         #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
         provider: Option<String>,
-        /// Profile to use
-        #[arg(short = 'P', long, env = "SECRETSPEC_PROFILE")]
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
         profile: Option<String>,
+        /// Only inject the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Only pass on the environment variables allowed in the spec's [run] section
+        #[arg(long)]
+        clean_env: bool,
+        /// Disable core dumps and ptrace access before resolving secrets
+        #[arg(long)]
+        harden: bool,
+        /// Replace secret values in the command's output with [REDACTED:KEY]
+        #[arg(long)]
+        redact_output: bool,
         /// Command and arguments to run
         #[arg(trailing_var_arg = true)]
         command: Vec<String>,
     },
+    /// Run a hook whenever the declared secrets change
+    Watch {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Only watch the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Only pass on the environment variables allowed in the spec's [run] section
+        #[arg(long)]
+        clean_env: bool,
+        /// Seconds between checks for changes
+        #[arg(long, value_name = "SECONDS", default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Shell command run with the secrets injected after a change
+        #[arg(long, value_name = "COMMAND")]
+        on_change: String,
+    },
     /// Check if all required secrets are in the provider, if not set them
     Check {
         /// Provider backend to use
         #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
         provider: Option<String>,
-        /// Profile to use
-        #[arg(short = 'P', long, env = "SECRETSPEC_PROFILE")]
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Fail if secrets differ from secretspec.lock instead of updating it
+        #[arg(long)]
+        frozen: bool,
+        /// Only check the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Check every profile against its provider and print which secrets are set
+        #[arg(long, conflicts_with_all = ["profile", "frozen"])]
+        all_profiles: bool,
+        /// Output format of --all-profiles: table, csv or json
+        #[arg(long, default_value = "table", requires = "all_profiles")]
+        format: MatrixFormat,
+    },
+    /// Set the declared secrets that are missing, leaving existing ones alone
+    Ensure {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Only ensure the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Read values from a TOML file mapping secret names to values
+        #[arg(long, value_name = "PATH")]
+        from_file: Option<PathBuf>,
+    },
+    /// Print the resolved secrets for other tools, like Ansible
+    Export {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Only export the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Output format: shell, docker, json, ansible-vars or systemd
+        #[arg(long, default_value = "shell")]
+        format: ExportFormat,
+    },
+    /// Write the secrets to an environment file for the systemd service running a process
+    Env {
+        /// Process whose service receives the secrets
+        #[arg(long)]
+        pid: u32,
+        /// Environment file to write instead of /run/secretspec/<service>.env
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Only write the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+    },
+    /// Compare the environment of a running process with the resolved secrets
+    EnvAudit {
+        /// Process to inspect; without it, the environment of secretspec itself,
+        /// as under `secretspec run -- secretspec env-audit`
+        #[arg(long)]
+        pid: Option<u32>,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Only compare the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+    },
+    /// Print the resolved secrets as deterministic JSON for Nix shells
+    Nix {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Only print the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Refuse providers that need the network, for sandboxed builds
+        #[arg(long)]
+        sandbox: bool,
+    },
+    /// Read secrets from Terraform's external data source
+    ///
+    /// Terraform passes a query like {"profile": "production", "secrets":
+    /// "API_KEY,DATABASE_URL"} on stdin and reads the secrets as a JSON
+    /// object from stdout. The query may also set provider and group.
+    TerraformOutput,
+    /// Act as a Cargo credential provider for registry tokens
+    ///
+    /// Add `global-credential-providers = ["secretspec cargo-credential"]`
+    /// to the [registry] table of ~/.cargo/config.toml. The token of a
+    /// registry is the secret CARGO_REGISTRY_TOKEN for crates.io and
+    /// CARGO_REGISTRIES_<NAME>_TOKEN for others.
+    CargoCredential {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Set by Cargo when it starts the provider
+        #[arg(long, hide = true)]
+        cargo_plugin: bool,
+    },
+    /// Act as a Docker credential helper for registry logins
+    ///
+    /// Docker runs the helper with get, store, erase or list and talks to it
+    /// over stdin and stdout. Link the binary as docker-credential-secretspec
+    /// and set "credsStore": "secretspec" in ~/.docker/config.json, or wrap
+    /// this command in a script of that name.
+    DockerCredential {
+        /// Action Docker asks for: get, store, erase or list
+        action: String,
+        /// Provider backend to store logins in
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+    },
+    /// Print an .npmrc with registry tokens read from the provider
+    Npmrc {
+        /// Registry as [@scope:]URL=SECRET; defaults to https://registry.npmjs.org/=NPM_TOKEN
+        #[arg(long = "registry", value_name = "[@SCOPE:]URL=SECRET")]
+        registries: Vec<String>,
+        /// Write to a file readable only by its owner instead of stdout
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Write PostgreSQL logins of connection URLs to ~/.pgpass
+    Pgpass {
+        /// Secrets holding postgres:// URLs; all of them if none are given
+        secrets: Vec<String>,
+        /// File to write instead of PGPASSFILE or ~/.pgpass
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Write MySQL logins of connection URLs to ~/.my.cnf
+    Mycnf {
+        /// Secrets holding mysql:// URLs; all of them if none are given
+        secrets: Vec<String>,
+        /// Option file to write instead of ~/.my.cnf
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Option group for a single login, instead of client
+        #[arg(long)]
+        group: Option<String>,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Seal an age identity to this machine's TPM
+    ///
+    /// Encrypted providers decrypt with the sealed identity when given
+    /// ?identity=tpm:<OUTPUT>, so the identity needn't be kept in a file.
+    /// Requires a TPM 2.0 and tpm2-tools.
+    TpmSeal {
+        /// age identity file to seal
+        identity: PathBuf,
+        /// Directory the sealed identity is written to
+        output: PathBuf,
+    },
+    /// Load secrets into devenv.sh shells and dev containers
+    Devenv {
+        #[command(subcommand)]
+        action: DevenvAction,
+    },
+    /// Keep provider sessions unlocked in a background agent
+    #[cfg(unix)]
+    Agent {
+        #[command(subcommand)]
+        action: AgentAction,
+    },
+    /// Serve secrets to apps over gRPC, authenticating them with mTLS
+    ///
+    /// Exposes Get, Set, List and Check RPCs (see proto/secretspec.proto)
+    /// for one provider and profile, so apps fetch their secrets from this
+    /// host instead of holding provider credentials. Clients must present a
+    /// certificate signed by --client-ca. Requires secretspec built with the
+    /// 'grpc' feature.
+    Serve {
+        /// Address to serve gRPC on, e.g. 127.0.0.1:50051
+        #[arg(long, value_name = "ADDR")]
+        grpc: SocketAddr,
+        /// PEM certificate chain the server presents
+        #[arg(long, value_name = "PATH")]
+        tls_cert: PathBuf,
+        /// PEM private key of --tls-cert
+        #[arg(long, value_name = "PATH")]
+        tls_key: PathBuf,
+        /// PEM certificates of the CAs client certificates must be signed by
+        #[arg(long, value_name = "PATH")]
+        client_ca: PathBuf,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
         profile: Option<String>,
     },
     /// Init or show ~/.config/secretspec/config.toml
@@ -88,8 +426,305 @@ enum Commands {
     /// Import secrets from a provider to another provider
     Import {
         /// Provider backend to import from (secrets will be imported to the default provider)
-        from_provider: String,
+        #[arg(required_unless_present = "apply_plan", conflicts_with = "apply_plan")]
+        from_provider: Option<String>,
+        /// Print the secrets that would be imported, as text or json, without importing them
+        #[arg(long, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "text")]
+        plan: Option<PlanFormat>,
+        /// Import exactly the secrets of a plan saved with --plan json
+        #[arg(long, value_name = "PATH", conflicts_with = "plan")]
+        apply_plan: Option<PathBuf>,
+    },
+    /// Push the resolved secrets of a profile to another provider, like a deployment platform
+    Sync {
+        /// Provider to push to, e.g. fly://my-app or heroku://my-app
+        #[arg(long)]
+        to: String,
+        /// Provider backend to resolve from
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// List the secrets that would be pushed without pushing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Re-encrypt stored secrets to the recipients in secretspec.toml
+    Rekey {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Print the secrets that would be rekeyed, as text or json, without rekeying them
+        #[arg(long, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "text")]
+        plan: Option<PlanFormat>,
+        /// Rekey exactly the secrets of a plan saved with --plan json
+        #[arg(long, value_name = "PATH", conflicts_with = "plan")]
+        apply_plan: Option<PathBuf>,
+    },
+    /// Replace a secret with a new value from its generator command
+    Rotate {
+        /// Name of the secret to rotate
+        name: String,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Move a stored secret to a new name, optionally in another profile
+    Mv {
+        /// Name the secret is stored under
+        old: String,
+        /// Name to store the secret under
+        new: String,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile the secret is stored in, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Profile to move the secret to (default: the same profile)
+        #[arg(long)]
+        to_profile: Option<String>,
+    },
+    /// Delete a stored secret, moving it to the provider's trash if it has one
+    Delete {
+        /// Name of the secret to delete
+        name: String,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Delete the secret for good instead of moving it to the trash
+        #[arg(long)]
+        permanent: bool,
+    },
+    /// Bring back a secret deleted to the provider's trash
+    Restore {
+        /// Name of the secret to restore
+        name: String,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// List the versions the provider keeps of a secret, newest first
+    History {
+        /// Name of the secret
+        name: String,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Delete secrets stored in the provider that the spec no longer declares
+    Prune {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// List the orphaned secrets without deleting them
+        #[arg(long)]
+        dry_run: bool,
+        /// Delete every orphaned secret without asking
+        #[arg(short, long, conflicts_with = "dry_run")]
+        yes: bool,
+        /// Print the secrets that would be deleted, as text or json, without deleting them
+        #[arg(
+            long,
+            value_name = "FORMAT",
+            num_args = 0..=1,
+            default_missing_value = "text",
+            conflicts_with_all = ["dry_run", "yes"]
+        )]
+        plan: Option<PlanFormat>,
+        /// Delete exactly the secrets of a plan saved with --plan json
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["plan", "dry_run", "yes"])]
+        apply_plan: Option<PathBuf>,
+    },
+    /// Delete secrets whose ttl has passed
+    Gc {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// List the expired secrets without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Take over existing provider items for the declared secrets, addressing
+    /// them by ID from then on
+    Adopt {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// List the vaults of a provider and the names of their items
+    ///
+    /// Helps find the item and field names to use in secretspec.toml.
+    /// Values are never read. Supported for OnePassword.
+    Browse {
+        /// Provider URI to browse (e.g. onepassword://, onepassword://Private)
+        uri: String,
+        /// Only show items fuzzily matching this, best match first
+        query: Option<String>,
+    },
+    /// List who can access the secrets of a profile
+    Access {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Grant a user read access to the secrets of a profile
+    Grant {
+        /// User to grant access to, e.g. an email address
+        principal: String,
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Print the policy without applying it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Diagnose problems with the spec, providers and environment
+    Doctor {
+        /// Provider backend to check
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Rewrite secretspec.toml in the newest schema, preserving comments
+    Migrate {
+        /// Print the migrated file instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Convert the spec to another file format
+    ///
+    /// The spec is written as secretspec.<FORMAT> and the original file is
+    /// removed. Comments are not carried over.
+    Convert {
+        /// Format to convert to: toml, json or yaml
+        to: SpecFormat,
+        /// Print the converted spec instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the JSON Schema of secretspec.toml for editor completion and validation
+    Schema,
+    /// Measure get/set latency of providers with synthetic secrets
+    ///
+    /// Secrets named SECRETSPEC_BENCH_<n> are written to the secretspec-bench
+    /// project of every provider that allows writes.
+    Bench {
+        /// Provider backend to benchmark; repeat to compare several
+        #[arg(short, long, required = true)]
+        provider: Vec<String>,
+        /// Number of synthetic secrets
+        #[arg(long, default_value_t = 10)]
+        keys: usize,
+        /// Size of each synthetic value in bytes
+        #[arg(long, default_value_t = 64)]
+        size: usize,
+        /// Number of times every secret is written and read
+        #[arg(long, default_value_t = 3)]
+        iterations: usize,
+    },
+    /// Inspect provider URIs
+    Provider {
+        #[command(subcommand)]
+        action: ProviderAction,
+    },
+    /// Inspect profile selection
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    /// Check that a provider behaves the way secretspec expects
+    ///
+    /// Writes, reads and deletes SECRETSPEC_CONFORMANCE_* secrets in a
+    /// throwaway secretspec-conformance-<n> project.
+    TestProvider {
+        /// Provider URI to test (e.g. keyring://, onepassword://Testing)
+        uri: String,
+    },
+    /// Show local lookup counts and latency per provider
+    Stats {
+        /// Delete the recorded statistics
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Show which secrets derive their defaults from others
+    Graph {
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Print the graph in Graphviz DOT format
+        #[arg(long)]
+        dot: bool,
+    },
+    /// Show how many items each profile stores in its provider, and their size
+    Usage {
+        /// Provider backend to use for every profile
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Only measure this profile
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
+    /// Report weak, short, guessable and reused secret values, without printing them
+    Analyze {
+        /// Provider backend to use for every profile
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Only analyze this profile
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Only analyze the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Check secrets with type = "password" against Have I Been Pwned,
+        /// sending only the first five characters of their SHA-1 digests
+        #[arg(long)]
+        hibp: bool,
+    },
+    /// Update secretspec to the newest signed release
+    SelfUpdate {
+        /// Release channel to update from
+        #[arg(long, value_enum, default_value = "stable")]
+        channel: Channel,
+        /// Only check whether an update is available
+        #[arg(long)]
+        check: bool,
     },
+    /// Verify the running binary against the signed release it came from
+    VerifyInstall,
 }
 
 /// Configuration-related subcommands.
@@ -102,6 +737,97 @@ enum ConfigAction {
     Init,
     /// Show current configuration
     Show,
+    /// Set a configuration value (e.g., projects.my-app.provider keyring)
+    Set {
+        /// Dotted configuration key
+        key: String,
+        /// Value to store
+        value: String,
+    },
+    /// Get a configuration value
+    Get {
+        /// Dotted configuration key
+        key: String,
+    },
+    /// List all configuration values
+    List,
+}
+
+/// Subcommands of `secretspec devenv`.
+#[derive(Subcommand)]
+enum DevenvAction {
+    /// Print the resolved secrets for a devenv shell or dev container
+    Export {
+        /// Provider backend to use
+        #[arg(short, long, env = "SECRETSPEC_PROVIDER")]
+        provider: Option<String>,
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+        /// Only export the secrets in these groups
+        #[arg(short, long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Output format: shell, docker, json, ansible-vars or systemd
+        #[arg(long, default_value = "shell")]
+        format: ExportFormat,
+    },
+    /// Print a hook that loads the secrets, for devenv or devcontainer
+    Hook {
+        /// Environment to load the secrets into: devenv or devcontainer
+        target: HookTarget,
+    },
+}
+
+/// Subcommands of `secretspec agent`.
+#[cfg(unix)]
+#[derive(Subcommand)]
+enum AgentAction {
+    /// Start the agent and print the shell commands pointing secretspec at it
+    Start {
+        /// Socket to listen on instead of the default
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+        /// Lock after this many seconds without lookups
+        #[arg(long, value_name = "SECONDS", default_value_t = 900, value_parser = clap::value_parser!(u64).range(1..))]
+        idle_timeout: u64,
+        /// Run in the foreground instead of in the background
+        #[arg(long)]
+        foreground: bool,
+        /// Keep sessions when locking after the idle timeout and unlock with Touch ID (macOS)
+        #[arg(long)]
+        biometric: bool,
+    },
+    /// Drop the agent's provider sessions until the next lookup
+    Lock,
+    /// Stop the agent
+    Stop,
+}
+
+/// Subcommands of `secretspec provider`.
+#[derive(Subcommand)]
+enum ProviderAction {
+    /// Show the configuration a provider URI parses to
+    ///
+    /// Fails on query parameters the provider doesn't understand, which
+    /// commands silently ignore.
+    Explain {
+        /// Provider URI to explain (e.g. "bitwarden://org@collection?type=card")
+        uri: String,
+    },
+}
+
+/// Subcommands of `secretspec profile`.
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// Show the active profile and where it was chosen
+    ///
+    /// The first of --profile, SECRETSPEC_PROFILE, the user configuration
+    /// and the spec's `default` profile that selects a profile wins.
+    Show {
+        /// Profile to use, overriding SECRETSPEC_PROFILE
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+    },
 }
 
 /// Returns an example TOML configuration string
@@ -147,7 +873,7 @@ fn generate_toml_with_comments(config: &Config) -> crate::Result<String> {
     // Project section
     output.push_str("[project]\n");
     output.push_str(&format!("name = \"{}\"\n", config.project.name));
-    output.push_str(&format!("revision = \"{}\"\n", config.project.revision));
+    output.push_str(&format!("schema = {}\n", config.project.schema));
 
     // Add extends comment and field if needed
     output.push_str("# Extend configurations from subdirectories\n");
@@ -156,6 +882,9 @@ fn generate_toml_with_comments(config: &Config) -> crate::Result<String> {
     // Profile sections
     for (profile_name, profile_config) in &config.profiles {
         output.push_str(&format!("\n[profiles.{}]\n", profile_name));
+        if let Some(provider) = &profile_config.provider {
+            output.push_str(&format!("provider = \"{}\"\n", provider));
+        }
 
         for (secret_name, secret_config) in &profile_config.secrets {
             output.push_str(&format!(
@@ -176,6 +905,164 @@ fn generate_toml_with_comments(config: &Config) -> crate::Result<String> {
     Ok(output)
 }
 
+/// Prints a plan for review or as JSON to apply later.
+fn print_plan(plan: &Plan, format: PlanFormat) -> Result<()> {
+    match format {
+        PlanFormat::Text => print!("{}", plan),
+        PlanFormat::Json => print!("{}", plan.to_json().into_diagnostic()?),
+    }
+    Ok(())
+}
+
+/// Reads a plan saved with `--plan json`, checking it was made by the
+/// command applying it.
+fn read_plan(path: &Path, operation: PlanOperation) -> Result<Plan> {
+    let json = fs::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to read plan {}", path.display()))?;
+    let plan = Plan::from_json(&json)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to read plan {}", path.display()))?;
+    if plan.operation != operation {
+        return Err(miette!(
+            "{} is a plan for secretspec {}, not {}",
+            path.display(),
+            plan.operation.as_str(),
+            operation.as_str()
+        ));
+    }
+    Ok(plan)
+}
+
+/// Prints the presence matrix of `check --all-profiles` as a table, with a
+/// row per secret and a column per profile.
+fn print_matrix(matrix: &Matrix) {
+    let secrets = matrix.secrets();
+    let width = secrets
+        .iter()
+        .map(|secret| secret.len())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    let headers: Vec<String> = matrix
+        .profiles
+        .iter()
+        .map(|column| match &column.provider {
+            Some(provider) => format!("{} ({})", column.profile, provider),
+            None => column.profile.clone(),
+        })
+        .collect();
+
+    print!("{:<width$}", "Secret".bold(), width = width);
+    for header in &headers {
+        print!("  {}", header.bold());
+    }
+    println!();
+    for secret in &secrets {
+        print!("{:<width$}", secret, width = width);
+        for (column, header) in matrix.profiles.iter().zip(&headers) {
+            let cell = match (&column.error, column.secrets.get(*secret)) {
+                (Some(_), _) => "?".yellow(),
+                (None, Some(Presence::Set)) => "✓".green(),
+                (None, Some(Presence::Default)) => "default".normal(),
+                (None, Some(Presence::Missing)) => "✗".red(),
+                (None, Some(Presence::Unset)) => "○".dimmed(),
+                (None, None) => "".normal(),
+            };
+            // Colors don't count towards the width
+            let padding = header.chars().count().saturating_sub(cell.chars().count());
+            print!("  {}{}", cell, " ".repeat(padding));
+        }
+        println!();
+    }
+    for column in &matrix.profiles {
+        if let Some(error) = &column.error {
+            println!("{} {}: {}", "?".yellow(), column.profile, error);
+        }
+    }
+}
+
+/// Warns when `--provider` reads production secrets from another backend
+/// than the spec pins them to.
+fn warn_on_provider_override(app: &Secrets) {
+    if let Some(warning) = app.provider_override_warning() {
+        crate::warning::warn(warning);
+    }
+}
+
+/// Prints checks with a status symbol and fix suggestions, followed by a summary.
+///
+/// # Errors
+///
+/// Returns an error if any check failed
+fn print_checks(checks: &[Check]) -> Result<()> {
+    for check in checks {
+        let symbol = match check.status {
+            Status::Pass => "✓".green(),
+            Status::Warn => "!".yellow(),
+            Status::Fail => "✗".red(),
+        };
+        println!("{} {} - {}", symbol, check.name.bold(), check.detail);
+        if let Some(fix) = &check.fix {
+            for line in fix.lines() {
+                println!("    {}", line.dimmed());
+            }
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warned = checks.iter().filter(|c| c.status == Status::Warn).count();
+    println!(
+        "\nSummary: {} passed, {} warnings, {} failed",
+        (checks.len() - failed - warned).to_string().green(),
+        warned.to_string().yellow(),
+        failed.to_string().red()
+    );
+    if failed > 0 {
+        return Err(miette!("{} check(s) failed", failed));
+    }
+    Ok(())
+}
+
+/// Answers a request of Docker's credential helper protocol, reading the
+/// request from stdin.
+///
+/// Docker only recognizes errors written to stdout, so they are printed
+/// there and the process exits with a failure status.
+fn docker_credential(action: &str, provider: Option<String>) -> Result<()> {
+    let result = docker::provider(provider).and_then(|provider| {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        docker::run(provider.as_ref(), action, &input)
+    });
+    match result {
+        Ok(output) => {
+            print!("{}", output.as_str());
+            Ok(())
+        }
+        Err(SecretSpecError::SecretNotFound(message)) if message == docker::NOT_FOUND => {
+            println!("{}", message);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns the action Docker asks for when the binary runs as
+/// `docker-credential-secretspec`.
+fn docker_helper_action() -> Option<String> {
+    let mut args = std::env::args_os();
+    let program = PathBuf::from(args.next()?);
+    let name = program.file_stem()?.to_string_lossy().into_owned();
+    if !name.starts_with("docker-credential-") {
+        return None;
+    }
+    Some(args.next()?.to_string_lossy().into_owned())
+}
+
 /// Main entry point for the secretspec CLI application.
 ///
 /// Parses command-line arguments and executes the appropriate command.
@@ -187,18 +1074,38 @@ fn generate_toml_with_comments(config: &Config) -> crate::Result<String> {
 /// * `Err` - If any error occurred during execution
 #[doc(hidden)]
 pub fn main() -> Result<()> {
-    let cli = Cli::parse();
+    if let Some(action) = docker_helper_action() {
+        return docker_credential(&action, std::env::var("SECRETSPEC_PROVIDER").ok());
+    }
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(project) = cli.project {
+        config::set_project_override(project);
+    }
+    if cli.no_color {
+        output::set_color(false);
+    }
+    output::set_progress(true);
+    if cli.otel {
+        let name = matches.subcommand_name().unwrap_or_default();
+        telemetry::enable(format!("secretspec {}", name));
+    }
+
+    let result = execute(cli.command);
+    telemetry::finish(result.as_ref().err().map(|e| e as &dyn std::fmt::Display));
+    result
+}
 
-    match cli.command {
+/// Executes a subcommand.
+fn execute(command: Commands) -> Result<()> {
+    match command {
         // Initialize a new secretspec.toml configuration file
         Commands::Init { from } => {
             // Check if secretspec.toml already exists
             if PathBuf::from("secretspec.toml").exists() {
-                use inquire::Confirm;
-                let overwrite = Confirm::new("secretspec.toml already exists. Overwrite?")
-                    .with_default(false)
-                    .prompt()
-                    .into_diagnostic()?;
+                let overwrite =
+                    prompt::confirm("secretspec.toml already exists. Overwrite?", false)
+                        .into_diagnostic()?;
 
                 if !overwrite {
                     println!("Cancelled.");
@@ -229,20 +1136,27 @@ pub fn main() -> Result<()> {
 
             // Create a new project config
             let mut profiles = HashMap::new();
-            profiles.insert("default".to_string(), Profile { secrets });
+            profiles.insert(
+                "default".to_string(),
+                Profile {
+                    provider: None,
+                    secrets,
+                },
+            );
 
             let project_config = Config {
                 project: Project {
-                    name: std::env::current_dir()
-                        .into_diagnostic()?
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string(),
-                    revision: "1.0".to_string(),
+                    name: config::project_name("", &std::env::current_dir().into_diagnostic()?)
+                        .unwrap_or_default(),
+                    schema: config::CURRENT_SCHEMA,
                     extends: None,
                 },
                 profiles,
+                recipients: HashMap::new(),
+                assume_role: HashMap::new(),
+                run: RunConfig::default(),
+                notify: NotifyConfig::default(),
+                keys: KeysConfig::default(),
             };
             let mut content = generate_toml_with_comments(&project_config).into_diagnostic()?;
 
@@ -274,12 +1188,54 @@ pub fn main() -> Result<()> {
 
             Ok(())
         }
+        // Serve secrets over gRPC
+        Commands::Serve {
+            grpc,
+            tls_cert,
+            tls_key,
+            client_ca,
+            provider,
+            profile,
+        } => {
+            #[cfg(feature = "grpc")]
+            {
+                let mut app = Secrets::load()
+                    .into_diagnostic()
+                    .wrap_err("Failed to load secretspec configuration")?;
+                if let Some(p) = provider {
+                    app.set_provider(p);
+                }
+                if let Some(p) = profile {
+                    app.set_profile(p);
+                }
+                let config = crate::grpc::ServeConfig {
+                    addr: grpc,
+                    tls_cert,
+                    tls_key,
+                    client_ca,
+                };
+                crate::grpc::serve(app, &config, |addr| {
+                    eprintln!("Serving secrets over gRPC on {}", addr);
+                })
+                .into_diagnostic()
+                .wrap_err("gRPC server failed")
+            }
+            #[cfg(not(feature = "grpc"))]
+            {
+                let _ = (grpc, tls_cert, tls_key, client_ca, provider, profile);
+                Err(miette!(
+                    "secretspec serve --grpc requires secretspec built with the 'grpc' feature"
+                ))
+            }
+        }
         // Handle configuration management commands
         Commands::Config { action } => match action {
             // Initialize user configuration with interactive prompts
             ConfigAction::Init => {
                 use inquire::Select;
 
+                prompt::require_tty("run the interactive configuration").into_diagnostic()?;
+
                 // Get provider choices from the centralized registry
                 let provider_choices: Vec<String> = providers()
                     .into_iter()
@@ -308,11 +1264,11 @@ pub fn main() -> Result<()> {
                     Some(profile_choice.to_string())
                 };
 
-                let config = GlobalConfig {
-                    defaults: GlobalDefaults {
-                        provider: Some(provider.to_string()),
-                        profile,
-                    },
+                let mut config = GlobalConfig::load().into_diagnostic()?.unwrap_or_default();
+                config.defaults = GlobalDefaults {
+                    provider: Some(provider.to_string()),
+                    profile,
+                    ..config.defaults
                 };
 
                 config.save().into_diagnostic()?;
@@ -347,11 +1303,43 @@ pub fn main() -> Result<()> {
                 }
                 Ok(())
             }
+            // Set a single configuration value
+            ConfigAction::Set { key, value } => {
+                let mut config = GlobalConfig::load().into_diagnostic()?.unwrap_or_default();
+                config.set(&key, &value).map_err(|e| miette!(e))?;
+                config.save().into_diagnostic()?;
+                println!("✓ {} = {}", key, value);
+                Ok(())
+            }
+            // Print a single configuration value
+            ConfigAction::Get { key } => {
+                let config = GlobalConfig::load().into_diagnostic()?.unwrap_or_default();
+                match config.get(&key).map_err(|e| miette!(e))? {
+                    Some(value) => {
+                        println!("{}", value);
+                        Ok(())
+                    }
+                    None => Err(miette!("Configuration key '{}' is not set", key)),
+                }
+            }
+            // Print all configuration values
+            ConfigAction::List => {
+                let config = GlobalConfig::load().into_diagnostic()?.unwrap_or_default();
+                for (key, value) in config.entries() {
+                    println!("{} = {}", key, value);
+                }
+                Ok(())
+            }
         },
         // Set a secret value in the specified provider
         Commands::Set {
             name,
             value,
+            from_stdin,
+            from_file,
+            editor,
+            if_revision,
+            force,
             provider,
             profile,
         } => {
@@ -364,14 +1352,29 @@ pub fn main() -> Result<()> {
             if let Some(p) = profile {
                 app.set_profile(p);
             }
-            app.set(&name, value)
-                .into_diagnostic()
-                .wrap_err("Failed to set secret")?;
+            app.set_allow_undeclared(force);
+            warn_on_provider_override(&app);
+            let value = if from_stdin {
+                Some(prompt::from_stdin().into_diagnostic()?)
+            } else if let Some(path) = from_file {
+                Some(prompt::from_file(&path).into_diagnostic()?)
+            } else if editor {
+                Some(prompt::from_editor(&name).into_diagnostic()?)
+            } else {
+                value.map(|v| SecretString::new(v.into()))
+            };
+            match if_revision {
+                Some(revision) => app.set_if_revision(&name, value, &revision),
+                None => app.set_value(&name, value),
+            }
+            .into_diagnostic()
+            .wrap_err("Failed to set secret")?;
             Ok(())
         }
         // Retrieve and display a secret value
         Commands::Get {
             name,
+            force,
             provider,
             profile,
         } => {
@@ -384,9 +1387,63 @@ pub fn main() -> Result<()> {
             if let Some(p) = profile {
                 app.set_profile(p);
             }
-            app.get(&name)
+            app.set_allow_undeclared(force);
+            warn_on_provider_override(&app);
+            let value = app
+                .get(&name)
                 .into_diagnostic()
                 .wrap_err("Failed to get secret")?;
+            println!("{}", value.expose_secret());
+            Ok(())
+        }
+        // Trace how a secret resolves
+        Commands::Explain {
+            name,
+            provider,
+            profile,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            let trace = app
+                .explain(&name)
+                .into_diagnostic()
+                .wrap_err("Failed to explain secret")?;
+            println!("{}", trace.secret.bold());
+            for step in &trace.steps {
+                println!("  {:<10} {}", step.label.dimmed(), step.detail);
+            }
+            match &trace.source {
+                Some(source) => println!("  {:<10} {}", "source".green(), source),
+                None => println!("  {:<10} not set", "source".red()),
+            }
+            Ok(())
+        }
+        // Show which secrets are set and when they last changed
+        Commands::List {
+            provider,
+            profile,
+            group,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.set_groups(group);
+            app.list()
+                .into_diagnostic()
+                .wrap_err("Failed to list secrets")?;
             Ok(())
         }
         // Execute a command with secrets injected as environment variables
@@ -394,6 +1451,10 @@ pub fn main() -> Result<()> {
             command,
             provider,
             profile,
+            group,
+            clean_env,
+            harden,
+            redact_output,
         } => {
             let mut app = Secrets::load()
                 .into_diagnostic()
@@ -404,13 +1465,428 @@ pub fn main() -> Result<()> {
             if let Some(p) = profile {
                 app.set_profile(p);
             }
-            app.run(command)
+            app.set_groups(group);
+            app.set_clean_env(clean_env);
+            app.set_harden(harden);
+            app.set_redact_output(redact_output);
+            warn_on_provider_override(&app);
+            let status = app
+                .run(command)
                 .into_diagnostic()
                 .wrap_err("Failed to run command")?;
+            // Exiting skips the export in main
+            telemetry::finish(None);
+            std::process::exit(status.code().unwrap_or(1));
+        }
+        // Poll the provider and run the hook on changes
+        Commands::Watch {
+            provider,
+            profile,
+            group,
+            clean_env,
+            interval,
+            on_change,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.set_groups(group);
+            app.set_clean_env(clean_env);
+            app.watch(&on_change, Duration::from_secs(interval))
+                .into_diagnostic()
+                .wrap_err("Failed to watch secrets")?;
+            Ok(())
+        }
+        // Fill in missing secrets without touching existing ones
+        Commands::Ensure {
+            provider,
+            profile,
+            group,
+            from_file,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.set_groups(group);
+            app.ensure(from_file.as_deref())
+                .into_diagnostic()
+                .wrap_err("Failed to ensure secrets")?;
+            Ok(())
+        }
+        // Print secrets in the format of another tool
+        Commands::Export {
+            provider,
+            profile,
+            group,
+            format,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.set_groups(group);
+            let output = app
+                .export(format)
+                .into_diagnostic()
+                .wrap_err("Failed to export secrets")?;
+            print!("{}", output.as_str());
+            Ok(())
+        }
+        Commands::Env {
+            pid,
+            output,
+            provider,
+            profile,
+            group,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.set_groups(group);
+            app.env_for_pid(pid, output.as_deref())
+                .into_diagnostic()
+                .wrap_err("Failed to write the environment file")?;
+            Ok(())
+        }
+        // Compare a process environment with the spec, without printing values
+        Commands::EnvAudit {
+            pid,
+            provider,
+            profile,
+            group,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.set_groups(group);
+            let findings = app
+                .env_audit(pid)
+                .into_diagnostic()
+                .wrap_err("Failed to audit the environment")?;
+
+            let mut stale = 0;
+            for finding in &findings {
+                match finding {
+                    EnvFinding::Current(name) => println!("{} {}", "✓".green(), name),
+                    EnvFinding::Stale(name) => {
+                        stale += 1;
+                        println!(
+                            "{} {} {}",
+                            "✗".red(),
+                            name,
+                            "(differs from the resolved value)".dimmed()
+                        );
+                    }
+                    EnvFinding::Missing(name) => {
+                        println!("{} {} {}", "○".yellow(), name, "(not set)".dimmed())
+                    }
+                    EnvFinding::Extraneous {
+                        name,
+                        copy_of: Some(owner),
+                    } => println!(
+                        "{} {} {}",
+                        "!".yellow(),
+                        name,
+                        format!("(not declared; holds the value of {})", owner).dimmed()
+                    ),
+                    EnvFinding::Extraneous {
+                        name,
+                        copy_of: None,
+                    } => println!(
+                        "{} {} {}",
+                        "!".yellow(),
+                        name,
+                        "(not declared; looks like a secret)".dimmed()
+                    ),
+                }
+            }
+            if stale > 0 {
+                return Err(miette!(
+                    "{} secret(s) differ from the resolved values; restart the process to pick them up",
+                    stale
+                ));
+            }
+            Ok(())
+        }
+        // Print secrets as JSON for nix-shell and flakes
+        Commands::Nix {
+            provider,
+            profile,
+            group,
+            sandbox,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.set_groups(group);
+            let output = app
+                .nix(sandbox)
+                .into_diagnostic()
+                .wrap_err("Failed to resolve secrets for Nix")?;
+            print!("{}", output.as_str());
+            Ok(())
+        }
+        // Answer a query of Terraform's external data source
+        Commands::TerraformOutput => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).into_diagnostic()?;
+            let query = terraform::Query::parse(&input).into_diagnostic()?;
+
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = query.provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = query.profile {
+                app.set_profile(p);
+            }
+            app.set_groups(query.groups);
+            let output = app
+                .terraform_output(&query.secrets)
+                .into_diagnostic()
+                .wrap_err("Failed to read secrets for Terraform")?;
+            println!("{}", output.as_str());
+            Ok(())
+        }
+        // Answer Cargo's credential requests on stdin and stdout
+        Commands::CargoCredential {
+            provider,
+            profile,
+            cargo_plugin: _,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.cargo_credential(io::stdin().lock(), io::stdout().lock())
+                .into_diagnostic()?;
+            Ok(())
+        }
+        // Answer Docker's credential helper requests
+        Commands::DockerCredential { action, provider } => docker_credential(&action, provider),
+        // Print or write an .npmrc with registry tokens
+        Commands::Npmrc {
+            registries,
+            output,
+            provider,
+            profile,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            let npmrc = app.npmrc(&registries).into_diagnostic()?;
+            match output {
+                Some(path) => {
+                    systemd::write_env_file(&path, &npmrc).into_diagnostic()?;
+                    println!("{} Wrote {}", "✓".green(), path.display());
+                }
+                None => print!("{}", npmrc.as_str()),
+            }
+            Ok(())
+        }
+        // Write database logins to ~/.pgpass
+        Commands::Pgpass {
+            secrets,
+            output,
+            provider,
+            profile,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.pgpass(&secrets, output.as_deref()).into_diagnostic()?;
+            Ok(())
+        }
+        // Write database logins to ~/.my.cnf
+        Commands::Mycnf {
+            secrets,
+            output,
+            group,
+            provider,
+            profile,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.mycnf(&secrets, output.as_deref(), group.as_deref())
+                .into_diagnostic()?;
+            Ok(())
+        }
+        // Seal an age identity to the TPM
+        Commands::TpmSeal { identity, output } => {
+            let content = zeroize::Zeroizing::new(
+                fs::read_to_string(&identity)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Failed to read {}", identity.display()))?,
+            );
+            tpm::seal(&content, &output, &SystemRunner).into_diagnostic()?;
+            println!(
+                "{} Sealed {} to the TPM in {}",
+                "✓".green(),
+                identity.display(),
+                output.display()
+            );
+            println!(
+                "\nDecrypt with ?identity=tpm:{} in the provider URI. Keep a backup of {} elsewhere before deleting it: the sealed copy only works on this machine.",
+                output.display(),
+                identity.display()
+            );
             Ok(())
         }
+        // Print secrets or hooks for devenv.sh and dev containers
+        Commands::Devenv { action } => match action {
+            DevenvAction::Export {
+                provider,
+                profile,
+                group,
+                format,
+            } => {
+                let mut app = Secrets::load()
+                    .into_diagnostic()
+                    .wrap_err("Failed to load secretspec configuration")?;
+                if let Some(p) = provider {
+                    app.set_provider(p);
+                }
+                if let Some(p) = profile {
+                    app.set_profile(p);
+                }
+                app.set_groups(group);
+                let output = app
+                    .export(format)
+                    .into_diagnostic()
+                    .wrap_err("Failed to export secrets")?;
+                print!("{}", output.as_str());
+                Ok(())
+            }
+            DevenvAction::Hook { target } => {
+                print!("{}", devenv::hook(target));
+                Ok(())
+            }
+        },
+        #[cfg(unix)]
+        Commands::Agent { action } => {
+            let socket_of = |socket: Option<PathBuf>| {
+                socket
+                    .or_else(|| std::env::var_os(agent::SOCKET_ENV).map(PathBuf::from))
+                    .unwrap_or_else(agent::default_socket)
+            };
+            match action {
+                AgentAction::Start {
+                    socket,
+                    idle_timeout,
+                    foreground,
+                    biometric,
+                } => {
+                    let socket = socket.unwrap_or_else(agent::default_socket);
+                    let idle_timeout = Duration::from_secs(idle_timeout);
+                    if foreground {
+                        eprintln!(
+                            "Starting the agent on {}; point secretspec at it with {}={}",
+                            socket.display(),
+                            agent::SOCKET_ENV,
+                            socket.display()
+                        );
+                        agent::serve(&socket, idle_timeout, biometric)
+                            .into_diagnostic()
+                            .wrap_err("Agent failed")?;
+                    } else {
+                        let pid = agent::start(&socket, idle_timeout, biometric)
+                            .into_diagnostic()
+                            .wrap_err("Failed to start the agent")?;
+                        println!(
+                            "{}='{}'; export {};",
+                            agent::SOCKET_ENV,
+                            socket.to_string_lossy().replace('\'', r"'\''"),
+                            agent::SOCKET_ENV
+                        );
+                        println!("echo Agent pid {};", pid);
+                    }
+                    Ok(())
+                }
+                AgentAction::Lock => {
+                    agent::lock(&socket_of(None))
+                        .into_diagnostic()
+                        .wrap_err("Failed to lock the agent")?;
+                    println!("{} Agent locked", "✓".green());
+                    Ok(())
+                }
+                AgentAction::Stop => {
+                    agent::stop(&socket_of(None))
+                        .into_diagnostic()
+                        .wrap_err("Failed to stop the agent")?;
+                    println!("{} Agent stopped", "✓".green());
+                    Ok(())
+                }
+            }
+        }
         // Verify all required secrets are available
-        Commands::Check { provider, profile } => {
+        Commands::Check {
+            provider,
+            profile,
+            frozen,
+            group,
+            all_profiles,
+            format,
+        } => {
             let mut app = Secrets::load()
                 .into_diagnostic()
                 .wrap_err("Failed to load secretspec configuration")?;
@@ -420,20 +1896,731 @@ pub fn main() -> Result<()> {
             if let Some(p) = profile {
                 app.set_profile(p);
             }
+            app.set_frozen(frozen);
+            app.set_groups(group);
+            if all_profiles {
+                let matrix = app.check_all_profiles();
+                match format {
+                    MatrixFormat::Table => print_matrix(&matrix),
+                    MatrixFormat::Csv => print!("{}", matrix.to_csv()),
+                    MatrixFormat::Json => print!("{}", matrix.to_json().into_diagnostic()?),
+                }
+                if !matrix.is_complete() {
+                    return Err(miette!("Some profiles are missing required secrets"));
+                }
+                return Ok(());
+            }
+            warn_on_provider_override(&app);
             app.check()
                 .into_diagnostic()
                 .wrap_err("Failed to check secrets")?;
             Ok(())
         }
         // Import secrets from one provider to another
-        Commands::Import { from_provider } => {
+        Commands::Import {
+            from_provider,
+            plan,
+            apply_plan,
+        } => {
             let app = Secrets::load()
                 .into_diagnostic()
                 .wrap_err("Failed to load secretspec configuration")?;
-            app.import(&from_provider)
+            if let Some(path) = apply_plan {
+                let plan = read_plan(&path, PlanOperation::Import)?;
+                return app
+                    .apply_plan(&plan)
+                    .into_diagnostic()
+                    .wrap_err("Failed to apply plan");
+            }
+            // Required by clap unless a plan is applied
+            let from_provider = from_provider.unwrap_or_default();
+            match plan {
+                Some(format) => print_plan(
+                    &app.plan_import(&from_provider)
+                        .into_diagnostic()
+                        .wrap_err("Failed to plan import")?,
+                    format,
+                ),
+                None => app
+                    .import(&from_provider)
+                    .into_diagnostic()
+                    .wrap_err("Failed to import secrets"),
+            }
+        }
+        // Re-encrypt secrets to the current recipients
+        Commands::Rekey {
+            provider,
+            profile,
+            plan,
+            apply_plan,
+        } => {
+            let mut app = Secrets::load()
                 .into_diagnostic()
-                .wrap_err("Failed to import secrets")?;
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            if let Some(path) = apply_plan {
+                let plan = read_plan(&path, PlanOperation::Rekey)?;
+                return app
+                    .apply_plan(&plan)
+                    .into_diagnostic()
+                    .wrap_err("Failed to apply plan");
+            }
+            match plan {
+                Some(format) => print_plan(
+                    &app.plan_rekey()
+                        .into_diagnostic()
+                        .wrap_err("Failed to plan rekey")?,
+                    format,
+                ),
+                None => app
+                    .rekey()
+                    .into_diagnostic()
+                    .wrap_err("Failed to rekey secrets"),
+            }
+        }
+        // Push resolved secrets to another provider
+        Commands::Sync {
+            to,
+            provider,
+            profile,
+            dry_run,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.sync(&to, dry_run)
+                .into_diagnostic()
+                .wrap_err("Failed to sync secrets")?;
+            Ok(())
+        }
+        // Replace a secret with a generated value
+        Commands::Rotate {
+            name,
+            provider,
+            profile,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.rotate(&name)
+                .into_diagnostic()
+                .wrap_err("Failed to rotate secret")?;
+            Ok(())
+        }
+        // Move a secret to a new name or profile
+        Commands::Mv {
+            old,
+            new,
+            provider,
+            profile,
+            to_profile,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.rename(&old, &new, to_profile.as_deref())
+                .into_diagnostic()
+                .wrap_err("Failed to move secret")?;
+            Ok(())
+        }
+        // Delete a secret, to the trash where the provider has one
+        Commands::Delete {
+            name,
+            provider,
+            profile,
+            permanent,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.delete(&name, permanent)
+                .into_diagnostic()
+                .wrap_err("Failed to delete secret")?;
+            Ok(())
+        }
+        // Bring a secret back from the trash
+        Commands::Restore {
+            name,
+            provider,
+            profile,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.restore(&name)
+                .into_diagnostic()
+                .wrap_err("Failed to restore secret")?;
+            Ok(())
+        }
+        // List the versions of a secret
+        Commands::History {
+            name,
+            provider,
+            profile,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            let versions = app
+                .history(&name)
+                .into_diagnostic()
+                .wrap_err("Failed to list versions")?;
+            if versions.is_empty() {
+                output::say(format!("No versions of '{}' are stored", name));
+            }
+            for version in versions {
+                println!("{}", version);
+            }
+            Ok(())
+        }
+        // Delete secrets the spec no longer declares
+        Commands::Prune {
+            provider,
+            profile,
+            dry_run,
+            yes,
+            plan,
+            apply_plan,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            if let Some(path) = apply_plan {
+                let plan = read_plan(&path, PlanOperation::Prune)?;
+                return app
+                    .apply_plan(&plan)
+                    .into_diagnostic()
+                    .wrap_err("Failed to apply plan");
+            }
+            match plan {
+                Some(format) => print_plan(
+                    &app.plan_prune()
+                        .into_diagnostic()
+                        .wrap_err("Failed to plan prune")?,
+                    format,
+                ),
+                None => app
+                    .prune(dry_run, yes)
+                    .into_diagnostic()
+                    .wrap_err("Failed to prune secrets"),
+            }
+        }
+        // Delete secrets past their ttl
+        Commands::Gc {
+            provider,
+            profile,
+            dry_run,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.gc(dry_run)
+                .into_diagnostic()
+                .wrap_err("Failed to delete expired secrets")?;
+            Ok(())
+        }
+        // Record the IDs of existing items
+        Commands::Adopt { provider, profile } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.adopt()
+                .into_diagnostic()
+                .wrap_err("Failed to adopt items")
+        }
+        Commands::Browse { uri, query } => {
+            let vaults = provider::browse(&uri, query.as_deref())
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed to browse '{}'", uri))?;
+            if vaults.is_empty() {
+                println!("No matching items");
+                return Ok(());
+            }
+            for vault in vaults {
+                println!("{}", vault.name.bold());
+                for item in vault.items {
+                    println!("  {}", item);
+                }
+            }
+            Ok(())
+        }
+        // List who can access secrets
+        Commands::Access { provider, profile } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.access()
+                .into_diagnostic()
+                .wrap_err("Failed to list access")?;
+            Ok(())
+        }
+        // Grant read access to secrets
+        Commands::Grant {
+            principal,
+            provider,
+            profile,
+            dry_run,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.grant(&principal, dry_run)
+                .into_diagnostic()
+                .wrap_err("Failed to grant access")?;
+            Ok(())
+        }
+        // Diagnose the environment
+        Commands::Doctor { provider, profile } => print_checks(&doctor::run(provider, profile)),
+        // Upgrade secretspec.toml to the newest schema
+        Commands::Migrate { dry_run } => {
+            let content = fs::read_to_string("secretspec.toml")
+                .into_diagnostic()
+                .wrap_err("Failed to read secretspec.toml")?;
+            let migrated = config::migrate(&content)
+                .into_diagnostic()
+                .wrap_err("Failed to migrate secretspec.toml")?;
+
+            match migrated {
+                None => println!(
+                    "secretspec.toml already uses schema {}",
+                    config::CURRENT_SCHEMA
+                ),
+                Some(migrated) if dry_run => print!("{}", migrated),
+                Some(migrated) => {
+                    fs::write("secretspec.toml", migrated).into_diagnostic()?;
+                    println!(
+                        "✓ Migrated secretspec.toml to schema {}",
+                        config::CURRENT_SCHEMA
+                    );
+                }
+            }
+            Ok(())
+        }
+        // Rewrite the spec in another format
+        Commands::Convert { to, dry_run } => {
+            let from_path = SpecFormat::find(Path::new(".")).into_diagnostic()?;
+            let from = SpecFormat::from_path(&from_path);
+            if from == to {
+                return Err(miette!("The spec is already {}", to.file_name()));
+            }
+
+            let content = fs::read_to_string(&from_path)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed to read {}", from.file_name()))?;
+            let config = Config::parse(&content, from)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed to parse {}", from.file_name()))?;
+            let converted = to
+                .serialize(&config)
+                .map_err(|e| miette!("Failed to write the spec as {}: {}", to.file_name(), e))?;
+
+            if dry_run {
+                print!("{}", converted);
+                return Ok(());
+            }
+            fs::write(to.file_name(), converted).into_diagnostic()?;
+            fs::remove_file(&from_path).into_diagnostic()?;
+            println!("✓ Converted {} to {}", from.file_name(), to.file_name());
+            Ok(())
+        }
+        // Print the JSON Schema of the current spec schema
+        Commands::Schema => {
+            let schema = serde_json::to_string_pretty(&schema::json_schema()).into_diagnostic()?;
+            println!("{}", schema);
+            Ok(())
+        }
+        // Benchmark providers with synthetic secrets
+        Commands::Bench {
+            provider,
+            keys,
+            size,
+            iterations,
+        } => {
+            let options = BenchOptions {
+                keys,
+                value_size: size,
+                iterations,
+            };
+
+            let mut reports = Vec::new();
+            for spec in &provider {
+                let bench = BenchProvider::open(spec)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Failed to open provider '{}'", spec))?;
+                eprintln!("Benchmarking {}...", spec.blue());
+                let report = bench
+                    .run(&options)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Benchmark of '{}' failed", spec))?;
+                reports.push((spec, report));
+            }
+
+            let ms = |latency: Duration| latency.as_secs_f64() * 1000.0;
+            let set_column = |set: &Option<Latencies>, quantile: f64| match set {
+                Some(set) => format!("{:.2} ms", ms(set.percentile(quantile))),
+                None => "read-only".to_string(),
+            };
+            println!(
+                "{:<28} {:>11} {:>11} {:>11} {:>11} {:>11}",
+                "Provider".bold(),
+                "Set p50".bold(),
+                "Set p95".bold(),
+                "Get p50".bold(),
+                "Get p95".bold(),
+                "Get ops/s".bold()
+            );
+            for (spec, report) in &reports {
+                println!(
+                    "{:<28} {:>11} {:>11} {:>8.2} ms {:>8.2} ms {:>11.1}",
+                    spec,
+                    set_column(&report.set, 0.5),
+                    set_column(&report.set, 0.95),
+                    ms(report.get.percentile(0.5)),
+                    ms(report.get.percentile(0.95)),
+                    report.get.throughput()
+                );
+            }
+            Ok(())
+        }
+        Commands::Provider {
+            action: ProviderAction::Explain { uri },
+        } => {
+            let explanation = provider::explain(&uri)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Invalid provider URI '{}'", uri))?;
+            println!("{} {}", "Provider:".bold(), explanation.provider);
+            println!("{} {}", "URI:".bold(), explanation.uri);
+            if let Some(recipients) = explanation.encryption {
+                println!(
+                    "{} client-side, to {} recipient{} from the URI",
+                    "Encryption:".bold(),
+                    recipients,
+                    if recipients == 1 { "" } else { "s" }
+                );
+            }
+            if explanation.env_override {
+                println!("{} secrets set in the environment win", "Overrides:".bold());
+            }
+            println!("{}\n{}", "Config:".bold(), explanation.config);
+            Ok(())
+        }
+        Commands::Profile {
+            action: ProfileAction::Show { profile },
+        } => {
+            let app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec.toml")?;
+            let project = &app.config().project.name;
+            let (active, source) = app.resolve_profile_with_source(profile.as_deref());
+            println!(
+                "{} {} (from {})",
+                "Profile:".bold(),
+                active.green(),
+                source.describe(project)
+            );
+            if !app.config().profiles.contains_key(&active) {
+                println!(
+                    "{} the spec doesn't define [profiles.{}], so only the default profile's secrets apply",
+                    "Note:".yellow(),
+                    active
+                );
+            }
+
+            println!();
+            for (candidate, name) in app.profile_candidates(profile.as_deref()) {
+                let label = format!("{:<40}", candidate.describe(project));
+                match name {
+                    Some(name) if candidate == source => {
+                        println!("  {} {}  (active)", label, name.green())
+                    }
+                    Some(name) => println!("  {} {}", label, name),
+                    None => println!("  {} {}", label, "not set".dimmed()),
+                }
+            }
+            Ok(())
+        }
+        // Run the provider conformance suite
+        Commands::TestProvider { uri } => {
+            let suite = ConformanceSuite::open(&uri)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed to open provider '{}'", uri))?;
+            eprintln!(
+                "Testing {} in project {}...\n",
+                suite.name().blue(),
+                suite.project()
+            );
+            print_checks(&suite.run())
+        }
+        // Show local usage statistics
+        Commands::Stats { reset } => {
+            let path = Stats::path()
+                .into_diagnostic()
+                .wrap_err("Failed to locate statistics file")?;
+            if reset {
+                match fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e).into_diagnostic(),
+                }
+                println!("{} Statistics reset", "✓".green());
+                return Ok(());
+            }
+
+            let stats = Stats::load(&path)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            if stats.providers.is_empty() {
+                println!("No lookups recorded yet");
+                return Ok(());
+            }
+
+            println!(
+                "{:<16} {:>8} {:>8} {:>8} {:>12}",
+                "Provider".bold(),
+                "Lookups".bold(),
+                "Found".bold(),
+                "Errors".bold(),
+                "Avg latency".bold()
+            );
+            for (name, counters) in &stats.providers {
+                println!(
+                    "{:<16} {:>8} {:>7.0}% {:>8} {:>9.1} ms",
+                    name,
+                    counters.lookups,
+                    counters.found_rate() * 100.0,
+                    counters.errors,
+                    counters.average_latency().as_secs_f64() * 1000.0
+                );
+            }
+            println!(
+                "\n{}",
+                format!(
+                    "Recorded locally in {}; set SECRETSPEC_NO_STATS=1 to disable",
+                    path.display()
+                )
+                .dimmed()
+            );
+            Ok(())
+        }
+        // Show the references between defaults
+        Commands::Graph { profile, dot } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            let profile = app.resolve_profile(None);
+            let graph = app
+                .dependency_graph(&profile)
+                .into_diagnostic()
+                .wrap_err("Failed to read secretspec configuration")?;
+            if dot {
+                // Loops are drawn too, to see where they are
+                print!("{}", graph.to_dot());
+                return Ok(());
+            }
+
+            graph.order().into_diagnostic()?;
+            let lines = graph.to_string();
+            if lines.is_empty() {
+                println!(
+                    "No secrets of profile '{}' derive their default from another",
+                    profile
+                );
+            } else {
+                print!("{}", lines);
+            }
+            Ok(())
+        }
+        // Report weak and reused values without printing them
+        Commands::Analyze {
+            provider,
+            profile,
+            group,
+            hibp,
+        } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            app.set_groups(group);
+            let findings = app
+                .analyze(hibp)
+                .into_diagnostic()
+                .wrap_err("Failed to analyze secrets")?;
+            if findings.is_empty() {
+                println!("{} No weak or reused values found", "✓".green());
+                return Ok(());
+            }
+
+            for finding in &findings {
+                match finding {
+                    Finding::Weak(location) => {
+                        println!("{} {}: placeholder or common password", "✗".red(), location)
+                    }
+                    Finding::Short { location, length } => {
+                        println!("{} {}: only {} characters", "!".yellow(), location, length)
+                    }
+                    Finding::LowEntropy { location, bits } => println!(
+                        "{} {}: about {:.0} bits of entropy",
+                        "!".yellow(),
+                        location,
+                        bits
+                    ),
+                    Finding::Breached {
+                        location,
+                        occurrences,
+                    } => println!(
+                        "{} {}: seen {} times in data breaches",
+                        "✗".red(),
+                        location,
+                        occurrences
+                    ),
+                    Finding::Reused(locations) => println!(
+                        "{} Same value in {}",
+                        "✗".red(),
+                        locations
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                }
+            }
+            let severe = findings.iter().filter(|f| f.is_severe()).count();
+            if severe > 0 {
+                return Err(miette!(
+                    "{} weak, reused or breached value(s); rotate them with 'secretspec rotate'",
+                    severe
+                ));
+            }
+            Ok(())
+        }
+        // Show what the project stores in its providers
+        Commands::Usage { provider, profile } => {
+            let mut app = Secrets::load()
+                .into_diagnostic()
+                .wrap_err("Failed to load secretspec configuration")?;
+            if let Some(p) = provider {
+                app.set_provider(p);
+            }
+            if let Some(p) = profile {
+                app.set_profile(p);
+            }
+            let usage = app
+                .usage()
+                .into_diagnostic()
+                .wrap_err("Failed to measure stored secrets")?;
+            if usage.is_empty() {
+                println!("No profile has a provider configured");
+                return Ok(());
+            }
+
+            println!(
+                "{:<16} {:<16} {:>8} {:>12}",
+                "Provider".bold(),
+                "Profile".bold(),
+                "Items".bold(),
+                "Storage".bold()
+            );
+            for profile in &usage {
+                let (items, storage) = match (profile.items, profile.bytes) {
+                    (Some(items), Some(bytes)) => (items.to_string(), usage::format_size(bytes)),
+                    _ => ("-".to_string(), "-".to_string()),
+                };
+                println!(
+                    "{:<16} {:<16} {:>8} {:>12}",
+                    profile.provider, profile.profile, items, storage
+                );
+            }
+            if usage.iter().any(|profile| profile.items.is_none()) {
+                println!(
+                    "\n{}",
+                    "- marks providers that can't list their secrets".dimmed()
+                );
+            }
             Ok(())
         }
+        // Replace the running binary with the newest release
+        Commands::SelfUpdate { channel, check } => release::self_update(channel, check),
+        // Check the running binary against the published signature
+        Commands::VerifyInstall => release::verify_install(),
     }
 }
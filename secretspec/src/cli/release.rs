@@ -0,0 +1,561 @@
+//! Release discovery, signature verification, self-update and install verification
+//!
+//! Releases are published on GitHub by `dist`. Every archive is accompanied by
+//! a minisign signature (`<archive>.minisig`) made with the project's release
+//! key, whose public half is compiled into official builds through the
+//! `SECRETSPEC_RELEASE_PUBLIC_KEY` environment variable. The signed trusted
+//! comment names the release tag and file, and must match the file asked
+//! for. Builds without a key refuse to self-update rather than install
+//! unverified binaries. The unified
+//! `sha256.sum` checksum file is signed the same way, which lets
+//! `verify-install` check the running binary against the published release.
+//!
+//! Downloads and archive extraction use the system `curl` and `tar`, which
+//! ship with macOS, Windows 10+ and practically every Linux distribution.
+
+use crate::SecretSpecError;
+use crate::provider::command::{self, Invocation, SystemRunner};
+use clap::ValueEnum;
+use miette::{IntoDiagnostic, Result, WrapErr, miette};
+use minisign_verify::{PublicKey, Signature};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// GitHub API endpoint listing the project's releases, newest first.
+const RELEASES_URL: &str = "https://api.github.com/repos/cachix/secretspec/releases";
+
+/// Unified checksum file published with every release.
+const CHECKSUMS_FILE: &str = "sha256.sum";
+
+/// Minisign public key used to verify release artifacts, set at build time.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("SECRETSPEC_RELEASE_PUBLIC_KEY");
+
+/// Which releases to consider when updating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Channel {
+    /// Only full releases
+    Stable,
+    /// Full releases and prereleases
+    Prerelease,
+}
+
+/// A GitHub release as returned by the releases API.
+#[derive(Debug, Deserialize)]
+struct Release {
+    /// The git tag, e.g. `v0.3.0`
+    tag_name: String,
+    /// Whether the release is marked as a prerelease
+    #[serde(default)]
+    prerelease: bool,
+    /// Whether the release is an unpublished draft
+    #[serde(default)]
+    draft: bool,
+    /// Files attached to the release
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+/// A file attached to a GitHub release.
+#[derive(Debug, Deserialize)]
+struct Asset {
+    /// File name, e.g. `secretspec-x86_64-unknown-linux-gnu.tar.xz`
+    name: String,
+    /// Direct download URL
+    browser_download_url: String,
+}
+
+impl Release {
+    /// Returns the release version without a leading `v`.
+    fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    /// Finds an asset by file name.
+    fn asset(&self, name: &str) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| miette!("Release {} has no asset named '{}'", self.tag_name, name))
+    }
+}
+
+/// Updates the running binary to the newest release on the given channel.
+///
+/// The archive for the current platform is downloaded, its minisign signature
+/// verified, and the binary swapped into place with a rename so that an
+/// interrupted update never leaves a partially written executable.
+///
+/// # Arguments
+///
+/// * `channel` - Whether to consider prereleases
+/// * `check_only` - Only report whether an update is available
+pub fn self_update(channel: Channel, check_only: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let releases = fetch_releases()?;
+
+    let Some(release) = latest_release(&releases, channel) else {
+        println!("No {:?} releases found.", channel);
+        return Ok(());
+    };
+
+    if !is_newer(release.version(), current) {
+        println!("secretspec {} is up to date.", current);
+        return Ok(());
+    }
+
+    if check_only {
+        println!(
+            "secretspec {} is available (current: {}). Run 'secretspec self-update' to install it.",
+            release.version(),
+            current
+        );
+        return Ok(());
+    }
+
+    let public_key = release_public_key()?;
+    let archive_name = archive_name()?;
+    let archive = download(&release.asset(&archive_name)?.browser_download_url)?;
+    let signature = download(
+        &release
+            .asset(&format!("{}.minisig", archive_name))?
+            .browser_download_url,
+    )?;
+    verify_signature(
+        &public_key,
+        &archive,
+        &signature,
+        &release.tag_name,
+        &archive_name,
+    )
+    .wrap_err_with(|| format!("Refusing to install {}", archive_name))?;
+
+    let current_exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .into_diagnostic()?;
+    let staging = tempfile::tempdir().into_diagnostic()?;
+    let archive_path = staging.path().join(&archive_name);
+    fs::write(&archive_path, &archive).into_diagnostic()?;
+    let new_binary = extract_binary(&archive_path, staging.path())?;
+    replace_binary(&new_binary, &current_exe)?;
+
+    println!("✓ Updated secretspec {} -> {}", current, release.version());
+    Ok(())
+}
+
+/// Verifies that the running binary is an unmodified official release.
+///
+/// The signed `sha256.sum` of the release matching this version is checked
+/// first; the platform archive is then verified against it, and the binary
+/// inside compared with the running executable.
+pub fn verify_install() -> Result<()> {
+    let version = env!("CARGO_PKG_VERSION");
+    let public_key = release_public_key()?;
+    let releases = fetch_releases()?;
+    let release = releases
+        .iter()
+        .find(|r| r.version() == version)
+        .ok_or_else(|| miette!("No published release found for secretspec {}", version))?;
+
+    let checksums = download(&release.asset(CHECKSUMS_FILE)?.browser_download_url)?;
+    let signature = download(
+        &release
+            .asset(&format!("{}.minisig", CHECKSUMS_FILE))?
+            .browser_download_url,
+    )?;
+    verify_signature(
+        &public_key,
+        &checksums,
+        &signature,
+        &release.tag_name,
+        CHECKSUMS_FILE,
+    )
+    .wrap_err_with(|| {
+        format!(
+            "{} of release {} is not authentic",
+            CHECKSUMS_FILE, release.tag_name
+        )
+    })?;
+
+    let archive_name = archive_name()?;
+    let expected = String::from_utf8_lossy(&checksums)
+        .lines()
+        .find_map(|line| parse_checksum_line(line, &archive_name))
+        .ok_or_else(|| miette!("{} has no checksum for {}", CHECKSUMS_FILE, archive_name))?;
+    let archive = download(&release.asset(&archive_name)?.browser_download_url)?;
+    if sha256_hex(&archive) != expected {
+        return Err(miette!(
+            "{} does not match its signed checksum",
+            archive_name
+        ));
+    }
+
+    let staging = tempfile::tempdir().into_diagnostic()?;
+    let archive_path = staging.path().join(&archive_name);
+    fs::write(&archive_path, &archive).into_diagnostic()?;
+    let released = fs::read(extract_binary(&archive_path, staging.path())?).into_diagnostic()?;
+    let current_exe = env::current_exe().into_diagnostic()?;
+    let installed = fs::read(&current_exe)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to read {}", current_exe.display()))?;
+
+    if sha256_hex(&installed) != sha256_hex(&released) {
+        return Err(miette!(
+            "{} does not match the signed secretspec {} release for {}",
+            current_exe.display(),
+            version,
+            target()?
+        ));
+    }
+
+    println!(
+        "✓ {} matches the signed secretspec {} release ({})",
+        current_exe.display(),
+        version,
+        archive_name
+    );
+    Ok(())
+}
+
+/// Fetches the list of published releases, newest first.
+fn fetch_releases() -> Result<Vec<Release>> {
+    serde_json::from_slice(&download(RELEASES_URL)?)
+        .into_diagnostic()
+        .wrap_err("Failed to parse the GitHub releases response")
+}
+
+/// Returns the checksum from a `sha256sum`-style line if it is for `file`.
+///
+/// Lines look like `<hex>  <file>`, with `*` marking binary mode.
+fn parse_checksum_line(line: &str, file: &str) -> Option<String> {
+    let (checksum, name) = line.split_once(char::is_whitespace)?;
+    let name = name.trim_start().trim_start_matches('*');
+    (name == file).then(|| checksum.to_ascii_lowercase())
+}
+
+/// Returns the hex-encoded SHA-256 digest of `data`.
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Returns the compiled-in release key.
+///
+/// # Errors
+///
+/// Returns an error for builds made without `SECRETSPEC_RELEASE_PUBLIC_KEY`
+fn release_public_key() -> Result<PublicKey> {
+    let key = RELEASE_PUBLIC_KEY
+        .filter(|key| !key.trim().is_empty())
+        .ok_or_else(|| {
+        miette!(
+            "This build of secretspec has no release signing key, so downloads cannot be verified.\n\nInstall updates with the package manager or installer you used originally."
+        )
+    })?;
+    PublicKey::from_base64(key.trim()).map_err(|e| miette!("Invalid release public key: {}", e))
+}
+
+/// Verifies a minisign signature over `data`, which must be the file `file`
+/// of the release tagged `tag`.
+///
+/// The signature covers its trusted comment, which names the release and the
+/// file. Checking it keeps a validly signed file from being passed off as
+/// another, like an older release's archive served for a newer one.
+fn verify_signature(
+    public_key: &PublicKey,
+    data: &[u8],
+    signature: &[u8],
+    tag: &str,
+    file: &str,
+) -> Result<()> {
+    let signature = std::str::from_utf8(signature)
+        .map_err(|_| miette!("Signature file is not valid UTF-8"))
+        .and_then(|s| Signature::decode(s).map_err(|e| miette!("Invalid signature: {}", e)))?;
+    public_key
+        .verify(data, &signature, false)
+        .map_err(|e| miette!("Signature verification failed: {}", e))?;
+    check_trusted_comment(signature.trusted_comment(), tag, file)
+}
+
+/// Checks that a verified trusted comment is the one `sign-release.yml`
+/// writes for `file` of the release tagged `tag`: `secretspec <tag> <file>`.
+fn check_trusted_comment(comment: &str, tag: &str, file: &str) -> Result<()> {
+    let expected = format!("secretspec {} {}", tag, file);
+    if comment.trim() != expected {
+        return Err(miette!(
+            "Signature is for '{}', expected '{}'",
+            comment.trim(),
+            expected
+        ));
+    }
+    Ok(())
+}
+
+/// Picks the newest published release on the channel.
+///
+/// The API lists releases newest first, so the first match wins.
+fn latest_release(releases: &[Release], channel: Channel) -> Option<&Release> {
+    releases
+        .iter()
+        .filter(|r| !r.draft)
+        .find(|r| channel == Channel::Prerelease || !r.prerelease)
+}
+
+/// Returns whether `candidate` is a newer version than `current`.
+///
+/// Versions are compared by their numeric `major.minor.patch` components; a
+/// release is newer than a prerelease with the same numbers.
+fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parse(version: &str) -> (Vec<u64>, Option<&str>) {
+        let (numbers, pre) = match version.split_once('-') {
+            Some((numbers, pre)) => (numbers, Some(pre)),
+            None => (version, None),
+        };
+        let numbers = numbers.split('.').map(|n| n.parse().unwrap_or(0)).collect();
+        (numbers, pre)
+    }
+
+    let (candidate_numbers, candidate_pre) = parse(candidate);
+    let (current_numbers, current_pre) = parse(current);
+    match candidate_numbers.cmp(&current_numbers) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => match (candidate_pre, current_pre) {
+            (None, Some(_)) => true,
+            (Some(candidate), Some(current)) => candidate > current,
+            _ => false,
+        },
+    }
+}
+
+/// Returns the target triple of the release built for this platform.
+fn target() -> Result<&'static str> {
+    match (env::consts::ARCH, env::consts::OS) {
+        ("aarch64", "macos") => Ok("aarch64-apple-darwin"),
+        ("x86_64", "macos") => Ok("x86_64-apple-darwin"),
+        ("x86_64", "linux") => Ok("x86_64-unknown-linux-gnu"),
+        ("x86_64", "windows") => Ok("x86_64-pc-windows-msvc"),
+        (arch, os) => Err(miette!(
+            "No prebuilt secretspec releases exist for {}-{}",
+            arch,
+            os
+        )),
+    }
+}
+
+/// Returns the file name of the release archive for this platform.
+fn archive_name() -> Result<String> {
+    let target = target()?;
+    let extension = if cfg!(windows) { "zip" } else { "tar.xz" };
+    Ok(format!("secretspec-{}.{}", target, extension))
+}
+
+/// Downloads a URL into memory with `curl`.
+///
+/// curl writes to a temporary file, since the download may be binary.
+fn download(url: &str) -> Result<Vec<u8>> {
+    let dir = tempfile::tempdir().into_diagnostic()?;
+    let path = dir.path().join("download");
+    let invocation = Invocation::new("curl").args([
+        "--proto",
+        "=https",
+        "--tlsv1.2",
+        "-fsSL",
+        "-H",
+        "User-Agent: secretspec",
+        "--output",
+        &path.to_string_lossy(),
+        url,
+    ]);
+    command::curl(
+        &SystemRunner,
+        &invocation,
+        "update secretspec",
+        &format!("Failed to download {}", url),
+    )
+    .map_err(|e| match e {
+        SecretSpecError::ProviderOperationFailed(message) => miette!("{}", message),
+        e => miette!("{}", e),
+    })?;
+    fs::read(&path).into_diagnostic()
+}
+
+/// Extracts the archive and returns the path of the secretspec binary inside.
+fn extract_binary(archive: &Path, dir: &Path) -> Result<PathBuf> {
+    let status = Command::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(dir)
+        .status()
+        .into_diagnostic()
+        .wrap_err("Failed to run tar; is it installed?")?;
+    if !status.success() {
+        return Err(miette!("Failed to extract {}", archive.display()));
+    }
+
+    let binary_name = format!("secretspec{}", env::consts::EXE_SUFFIX);
+    find_file(dir, &binary_name)
+        .ok_or_else(|| miette!("{} not found in {}", binary_name, archive.display()))
+}
+
+/// Recursively searches `dir` for a file named `name`.
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name) {
+                return Some(found);
+            }
+        } else if path.file_name().is_some_and(|n| n == name) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Atomically replaces `target` with `new_binary`.
+///
+/// The new binary is first copied next to the target so that the final
+/// rename never crosses filesystems. Windows cannot overwrite a running
+/// executable, so the old binary is moved aside first.
+fn replace_binary(new_binary: &Path, target: &Path) -> Result<()> {
+    let dir = target
+        .parent()
+        .ok_or_else(|| miette!("Cannot determine the directory of {}", target.display()))?;
+    let staged = dir.join(".secretspec.new");
+    fs::copy(new_binary, &staged)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to write to {}", dir.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755)).into_diagnostic()?;
+    }
+
+    #[cfg(windows)]
+    {
+        let old = dir.join(".secretspec.old");
+        let _ = fs::remove_file(&old);
+        fs::rename(target, &old).into_diagnostic()?;
+    }
+
+    fs::rename(&staged, target)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to replace {}", target.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool, draft: bool) -> Release {
+        Release {
+            tag_name: tag.to_string(),
+            prerelease,
+            draft,
+            assets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.3.0", "0.2.0"));
+        assert!(is_newer("0.10.0", "0.9.1"));
+        assert!(is_newer("0.3.0", "0.3.0-prerelease.1"));
+        assert!(is_newer("0.3.0-prerelease.2", "0.3.0-prerelease.1"));
+        assert!(!is_newer("0.2.0", "0.2.0"));
+        assert!(!is_newer("0.2.0", "0.3.0"));
+        assert!(!is_newer("0.3.0-prerelease.1", "0.3.0"));
+    }
+
+    #[test]
+    fn test_latest_release_respects_channel() {
+        let releases = vec![
+            release("v0.4.0", false, true),
+            release("v0.4.0-prerelease.1", true, false),
+            release("v0.3.0", false, false),
+        ];
+        assert_eq!(
+            latest_release(&releases, Channel::Stable)
+                .unwrap()
+                .version(),
+            "0.3.0"
+        );
+        assert_eq!(
+            latest_release(&releases, Channel::Prerelease)
+                .unwrap()
+                .version(),
+            "0.4.0-prerelease.1"
+        );
+    }
+
+    #[test]
+    fn test_parse_checksum_line() {
+        let archive = "secretspec-x86_64-unknown-linux-gnu.tar.xz";
+        assert_eq!(
+            parse_checksum_line(&format!("ABC123  {}", archive), archive),
+            Some("abc123".to_string())
+        );
+        assert_eq!(
+            parse_checksum_line(&format!("abc123 *{}", archive), archive),
+            Some("abc123".to_string())
+        );
+        assert_eq!(
+            parse_checksum_line("abc123  secretspec-x86_64-apple-darwin.tar.xz", archive),
+            None
+        );
+        assert_eq!(parse_checksum_line("", archive), None);
+    }
+
+    #[test]
+    fn test_check_trusted_comment() {
+        let archive = "secretspec-x86_64-unknown-linux-gnu.tar.xz";
+        assert!(
+            check_trusted_comment(
+                "secretspec v0.3.0 secretspec-x86_64-unknown-linux-gnu.tar.xz",
+                "v0.3.0",
+                archive
+            )
+            .is_ok()
+        );
+        // An older release's archive, and another file of the same release
+        assert!(
+            check_trusted_comment(
+                "secretspec v0.2.0 secretspec-x86_64-unknown-linux-gnu.tar.xz",
+                "v0.3.0",
+                archive
+            )
+            .is_err()
+        );
+        assert!(check_trusted_comment("secretspec v0.3.0 sha256.sum", "v0.3.0", archive).is_err());
+        assert!(check_trusted_comment("timestamp:1700000000", "v0.3.0", archive).is_err());
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_find_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("secretspec-x86_64-unknown-linux-gnu");
+        fs::create_dir(&nested).unwrap();
+        fs::write(nested.join("secretspec"), "binary").unwrap();
+        assert_eq!(
+            find_file(dir.path(), "secretspec"),
+            Some(nested.join("secretspec"))
+        );
+        assert!(find_file(dir.path(), "missing").is_none());
+    }
+}
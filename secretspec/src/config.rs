@@ -6,7 +6,8 @@
 //! SecretSpec uses a declarative TOML-based configuration format to define secrets
 //! and their requirements across different environments (profiles). The type system
 //! supports configuration inheritance, allowing projects to extend shared configurations
-//! while maintaining type safety and preventing circular dependencies.
+//! while maintaining type safety and preventing circular dependencies. Specs can also be
+//! written in JSON or YAML, see [`SpecFormat`].
 //!
 //! ## Key Features
 //!
@@ -22,7 +23,7 @@
 //! ```toml
 //! [project]
 //! name = "my-app"
-//! revision = "1.0"
+//! schema = 3
 //! extends = ["../shared/common"]  # Optional inheritance
 //!
 //! [profiles.default]
@@ -32,13 +33,36 @@
 //! [profiles.production]
 //! DATABASE_URL = { description = "Production database", required = true }
 //! ```
+//!
+//! ## Schema Versions
+//!
+//! Specs declare their format with `schema` in the `[project]` table. Older
+//! specs used `revision = "1.0"` instead; these are schema 1 and are upgraded
+//! to schema 2 in memory when loaded. `secretspec migrate` rewrites them on
+//! disk.
+//!
+//! Schema 3 has the same format as schema 2 but is parsed strictly: keys the
+//! spec format doesn't know, and query parameters the provider doesn't
+//! understand, are errors instead of being ignored, so typos don't go
+//! unnoticed.
 
+use crate::transform::Transform;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// The newest spec schema version understood by this release.
+pub const CURRENT_SCHEMA: u32 = 3;
+
+/// The first schema whose specs are parsed strictly.
+const STRICT_SCHEMA: u32 = 3;
+
+/// The `revision` value used by schema 1 specs, which predate `schema`.
+const SCHEMA_1_REVISION: &str = "1.0";
 
 /// The root configuration structure for a SecretSpec project.
 ///
@@ -49,7 +73,32 @@ pub struct Config {
     /// Project metadata including name, revision, and optional inheritance
     pub project: Project,
     /// Map of profile names to their configurations (e.g., "default", "production", "staging")
+    #[serde(serialize_with = "sorted")]
     pub profiles: HashMap<String, Profile>,
+    /// Public keys that `enc+` and `gitvault` providers encrypt values to,
+    /// keyed by profile name and then by team member
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted"
+    )]
+    pub recipients: HashMap<String, BTreeMap<String, String>>,
+    /// AWS roles assumed to derive short-lived credentials, keyed by profile name
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted"
+    )]
+    pub assume_role: HashMap<String, AssumeRole>,
+    /// Which variables of its own environment `run` passes on to the command
+    #[serde(default, skip_serializing_if = "RunConfig::is_default")]
+    pub run: RunConfig,
+    /// Where changes to secrets are announced
+    #[serde(default, skip_serializing_if = "NotifyConfig::is_default")]
+    pub notify: NotifyConfig,
+    /// Exceptions to the convention that secret names are uppercase
+    #[serde(default, skip_serializing_if = "KeysConfig::is_default")]
+    pub keys: KeysConfig,
 }
 
 impl Config {
@@ -84,9 +133,110 @@ impl Config {
             })?;
         }
 
+        for (profile_name, members) in &self.recipients {
+            for (member, key) in members {
+                if !is_valid_recipient(key) {
+                    return Err(ParseError::Validation(format!(
+                        "Recipient '{}' of profile '{}' must be an age (age1...) or SSH (ssh-ed25519, ssh-rsa) public key",
+                        member, profile_name
+                    )));
+                }
+            }
+        }
+
+        for (profile_name, role) in &self.assume_role {
+            role.validate().map_err(|e| {
+                ParseError::Validation(format!("assume_role.{}: {}", profile_name, e))
+            })?;
+        }
+
+        self.run
+            .validate()
+            .map_err(|e| ParseError::Validation(format!("run: {}", e)))?;
+        self.notify
+            .validate()
+            .map_err(|e| ParseError::Validation(format!("notify: {}", e)))?;
+        self.keys
+            .validate()
+            .map_err(|e| ParseError::Validation(format!("keys: {}", e)))?;
+
         Ok(())
     }
 
+    /// Returns the ways secret names break the naming policy.
+    ///
+    /// Names are expected in uppercase environment style, like `API_KEY`,
+    /// unless `[keys]` lists them as exceptions. Names differing only by case
+    /// are reported too, since providers that match names case-insensitively
+    /// can't tell them apart. Strict specs reject these problems; others
+    /// only warn about them.
+    pub fn key_problems(&self) -> Vec<String> {
+        let mut names: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+        for profile in self.profiles.values() {
+            for (name, secret) in &profile.secrets {
+                let mut declared = secret.fields.keys().map(String::as_str).collect::<Vec<_>>();
+                declared.push(name);
+                for name in declared {
+                    names
+                        .entry(name.to_ascii_uppercase())
+                        .or_default()
+                        .insert(name);
+                }
+            }
+        }
+
+        let mut problems = Vec::new();
+        for (normalized, spellings) in &names {
+            if spellings.len() > 1 {
+                let spellings: Vec<String> =
+                    spellings.iter().map(|name| format!("'{}'", name)).collect();
+                problems.push(format!(
+                    "Secrets {} differ only by case; rename them to {}",
+                    spellings.join(" and "),
+                    normalized
+                ));
+            } else if let Some(name) = spellings.iter().find(|name| !self.keys.allows(name)) {
+                problems.push(format!(
+                    "Secret '{}' is not uppercase; rename it to {} or list it in [keys] exceptions",
+                    name, normalized
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Get the recipients values of a profile are encrypted to.
+    ///
+    /// Profiles without their own recipients use those of the `default` profile.
+    pub fn recipients_for(&self, profile: &str) -> Option<&BTreeMap<String, String>> {
+        self.recipients
+            .get(profile)
+            .or_else(|| self.recipients.get("default"))
+    }
+
+    /// Get the AWS role assumed for a profile.
+    ///
+    /// Profiles without their own role use that of the `default` profile.
+    pub fn assume_role_for(&self, profile: &str) -> Option<&AssumeRole> {
+        self.assume_role
+            .get(profile)
+            .or_else(|| self.assume_role.get("default"))
+    }
+
+    /// Get the provider a profile's secrets are stored in.
+    ///
+    /// Profiles without their own provider use that of the `default` profile.
+    pub fn provider_for(&self, profile: &str) -> Option<&str> {
+        self.profiles
+            .get(profile)
+            .and_then(|p| p.provider.as_deref())
+            .or_else(|| {
+                self.profiles
+                    .get("default")
+                    .and_then(|p| p.provider.as_deref())
+            })
+    }
+
     /// Get a profile by name.
     pub fn get_profile(&self, name: &str) -> Option<&Profile> {
         self.profiles.get(name)
@@ -113,6 +263,23 @@ impl Config {
                 }
             }
         }
+
+        // Recipients are inherited per profile
+        for (profile_name, members) in other.recipients {
+            self.recipients.entry(profile_name).or_insert(members);
+        }
+
+        for (profile_name, role) in other.assume_role {
+            self.assume_role.entry(profile_name).or_insert(role);
+        }
+
+        if self.run.is_default() {
+            self.run = other.run;
+        }
+        if self.notify.is_default() {
+            self.notify = other.notify;
+        }
+        self.keys.exceptions.extend(other.keys.exceptions);
     }
 
     // Internal methods
@@ -138,20 +305,20 @@ impl Config {
         }
 
         let content = fs::read_to_string(path)?;
-        Self::from_str_with_visited(&content, Some(path), visited)
+        Self::from_str_with_visited(&content, SpecFormat::from_path(path), Some(path), visited)
     }
 
     fn from_str_with_visited(
         content: &str,
+        format: SpecFormat,
         base_path: Option<&Path>,
         visited: &mut HashSet<PathBuf>,
     ) -> Result<Self, ParseError> {
-        let mut config: Config = toml::from_str(content)?;
-
-        // Validate revision
-        if config.project.revision != "1.0" {
-            return Err(ParseError::UnsupportedRevision(config.project.revision));
+        let mut value = format.parse(content)?;
+        if upgrade_schema(&mut value)? >= STRICT_SCHEMA {
+            check_unknown_keys(&value)?;
         }
+        let mut config: Config = value.try_into()?;
 
         // Process extends if present
         if let Some(extends_paths) = config.project.extends.clone() {
@@ -171,7 +338,7 @@ impl Config {
         visited: &mut HashSet<PathBuf>,
     ) -> Result<Config, ParseError> {
         for extend_path in extends_paths {
-            let full_path = base_dir.join(extend_path).join("secretspec.toml");
+            let full_path = SpecFormat::find(&base_dir.join(extend_path))?;
 
             if !full_path.exists() {
                 return Err(ParseError::Io(io::Error::new(
@@ -196,8 +363,24 @@ impl FromStr for Config {
     /// Note: Configuration inheritance (`extends`) is not supported when parsing
     /// from a string since there's no base path to resolve relative paths.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, SpecFormat::Toml)
+    }
+}
+
+impl Config {
+    /// Parse configuration from a string in the given format.
+    ///
+    /// Like [`FromStr`], this doesn't resolve `extends`.
+    pub fn parse(content: &str, format: SpecFormat) -> Result<Self, ParseError> {
         let mut visited = HashSet::new();
-        Self::from_str_with_visited(s, None, &mut visited)
+        Self::from_str_with_visited(content, format, None, &mut visited)
+    }
+
+    /// Returns whether the spec is parsed strictly, which also makes
+    /// commands reject provider URI parameters the provider doesn't
+    /// understand.
+    pub fn is_strict(&self) -> bool {
+        self.project.schema >= STRICT_SCHEMA
     }
 }
 
@@ -207,10 +390,346 @@ impl TryFrom<&Path> for Config {
     /// Load configuration from a file path.
     ///
     /// This supports configuration inheritance via `extends` and circular dependency detection.
+    /// A missing project name is detected from the git remote, a package
+    /// manifest or the directory name.
     fn try_from(path: &Path) -> Result<Self, Self::Error> {
         let mut visited = HashSet::new();
-        Self::from_path_with_visited(path, &mut visited)
+        let mut config = Self::from_path_with_visited(path, &mut visited)?;
+        let dir = path
+            .canonicalize()?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        if let Some(name) = project_name(&config.project.name, &dir) {
+            config.project.name = name;
+        }
+        Ok(config)
+    }
+}
+
+/// Project name set with `--project`, which takes precedence over the spec.
+static PROJECT_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Sets the project name every spec loaded by this process uses.
+pub(crate) fn set_project_override(name: String) {
+    let _ = PROJECT_OVERRIDE.set(name);
+}
+
+/// Returns the name of the project whose spec is in `dir` and declares
+/// `declared`, or `None` to keep the declared name.
+///
+/// The first of these wins:
+/// 1. `--project`, then the `SECRETSPEC_PROJECT` environment variable
+/// 2. `name` in the spec's `[project]` table
+/// 3. The repository name of the git remote `origin`
+/// 4. The package name in `Cargo.toml` or `package.json` next to the spec
+/// 5. The name of the directory holding the spec
+///
+/// Detecting the name from the remote or a manifest keeps it, and so where
+/// secrets are stored, the same for teammates with differently named
+/// checkouts.
+pub(crate) fn project_name(declared: &str, dir: &Path) -> Option<String> {
+    if let Some(name) = PROJECT_OVERRIDE
+        .get()
+        .cloned()
+        .or_else(|| std::env::var("SECRETSPEC_PROJECT").ok())
+        .filter(|name| !name.is_empty())
+    {
+        return Some(name);
+    }
+    if !declared.is_empty() {
+        return None;
+    }
+    git_remote_name(dir)
+        .or_else(|| manifest_name(dir))
+        .or_else(|| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+}
+
+/// Returns the repository name of the `origin` remote of the repository
+/// holding `dir`.
+fn git_remote_name(dir: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["config", "--get", "remote.origin.url"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    repository_name(String::from_utf8(output.stdout).ok()?.trim())
+}
+
+/// Returns the repository name in a git remote URL, like `app` for
+/// `git@github.com:org/app.git` or `https://github.com/org/app`.
+pub(crate) fn repository_name(url: &str) -> Option<String> {
+    let path = url.trim_end_matches('/');
+    let name = path.rsplit(['/', ':']).next()?;
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Returns the package name in `Cargo.toml` or `package.json` in `dir`.
+///
+/// npm scopes are dropped, so `@org/app` is named `app`.
+fn manifest_name(dir: &Path) -> Option<String> {
+    let cargo = fs::read_to_string(dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|content| toml::from_str::<toml::Value>(&content).ok())
+        .and_then(|manifest| {
+            manifest
+                .get("package")?
+                .get("name")?
+                .as_str()
+                .map(String::from)
+        });
+    cargo
+        .or_else(|| {
+            let content = fs::read_to_string(dir.join("package.json")).ok()?;
+            let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+            let name = manifest.get("name")?.as_str()?;
+            Some(name.rsplit('/').next().unwrap_or(name).to_string())
+        })
+        .filter(|name| !name.is_empty())
+}
+
+/// A file format specs can be written in.
+///
+/// All formats deserialize into the same types, so a spec means the same
+/// thing whichever format it is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecFormat {
+    /// `secretspec.toml`
+    Toml,
+    /// `secretspec.json`
+    Json,
+    /// `secretspec.yaml`
+    Yaml,
+}
+
+impl SpecFormat {
+    /// Every format, in the order spec files are looked for.
+    pub const ALL: [SpecFormat; 3] = [SpecFormat::Toml, SpecFormat::Json, SpecFormat::Yaml];
+
+    /// Returns the name of the spec file in this format.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            SpecFormat::Toml => "secretspec.toml",
+            SpecFormat::Json => "secretspec.json",
+            SpecFormat::Yaml => "secretspec.yaml",
+        }
+    }
+
+    /// Returns the format of a spec file from its extension, defaulting to TOML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => SpecFormat::Json,
+            Some("yaml" | "yml") => SpecFormat::Yaml,
+            _ => SpecFormat::Toml,
+        }
+    }
+
+    /// Returns the path of the spec file in `dir`.
+    ///
+    /// If the directory has no spec, the path of `secretspec.toml` is
+    /// returned so that reading it reports the missing file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory has specs in more than one format,
+    /// since it would be ambiguous which one applies
+    pub fn find(dir: &Path) -> Result<PathBuf, ParseError> {
+        let found: Vec<PathBuf> = Self::ALL
+            .iter()
+            .map(|format| dir.join(format.file_name()))
+            .filter(|path| path.exists())
+            .collect();
+        match found.as_slice() {
+            [] => Ok(dir.join(SpecFormat::Toml.file_name())),
+            [path] => Ok(path.clone()),
+            _ => Err(ParseError::Validation(format!(
+                "Found more than one spec in {}: {}. Keep only one.",
+                dir.display(),
+                found
+                    .iter()
+                    .filter_map(|path| path.file_name())
+                    .map(|name| name.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// Parses a spec into a TOML value, which schema upgrades work on.
+    fn parse(self, content: &str) -> Result<toml::Value, ParseError> {
+        match self {
+            SpecFormat::Toml => Ok(toml::from_str(content)?),
+            SpecFormat::Json => serde_json::from_str(content).map_err(ParseError::Json),
+            SpecFormat::Yaml => serde_yaml::from_str(content).map_err(ParseError::Yaml),
+        }
+    }
+
+    /// Writes a spec in this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the spec can't be represented in the format
+    pub fn serialize(self, config: &Config) -> Result<String, String> {
+        match self {
+            SpecFormat::Toml => toml::to_string_pretty(config).map_err(|e| e.to_string()),
+            SpecFormat::Json => serde_json::to_string_pretty(config)
+                .map(|json| json + "\n")
+                .map_err(|e| e.to_string()),
+            SpecFormat::Yaml => serde_yaml::to_string(config).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl FromStr for SpecFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "toml" => Ok(SpecFormat::Toml),
+            "json" => Ok(SpecFormat::Json),
+            "yaml" | "yml" => Ok(SpecFormat::Yaml),
+            _ => Err(format!(
+                "Unknown spec format '{}'. Use toml, json or yaml.",
+                format
+            )),
+        }
+    }
+}
+
+/// Upgrades a parsed spec of any supported schema to the format of
+/// [`CURRENT_SCHEMA`].
+///
+/// Returns the schema version the spec was written in. Schema 2 and later
+/// share a format, so their `schema` is kept and decides whether the spec
+/// is strict.
+///
+/// # Errors
+///
+/// Returns an error if the spec declares an unknown schema or revision.
+fn upgrade_schema(value: &mut toml::Value) -> Result<u32, ParseError> {
+    // A missing [project] table is reported by deserialization
+    let Some(project) = value.get_mut("project").and_then(|p| p.as_table_mut()) else {
+        return Ok(CURRENT_SCHEMA);
+    };
+
+    let schema = match (project.get("schema"), project.get("revision")) {
+        (Some(schema), _) => {
+            let schema = schema
+                .as_integer()
+                .and_then(|s| u32::try_from(s).ok())
+                .ok_or_else(|| {
+                    ParseError::Validation("`schema` must be a positive integer".into())
+                })?;
+            if schema == 0 || schema > CURRENT_SCHEMA {
+                return Err(ParseError::UnsupportedSchema(schema));
+            }
+            schema
+        }
+        (None, Some(revision)) => {
+            let revision = revision.as_str().unwrap_or_default();
+            if revision != SCHEMA_1_REVISION {
+                return Err(ParseError::UnsupportedRevision(revision.to_string()));
+            }
+            1
+        }
+        (None, None) => {
+            return Err(ParseError::Validation(format!(
+                "[project] must declare `schema = {}`",
+                CURRENT_SCHEMA
+            )));
+        }
+    };
+
+    // Schema 1 -> 2: `revision = "1.0"` is replaced by `schema = 2`
+    if schema == 1 {
+        project.remove("revision");
+        project.insert("schema".to_string(), toml::Value::Integer(2));
     }
+
+    Ok(schema)
+}
+
+/// Rejects keys that the spec format doesn't know, suggesting the closest
+/// known key.
+///
+/// # Errors
+///
+/// Returns a validation error naming the first unknown key.
+fn check_unknown_keys(value: &toml::Value) -> Result<(), ParseError> {
+    let value = serde_json::to_value(value).map_err(ParseError::Json)?;
+    let Some(unknown) = crate::schema::unknown_key(&value) else {
+        return Ok(());
+    };
+
+    let mut message = if unknown.table.is_empty() {
+        format!("Unknown top-level key '{}'", unknown.key)
+    } else {
+        format!("Unknown key '{}' in [{}]", unknown.key, unknown.table)
+    };
+    let known: Vec<&str> = unknown.known.iter().map(String::as_str).collect();
+    if let Some(hint) = crate::suggest::hint(&unknown.key, &known) {
+        message = format!("{}. {}", message, hint);
+    }
+    Err(ParseError::Validation(message))
+}
+
+/// Rewrites a spec in the current schema, preserving comments and formatting.
+///
+/// Returns `None` if the spec already uses [`CURRENT_SCHEMA`].
+///
+/// # Errors
+///
+/// Returns an error if the spec cannot be parsed, declares an unknown
+/// schema or revision, or has keys the strict current schema rejects.
+pub fn migrate(content: &str) -> Result<Option<String>, ParseError> {
+    let mut value: toml::Value = toml::from_str(content)?;
+    if upgrade_schema(&mut value)? == CURRENT_SCHEMA {
+        return Ok(None);
+    }
+
+    let mut document: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e: toml_edit::TomlError| ParseError::Validation(e.to_string()))?;
+    let project = document
+        .get_mut("project")
+        .and_then(|p| p.as_table_mut())
+        .ok_or_else(|| ParseError::Validation("migration requires a [project] table".into()))?;
+
+    // Rebuild the table so the new `schema` takes the place of `revision`
+    // or the old `schema`, keeping the comments attached to every key
+    let original = project.clone();
+    project.clear();
+    for (name, item) in original.iter() {
+        let key = original.key(name).expect("key exists while iterating");
+        match name {
+            "schema" if original.contains_key("revision") => {}
+            "schema" | "revision" => {
+                let mut schema_key = toml_edit::Key::new("schema");
+                *schema_key.leaf_decor_mut() = key.leaf_decor().clone();
+                let mut schema = toml_edit::value(i64::from(CURRENT_SCHEMA));
+                if let (Some(new), Some(old)) = (schema.as_value_mut(), item.as_value()) {
+                    *new.decor_mut() = old.decor().clone();
+                }
+                project.insert_formatted(&schema_key, schema);
+            }
+            _ => {
+                project.insert_formatted(key, item.clone());
+            }
+        }
+    }
+
+    // Keys older schemas ignored are errors now
+    let migrated = document.to_string();
+    Config::parse(&migrated, SpecFormat::Toml)?;
+    Ok(Some(migrated))
 }
 
 /// Project metadata and inheritance configuration.
@@ -220,10 +739,12 @@ impl TryFrom<&Path> for Config {
 /// enabling shared configuration patterns across multiple projects.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
-    /// The name of the project, used for identification and namespacing
+    /// The name of the project, used for identification and namespacing;
+    /// detected when loading a spec that omits it
+    #[serde(default)]
     pub name: String,
-    /// Configuration format revision (currently must be "1.0")
-    pub revision: String,
+    /// Spec schema version; schema 1 specs are upgraded when loaded
+    pub schema: u32,
     /// Optional list of relative paths to other SecretSpec projects to inherit from
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extends: Option<Vec<String>>,
@@ -235,8 +756,12 @@ pub struct Project {
 /// Each profile contains its own set of secret definitions with their requirements.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
+    /// Provider the profile's secrets are stored in, unless the user
+    /// configures one for the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
     /// Map of secret names to their configurations, flattened in TOML for cleaner syntax
-    #[serde(flatten)]
+    #[serde(flatten, serialize_with = "sorted")]
     pub secrets: HashMap<String, Secret>,
 }
 
@@ -244,26 +769,52 @@ impl Profile {
     /// Create a new empty profile configuration.
     pub fn new() -> Self {
         Self {
+            provider: None,
             secrets: HashMap::new(),
         }
     }
 
     /// Validate the profile configuration.
     ///
-    /// Ensures all secrets have valid names and configurations.
+    /// Ensures all secrets have valid names and configurations. A profile
+    /// that only sets a provider inherits its secrets from the default
+    /// profile.
     pub fn validate(&self) -> Result<(), String> {
-        if self.secrets.is_empty() {
+        if self.secrets.is_empty() && self.provider.is_none() {
             return Err("Profile must define at least one secret".into());
         }
+        if self.provider.as_deref() == Some("") {
+            return Err("provider cannot be empty".into());
+        }
 
         for (name, secret) in &self.secrets {
-            // Validate secret name is a valid identifier
-            if !is_valid_identifier(name) {
+            // Validate secret name is a valid identifier, or a pattern like FEATURE_FLAG_*
+            let pattern_prefix = Secret::pattern_prefix(name);
+            if !is_valid_identifier(pattern_prefix.unwrap_or(name)) {
                 return Err(format!(
                     "Invalid secret name '{}': must be a valid identifier (alphanumeric and underscores, not starting with a number)",
                     name
                 ));
             }
+            if pattern_prefix.is_some() && secret.default.is_some() {
+                return Err(format!(
+                    "Secret '{}': patterns cannot have default values",
+                    name
+                ));
+            }
+            if pattern_prefix.is_some() && secret.item.is_some() {
+                return Err(format!("Secret '{}': patterns cannot read an item", name));
+            }
+            if let Some(field) = secret.fields.keys().find(|field| {
+                self.secrets.iter().any(|(other, other_secret)| {
+                    other != name && (other == *field || other_secret.fields.contains_key(*field))
+                })
+            }) {
+                return Err(format!(
+                    "Secret '{}': field '{}' is already declared as a secret",
+                    name, field
+                ));
+            }
 
             secret
                 .validate()
@@ -273,11 +824,23 @@ impl Profile {
         Ok(())
     }
 
+    /// Returns the declaration reading a secret from an item's fields, with
+    /// its name.
+    pub fn item_declaration(&self, field: &str) -> Option<(&str, &Secret)> {
+        self.secrets
+            .iter()
+            .find(|(_, secret)| secret.fields.contains_key(field))
+            .map(|(name, secret)| (name.as_str(), secret))
+    }
+
     /// Merge another profile configuration into this one.
     ///
     /// The current profile takes precedence - secrets from `other`
     /// are only added if they don't already exist.
     pub fn merge_with(&mut self, other: Profile) {
+        if self.provider.is_none() {
+            self.provider = other.provider;
+        }
         for (secret_name, secret_config) in other.secrets {
             self.secrets.entry(secret_name).or_insert(secret_config);
         }
@@ -290,6 +853,413 @@ impl Default for Profile {
     }
 }
 
+/// An AWS role assumed with `sts:AssumeRole` to derive short-lived credentials.
+///
+/// The resulting `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+/// `AWS_SESSION_TOKEN` are added to the resolved secrets instead of being
+/// read from the provider.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssumeRole {
+    /// ARN of the role to assume
+    pub role_arn: String,
+    /// Name of the role session, defaulting to `secretspec-<project>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_name: Option<String>,
+    /// Lifetime of the credentials in seconds, defaulting to the role's setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u32>,
+    /// AWS CLI profile holding the credentials used to assume the role
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_profile: Option<String>,
+    /// ARN or serial number of the MFA device the role requires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfa_serial: Option<String>,
+    /// Name of the secret holding the MFA device's TOTP seed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfa_secret: Option<String>,
+}
+
+impl AssumeRole {
+    /// Validate the role configuration.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.role_arn.starts_with("arn:") {
+            return Err(format!("role_arn '{}' is not an ARN", self.role_arn));
+        }
+        if let Some(duration) = self.duration_seconds
+            && !(900..=43200).contains(&duration)
+        {
+            return Err("duration_seconds must be between 900 and 43200".into());
+        }
+        if let Some(secret) = &self.mfa_secret {
+            if !is_valid_identifier(secret) {
+                return Err(format!(
+                    "mfa_secret '{}' is not a valid secret name",
+                    secret
+                ));
+            }
+            if self.mfa_serial.is_none() {
+                return Err("mfa_secret requires mfa_serial".into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Exceptions to the convention that secret names are uppercase, like
+/// environment variables.
+///
+/// ```toml
+/// [keys]
+/// exceptions = ["npm_config_registry"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeysConfig {
+    /// Secret names allowed to be lowercase or mixed case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exceptions: Vec<String>,
+}
+
+impl KeysConfig {
+    /// Validate the key policy.
+    pub fn validate(&self) -> Result<(), String> {
+        match self
+            .exceptions
+            .iter()
+            .find(|name| !is_valid_identifier(name))
+        {
+            Some(name) => Err(format!("exception '{}' is not a valid secret name", name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns whether no exceptions are configured.
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Returns whether a secret name follows the policy: uppercase, or listed
+    /// as an exception. Patterns like `FEATURE_FLAG_*` are checked by their
+    /// prefix.
+    pub fn allows(&self, name: &str) -> bool {
+        let prefix = Secret::pattern_prefix(name).unwrap_or(name);
+        !prefix.chars().any(|c| c.is_ascii_lowercase())
+            || self.exceptions.iter().any(|exception| exception == name)
+    }
+}
+
+/// Returns the form secret names are compared in: uppercase, with every run
+/// of other characters than letters and digits replaced by an underscore.
+///
+/// `api_key`, `API_KEY` and `Api Key` all normalize to `API_KEY`, so item
+/// names in providers that don't follow the environment style can still be
+/// matched exactly.
+pub(crate) fn normalize_key(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for part in name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+    {
+        if !normalized.is_empty() {
+            normalized.push('_');
+        }
+        normalized.push_str(&part.to_ascii_uppercase());
+    }
+    normalized
+}
+
+/// Controls which variables of its own environment `run` passes on to the
+/// command it launches.
+///
+/// ```toml
+/// [run]
+/// clean_env = true
+/// allow = ["PATH", "HOME", "LC_*"]
+/// deny = ["AWS_*"]
+/// harden = true
+///
+/// [run.kubeconfig]
+/// server = "KUBE_SERVER"
+/// certificate_authority = "KUBE_CA"
+/// token = "KUBE_TOKEN"
+///
+/// [run.aws]
+/// profile = "deploy"
+///
+/// [run.gcloud]
+/// credentials = "GOOGLE_CREDENTIALS"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunConfig {
+    /// Only pass on the variables listed in `allow`, as with `--clean-env`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clean_env: bool,
+    /// Variables passed on with a clean environment, by name or by a prefix
+    /// pattern like `LC_*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    /// Variables never passed on, by name or by a prefix pattern like `AWS_*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    /// Disable core dumps and ptrace access before resolving secrets, as
+    /// with `--harden`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub harden: bool,
+    /// Cluster credentials assembled into a kubeconfig for the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kubeconfig: Option<KubeconfigConfig>,
+    /// AWS credentials written to a shared credentials file for the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws: Option<AwsCredentialsConfig>,
+    /// Google Cloud credentials written as application default credentials
+    /// for the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcloud: Option<GcloudCredentialsConfig>,
+}
+
+impl RunConfig {
+    /// Validate the run configuration.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(kubeconfig) = &self.kubeconfig {
+            kubeconfig
+                .validate()
+                .map_err(|e| format!("kubeconfig: {}", e))?;
+        }
+        if let Some(aws) = &self.aws {
+            aws.validate().map_err(|e| format!("aws: {}", e))?;
+        }
+        if let Some(gcloud) = &self.gcloud
+            && !is_valid_identifier(&gcloud.credentials)
+        {
+            return Err(format!(
+                "gcloud: credentials '{}' is not a valid secret name",
+                gcloud.credentials
+            ));
+        }
+        for (list, patterns) in [("allow", &self.allow), ("deny", &self.deny)] {
+            if let Some(pattern) = patterns.iter().find(|pattern| {
+                let name = Secret::pattern_prefix(pattern).unwrap_or(pattern);
+                name.is_empty() || name.contains(['=', '*'])
+            }) {
+                return Err(format!(
+                    "{}: '{}' is not a variable name or a pattern like LC_*",
+                    list, pattern
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether a variable of the environment is passed on to the
+    /// command.
+    ///
+    /// Denied variables are never passed on. With a clean environment, from
+    /// `clean_env` or the `--clean-env` flag, only allowed variables are.
+    pub fn inherits(&self, name: &str, clean_env: bool) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| match Secret::pattern_prefix(pattern) {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => pattern == name,
+                })
+        };
+        (!(clean_env || self.clean_env) || matches(&self.allow)) && !matches(&self.deny)
+    }
+
+    /// Returns whether nothing is configured.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Secrets `run` assembles into a kubeconfig, so the command can reach a
+/// cluster without its credentials being kept in `~/.kube`.
+///
+/// Each field names the secret holding that part of the cluster's
+/// credentials, so every profile can point at a different cluster.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KubeconfigConfig {
+    /// Secret holding the URL of the cluster's API server
+    pub server: String,
+    /// Secret holding the cluster's CA certificate, as PEM or base64-encoded
+    /// PEM; the system's roots are trusted without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate_authority: Option<String>,
+    /// Secret holding the bearer token of the cluster user
+    pub token: String,
+    /// Namespace the context selects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+impl KubeconfigConfig {
+    /// Validate the kubeconfig configuration.
+    pub fn validate(&self) -> Result<(), String> {
+        for (field, secret) in [
+            ("server", Some(&self.server)),
+            ("certificate_authority", self.certificate_authority.as_ref()),
+            ("token", Some(&self.token)),
+        ] {
+            if let Some(secret) = secret
+                && !is_valid_identifier(secret)
+            {
+                return Err(format!("{} '{}' is not a valid secret name", field, secret));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Secrets `run` writes to an AWS shared credentials file, for tools that
+/// only read credentials from a file.
+///
+/// Every field defaults to the variable the AWS SDKs read, so an empty
+/// `[run.aws]` table writes the usual credentials, including those of an
+/// [assumed role](AssumeRole).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AwsCredentialsConfig {
+    /// Profile the credentials are written under and `AWS_PROFILE` selects
+    pub profile: String,
+    /// Secret holding the access key ID
+    pub access_key_id: String,
+    /// Secret holding the secret access key
+    pub secret_access_key: String,
+    /// Secret holding the session token of temporary credentials, left out
+    /// of the file when it has no value
+    pub session_token: String,
+}
+
+impl Default for AwsCredentialsConfig {
+    fn default() -> Self {
+        Self {
+            profile: "default".to_string(),
+            access_key_id: "AWS_ACCESS_KEY_ID".to_string(),
+            secret_access_key: "AWS_SECRET_ACCESS_KEY".to_string(),
+            session_token: "AWS_SESSION_TOKEN".to_string(),
+        }
+    }
+}
+
+impl AwsCredentialsConfig {
+    /// Validate the AWS credentials configuration.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.profile.is_empty() || self.profile.contains(['[', ']', '\n']) {
+            return Err(format!("profile '{}' is not a profile name", self.profile));
+        }
+        for (field, secret) in [
+            ("access_key_id", &self.access_key_id),
+            ("secret_access_key", &self.secret_access_key),
+            ("session_token", &self.session_token),
+        ] {
+            if !is_valid_identifier(secret) {
+                return Err(format!("{} '{}' is not a valid secret name", field, secret));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The secret `run` writes as Google Cloud application default credentials.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GcloudCredentialsConfig {
+    /// Secret holding a service account key or authorized user credentials
+    /// as JSON
+    pub credentials: String,
+}
+
+/// Announces changes to secrets, so a team learns about new or rotated
+/// credentials right away.
+///
+/// Messages name the project, profile, keys and who made the change, never a
+/// value. Targets given as `env:NAME` are read from that environment
+/// variable, which keeps webhook URLs out of the spec.
+///
+/// ```toml
+/// [notify]
+/// profiles = ["production"]
+/// slack = ["env:SLACK_WEBHOOK_URL"]
+/// webhook = ["https://ops.example.com/hooks/secretspec"]
+/// email = ["secops@example.com"]
+/// smtp = "smtps://smtp.example.com:465"
+/// from = "secretspec@example.com"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Profiles whose changes are announced; empty for all profiles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// Slack incoming webhook URLs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slack: Vec<String>,
+    /// URLs that receive a JSON description of each change
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhook: Vec<String>,
+    /// Email addresses notified through `smtp`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub email: Vec<String>,
+    /// SMTP server URL, like `smtps://smtp.example.com:465`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<String>,
+    /// Sender address of emails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+}
+
+impl NotifyConfig {
+    /// Validate the notification configuration.
+    pub fn validate(&self) -> Result<(), String> {
+        for (list, targets) in [("slack", &self.slack), ("webhook", &self.webhook)] {
+            if let Some(target) = targets.iter().find(|target| {
+                !(target.starts_with("https://")
+                    || target.starts_with("http://")
+                    || target
+                        .strip_prefix("env:")
+                        .is_some_and(|name| !name.is_empty()))
+            }) {
+                return Err(format!(
+                    "{}: '{}' is not an http(s) URL or env:NAME",
+                    list, target
+                ));
+            }
+        }
+        if let Some(address) = self.email.iter().find(|address| !address.contains('@')) {
+            return Err(format!("email: '{}' is not an email address", address));
+        }
+        if !self.email.is_empty() && (self.smtp.is_none() || self.from.is_none()) {
+            return Err("email requires smtp and from".into());
+        }
+        if let Some(smtp) = &self.smtp
+            && !(smtp.starts_with("smtp://")
+                || smtp.starts_with("smtps://")
+                || smtp.starts_with("env:"))
+        {
+            return Err(format!(
+                "smtp: '{}' is not an smtp:// or smtps:// URL or env:NAME",
+                smtp
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns whether changes to a profile are announced anywhere.
+    pub fn covers(&self, profile: &str) -> bool {
+        let has_targets =
+            !(self.slack.is_empty() && self.webhook.is_empty() && self.email.is_empty());
+        has_targets && (self.profiles.is_empty() || self.profiles.iter().any(|p| p == profile))
+    }
+
+    /// Returns whether nothing is configured.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Configuration for an individual secret.
 ///
 /// Defines the properties of a secret including its documentation,
@@ -305,12 +1275,127 @@ pub struct Secret {
     /// Optional default value if the secret is not provided
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default: Option<String>,
+    /// Makes the secret required only when the condition holds, in place
+    /// of `required`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_if: Option<RequiredIf>,
+    /// Makes the secret optional once all of these other secrets are set
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub required_unless_set: Vec<String>,
+    /// Named group the secret belongs to, for operating on a slice of the
+    /// spec with `--group`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Platforms the secret is needed on, as an OS like `macos` optionally
+    /// followed by an architecture like `macos-aarch64`; empty for all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<String>,
+    /// Transforms applied in order to the resolved value, like `trim`,
+    /// `base64d` or `jsonpath:$.private_key`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transform: Vec<String>,
+    /// Provider item whose fields are exported as several secrets, read in
+    /// a single fetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+    /// Secrets read from the fields of `item`, mapping each secret name to
+    /// the name of its field
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    /// How long a written value lives, like `24h`, for ephemeral secrets
+    /// that `secretspec gc` deletes once expired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
+    /// Command that generates a new value, for `secretspec rotate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<Generator>,
+    /// Whether resolving the secret requires a touch on a FIDO2 security
+    /// key, which holds an extra layer of encryption over the stored value
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hardware_confirm: bool,
+    /// ID of the Bitwarden item holding the secret, which is then read and
+    /// written by ID instead of found by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitwarden_item_id: Option<String>,
+    /// What kind of value the secret holds, set with `type`, for checks
+    /// that only make sense for it
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<SecretKind>,
+}
+
+/// What kind of value a secret holds.
+///
+/// ```toml
+/// DB_PASSWORD = { description = "Database password", type = "password" }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretKind {
+    /// A password chosen by a person, which `secretspec analyze --hibp`
+    /// checks against known breaches
+    Password,
+}
+
+/// Condition under which a secret is required.
+///
+/// ```toml
+/// SENTRY_DSN = { description = "Sentry DSN", required_if = { profile = ["staging", "production"] } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequiredIf {
+    /// Profiles in which the secret is required
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub profile: Vec<String>,
+}
+
+/// Command that generates a new value for a secret.
+///
+/// ```toml
+/// SESSION_KEY = { description = "Session key", generator = { command = "openssl rand -hex 32" } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Generator {
+    /// Shell command printing the new value on stdout
+    pub command: String,
+    /// How long the command may run, like `30s` (default: 30s)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Fewest characters the value may have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    /// Whether the value may span several lines, like a PEM key
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub multiline: bool,
+}
+
+impl Generator {
+    /// Validate the generator configuration.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.command.trim().is_empty() {
+            return Err("command cannot be empty".into());
+        }
+        if let Some(timeout) = &self.timeout {
+            crate::ttl::parse(timeout).map_err(|e| format!("timeout: {}", e))?;
+        }
+        Ok(())
+    }
 }
 
 impl Secret {
     /// Validate the secret configuration.
     ///
-    /// Ensures that required secrets don't have default values.
+    /// Ensures that required secrets don't have default values and that
+    /// conditions are well-formed.
     pub fn validate(&self) -> Result<(), String> {
         if let Some(desc) = &self.description {
             if desc.is_empty() {
@@ -320,18 +1405,233 @@ impl Secret {
             return Err("missing description".into());
         }
 
-        if self.required && self.default.is_some() {
+        if self.required && self.default.is_some() && !self.is_conditional() {
             return Err("Required secrets cannot have default values".into());
         }
 
+        if let Some(condition) = &self.required_if
+            && condition.profile.is_empty()
+        {
+            return Err("required_if needs a condition, e.g. { profile = \"production\" }".into());
+        }
+
+        if self.group.as_deref().is_some_and(str::is_empty) {
+            return Err("group cannot be empty".into());
+        }
+
+        if let Some(platform) = self.platforms.iter().find(|p| !is_valid_platform(p)) {
+            return Err(format!(
+                "platforms: unknown platform '{}'. Use an OS like linux, macos or windows, optionally with an architecture like macos-aarch64",
+                platform
+            ));
+        }
+
+        for transform in &self.transform {
+            transform
+                .parse::<Transform>()
+                .map_err(|e| format!("transform: {}", e))?;
+        }
+
+        if let Some(name) = self
+            .required_unless_set
+            .iter()
+            .find(|name| !is_valid_identifier(name))
+        {
+            return Err(format!(
+                "required_unless_set: '{}' is not a valid secret name",
+                name
+            ));
+        }
+
+        match (&self.item, self.fields.is_empty()) {
+            (Some(item), _) if item.is_empty() => return Err("item cannot be empty".into()),
+            (Some(_), true) => {
+                return Err("item needs fields, e.g. { DB_USER = \"username\" }".into());
+            }
+            (None, false) => return Err("fields needs the item to read them from".into()),
+            _ => {}
+        }
+        if self.item.is_some() && self.default.is_some() {
+            return Err("secrets read from an item cannot have default values".into());
+        }
+        if let Some(name) = self.fields.keys().find(|name| !is_valid_identifier(name)) {
+            return Err(format!("fields: '{}' is not a valid secret name", name));
+        }
+        if let Some(ttl) = &self.ttl {
+            if self.item.is_some() {
+                return Err("secrets read from an item cannot have a ttl".into());
+            }
+            crate::ttl::parse(ttl).map_err(|e| format!("ttl: {}", e))?;
+        }
+        if let Some(generator) = &self.generator {
+            if self.item.is_some() {
+                return Err("secrets read from an item cannot have a generator".into());
+            }
+            generator
+                .validate()
+                .map_err(|e| format!("generator: {}", e))?;
+        }
+        if self.hardware_confirm && self.item.is_some() {
+            return Err("secrets read from an item cannot have hardware_confirm".into());
+        }
+        if let Some(id) = &self.bitwarden_item_id {
+            if self.item.is_some() {
+                return Err("secrets read from an item cannot have a bitwarden_item_id".into());
+            }
+            if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+                return Err(format!("bitwarden_item_id '{}' is not an item ID", id));
+            }
+        }
+
         Ok(())
     }
+
+    /// Returns the names the secret is exported under: the names of its
+    /// fields if it reads an item, otherwise its own name.
+    pub fn exported_names<'a>(&'a self, name: &'a str) -> Vec<&'a str> {
+        if self.item.is_some() {
+            self.fields.keys().map(String::as_str).collect()
+        } else {
+            vec![name]
+        }
+    }
+
+    /// Returns the prefix of a pattern name like `FEATURE_FLAG_*`.
+    ///
+    /// Patterns match every provider key that starts with the prefix. Returns
+    /// `None` for ordinary secret names.
+    pub fn pattern_prefix(name: &str) -> Option<&str> {
+        name.strip_suffix('*')
+    }
+
+    /// Returns whether the secret is needed on an OS and architecture, named
+    /// as in [`std::env::consts`].
+    pub fn applies_to(&self, os: &str, arch: &str) -> bool {
+        self.platforms.is_empty()
+            || self
+                .platforms
+                .iter()
+                .any(|platform| match platform.split_once('-') {
+                    Some((platform_os, platform_arch)) => {
+                        platform_os == os && platform_arch == arch
+                    }
+                    None => platform == os,
+                })
+    }
+
+    /// Returns whether the requirement depends on the profile or other secrets.
+    pub fn is_conditional(&self) -> bool {
+        self.required_if.is_some() || !self.required_unless_set.is_empty()
+    }
+
+    /// Returns whether the secret must be set.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The active profile
+    /// * `is_set` - Whether another secret has a value
+    pub fn is_required(&self, profile: &str, is_set: impl Fn(&str) -> bool) -> bool {
+        let required = match &self.required_if {
+            Some(condition) => condition.profile.iter().any(|p| p == profile),
+            None => self.required,
+        };
+        let alternative_set = !self.required_unless_set.is_empty()
+            && self.required_unless_set.iter().all(|name| is_set(name));
+        required && !alternative_set
+    }
+}
+
+/// Deserializes a string or a list of strings.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// Serializes a map with its keys in order, so written specs are stable.
+fn sorted<S, V>(map: &HashMap<String, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
 }
 
 fn default_true() -> bool {
     true
 }
 
+/// Check if a profile holds production secrets, by the names deployment
+/// platforms use for it: `production` and `prod`.
+pub(crate) fn is_production(profile: &str) -> bool {
+    matches!(profile, "production" | "prod")
+}
+
+/// Check if a string is a namespace: `/`-separated segments of letters,
+/// digits, `-`, `_` and `.`, none of them `.` or `..`.
+pub(crate) fn is_valid_namespace(namespace: &str) -> bool {
+    namespace.split('/').all(|segment| {
+        !segment.is_empty()
+            && segment != "."
+            && segment != ".."
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+    })
+}
+
+/// Check if a string is an age or SSH public key usable as an encryption recipient.
+pub(crate) fn is_valid_recipient(key: &str) -> bool {
+    key.starts_with("age1") || key.starts_with("ssh-ed25519 ") || key.starts_with("ssh-rsa ")
+}
+
+/// Operating systems accepted in `platforms`, as named by `std::env::consts::OS`.
+const PLATFORM_OS: &[&str] = &[
+    "linux",
+    "macos",
+    "windows",
+    "freebsd",
+    "netbsd",
+    "openbsd",
+    "dragonfly",
+    "solaris",
+    "illumos",
+    "android",
+    "ios",
+];
+
+/// Architectures accepted in `platforms`, as named by `std::env::consts::ARCH`.
+const PLATFORM_ARCH: &[&str] = &[
+    "x86",
+    "x86_64",
+    "arm",
+    "aarch64",
+    "loongarch64",
+    "powerpc",
+    "powerpc64",
+    "riscv64",
+    "s390x",
+];
+
+/// Check if a string is a platform, `<os>` or `<os>-<arch>`.
+fn is_valid_platform(platform: &str) -> bool {
+    match platform.split_once('-') {
+        Some((os, arch)) => PLATFORM_OS.contains(&os) && PLATFORM_ARCH.contains(&arch),
+        None => PLATFORM_OS.contains(&platform),
+    }
+}
+
 /// Check if a string is a valid identifier.
 fn is_valid_identifier(s: &str) -> bool {
     if s.is_empty() {
@@ -351,13 +1651,29 @@ fn is_valid_identifier(s: &str) -> bool {
 /// Global user configuration for SecretSpec.
 ///
 /// This configuration is stored in the user's config directory and provides
-/// defaults that apply across all projects.
+/// defaults that apply across all projects, optionally overridden per project
+/// and per profile:
+///
+/// ```toml
+/// [defaults]
+/// provider = "keyring"
+/// profile = "development"
+///
+/// [defaults.profiles.production]
+/// provider = "onepassword://Production"
+///
+/// [projects.my-app]
+/// provider = "dotenv://.env"
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[doc(hidden)]
 pub struct GlobalConfig {
     /// Default settings
     #[serde(default)]
     pub defaults: GlobalDefaults,
+    /// Per-project overrides, keyed by project name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub projects: HashMap<String, ProjectDefaults>,
 }
 
 /// Default settings in the global configuration.
@@ -370,9 +1686,409 @@ pub struct GlobalDefaults {
     /// Default profile to use when not specified
     #[serde(skip_serializing_if = "Option::is_none")]
     pub profile: Option<String>,
+    /// Number of profiles `check --all-profiles` checks at a time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+    /// Time in seconds a command keeps each lookup before asking the
+    /// provider again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_ttl: Option<u64>,
+    /// Requests per second allowed to each provider, keyed by provider name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub rate_limits: HashMap<String, f64>,
+    /// Per-profile overrides that apply to every project
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, ProfileDefaults>,
+    /// Prefix of the project name in every stored item name or path, for
+    /// vaults shared with other teams
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+/// Per-project settings in the global configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[doc(hidden)]
+pub struct ProjectDefaults {
+    /// Provider to use for this project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Profile to use for this project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Per-profile overrides for this project
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, ProfileDefaults>,
+    /// Namespace of this project's stored items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+/// Per-profile settings in the global configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[doc(hidden)]
+pub struct ProfileDefaults {
+    /// Provider to use for this profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+}
+
+/// A setting in the global configuration, addressed by a dotted key.
+///
+/// Supported keys:
+///
+/// - `defaults.provider`, `defaults.profile`
+/// - `defaults.concurrency`, `defaults.cache_ttl`, `defaults.namespace`
+/// - `defaults.rate_limits.<provider>`
+/// - `defaults.profiles.<profile>.provider`
+/// - `projects.<project>.provider`, `projects.<project>.profile`,
+///   `projects.<project>.namespace`
+/// - `projects.<project>.profiles.<profile>.provider`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GlobalConfigKey {
+    Provider {
+        project: Option<String>,
+        profile: Option<String>,
+    },
+    Profile {
+        project: Option<String>,
+    },
+    Concurrency,
+    CacheTtl,
+    RateLimit {
+        provider: String,
+    },
+    Namespace {
+        project: Option<String>,
+    },
+}
+
+impl FromStr for GlobalConfigKey {
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let parsed = if let Some(rest) = key.strip_prefix("defaults.") {
+            match rest {
+                "provider" => Some(GlobalConfigKey::Provider {
+                    project: None,
+                    profile: None,
+                }),
+                "profile" => Some(GlobalConfigKey::Profile { project: None }),
+                "concurrency" => Some(GlobalConfigKey::Concurrency),
+                "cache_ttl" => Some(GlobalConfigKey::CacheTtl),
+                "namespace" => Some(GlobalConfigKey::Namespace { project: None }),
+                _ if rest.starts_with("rate_limits.") => rest
+                    .strip_prefix("rate_limits.")
+                    .filter(|provider| !provider.is_empty() && !provider.contains('.'))
+                    .map(|provider| GlobalConfigKey::RateLimit {
+                        provider: provider.to_string(),
+                    }),
+                _ => rest
+                    .strip_prefix("profiles.")
+                    .and_then(|r| r.strip_suffix(".provider"))
+                    .filter(|profile| !profile.is_empty())
+                    .map(|profile| GlobalConfigKey::Provider {
+                        project: None,
+                        profile: Some(profile.to_string()),
+                    }),
+            }
+        } else if let Some(rest) = key.strip_prefix("projects.") {
+            if let Some(inner) = rest.strip_suffix(".provider") {
+                match inner.split_once(".profiles.") {
+                    Some((project, profile)) if !project.is_empty() && !profile.is_empty() => {
+                        Some(GlobalConfigKey::Provider {
+                            project: Some(project.to_string()),
+                            profile: Some(profile.to_string()),
+                        })
+                    }
+                    Some(_) => None,
+                    None if !inner.is_empty() => Some(GlobalConfigKey::Provider {
+                        project: Some(inner.to_string()),
+                        profile: None,
+                    }),
+                    None => None,
+                }
+            } else if let Some(project) = rest.strip_suffix(".namespace") {
+                Some(project)
+                    .filter(|project| !project.is_empty())
+                    .map(|project| GlobalConfigKey::Namespace {
+                        project: Some(project.to_string()),
+                    })
+            } else {
+                rest.strip_suffix(".profile")
+                    .filter(|project| !project.is_empty())
+                    .map(|project| GlobalConfigKey::Profile {
+                        project: Some(project.to_string()),
+                    })
+            }
+        } else {
+            None
+        };
+
+        parsed.ok_or_else(|| {
+            format!(
+                "Unknown configuration key '{}'. Supported keys: defaults.provider, defaults.profile, defaults.concurrency, defaults.cache_ttl, defaults.namespace, defaults.rate_limits.<provider>, defaults.profiles.<profile>.provider, projects.<project>.provider, projects.<project>.profile, projects.<project>.namespace, projects.<project>.profiles.<profile>.provider",
+                key
+            )
+        })
+    }
 }
 
 impl GlobalConfig {
+    /// Returns the default provider for a project and profile.
+    ///
+    /// The most specific setting wins:
+    /// 1. `projects.<project>.profiles.<profile>.provider`
+    /// 2. `projects.<project>.provider`
+    /// 3. `defaults.profiles.<profile>.provider`
+    /// 4. `defaults.provider`
+    pub fn provider_for(&self, project: &str, profile: &str) -> Option<&str> {
+        self.project_provider_for(project, profile)
+            .or_else(|| self.default_provider_for(profile))
+    }
+
+    /// Returns the provider set for a project specifically, by
+    /// `projects.<project>.profiles.<profile>.provider` or else
+    /// `projects.<project>.provider`.
+    pub fn project_provider_for(&self, project: &str, profile: &str) -> Option<&str> {
+        let project_defaults = self.projects.get(project)?;
+        project_defaults
+            .profiles
+            .get(profile)
+            .and_then(|p| p.provider.as_deref())
+            .or(project_defaults.provider.as_deref())
+    }
+
+    /// Returns the provider set for all projects, by
+    /// `defaults.profiles.<profile>.provider` or else `defaults.provider`.
+    pub fn default_provider_for(&self, profile: &str) -> Option<&str> {
+        self.defaults
+            .profiles
+            .get(profile)
+            .and_then(|p| p.provider.as_deref())
+            .or(self.defaults.provider.as_deref())
+    }
+
+    /// Returns the requests per second allowed to a provider, if limited.
+    pub fn rate_limit_for(&self, provider: &str) -> Option<f64> {
+        self.defaults.rate_limits.get(provider).copied()
+    }
+
+    /// Returns the namespace of a project's stored items.
+    ///
+    /// `projects.<project>.namespace` takes precedence over
+    /// `defaults.namespace`.
+    pub fn namespace_for(&self, project: &str) -> Option<&str> {
+        self.projects
+            .get(project)
+            .and_then(|p| p.namespace.as_deref())
+            .or(self.defaults.namespace.as_deref())
+    }
+
+    /// Returns the default profile for a project.
+    ///
+    /// `projects.<project>.profile` takes precedence over `defaults.profile`.
+    pub fn profile_for(&self, project: &str) -> Option<&str> {
+        self.projects
+            .get(project)
+            .and_then(|p| p.profile.as_deref())
+            .or(self.defaults.profile.as_deref())
+    }
+
+    /// Gets a setting by its dotted key (e.g. `projects.my-app.provider`).
+    ///
+    /// # Returns
+    ///
+    /// The setting's value, or `None` if it is not set
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is not a supported setting
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        Ok(match key.parse::<GlobalConfigKey>()? {
+            GlobalConfigKey::Provider {
+                project: Some(name),
+                profile,
+            } => {
+                let project = self.projects.get(&name);
+                match profile {
+                    Some(profile) => project
+                        .and_then(|p| p.profiles.get(&profile))
+                        .and_then(|p| p.provider.clone()),
+                    None => project.and_then(|p| p.provider.clone()),
+                }
+            }
+            GlobalConfigKey::Provider {
+                project: None,
+                profile: Some(profile),
+            } => self
+                .defaults
+                .profiles
+                .get(&profile)
+                .and_then(|p| p.provider.clone()),
+            GlobalConfigKey::Provider {
+                project: None,
+                profile: None,
+            } => self.defaults.provider.clone(),
+            GlobalConfigKey::Profile {
+                project: Some(name),
+            } => self.projects.get(&name).and_then(|p| p.profile.clone()),
+            GlobalConfigKey::Profile { project: None } => self.defaults.profile.clone(),
+            GlobalConfigKey::Concurrency => self.defaults.concurrency.map(|c| c.to_string()),
+            GlobalConfigKey::CacheTtl => self.defaults.cache_ttl.map(|t| t.to_string()),
+            GlobalConfigKey::RateLimit { provider } => {
+                self.rate_limit_for(&provider).map(|r| r.to_string())
+            }
+            GlobalConfigKey::Namespace {
+                project: Some(name),
+            } => self.projects.get(&name).and_then(|p| p.namespace.clone()),
+            GlobalConfigKey::Namespace { project: None } => self.defaults.namespace.clone(),
+        })
+    }
+
+    /// Sets a setting by its dotted key (e.g. `defaults.profiles.production.provider`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is not supported or the value has the wrong type
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key.parse::<GlobalConfigKey>()? {
+            GlobalConfigKey::Provider { project, profile } => {
+                let provider = Some(value.to_string());
+                match (project, profile) {
+                    (Some(name), Some(profile)) => {
+                        self.projects
+                            .entry(name)
+                            .or_default()
+                            .profiles
+                            .entry(profile)
+                            .or_default()
+                            .provider = provider
+                    }
+                    (Some(name), None) => {
+                        self.projects.entry(name).or_default().provider = provider
+                    }
+                    (None, Some(profile)) => {
+                        self.defaults.profiles.entry(profile).or_default().provider = provider
+                    }
+                    (None, None) => self.defaults.provider = provider,
+                }
+            }
+            GlobalConfigKey::Profile {
+                project: Some(name),
+            } => self.projects.entry(name).or_default().profile = Some(value.to_string()),
+            GlobalConfigKey::Profile { project: None } => {
+                self.defaults.profile = Some(value.to_string())
+            }
+            GlobalConfigKey::Concurrency => {
+                let concurrency =
+                    value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|c| *c > 0)
+                        .ok_or_else(|| {
+                            format!(
+                                "Invalid concurrency '{}': expected a positive integer",
+                                value
+                            )
+                        })?;
+                self.defaults.concurrency = Some(concurrency);
+            }
+            GlobalConfigKey::CacheTtl => {
+                let ttl = value.parse::<u64>().map_err(|_| {
+                    format!(
+                        "Invalid cache_ttl '{}': expected a number of seconds",
+                        value
+                    )
+                })?;
+                self.defaults.cache_ttl = Some(ttl);
+            }
+            GlobalConfigKey::RateLimit { provider } => {
+                let rate = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|r| *r > 0.0 && r.is_finite())
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid rate limit '{}': expected a positive number of requests per second",
+                            value
+                        )
+                    })?;
+                self.defaults.rate_limits.insert(provider, rate);
+            }
+            GlobalConfigKey::Namespace { project } => {
+                if !is_valid_namespace(value) {
+                    return Err(format!(
+                        "Invalid namespace '{}': use letters, digits, '-', '_' and '.', separated by '/'",
+                        value
+                    ));
+                }
+                let namespace = Some(value.to_string());
+                match project {
+                    Some(name) => self.projects.entry(name).or_default().namespace = namespace,
+                    None => self.defaults.namespace = namespace,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lists all settings that are set, as sorted `(key, value)` pairs.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        let mut push = |key: String, value: Option<String>| {
+            if let Some(value) = value {
+                entries.push((key, value));
+            }
+        };
+
+        push("defaults.provider".into(), self.defaults.provider.clone());
+        push("defaults.profile".into(), self.defaults.profile.clone());
+        push(
+            "defaults.concurrency".into(),
+            self.defaults.concurrency.map(|c| c.to_string()),
+        );
+        push(
+            "defaults.cache_ttl".into(),
+            self.defaults.cache_ttl.map(|t| t.to_string()),
+        );
+        push("defaults.namespace".into(), self.defaults.namespace.clone());
+        for (provider, rate) in &self.defaults.rate_limits {
+            push(
+                format!("defaults.rate_limits.{}", provider),
+                Some(rate.to_string()),
+            );
+        }
+        for (profile, settings) in &self.defaults.profiles {
+            push(
+                format!("defaults.profiles.{}.provider", profile),
+                settings.provider.clone(),
+            );
+        }
+        for (project, settings) in &self.projects {
+            push(
+                format!("projects.{}.provider", project),
+                settings.provider.clone(),
+            );
+            push(
+                format!("projects.{}.profile", project),
+                settings.profile.clone(),
+            );
+            push(
+                format!("projects.{}.namespace", project),
+                settings.namespace.clone(),
+            );
+            for (profile, profile_settings) in &settings.profiles {
+                push(
+                    format!("projects.{}.profiles.{}.provider", project, profile),
+                    profile_settings.provider.clone(),
+                );
+            }
+        }
+
+        entries.sort();
+        entries
+    }
+
     /// Gets the path to the global configuration file.
     ///
     /// The configuration file is stored in the system's config directory,
@@ -482,8 +2198,14 @@ pub enum ParseError {
     Io(io::Error),
     /// TOML parsing error
     Toml(toml::de::Error),
-    /// Unsupported configuration revision
+    /// JSON parsing error
+    Json(serde_json::Error),
+    /// YAML parsing error
+    Yaml(serde_yaml::Error),
+    /// Unsupported legacy configuration revision
     UnsupportedRevision(String),
+    /// Unsupported spec schema version
+    UnsupportedSchema(u32),
     /// Circular dependency detected in configuration inheritance
     CircularDependency(String),
     /// Validation error
@@ -495,6 +2217,8 @@ impl std::fmt::Display for ParseError {
         match self {
             ParseError::Io(e) => write!(f, "I/O error: {}", e),
             ParseError::Toml(e) => write!(f, "TOML parsing error: {}", e),
+            ParseError::Json(e) => write!(f, "JSON parsing error: {}", e),
+            ParseError::Yaml(e) => write!(f, "YAML parsing error: {}", e),
             ParseError::UnsupportedRevision(rev) => {
                 write!(
                     f,
@@ -502,6 +2226,13 @@ impl std::fmt::Display for ParseError {
                     rev
                 )
             }
+            ParseError::UnsupportedSchema(schema) => {
+                write!(
+                    f,
+                    "Unsupported schema {}. Schemas up to {} are supported.",
+                    schema, CURRENT_SCHEMA
+                )
+            }
             ParseError::CircularDependency(msg) => {
                 write!(f, "Circular dependency detected: {}", msg)
             }
@@ -515,6 +2246,8 @@ impl std::error::Error for ParseError {
         match self {
             ParseError::Io(e) => Some(e),
             ParseError::Toml(e) => Some(e),
+            ParseError::Json(e) => Some(e),
+            ParseError::Yaml(e) => Some(e),
             _ => None,
         }
     }
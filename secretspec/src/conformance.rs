@@ -0,0 +1,477 @@
+//! Provider conformance suite
+//!
+//! `secretspec test-provider <uri>` runs the same behavioral checks against
+//! any provider, so a new or modified backend can be verified against the
+//! contract the rest of secretspec relies on:
+//!
+//! - Missing secrets are reported as absent rather than as errors
+//! - Values round-trip unchanged, including unicode, whitespace, shell
+//!   metacharacters and values larger than the backend's size limit
+//! - Writing a secret again replaces its value
+//! - Concurrent writes to one secret leave exactly one of the written values
+//! - Deleted secrets are gone and deleting twice succeeds
+//! - Listing returns the stored keys
+//! - Profiles keep separate values
+//!
+//! Checks write to a throwaway project named `secretspec-conformance-<n>` and
+//! delete everything they wrote when the provider supports deletes.
+
+use crate::Result;
+use crate::doctor::Check;
+use crate::provider::Provider;
+use crate::provider::chunked::ChunkedProvider;
+use secrecy::{ExposeSecret, SecretString};
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Prefix of the throwaway project checks write to.
+const PROJECT_PREFIX: &str = "secretspec-conformance";
+
+/// Profile most checks write to.
+const PROFILE: &str = "default";
+
+/// Second profile used to check profile isolation.
+const OTHER_PROFILE: &str = "conformance";
+
+/// Size in bytes of the value written by the large value check.
+const LARGE_VALUE_SIZE: usize = 64 * 1024;
+
+/// Number of threads writing concurrently.
+const WRITERS: usize = 8;
+
+/// Values that must round-trip unchanged.
+const UNICODE_VALUES: &[&str] = &[
+    "héllo wörld",
+    "日本語のテキスト",
+    "emoji 🔐🚀",
+    "multi\nline\nvalue",
+    "quotes \"double\" and 'single'",
+    "shell $HOME `cmd` \\ backslash",
+    "equals=sign=value",
+    "  padded  ",
+];
+
+/// The outcome of a single check: a description of what was verified, or
+/// of what went wrong.
+type Outcome = std::result::Result<String, String>;
+
+/// The conformance checks for one provider.
+pub(crate) struct ConformanceSuite {
+    /// The provider, wrapped for chunking like in regular use
+    provider: Box<dyn Provider>,
+    /// Throwaway project the checks write to
+    project: String,
+}
+
+impl ConformanceSuite {
+    /// Opens the provider named by a provider URI.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI does not name a valid provider
+    pub fn open(spec: &str) -> Result<Self> {
+        Ok(Self::new(Box::<dyn Provider>::try_from(spec)?))
+    }
+
+    /// Creates a suite for an already constructed provider.
+    pub fn new(provider: Box<dyn Provider>) -> Self {
+        let suffix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_micros() % 1_000_000)
+            .unwrap_or_default();
+        Self {
+            provider: ChunkedProvider::wrap(provider),
+            project: format!("{}-{}", PROJECT_PREFIX, suffix),
+        }
+    }
+
+    /// Returns the name of the provider under test.
+    pub fn name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Returns the project the checks write to.
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+
+    /// Runs every check and returns the findings in order.
+    ///
+    /// Checks that need a capability the provider lacks, like writes or
+    /// deletes, are reported as warnings rather than failures.
+    pub fn run(&self) -> Vec<Check> {
+        let mut checks = vec![to_check("Missing secrets", self.check_missing())];
+
+        if !self.provider.allows_set() {
+            checks.push(skipped("Writes", "provider is read-only"));
+            return checks;
+        }
+
+        checks.push(to_check("Round trip", self.check_round_trip()));
+        checks.push(to_check("Overwrite", self.check_overwrite()));
+        checks.push(to_check("Unicode values", self.check_unicode()));
+        checks.push(to_check("Large values", self.check_large_value()));
+        checks.push(to_check(
+            "Concurrent writes",
+            self.check_concurrent_writes(),
+        ));
+        checks.push(self.check_profile_isolation());
+
+        if self.provider.allows_delete() {
+            checks.push(to_check("Delete", self.check_delete()));
+        } else {
+            checks.push(skipped("Delete", "provider does not support deletes"));
+        }
+
+        if self.provider.allows_list() {
+            checks.push(to_check("List", self.check_list()));
+        } else {
+            checks.push(skipped("List", "provider does not support listing"));
+        }
+
+        checks.push(self.cleanup());
+        checks
+    }
+
+    /// Returns the key used by a check.
+    fn key(name: &str) -> String {
+        format!("SECRETSPEC_CONFORMANCE_{}", name)
+    }
+
+    fn set(&self, key: &str, value: &str, profile: &str) -> Outcome {
+        self.provider
+            .set(
+                &self.project,
+                key,
+                &SecretString::new(value.to_string().into()),
+                profile,
+            )
+            .map(|()| String::new())
+            .map_err(|e| format!("storing {} failed: {}", key, e))
+    }
+
+    fn get(&self, key: &str, profile: &str) -> std::result::Result<Option<String>, String> {
+        self.provider
+            .get(&self.project, key, profile)
+            .map(|value| value.map(|v| v.expose_secret().to_string()))
+            .map_err(|e| format!("reading {} failed: {}", key, e))
+    }
+
+    /// Checks that a stored value matches the expected one.
+    fn expect(&self, key: &str, profile: &str, expected: &str) -> Outcome {
+        match self.get(key, profile)? {
+            Some(actual) if actual == expected => Ok(String::new()),
+            Some(actual) => Err(format!(
+                "{} read back as {} instead of {}",
+                key,
+                describe(&actual),
+                describe(expected)
+            )),
+            None => Err(format!("{} was not found after it was stored", key)),
+        }
+    }
+
+    fn check_missing(&self) -> Outcome {
+        match self.get(&Self::key("NEVER_STORED"), PROFILE)? {
+            None => Ok("Unknown keys are reported as absent".to_string()),
+            Some(_) => Err("A key that was never stored returned a value".to_string()),
+        }
+    }
+
+    fn check_round_trip(&self) -> Outcome {
+        let key = Self::key("ROUND_TRIP");
+        self.set(&key, "conformance-value", PROFILE)?;
+        self.expect(&key, PROFILE, "conformance-value")?;
+        Ok("Stored values are read back unchanged".to_string())
+    }
+
+    fn check_overwrite(&self) -> Outcome {
+        let key = Self::key("OVERWRITE");
+        self.set(&key, "first", PROFILE)?;
+        self.set(&key, "second", PROFILE)?;
+        self.expect(&key, PROFILE, "second")?;
+        Ok("Writing again replaces the value".to_string())
+    }
+
+    fn check_unicode(&self) -> Outcome {
+        for (index, value) in UNICODE_VALUES.iter().enumerate() {
+            let key = Self::key(&format!("UNICODE_{}", index));
+            self.set(&key, value, PROFILE)?;
+            self.expect(&key, PROFILE, value)?;
+        }
+        Ok(format!(
+            "{} values with unicode, whitespace and special characters round-trip",
+            UNICODE_VALUES.len()
+        ))
+    }
+
+    fn check_large_value(&self) -> Outcome {
+        let key = Self::key("LARGE");
+        let value: String = "0123456789abcdef"
+            .chars()
+            .cycle()
+            .take(LARGE_VALUE_SIZE)
+            .collect();
+        self.set(&key, &value, PROFILE)?;
+        self.expect(&key, PROFILE, &value)?;
+        Ok(format!(
+            "A {} KiB value round-trips",
+            LARGE_VALUE_SIZE / 1024
+        ))
+    }
+
+    fn check_concurrent_writes(&self) -> Outcome {
+        let key = Self::key("CONCURRENT");
+        // Values have equal lengths so partial writes cannot hide behind a
+        // longer value that happens to share a prefix
+        let values: Vec<String> = (0..WRITERS).map(|i| format!("writer-{:02}", i)).collect();
+
+        std::thread::scope(|scope| {
+            let writers: Vec<_> = values
+                .iter()
+                .map(|value| scope.spawn(|| self.set(&key, value, PROFILE)))
+                .collect();
+            writers
+                .into_iter()
+                .map(|writer| {
+                    writer
+                        .join()
+                        .unwrap_or_else(|_| Err("writer panicked".into()))
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()
+        })?;
+
+        match self.get(&key, PROFILE)? {
+            Some(value) if values.contains(&value) => Ok(format!(
+                "{} concurrent writes leave one of the written values",
+                WRITERS
+            )),
+            Some(value) => Err(format!(
+                "After {} concurrent writes {} holds {}, which no writer stored",
+                WRITERS,
+                key,
+                describe(&value)
+            )),
+            None => Err(format!(
+                "After {} concurrent writes {} is missing",
+                WRITERS, key
+            )),
+        }
+    }
+
+    /// Checks that profiles keep separate values.
+    ///
+    /// Providers without profile namespacing, like dotenv files, share values
+    /// across profiles by design, so this is a warning rather than a failure.
+    fn check_profile_isolation(&self) -> Check {
+        const NAME: &str = "Profile isolation";
+        let key = Self::key("PROFILE");
+        let outcome = self
+            .set(&key, "default-value", PROFILE)
+            .and_then(|_| self.set(&key, "other-value", OTHER_PROFILE))
+            .and_then(|_| self.get(&key, PROFILE));
+
+        match outcome {
+            Ok(Some(value)) if value == "default-value" => {
+                Check::pass(NAME, "Each profile keeps its own value")
+            }
+            Ok(Some(value)) if value == "other-value" => Check::warn(
+                NAME,
+                "Profiles share values",
+                Some("Use a separate provider URI for each profile".to_string()),
+            ),
+            Ok(value) => Check::fail(
+                NAME,
+                format!(
+                    "{} in profile {} holds {} after writing another profile",
+                    key,
+                    PROFILE,
+                    value.as_deref().map_or("nothing".to_string(), describe)
+                ),
+                None,
+            ),
+            Err(e) => Check::fail(NAME, e, None),
+        }
+    }
+
+    fn check_delete(&self) -> Outcome {
+        let key = Self::key("DELETE");
+        self.set(&key, "to-be-deleted", PROFILE)?;
+        self.delete(&key, PROFILE)?;
+        if self.get(&key, PROFILE)?.is_some() {
+            return Err(format!("{} still has a value after it was deleted", key));
+        }
+        self.delete(&key, PROFILE)
+            .map_err(|e| format!("Deleting a missing secret failed: {}", e))?;
+        Ok("Deleted secrets are gone and deleting twice succeeds".to_string())
+    }
+
+    fn check_list(&self) -> Outcome {
+        let keys = [Self::key("LIST_A"), Self::key("LIST_B")];
+        for key in &keys {
+            self.set(key, "listed", PROFILE)?;
+        }
+
+        let listed = self.list()?;
+        if let Some(missing) = keys.iter().find(|key| !listed.contains(key)) {
+            return Err(format!("{} is stored but was not listed", missing));
+        }
+
+        if self.provider.allows_delete() {
+            self.delete(&keys[0], PROFILE)?;
+            if self.list()?.contains(&keys[0]) {
+                return Err(format!("{} is still listed after it was deleted", keys[0]));
+            }
+        }
+        Ok("Stored keys are listed".to_string())
+    }
+
+    fn delete(&self, key: &str, profile: &str) -> Outcome {
+        self.provider
+            .delete(&self.project, key, profile)
+            .map(|()| String::new())
+            .map_err(|e| format!("deleting {} failed: {}", key, e))
+    }
+
+    fn list(&self) -> std::result::Result<Vec<String>, String> {
+        self.provider
+            .list_keys(&self.project, PROFILE)
+            .map_err(|e| format!("listing keys failed: {}", e))
+    }
+
+    /// Deletes every secret the checks may have written.
+    fn cleanup(&self) -> Check {
+        const NAME: &str = "Cleanup";
+        if !self.provider.allows_delete() {
+            return Check::warn(
+                NAME,
+                format!("Test secrets remain in project {}", self.project),
+                Some("Remove the SECRETSPEC_CONFORMANCE_* entries manually".to_string()),
+            );
+        }
+
+        let mut keys: Vec<String> = [
+            "ROUND_TRIP",
+            "OVERWRITE",
+            "LARGE",
+            "CONCURRENT",
+            "PROFILE",
+            "DELETE",
+            "LIST_A",
+            "LIST_B",
+        ]
+        .iter()
+        .map(|name| Self::key(name))
+        .collect();
+        keys.extend((0..UNICODE_VALUES.len()).map(|i| Self::key(&format!("UNICODE_{}", i))));
+
+        let failed: Vec<String> = keys
+            .iter()
+            .flat_map(|key| [(key, PROFILE), (key, OTHER_PROFILE)])
+            .filter_map(|(key, profile)| self.delete(key, profile).err())
+            .collect();
+        if failed.is_empty() {
+            Check::pass(NAME, "Test secrets were deleted")
+        } else {
+            Check::warn(
+                NAME,
+                failed.join("; "),
+                Some(format!(
+                    "Remove the remaining entries of project {} manually",
+                    self.project
+                )),
+            )
+        }
+    }
+}
+
+/// Converts an outcome into a check.
+fn to_check(name: &str, outcome: Outcome) -> Check {
+    match outcome {
+        Ok(detail) => Check::pass(name, detail),
+        Err(detail) => Check::fail(name, detail, None),
+    }
+}
+
+/// Returns the warning for a check the provider cannot run.
+fn skipped(name: &str, reason: &str) -> Check {
+    Check::warn(name, format!("Skipped: {}", reason), None)
+}
+
+/// Describes a value for an error message without printing large values in full.
+fn describe(value: &str) -> String {
+    const MAX_CHARS: usize = 40;
+    if value.chars().count() <= MAX_CHARS {
+        format!("{:?}", value)
+    } else {
+        let head: String = value.chars().take(MAX_CHARS).collect();
+        format!("{:?}... ({} bytes)", head, value.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::Status;
+    use crate::provider::tests::MockProvider;
+
+    /// Provider that trims values and ignores profiles, like a careless backend.
+    struct SloppyProvider(MockProvider);
+
+    impl Provider for SloppyProvider {
+        fn name(&self) -> &'static str {
+            "sloppy"
+        }
+
+        fn get(&self, project: &str, key: &str, _profile: &str) -> Result<Option<SecretString>> {
+            self.0.get(project, key, PROFILE)
+        }
+
+        fn set(
+            &self,
+            project: &str,
+            key: &str,
+            value: &SecretString,
+            _profile: &str,
+        ) -> Result<()> {
+            let trimmed = value.expose_secret().trim().to_string();
+            self.0
+                .set(project, key, &SecretString::new(trimmed.into()), PROFILE)
+        }
+    }
+
+    fn status(checks: &[Check], name: &str) -> Status {
+        checks
+            .iter()
+            .find(|check| check.name == name)
+            .unwrap_or_else(|| panic!("no {} check", name))
+            .status
+    }
+
+    #[test]
+    fn test_conforming_provider_passes() {
+        let checks = ConformanceSuite::new(Box::new(MockProvider::new())).run();
+        for check in &checks {
+            assert_eq!(
+                check.status,
+                Status::Pass,
+                "{}: {}",
+                check.name,
+                check.detail
+            );
+        }
+        assert_eq!(checks.len(), 10);
+    }
+
+    #[test]
+    fn test_nonconforming_provider_is_reported() {
+        let checks = ConformanceSuite::new(Box::new(SloppyProvider(MockProvider::new()))).run();
+
+        assert_eq!(status(&checks, "Round trip"), Status::Pass);
+        assert_eq!(status(&checks, "Unicode values"), Status::Fail);
+        assert_eq!(status(&checks, "Profile isolation"), Status::Warn);
+        assert_eq!(status(&checks, "Delete"), Status::Warn);
+        assert_eq!(status(&checks, "List"), Status::Warn);
+        assert_eq!(status(&checks, "Cleanup"), Status::Warn);
+    }
+}
@@ -0,0 +1,76 @@
+//! Integration with devenv.sh and dev containers
+//!
+//! `secretspec devenv export` prints the resolved secrets in the formats
+//! these environments load, and `secretspec devenv hook` prints the glue
+//! that runs it:
+//!
+//! - devenv shells evaluate `export` statements in `enterShell`
+//! - Dev containers read a Docker env file passed with `--env-file`, written
+//!   on the host by `initializeCommand` before the container starts
+//!
+//! Values are only ever printed to stdout. The hooks keep them out of the
+//! Nix store and out of `devcontainer.json`.
+
+use std::str::FromStr;
+
+/// An environment a hook loads the secrets into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookTarget {
+    /// A devenv.sh shell, through `enterShell` in `devenv.nix`
+    Devenv,
+    /// A dev container, through `initializeCommand` and `--env-file`
+    Devcontainer,
+}
+
+impl FromStr for HookTarget {
+    type Err = String;
+
+    fn from_str(target: &str) -> std::result::Result<Self, Self::Err> {
+        match target {
+            "devenv" => Ok(HookTarget::Devenv),
+            "devcontainer" => Ok(HookTarget::Devcontainer),
+            _ => Err(format!(
+                "Unknown hook target '{}'. Use devenv or devcontainer.",
+                target
+            )),
+        }
+    }
+}
+
+/// Hook for `devenv.nix`, evaluating the exported secrets in the shell.
+const DEVENV_HOOK: &str = r#"# devenv.nix: loads the secrets from secretspec when entering the shell
+{ pkgs, ... }:
+
+{
+  packages = [ pkgs.secretspec ];
+
+  enterShell = ''
+    eval "$(secretspec devenv export --format shell)"
+  '';
+}
+"#;
+
+/// Hook for dev containers, writing a Docker env file on the host.
+const DEVCONTAINER_HOOK: &str = r#"#!/bin/sh
+# .devcontainer/secretspec.sh: writes the secrets from secretspec to
+# .devcontainer/secretspec.env on the host before the container starts.
+#
+# In .devcontainer/devcontainer.json:
+#
+#   "initializeCommand": "sh .devcontainer/secretspec.sh",
+#   "runArgs": ["--env-file", ".devcontainer/secretspec.env"]
+#
+# Add .devcontainer/secretspec.env to .gitignore.
+set -eu
+cd "$(dirname "$0")/.."
+umask 077
+secretspec devenv export --format docker > .devcontainer/secretspec.env
+"#;
+
+/// Returns the hook loading the secrets into `target`.
+pub(crate) fn hook(target: HookTarget) -> &'static str {
+    match target {
+        HookTarget::Devenv => DEVENV_HOOK,
+        HookTarget::Devcontainer => DEVCONTAINER_HOOK,
+    }
+}
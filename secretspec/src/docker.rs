@@ -0,0 +1,169 @@
+//! Docker credential helper protocol
+//!
+//! Docker asks a credential helper for registry logins instead of keeping
+//! them base64-encoded in `~/.docker/config.json`. The helper is a program
+//! named `docker-credential-<name>`, so secretspec acts as one when started
+//! through a link of that name:
+//!
+//! ```bash
+//! ln -s "$(command -v secretspec)" ~/.local/bin/docker-credential-secretspec
+//! ```
+//!
+//! ```json
+//! { "credsStore": "secretspec" }
+//! ```
+//!
+//! Docker runs the helper with one of `get`, `store`, `erase` or `list` and
+//! talks to it over stdin and stdout. Logins aren't part of any project, so
+//! they are kept in the provider under the project `docker` and the profile
+//! `default`, one secret per registry holding the server URL, username and
+//! secret as JSON.
+
+use crate::config::GlobalConfig;
+use crate::provider::Provider;
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use zeroize::Zeroizing;
+
+/// Project the logins are stored under.
+const PROJECT: &str = "docker";
+
+/// Profile the logins are stored under.
+const PROFILE: &str = "default";
+
+/// Prefix of the secrets holding logins.
+const KEY_PREFIX: &str = "DOCKER_";
+
+/// Message Docker recognizes as a registry without a login.
+pub(crate) const NOT_FOUND: &str = "credentials not found in native keychain";
+
+/// A registry login, in the shape Docker reads and writes.
+#[derive(Serialize, Deserialize)]
+struct Credentials {
+    #[serde(rename = "ServerURL")]
+    server_url: String,
+    #[serde(rename = "Username")]
+    username: String,
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+impl Drop for Credentials {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.secret);
+    }
+}
+
+/// Returns the provider logins are kept in: the one given, or the default
+/// one of the global configuration.
+///
+/// # Errors
+///
+/// Returns an error if no provider is configured or it can't be created
+pub(crate) fn provider(provider_arg: Option<String>) -> Result<Box<dyn Provider>> {
+    let global_config = GlobalConfig::load()?;
+    let spec = provider_arg
+        .or_else(|| {
+            global_config
+                .as_ref()
+                .and_then(|gc| gc.default_provider_for(PROFILE))
+                .map(str::to_string)
+        })
+        .ok_or(SecretSpecError::NoProviderConfigured)?;
+    Box::<dyn Provider>::try_from(spec)
+}
+
+/// Returns the secret a registry's login is stored in: its host in capitals
+/// followed by part of the hash of the server URL, so registries whose
+/// hosts only differ in punctuation don't collide.
+fn key(server_url: &str) -> String {
+    let host = server_url
+        .split_once("://")
+        .map_or(server_url, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    let host: String = host
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let digest = Sha256::digest(server_url.as_bytes());
+    let hash: String = digest[..4].iter().map(|b| format!("{:02X}", b)).collect();
+    format!("{}{}_{}", KEY_PREFIX, host, hash)
+}
+
+/// Runs a helper action, reading its input from `input` and returning what
+/// it prints.
+///
+/// # Errors
+///
+/// Returns [`SecretSpecError::SecretNotFound`] with [`NOT_FOUND`] if `get`
+/// finds no login, and an error for unknown actions, malformed input or
+/// provider failures
+pub(crate) fn run(provider: &dyn Provider, action: &str, input: &str) -> Result<Zeroizing<String>> {
+    match action {
+        "get" => {
+            let server_url = input.trim();
+            let stored = provider
+                .get(PROJECT, &key(server_url), PROFILE)?
+                .ok_or_else(|| SecretSpecError::SecretNotFound(NOT_FOUND.to_string()))?;
+            // Parsed, so a secret written by something else isn't handed to Docker
+            let credentials: Credentials = serde_json::from_str(stored.expose_secret())?;
+            Ok(Zeroizing::new(serde_json::to_string(&credentials)?))
+        }
+        "store" => {
+            let credentials: Credentials = serde_json::from_str(input).map_err(|e| {
+                SecretSpecError::InvalidInput(format!("Malformed credentials from Docker: {}", e))
+            })?;
+            let value = SecretString::from(serde_json::to_string(&credentials)?);
+            provider.set(PROJECT, &key(&credentials.server_url), &value, PROFILE)?;
+            Ok(Zeroizing::new(String::new()))
+        }
+        "erase" => {
+            provider.delete(PROJECT, &key(input.trim()), PROFILE)?;
+            Ok(Zeroizing::new(String::new()))
+        }
+        "list" => {
+            let mut logins = BTreeMap::new();
+            if provider.allows_list() {
+                for key in provider.list_keys(PROJECT, PROFILE)? {
+                    if !key.starts_with(KEY_PREFIX) {
+                        continue;
+                    }
+                    if let Some(stored) = provider.get(PROJECT, &key, PROFILE)? {
+                        let credentials: Credentials =
+                            serde_json::from_str(stored.expose_secret())?;
+                        logins.insert(credentials.server_url.clone(), credentials.username.clone());
+                    }
+                }
+            }
+            Ok(Zeroizing::new(serde_json::to_string(&logins)?))
+        }
+        _ => Err(SecretSpecError::InvalidInput(format!(
+            "Unknown credential helper action '{}'. Use get, store, erase or list.",
+            action
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert!(key("https://index.docker.io/v1/").starts_with("DOCKER_INDEX_DOCKER_IO_"));
+        assert!(key("ghcr.io").starts_with("DOCKER_GHCR_IO_"));
+        assert_ne!(key("a-b.io"), key("a.b.io"));
+        assert_eq!(key("ghcr.io"), key("ghcr.io"));
+    }
+}
@@ -0,0 +1,374 @@
+//! Environment diagnostics for `secretspec doctor`
+//!
+//! The doctor inspects everything secretspec depends on and reports each
+//! finding as a [`Check`] with a pass/warn/fail status and, where possible, a
+//! suggested fix:
+//!
+//! - `secretspec.toml` parses and uses the current schema
+//! - A provider is configured and can be constructed
+//! - Provider-specific checks from [`Provider::doctor`], such as CLI presence
+//!   and version, authentication state, and dotenv file permissions
+//! - The system keyring is available
+//! - The system clock is close enough to network time for TOTP codes
+
+use crate::config::{self, Config, GlobalConfig, SpecFormat};
+use crate::error::{Result, SecretSpecError};
+use crate::provider::Provider;
+use crate::secrets::Secrets;
+use std::fs;
+use std::io;
+use std::net::UdpSocket;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// NTP server queried for the clock skew check.
+const NTP_SERVER: &str = "pool.ntp.org:123";
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970).
+const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+
+/// Skew in seconds above which TOTP codes are likely to be rejected.
+const MAX_CLOCK_SKEW: f64 = 30.0;
+
+/// Skew in seconds above which a warning is shown.
+const WARN_CLOCK_SKEW: f64 = 5.0;
+
+/// The outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Everything is in order
+    Pass,
+    /// Works, but something should be looked at
+    Warn,
+    /// Broken; secretspec will not work until this is fixed
+    Fail,
+}
+
+/// A single diagnostic finding.
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// Short name of what was checked
+    pub name: String,
+    /// The outcome
+    pub status: Status,
+    /// What was found
+    pub detail: String,
+    /// How to fix a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    /// Creates a passing check.
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    /// Creates a warning with an optional fix suggestion.
+    pub fn warn(name: impl Into<String>, detail: impl Into<String>, fix: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Warn,
+            detail: detail.into(),
+            fix,
+        }
+    }
+
+    /// Creates a failure with an optional fix suggestion.
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>, fix: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Fail,
+            detail: detail.into(),
+            fix,
+        }
+    }
+
+    /// Creates a failure from an error.
+    ///
+    /// Provider errors put a one-line summary first and installation or login
+    /// instructions after a blank line; the instructions become the fix.
+    pub fn from_error(name: impl Into<String>, error: &SecretSpecError) -> Self {
+        let message = match error {
+            SecretSpecError::ProviderOperationFailed(msg) => msg.clone(),
+            other => other.to_string(),
+        };
+        let (detail, fix) = match message.split_once("\n\n") {
+            Some((detail, fix)) => (detail.trim().to_string(), Some(fix.trim().to_string())),
+            None => (message.trim().to_string(), None),
+        };
+        Self::fail(name, detail, fix)
+    }
+}
+
+/// Runs all checks for the current directory.
+///
+/// # Arguments
+///
+/// * `provider` - Provider override, as passed to `--provider`
+/// * `profile` - Profile override, as passed to `--profile`
+pub fn run(provider: Option<String>, profile: Option<String>) -> Vec<Check> {
+    let mut checks = vec![match SpecFormat::find(Path::new(".")) {
+        Ok(path) => check_spec(&path),
+        Err(e) => Check::fail("secretspec.toml", e.to_string(), None),
+    }];
+
+    let global_config = match GlobalConfig::load() {
+        Ok(Some(global_config)) => {
+            checks.push(Check::pass("Global config", "Loaded user configuration"));
+            Some(global_config)
+        }
+        Ok(None) => {
+            checks.push(Check::warn(
+                "Global config",
+                "No user configuration found",
+                Some("Run 'secretspec config init' to choose a default provider".to_string()),
+            ));
+            None
+        }
+        Err(e) => {
+            checks.push(Check::fail(
+                "Global config",
+                e.to_string(),
+                Some("Fix or remove ~/.config/secretspec/config.toml".to_string()),
+            ));
+            None
+        }
+    };
+
+    // Resolve the provider the same way other commands do when the spec
+    // loads, falling back to the global default otherwise
+    let provider_spec = match Secrets::load() {
+        Ok(mut secrets) => {
+            if let Some(p) = profile {
+                secrets.set_profile(p);
+            }
+            secrets.provider_spec(provider).ok()
+        }
+        Err(_) => provider
+            .or_else(|| std::env::var("SECRETSPEC_PROVIDER").ok())
+            .or_else(|| global_config.and_then(|gc| gc.defaults.provider)),
+    };
+
+    let provider_name = match provider_spec {
+        None => {
+            checks.push(Check::from_error(
+                "Provider",
+                &SecretSpecError::NoProviderConfigured,
+            ));
+            None
+        }
+        Some(spec) => match Box::<dyn Provider>::try_from(spec.as_str()) {
+            Ok(backend) => {
+                checks.push(Check::pass("Provider", format!("Using {}", spec)));
+                checks.extend(backend.doctor());
+                Some(backend.name())
+            }
+            Err(e) => {
+                checks.push(Check::from_error("Provider", &e));
+                None
+            }
+        },
+    };
+
+    // The keyring is the recommended default, so report on it even when
+    // another provider is configured
+    #[cfg(feature = "keyring")]
+    if provider_name != Some("keyring")
+        && let Ok(keyring) = Box::<dyn Provider>::try_from("keyring")
+    {
+        checks.extend(keyring.doctor());
+    }
+    #[cfg(not(feature = "keyring"))]
+    let _ = provider_name;
+
+    checks.push(check_clock());
+    checks
+}
+
+/// Checks that the spec parses and uses the current schema.
+fn check_spec(path: &Path) -> Check {
+    const NAME: &str = "secretspec.toml";
+
+    if !path.exists() {
+        return Check::fail(
+            NAME,
+            "No secretspec.toml found in current directory",
+            Some("Run 'secretspec init' to create one".to_string()),
+        );
+    }
+
+    let config = match Config::try_from(path) {
+        Ok(config) => config,
+        Err(e) => return Check::fail(NAME, e.to_string(), None),
+    };
+    if let Some(problem) = config.key_problems().into_iter().next() {
+        return if config.is_strict() {
+            Check::fail(NAME, problem, None)
+        } else {
+            Check::warn(NAME, problem, None)
+        };
+    }
+
+    match fs::read_to_string(path).map(|content| config::migrate(&content)) {
+        Ok(Ok(Some(_))) => Check::warn(
+            NAME,
+            "Uses an older schema",
+            Some("Run 'secretspec migrate' to upgrade it".to_string()),
+        ),
+        _ => Check::pass(NAME, format!("Valid (schema {})", config::CURRENT_SCHEMA)),
+    }
+}
+
+/// Reports whether a provider CLI is installed, given the output of `--version`.
+///
+/// # Arguments
+///
+/// * `name` - The check name, e.g. "OnePassword CLI"
+/// * `version` - The result of running the CLI with `--version`
+pub(crate) fn check_cli(name: &str, version: Result<impl AsRef<str>>) -> Check {
+    match version {
+        Ok(output) => Check::pass(name, output.as_ref().lines().next().unwrap_or("").trim()),
+        Err(e) => Check::from_error(name, &e),
+    }
+}
+
+/// Checks that the system clock is close to network time.
+///
+/// TOTP codes are only valid for 30 seconds, so a skewed clock makes
+/// one-time passwords fail in confusing ways.
+fn check_clock() -> Check {
+    const NAME: &str = "Clock skew";
+
+    match clock_skew() {
+        Ok(skew) if skew.abs() > MAX_CLOCK_SKEW => Check::fail(
+            NAME,
+            format!(
+                "System clock is off by {:.1}s; TOTP codes will be rejected",
+                skew
+            ),
+            Some("Enable network time synchronization (e.g., 'timedatectl set-ntp true')".into()),
+        ),
+        Ok(skew) if skew.abs() > WARN_CLOCK_SKEW => Check::warn(
+            NAME,
+            format!("System clock is off by {:.1}s", skew),
+            Some("Enable network time synchronization (e.g., 'timedatectl set-ntp true')".into()),
+        ),
+        Ok(skew) => Check::pass(NAME, format!("System clock is within {:.1}s", skew.abs())),
+        Err(e) => Check::warn(NAME, format!("Could not reach {}: {}", NTP_SERVER, e), None),
+    }
+}
+
+/// Queries an NTP server and returns `server time - local time` in seconds.
+fn clock_skew() -> io::Result<f64> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(Duration::from_secs(3)))?;
+    socket.connect(NTP_SERVER)?;
+
+    // LI = 0, version = 3, mode = 3 (client)
+    let mut request = [0u8; 48];
+    request[0] = 0x1b;
+
+    let sent = unix_seconds(SystemTime::now());
+    socket.send(&request)?;
+    let mut response = [0u8; 48];
+    socket.recv(&mut response)?;
+    let received = unix_seconds(SystemTime::now());
+
+    let server = parse_ntp_timestamp(&response[40..48]);
+    Ok(server - (sent + received) / 2.0)
+}
+
+/// Converts an NTP timestamp (seconds and fraction since 1900) to Unix seconds.
+fn parse_ntp_timestamp(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as f64;
+    seconds + fraction / 4_294_967_296.0 - NTP_UNIX_OFFSET
+}
+
+/// Returns the given time as fractional seconds since the Unix epoch.
+fn unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_error_splits_fix() {
+        let error = SecretSpecError::ProviderOperationFailed(
+            "LastPass CLI (lpass) is not installed.\n\nTo install it:\n  - macOS: brew install lastpass-cli".to_string(),
+        );
+        let check = Check::from_error("LastPass CLI", &error);
+        assert_eq!(check.status, Status::Fail);
+        assert_eq!(check.detail, "LastPass CLI (lpass) is not installed.");
+        assert!(check.fix.unwrap().starts_with("To install it:"));
+    }
+
+    #[test]
+    fn test_parse_ntp_timestamp() {
+        // 2024-01-01T00:00:00Z plus half a second
+        let seconds = (1_704_067_200u64 + 2_208_988_800) as u32;
+        let mut bytes = seconds.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&0x8000_0000u32.to_be_bytes());
+        assert_eq!(parse_ntp_timestamp(&bytes), 1_704_067_200.5);
+    }
+
+    #[test]
+    fn test_check_cli() {
+        let check = check_cli("Test CLI", Ok("2.30.0\n".to_string()));
+        assert_eq!(check.status, Status::Pass);
+        assert_eq!(check.detail, "2.30.0");
+
+        let error = SecretSpecError::ProviderOperationFailed("boom".to_string());
+        assert_eq!(
+            check_cli("Test CLI", Err::<String, _>(error)).status,
+            Status::Fail
+        );
+    }
+
+    #[test]
+    fn test_check_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secretspec.toml");
+        assert_eq!(check_spec(&path).status, Status::Fail);
+
+        fs::write(
+            &path,
+            "[project]\nname = \"test\"\nrevision = \"1.0\"\n\n[profiles.default]\nA = { description = \"A\" }\n",
+        )
+        .unwrap();
+        assert_eq!(check_spec(&path).status, Status::Warn);
+
+        fs::write(
+            &path,
+            "[project]\nname = \"test\"\nschema = 2\n\n[profiles.default]\nA = { description = \"A\" }\n",
+        )
+        .unwrap();
+        assert_eq!(check_spec(&path).status, Status::Warn);
+
+        fs::write(
+            &path,
+            "[project]\nname = \"test\"\nschema = 3\n\n[profiles.default]\nA = { description = \"A\" }\n",
+        )
+        .unwrap();
+        assert_eq!(check_spec(&path).status, Status::Pass);
+
+        // Secret names are expected in uppercase
+        fs::write(
+            &path,
+            "[project]\nname = \"test\"\nschema = 3\n\n[profiles.default]\napi_key = { description = \"A\" }\n",
+        )
+        .unwrap();
+        let check = check_spec(&path);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.detail.contains("rename it to API_KEY"));
+    }
+}
@@ -0,0 +1,238 @@
+//! Comparing a process environment with the spec
+//!
+//! A process keeps the environment it was started with, so after a secret
+//! is rotated a long-running app still holds the old value, and variables
+//! left over from an old shell or deploy script linger next to the
+//! declared ones. `secretspec env-audit` reads the environment of a process
+//! and compares it with the resolved secrets, naming the variables that are
+//! stale, missing or look like undeclared secrets. Values are only compared,
+//! never printed.
+
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use zeroize::Zeroizing;
+
+/// Parts of variable names that suggest a secret, like `KEY` in `API_KEY`.
+const SECRET_WORDS: [&str; 13] = [
+    "KEY",
+    "KEYS",
+    "APIKEY",
+    "TOKEN",
+    "TOKENS",
+    "SECRET",
+    "SECRETS",
+    "PASSWORD",
+    "PASSWD",
+    "PASS",
+    "CREDENTIAL",
+    "CREDENTIALS",
+    "PRIVATE",
+];
+
+/// Last parts of variable names that point at secrets rather than hold
+/// them, like `AWS_SHARED_CREDENTIALS_FILE`, or name them, like
+/// `AWS_ACCESS_KEY_ID`.
+const REFERENCE_WORDS: [&str; 5] = ["FILE", "PATH", "DIR", "SOCK", "ID"];
+
+/// How a variable of a process environment compares with the spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvFinding {
+    /// A declared secret set to its resolved value
+    Current(String),
+    /// A declared secret set to another value than the resolved one
+    Stale(String),
+    /// A declared secret the environment doesn't set
+    Missing(String),
+    /// An undeclared variable that looks like a secret, with the declared
+    /// secret whose value it holds, if any
+    Extraneous {
+        name: String,
+        copy_of: Option<String>,
+    },
+}
+
+/// Reads the environment of the process `pid` from `/proc`.
+///
+/// # Errors
+///
+/// Returns an error if the process doesn't exist, belongs to another user
+/// or the platform has no `/proc`
+pub(crate) fn process_env(pid: u32) -> Result<HashMap<String, Zeroizing<String>>> {
+    let path = format!("/proc/{}/environ", pid);
+    let environ = match fs::read(&path) {
+        Ok(environ) => Zeroizing::new(environ),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(SecretSpecError::NotFound(format!(
+                "No process {}, or its environment can't be read on this platform",
+                pid
+            )));
+        }
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            return Err(SecretSpecError::PermissionDenied(format!(
+                "Can't read the environment of process {}; run as its user",
+                pid
+            )));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(parse_environ(&environ))
+}
+
+/// Parses the NUL-separated `NAME=value` entries of `/proc/<pid>/environ`.
+fn parse_environ(environ: &[u8]) -> HashMap<String, Zeroizing<String>> {
+    environ
+        .split(|byte| *byte == 0)
+        .filter_map(|entry| {
+            let entry = String::from_utf8_lossy(entry);
+            let (name, value) = entry.split_once('=')?;
+            Some((name.to_string(), Zeroizing::new(value.to_string())))
+        })
+        .collect()
+}
+
+/// Returns whether a variable name looks like it holds a secret.
+fn looks_secret(name: &str) -> bool {
+    if name.starts_with("SECRETSPEC_") {
+        return false;
+    }
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_uppercase)
+        .collect();
+    if words
+        .last()
+        .is_some_and(|last| REFERENCE_WORDS.contains(&last.as_str()))
+    {
+        return false;
+    }
+    words
+        .iter()
+        .any(|word| SECRET_WORDS.contains(&word.as_str()))
+}
+
+/// Compares an environment with the resolved secrets of the spec.
+///
+/// `declared` are the secrets the spec declares for the profile, and
+/// `resolved` the values they resolved to. Declared secrets come first,
+/// sorted by name, followed by the extraneous variables.
+pub(crate) fn audit(
+    env: &HashMap<String, Zeroizing<String>>,
+    declared: &BTreeSet<String>,
+    resolved: &HashMap<String, SecretString>,
+) -> Vec<EnvFinding> {
+    let mut findings: Vec<EnvFinding> = declared
+        .iter()
+        .map(|name| match (env.get(name), resolved.get(name)) {
+            (None, _) => EnvFinding::Missing(name.clone()),
+            (Some(value), Some(secret)) if value.as_str() != secret.expose_secret() => {
+                EnvFinding::Stale(name.clone())
+            }
+            (Some(_), _) => EnvFinding::Current(name.clone()),
+        })
+        .collect();
+
+    // Sorted so the owner of a value shared by several secrets is stable
+    let owners: BTreeMap<&str, &str> = resolved
+        .iter()
+        .filter(|(_, secret)| !secret.expose_secret().is_empty())
+        .map(|(name, secret)| (name.as_str(), secret.expose_secret()))
+        .collect();
+    let mut extraneous: Vec<EnvFinding> = env
+        .iter()
+        .filter(|(name, _)| !declared.contains(*name))
+        .filter_map(|(name, value)| {
+            let copy_of = owners
+                .iter()
+                .find(|(_, secret)| **secret == value.as_str())
+                .map(|(owner, _)| owner.to_string());
+            (copy_of.is_some() || looks_secret(name)).then(|| EnvFinding::Extraneous {
+                name: name.clone(),
+                copy_of,
+            })
+        })
+        .collect();
+    extraneous.sort_by(|a, b| match (a, b) {
+        (EnvFinding::Extraneous { name: a, .. }, EnvFinding::Extraneous { name: b, .. }) => {
+            a.cmp(b)
+        }
+        _ => std::cmp::Ordering::Equal,
+    });
+    findings.extend(extraneous);
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_secret() {
+        for name in [
+            "API_KEY",
+            "GITHUB_TOKEN",
+            "db_password",
+            "STRIPE_SECRET_KEY",
+        ] {
+            assert!(looks_secret(name), "{}", name);
+        }
+        for name in [
+            "PATH",
+            "HOME",
+            "AWS_SHARED_CREDENTIALS_FILE",
+            "AWS_ACCESS_KEY_ID",
+            "SSH_AUTH_SOCK",
+            "KEYMAP",
+            "SECRETSPEC_HARDWARE_KEY",
+        ] {
+            assert!(!looks_secret(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_audit() {
+        let env = parse_environ(
+            b"DATABASE_URL=postgres://old\0API_KEY=sk_1\0OLD_API_KEY=sk_0\0BACKUP=sk_1\0HOME=/root\0",
+        );
+        let declared = BTreeSet::from([
+            "API_KEY".to_string(),
+            "DATABASE_URL".to_string(),
+            "REDIS_URL".to_string(),
+        ]);
+        let resolved = HashMap::from([
+            ("API_KEY".to_string(), SecretString::from("sk_1")),
+            (
+                "DATABASE_URL".to_string(),
+                SecretString::from("postgres://new"),
+            ),
+        ]);
+        assert_eq!(
+            audit(&env, &declared, &resolved),
+            [
+                EnvFinding::Current("API_KEY".to_string()),
+                EnvFinding::Stale("DATABASE_URL".to_string()),
+                EnvFinding::Missing("REDIS_URL".to_string()),
+                EnvFinding::Extraneous {
+                    name: "BACKUP".to_string(),
+                    copy_of: Some("API_KEY".to_string()),
+                },
+                EnvFinding::Extraneous {
+                    name: "OLD_API_KEY".to_string(),
+                    copy_of: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_env() {
+        let env = process_env(std::process::id());
+        if cfg!(target_os = "linux") {
+            assert!(env.unwrap().contains_key("PATH"));
+        }
+        assert!(process_env(u32::MAX).is_err());
+    }
+}
@@ -2,6 +2,7 @@
 
 use miette::Diagnostic;
 use std::io;
+use std::time::Duration;
 use thiserror::Error;
 
 // Internal use only
@@ -22,6 +23,8 @@ pub enum SecretSpecError {
         "Unsupported secretspec revision '{0}'. This version of secretspec only supports revision '1.0'"
     )]
     UnsupportedRevision(String),
+    #[error("Unsupported secretspec schema {0}. Upgrade secretspec to read this secretspec.toml")]
+    UnsupportedSchema(u32),
     #[error("TOML serialization error: {0}")]
     TomlSer(#[from] toml::ser::Error),
     #[cfg(feature = "keyring")]
@@ -37,22 +40,118 @@ pub enum SecretSpecError {
     ProviderNotFound(String),
     #[error("Secret '{0}' not found")]
     SecretNotFound(String),
+    /// The secret isn't declared in the profile, and undeclared secrets
+    /// aren't allowed
+    #[error(
+        "Secret '{name}' is not defined in profile '{profile}'. {hint}\n\nUse --force to use an undeclared secret"
+    )]
+    SecretNotDeclared {
+        /// Name of the secret
+        name: String,
+        /// The profile it was looked up in
+        profile: String,
+        /// The closest declared names, or the declared names if none is close
+        hint: String,
+    },
     #[error("Secret '{0}' is required but not set")]
     RequiredSecretMissing(String),
     #[error("No secretspec.toml found in current directory")]
     NoManifest,
     #[error("Project name not found in secretspec.toml")]
     NoProjectName,
+    /// A provider operation failed for a reason not covered by a more
+    /// specific variant below
     #[error("Provider operation failed: {0}")]
     ProviderOperationFailed(String),
+    /// The provider needs the user to sign in or unlock before it can be used
+    #[error("{provider} authentication required.\n\n{fix}")]
+    AuthRequired {
+        /// Human-readable name of the provider or service
+        provider: String,
+        /// How to authenticate
+        fix: String,
+    },
+    /// An item or resource a provider operation needed does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+    /// The provider rejected the request because too many were made
+    #[error("{provider} rate limit exceeded{}", retry_hint(.retry_after))]
+    RateLimited {
+        /// Human-readable name of the provider or service
+        provider: String,
+        /// How long to wait before retrying, if known
+        retry_after: Option<Duration>,
+    },
+    /// The authenticated user or account lacks access to the resource
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    /// The command-line tool a provider runs is not installed
+    #[error("{cli} is not installed.\n\n{install_hint}")]
+    CliMissing {
+        /// Name of the tool, e.g. "OnePassword CLI (op)"
+        cli: String,
+        /// How to install and set up the tool
+        install_hint: String,
+    },
+    /// The operation conflicts with the current state, e.g. the item already exists
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[cfg(feature = "native")]
     #[error("User interaction error: {0}")]
     InquireError(#[from] inquire::InquireError),
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
     #[error("Invalid profile: {0}")]
     InvalidProfile(String),
     #[error("Validation failed: {0}")]
     ValidationFailed(ValidationErrors),
+    #[error(
+        "No terminal attached: cannot {0}.\n\nPass the value as an argument, or use --from-stdin or --from-file"
+    )]
+    NoTty(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Lockfile check failed: {0}")]
+    LockfileDrift(String),
+    /// Default values reference each other in a loop
+    #[error("Circular reference in default values: {0}")]
+    ReferenceCycle(String),
+    /// A `ref:` default points at a value that isn't set
+    #[error("Reference '{0}' could not be resolved: the referenced value is not set")]
+    UnresolvedReference(String),
+    /// The command generating a new value failed or printed an invalid one
+    #[error("Generator for '{name}' failed: {reason}")]
+    GeneratorFailed {
+        /// The secret being generated
+        name: String,
+        /// What went wrong
+        reason: String,
+    },
+}
+
+impl SecretSpecError {
+    /// Returns whether the same operation may succeed if retried later.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, SecretSpecError::RateLimited { .. })
+    }
+
+    /// Returns how long to wait before retrying, if the provider said so.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            SecretSpecError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// Formats the retry delay of a rate limit error.
+fn retry_hint(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(delay) => format!(". Please wait ~{} seconds and try again", delay.as_secs()),
+        None => ". Please wait and try again".to_string(),
+    }
 }
 
 /// A type alias for `Result<T, SecretSpecError>`
@@ -72,7 +171,10 @@ impl From<ParseError> for SecretSpecError {
                 }
             }
             ParseError::Toml(toml_err) => SecretSpecError::Toml(toml_err),
+            ParseError::Json(json_err) => SecretSpecError::Json(json_err),
+            ParseError::Yaml(yaml_err) => SecretSpecError::Yaml(yaml_err),
             ParseError::UnsupportedRevision(rev) => SecretSpecError::UnsupportedRevision(rev),
+            ParseError::UnsupportedSchema(schema) => SecretSpecError::UnsupportedSchema(schema),
             ParseError::CircularDependency(msg) => {
                 SecretSpecError::Io(io::Error::new(io::ErrorKind::InvalidData, msg))
             }
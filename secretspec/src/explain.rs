@@ -0,0 +1,52 @@
+//! Resolution traces
+//!
+//! `secretspec explain KEY` resolves a single secret the way every other
+//! command does and records each step on the way: the profile and why it
+//! was chosen, where the secret is declared, the provider and the layers
+//! around it, every lookup in order and whether it found a value, and the
+//! defaults, references and transforms applied. Values are never recorded.
+
+/// One step of resolving a secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// What kind of step this is, like `provider` or `lookup`
+    pub label: &'static str,
+    /// What happened, like `STRIPE_KEY in onepassword: not set`
+    pub detail: String,
+}
+
+/// How a secret resolves, step by step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// Name of the secret
+    pub secret: String,
+    /// The steps in the order they were taken
+    pub steps: Vec<Step>,
+    /// Where the value came from, or `None` if the secret isn't set
+    pub source: Option<String>,
+}
+
+impl Trace {
+    /// Starts the trace of a secret.
+    pub(crate) fn new(secret: &str) -> Self {
+        Self {
+            secret: secret.to_string(),
+            steps: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// Records a step.
+    pub(crate) fn step(&mut self, label: &'static str, detail: impl Into<String>) {
+        self.steps.push(Step {
+            label,
+            detail: detail.into(),
+        });
+    }
+
+    /// Records the outcome of a lookup.
+    pub(crate) fn lookup(&mut self, what: impl std::fmt::Display, found: bool) {
+        let outcome = if found { "found" } else { "not set" };
+        self.step("lookup", format!("{}: {}", what, outcome));
+    }
+}
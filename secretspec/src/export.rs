@@ -0,0 +1,200 @@
+//! Export of resolved secrets in formats other tools load
+//!
+//! `secretspec export` and `secretspec devenv export` print the resolved
+//! secrets, sorted by name, for shells, Docker env files, JSON consumers,
+//! Ansible and systemd. Values are only ever printed to stdout.
+
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// A format the resolved secrets can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `export NAME='value'` statements for `eval` in a shell
+    Shell,
+    /// `NAME=value` lines of a Docker env file
+    Docker,
+    /// A JSON object of names and values, sorted by name
+    Json,
+    /// A YAML variables file for Ansible's `vars_files` or `--extra-vars @file`
+    AnsibleVars,
+    /// An `EnvironmentFile=` for systemd services
+    Systemd,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+        match format {
+            "shell" => Ok(ExportFormat::Shell),
+            "docker" => Ok(ExportFormat::Docker),
+            "json" => Ok(ExportFormat::Json),
+            "ansible-vars" => Ok(ExportFormat::AnsibleVars),
+            "systemd" => Ok(ExportFormat::Systemd),
+            _ => Err(format!(
+                "Unknown export format '{}'. Use shell, docker, json, ansible-vars or systemd.",
+                format
+            )),
+        }
+    }
+}
+
+/// Returns whether a name can be used as a shell variable.
+fn is_shell_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Formats resolved secrets in `format`, sorted by name.
+///
+/// # Errors
+///
+/// Returns an error if a name isn't a valid variable name, or for the
+/// Docker format if a value spans several lines, which env files can't hold
+pub(crate) fn export(
+    secrets: &HashMap<String, SecretString>,
+    format: ExportFormat,
+) -> Result<Zeroizing<String>> {
+    let sorted: BTreeMap<&str, &str> = secrets
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.expose_secret()))
+        .collect();
+    if let Some(name) = sorted.keys().find(|name| !is_shell_name(name)) {
+        return Err(SecretSpecError::InvalidInput(format!(
+            "'{}' is not a valid environment variable name",
+            name
+        )));
+    }
+
+    let mut output = Zeroizing::new(String::new());
+    match format {
+        ExportFormat::Shell => {
+            for (name, value) in sorted {
+                let _ = writeln!(output, "export {}='{}'", name, value.replace('\'', r"'\''"));
+            }
+        }
+        ExportFormat::Docker => {
+            for (name, value) in sorted {
+                if value.contains(['\n', '\r']) {
+                    return Err(SecretSpecError::InvalidInput(format!(
+                        "{} spans several lines, which Docker env files can't hold",
+                        name
+                    )));
+                }
+                let _ = writeln!(output, "{}={}", name, value);
+            }
+        }
+        ExportFormat::Json => {
+            output.push_str(&Zeroizing::new(serde_json::to_string_pretty(&sorted)?));
+            output.push('\n');
+        }
+        ExportFormat::AnsibleVars => {
+            // JSON strings are valid double-quoted YAML scalars. `!unsafe`
+            // keeps Ansible from templating values containing `{{` or `{%`
+            output.push_str("---\n");
+            for (name, value) in sorted {
+                let value = Zeroizing::new(serde_json::to_string(value)?);
+                let _ = writeln!(output, "{}: !unsafe {}", name, value.as_str());
+            }
+        }
+        ExportFormat::Systemd => {
+            // Double-quoted values may span lines; only these are escaped
+            for (name, value) in sorted {
+                let mut quoted = Zeroizing::new(String::with_capacity(value.len()));
+                for c in value.chars() {
+                    if matches!(c, '"' | '\\' | '`' | '$') {
+                        quoted.push('\\');
+                    }
+                    quoted.push(c);
+                }
+                let _ = writeln!(output, "{}=\"{}\"", name, quoted.as_str());
+            }
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secrets(values: &[(&str, &str)]) -> HashMap<String, SecretString> {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), SecretString::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_export() {
+        let secrets = secrets(&[("B_TOKEN", "it's"), ("A_URL", "postgres://db")]);
+        assert_eq!(
+            export(&secrets, ExportFormat::Shell).unwrap().as_str(),
+            "export A_URL='postgres://db'\nexport B_TOKEN='it'\\''s'\n"
+        );
+        assert_eq!(
+            export(&secrets, ExportFormat::Docker).unwrap().as_str(),
+            "A_URL=postgres://db\nB_TOKEN=it's\n"
+        );
+        assert_eq!(
+            export(&secrets, ExportFormat::Json).unwrap().as_str(),
+            "{\n  \"A_URL\": \"postgres://db\",\n  \"B_TOKEN\": \"it's\"\n}\n"
+        );
+        assert_eq!(
+            export(&secrets, ExportFormat::AnsibleVars)
+                .unwrap()
+                .as_str(),
+            "---\nA_URL: !unsafe \"postgres://db\"\nB_TOKEN: !unsafe \"it's\"\n"
+        );
+    }
+
+    #[test]
+    fn test_export_ansible_vars_are_not_templated() {
+        let secrets = secrets(&[("TOKEN", "{{ lookup('env', 'HOME') }}{% raw %}")]);
+        let output = export(&secrets, ExportFormat::AnsibleVars).unwrap();
+        assert_eq!(
+            output.as_str(),
+            "---\nTOKEN: !unsafe \"{{ lookup('env', 'HOME') }}{% raw %}\"\n"
+        );
+        let vars: HashMap<String, String> = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(vars["TOKEN"], "{{ lookup('env', 'HOME') }}{% raw %}");
+    }
+
+    #[test]
+    fn test_export_rejects_what_the_format_cannot_hold() {
+        let multiline = secrets(&[("KEY", "line1\nline2")]);
+        assert!(export(&multiline, ExportFormat::Shell).is_ok());
+        assert!(export(&multiline, ExportFormat::Docker).is_err());
+        assert_eq!(
+            export(&multiline, ExportFormat::AnsibleVars)
+                .unwrap()
+                .as_str(),
+            "---\nKEY: !unsafe \"line1\\nline2\"\n"
+        );
+        assert_eq!(
+            export(&multiline, ExportFormat::Systemd).unwrap().as_str(),
+            "KEY=\"line1\nline2\"\n"
+        );
+
+        assert_eq!(
+            export(
+                &secrets(&[("KEY", r#"a "b" \ `c` $D"#)]),
+                ExportFormat::Systemd
+            )
+            .unwrap()
+            .as_str(),
+            r#"KEY="a \"b\" \\ \`c\` \$D""#.to_string() + "\n"
+        );
+
+        let invalid = secrets(&[("MY-KEY", "value")]);
+        assert!(export(&invalid, ExportFormat::Shell).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! New values from external commands.
+//!
+//! A secret with a [`Generator`] gets its new value from a shell command when
+//! it is rotated, so rotation can call out to whatever tooling issues the
+//! credential:
+//!
+//! ```toml
+//! [profiles.default]
+//! SESSION_KEY = { description = "Session key", generator = { command = "openssl rand -hex 32" } }
+//! GCP_KEY = { description = "Service account key", generator = { command = "gcloud iam service-accounts keys create - --iam-account=ci@app.iam.gserviceaccount.com", timeout = "2m", multiline = true } }
+//! ```
+//!
+//! The command runs with the name of the secret, the project and the profile
+//! in `SECRETSPEC_SECRET`, `SECRETSPEC_PROJECT` and `SECRETSPEC_PROFILE`. It
+//! is killed if it runs longer than its timeout, and its output is checked
+//! before it is stored.
+
+use crate::config::Generator;
+use crate::ttl;
+use crate::{Result, SecretSpecError};
+use secrecy::SecretString;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+/// How long a command may run when the generator doesn't say.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a running command is checked on.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Runs a secret's generator and returns the value it printed.
+///
+/// A single trailing newline is dropped from the output.
+///
+/// # Arguments
+///
+/// * `name` - The secret being generated
+/// * `generator` - Its generator
+/// * `project` - The project, passed to the command
+/// * `profile` - The profile, passed to the command
+///
+/// # Errors
+///
+/// Returns [`SecretSpecError::GeneratorFailed`] if the command can't be
+/// started, fails, runs out of time, or prints a value that is empty, spans
+/// several lines without `multiline`, or is shorter than `min_length`
+pub(crate) fn generate(
+    name: &str,
+    generator: &Generator,
+    project: &str,
+    profile: &str,
+) -> Result<SecretString> {
+    let failed = |reason: String| SecretSpecError::GeneratorFailed {
+        name: name.to_string(),
+        reason,
+    };
+    let timeout = match &generator.timeout {
+        Some(timeout) => ttl::parse(timeout).map_err(failed)?,
+        None => DEFAULT_TIMEOUT,
+    };
+
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", &generator.command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", &generator.command]);
+        cmd
+    };
+    let mut child = cmd
+        .env("SECRETSPEC_SECRET", name)
+        .env("SECRETSPEC_PROJECT", project)
+        .env("SECRETSPEC_PROFILE", profile)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| failed(format!("cannot run `{}`: {}", generator.command, e)))?;
+
+    // Read while the command runs, so a full pipe can't stall it
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stdout = thread::spawn(move || {
+        let mut output = Zeroizing::new(Vec::new());
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let stderr = thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output);
+        output
+    });
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(failed(format!(
+                "`{}` did not finish within {}",
+                generator.command,
+                ttl::format(timeout.as_secs())
+            )));
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    let output = stdout
+        .join()
+        .expect("reading stdout doesn't panic")
+        .map_err(|e| failed(format!("cannot read the output: {}", e)))?;
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        return Err(failed(format!(
+            "`{}` exited with {}{}",
+            generator.command,
+            status,
+            match stderr.trim() {
+                "" => String::new(),
+                stderr => format!(": {}", stderr),
+            }
+        )));
+    }
+
+    let output = std::str::from_utf8(&output)
+        .map_err(|_| failed("the output is not valid UTF-8".to_string()))?;
+    let value = check(output, generator).map_err(failed)?;
+    Ok(SecretString::new(value.into()))
+}
+
+/// Checks the output of a generator, returning it without its trailing
+/// newline.
+fn check<'a>(output: &'a str, generator: &Generator) -> std::result::Result<&'a str, String> {
+    let value = output
+        .strip_suffix('\n')
+        .map_or(output, |value| value.strip_suffix('\r').unwrap_or(value));
+    if value.trim().is_empty() {
+        return Err("the command printed nothing".to_string());
+    }
+    if !generator.multiline && value.contains('\n') {
+        return Err(
+            "the command printed several lines; set multiline = true if that is expected"
+                .to_string(),
+        );
+    }
+    if let Some(min_length) = generator.min_length
+        && value.chars().count() < min_length
+    {
+        return Err(format!(
+            "the value has {} characters, fewer than min_length = {}",
+            value.chars().count(),
+            min_length
+        ));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+
+    fn generator(command: &str) -> Generator {
+        Generator {
+            command: command.to_string(),
+            ..Generator::default()
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let plain = generator("true");
+        assert_eq!(check("abc\n", &plain), Ok("abc"));
+        assert_eq!(check("abc\r\n", &plain), Ok("abc"));
+        assert!(check("\n", &plain).is_err());
+        assert!(check("a\nb\n", &plain).is_err());
+
+        let pem = Generator {
+            multiline: true,
+            min_length: Some(5),
+            ..generator("true")
+        };
+        assert_eq!(check("a\nb\nc\n", &pem), Ok("a\nb\nc"));
+        assert!(check("a\nb\n", &pem).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_generate() {
+        let value = generate(
+            "API_KEY",
+            &generator(r#"printf '%s-%s' "$SECRETSPEC_SECRET" "$SECRETSPEC_PROFILE""#),
+            "app",
+            "ci",
+        )
+        .unwrap();
+        assert_eq!(value.expose_secret(), "API_KEY-ci");
+
+        let err = generate("API_KEY", &generator("echo nope >&2; exit 3"), "app", "ci")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("nope"), "{}", err);
+
+        let slow = Generator {
+            timeout: Some("1s".to_string()),
+            ..generator("sleep 5; echo late")
+        };
+        let start = Instant::now();
+        let err = generate("API_KEY", &slow, "app", "ci").unwrap_err();
+        assert!(err.to_string().contains("did not finish within 1s"));
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+}
@@ -0,0 +1,230 @@
+//! Dependencies between secrets.
+//!
+//! A secret whose default is a `ref:` (see [`crate::reference`]) derives its
+//! value from another secret or provider key when it isn't set itself. The
+//! [`Graph`] of these references is checked for cycles before anything is
+//! read, gives the order secrets are resolved in, so every secret is
+//! resolved after the ones it derives from, and is rendered by
+//! `secretspec graph`:
+//!
+//! ```text
+//! STRIPE_KEY <- onepassword://Shared/Stripe#publishable
+//! CHECKOUT_KEY <- STRIPE_KEY
+//! ```
+
+use crate::reference::Reference;
+use crate::{Result, SecretSpecError};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// What a secret's default refers to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Dependency {
+    /// Another secret, which may not be declared
+    Secret(String),
+    /// A key of another provider, as `<provider-uri>#<KEY>`
+    Provider(String),
+}
+
+impl Dependency {
+    /// Returns the name of the secret or the provider key.
+    fn label(&self) -> &str {
+        match self {
+            Dependency::Secret(name) | Dependency::Provider(name) => name,
+        }
+    }
+}
+
+/// The references between the secrets of a profile.
+#[derive(Debug, Default)]
+pub(crate) struct Graph {
+    /// What each declared secret refers to, empty for plain secrets
+    dependencies: BTreeMap<String, Vec<Dependency>>,
+}
+
+impl Graph {
+    /// Builds the graph of secrets and their defaults.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a default is a malformed reference
+    pub(crate) fn new<'a>(
+        secrets: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    ) -> Result<Self> {
+        let mut dependencies = BTreeMap::new();
+        for (name, default) in secrets {
+            let dependency = match default.map(Reference::parse).transpose()?.flatten() {
+                Some(Reference::Secret(target)) => Some(Dependency::Secret(target.to_string())),
+                Some(Reference::Provider { uri, key }) => {
+                    Some(Dependency::Provider(format!("{}#{}", uri, key)))
+                }
+                None => None,
+            };
+            dependencies.insert(name.to_string(), dependency.into_iter().collect());
+        }
+        Ok(Self { dependencies })
+    }
+
+    /// Returns the declared secrets ordered so each comes after the secrets
+    /// it refers to, in name order otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SecretSpecError::ReferenceCycle`] with the chain of secrets
+    /// if references loop
+    pub(crate) fn order(&self) -> Result<Vec<&str>> {
+        let mut order = Vec::with_capacity(self.dependencies.len());
+        let mut done = BTreeSet::new();
+        for name in self.dependencies.keys() {
+            self.visit(name, &mut Vec::new(), &mut done, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// Adds a secret to `order` after the secrets it refers to.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The secret to add
+    /// * `path` - The secrets whose references lead here, to detect cycles
+    /// * `done` - The secrets already added
+    /// * `order` - The secrets in resolution order
+    fn visit<'a>(
+        &'a self,
+        name: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+        order: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|visited| *visited == name) {
+            let mut chain = path[start..].to_vec();
+            chain.push(name);
+            return Err(SecretSpecError::ReferenceCycle(chain.join(" -> ")));
+        }
+        let Some((name, dependencies)) = self.dependencies.get_key_value(name) else {
+            // Undeclared secrets are reported when they are resolved
+            return Ok(());
+        };
+
+        path.push(name);
+        for dependency in dependencies {
+            if let Dependency::Secret(target) = dependency {
+                self.visit(target, path, done, order)?;
+            }
+        }
+        path.pop();
+        done.insert(name);
+        order.push(name);
+        Ok(())
+    }
+
+    /// Returns the graph in Graphviz DOT format, with edges pointing from a
+    /// value to the secrets derived from it.
+    pub(crate) fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph secretspec {\n    rankdir=LR;\n");
+        let mut providers = BTreeSet::new();
+        for (name, dependencies) in &self.dependencies {
+            for dependency in dependencies {
+                if let Dependency::Provider(key) = dependency {
+                    providers.insert(key.as_str());
+                }
+                dot.push_str(&format!(
+                    "    {} -> {};\n",
+                    quote(dependency.label()),
+                    quote(name)
+                ));
+            }
+        }
+        for key in providers {
+            dot.push_str(&format!("    {} [shape=box, style=dashed];\n", quote(key)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl fmt::Display for Graph {
+    /// Lists each derived secret after the ones it refers to, like
+    /// `CHECKOUT_KEY <- STRIPE_KEY`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // References that loop are listed in name order
+        let order = self
+            .order()
+            .unwrap_or_else(|_| self.dependencies.keys().map(String::as_str).collect());
+        for name in order {
+            for dependency in &self.dependencies[name] {
+                writeln!(f, "{} <- {}", name, dependency.label())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quotes a node name for DOT.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph() {
+        let graph = Graph::new([
+            ("WEBHOOK_URL", Some("ref:API_URL")),
+            ("API_URL", Some("ref:BASE_URL")),
+            ("BASE_URL", Some("https://example.com")),
+            (
+                "STRIPE_KEY",
+                Some("ref:onepassword://Shared/Stripe#publishable"),
+            ),
+            ("DATABASE_URL", None),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            graph.order().unwrap(),
+            [
+                "BASE_URL",
+                "API_URL",
+                "DATABASE_URL",
+                "STRIPE_KEY",
+                "WEBHOOK_URL"
+            ]
+        );
+        assert_eq!(
+            graph.to_string(),
+            "API_URL <- BASE_URL\n\
+             STRIPE_KEY <- onepassword://Shared/Stripe#publishable\n\
+             WEBHOOK_URL <- API_URL\n"
+        );
+        assert_eq!(
+            graph.to_dot(),
+            "digraph secretspec {\n    rankdir=LR;\n    \
+             \"BASE_URL\" -> \"API_URL\";\n    \
+             \"onepassword://Shared/Stripe#publishable\" -> \"STRIPE_KEY\";\n    \
+             \"API_URL\" -> \"WEBHOOK_URL\";\n    \
+             \"onepassword://Shared/Stripe#publishable\" [shape=box, style=dashed];\n}\n"
+        );
+
+        let cycle = Graph::new([
+            ("A", Some("ref:B")),
+            ("B", Some("ref:C")),
+            ("C", Some("ref:B")),
+        ])
+        .unwrap();
+        let err = cycle.order().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Circular reference in default values: B -> C -> B"
+        );
+
+        // References to undeclared secrets are left to resolution
+        let graph = Graph::new([("A", Some("ref:MISSING"))]).unwrap();
+        assert_eq!(graph.order().unwrap(), ["A"]);
+    }
+}
@@ -0,0 +1,496 @@
+//! gRPC service of `secretspec serve --grpc`
+//!
+//! The server resolves the secrets of one project through one provider and
+//! profile, so a sidecar can hold the provider's credentials while the apps
+//! next to it fetch their secrets over localhost.
+//!
+//! - Connections use TLS, and clients must present a certificate signed by
+//!   the configured client CA; there is no plaintext mode
+//! - `Get` and `Set` follow the spec like `secretspec get` and `secretspec
+//!   set`: undeclared secrets are refused
+//! - Provider calls block, so every request runs on tokio's blocking pool
+//!
+//! The messages mirror `proto/secretspec.proto`, which clients in other
+//! languages generate their stubs from.
+
+use crate::{Result, SecretSpecError, Secrets};
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tonic::{Request, Response, Status};
+
+include!(concat!(env!("OUT_DIR"), "/secretspec.v1.Secrets.rs"));
+
+/// Request of `Get`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetRequest {
+    #[prost(string, tag = "1")]
+    pub name: String,
+}
+
+/// Response of `Get`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetResponse {
+    #[prost(string, tag = "1")]
+    pub value: String,
+}
+
+/// Request of `Set`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SetRequest {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
+
+/// Response of `Set`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SetResponse {}
+
+/// Request of `List`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListRequest {}
+
+/// Response of `List`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub secrets: Vec<SecretStatus>,
+}
+
+/// A declared secret and whether it is set.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SecretStatus {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub description: String,
+    #[prost(enumeration = "secret_status::Status", tag = "3")]
+    pub status: i32,
+}
+
+/// Nested types of [`SecretStatus`].
+pub mod secret_status {
+    /// Where a secret's value comes from.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Status {
+        Unspecified = 0,
+        /// The provider has a value
+        Set = 1,
+        /// The provider has no value and the default is used
+        Default = 2,
+        /// The provider has no value and there is no default
+        Missing = 3,
+    }
+}
+
+/// Request of `Check`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CheckRequest {}
+
+/// Response of `Check`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CheckResponse {
+    /// Whether every required secret is available
+    #[prost(bool, tag = "1")]
+    pub ok: bool,
+    #[prost(string, repeated, tag = "2")]
+    pub missing_required: Vec<String>,
+    #[prost(string, repeated, tag = "3")]
+    pub missing_optional: Vec<String>,
+}
+
+/// Where `secretspec serve --grpc` listens and the TLS files it uses.
+#[derive(Debug, Clone)]
+pub struct ServeConfig {
+    /// Address to listen on, e.g. `127.0.0.1:50051`
+    pub addr: SocketAddr,
+    /// PEM certificate chain of the server
+    pub tls_cert: PathBuf,
+    /// PEM private key of the server
+    pub tls_key: PathBuf,
+    /// PEM certificates of the CAs that client certificates must be signed by
+    pub client_ca: PathBuf,
+}
+
+impl ServeConfig {
+    /// Reads the TLS files, requiring clients to authenticate.
+    fn tls(&self) -> Result<ServerTlsConfig> {
+        let read = |path: &PathBuf| {
+            std::fs::read(path).map_err(|e| {
+                SecretSpecError::InvalidInput(format!("Cannot read {}: {}", path.display(), e))
+            })
+        };
+        Ok(ServerTlsConfig::new()
+            .identity(Identity::from_pem(
+                read(&self.tls_cert)?,
+                read(&self.tls_key)?,
+            ))
+            .client_ca_root(Certificate::from_pem(read(&self.client_ca)?)))
+    }
+}
+
+/// Converts an error to the status returned to the client.
+fn status(e: SecretSpecError) -> Status {
+    let message = e.to_string();
+    match e {
+        SecretSpecError::SecretNotFound(_)
+        | SecretSpecError::SecretNotDeclared { .. }
+        | SecretSpecError::RequiredSecretMissing(_)
+        | SecretSpecError::NotFound(_) => Status::not_found(message),
+        SecretSpecError::InvalidInput(_) | SecretSpecError::InvalidProfile(_) => {
+            Status::invalid_argument(message)
+        }
+        SecretSpecError::PermissionDenied(_) => Status::permission_denied(message),
+        SecretSpecError::Conflict(_) => Status::aborted(message),
+        SecretSpecError::RateLimited { .. } => Status::resource_exhausted(message),
+        SecretSpecError::AuthRequired { .. } => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
+
+/// Serves the secrets of one provider and profile.
+struct Service {
+    secrets: Arc<Secrets>,
+}
+
+impl Service {
+    /// Runs a lookup on the blocking pool.
+    async fn run<T, F>(&self, f: F) -> std::result::Result<Response<T>, Status>
+    where
+        F: FnOnce(&Secrets) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let secrets = Arc::clone(&self.secrets);
+        tokio::task::spawn_blocking(move || f(&secrets))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map(Response::new)
+            .map_err(status)
+    }
+}
+
+/// Returns the status of every secret selected in the current profile.
+fn list(secrets: &Secrets) -> Result<ListResponse> {
+    let names = secrets.selected_secrets(&secrets.resolve_profile(None))?;
+    let (missing, with_defaults): (BTreeSet<String>, BTreeSet<String>) = match secrets.validate()? {
+        Ok(validated) => (
+            validated.missing_optional.into_iter().collect(),
+            validated
+                .with_defaults
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect(),
+        ),
+        Err(errors) => (
+            errors
+                .missing_required
+                .into_iter()
+                .chain(errors.missing_optional)
+                .collect(),
+            errors
+                .with_defaults
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect(),
+        ),
+    };
+
+    let statuses = names
+        .into_iter()
+        .map(|name| {
+            let status = if missing.contains(&name) {
+                secret_status::Status::Missing
+            } else if with_defaults.contains(&name) {
+                secret_status::Status::Default
+            } else {
+                secret_status::Status::Set
+            };
+            SecretStatus {
+                description: secrets
+                    .resolve_secret_config(&name, None)
+                    .and_then(|secret| secret.description)
+                    .unwrap_or_default(),
+                name,
+                status: status as i32,
+            }
+        })
+        .collect();
+    Ok(ListResponse { secrets: statuses })
+}
+
+/// Reports the missing secrets of the current profile.
+fn check(secrets: &Secrets) -> Result<CheckResponse> {
+    Ok(match secrets.validate()? {
+        Ok(validated) => CheckResponse {
+            ok: true,
+            missing_required: Vec::new(),
+            missing_optional: validated.missing_optional,
+        },
+        Err(errors) => CheckResponse {
+            ok: false,
+            missing_required: errors.missing_required,
+            missing_optional: errors.missing_optional,
+        },
+    })
+}
+
+#[tonic::async_trait]
+impl secrets_server::Secrets for Service {
+    async fn get(
+        &self,
+        request: Request<GetRequest>,
+    ) -> std::result::Result<Response<GetResponse>, Status> {
+        let name = request.into_inner().name;
+        self.run(move |secrets| {
+            let value = secrets.get(&name)?;
+            Ok(GetResponse {
+                value: value.expose_secret().to_string(),
+            })
+        })
+        .await
+    }
+
+    async fn set(
+        &self,
+        request: Request<SetRequest>,
+    ) -> std::result::Result<Response<SetResponse>, Status> {
+        let SetRequest { name, value } = request.into_inner();
+        self.run(move |secrets| {
+            secrets.set_secret(&name, SecretString::from(value))?;
+            Ok(SetResponse {})
+        })
+        .await
+    }
+
+    async fn list(
+        &self,
+        _request: Request<ListRequest>,
+    ) -> std::result::Result<Response<ListResponse>, Status> {
+        self.run(list).await
+    }
+
+    async fn check(
+        &self,
+        _request: Request<CheckRequest>,
+    ) -> std::result::Result<Response<CheckResponse>, Status> {
+        self.run(check).await
+    }
+}
+
+/// Serves connections from `incoming` until `shutdown` completes.
+async fn run(
+    secrets: Secrets,
+    tls: ServerTlsConfig,
+    incoming: TcpIncoming,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    let service = Service {
+        secrets: Arc::new(secrets),
+    };
+    Server::builder()
+        .tls_config(tls)
+        .map_err(|e| SecretSpecError::InvalidInput(format!("Invalid TLS configuration: {}", e)))?
+        .add_service(secrets_server::SecretsServer::new(service))
+        .serve_with_incoming_shutdown(incoming, shutdown)
+        .await
+        .map_err(|e| SecretSpecError::ProviderOperationFailed(format!("gRPC server failed: {}", e)))
+}
+
+/// Serves the secrets over gRPC until interrupted.
+///
+/// `on_listening` is called with the bound address once connections are
+/// accepted.
+///
+/// # Errors
+///
+/// Returns an error if the TLS files can't be read, the address can't be
+/// bound, or the server fails
+pub(crate) fn serve(
+    secrets: Secrets,
+    config: &ServeConfig,
+    on_listening: impl FnOnce(SocketAddr),
+) -> Result<()> {
+    let tls = config.tls()?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let incoming = TcpIncoming::bind(config.addr)?;
+        on_listening(incoming.local_addr()?);
+        run(secrets, tls, incoming, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::tests::MockProvider;
+    use crate::{Config, SpecFormat};
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, Issuer, KeyPair};
+    use tonic::Code;
+    use tonic::transport::{Channel, ClientTlsConfig};
+
+    /// A CA with a server identity for `localhost` and a client identity it
+    /// signed.
+    struct Pki {
+        ca: String,
+        server: Identity,
+        client: Identity,
+    }
+
+    fn pki() -> Pki {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let issuer = Issuer::new(ca_params, ca_key);
+
+        let identity = |names: Vec<String>| {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(names)
+                .unwrap()
+                .signed_by(&key, &issuer)
+                .unwrap();
+            Identity::from_pem(cert.pem(), key.serialize_pem())
+        };
+        Pki {
+            ca: ca.pem(),
+            server: identity(vec!["localhost".to_string()]),
+            client: identity(vec!["app".to_string()]),
+        }
+    }
+
+    fn secrets() -> Secrets {
+        let config = Config::parse(
+            r#"
+[project]
+name = "test"
+revision = "1.0"
+
+[profiles.default]
+API_KEY = { description = "API key" }
+PORT = { description = "Port", default = "8080" }
+SENTRY_DSN = { description = "Sentry", required = false }
+"#,
+            SpecFormat::Toml,
+        )
+        .unwrap();
+        let mut secrets = Secrets::new(config, None, None, None);
+        secrets.set_provider_instance(Box::new(MockProvider::new()));
+        secrets
+    }
+
+    async fn connect(addr: SocketAddr, tls: ClientTlsConfig) -> Channel {
+        Channel::from_shared(format!("https://localhost:{}", addr.port()))
+            .unwrap()
+            .tls_config(tls)
+            .unwrap()
+            .connect()
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_serve_with_client_certificate() {
+        let pki = pki();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+            let addr = incoming.local_addr().unwrap();
+            let tls = ServerTlsConfig::new()
+                .identity(pki.server)
+                .client_ca_root(Certificate::from_pem(&pki.ca));
+            let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+            let server = tokio::spawn(run(secrets(), tls, incoming, async {
+                let _ = stopped.await;
+            }));
+
+            let client_tls = ClientTlsConfig::new()
+                .ca_certificate(Certificate::from_pem(&pki.ca))
+                .domain_name("localhost");
+            let mut client = secrets_client::SecretsClient::new(
+                connect(addr, client_tls.clone().identity(pki.client)).await,
+            );
+
+            let check = client.check(CheckRequest {}).await.unwrap().into_inner();
+            assert!(!check.ok);
+            assert_eq!(check.missing_required, ["API_KEY"]);
+
+            client
+                .set(SetRequest {
+                    name: "API_KEY".to_string(),
+                    value: "sk-123".to_string(),
+                })
+                .await
+                .unwrap();
+            let get = client
+                .get(GetRequest {
+                    name: "API_KEY".to_string(),
+                })
+                .await
+                .unwrap();
+            assert_eq!(get.into_inner().value, "sk-123");
+            let port = client
+                .get(GetRequest {
+                    name: "PORT".to_string(),
+                })
+                .await
+                .unwrap();
+            assert_eq!(port.into_inner().value, "8080");
+
+            let undeclared = client
+                .get(GetRequest {
+                    name: "OTHER".to_string(),
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(undeclared.code(), Code::NotFound);
+
+            let list = client.list(ListRequest {}).await.unwrap().into_inner();
+            let statuses: Vec<_> = list
+                .secrets
+                .iter()
+                .map(|secret| (secret.name.as_str(), secret.status()))
+                .collect();
+            assert_eq!(
+                statuses,
+                [
+                    ("API_KEY", secret_status::Status::Set),
+                    ("PORT", secret_status::Status::Default),
+                    ("SENTRY_DSN", secret_status::Status::Missing),
+                ]
+            );
+            assert_eq!(list.secrets[0].description, "API key");
+            assert!(client.check(CheckRequest {}).await.unwrap().into_inner().ok);
+
+            // Without a client certificate the handshake is refused
+            let anonymous = Channel::from_shared(format!("https://localhost:{}", addr.port()))
+                .unwrap()
+                .tls_config(client_tls)
+                .unwrap()
+                .connect()
+                .await;
+            if let Ok(channel) = anonymous {
+                let mut client = secrets_client::SecretsClient::new(channel);
+                assert!(client.check(CheckRequest {}).await.is_err());
+            }
+
+            stop.send(()).unwrap();
+            server.await.unwrap().unwrap();
+        });
+    }
+}
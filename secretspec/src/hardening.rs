@@ -0,0 +1,112 @@
+//! Process hardening for `run`
+//!
+//! With `run --harden`, or `harden = true` in the spec's `[run]` section,
+//! secretspec makes it harder for resolved values to end up in crash
+//! artifacts or be read by other processes before it resolves them:
+//!
+//! - core dumps are disabled with `RLIMIT_CORE = 0`, which the command
+//!   inherits as well
+//! - on Linux, `PR_SET_DUMPABLE = 0` keeps other processes of the same user
+//!   from attaching with ptrace or reading `/proc/<pid>/mem`
+//! - a warning is printed when a debugger is already attached
+
+use crate::Result;
+#[cfg(unix)]
+use crate::SecretSpecError;
+use crate::warning;
+
+/// Hardens the current process.
+///
+/// # Errors
+///
+/// Returns an error if core dumps can't be disabled or, on Linux, the
+/// process can't be made non-dumpable
+pub(crate) fn apply() -> Result<()> {
+    disable_core_dumps()?;
+    disable_dumpable()?;
+    if let Some(tracer) = tracer_pid() {
+        warning::warn(format!(
+            "a debugger (pid {}) is attached to secretspec and can read secret values",
+            tracer
+        ));
+    }
+    Ok(())
+}
+
+/// Returns an error for a failed system call.
+#[cfg(unix)]
+fn failed(what: &str) -> SecretSpecError {
+    let error = std::io::Error::last_os_error();
+    SecretSpecError::Io(std::io::Error::new(
+        error.kind(),
+        format!("Failed to {}: {}", what, error),
+    ))
+}
+
+#[cfg(unix)]
+fn disable_core_dumps() -> Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: setrlimit only reads the limit passed by reference
+    if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+        return Err(failed("disable core dumps"));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn disable_core_dumps() -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn disable_dumpable() -> Result<()> {
+    // SAFETY: PR_SET_DUMPABLE takes a plain integer argument
+    if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+        return Err(failed("make the process non-dumpable"));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn disable_dumpable() -> Result<()> {
+    Ok(())
+}
+
+/// Returns the pid of the process tracing this one, if any.
+///
+/// Only detected on Linux; elsewhere `None` is returned.
+fn tracer_pid() -> Option<u32> {
+    if cfg!(target_os = "linux") {
+        parse_tracer_pid(&std::fs::read_to_string("/proc/self/status").ok()?)
+    } else {
+        None
+    }
+}
+
+/// Parses the `TracerPid` of `/proc/<pid>/status`, which is 0 when untraced.
+fn parse_tracer_pid(status: &str) -> Option<u32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))
+        .and_then(|pid| pid.trim().parse().ok())
+        .filter(|pid| *pid != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tracer_pid() {
+        let status = "Name:\tsecretspec\nState:\tS (sleeping)\nTracerPid:\t0\nUid:\t1000\n";
+        assert_eq!(parse_tracer_pid(status), None);
+        assert_eq!(
+            parse_tracer_pid(&status.replace("TracerPid:\t0", "TracerPid:\t4242")),
+            Some(4242)
+        );
+        assert_eq!(parse_tracer_pid("Name:\tsecretspec\n"), None);
+    }
+}
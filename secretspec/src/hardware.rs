@@ -0,0 +1,130 @@
+//! Hardware-key confirmation for the most sensitive secrets.
+//!
+//! Secrets declared with `hardware_confirm = true` get a layer of encryption
+//! tied to a FIDO2 security key. Before such a value is stored it is
+//! encrypted with [age](https://age-encryption.org) to the key's
+//! [`age-plugin-fido2-hmac`](https://github.com/olastor/age-plugin-fido2-hmac)
+//! recipient, which derives the file key from the authenticator's
+//! hmac-secret extension. Decrypting it only succeeds after a touch on the
+//! key, so a copy of the stored value, or of a provider session, is useless
+//! without physical presence.
+//!
+//! Stored values look like `fido2:<base64 ciphertext>`. The recipients are
+//! read from `SECRETSPEC_HARDWARE_KEY`, separated by commas so each member
+//! of a team can use their own key. Decryption uses the identity file in
+//! `SECRETSPEC_HARDWARE_IDENTITY`, or the plugin alone for keys whose
+//! credential is held in the recipient.
+
+use crate::provider::command::SystemRunner;
+use crate::provider::encrypted::{age_encrypt, execute_age};
+use crate::warning;
+use crate::{Result, SecretSpecError};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use secrecy::{ExposeSecret, SecretString};
+
+/// Prefix of every value sealed to a hardware key.
+const VALUE_PREFIX: &str = "fido2:";
+
+/// Environment variable listing the recipients of the hardware keys.
+const RECIPIENTS_ENV: &str = "SECRETSPEC_HARDWARE_KEY";
+
+/// Environment variable naming the identity file of the hardware key.
+const IDENTITY_ENV: &str = "SECRETSPEC_HARDWARE_IDENTITY";
+
+/// Name of the age plugin that talks to the hardware key.
+const PLUGIN: &str = "fido2-hmac";
+
+/// Encrypts the value of a secret to the hardware keys for storage.
+///
+/// # Errors
+///
+/// Returns an error if no hardware key is configured or age fails
+pub(crate) fn seal(name: &str, value: &SecretString) -> Result<SecretString> {
+    let recipients = parse_recipients(&std::env::var(RECIPIENTS_ENV).unwrap_or_default());
+    if recipients.is_empty() {
+        return Err(SecretSpecError::InvalidInput(format!(
+            "Secret '{}' needs hardware confirmation, but no hardware key is configured.\n\nSet {} to the age recipient of your FIDO2 key, from `age-plugin-fido2-hmac -g`",
+            name, RECIPIENTS_ENV
+        )));
+    }
+
+    let ciphertext = age_encrypt(&SystemRunner, &recipients, value.expose_secret().as_bytes())?;
+    Ok(SecretString::new(
+        format!("{}{}", VALUE_PREFIX, BASE64.encode(ciphertext)).into(),
+    ))
+}
+
+/// Decrypts a value sealed to a hardware key, asking for a touch.
+///
+/// # Errors
+///
+/// Returns an error if the value isn't sealed, for example because it was
+/// written by another tool, or the key isn't touched or present
+pub(crate) fn open(name: &str, stored: &SecretString) -> Result<SecretString> {
+    let ciphertext = unwrap(name, stored)?;
+
+    warning::notice(format!("🔑 Touch your security key to unlock {}", name));
+    let identity = std::env::var(IDENTITY_ENV).ok();
+    let args = match &identity {
+        Some(identity) => vec!["--decrypt", "--identity", identity.as_str()],
+        None => vec!["--decrypt", "-j", PLUGIN],
+    };
+    let plaintext = execute_age(&SystemRunner, &args, &ciphertext).map_err(|e| {
+        SecretSpecError::PermissionDenied(format!(
+            "Secret '{}' could not be unlocked with the hardware key: {}",
+            name, e
+        ))
+    })?;
+    Ok(SecretString::from(plaintext.as_str()))
+}
+
+/// Returns the ciphertext of a sealed value.
+fn unwrap(name: &str, stored: &SecretString) -> Result<Vec<u8>> {
+    let encoded = stored
+        .expose_secret()
+        .strip_prefix(VALUE_PREFIX)
+        .ok_or_else(|| {
+            SecretSpecError::PermissionDenied(format!(
+                "Secret '{}' needs hardware confirmation but is stored without it; set it again with secretspec to seal it",
+                name
+            ))
+        })?;
+    BASE64.decode(encoded.trim()).map_err(|e| {
+        SecretSpecError::ProviderOperationFailed(format!(
+            "{} has a malformed sealed value: {}",
+            name, e
+        ))
+    })
+}
+
+/// Splits a comma-separated list of recipients.
+fn parse_recipients(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|recipient| !recipient.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_values() {
+        assert_eq!(
+            parse_recipients(" age1fido2-hmac1abc, ,age1fido2-hmac1def "),
+            ["age1fido2-hmac1abc", "age1fido2-hmac1def"]
+        );
+        assert!(parse_recipients("").is_empty());
+
+        let sealed = SecretString::from(format!("{}{}", VALUE_PREFIX, BASE64.encode(b"age")));
+        assert_eq!(unwrap("API_KEY", &sealed).unwrap(), b"age");
+
+        // Plaintext written by other tools is refused rather than returned
+        let err = unwrap("API_KEY", &SecretString::from("sk-123")).unwrap_err();
+        assert!(matches!(err, SecretSpecError::PermissionDenied(_)));
+        assert!(unwrap("API_KEY", &SecretString::from("fido2:not base64!")).is_err());
+    }
+}
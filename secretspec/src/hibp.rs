@@ -0,0 +1,153 @@
+//! Have I Been Pwned checks for passwords
+//!
+//! `secretspec analyze --hibp` checks the secrets declared with
+//! `type = "password"` against the Pwned Passwords range API. Only the first
+//! five hex characters of a password's SHA-1 digest are sent; the API answers
+//! with the suffixes of every breached password sharing that prefix, and the
+//! match happens locally, so neither the password nor its full digest leave
+//! the machine. Responses are padded with fake suffixes so their size doesn't
+//! hint at the prefix either. Requests use the `curl` CLI.
+
+use crate::Result;
+use crate::analyze::{Finding, Sample};
+use crate::config::SecretKind;
+use crate::provider::command::{self, CommandRunner, Invocation};
+use secrecy::ExposeSecret;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+
+/// Endpoint of the range API, followed by a five character prefix.
+const RANGE_URL: &str = "https://api.pwnedpasswords.com/range/";
+
+/// Checks the password samples against known breaches, returning a finding
+/// for each breached one.
+///
+/// Defaults from the spec aren't checked, and each distinct password is
+/// looked up once.
+///
+/// # Errors
+///
+/// Returns an error if curl is missing or the API can't be reached
+pub(crate) fn breached(samples: &[Sample], runner: &dyn CommandRunner) -> Result<Vec<Finding>> {
+    let mut counts: HashMap<[u8; 20], u64> = HashMap::new();
+    let mut findings = Vec::new();
+    for sample in samples {
+        let value = sample.value.expose_secret();
+        if sample.kind != Some(SecretKind::Password)
+            || sample.provider.is_none()
+            || value.is_empty()
+        {
+            continue;
+        }
+        // SHA-1 is broken for signatures, but it is what the API is keyed
+        // by, and only a prefix of the digest is sent
+        let digest: [u8; 20] = Sha1::digest(value.as_bytes()).into();
+        let occurrences = match counts.get(&digest) {
+            Some(occurrences) => *occurrences,
+            None => {
+                let occurrences = occurrences(&digest, runner)?;
+                counts.insert(digest, occurrences);
+                occurrences
+            }
+        };
+        if occurrences > 0 {
+            findings.push(Finding::Breached {
+                location: sample.location.clone(),
+                occurrences,
+            });
+        }
+    }
+    Ok(findings)
+}
+
+/// Returns how often the password with this SHA-1 digest appears in known
+/// breaches, sending only the first five hex characters of the digest.
+fn occurrences(digest: &[u8; 20], runner: &dyn CommandRunner) -> Result<u64> {
+    let hex: String = digest.iter().map(|byte| format!("{:02X}", byte)).collect();
+    let (prefix, suffix) = hex.split_at(5);
+    let invocation = Invocation::new("curl").args([
+        "--fail",
+        "--silent",
+        "--show-error",
+        "--max-time",
+        "10",
+        "--header",
+        "Add-Padding: true",
+        "--user-agent",
+        "secretspec",
+        &format!("{}{}", RANGE_URL, prefix),
+    ]);
+    let response = command::curl(
+        runner,
+        &invocation,
+        "check passwords against Have I Been Pwned",
+        "Have I Been Pwned lookup failed",
+    )?;
+    Ok(count(&response, suffix))
+}
+
+/// Finds a suffix in a range response of `SUFFIX:COUNT` lines. Padding
+/// lines have a count of 0.
+fn count(response: &str, suffix: &str) -> u64 {
+    response
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyze::Location;
+    use crate::provider::command::FakeRunner;
+    use secrecy::SecretString;
+
+    #[test]
+    fn test_breached() {
+        let sample = |secret: &str, kind: Option<SecretKind>, value: &str| Sample {
+            location: Location {
+                profile: "default".to_string(),
+                secret: secret.to_string(),
+            },
+            provider: Some("keyring".to_string()),
+            kind,
+            value: SecretString::from(value),
+        };
+        // SHA-1 of "P@ssw0rd" is 21BD12DC183F740EE76F27B78EB39C8AD972A757
+        let runner = FakeRunner::new().ok(
+            &["curl", "*", "*", "*", "*", "*", "*", "*", "*", "*", "*"],
+            "0018A45C4D1DEF81644B54AB7F969B88D65:0\r\n\
+             2DC183F740EE76F27B78EB39C8AD972A757:52579\r\n",
+        );
+        let findings = breached(
+            &[
+                sample("DB_PASSWORD", Some(SecretKind::Password), "P@ssw0rd"),
+                sample("ADMIN_PASSWORD", Some(SecretKind::Password), "P@ssw0rd"),
+                sample("API_KEY", None, "P@ssw0rd"),
+            ],
+            &runner,
+        )
+        .unwrap();
+        assert!(runner.is_exhausted());
+        assert_eq!(
+            runner.calls()[0].args.last().unwrap(),
+            "https://api.pwnedpasswords.com/range/21BD1"
+        );
+        assert_eq!(findings.len(), 2);
+        assert!(matches!(
+            &findings[0],
+            Finding::Breached { location, occurrences: 52579 } if location.secret == "DB_PASSWORD"
+        ));
+    }
+
+    #[test]
+    fn test_count() {
+        let response =
+            "0018A45C4D1DEF81644B54AB7F969B88D65:1\n00D4F6E8FA6EECAD2A3AA415EEC418D38EC:0";
+        assert_eq!(count(response, "0018A45C4D1DEF81644B54AB7F969B88D65"), 1);
+        assert_eq!(count(response, "00D4F6E8FA6EECAD2A3AA415EEC418D38EC"), 0);
+        assert_eq!(count(response, "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"), 0);
+    }
+}
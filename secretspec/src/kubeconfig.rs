@@ -0,0 +1,145 @@
+//! Kubeconfig assembly
+//!
+//! Specs can name the secrets holding a cluster's credentials in
+//! `[run.kubeconfig]`:
+//!
+//! ```toml
+//! [run.kubeconfig]
+//! server = "KUBE_SERVER"
+//! certificate_authority = "KUBE_CA"
+//! token = "KUBE_TOKEN"
+//! ```
+//!
+//! `secretspec run` then assembles a kubeconfig with a single cluster, user
+//! and context from the profile's values, and points `KUBECONFIG` at it for
+//! the duration of the command. kubectl reads JSON as well as YAML, so the
+//! file is rendered as JSON.
+
+use crate::config::KubeconfigConfig;
+use crate::{Result, SecretSpecError};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::json;
+use std::collections::HashMap;
+use zeroize::Zeroizing;
+
+/// Name of the rendered file.
+pub(crate) const FILE_NAME: &str = "kubeconfig";
+
+/// Variable kubectl finds the file through.
+pub(crate) const VARIABLE: &str = "KUBECONFIG";
+
+/// Renders the kubeconfig of `config` from the resolved `secrets`.
+///
+/// The cluster, user and context are all called `name`, which is the
+/// project and profile the credentials belong to.
+///
+/// # Errors
+///
+/// Returns an error if the server or token secret has no value, or the CA
+/// certificate is neither PEM nor base64-encoded PEM
+pub(crate) fn render(
+    config: &KubeconfigConfig,
+    name: &str,
+    secrets: &HashMap<String, SecretString>,
+) -> Result<Zeroizing<String>> {
+    let value = |secret: &str| {
+        secrets
+            .get(secret)
+            .map(|value| value.expose_secret())
+            .ok_or_else(|| SecretSpecError::RequiredSecretMissing(secret.to_string()))
+    };
+
+    let mut cluster = json!({ "server": value(&config.server)?.trim() });
+    if let Some(secret) = &config.certificate_authority
+        && let Some(ca) = secrets.get(secret)
+    {
+        cluster["certificate-authority-data"] = json!(ca_data(secret, ca.expose_secret())?);
+    }
+    let mut context = json!({ "cluster": name, "user": name });
+    if let Some(namespace) = &config.namespace {
+        context["namespace"] = json!(namespace);
+    }
+
+    let kubeconfig = json!({
+        "apiVersion": "v1",
+        "kind": "Config",
+        "clusters": [{ "name": name, "cluster": cluster }],
+        "users": [{ "name": name, "user": { "token": value(&config.token)?.trim() } }],
+        "contexts": [{ "name": name, "context": context }],
+        "current-context": name,
+    });
+    Ok(Zeroizing::new(serde_json::to_string_pretty(&kubeconfig)?))
+}
+
+/// Returns a CA certificate in the base64-encoded form kubeconfigs embed.
+fn ca_data(secret: &str, value: &str) -> Result<String> {
+    let value = value.trim();
+    if value.starts_with("-----BEGIN") {
+        return Ok(BASE64.encode(value));
+    }
+    match BASE64.decode(value) {
+        Ok(decoded) if decoded.starts_with(b"-----BEGIN") => Ok(value.to_string()),
+        _ => Err(SecretSpecError::InvalidInput(format!(
+            "'{}' is not a PEM certificate or base64-encoded PEM",
+            secret
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let config = KubeconfigConfig {
+            server: "KUBE_SERVER".to_string(),
+            certificate_authority: Some("KUBE_CA".to_string()),
+            token: "KUBE_TOKEN".to_string(),
+            namespace: Some("web".to_string()),
+        };
+        let pem = "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----";
+        let mut secrets = HashMap::from([
+            (
+                "KUBE_SERVER".to_string(),
+                SecretString::from("https://k8s.example.com:6443"),
+            ),
+            ("KUBE_CA".to_string(), SecretString::from(pem)),
+            ("KUBE_TOKEN".to_string(), SecretString::from("tok\n")),
+        ]);
+
+        let rendered = render(&config, "app-production", &secrets).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(value["current-context"], "app-production");
+        assert_eq!(
+            value["clusters"][0]["cluster"]["server"],
+            "https://k8s.example.com:6443"
+        );
+        assert_eq!(
+            value["clusters"][0]["cluster"]["certificate-authority-data"],
+            BASE64.encode(pem)
+        );
+        assert_eq!(value["users"][0]["user"]["token"], "tok");
+        assert_eq!(value["contexts"][0]["context"]["namespace"], "web");
+
+        // Already encoded certificates are embedded as they are
+        secrets.insert(
+            "KUBE_CA".to_string(),
+            SecretString::from(BASE64.encode(pem)),
+        );
+        let rendered = render(&config, "app-production", &secrets).unwrap();
+        assert!(rendered.contains(&BASE64.encode(pem)));
+
+        secrets.insert("KUBE_CA".to_string(), SecretString::from("not a cert"));
+        assert!(render(&config, "app-production", &secrets).is_err());
+
+        secrets.remove("KUBE_CA");
+        secrets.remove("KUBE_TOKEN");
+        assert!(matches!(
+            render(&config, "app-production", &secrets),
+            Err(SecretSpecError::RequiredSecretMissing(name)) if name == "KUBE_TOKEN"
+        ));
+    }
+}
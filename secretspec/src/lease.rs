@@ -0,0 +1,188 @@
+//! Lease renewal for short-lived credentials
+//!
+//! Providers such as Vault's dynamic secrets engines hand out credentials
+//! that expire with their lease. While `secretspec run` waits for its child
+//! process, a [`LeaseKeeper`] renews every renewable lease once two thirds of
+//! its time to live have passed, and revokes all leases when the child exits
+//! so the credentials don't outlive it.
+
+use crate::provider::{Lease, Provider};
+use crate::warning;
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Shortest delay between renewals, so a tiny TTL can't cause a busy loop.
+const MIN_RENEW_DELAY: Duration = Duration::from_millis(10);
+
+/// Returns how long to wait before renewing a lease with the given TTL.
+fn renew_delay(ttl: Duration) -> Duration {
+    (ttl * 2 / 3).max(MIN_RENEW_DELAY)
+}
+
+/// Keeps a provider's leases alive in a background thread.
+pub(crate) struct LeaseKeeper {
+    /// Stops the renewal thread when sent to or dropped
+    stop: Sender<()>,
+    /// The renewal thread, which returns the latest version of every lease
+    thread: JoinHandle<Vec<Lease>>,
+    /// The provider that issued the leases
+    provider: Arc<dyn Provider>,
+}
+
+impl LeaseKeeper {
+    /// Starts renewing the leases behind the values `provider` returned.
+    ///
+    /// Returns `None` if the provider issued no leases.
+    pub fn start(provider: Arc<dyn Provider>) -> Option<Self> {
+        let leases = provider.leases();
+        if leases.is_empty() {
+            return None;
+        }
+
+        let (stop, stopped) = mpsc::channel();
+        let renewer = Arc::clone(&provider);
+        let thread = std::thread::spawn(move || {
+            let mut leases: Vec<(Lease, Instant)> = leases
+                .into_iter()
+                .map(|lease| {
+                    let due = Instant::now() + renew_delay(lease.ttl);
+                    (lease, due)
+                })
+                .collect();
+
+            loop {
+                let next = leases
+                    .iter()
+                    .filter(|(lease, _)| lease.renewable)
+                    .map(|(_, due)| *due)
+                    .min();
+                let wait = match next {
+                    Some(due) => due.saturating_duration_since(Instant::now()),
+                    None => Duration::MAX,
+                };
+
+                match stopped.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                let now = Instant::now();
+                for (lease, due) in leases
+                    .iter_mut()
+                    .filter(|(lease, due)| lease.renewable && *due <= now)
+                {
+                    match renewer.renew_lease(lease) {
+                        Ok(renewed) => {
+                            *due = now + renew_delay(renewed.ttl);
+                            *lease = renewed;
+                        }
+                        Err(e) => {
+                            warning::warn(format!("Failed to renew lease {}: {}", lease.id, e));
+                            lease.renewable = false;
+                        }
+                    }
+                }
+            }
+
+            leases.into_iter().map(|(lease, _)| lease).collect()
+        });
+
+        Some(Self {
+            stop,
+            thread,
+            provider,
+        })
+    }
+
+    /// Stops renewing and revokes every lease.
+    ///
+    /// Revocation failures are reported as warnings, since the credentials
+    /// still expire on their own.
+    pub fn revoke(self) {
+        let _ = self.stop.send(());
+        let leases = self.thread.join().unwrap_or_default();
+        for lease in leases {
+            if let Err(e) = self.provider.revoke_lease(&lease) {
+                warning::warn(format!("Failed to revoke lease {}: {}", lease.id, e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+    use secrecy::SecretString;
+    use std::sync::Mutex;
+
+    /// Provider that issues a single lease and records renewals and revocations.
+    #[derive(Default)]
+    struct LeasingProvider {
+        renewed: Mutex<u32>,
+        revoked: Mutex<Vec<String>>,
+    }
+
+    impl Provider for LeasingProvider {
+        fn name(&self) -> &'static str {
+            "leasing"
+        }
+
+        fn get(&self, _: &str, _: &str, _: &str) -> Result<Option<SecretString>> {
+            Ok(None)
+        }
+
+        fn set(&self, _: &str, _: &str, _: &SecretString, _: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn leases(&self) -> Vec<Lease> {
+            vec![Lease {
+                id: "creds/1".to_string(),
+                ttl: Duration::from_millis(30),
+                renewable: true,
+            }]
+        }
+
+        fn renew_lease(&self, lease: &Lease) -> Result<Lease> {
+            *self.renewed.lock().unwrap() += 1;
+            Ok(lease.clone())
+        }
+
+        fn revoke_lease(&self, lease: &Lease) -> Result<()> {
+            self.revoked.lock().unwrap().push(lease.id.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_renew_delay() {
+        assert_eq!(
+            renew_delay(Duration::from_secs(3600)),
+            Duration::from_secs(2400)
+        );
+        assert_eq!(renew_delay(Duration::ZERO), MIN_RENEW_DELAY);
+    }
+
+    #[test]
+    fn test_leases_are_renewed_then_revoked() {
+        let provider = Arc::new(LeasingProvider::default());
+        let keeper = LeaseKeeper::start(provider.clone()).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        keeper.revoke();
+
+        assert!(*provider.renewed.lock().unwrap() >= 2);
+        assert_eq!(
+            *provider.revoked.lock().unwrap(),
+            vec!["creds/1".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_no_keeper_without_leases() {
+        let provider = crate::provider::tests::MockProvider::new();
+        assert!(LeaseKeeper::start(Arc::new(provider)).is_none());
+    }
+}
@@ -41,13 +41,67 @@
 //! ```
 
 // Internal modules
+#[cfg(unix)]
+mod agent;
+mod analyze;
+mod cargo;
+mod cloudsdk;
 mod config;
+mod conformance;
+mod dbfiles;
+mod devenv;
+mod docker;
+mod doctor;
+mod envaudit;
 mod error;
+mod explain;
+mod export;
+mod generator;
+mod graph;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod hardening;
+mod hardware;
+mod hibp;
+mod kubeconfig;
+mod lease;
+mod lockfile;
+mod matrix;
+mod memory;
+mod nix;
+mod notify;
+mod npmrc;
+mod output;
+mod plan;
+#[cfg(unix)]
+mod presence;
+mod prompt;
+mod redact;
+mod reference;
+mod runfiles;
+mod schema;
 mod secrets;
+mod stats;
+mod sts;
+mod suggest;
+mod systemd;
+mod telemetry;
+mod terraform;
+mod totp;
+mod tpm;
+mod transform;
+mod ttl;
+mod usage;
 mod validation;
+mod warning;
+mod watch;
 
 pub(crate) mod provider;
 
+// Provider benchmarks, shared by the CLI and the criterion benches
+#[doc(hidden)]
+pub mod bench;
+
 // CLI module (feature-gated)
 #[cfg(feature = "cli")]
 pub mod cli;
@@ -57,16 +111,35 @@ pub use config::Resolved;
 
 // Re-export config types for CLI usage only - these are marked #[doc(hidden)]
 #[doc(hidden)]
-pub use config::{Config, GlobalConfig, GlobalDefaults, Profile, Project};
+pub use config::{
+    AssumeRole, AwsCredentialsConfig, Config, GcloudCredentialsConfig, GlobalConfig,
+    GlobalDefaults, KeysConfig, KubeconfigConfig, NotifyConfig, Profile, ProfileDefaults, Project,
+    ProjectDefaults, RunConfig, SpecFormat,
+};
 
 // Re-export Secret for secretspec-derive
 #[doc(hidden)]
-pub use config::Secret;
+pub use config::{Generator, RequiredIf, Secret, SecretKind};
 
 // Public API exports
+pub use analyze::{Finding, Location};
+pub use devenv::HookTarget;
+pub use envaudit::EnvFinding;
 pub use error::{Result, SecretSpecError};
+pub use explain::{Step, Trace};
+pub use export::ExportFormat;
+pub use matrix::{Matrix, MatrixFormat, Presence, ProfileColumn};
+pub use output::set_output_handler;
+pub use plan::{Plan, PlanAction, PlanFormat, PlanOperation, PlannedChange};
+pub use provider::audited::AuditEvent;
+pub use provider::builder::{Layer, ProviderBuilder};
+#[cfg(target_arch = "wasm32")]
+pub use provider::vault::fetch::set_token as set_vault_token;
+pub use provider::{Provider, SecretMetadata, SecretValue};
 pub use secrets::Secrets;
+pub use usage::Usage;
 pub use validation::ValidatedSecrets;
+pub use warning::set_warning_handler;
 
 #[cfg(test)]
 mod tests;
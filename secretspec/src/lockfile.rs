@@ -0,0 +1,398 @@
+//! Project-local lockfile recording which provider satisfied each secret
+//!
+//! `secretspec check` writes a `secretspec.lock` next to `secretspec.toml`
+//! containing, for every secret of the checked profile, the provider it was
+//! resolved from and a salted Argon2id fingerprint of its value:
+//!
+//! ```toml
+//! version = 1
+//! salt = "c2VjcmV0c3BlYy1zYWx0IQ=="
+//!
+//! [profiles.default.DATABASE_URL]
+//! provider = "keyring"
+//! fingerprint = "argon2id:5e8c…"
+//! ```
+//!
+//! With `secretspec check --frozen` the lockfile is not written; instead any
+//! difference from the recorded state is an error, which lets CI detect
+//! unexpected secret drift.
+//!
+//! # Threat model
+//!
+//! The lockfile is meant to be committed, so anyone who can read the
+//! repository can test guesses of a value against its fingerprint. CI has to
+//! recompute fingerprints without any key of its own, so they can't be keyed
+//! with a secret kept off the repository; instead they are made expensive to
+//! guess:
+//!
+//! - The salt is generated once per lockfile, so precomputed tables don't
+//!   apply and fingerprints can't be compared across projects.
+//! - The secret name is part of the salt, so two secrets with the same value
+//!   don't share a fingerprint.
+//! - Argon2id with the parameters recommended by OWASP costs each guess
+//!   19 MiB of memory and two passes over it.
+//!
+//! Random API keys and generated passwords are out of reach of guessing.
+//! Values a person chose, like short passwords or PINs, may still be found by
+//! a dictionary attack; projects that can't accept that shouldn't commit the
+//! lockfile.
+//!
+//! `secretspec adopt` records the IDs of the provider items it took over
+//! in the same file, so they are addressed by ID rather than found by name:
+//!
+//! ```toml
+//! [items.production]
+//! STRIPE_API_KEY = "4d1f3a6e-..."
+//! ```
+
+use crate::error::{Result, SecretSpecError};
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The lockfile format version written by this release.
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Number of random bytes used for the fingerprint salt.
+const SALT_LENGTH: usize = 16;
+
+/// Number of bytes of a fingerprint.
+const FINGERPRINT_LENGTH: usize = 32;
+
+/// Provider name recorded for secrets resolved from their `default` value.
+pub(crate) const DEFAULT_VALUE_PROVIDER: &str = "default";
+
+/// Header written at the top of every lockfile.
+const LOCKFILE_HEADER: &str = "# This file is generated by `secretspec check` and `secretspec adopt`. Do not edit it by hand.\n\
+     # It contains salted Argon2id fingerprints of secret values, never the values themselves.\n\n";
+
+/// The contents of a `secretspec.lock` file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Lockfile {
+    /// Lockfile format version
+    pub version: u32,
+    /// Base64-encoded salt mixed into every fingerprint
+    pub salt: String,
+    /// Locked secrets, keyed by profile name and then secret name
+    #[serde(default)]
+    pub profiles: BTreeMap<String, BTreeMap<String, LockedSecret>>,
+    /// IDs of adopted provider items, keyed by profile name and then secret
+    /// name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub items: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// The recorded state of a single secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct LockedSecret {
+    /// Name of the provider that satisfied the secret, or `default`
+    pub provider: String,
+    /// Salted fingerprint of the value, formatted as `argon2id:<hex>`
+    pub fingerprint: String,
+}
+
+/// A single difference between the lockfile and the current state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Drift {
+    /// The secret is resolved now but was not recorded
+    Added(String),
+    /// The secret was recorded but is no longer resolved
+    Removed(String),
+    /// The secret is now satisfied by a different provider
+    ProviderChanged {
+        name: String,
+        locked: String,
+        current: String,
+    },
+    /// The secret's value no longer matches the recorded fingerprint
+    ValueChanged(String),
+}
+
+impl Drift {
+    /// Returns the name of the secret this difference refers to.
+    fn name(&self) -> &str {
+        match self {
+            Drift::Added(name) | Drift::Removed(name) | Drift::ValueChanged(name) => name,
+            Drift::ProviderChanged { name, .. } => name,
+        }
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::Added(name) => write!(f, "{} is not in the lockfile", name),
+            Drift::Removed(name) => write!(f, "{} is in the lockfile but no longer resolved", name),
+            Drift::ProviderChanged {
+                name,
+                locked,
+                current,
+            } => write!(
+                f,
+                "{} was provided by {} but is now provided by {}",
+                name, locked, current
+            ),
+            Drift::ValueChanged(name) => write!(f, "{} has changed", name),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Creates an empty lockfile with a freshly generated salt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the operating system's random number generator fails
+    pub fn new() -> Result<Self> {
+        let mut salt = [0u8; SALT_LENGTH];
+        random(&mut salt)?;
+        Ok(Self {
+            version: LOCKFILE_VERSION,
+            salt: BASE64.encode(salt),
+            profiles: BTreeMap::new(),
+            items: BTreeMap::new(),
+        })
+    }
+
+    /// Loads a lockfile, returning `None` if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, cannot be parsed, or was
+    /// written by a newer version of secretspec
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            // No file system, as on wasm32-unknown-unknown
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let lockfile: Lockfile = toml::from_str(&content)?;
+        if lockfile.version > LOCKFILE_VERSION {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "{} has version {}, but this version of secretspec only supports version {}",
+                path.display(),
+                lockfile.version,
+                LOCKFILE_VERSION
+            )));
+        }
+        Ok(Some(lockfile))
+    }
+
+    /// Writes the lockfile to disk.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string(self)?;
+        fs::write(path, format!("{}{}", LOCKFILE_HEADER, content))?;
+        Ok(())
+    }
+
+    /// Computes the salted Argon2id fingerprint of a secret value.
+    ///
+    /// The secret name is part of the salt so that two secrets with the same
+    /// value do not share a fingerprint.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Argon2id fails, which only happens if it can't
+    /// allocate its memory
+    pub fn fingerprint(&self, name: &str, value: &SecretString) -> Result<String> {
+        let mut salt = self.salt.as_bytes().to_vec();
+        salt.push(0);
+        salt.extend_from_slice(name.as_bytes());
+        let mut digest = [0u8; FINGERPRINT_LENGTH];
+        Argon2::default()
+            .hash_password_into(value.expose_secret().as_bytes(), &salt, &mut digest)
+            .map_err(|e| {
+                SecretSpecError::Io(io::Error::other(format!(
+                    "Failed to fingerprint '{}': {}",
+                    name, e
+                )))
+            })?;
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        Ok(format!("argon2id:{}", hex))
+    }
+
+    /// Builds the locked entries for a profile from resolved secrets.
+    ///
+    /// # Arguments
+    ///
+    /// * `secrets` - The resolved secret values
+    /// * `providers` - The provider that satisfied each secret
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value can't be fingerprinted
+    pub fn lock_secrets(
+        &self,
+        secrets: &HashMap<String, SecretString>,
+        providers: &HashMap<String, String>,
+    ) -> Result<BTreeMap<String, LockedSecret>> {
+        secrets
+            .iter()
+            .map(|(name, value)| {
+                let provider = providers
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| DEFAULT_VALUE_PROVIDER.to_string());
+                Ok((
+                    name.clone(),
+                    LockedSecret {
+                        provider,
+                        fingerprint: self.fingerprint(name, value)?,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Compares the recorded entries of a profile with the current entries.
+    ///
+    /// Returns the differences sorted by secret name; an empty result means the
+    /// profile is unchanged.
+    pub fn diff(&self, profile: &str, current: &BTreeMap<String, LockedSecret>) -> Vec<Drift> {
+        let empty = BTreeMap::new();
+        let locked = self.profiles.get(profile).unwrap_or(&empty);
+        let mut drift = Vec::new();
+
+        for (name, entry) in current {
+            match locked.get(name) {
+                None => drift.push(Drift::Added(name.clone())),
+                Some(locked_entry) if locked_entry.provider != entry.provider => {
+                    drift.push(Drift::ProviderChanged {
+                        name: name.clone(),
+                        locked: locked_entry.provider.clone(),
+                        current: entry.provider.clone(),
+                    })
+                }
+                Some(locked_entry) if locked_entry.fingerprint != entry.fingerprint => {
+                    drift.push(Drift::ValueChanged(name.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for name in locked.keys() {
+            if !current.contains_key(name) {
+                drift.push(Drift::Removed(name.clone()));
+            }
+        }
+
+        drift.sort_by(|a, b| a.name().cmp(b.name()));
+        drift
+    }
+}
+
+/// Fills `bytes` from the operating system's random number generator.
+#[cfg(feature = "native")]
+fn random(bytes: &mut [u8]) -> Result<()> {
+    getrandom::getrandom(bytes).map_err(|e| {
+        SecretSpecError::Io(io::Error::other(format!(
+            "Failed to generate lockfile salt: {}",
+            e
+        )))
+    })
+}
+
+#[cfg(not(feature = "native"))]
+fn random(_bytes: &mut [u8]) -> Result<()> {
+    Err(SecretSpecError::Io(io::Error::other(
+        "Generating a lockfile salt needs the native feature",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(value: &str) -> SecretString {
+        SecretString::new(value.to_string().into())
+    }
+
+    #[test]
+    fn test_fingerprint_is_salted_and_keyed() {
+        let a = Lockfile::new().unwrap();
+        let b = Lockfile::new().unwrap();
+        let value = secret("hunter2");
+
+        let fingerprint = a.fingerprint("API_KEY", &value).unwrap();
+
+        assert_eq!(fingerprint, a.fingerprint("API_KEY", &value).unwrap());
+        assert_ne!(fingerprint, b.fingerprint("API_KEY", &value).unwrap());
+        assert_ne!(fingerprint, a.fingerprint("OTHER_KEY", &value).unwrap());
+        assert!(fingerprint.starts_with("argon2id:"));
+        assert!(!fingerprint.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_diff_detects_drift() {
+        let mut lockfile = Lockfile::new().unwrap();
+        let mut secrets = HashMap::new();
+        secrets.insert("A".to_string(), secret("one"));
+        secrets.insert("B".to_string(), secret("two"));
+        secrets.insert("C".to_string(), secret("three"));
+        let mut providers = HashMap::new();
+        providers.insert("A".to_string(), "keyring".to_string());
+        providers.insert("B".to_string(), "keyring".to_string());
+        providers.insert("C".to_string(), "keyring".to_string());
+
+        let locked = lockfile.lock_secrets(&secrets, &providers).unwrap();
+        lockfile
+            .profiles
+            .insert("default".to_string(), locked.clone());
+        assert!(lockfile.diff("default", &locked).is_empty());
+
+        secrets.insert("A".to_string(), secret("changed"));
+        secrets.remove("B");
+        secrets.insert("D".to_string(), secret("four"));
+        providers.insert("C".to_string(), "dotenv".to_string());
+        providers.insert("D".to_string(), "keyring".to_string());
+
+        let drift = lockfile.diff(
+            "default",
+            &lockfile.lock_secrets(&secrets, &providers).unwrap(),
+        );
+        assert_eq!(
+            drift,
+            vec![
+                Drift::ValueChanged("A".to_string()),
+                Drift::Removed("B".to_string()),
+                Drift::ProviderChanged {
+                    name: "C".to_string(),
+                    locked: "keyring".to_string(),
+                    current: "dotenv".to_string(),
+                },
+                Drift::Added("D".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secretspec.lock");
+        assert!(Lockfile::load(&path).unwrap().is_none());
+
+        let mut lockfile = Lockfile::new().unwrap();
+        let mut secrets = HashMap::new();
+        secrets.insert("A".to_string(), secret("one"));
+        let locked = lockfile.lock_secrets(&secrets, &HashMap::new()).unwrap();
+        assert_eq!(locked["A"].provider, DEFAULT_VALUE_PROVIDER);
+        lockfile.profiles.insert("default".to_string(), locked);
+        lockfile.items.insert(
+            "default".to_string(),
+            BTreeMap::from([("A".to_string(), "item-1".to_string())]),
+        );
+        lockfile.save(&path).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("one\""));
+        assert_eq!(Lockfile::load(&path).unwrap().unwrap(), lockfile);
+    }
+}
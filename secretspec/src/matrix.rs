@@ -0,0 +1,214 @@
+//! Presence of every secret across profiles
+//!
+//! `secretspec check --all-profiles` checks each profile against the
+//! provider it uses, concurrently, and collects which secrets are set,
+//! fall back to their default or are missing. Compliance reviews asking
+//! whether every production secret is actually set get the answer as CSV
+//! or JSON. Only presence is recorded, never values.
+
+use crate::{Result, SecretSpecError};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+/// Whether a secret of a profile has a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    /// Stored with the provider
+    Set,
+    /// Not stored, but the declaration has a default
+    Default,
+    /// Required and not stored
+    Missing,
+    /// Optional and not stored
+    Unset,
+}
+
+impl Presence {
+    /// Returns the name used in CSV and JSON.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Presence::Set => "set",
+            Presence::Default => "default",
+            Presence::Missing => "missing",
+            Presence::Unset => "unset",
+        }
+    }
+}
+
+/// The secrets of one profile, checked against its provider.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProfileColumn {
+    /// The profile
+    pub profile: String,
+    /// Name of the provider the profile uses, if one could be created
+    pub provider: Option<String>,
+    /// Why the profile couldn't be checked, if it couldn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Presence of the secrets the profile declares, by name
+    pub secrets: BTreeMap<String, Presence>,
+}
+
+impl ProfileColumn {
+    /// Returns the column of a profile that couldn't be checked.
+    pub(crate) fn failed(profile: &str, provider: Option<String>, error: SecretSpecError) -> Self {
+        Self {
+            profile: profile.to_string(),
+            provider,
+            error: Some(error.to_string()),
+            secrets: BTreeMap::new(),
+        }
+    }
+}
+
+/// Presence of the secrets of every profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Matrix {
+    /// The profiles, sorted by name
+    pub profiles: Vec<ProfileColumn>,
+}
+
+impl Matrix {
+    /// Returns the names of the secrets any profile declares, sorted.
+    pub fn secrets(&self) -> BTreeSet<&str> {
+        self.profiles
+            .iter()
+            .flat_map(|column| column.secrets.keys().map(String::as_str))
+            .collect()
+    }
+
+    /// Returns whether every profile was checked and has all its required
+    /// secrets.
+    pub fn is_complete(&self) -> bool {
+        self.profiles.iter().all(|column| {
+            column.error.is_none()
+                && !column
+                    .secrets
+                    .values()
+                    .any(|presence| *presence == Presence::Missing)
+        })
+    }
+
+    /// Renders one `secret,profile,provider,status` row per secret and
+    /// profile. Secrets a profile doesn't declare have the status
+    /// `undeclared`, and those of profiles that couldn't be checked `error`.
+    pub(crate) fn to_csv(&self) -> String {
+        let mut csv = String::from("secret,profile,provider,status\n");
+        for secret in self.secrets() {
+            for column in &self.profiles {
+                let status = match (&column.error, column.secrets.get(secret)) {
+                    (Some(_), _) => "error",
+                    (None, Some(presence)) => presence.as_str(),
+                    (None, None) => "undeclared",
+                };
+                let row = [
+                    secret,
+                    &column.profile,
+                    column.provider.as_deref().unwrap_or(""),
+                    status,
+                ];
+                let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+        }
+        csv
+    }
+
+    /// Renders the matrix as JSON.
+    pub(crate) fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A format the matrix can be printed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixFormat {
+    /// A table with a row per secret and a column per profile
+    Table,
+    /// A row per secret and profile
+    Csv,
+    /// The profiles with the presence of their secrets
+    Json,
+}
+
+impl FromStr for MatrixFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+        match format {
+            "table" => Ok(MatrixFormat::Table),
+            "csv" => Ok(MatrixFormat::Csv),
+            "json" => Ok(MatrixFormat::Json),
+            _ => Err(format!(
+                "Unknown matrix format '{}'. Use table, csv or json.",
+                format
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix() -> Matrix {
+        Matrix {
+            profiles: vec![
+                ProfileColumn {
+                    profile: "default".to_string(),
+                    provider: Some("dotenv".to_string()),
+                    error: None,
+                    secrets: BTreeMap::from([
+                        ("API_KEY".to_string(), Presence::Set),
+                        ("LOG_LEVEL".to_string(), Presence::Default),
+                    ]),
+                },
+                ProfileColumn {
+                    profile: "production".to_string(),
+                    provider: Some("onepassword".to_string()),
+                    error: None,
+                    secrets: BTreeMap::from([("API_KEY".to_string(), Presence::Missing)]),
+                },
+                ProfileColumn::failed("staging", None, SecretSpecError::NoProviderConfigured),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_matrix_csv() {
+        let matrix = matrix();
+        assert!(!matrix.is_complete());
+        assert_eq!(
+            matrix.to_csv(),
+            "secret,profile,provider,status\n\
+             API_KEY,default,dotenv,set\n\
+             API_KEY,production,onepassword,missing\n\
+             API_KEY,staging,,error\n\
+             LOG_LEVEL,default,dotenv,default\n\
+             LOG_LEVEL,production,onepassword,undeclared\n\
+             LOG_LEVEL,staging,,error\n"
+        );
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_matrix_json() {
+        let json: serde_json::Value = serde_json::from_str(&matrix().to_json().unwrap()).unwrap();
+        assert_eq!(json["profiles"][0]["secrets"]["LOG_LEVEL"], "default");
+        assert_eq!(json["profiles"][1]["secrets"]["API_KEY"], "missing");
+        assert!(json["profiles"][0].get("error").is_none());
+        assert!(json["profiles"][2]["error"].is_string());
+    }
+}
@@ -0,0 +1,190 @@
+//! Memory hygiene for resolved secret values
+//!
+//! Values are kept in [`SecretString`]s, which zero their buffer when dropped.
+//! Intermediate copies made while reading, transforming or passing on values
+//! are held in [`Zeroizing`](zeroize::Zeroizing) buffers for the same reason.
+//!
+//! Setting `SECRETSPEC_MLOCK` additionally locks the pages holding resolved
+//! values into RAM with `mlock(2)`, so they are never written to swap, until
+//! the [`Locked`] guard holding them is dropped. Locking is best-effort: where
+//! it isn't supported, or the `RLIMIT_MEMLOCK` limit is reached, a warning is
+//! reported once and values stay unlocked.
+//!
+//! Tests install a scanning allocator that reports freed buffers still
+//! holding a canary value, see [`lingering`].
+
+use crate::warning;
+use secrecy::{ExposeSecret, SecretString};
+use std::sync::Once;
+
+/// Environment variable that enables locking resolved values into RAM.
+const MLOCK_ENV: &str = "SECRETSPEC_MLOCK";
+
+/// Returns whether resolved values are locked into RAM.
+fn mlock_enabled() -> bool {
+    std::env::var_os(MLOCK_ENV).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Pages locked into RAM, unlocked again when dropped.
+///
+/// The guard has to be dropped after the values it locked, so they are
+/// zeroed while their pages are still locked.
+#[derive(Debug, Default)]
+pub(crate) struct Locked {
+    /// Address and length of every locked range
+    ranges: Vec<(usize, usize)>,
+}
+
+impl Locked {
+    /// Locks the pages holding a value into RAM if `SECRETSPEC_MLOCK` is set.
+    pub(crate) fn lock(&mut self, value: &SecretString) {
+        let bytes = value.expose_secret().as_bytes();
+        if bytes.is_empty() || !mlock_enabled() {
+            return;
+        }
+
+        static WARNED: Once = Once::new();
+        match mlock(bytes) {
+            Ok(()) => self.ranges.push((bytes.as_ptr() as usize, bytes.len())),
+            Err(e) => WARNED.call_once(|| {
+                warning::warn(format!(
+                    "{} is set but secrets can't be locked into memory: {}",
+                    MLOCK_ENV, e
+                ));
+            }),
+        }
+    }
+}
+
+impl Drop for Locked {
+    fn drop(&mut self) {
+        for (address, len) in self.ranges.drain(..) {
+            munlock(address, len);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn mlock(bytes: &[u8]) -> std::io::Result<()> {
+    // SAFETY: the range is a live allocation borrowed for the whole call;
+    // mlock only changes how its pages are paged, not their contents
+    let result = unsafe { libc::mlock(bytes.as_ptr().cast(), bytes.len()) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(unix)]
+fn munlock(address: usize, len: usize) {
+    // SAFETY: munlock only changes how the pages are paged and never
+    // dereferences the range, which may have been freed by now
+    unsafe { libc::munlock(address as *const libc::c_void, len) };
+}
+
+#[cfg(not(unix))]
+fn mlock(_bytes: &[u8]) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "mlock is only available on Unix",
+    ))
+}
+
+#[cfg(not(unix))]
+fn munlock(_address: usize, _len: usize) {}
+
+#[cfg(test)]
+pub(crate) use scan::lingering;
+
+#[cfg(test)]
+mod scan {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+    /// Allocator that looks for a canary value in every buffer it frees.
+    struct ScanningAllocator;
+
+    static CANARY: AtomicPtr<u8> = AtomicPtr::new(std::ptr::null_mut());
+    static CANARY_LEN: AtomicUsize = AtomicUsize::new(0);
+    static HITS: AtomicUsize = AtomicUsize::new(0);
+
+    #[global_allocator]
+    static ALLOCATOR: ScanningAllocator = ScanningAllocator;
+
+    // SAFETY: allocation is delegated to the system allocator unchanged
+    unsafe impl GlobalAlloc for ScanningAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            let len = CANARY_LEN.load(Ordering::SeqCst);
+            let canary = CANARY.load(Ordering::SeqCst);
+            if len > 0 && !canary.is_null() && layout.size() >= len {
+                // SAFETY: the block is still allocated until the call below,
+                // and the canary is a 'static string
+                let (block, canary) = unsafe {
+                    (
+                        std::slice::from_raw_parts(ptr, layout.size()),
+                        std::slice::from_raw_parts(canary, len),
+                    )
+                };
+                if block.windows(len).any(|window| window == canary) {
+                    HITS.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    /// Serializes tests that watch for a canary.
+    static WATCH: Mutex<()> = Mutex::new(());
+
+    /// Runs `f` and returns how many buffers it freed while they still held
+    /// `canary`.
+    ///
+    /// Buffers that were zeroed before being freed aren't counted, so a
+    /// result of zero means no copy of the value was left behind.
+    pub(crate) fn lingering(canary: &'static str, f: impl FnOnce()) -> usize {
+        let _guard = WATCH.lock().unwrap_or_else(|e| e.into_inner());
+        HITS.store(0, Ordering::SeqCst);
+        CANARY.store(canary.as_ptr().cast_mut(), Ordering::SeqCst);
+        CANARY_LEN.store(canary.len(), Ordering::SeqCst);
+        f();
+        CANARY_LEN.store(0, Ordering::SeqCst);
+        CANARY.store(std::ptr::null_mut(), Ordering::SeqCst);
+        HITS.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lingering_detects_copies() {
+        const CANARY: &str = "canary-4f1c9e";
+        assert_eq!(
+            lingering(CANARY, || drop(CANARY.to_string().into_bytes())),
+            1
+        );
+        assert_eq!(lingering(CANARY, || drop(SecretString::from(CANARY))), 0);
+        assert_eq!(
+            lingering(CANARY, || drop(zeroize::Zeroizing::new(CANARY.to_string()))),
+            0
+        );
+    }
+
+    #[test]
+    fn test_lock() {
+        // Locking never fails the caller, whether or not it is enabled
+        let value = SecretString::from("value");
+        let mut locked = Locked::default();
+        locked.lock(&value);
+        locked.lock(&SecretString::from(""));
+        drop(value);
+        drop(locked);
+    }
+}
@@ -0,0 +1,85 @@
+//! Nix integration
+//!
+//! `secretspec nix` prints the resolved secrets as JSON for nix-shell
+//! `shellHook`s and flake dev shells. The output is deterministic: keys are
+//! sorted and only the project, profile and secrets are included, so the
+//! same values always produce the same bytes.
+//!
+//! With `--sandbox`, providers that need the network or a desktop session
+//! are refused before anything is read, since a Nix build sandbox has
+//! neither. Secrets are then resolved outside the build and read from a file.
+
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use zeroize::Zeroizing;
+
+/// How to get secrets into a sandboxed build instead.
+const SANDBOX_HINT: &str = "Resolve the secrets before the build with `secretspec export --format docker > secrets.env` and read them in the sandbox with `--provider dotenv://secrets.env`";
+
+/// Returns the error for something a sandboxed build can't reach.
+pub(crate) fn sandbox_error(what: &str) -> SecretSpecError {
+    SecretSpecError::ProviderOperationFailed(format!(
+        "{} needs network access or a desktop session, which the Nix sandbox doesn't have.\n\n{}",
+        what, SANDBOX_HINT
+    ))
+}
+
+/// The JSON printed by `secretspec nix`, borrowing the values.
+///
+/// Fields are declared in sorted order, so they are serialized sorted too.
+#[derive(Serialize)]
+struct Output<'a> {
+    profile: &'a str,
+    project: &'a str,
+    secrets: BTreeMap<&'a str, &'a str>,
+}
+
+/// Formats resolved secrets as deterministic JSON.
+///
+/// # Errors
+///
+/// Returns an error if serialization fails
+pub(crate) fn json(
+    project: &str,
+    profile: &str,
+    secrets: &HashMap<String, SecretString>,
+) -> Result<Zeroizing<String>> {
+    let output = Output {
+        profile,
+        project,
+        secrets: secrets
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.expose_secret()))
+            .collect(),
+    };
+    let mut output = Zeroizing::new(serde_json::to_string_pretty(&output)?);
+    output.push('\n');
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_is_deterministic() {
+        let secrets: HashMap<String, SecretString> = [("B", "2"), ("A", "1"), ("C", "3")]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), SecretString::from(value)))
+            .collect();
+        let output = json("app", "default", &secrets).unwrap();
+        assert_eq!(
+            output.as_str(),
+            "{\n  \"profile\": \"default\",\n  \"project\": \"app\",\n  \"secrets\": {\n    \"A\": \"1\",\n    \"B\": \"2\",\n    \"C\": \"3\"\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_sandbox_error_points_to_dotenv() {
+        let err = sandbox_error("Provider 'keyring'").to_string();
+        assert!(err.contains("Provider 'keyring' needs network access"));
+        assert!(err.contains("dotenv://"));
+    }
+}
@@ -0,0 +1,302 @@
+//! Notifications about changed secrets
+//!
+//! When the spec has a `[notify]` section, `set`, `import` and `rekey`
+//! announce what they changed to Slack, webhooks and email:
+//!
+//! ```toml
+//! [notify]
+//! profiles = ["production"]
+//! slack = ["env:SLACK_WEBHOOK_URL"]
+//! ```
+//!
+//! Messages name the project, profile, keys and the person who made the
+//! change, taken from `SECRETSPEC_ACTOR` or the login name. Values are never
+//! sent. Delivery uses the `curl` CLI; a failed notification is reported as a
+//! warning, since the change itself has already been made.
+
+use crate::config::NotifyConfig;
+use crate::provider::command::{self, CommandRunner, Invocation, SystemRunner, quote};
+use crate::warning;
+use crate::{Result, SecretSpecError};
+use serde_json::json;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable naming who makes changes, e.g. in CI.
+const ACTOR_ENV: &str = "SECRETSPEC_ACTOR";
+
+/// What happened to the secrets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// Secrets were set for the first time
+    Created,
+    /// Secrets were given new values
+    Changed,
+    /// Secrets were re-encrypted to the current recipients
+    Rotated,
+    /// Secrets were deleted
+    Deleted,
+}
+
+impl Action {
+    /// Returns the name of the action in webhook payloads.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Changed => "changed",
+            Self::Rotated => "rotated",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+/// A change to secrets of one profile.
+#[derive(Debug, Clone)]
+pub(crate) struct Change<'a> {
+    pub action: Action,
+    pub project: &'a str,
+    pub profile: &'a str,
+    /// Names of the changed secrets
+    pub keys: Vec<String>,
+    /// Name of the provider the secrets are stored in
+    pub provider: &'a str,
+}
+
+impl Change<'_> {
+    /// Returns a one-line summary of the change.
+    fn summary(&self, actor: &str) -> String {
+        format!(
+            "{} {} {} in {}/{} ({})",
+            actor,
+            self.action.as_str(),
+            self.keys.join(", "),
+            self.project,
+            self.profile,
+            self.provider
+        )
+    }
+}
+
+/// Returns who makes the change.
+fn actor() -> String {
+    [ACTOR_ENV, "USER", "USERNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Announces a change to every target configured for its profile.
+///
+/// Failures are printed as warnings.
+pub(crate) fn send(config: &NotifyConfig, change: &Change) {
+    if change.keys.is_empty() || !config.covers(change.profile) {
+        return;
+    }
+    for error in send_with(config, change, &actor(), &SystemRunner) {
+        warning::warn(format!("Failed to send notification: {}", error));
+    }
+}
+
+/// Announces a change through `runner` and returns the errors.
+fn send_with(
+    config: &NotifyConfig,
+    change: &Change,
+    actor: &str,
+    runner: &dyn CommandRunner,
+) -> Vec<SecretSpecError> {
+    let summary = change.summary(actor);
+    let slack = json!({ "text": summary }).to_string();
+    let webhook = json!({
+        "event": format!("secret.{}", change.action.as_str()),
+        "project": change.project,
+        "profile": change.profile,
+        "keys": change.keys,
+        "provider": change.provider,
+        "actor": actor,
+        "timestamp": SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    })
+    .to_string();
+
+    let mut errors = Vec::new();
+    let posts = config
+        .slack
+        .iter()
+        .map(|target| (target, &slack))
+        .chain(config.webhook.iter().map(|target| (target, &webhook)));
+    for (target, payload) in posts {
+        if let Err(e) = resolve(target).and_then(|url| post(runner, &url, payload)) {
+            errors.push(e);
+        }
+    }
+    if !config.email.is_empty()
+        && let Err(e) = email(config, &summary, runner)
+    {
+        errors.push(e);
+    }
+    errors
+}
+
+/// Resolves a target that may be given as `env:NAME`.
+fn resolve(target: &str) -> Result<String> {
+    match target.strip_prefix("env:") {
+        Some(name) => std::env::var(name).map_err(|_| {
+            SecretSpecError::InvalidInput(format!("notification target {} is not set", name))
+        }),
+        None => Ok(target.to_string()),
+    }
+}
+
+/// POSTs a JSON payload to a URL.
+///
+/// The URL is passed on standard input, since webhook URLs embed a token.
+fn post(runner: &dyn CommandRunner, url: &str, payload: &str) -> Result<()> {
+    let invocation = Invocation::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--header",
+            "Content-Type: application/json",
+            "--data-binary",
+            payload,
+            "--config",
+            "-",
+        ])
+        .stdin(format!("url = {}\n", quote(url)));
+    run_curl(runner, &invocation)
+}
+
+/// Sends the summary by email through the configured SMTP server.
+///
+/// The SMTP URL is passed on standard input, since it may embed the login.
+/// The message is uploaded from a temporary file.
+fn email(config: &NotifyConfig, summary: &str, runner: &dyn CommandRunner) -> Result<()> {
+    let (Some(smtp), Some(from)) = (&config.smtp, &config.from) else {
+        return Err(SecretSpecError::InvalidInput(
+            "email notifications require smtp and from".to_string(),
+        ));
+    };
+    let smtp = resolve(smtp)?;
+    let message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: [secretspec] {}\r\n\r\n{}\r\n",
+        from,
+        config.email.join(", "),
+        summary,
+        summary
+    );
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(message.as_bytes())?;
+
+    let mut invocation = Invocation::new("curl").args([
+        "--silent",
+        "--show-error",
+        "--ssl-reqd",
+        "--netrc-optional",
+        "--mail-from",
+        from,
+    ]);
+    for recipient in &config.email {
+        invocation = invocation.args(["--mail-rcpt", recipient]);
+    }
+    let stdin = format!(
+        "url = {}\nupload-file = {}\n",
+        quote(&smtp),
+        quote(&file.path().to_string_lossy())
+    );
+    run_curl(runner, &invocation.args(["--config", "-"]).stdin(stdin))
+}
+
+/// Runs curl and turns a failure into an error.
+fn run_curl(runner: &dyn CommandRunner, invocation: &Invocation) -> Result<()> {
+    command::curl(runner, invocation, "send notifications", "curl failed").map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::command::FakeRunner;
+
+    fn change(keys: &[&str]) -> Change<'static> {
+        Change {
+            action: Action::Changed,
+            project: "app",
+            profile: "production",
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            provider: "keyring",
+        }
+    }
+
+    #[test]
+    fn test_send_with() {
+        let config = NotifyConfig {
+            slack: vec!["https://hooks.slack.com/services/T0/B0/token".to_string()],
+            webhook: vec!["https://ops.example.com/hook".to_string()],
+            email: vec!["secops@example.com".to_string()],
+            smtp: Some("smtps://smtp.example.com:465".to_string()),
+            from: Some("secretspec@example.com".to_string()),
+            ..NotifyConfig::default()
+        };
+        let runner = FakeRunner::new()
+            .ok(&["curl", "*", "*", "*", "*", "*", "*", "*", "*", "*"], "")
+            .ok(&["curl", "*", "*", "*", "*", "*", "*", "*", "*", "*"], "")
+            .fail(
+                &[
+                    "curl",
+                    "--silent",
+                    "--show-error",
+                    "--ssl-reqd",
+                    "--netrc-optional",
+                    "--mail-from",
+                    "secretspec@example.com",
+                    "--mail-rcpt",
+                    "secops@example.com",
+                    "--config",
+                    "-",
+                ],
+                "Login denied",
+            );
+
+        let errors = send_with(&config, &change(&["API_KEY"]), "alice", &runner);
+        assert!(runner.is_exhausted());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("Login denied"));
+
+        let calls = runner.calls();
+        let stdin = |i: usize| String::from_utf8(calls[i].stdin.clone().unwrap()).unwrap();
+        // Webhook URLs are kept off the command line
+        assert!(calls[0].args.iter().all(|arg| !arg.contains("token")));
+        assert_eq!(
+            stdin(0),
+            "url = \"https://hooks.slack.com/services/T0/B0/token\"\n"
+        );
+        assert!(calls[0].args.contains(
+            &json!({ "text": "alice changed API_KEY in app/production (keyring)" }).to_string()
+        ));
+
+        let payload: serde_json::Value = serde_json::from_str(&calls[1].args[6]).unwrap();
+        assert_eq!(payload["event"], "secret.changed");
+        assert_eq!(payload["keys"], json!(["API_KEY"]));
+        assert_eq!(payload["actor"], "alice");
+        // So is the SMTP URL, which may embed the login
+        assert!(stdin(2).starts_with("url = \"smtps://smtp.example.com:465\"\nupload-file = "));
+    }
+
+    #[test]
+    fn test_send_with_env_target() {
+        let config = NotifyConfig {
+            webhook: vec!["env:SECRETSPEC_TEST_UNSET_WEBHOOK".to_string()],
+            ..NotifyConfig::default()
+        };
+        let runner = FakeRunner::new();
+        let errors = send_with(&config, &change(&["API_KEY"]), "alice", &runner);
+        assert!(
+            errors[0]
+                .to_string()
+                .contains("SECRETSPEC_TEST_UNSET_WEBHOOK")
+        );
+        assert!(runner.calls().is_empty());
+    }
+}
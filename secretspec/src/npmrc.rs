@@ -0,0 +1,148 @@
+//! `.npmrc` files with registry tokens
+//!
+//! `secretspec npmrc` renders the registry settings of an `.npmrc` from
+//! declared secrets, so npm reads tokens kept in the configured provider
+//! instead of a dotfile:
+//!
+//! ```bash
+//! secretspec npmrc --registry @acme:https://npm.pkg.github.com/=GITHUB_TOKEN > .npmrc
+//! npm publish --userconfig <(secretspec npmrc)
+//! ```
+//!
+//! Each registry is given as `[@scope:]URL=SECRET`. Without any, the token
+//! for the public registry is read from `NPM_TOKEN`.
+
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use std::fmt::Write;
+use zeroize::Zeroizing;
+
+/// The registry used when none is given.
+pub(crate) const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org/=NPM_TOKEN";
+
+/// A registry and the secret holding its token.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Registry {
+    /// Scope whose packages come from the registry, like `@acme`
+    pub scope: Option<String>,
+    /// URL of the registry, ending in a slash
+    pub url: String,
+    /// Secret holding the auth token
+    pub secret: String,
+}
+
+impl Registry {
+    /// Parses a registry given as `[@scope:]URL=SECRET`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the secret, scope or URL is missing or the URL
+    /// isn't http(s)
+    pub(crate) fn parse(spec: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            SecretSpecError::InvalidInput(format!(
+                "Invalid registry '{}': {}. Use [@scope:]URL=SECRET, e.g. @acme:https://npm.pkg.github.com/=GITHUB_TOKEN",
+                spec, reason
+            ))
+        };
+        let (target, secret) = spec
+            .rsplit_once('=')
+            .filter(|(_, secret)| !secret.is_empty())
+            .ok_or_else(|| invalid("no secret"))?;
+        let (scope, url) = match target.strip_prefix('@') {
+            Some(scoped) => {
+                let (scope, url) = scoped
+                    .split_once(':')
+                    .filter(|(scope, _)| !scope.is_empty())
+                    .ok_or_else(|| invalid("no URL after the scope"))?;
+                (Some(format!("@{}", scope)), url)
+            }
+            None => (None, target),
+        };
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(invalid("the URL must start with https://"));
+        }
+        let url = if url.ends_with('/') {
+            url.to_string()
+        } else {
+            format!("{}/", url)
+        };
+        Ok(Self {
+            scope,
+            url,
+            secret: secret.to_string(),
+        })
+    }
+
+    /// Returns the key npm looks up the registry's settings under, the URL
+    /// without its scheme.
+    fn auth_key(&self) -> &str {
+        self.url
+            .strip_prefix("https:")
+            .or_else(|| self.url.strip_prefix("http:"))
+            .unwrap_or(&self.url)
+    }
+}
+
+/// Renders the `.npmrc` lines of the registries, with the token of each.
+///
+/// # Errors
+///
+/// Returns an error if a token spans several lines, which `.npmrc` can't
+/// hold
+pub(crate) fn render(registries: &[(Registry, SecretString)]) -> Result<Zeroizing<String>> {
+    let mut npmrc = Zeroizing::new(String::new());
+    for (registry, token) in registries {
+        let token = token.expose_secret();
+        if token.contains(['\n', '\r']) {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "{} spans several lines and can't be written to .npmrc",
+                registry.secret
+            )));
+        }
+        if let Some(scope) = &registry.scope {
+            let _ = writeln!(npmrc, "{}:registry={}", scope, registry.url);
+        }
+        // npm expands ${VAR} in values unless the dollar is escaped
+        let _ = writeln!(
+            npmrc,
+            "{}:_authToken={}",
+            registry.auth_key(),
+            token.replace("${", "\\${")
+        );
+    }
+    Ok(npmrc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let public = Registry::parse(DEFAULT_REGISTRY).unwrap();
+        assert_eq!(public.scope, None);
+        assert_eq!(public.secret, "NPM_TOKEN");
+        let github = Registry::parse("@acme:https://npm.pkg.github.com=GITHUB_TOKEN").unwrap();
+        assert_eq!(github.scope.as_deref(), Some("@acme"));
+        assert_eq!(github.url, "https://npm.pkg.github.com/");
+        assert!(Registry::parse("https://registry.npmjs.org/").is_err());
+        assert!(Registry::parse("@acme=NPM_TOKEN").is_err());
+        assert!(Registry::parse("registry.npmjs.org=NPM_TOKEN").is_err());
+
+        let npmrc = render(&[
+            (public, SecretString::from("npm_abc")),
+            (github, SecretString::from("ghp_${x}")),
+        ])
+        .unwrap();
+        assert_eq!(
+            npmrc.as_str(),
+            "//registry.npmjs.org/:_authToken=npm_abc\n\
+             @acme:registry=https://npm.pkg.github.com/\n\
+             //npm.pkg.github.com/:_authToken=ghp_\\${x}\n"
+        );
+
+        let registry = Registry::parse(DEFAULT_REGISTRY).unwrap();
+        assert!(render(&[(registry, SecretString::from("a\nb"))]).is_err());
+    }
+}
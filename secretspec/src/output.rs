@@ -0,0 +1,354 @@
+//! Human-readable output of commands.
+//!
+//! Commands report one line per secret: a [`Mark`] showing its state, the
+//! name, its description and a note. [`Rows`] pads the names to the widest
+//! one so descriptions line up, and shortens descriptions that would wrap.
+//!
+//! Colors are left out when stdout isn't a terminal, when `NO_COLOR` is set,
+//! or after [`set_color`] turned them off for `--no-color`.
+//!
+//! Operations over many secrets report their [`Progress`] on stderr, so slow
+//! providers don't leave the terminal silent for seconds.
+//!
+//! Command-style library methods like `check` print their report with
+//! [`say`]. Programs embedding the library can take those lines with
+//! [`set_output_handler`] instead of having them on stdout.
+
+use crate::warning;
+use colored::{ColoredString, Colorize};
+use std::io::{self, IsTerminal, Write};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Narrowest description worth shortening to; below this it is left to wrap.
+const MIN_DESCRIPTION: usize = 16;
+
+/// How long an operation runs before its progress is shown, so fast ones
+/// don't flicker.
+const PROGRESS_DELAY: Duration = Duration::from_millis(300);
+
+/// Whether progress is reported, off for programs embedding the library.
+static PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Whether a progress line is on the terminal.
+static DRAWN: AtomicBool = AtomicBool::new(false);
+
+/// A function receiving lines of command output.
+type Handler = Box<dyn Fn(&str) + Send + Sync>;
+
+/// The handler set by the program, or `None` to print to stdout.
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+/// Sends the lines command-style methods print, like
+/// `✓ Secret 'API_KEY' saved to keyring`, to `handler` instead of stdout.
+/// The lines never contain secret values.
+///
+/// # Example
+///
+/// ```
+/// secretspec::set_output_handler(|line| {
+///     // e.g. log::info!("{}", line)
+///     let _ = line;
+/// });
+/// ```
+pub fn set_output_handler(handler: impl Fn(&str) + Send + Sync + 'static) {
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(handler));
+}
+
+/// Prints a line of command output to the handler, or to stdout if none is
+/// set.
+pub(crate) fn say(line: impl AsRef<str>) {
+    let line = line.as_ref();
+    match &*HANDLER.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(handler) => handler(line),
+        None => {
+            clear_progress();
+            println!("{}", line)
+        }
+    }
+}
+
+/// Turns colors on or off, overriding the terminal and `NO_COLOR` detection.
+pub(crate) fn set_color(enabled: bool) {
+    colored::control::set_override(enabled);
+}
+
+/// Turns progress reporting on or off.
+///
+/// Even when on, progress is only shown if both stdout and stderr are
+/// terminals, so output piped to another program or a file is unaffected.
+pub(crate) fn set_progress(enabled: bool) {
+    PROGRESS.store(enabled, Ordering::Relaxed);
+}
+
+/// Erases the progress line, if one is drawn, so a line of output can be
+/// printed. The next step of the progress draws it again.
+pub(crate) fn clear_progress() {
+    if DRAWN.swap(false, Ordering::Relaxed) {
+        eprint!("\r\x1b[K");
+    }
+}
+
+/// The state of a secret on a line of output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mark {
+    /// Set, or done: a green ✓
+    Found,
+    /// Not set but falling back to a default, or left alone: a yellow ○
+    Default,
+    /// Required but not set, or failed: a red ✗
+    Missing,
+    /// Optional and not set, or not applicable: a blue ○
+    Optional,
+}
+
+impl Mark {
+    /// Returns the glyph in the mark's color.
+    pub(crate) fn glyph(self) -> ColoredString {
+        match self {
+            Mark::Found => "✓".green(),
+            Mark::Default => "○".yellow(),
+            Mark::Missing => "✗".red(),
+            Mark::Optional => "○".blue(),
+        }
+    }
+
+    /// Returns `text` in the mark's color.
+    pub(crate) fn paint(self, text: &str) -> ColoredString {
+        match self {
+            Mark::Found => text.green(),
+            Mark::Default => text.yellow(),
+            Mark::Missing => text.red(),
+            Mark::Optional => text.blue(),
+        }
+    }
+}
+
+/// Lines about secrets, with their names padded to the same width.
+///
+/// The names are known up front, so lines can be printed as each secret is
+/// processed.
+pub(crate) struct Rows {
+    /// Width of the widest name
+    name_width: usize,
+    /// Width of the terminal, if stdout is one
+    columns: Option<usize>,
+}
+
+impl Rows {
+    /// Creates rows for the given names.
+    pub(crate) fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            name_width: names
+                .into_iter()
+                .map(|name| name.chars().count())
+                .max()
+                .unwrap_or(0),
+            columns: terminal_width(),
+        }
+    }
+
+    /// Prints a line: the mark, the padded name, the description if it
+    /// isn't empty and the note if any.
+    pub(crate) fn print(
+        &self,
+        mark: Mark,
+        name: &str,
+        description: &str,
+        note: Option<ColoredString>,
+    ) {
+        say(self.format(mark, name, description, note));
+    }
+
+    /// Formats a line, shortening the description to fit the terminal.
+    fn format(
+        &self,
+        mark: Mark,
+        name: &str,
+        description: &str,
+        note: Option<ColoredString>,
+    ) -> String {
+        let mut line = format!("{} {}", mark.glyph(), name);
+        if !description.is_empty() {
+            let padding = self.name_width.saturating_sub(name.chars().count());
+            line.push_str(&" ".repeat(padding));
+            line.push_str(" - ");
+
+            // Glyph, space, name, " - " and the note with its leading space
+            let used =
+                2 + self.name_width + 3 + note.as_ref().map_or(0, |note| note.chars().count() + 1);
+            line.push_str(&shorten(
+                description,
+                self.columns.map(|columns| columns.saturating_sub(used)),
+            ));
+        }
+        if let Some(note) = note {
+            line.push(' ');
+            line.push_str(&note.to_string());
+        }
+        line
+    }
+}
+
+/// A progress line for an operation over many secrets.
+///
+/// The line shows the secret being processed, such as
+/// `Resolving [3/12] DATABASE_URL`, and is erased when the progress is
+/// dropped. If it was shown, a summary like `Resolving 12 secrets took 4.2s`
+/// follows.
+pub(crate) struct Progress {
+    /// What is being done, such as `Resolving`
+    verb: &'static str,
+    /// Number of secrets
+    total: usize,
+    /// Number of secrets started so far
+    current: usize,
+    /// When the operation started, or `None` if progress isn't reported, so
+    /// the clock isn't read where there is none, as on wasm32
+    start: Option<Instant>,
+    /// Whether a progress line was ever drawn
+    shown: bool,
+}
+
+impl Progress {
+    /// Starts reporting an operation over `total` secrets.
+    pub(crate) fn new(verb: &'static str, total: usize) -> Self {
+        let enabled = PROGRESS.load(Ordering::Relaxed)
+            && io::stdout().is_terminal()
+            && io::stderr().is_terminal();
+        Self {
+            verb,
+            total,
+            current: 0,
+            start: enabled.then(Instant::now),
+            shown: false,
+        }
+    }
+
+    /// Moves on to the next secret.
+    pub(crate) fn step(&mut self, name: &str) {
+        self.current += 1;
+        match self.start {
+            Some(start) if start.elapsed() >= PROGRESS_DELAY => {}
+            _ => return,
+        }
+        let mut stderr = io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r\x1b[K{} [{}/{}] {}",
+            self.verb, self.current, self.total, name
+        );
+        let _ = stderr.flush();
+        DRAWN.store(true, Ordering::Relaxed);
+        self.shown = true;
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        clear_progress();
+        if self.shown
+            && let Some(start) = self.start
+        {
+            warning::notice(format!(
+                "{} {} secrets took {:.1}s",
+                self.verb,
+                self.current,
+                start.elapsed().as_secs_f64()
+            ));
+        }
+    }
+}
+
+/// Shortens `text` to `width` characters with an ellipsis, unless the width
+/// is unknown or too narrow to leave anything readable.
+fn shorten(text: &str, width: Option<usize>) -> String {
+    match width {
+        Some(width) if width >= MIN_DESCRIPTION && text.chars().count() > width => {
+            let kept: String = text.chars().take(width - 1).collect();
+            format!("{}…", kept.trim_end())
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Returns the width of the terminal stdout is attached to.
+///
+/// `COLUMNS` takes precedence, so the width can be set when the terminal
+/// can't be asked.
+fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+    {
+        return Some(columns);
+    }
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    window_width()
+}
+
+/// Asks the terminal attached to stdout for its width.
+#[cfg(unix)]
+fn window_width() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes a winsize to the pointer, which is valid
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(usize::from(size.ws_col))
+}
+
+/// Asks the terminal attached to stdout for its width.
+#[cfg(not(unix))]
+fn window_width() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows() {
+        set_color(false);
+        let rows = Rows {
+            name_width: 12,
+            columns: Some(50),
+        };
+        assert_eq!(
+            rows.format(Mark::Found, "API_KEY", "API key", None),
+            "✓ API_KEY      - API key"
+        );
+        assert_eq!(
+            rows.format(
+                Mark::Missing,
+                "DATABASE_URL",
+                "Connection string for the primary database",
+                Some(Mark::Missing.paint("(required)"))
+            ),
+            "✗ DATABASE_URL - Connection string for… (required)"
+        );
+        assert_eq!(
+            rows.format(
+                Mark::Default,
+                "TOKEN",
+                "",
+                Some(Mark::Default.paint("(not set)"))
+            ),
+            "○ TOKEN (not set)"
+        );
+
+        // Too narrow to shorten usefully
+        assert_eq!(
+            shorten("a long description here", Some(8)),
+            "a long description here"
+        );
+        assert_eq!(shorten("short", None), "short");
+    }
+}
@@ -0,0 +1,232 @@
+//! Reviewable plans of changes to stored secrets.
+//!
+//! `import`, `rekey` and `prune` can print the changes they would make as a
+//! [`Plan`] instead of making them, as text to read or JSON to attach to a
+//! change ticket. Applying the JSON later makes exactly the reviewed changes,
+//! and refuses to if the provider no longer matches what the plan expects.
+//!
+//! Plans name secrets but never carry their values.
+
+use crate::{Result, SecretSpecError};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the plan format, so plans from newer releases are refused.
+const PLAN_VERSION: u32 = 1;
+
+/// The command a plan was made by, and is applied with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanOperation {
+    /// Copying secrets missing from the provider from another one
+    Import,
+    /// Re-encrypting stored secrets to the current recipients
+    Rekey,
+    /// Deleting stored secrets the spec no longer declares
+    Prune,
+}
+
+impl PlanOperation {
+    /// Returns the name of the command.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Import => "import",
+            Self::Rekey => "rekey",
+            Self::Prune => "prune",
+        }
+    }
+}
+
+/// What a plan does to a secret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanAction {
+    /// Store a secret that isn't stored yet
+    Create,
+    /// Write a stored secret again
+    Update,
+    /// Delete a stored secret
+    Delete,
+}
+
+/// A change to one secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedChange {
+    /// What is done to the secret
+    pub action: PlanAction,
+    /// Name of the secret
+    pub key: String,
+}
+
+/// The changes an operation would make to the secrets of a profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    /// Version of the plan format
+    pub version: u32,
+    /// The command that made the plan
+    pub operation: PlanOperation,
+    /// Project the secrets belong to
+    pub project: String,
+    /// Profile the secrets belong to
+    pub profile: String,
+    /// Name of the provider the changes are made in
+    pub provider: String,
+    /// Provider values are imported from, for import plans
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The changes, sorted by secret name
+    pub changes: Vec<PlannedChange>,
+}
+
+impl Plan {
+    /// Creates a plan, sorting the changes by secret name.
+    pub(crate) fn new(
+        operation: PlanOperation,
+        project: &str,
+        profile: &str,
+        provider: &str,
+        source: Option<&str>,
+        mut changes: Vec<PlannedChange>,
+    ) -> Self {
+        changes.sort_by(|a, b| a.key.cmp(&b.key));
+        Self {
+            version: PLAN_VERSION,
+            operation,
+            project: project.to_string(),
+            profile: profile.to_string(),
+            provider: provider.to_string(),
+            source: source.map(str::to_string),
+            changes,
+        }
+    }
+
+    /// Reads a plan saved as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON isn't a plan, or was written by a newer
+    /// version of secretspec
+    pub fn from_json(json: &str) -> Result<Self> {
+        let plan: Self = serde_json::from_str(json)?;
+        if plan.version > PLAN_VERSION {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "Plan version {} is newer than this version of secretspec supports ({})",
+                plan.version, PLAN_VERSION
+            )));
+        }
+        Ok(plan)
+    }
+
+    /// Returns the plan as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// Returns how many changes of the action the plan makes.
+    pub fn count(&self, action: PlanAction) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| change.action == action)
+            .count()
+    }
+
+    /// Checks that the plan is applied to the project, profile and provider
+    /// it was made for.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SecretSpecError::Conflict`] naming the first mismatch
+    pub(crate) fn check_target(&self, project: &str, profile: &str, provider: &str) -> Result<()> {
+        for (what, planned, actual) in [
+            ("project", &self.project, project),
+            ("profile", &self.profile, profile),
+            ("provider", &self.provider, provider),
+        ] {
+            if planned != actual {
+                return Err(SecretSpecError::Conflict(format!(
+                    "The plan is for {} '{}', not '{}'",
+                    what, planned, actual
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Plan {
+    /// Formats the plan for review, like `terraform plan`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(
+                f,
+                "No changes. secretspec {} has nothing to do in {} (project: {}, profile: {}).",
+                self.operation.as_str(),
+                self.provider,
+                self.project,
+                self.profile
+            );
+        }
+
+        writeln!(
+            f,
+            "secretspec {} will make these changes in {} (project: {}, profile: {}):\n",
+            self.operation.as_str(),
+            self.provider.blue(),
+            self.project.bold(),
+            self.profile.cyan()
+        )?;
+        for change in &self.changes {
+            match change.action {
+                PlanAction::Create => match &self.source {
+                    Some(source) => writeln!(
+                        f,
+                        "  {} {} {}",
+                        "+".green(),
+                        change.key,
+                        format!("(from {})", source).dimmed()
+                    )?,
+                    None => writeln!(f, "  {} {}", "+".green(), change.key)?,
+                },
+                PlanAction::Update => writeln!(f, "  {} {}", "~".yellow(), change.key)?,
+                PlanAction::Delete => writeln!(f, "  {} {}", "-".red(), change.key)?,
+            }
+        }
+        writeln!(
+            f,
+            "\nPlan: {} to create, {} to update, {} to delete.",
+            self.count(PlanAction::Create).to_string().green(),
+            self.count(PlanAction::Update).to_string().yellow(),
+            self.count(PlanAction::Delete).to_string().red()
+        )
+    }
+}
+
+/// A format a plan can be printed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanFormat {
+    /// A summary for review, like `terraform plan`
+    Text,
+    /// JSON that can be applied with `--apply-plan`
+    Json,
+}
+
+impl FromStr for PlanFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(PlanFormat::Text),
+            "json" => Ok(PlanFormat::Json),
+            _ => Err(format!(
+                "Unknown plan format '{}'. Use text or json.",
+                format
+            )),
+        }
+    }
+}
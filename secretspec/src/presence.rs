@@ -0,0 +1,197 @@
+//! Confirming the user is present with the operating system's own prompt
+//!
+//! `secretspec agent start --biometric` keeps provider sessions when the
+//! agent locks, and asks the operating system to confirm the user is present
+//! before serving lookups again. Unlocking is then a fingerprint instead of
+//! retyping a provider's master password.
+//!
+//! On macOS the agent creates a keychain item that can only be read after
+//! Touch ID, or the login password on Macs without it, and reads it to
+//! unlock. Other platforms have no such prompt yet.
+
+use crate::Result;
+#[cfg(not(all(target_os = "macos", feature = "keyring")))]
+use crate::SecretSpecError;
+
+/// Keychain service of the item read to confirm presence.
+#[cfg(all(target_os = "macos", feature = "keyring"))]
+const SERVICE: &str = "secretspec-agent";
+
+/// Returns the error for platforms without a presence prompt.
+#[cfg(not(all(target_os = "macos", feature = "keyring")))]
+fn unsupported() -> SecretSpecError {
+    SecretSpecError::ProviderOperationFailed(
+        "Biometric unlock is only supported on macOS builds with the keyring feature".to_string(),
+    )
+}
+
+/// Prepares the presence check, failing early if it can't work.
+///
+/// # Errors
+///
+/// Returns an error on platforms without a presence prompt, or if the
+/// keychain item can't be created
+pub(crate) fn enroll() -> Result<()> {
+    #[cfg(all(target_os = "macos", feature = "keyring"))]
+    return macos::enroll();
+    #[cfg(not(all(target_os = "macos", feature = "keyring")))]
+    Err(unsupported())
+}
+
+/// Asks the user to confirm they are present, showing `reason`.
+///
+/// # Errors
+///
+/// Returns [`SecretSpecError::PermissionDenied`](crate::SecretSpecError::PermissionDenied) if the user cancels or
+/// fails the prompt
+pub(crate) fn verify(reason: &str) -> Result<()> {
+    #[cfg(all(target_os = "macos", feature = "keyring"))]
+    return macos::verify(reason);
+    #[cfg(not(all(target_os = "macos", feature = "keyring")))]
+    {
+        let _ = reason;
+        Err(unsupported())
+    }
+}
+
+#[cfg(all(target_os = "macos", feature = "keyring"))]
+mod macos {
+    use super::SERVICE;
+    use crate::{Result, SecretSpecError};
+    use core_foundation::base::{CFType, CFTypeRef, OSStatus, TCFType};
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::data::CFData;
+    use core_foundation::dictionary::CFDictionary;
+    use core_foundation::string::{CFString, CFStringRef};
+    use security_framework::access_control::{ProtectionMode, SecAccessControl};
+    use security_framework_sys::access_control::kSecAccessControlUserPresence;
+    use security_framework_sys::base::{errSecItemNotFound, errSecSuccess};
+    use security_framework_sys::item::{
+        kSecAttrAccessControl, kSecAttrAccount, kSecAttrService, kSecClass,
+        kSecClassGenericPassword, kSecReturnData, kSecUseDataProtectionKeychain, kSecValueData,
+    };
+    use security_framework_sys::keychain_item::{SecItemAdd, SecItemCopyMatching, SecItemDelete};
+    use std::ptr;
+
+    unsafe extern "C" {
+        static kSecUseOperationPrompt: CFStringRef;
+    }
+
+    /// Returned when the call succeeds.
+    const ERR_SEC_SUCCESS: OSStatus = errSecSuccess;
+
+    /// Returned when the item doesn't exist.
+    const ERR_SEC_ITEM_NOT_FOUND: OSStatus = errSecItemNotFound;
+
+    /// Returned when the user cancels the prompt.
+    const ERR_SEC_USER_CANCELED: OSStatus = -128;
+
+    /// Returned when the user fails the prompt.
+    const ERR_SEC_AUTH_FAILED: OSStatus = -25293;
+
+    /// Returned when the binary lacks the entitlements for the data protection keychain.
+    const ERR_SEC_MISSING_ENTITLEMENT: OSStatus = -34018;
+
+    /// Replaces the item with one only readable after user presence.
+    pub(super) fn enroll() -> Result<()> {
+        let query = CFDictionary::from_CFType_pairs(&item_query());
+        match unsafe { SecItemDelete(query.as_concrete_TypeRef()) } {
+            ERR_SEC_SUCCESS | ERR_SEC_ITEM_NOT_FOUND => {}
+            status => return Err(error(status)),
+        }
+
+        let access_control = SecAccessControl::create_with_protection(
+            Some(ProtectionMode::AccessibleWhenUnlockedThisDeviceOnly),
+            kSecAccessControlUserPresence,
+        )
+        .map_err(|e| {
+            SecretSpecError::ProviderOperationFailed(format!(
+                "Failed to create keychain access control: {}",
+                e
+            ))
+        })?;
+        let mut attributes = item_query();
+        attributes.push((
+            key(unsafe { kSecValueData }),
+            CFData::from_buffer(b"1").into_CFType(),
+        ));
+        attributes.push((
+            key(unsafe { kSecAttrAccessControl }),
+            access_control.into_CFType(),
+        ));
+        let attributes = CFDictionary::from_CFType_pairs(&attributes);
+        match unsafe { SecItemAdd(attributes.as_concrete_TypeRef(), ptr::null_mut()) } {
+            ERR_SEC_SUCCESS => Ok(()),
+            status => Err(error(status)),
+        }
+    }
+
+    /// Reads the item, which shows the Touch ID or password prompt.
+    pub(super) fn verify(reason: &str) -> Result<()> {
+        let mut query = item_query();
+        query.push((
+            key(unsafe { kSecReturnData }),
+            CFBoolean::true_value().into_CFType(),
+        ));
+        query.push((
+            key(unsafe { kSecUseOperationPrompt }),
+            CFString::new(reason).into_CFType(),
+        ));
+        let query = CFDictionary::from_CFType_pairs(&query);
+
+        let mut result: CFTypeRef = ptr::null();
+        let status = unsafe { SecItemCopyMatching(query.as_concrete_TypeRef(), &mut result) };
+        if !result.is_null() {
+            drop(unsafe { CFData::wrap_under_create_rule(result as _) });
+        }
+        match status {
+            ERR_SEC_SUCCESS => Ok(()),
+            ERR_SEC_USER_CANCELED | ERR_SEC_AUTH_FAILED => Err(SecretSpecError::PermissionDenied(
+                "Unlocking the agent was cancelled".to_string(),
+            )),
+            ERR_SEC_ITEM_NOT_FOUND => Err(SecretSpecError::ProviderOperationFailed(
+                "The agent's keychain item was removed; restart the agent".to_string(),
+            )),
+            status => Err(error(status)),
+        }
+    }
+
+    /// Builds the attributes identifying the item.
+    fn item_query() -> Vec<(CFString, CFType)> {
+        vec![
+            (
+                key(unsafe { kSecClass }),
+                key(unsafe { kSecClassGenericPassword }).into_CFType(),
+            ),
+            (
+                key(unsafe { kSecAttrService }),
+                CFString::new(SERVICE).into_CFType(),
+            ),
+            (
+                key(unsafe { kSecAttrAccount }),
+                CFString::new(&whoami::username()).into_CFType(),
+            ),
+            (
+                key(unsafe { kSecUseDataProtectionKeychain }),
+                CFBoolean::true_value().into_CFType(),
+            ),
+        ]
+    }
+
+    /// Wraps a Core Foundation string constant.
+    fn key(name: CFStringRef) -> CFString {
+        unsafe { CFString::wrap_under_get_rule(name) }
+    }
+
+    /// Describes a failed Keychain Services call.
+    fn error(status: OSStatus) -> SecretSpecError {
+        if status == ERR_SEC_MISSING_ENTITLEMENT {
+            return SecretSpecError::ProviderOperationFailed(
+                "This secretspec binary is not entitled to use the data protection keychain, which biometric unlock requires; use a signed build of secretspec or start the agent without --biometric"
+                    .to_string(),
+            );
+        }
+        let message = security_framework::base::Error::from(status).to_string();
+        SecretSpecError::ProviderOperationFailed(format!("Keychain error: {}", message))
+    }
+}
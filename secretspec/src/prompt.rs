@@ -0,0 +1,355 @@
+//! Interactive and non-interactive input of secret values
+//!
+//! All user input for `set`, `check`, `ensure` and `init` goes through this
+//! module so that the commands behave consistently:
+//!
+//! - Masked terminal input, with a confirmation prompt for high-entropy values
+//! - Reading the value from stdin (`--from-stdin`) or a file (`--from-file`)
+//! - Reading the values of several secrets from a TOML answers file
+//! - Editing multi-line values in `$VISUAL` / `$EDITOR` (`--editor`)
+//! - A hard error instead of a hang when a prompt needs a terminal but none is attached
+//!
+//! Without the `native` feature every prompt fails as if no terminal was
+//! attached.
+
+use crate::error::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::Command;
+use zeroize::Zeroizing;
+
+/// Minimum length before a value is considered for entropy-based confirmation.
+const CONFIRM_MIN_LENGTH: usize = 12;
+
+/// Shannon entropy (bits per character) above which a value must be confirmed.
+const CONFIRM_MIN_ENTROPY: f64 = 3.0;
+
+/// Header written to the editor buffer, removed again from the first line.
+const EDITOR_HEADER: &str = "# Enter the value for {name} below. This line is removed.\n";
+
+/// Returns an error if stdin is not attached to a terminal.
+///
+/// # Arguments
+///
+/// * `action` - Short description of what needed the terminal, used in the error message
+///
+/// # Errors
+///
+/// Returns `SecretSpecError::NoTty` if stdin is not a terminal
+pub(crate) fn require_tty(action: &str) -> Result<()> {
+    if io::stdin().is_terminal() {
+        Ok(())
+    } else {
+        Err(SecretSpecError::NoTty(action.to_string()))
+    }
+}
+
+/// Prompts for a secret value with masked input.
+///
+/// Values that look randomly generated (long and high-entropy) are hard to type
+/// correctly without seeing them, so the user is asked to enter them twice.
+///
+/// # Arguments
+///
+/// * `name` - The secret name shown in the prompt
+/// * `profile` - The profile shown in the prompt
+///
+/// # Errors
+///
+/// Returns an error if no terminal is attached or the confirmation does not match
+pub(crate) fn secret(name: &str, profile: &str) -> Result<SecretString> {
+    require_tty(&format!("prompt for the value of {}", name))?;
+
+    print!("Enter value for {} (profile: {}): ", name, profile);
+    io::stdout().flush()?;
+    let value = SecretString::new(read_password()?.into());
+
+    if needs_confirmation(value.expose_secret()) {
+        print!("Confirm value for {}: ", name);
+        io::stdout().flush()?;
+        let confirmation = read_password()?;
+        if confirmation != value.expose_secret() {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "Values entered for {} do not match",
+                name
+            )));
+        }
+    }
+
+    Ok(value)
+}
+
+/// Prompts for a login password with masked input, without confirmation.
+///
+/// # Arguments
+///
+/// * `label` - What the password is for, e.g. "Vault LDAP password for alice"
+///
+/// # Errors
+///
+/// Returns `SecretSpecError::NoTty` if no terminal is attached
+pub(crate) fn password(label: &str) -> Result<SecretString> {
+    require_tty(&format!("prompt for the {}", label))?;
+
+    print!("{}: ", label);
+    io::stdout().flush()?;
+    Ok(SecretString::new(read_password()?.into()))
+}
+
+/// Reads a line from the terminal without echoing it.
+#[cfg(feature = "native")]
+fn read_password() -> io::Result<String> {
+    rpassword::read_password()
+}
+
+#[cfg(not(feature = "native"))]
+fn read_password() -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "masked input needs the native feature",
+    ))
+}
+
+/// Asks a yes/no question.
+///
+/// # Errors
+///
+/// Returns `SecretSpecError::NoTty` if no terminal is attached
+#[cfg(feature = "native")]
+pub(crate) fn confirm(message: &str, default: bool) -> Result<bool> {
+    require_tty(message)?;
+    Ok(inquire::Confirm::new(message)
+        .with_default(default)
+        .prompt()?)
+}
+
+#[cfg(not(feature = "native"))]
+pub(crate) fn confirm(message: &str, _default: bool) -> Result<bool> {
+    Err(SecretSpecError::NoTty(message.to_string()))
+}
+
+/// Asks the user to pick one of several options, or to skip the question.
+///
+/// # Returns
+///
+/// The index of the chosen option, or `None` if the question was skipped
+///
+/// # Errors
+///
+/// Returns `SecretSpecError::NoTty` if no terminal is attached
+#[cfg(feature = "native")]
+pub(crate) fn select(message: &str, mut options: Vec<String>) -> Result<Option<usize>> {
+    require_tty(message)?;
+    let skip = options.len();
+    options.push("(skip)".to_string());
+    let choice = inquire::Select::new(message, options).raw_prompt()?;
+    Ok((choice.index != skip).then_some(choice.index))
+}
+
+#[cfg(not(feature = "native"))]
+pub(crate) fn select(message: &str, _options: Vec<String>) -> Result<Option<usize>> {
+    Err(SecretSpecError::NoTty(message.to_string()))
+}
+
+/// Reads a secret value from stdin until EOF.
+///
+/// A single trailing newline is removed so that `echo value | secretspec set KEY`
+/// stores `value`.
+pub(crate) fn from_stdin() -> Result<SecretString> {
+    let mut buffer = String::new();
+    io::stdin().read_to_string(&mut buffer)?;
+    Ok(SecretString::new(strip_trailing_newline(buffer).into()))
+}
+
+/// Reads a secret value from a file.
+///
+/// A single trailing newline is removed, matching [`from_stdin`].
+pub(crate) fn from_file(path: &Path) -> Result<SecretString> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        SecretSpecError::Io(io::Error::new(
+            e.kind(),
+            format!("Failed to read {}: {}", path.display(), e),
+        ))
+    })?;
+    Ok(SecretString::new(strip_trailing_newline(content).into()))
+}
+
+/// Reads the values of several secrets from a TOML answers file.
+///
+/// The file maps secret names to values, like `API_KEY = "sk-123"`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or a value isn't a string
+pub(crate) fn answers(path: &Path) -> Result<HashMap<String, SecretString>> {
+    let content = Zeroizing::new(fs::read_to_string(path).map_err(|e| {
+        SecretSpecError::Io(io::Error::new(
+            e.kind(),
+            format!("Failed to read {}: {}", path.display(), e),
+        ))
+    })?);
+    let answers: HashMap<String, String> = toml::from_str(&content)?;
+    Ok(answers
+        .into_iter()
+        .map(|(name, value)| (name, SecretString::new(value.into())))
+        .collect())
+}
+
+/// Opens `$VISUAL` or `$EDITOR` (falling back to `vi`) to enter a multi-line value.
+///
+/// The buffer is written to a private temporary file, which is removed once
+/// the editor exits. Only the header line written by secretspec is removed,
+/// so values can have lines starting with `#`.
+///
+/// # Errors
+///
+/// Returns an error if no terminal is attached, the editor cannot be started,
+/// or it exits with a non-zero status
+pub(crate) fn from_editor(name: &str) -> Result<SecretString> {
+    require_tty(&format!("open an editor for {}", name))?;
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    let mut file = tempfile::Builder::new()
+        .prefix("secretspec-")
+        .suffix(".txt")
+        .tempfile()?;
+    let header = EDITOR_HEADER.replace("{name}", name);
+    file.write_all(header.as_bytes())?;
+    file.flush()?;
+
+    // Editors are commonly configured with arguments, e.g. EDITOR="code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(parts)
+        .arg(file.path())
+        .status()
+        .map_err(|e| {
+            SecretSpecError::InvalidInput(format!("Failed to start editor '{}': {}", editor, e))
+        })?;
+
+    if !status.success() {
+        return Err(SecretSpecError::InvalidInput(format!(
+            "Editor '{}' exited with {}",
+            editor, status
+        )));
+    }
+
+    let content = Zeroizing::new(fs::read_to_string(file.path())?);
+    let value = strip_editor_header(&content, &header).to_string();
+    Ok(SecretString::new(strip_trailing_newline(value).into()))
+}
+
+/// Removes the header from the first line of an editor buffer, if it is still
+/// there. Editors may have rewritten its line ending.
+fn strip_editor_header<'a>(content: &'a str, header: &str) -> &'a str {
+    match content.split_once('\n') {
+        Some((first, rest)) if first.trim_end_matches('\r') == header.trim_end() => rest,
+        _ => content,
+    }
+}
+
+/// Removes a single trailing `\n` or `\r\n`.
+fn strip_trailing_newline(mut value: String) -> String {
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    value
+}
+
+/// Returns whether a value looks generated and should be entered twice.
+fn needs_confirmation(value: &str) -> bool {
+    value.chars().count() >= CONFIRM_MIN_LENGTH && shannon_entropy(value) >= CONFIRM_MIN_ENTROPY
+}
+
+/// Calculates the Shannon entropy of a string in bits per character.
+fn shannon_entropy(value: &str) -> f64 {
+    let mut counts = std::collections::HashMap::new();
+    let mut total = 0usize;
+    for c in value.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+        total += 1;
+    }
+    if total == 0 {
+        return 0.0;
+    }
+
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_trailing_newline() {
+        assert_eq!(strip_trailing_newline("value\n".to_string()), "value");
+        assert_eq!(strip_trailing_newline("value\r\n".to_string()), "value");
+        assert_eq!(strip_trailing_newline("value\n\n".to_string()), "value\n");
+        assert_eq!(strip_trailing_newline("value".to_string()), "value");
+    }
+
+    #[test]
+    fn test_strip_editor_header() {
+        let header = EDITOR_HEADER.replace("{name}", "SSH_KEY");
+        let key = "-----BEGIN KEY-----\n# not a comment\n-----END KEY-----\n";
+        assert_eq!(
+            strip_editor_header(&format!("{}{}", header, key), &header),
+            key
+        );
+        assert_eq!(
+            strip_editor_header(&format!("{}\r\n{}", header.trim_end(), key), &header),
+            key
+        );
+        // Without the header nothing is removed
+        assert_eq!(strip_editor_header(key, &header), key);
+        assert_eq!(strip_editor_header("# value", &header), "# value");
+    }
+
+    #[test]
+    fn test_needs_confirmation() {
+        assert!(!needs_confirmation("short"));
+        assert!(!needs_confirmation("aaaaaaaaaaaaaaaaaaaa"));
+        assert!(needs_confirmation("xK9#mQ2$vL7@pR4!"));
+    }
+
+    #[test]
+    fn test_from_file_strips_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.txt");
+        fs::write(&path, "line1\nline2\n").unwrap();
+
+        let value = from_file(&path).unwrap();
+        assert_eq!(value.expose_secret(), "line1\nline2");
+    }
+
+    #[test]
+    fn test_answers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("answers.toml");
+        fs::write(&path, "API_KEY = \"sk-123\"\nDB_URL = \"postgres://\"\n").unwrap();
+
+        let answers = answers(&path).unwrap();
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers["API_KEY"].expose_secret(), "sk-123");
+
+        fs::write(&path, "PORT = 5432\n").unwrap();
+        assert!(super::answers(&path).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Audit events for every secret access.
+//!
+//! The [`AuditedProvider`] wrapper reports each read, write, delete, restore,
+//! listing and grant made through a provider to a callback, along with whether it
+//! succeeded. Events name the secret but never carry its value, so they can
+//! be logged or shipped to an audit trail as they are.
+
+use super::macros::forward_provider;
+use super::{AccessPolicy, ItemMatch, Provider, SecretMetadata, SecretValue};
+use crate::{Result, SecretSpecError};
+use secrecy::SecretString;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// An operation made through an [`AuditedProvider`].
+#[derive(Debug)]
+pub struct AuditEvent<'a> {
+    /// Name of the provider
+    pub provider: &'static str,
+    /// The operation: `get`, `set`, `delete`, `purge`, `restore`, `list` or
+    /// `grant`
+    pub operation: &'static str,
+    /// The project the operation was for
+    pub project: &'a str,
+    /// The profile the operation was for
+    pub profile: &'a str,
+    /// The secret, item or principal the operation was for, if any
+    pub target: Option<&'a str>,
+    /// The error the operation failed with, or `None` if it succeeded
+    pub error: Option<&'a SecretSpecError>,
+}
+
+/// Receives the events of an [`AuditedProvider`].
+pub type AuditSink = Arc<dyn Fn(&AuditEvent<'_>) + Send + Sync>;
+
+/// Provider wrapper that reports every operation to a sink.
+pub struct AuditedProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+    /// Where events are reported
+    sink: AuditSink,
+}
+
+impl AuditedProvider {
+    /// Creates a new AuditedProvider reporting to `sink`.
+    pub fn new(inner: Box<dyn Provider>, sink: AuditSink) -> Self {
+        Self { inner, sink }
+    }
+
+    /// Reports the outcome of an operation and returns it.
+    fn record<T>(
+        &self,
+        operation: &'static str,
+        project: &str,
+        profile: &str,
+        target: Option<&str>,
+        result: Result<T>,
+    ) -> Result<T> {
+        (self.sink)(&AuditEvent {
+            provider: self.inner.name(),
+            operation,
+            project,
+            profile,
+            target,
+            error: result.as_ref().err(),
+        });
+        result
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for AuditedProvider {
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        let result = self.inner.get(project, key, profile);
+        self.record("get", project, profile, Some(key), result)
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let result = self.inner.get_with_metadata(project, key, profile);
+        self.record("get", project, profile, Some(key), result)
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        let result = self.inner.set(project, key, value, profile);
+        self.record("set", project, profile, Some(key), result)
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        let result = self.inner.set_many(project, values, profile);
+        self.record("set", project, profile, None, result)
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        let result = self
+            .inner
+            .compare_and_set(project, key, value, profile, expected);
+        self.record("set", project, profile, Some(key), result)
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let result = self.inner.delete(project, key, profile);
+        self.record("delete", project, profile, Some(key), result)
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let result = self.inner.purge(project, key, profile);
+        self.record("purge", project, profile, Some(key), result)
+    }
+
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let result = self.inner.restore(project, key, profile);
+        self.record("restore", project, profile, Some(key), result)
+    }
+
+    fn history(&self, project: &str, key: &str, profile: &str) -> Result<Vec<SecretMetadata>> {
+        let result = self.inner.history(project, key, profile);
+        self.record("list", project, profile, Some(key), result)
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let result = self.inner.list_keys(project, profile);
+        self.record("list", project, profile, None, result)
+    }
+
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        let result = self.inner.get_fields(project, item, fields, profile);
+        self.record("get", project, profile, Some(item), result)
+    }
+
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        let result = self.inner.find_items(project, key, profile);
+        self.record("list", project, profile, Some(key), result)
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let result = self.inner.get_by_id(project, key, id, profile);
+        self.record("get", project, profile, Some(key), result)
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        let result = self.inner.set_by_id(project, key, id, value, profile);
+        self.record("set", project, profile, Some(key), result)
+    }
+
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        let result = self.inner.grant(project, profile, principal);
+        self.record("grant", project, profile, Some(principal), result)
+    }
+
+    forward_provider!(inner =>
+        allows_set, check_writable, allows_delete, allows_restore, allows_list, browse,
+        max_value_size, works_in_sandbox, doctor, list_access, access_policy, leases, renew_lease,
+        revoke_lease, name,
+    );
+}
@@ -1,9 +1,21 @@
-use crate::provider::Provider;
+use crate::config::normalize_key;
+use crate::doctor::{self, Check, Status};
+use crate::provider::command::{CommandRunner, Invocation, SystemRunner};
+use crate::provider::session::{self, CachedToken, NoTokenCache, TokenCache};
+use crate::provider::{
+    AccessEntry, AccessPolicy, ItemMatch, Provider, SecretMetadata, SecretValue, json_fields,
+    operation_unsupported, short_hash,
+};
+use crate::warning;
 use crate::{Result, SecretSpecError};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use url::Url;
+use zeroize::Zeroizing;
 
 /// Bitwarden service type enum for distinguishing between Password Manager and Secrets Manager
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -14,6 +26,33 @@ pub enum BitwardenService {
     SecretsManager,
 }
 
+/// When the Password Manager provider runs `bw sync` to refresh its local vault copy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BitwardenSync {
+    /// Sync when a key is missing, in case it was added or shared since the last sync
+    #[default]
+    Auto,
+    /// Sync before the first read
+    Always,
+    /// Never sync, leaving it to `bw sync` or the CLI's own schedule
+    Never,
+}
+
+impl BitwardenSync {
+    /// Parse from string (for URI parameters)
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Some(BitwardenSync::Auto),
+            "always" => Some(BitwardenSync::Always),
+            "never" => Some(BitwardenSync::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Default time after a sync during which the vault isn't synced again.
+const DEFAULT_SYNC_COOLDOWN: Duration = Duration::from_secs(300);
+
 /// Bitwarden item type enum for different vault item types
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum BitwardenItemType {
@@ -479,6 +518,15 @@ struct BitwardenSecret {
     pub revision_date: String,
 }
 
+/// A folder of the vault, as listed by `bw list folders`.
+#[derive(Debug, Deserialize)]
+struct BitwardenFolder {
+    /// Unique identifier for the folder, `null` for "No Folder".
+    id: Option<String>,
+    /// The folder's name.
+    name: String,
+}
+
 /// Represents a Bitwarden Secrets Manager project.
 ///
 /// Projects are used to organize secrets in Secrets Manager.
@@ -501,6 +549,48 @@ struct BitwardenProject {
     pub revision_date: String,
 }
 
+/// Represents a member of a Bitwarden organization, as listed by `bw list org-members`.
+#[derive(Debug, Deserialize)]
+struct BitwardenOrgMember {
+    /// Unique identifier of the membership.
+    id: String,
+    /// The member's email address.
+    email: String,
+}
+
+/// Grants an organization member read-only access to a collection.
+///
+/// The collection is the JSON returned by `bw get org-collection`. Members
+/// that already have access keep their current permissions.
+///
+/// # Returns
+///
+/// `true` if the collection was changed
+pub(crate) fn add_collection_reader(collection: &mut serde_json::Value, member_id: &str) -> bool {
+    let users = &mut collection["users"];
+    if !users.is_array() {
+        *users = serde_json::Value::Array(Vec::new());
+    }
+    let Some(users) = users.as_array_mut() else {
+        return false;
+    };
+    if users.iter().any(|user| user["id"] == member_id) {
+        return false;
+    }
+    users.push(collection_reader(member_id));
+    true
+}
+
+/// Returns the collection access entry for a read-only member.
+fn collection_reader(member_id: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": member_id,
+        "readOnly": true,
+        "hidePasswords": false,
+        "manage": false,
+    })
+}
+
 /// Configuration for the Bitwarden provider.
 ///
 /// This struct contains all the necessary configuration options for
@@ -547,11 +637,35 @@ pub struct BitwardenConfig {
     /// When set, the CLI will be configured to use the specified server
     /// instead of the default bitwarden.com. Should include the full URL.
     pub server: Option<String>,
+    /// Whether a server other than the CLI's current one gets its own `bw`
+    /// data directory (Password Manager only).
+    ///
+    /// With isolation (the default) the server is configured in a separate
+    /// `BITWARDENCLI_APPDATA_DIR` and the user's own CLI setup is never
+    /// touched. Without it the CLI is switched to the server, which requires
+    /// being logged out, and switched back when the provider is dropped.
+    pub isolate_server: bool,
     /// Optional folder name prefix for organizing secrets in Bitwarden (Password Manager only).
     ///
     /// Supports placeholders: {project} and {profile}.
     /// Defaults to "secretspec/{project}/{profile}" if not specified.
     pub folder_prefix: Option<String>,
+    /// When to run `bw sync` before reading (Password Manager only).
+    ///
+    /// The vault is synced at most once per process.
+    pub sync: BitwardenSync,
+    /// Time after the last sync during which the vault isn't synced again
+    /// (Password Manager only).
+    ///
+    /// The last sync is read from `bw sync --last`, so syncs by other
+    /// processes count too.
+    pub sync_cooldown: Duration,
+    /// Reference to the master password used to unlock the vault
+    /// (Password Manager only), as `<provider-uri>#<KEY>`.
+    ///
+    /// Together with `BW_CLIENTID`/`BW_CLIENTSECRET` for `bw login --apikey`
+    /// this allows fully non-interactive use, e.g. in CI.
+    pub password_ref: Option<String>,
 
     // Secrets Manager specific fields
     /// Optional project ID for Secrets Manager projects.
@@ -563,6 +677,13 @@ pub struct BitwardenConfig {
     ///
     /// If not provided, will use BWS_ACCESS_TOKEN environment variable.
     pub access_token: Option<String>,
+    /// Directory holding the CLIs' authentication state.
+    ///
+    /// Each `bws` access token gets its own state file in this directory,
+    /// passed with `--state-file` so it doesn't log in on every command, and
+    /// each isolated `bw` server its own data directory. Defaults to
+    /// `bws-state` and `bw-servers` in the secretspec data directory.
+    pub state_dir: Option<PathBuf>,
 
     // Flexible item creation fields
     /// Default item type for creating new items.
@@ -580,9 +701,14 @@ impl Default for BitwardenConfig {
             organization_id: None,
             collection_id: None,
             server: None,
+            isolate_server: true,
             folder_prefix: None,
+            sync: BitwardenSync::default(),
+            sync_cooldown: DEFAULT_SYNC_COOLDOWN,
+            password_ref: None,
             project_id: None,
             access_token: None,
+            state_dir: None,
             default_item_type: Some(BitwardenItemType::Login), // Login by default
             default_field: None,
         }
@@ -635,7 +761,30 @@ impl TryFrom<&Url> for BitwardenConfig {
                         "org" | "organization" => config.organization_id = Some(value.into_owned()),
                         "collection" => config.collection_id = Some(value.into_owned()),
                         "server" => config.server = Some(value.into_owned()),
+                        "isolate" => config.isolate_server = value != "false",
+                        "state_dir" => config.state_dir = Some(PathBuf::from(value.as_ref())),
                         "folder" => config.folder_prefix = Some(value.into_owned()),
+                        "sync" => {
+                            config.sync = BitwardenSync::from_str(&value).ok_or_else(|| {
+                                SecretSpecError::ProviderOperationFailed(format!(
+                                    "Invalid sync mode '{}' for Bitwarden provider. Use auto, always or never.",
+                                    value
+                                ))
+                            })?;
+                        }
+                        "sync_cooldown" => {
+                            let seconds = value.parse().map_err(|_| {
+                                SecretSpecError::ProviderOperationFailed(format!(
+                                    "Invalid sync_cooldown '{}' for Bitwarden provider. Use a number of seconds.",
+                                    value
+                                ))
+                            })?;
+                            config.sync_cooldown = Duration::from_secs(seconds);
+                        }
+                        "password_ref" => {
+                            split_reference(&value)?;
+                            config.password_ref = Some(value.into_owned());
+                        }
                         "type" => {
                             if let Some(item_type) = BitwardenItemType::from_str(&value) {
                                 config.default_item_type = Some(item_type);
@@ -660,6 +809,7 @@ impl TryFrom<&Url> for BitwardenConfig {
                     match key.as_ref() {
                         "project" => config.project_id = Some(value.into_owned()),
                         "token" => config.access_token = Some(value.into_owned()),
+                        "state_dir" => config.state_dir = Some(PathBuf::from(value.as_ref())),
                         "type" => {
                             if let Some(item_type) = BitwardenItemType::from_str(&value) {
                                 config.default_item_type = Some(item_type);
@@ -684,7 +834,79 @@ impl TryFrom<Url> for BitwardenConfig {
     }
 }
 
-impl BitwardenConfig {}
+impl BitwardenConfig {
+    /// Returns the query parameters understood for a scheme.
+    pub(crate) fn params(scheme: &str) -> &'static [&'static str] {
+        match scheme {
+            "bws" => &["project", "token", "state_dir", "type", "field"],
+            _ => &[
+                "org",
+                "organization",
+                "collection",
+                "server",
+                "isolate",
+                "state_dir",
+                "folder",
+                "sync",
+                "sync_cooldown",
+                "password_ref",
+                "type",
+                "field",
+            ],
+        }
+    }
+
+    /// Returns the `bws` state file for an access token.
+    ///
+    /// The file is named after a hash of the token so that switching tokens
+    /// never reuses another machine account's session. Returns `None` if no
+    /// state directory is configured and the data directory is unknown.
+    pub fn state_file(&self, access_token: &str) -> Option<PathBuf> {
+        let dir = self.state_root("bws-state")?;
+        Some(dir.join(format!("{}.json", short_hash(access_token))))
+    }
+
+    /// Returns the `bw` data directory used for an isolated server.
+    ///
+    /// Returns `None` if no state directory is configured and the data
+    /// directory is unknown.
+    pub fn server_appdata_dir(&self, server: &str) -> Option<PathBuf> {
+        let dir = self.state_root("bw-servers")?;
+        Some(dir.join(short_hash(normalize_server(server))))
+    }
+
+    /// Returns the configured state directory, or `default` in the
+    /// secretspec data directory.
+    fn state_root(&self, default: &str) -> Option<PathBuf> {
+        match &self.state_dir {
+            Some(dir) => Some(dir.clone()),
+            None => Some(
+                directories::ProjectDirs::from("", "", "secretspec")?
+                    .data_local_dir()
+                    .join(default),
+            ),
+        }
+    }
+}
+
+/// Normalizes a server URL for comparison with `bw config server` output.
+fn normalize_server(server: &str) -> &str {
+    server.trim().trim_end_matches('/')
+}
+
+/// Creates a directory holding CLI state, readable only by the user.
+///
+/// Returns whether the directory is usable.
+fn create_state_dir(dir: &Path) -> bool {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir).is_ok()
+}
 
 /// Provider implementation for Bitwarden password manager.
 ///
@@ -722,6 +944,25 @@ impl BitwardenConfig {}
 pub struct BitwardenProvider {
     /// Configuration for the provider including org/collection settings.
     config: BitwardenConfig,
+    /// Runs the `bw` and `bws` CLIs.
+    runner: Arc<dyn CommandRunner>,
+    /// Whether this provider already considered syncing the vault.
+    sync_checked: Mutex<bool>,
+    /// Session key from unlocking the vault with `password_ref`.
+    session: Mutex<Option<String>>,
+    /// Stores session keys between commands.
+    session_cache: Arc<dyn TokenCache>,
+    /// How the CLI was pointed at the configured server, once it was.
+    server_setup: Mutex<Option<ServerSetup>>,
+}
+
+/// How the `bw` CLI was set up to talk to the configured server.
+#[derive(Debug, Default)]
+struct ServerSetup {
+    /// Data directory of the isolated CLI configuration, if one is used
+    appdata_dir: Option<PathBuf>,
+    /// Server the user's CLI was switched away from, restored on drop
+    previous_server: Option<String>,
 }
 
 crate::register_provider! {
@@ -737,6 +978,83 @@ crate::register_provider! {
         "bws://",
         "bws://project-id"
     ],
+    params: BitwardenConfig::params,
+}
+
+/// How long Bitwarden Secrets Manager asks clients to back off after a rate limit.
+const BWS_RATE_LIMIT_DELAY: Duration = Duration::from_secs(20);
+
+/// Service that session keys are cached under.
+const SESSION_CACHE_SERVICE: &str = "secretspec-bitwarden";
+
+/// How long a cached session key is reused by later commands.
+///
+/// `bw` session keys don't expire until the vault is locked, so this bounds
+/// how long a leaked cache entry stays useful.
+const SESSION_TTL: Duration = Duration::from_secs(3600);
+
+/// Server `bw` uses when none is configured.
+const DEFAULT_SERVER: &str = "https://vault.bitwarden.com";
+
+/// Output of `bw status`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BwStatus {
+    /// `unauthenticated`, `locked` or `unlocked`
+    #[serde(default)]
+    status: String,
+    /// Server the CLI is logged in to, `null` for the default server
+    #[serde(default)]
+    server_url: Option<String>,
+    /// Account the CLI is logged in as
+    #[serde(default)]
+    user_email: Option<String>,
+}
+
+/// Returns the error for operations that need an unlocked vault.
+fn unlock_required() -> SecretSpecError {
+    SecretSpecError::AuthRequired {
+        provider: "Bitwarden".to_string(),
+        fix: "Run 'bw login' and 'bw unlock', then set the BW_SESSION environment variable."
+            .to_string(),
+    }
+}
+
+/// Splits a `<provider-uri>#<KEY>` reference into the provider URI and key.
+fn split_reference(reference: &str) -> Result<(&str, &str)> {
+    match reference.rsplit_once('#') {
+        Some((uri, key)) if !uri.is_empty() && !key.is_empty() => Ok((uri, key)),
+        _ => Err(SecretSpecError::ProviderOperationFailed(format!(
+            "Invalid password_ref '{}' for Bitwarden provider. Use <provider-uri>#<KEY>, e.g. keyring://#BW_PASSWORD",
+            reference
+        ))),
+    }
+}
+
+/// Parses a UTC timestamp as printed by `bw sync --last`, such as
+/// `2024-01-15T10:30:00.000Z`.
+fn parse_sync_time(value: &str) -> Option<SystemTime> {
+    let value = value.trim().strip_suffix('Z')?;
+    let (date, time) = value.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let time = time.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since the Unix epoch in the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
 }
 
 impl BitwardenProvider {
@@ -746,7 +1064,112 @@ impl BitwardenProvider {
     ///
     /// * `config` - The configuration for the provider
     pub fn new(config: BitwardenConfig) -> Self {
-        Self { config }
+        Self::with_runner(config, Arc::new(SystemRunner))
+            .with_session_cache(session::default_cache(SESSION_CACHE_SERVICE))
+    }
+
+    /// Creates a BitwardenProvider that runs the `bw` and `bws` CLIs through `runner`.
+    ///
+    /// Session keys are only kept for the lifetime of the provider.
+    pub(crate) fn with_runner(config: BitwardenConfig, runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            config,
+            runner,
+            sync_checked: Mutex::new(false),
+            session: Mutex::new(None),
+            session_cache: Arc::new(NoTokenCache),
+            server_setup: Mutex::new(None),
+        }
+    }
+
+    /// Replaces the cache that session keys are stored in between commands.
+    pub(crate) fn with_session_cache(mut self, session_cache: Arc<dyn TokenCache>) -> Self {
+        self.session_cache = session_cache;
+        self
+    }
+
+    /// Builds a Bitwarden Password Manager CLI invocation.
+    ///
+    /// Points the CLI at the configured server first, and sets `BW_SESSION`
+    /// once the provider unlocked the vault itself.
+    fn bw_invocation(&self, args: &[&str]) -> Result<Invocation> {
+        let mut invocation = Invocation::new("bw").args(args);
+        if let Some(appdata_dir) = self.setup_server()? {
+            invocation = invocation.env("BITWARDENCLI_APPDATA_DIR", appdata_dir.to_string_lossy());
+        }
+        if let Some(session) = &*self.session.lock().unwrap() {
+            invocation = invocation.env("BW_SESSION", session);
+        }
+        Ok(invocation)
+    }
+
+    /// Points the CLI at the configured self-hosted server, once.
+    ///
+    /// `BW_SERVER` isn't honored by every CLI version, so the server is set
+    /// with `bw config server`. If the CLI already uses it nothing changes.
+    /// Otherwise the server is configured in its own data directory, or, with
+    /// isolation turned off, the user's CLI is switched and later restored.
+    ///
+    /// # Returns
+    ///
+    /// The `BITWARDENCLI_APPDATA_DIR` to run `bw` with, if any
+    fn setup_server(&self) -> Result<Option<PathBuf>> {
+        let Some(server) = &self.config.server else {
+            return Ok(None);
+        };
+        let mut setup = self.server_setup.lock().unwrap();
+        if let Some(setup) = &*setup {
+            return Ok(setup.appdata_dir.clone());
+        }
+
+        let config_server = |appdata_dir: Option<&Path>| {
+            let invocation = Invocation::new("bw").args(["config", "server"]);
+            match appdata_dir {
+                Some(dir) => invocation.env("BITWARDENCLI_APPDATA_DIR", dir.to_string_lossy()),
+                None => invocation,
+            }
+        };
+        let current = self.run_bw(&config_server(None))?;
+
+        let mut new_setup = ServerSetup::default();
+        if normalize_server(&current) != normalize_server(server) {
+            if self.config.isolate_server {
+                let dir = self
+                    .config
+                    .server_appdata_dir(server)
+                    .filter(|dir| create_state_dir(dir))
+                    .ok_or_else(|| {
+                        SecretSpecError::ProviderOperationFailed(
+                            "Could not create a Bitwarden CLI data directory for the server"
+                                .to_string(),
+                        )
+                    })?;
+                let isolated = self.run_bw(&config_server(Some(&dir)))?;
+                if normalize_server(&isolated) != normalize_server(server) {
+                    self.run_bw(&config_server(Some(&dir)).args([server]))?;
+                }
+                new_setup.appdata_dir = Some(dir);
+            } else {
+                self.run_bw(&config_server(None).args([server]))
+                    .map_err(|e| match e {
+                        SecretSpecError::ProviderOperationFailed(msg)
+                            if msg.contains("Logout required") =>
+                        {
+                            SecretSpecError::ProviderOperationFailed(format!(
+                                "Cannot switch the Bitwarden CLI to {} while logged in to {}. Log out, or remove isolate=false to use a separate CLI configuration.",
+                                server,
+                                current.trim()
+                            ))
+                        }
+                        e => e,
+                    })?;
+                new_setup.previous_server = Some(current.trim().to_string());
+            }
+        }
+
+        let appdata_dir = new_setup.appdata_dir.clone();
+        *setup = Some(new_setup);
+        Ok(appdata_dir)
     }
 
     /// Executes a Bitwarden Password Manager CLI command with proper error handling.
@@ -771,48 +1194,84 @@ impl BitwardenProvider {
     /// - Missing Bitwarden CLI installation
     /// - Authentication required (not logged in or unlocked)
     /// - Command execution failures
-    fn execute_bw_command(&self, args: &[&str]) -> Result<String> {
-        let mut cmd = Command::new("bw");
+    fn execute_bw_command(&self, args: &[&str]) -> Result<Zeroizing<String>> {
+        self.run_bw(&self.bw_invocation(args)?)
+    }
 
-        // Configure server if specified
-        if let Some(server) = &self.config.server {
-            cmd.env("BW_SERVER", server);
+    /// Syncs the vault unless it was synced within the cooldown.
+    ///
+    /// Only the first call per provider does anything, so a process syncs at
+    /// most once. A failed sync is reported as a warning, since the local
+    /// copy of the vault can still be read.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - The vault was synced
+    /// * `Ok(false)` - The vault was not synced
+    fn sync_if_due(&self) -> Result<bool> {
+        let mut checked = self.sync_checked.lock().unwrap();
+        if self.config.sync == BitwardenSync::Never || *checked {
+            return Ok(false);
+        }
+        *checked = true;
+
+        // `bw sync --last` prints nothing if the vault was never synced
+        let last = parse_sync_time(&self.execute_bw_command(&["sync", "--last"])?);
+        if let Some(last) = last
+            && SystemTime::now()
+                .duration_since(last)
+                .map_or(true, |elapsed| elapsed < self.config.sync_cooldown)
+        {
+            return Ok(false);
         }
 
-        cmd.args(args);
+        match self.execute_bw_command(&["sync"]) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                warning::warn(format!("Failed to sync the Bitwarden vault: {}", e));
+                Ok(false)
+            }
+        }
+    }
 
-        let output = match cmd.output() {
+    /// Runs a `bw` invocation, translating common failures into helpful errors.
+    fn run_bw(&self, invocation: &Invocation) -> Result<Zeroizing<String>> {
+        let output = match self.runner.run(invocation) {
             Ok(output) => output,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden CLI (bw) is not installed.\n\nTo install it:\n  - npm: npm install -g @bitwarden/cli\n  - Homebrew: brew install bitwarden-cli\n  - Chocolatey: choco install bitwarden-cli\n  - Download: https://bitwarden.com/help/cli/\n\nAfter installation, run 'bw login' and 'bw unlock' to authenticate.".to_string(),
-                ));
+                return Err(SecretSpecError::CliMissing {
+                    cli: "Bitwarden CLI (bw)".to_string(),
+                    install_hint: "To install it:\n  - npm: npm install -g @bitwarden/cli\n  - Homebrew: brew install bitwarden-cli\n  - Chocolatey: choco install bitwarden-cli\n  - Download: https://bitwarden.com/help/cli/\n\nAfter installation, run 'bw login' and 'bw unlock' to authenticate.".to_string(),
+                });
             }
             Err(e) => return Err(e.into()),
         };
 
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
+        if !output.success {
+            let error_msg = output.stderr;
 
             if error_msg.contains("You are not logged in") {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden authentication required. Please run 'bw login' first.".to_string(),
-                ));
+                return Err(SecretSpecError::AuthRequired {
+                    provider: "Bitwarden".to_string(),
+                    fix: "Run 'bw login' first.".to_string(),
+                });
             }
 
             if error_msg.contains("Vault is locked") {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden vault is locked. Please run 'bw unlock' and set the BW_SESSION environment variable.".to_string(),
-                ));
+                return Err(SecretSpecError::AuthRequired {
+                    provider: "Bitwarden".to_string(),
+                    fix: "The vault is locked. Run 'bw unlock' and set the BW_SESSION environment variable.".to_string(),
+                });
             }
 
-            return Err(SecretSpecError::ProviderOperationFailed(
-                error_msg.to_string(),
-            ));
+            if error_msg.contains("Not found.") {
+                return Err(SecretSpecError::NotFound(error_msg));
+            }
+
+            return Err(SecretSpecError::ProviderOperationFailed(error_msg));
         }
 
-        String::from_utf8(output.stdout)
-            .map_err(|e| SecretSpecError::ProviderOperationFailed(e.to_string()))
+        Ok(output.stdout)
     }
 
     /// Executes a Bitwarden Secrets Manager CLI command with proper error handling.
@@ -839,87 +1298,248 @@ impl BitwardenProvider {
     /// - Authentication required (missing access token)
     /// - Rate limiting issues
     /// - Command execution failures
-    fn execute_bws_command(&self, args: &[&str]) -> Result<String> {
-        let mut cmd = Command::new("bws");
+    fn execute_bws_command(&self, args: &[&str]) -> Result<Zeroizing<String>> {
+        let mut invocation = Invocation::new("bws").args(args);
 
         // Configure access token - check config first, then environment variable
-        if let Some(token) = &self.config.access_token {
-            cmd.env("BWS_ACCESS_TOKEN", token);
-        } else if let Ok(token) = std::env::var("BWS_ACCESS_TOKEN") {
-            cmd.env("BWS_ACCESS_TOKEN", token);
+        let token = self
+            .config
+            .access_token
+            .clone()
+            .or_else(|| std::env::var("BWS_ACCESS_TOKEN").ok());
+        if let Some(token) = token {
+            // Persist the session so repeated commands don't log in again,
+            // which is what trips the rate limit on the identity endpoint
+            if let Some(state_file) = self.config.state_file(&token)
+                && state_file.parent().is_some_and(create_state_dir)
+            {
+                invocation = invocation.args(["--state-file", &state_file.to_string_lossy()]);
+            }
+            invocation = invocation.env("BWS_ACCESS_TOKEN", token);
         }
 
-        cmd.args(args);
-
-        let output = match cmd.output() {
+        let output = match self.runner.run(&invocation) {
             Ok(output) => output,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden Secrets Manager CLI (bws) is not installed.\n\nTo install it:\n  - Cargo: cargo install bws\n  - Script: curl -sSL https://bitwarden.com/secrets/install | sh\n  - Download: https://github.com/bitwarden/sdk-sm/releases\n\nAfter installation, set BWS_ACCESS_TOKEN environment variable with your access token.".to_string(),
-                ));
+                return Err(SecretSpecError::CliMissing {
+                    cli: "Bitwarden Secrets Manager CLI (bws)".to_string(),
+                    install_hint: "To install it:\n  - Cargo: cargo install bws\n  - Script: curl -sSL https://bitwarden.com/secrets/install | sh\n  - Download: https://github.com/bitwarden/sdk-sm/releases\n\nAfter installation, set BWS_ACCESS_TOKEN environment variable with your access token.".to_string(),
+                });
             }
             Err(e) => return Err(e.into()),
         };
 
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
+        if !output.success {
+            let error_msg = output.stderr;
 
             // Handle common Secrets Manager errors
             if error_msg.contains("Access token is required") || error_msg.contains("Unauthorized")
             {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden Secrets Manager authentication required. Please set the BWS_ACCESS_TOKEN environment variable with your machine account access token.".to_string(),
-                ));
+                return Err(SecretSpecError::AuthRequired {
+                    provider: "Bitwarden Secrets Manager".to_string(),
+                    fix: "Set the BWS_ACCESS_TOKEN environment variable with your machine account access token.".to_string(),
+                });
             }
 
             if error_msg.contains("Internal error: Failed to parse IdentityTokenResponse") {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden Secrets Manager rate limit exceeded. Please wait ~20 seconds and try again. Consider using state files to reduce API calls.".to_string(),
-                ));
+                return Err(SecretSpecError::RateLimited {
+                    provider: "Bitwarden Secrets Manager".to_string(),
+                    retry_after: Some(BWS_RATE_LIMIT_DELAY),
+                });
             }
 
             if error_msg.contains("Resource not found") || error_msg.contains("Not found") {
                 // This often indicates permission issues rather than missing resources
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden Secrets Manager access denied. Please verify:\n1. Machine account has read/write access to the specified project\n2. Project ID is correct\n3. Organization permissions are properly configured\n\nResource not found errors often indicate permission issues rather than missing resources.".to_string()
+                return Err(SecretSpecError::PermissionDenied(
+                    "Bitwarden Secrets Manager could not access the resource.\n\nPlease verify:\n1. Machine account has read/write access to the specified project\n2. Project ID is correct\n3. Organization permissions are properly configured\n\nResource not found errors often indicate permission issues rather than missing resources.".to_string()
                 ));
             }
 
+            if error_msg.contains("already exists") {
+                return Err(SecretSpecError::Conflict(error_msg));
+            }
+
             return Err(SecretSpecError::ProviderOperationFailed(format!(
                 "Bitwarden Secrets Manager CLI error: {}",
                 error_msg
             )));
         }
 
-        String::from_utf8(output.stdout)
-            .map_err(|e| SecretSpecError::ProviderOperationFailed(e.to_string()))
+        Ok(output.stdout)
+    }
+
+    /// Returns the vault status reported by `bw status`: `unauthenticated`,
+    /// `locked` or `unlocked`.
+    fn status(&self) -> Result<String> {
+        Ok(self.status_info()?.status)
+    }
+
+    /// Returns the output of `bw status`.
+    fn status_info(&self) -> Result<BwStatus> {
+        match self.execute_bw_command(&["status"]) {
+            Ok(output) => Ok(serde_json::from_str(&output)?),
+            Err(SecretSpecError::AuthRequired { .. }) => Ok(BwStatus {
+                status: "unauthenticated".to_string(),
+                ..BwStatus::default()
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the key the session of the logged in account is cached under,
+    /// or `None` if the account isn't known.
+    fn session_cache_key(&self, status: &BwStatus) -> Option<String> {
+        let server = status
+            .server_url
+            .as_deref()
+            .or(self.config.server.as_deref())
+            .unwrap_or(DEFAULT_SERVER);
+        let email = status.user_email.as_deref()?;
+        Some(format!("{}|{}", normalize_server(server), email))
+    }
+
+    /// Uses a session key cached by an earlier command, if it still unlocks
+    /// the vault. A stale key is removed from the cache.
+    fn restore_session(&self, key: &str) -> Result<bool> {
+        let Some(cached) = self.session_cache.load(key) else {
+            return Ok(false);
+        };
+        if cached.is_fresh() {
+            *self.session.lock().unwrap() = Some(cached.token);
+            if self.status()? == "unlocked" {
+                return Ok(true);
+            }
+            *self.session.lock().unwrap() = None;
+        }
+        self.session_cache.clear(key);
+        Ok(false)
     }
 
-    /// Checks if the user is authenticated with Bitwarden.
+    /// Makes sure the vault is unlocked, logging in and unlocking it
+    /// non-interactively if possible.
     ///
-    /// Uses the `bw status` command to verify authentication status.
-    /// This is non-intrusive and provides detailed status information.
+    /// Logging in uses the API key in `BW_CLIENTID` and `BW_CLIENTSECRET`;
+    /// unlocking uses the master password that `password_ref` points to,
+    /// read for the same project and profile. The session key is cached per
+    /// server and account, so later commands skip unlocking while it lasts.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// * `Ok(true)` - User is authenticated and unlocked
-    /// * `Ok(false)` - User is not authenticated or vault is locked
-    /// * `Err(_)` - Command execution failed
-    fn is_authenticated(&self) -> Result<bool> {
-        match self.execute_bw_command(&["status"]) {
-            Ok(output) => {
-                // Parse the JSON status response
-                let status: serde_json::Value = serde_json::from_str(&output)?;
-                let status_str = status["status"].as_str().unwrap_or("");
-                Ok(status_str == "unlocked")
+    /// Returns [`SecretSpecError::AuthRequired`] if the vault is locked and
+    /// can't be unlocked without the user
+    fn ensure_unlocked(&self, project: &str, profile: &str) -> Result<()> {
+        let status = self.status_info()?;
+        let cache_key = self.session_cache_key(&status);
+        match status.status.as_str() {
+            "unlocked" => return Ok(()),
+            "unauthenticated" => {
+                if std::env::var_os("BW_CLIENTID").is_none()
+                    || std::env::var_os("BW_CLIENTSECRET").is_none()
+                {
+                    let login = match self.setup_server()? {
+                        Some(dir) => {
+                            format!("BITWARDENCLI_APPDATA_DIR={} bw login", dir.display())
+                        }
+                        None => "bw login".to_string(),
+                    };
+                    return Err(SecretSpecError::AuthRequired {
+                        provider: "Bitwarden".to_string(),
+                        fix: format!(
+                            "Run '{}', or set BW_CLIENTID and BW_CLIENTSECRET to log in with an API key.",
+                            login
+                        ),
+                    });
+                }
+                // `bw` reads the API key from the environment
+                self.execute_bw_command(&["login", "--apikey"])?;
             }
-            Err(SecretSpecError::ProviderOperationFailed(msg))
-                if msg.contains("You are not logged in") || msg.contains("Vault is locked") =>
-            {
-                Ok(false)
+            _ => {
+                if let Some(key) = &cache_key
+                    && self.restore_session(key)?
+                {
+                    return Ok(());
+                }
             }
-            Err(e) => Err(e),
         }
+
+        let Some(reference) = &self.config.password_ref else {
+            return Err(unlock_required());
+        };
+        let (uri, key) = split_reference(reference)?;
+        let password = Box::<dyn Provider>::try_from(uri)?
+            .get(project, key, profile)?
+            .ok_or_else(|| SecretSpecError::SecretNotFound(key.to_string()))?;
+
+        let session = self.run_bw(
+            &self
+                .bw_invocation(&["unlock", "--passwordenv", "BW_PASSWORD", "--raw"])?
+                .env("BW_PASSWORD", password.expose_secret()),
+        )?;
+        let session = session.trim().to_string();
+        if let Some(key) = &cache_key {
+            self.session_cache
+                .store(key, &CachedToken::new(session.clone(), Some(SESSION_TTL)));
+        }
+        *self.session.lock().unwrap() = Some(session);
+        Ok(())
+    }
+
+    /// Returns the organization and collection whose access is managed.
+    ///
+    /// Access can only be managed for Password Manager organization
+    /// collections; the `BITWARDEN_ORGANIZATION` and `BITWARDEN_COLLECTION`
+    /// environment variables override the URI like they do for stored items.
+    fn organization_collection(&self) -> Result<(String, String)> {
+        if self.config.service == BitwardenService::SecretsManager {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "Bitwarden Secrets Manager access is managed in the Bitwarden web app.\n\nGrant the machine account or user access to the project under Projects > Access.".to_string(),
+            ));
+        }
+        let organization = std::env::var("BITWARDEN_ORGANIZATION")
+            .ok()
+            .or_else(|| self.config.organization_id.clone());
+        let collection = std::env::var("BITWARDEN_COLLECTION")
+            .ok()
+            .or_else(|| self.config.collection_id.clone());
+        match (organization, collection) {
+            (Some(organization), Some(collection)) => Ok((organization, collection)),
+            _ => Err(SecretSpecError::ProviderOperationFailed(
+                "Bitwarden access can only be managed for organization collections.\n\nUse a provider URI like bitwarden://org@collection.".to_string(),
+            )),
+        }
+    }
+
+    /// Lists the members of an organization.
+    fn org_members(&self, organization: &str) -> Result<Vec<BitwardenOrgMember>> {
+        let output =
+            self.execute_bw_command(&["list", "org-members", "--organizationid", organization])?;
+        Ok(serde_json::from_str(&output)?)
+    }
+
+    /// Retrieves an organization collection including its member access.
+    fn org_collection(&self, organization: &str, collection: &str) -> Result<serde_json::Value> {
+        let output = self.execute_bw_command(&[
+            "get",
+            "org-collection",
+            collection,
+            "--organizationid",
+            organization,
+        ])?;
+        Ok(serde_json::from_str(&output)?)
+    }
+
+    /// Looks up the membership ID of an organization member by email.
+    fn member_id(&self, organization: &str, email: &str) -> Result<String> {
+        self.org_members(organization)?
+            .into_iter()
+            .find(|member| member.email.eq_ignore_ascii_case(email))
+            .map(|member| member.id)
+            .ok_or_else(|| {
+                SecretSpecError::ProviderOperationFailed(format!(
+                    "'{}' is not a member of Bitwarden organization {}.\n\nInvite them to the organization first.",
+                    email, organization
+                ))
+            })
     }
 
     /// Formats the item name for storage in Bitwarden.
@@ -1026,18 +1646,57 @@ impl BitwardenProvider {
         project: &str,
         key: &str,
         profile: &str,
-    ) -> Result<Option<SecretString>> {
+    ) -> Result<Option<SecretValue>> {
         // Check authentication status first
-        if !self.is_authenticated()? {
-            return Err(SecretSpecError::ProviderOperationFailed(
-                "Bitwarden authentication required. Please run 'bw login' and 'bw unlock', then set the BW_SESSION environment variable.".to_string(),
-            ));
+        self.ensure_unlocked(project, profile)?;
+
+        if self.config.sync == BitwardenSync::Always {
+            self.sync_if_due()?;
         }
 
-        eprintln!("DEBUG: get_from_password_manager called for key='{}'", key);
+        // Bitwarden's search matches any part of any field, so only use an
+        // item whose name is the key
+        let items = self.search_items(key)?;
+        let legacy_item_name = self.format_item_name(project, key, profile);
+        if let Some(item) = Self::find_item(&items, key, &legacy_item_name) {
+            let metadata = SecretMetadata {
+                revised: item.revision_date.clone(),
+                ..SecretMetadata::default()
+            };
+            return Ok(self
+                .extract_value_from_item(item, key)?
+                .map(|value| SecretValue { value, metadata }));
+        }
+
+        // No matching item found
+        Ok(None)
+    }
+
+    /// Returns the item named after a key.
+    ///
+    /// An exact match of the key, or of the legacy `secretspec/...` item
+    /// name, wins. Otherwise the names are compared in their normalized form,
+    /// so `Stripe API Key` matches `STRIPE_API_KEY` but `OLD_STRIPE_API_KEY`
+    /// doesn't.
+    fn find_item<'a>(
+        items: &'a [BitwardenItem],
+        key: &str,
+        legacy_item_name: &str,
+    ) -> Option<&'a BitwardenItem> {
+        let normalized = normalize_key(key);
+        items
+            .iter()
+            .find(|item| item.name == legacy_item_name || item.name == key)
+            .or_else(|| {
+                items
+                    .iter()
+                    .find(|item| normalize_key(&item.name) == normalized)
+            })
+    }
 
-        // Use Bitwarden's built-in search to find items matching the key
-        let mut list_args = vec!["list", "items", "--search", key];
+    /// Searches the vault for items, syncing once if nothing is found.
+    fn search_items(&self, search: &str) -> Result<Vec<BitwardenItem>> {
+        let mut list_args = vec!["list", "items", "--search", search];
 
         // Add organization filter if configured (from config or environment variable)
         let org_id = std::env::var("BITWARDEN_ORGANIZATION")
@@ -1048,15 +1707,109 @@ impl BitwardenProvider {
         }
 
         let output = self.execute_bw_command(&list_args)?;
-        let items: Vec<BitwardenItem> = serde_json::from_str(&output)?;
+        let mut items: Vec<BitwardenItem> = serde_json::from_str(&output)?;
 
-        // If we found items, use the first one (Bitwarden's search is already good)
-        if let Some(item) = items.first() {
-            return self.extract_value_from_item(item, key);
+        // The item may have been added or shared since the last sync
+        if items.is_empty() && self.sync_if_due()? {
+            let output = self.execute_bw_command(&list_args)?;
+            items = serde_json::from_str(&output)?;
         }
 
-        // No matching item found
-        Ok(None)
+        Ok(items)
+    }
+
+    /// Searches the trash for deleted items.
+    fn search_trash(&self, search: &str) -> Result<Vec<BitwardenItem>> {
+        let mut list_args = vec!["list", "items", "--trash", "--search", search];
+
+        let org_id = std::env::var("BITWARDEN_ORGANIZATION")
+            .ok()
+            .or_else(|| self.config.organization_id.clone());
+        if let Some(org_id) = &org_id {
+            list_args.extend_from_slice(&["--organizationid", org_id]);
+        }
+
+        let output = self.execute_bw_command(&list_args)?;
+        Ok(serde_json::from_str(&output)?)
+    }
+
+    /// Deletes the item named after a key, moving it to the trash unless
+    /// `permanent` is set.
+    ///
+    /// A permanent delete also erases an item that is already in the trash.
+    fn delete_from_password_manager(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+        permanent: bool,
+    ) -> Result<()> {
+        self.ensure_unlocked(project, profile)?;
+
+        let legacy_item_name = self.format_item_name(project, key, profile);
+        let items = self.search_items(key)?;
+        let mut id = Self::find_item(&items, key, &legacy_item_name).map(|item| item.id.clone());
+        if id.is_none() && permanent {
+            let trash = self.search_trash(key)?;
+            id = Self::find_item(&trash, key, &legacy_item_name).map(|item| item.id.clone());
+        }
+        let Some(id) = id else {
+            return Ok(());
+        };
+
+        let mut args = vec!["delete", "item", id.as_str()];
+        if permanent {
+            args.push("--permanent");
+        }
+        self.execute_bw_command(&args)?;
+        Ok(())
+    }
+
+    /// Deletes a Secrets Manager secret, which has no trash.
+    fn delete_from_secrets_manager(&self, project: &str, key: &str) -> Result<()> {
+        let secret_name = format!("{}_{}", project, key);
+        let mut args = vec!["secret", "list"];
+        if let Some(project_id) = &self.config.project_id {
+            args.push(project_id);
+        }
+        let output = match self.execute_bws_command(&args) {
+            Ok(output) => output,
+            Err(SecretSpecError::NotFound(_)) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let secrets: Vec<BitwardenSecret> = serde_json::from_str(&output)?;
+        if let Some(secret) = secrets
+            .iter()
+            .find(|secret| secret.key == secret_name || secret.key == key)
+        {
+            self.execute_bws_command(&["secret", "delete", &secret.id])?;
+        }
+        Ok(())
+    }
+
+    /// Returns a field of an item by name.
+    ///
+    /// `username`, `password`, `totp` and `notes` name the built-in fields;
+    /// any other name is looked up among the custom fields.
+    fn item_field(item: &BitwardenItem, name: &str) -> Option<String> {
+        let login = item.login.as_ref();
+        match name.to_lowercase().as_str() {
+            "username" => login.and_then(|login| login.username.clone()),
+            "password" => login.and_then(|login| login.password.clone()),
+            "totp" => login.and_then(|login| login.totp.clone()),
+            "notes" => item.notes.clone(),
+            _ => item
+                .fields
+                .iter()
+                .flatten()
+                .find(|field| {
+                    field
+                        .name
+                        .as_deref()
+                        .is_some_and(|field_name| field_name.eq_ignore_ascii_case(name))
+                })
+                .and_then(|field| field.value.clone()),
+        }
     }
 
     /// Extracts a value from a Bitwarden item using smart field detection based on item type.
@@ -1402,7 +2155,7 @@ impl BitwardenProvider {
         project: &str,
         key: &str,
         _profile: &str,
-    ) -> Result<Option<SecretString>> {
+    ) -> Result<Option<SecretValue>> {
         // For Secrets Manager, we create a secret name based on project and key
         // Profile is encoded in the secret name since SM doesn't have built-in profile support
         let secret_name = format!("{}_{}", project, key);
@@ -1422,16 +2175,20 @@ impl BitwardenProvider {
                 // Look for a secret with matching key name
                 for secret in secrets {
                     if secret.key == secret_name || secret.key == key {
-                        return Ok(Some(SecretString::new(secret.value.into())));
+                        return Ok(Some(SecretValue {
+                            value: SecretString::new(secret.value.into()),
+                            metadata: SecretMetadata {
+                                revised: Some(secret.revision_date),
+                                ..SecretMetadata::default()
+                            },
+                        }));
                     }
                 }
 
                 // No matching secret found
                 Ok(None)
             }
-            Err(SecretSpecError::ProviderOperationFailed(msg)) if msg.contains("Not found") => {
-                Ok(None)
-            }
+            Err(SecretSpecError::NotFound(_)) => Ok(None),
             Err(e) => Err(e),
         }
     }
@@ -1448,11 +2205,7 @@ impl BitwardenProvider {
         profile: &str,
     ) -> Result<()> {
         // Check authentication status first
-        if !self.is_authenticated()? {
-            return Err(SecretSpecError::ProviderOperationFailed(
-                "Bitwarden authentication required. Please run 'bw login' and 'bw unlock', then set the BW_SESSION environment variable.".to_string(),
-            ));
-        }
+        self.ensure_unlocked(project, profile)?;
 
         // First, search for existing items using the same strategy as get()
         let mut list_args = vec!["list", "items"];
@@ -1471,7 +2224,7 @@ impl BitwardenProvider {
         // Search strategies (same as get method):
         // 1. Exact name match with secretspec format (for compatibility)
         // 2. Exact name match with key
-        // 3. Items containing the key in their name
+        // 3. Same name as the key in normalized form, e.g. "Stripe Key"
 
         let legacy_item_name = self.format_item_name(project, key, profile);
 
@@ -1485,10 +2238,11 @@ impl BitwardenProvider {
             return self.update_existing_item(item, key, value.expose_secret());
         }
 
-        // Strategy 3: Contains key in name (case-insensitive)
+        // Strategy 3: Same name in normalized form
+        let normalized = normalize_key(key);
         if let Some(item) = items
             .iter()
-            .find(|item| item.name.to_lowercase().contains(&key.to_lowercase()))
+            .find(|item| normalize_key(&item.name) == normalized)
         {
             return self.update_existing_item(item, key, value.expose_secret());
         }
@@ -1509,23 +2263,33 @@ impl BitwardenProvider {
             .unwrap_or_else(|| item.item_type.default_field_for_hint(key));
 
         // Get the current item as JSON template
-        let mut item_json = self.get_item_as_template(&item.id)?;
+        let item_json = self.get_item_as_template(&item.id)?;
+        self.update_item_json(item, item_json, &target_field, value)
+    }
 
+    /// Sets a field of an item in its JSON template and saves it.
+    fn update_item_json(
+        &self,
+        item: &BitwardenItem,
+        mut item_json: serde_json::Value,
+        target_field: &str,
+        value: &str,
+    ) -> Result<()> {
         match item.item_type {
             BitwardenItemType::Login => {
-                self.update_login_item_json(&mut item_json, &target_field, value)
+                self.update_login_item_json(&mut item_json, target_field, value)
             }
             BitwardenItemType::SecureNote => {
-                self.update_secure_note_item_json(&mut item_json, &target_field, value)
+                self.update_secure_note_item_json(&mut item_json, target_field, value)
             }
             BitwardenItemType::Card => {
-                self.update_card_item_json(&mut item_json, &target_field, value)
+                self.update_card_item_json(&mut item_json, target_field, value)
             }
             BitwardenItemType::Identity => {
-                self.update_identity_item_json(&mut item_json, &target_field, value)
+                self.update_identity_item_json(&mut item_json, target_field, value)
             }
             BitwardenItemType::SshKey => {
-                self.update_ssh_key_item_json(&mut item_json, &target_field, value)
+                self.update_ssh_key_item_json(&mut item_json, target_field, value)
             }
         }?;
 
@@ -1727,16 +2491,8 @@ impl BitwardenProvider {
         // TODO: Research if all item types actually need this encoding or if
         // some could use simpler command formats for better performance
         use base64::{Engine as _, engine::general_purpose};
-        use std::process::Stdio;
         let encoded_json = general_purpose::STANDARD.encode(&item_json_str);
 
-        let mut cmd = std::process::Command::new("bw");
-
-        // Set server if specified
-        if let Some(server) = &self.config.server {
-            cmd.env("BW_SERVER", server);
-        }
-
         let mut args = vec!["edit", "item", item_id];
         let org_id = std::env::var("BITWARDEN_ORGANIZATION")
             .ok()
@@ -1745,39 +2501,7 @@ impl BitwardenProvider {
             args.extend_from_slice(&["--organizationid", org_id]);
         }
 
-        cmd.args(&args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let mut child = cmd.spawn().map_err(|e| {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden CLI (bw) is not installed.\n\nTo install it:\n  - npm: npm install -g @bitwarden/cli\n  - Homebrew: brew install bitwarden-cli\n  - Chocolatey: choco install bitwarden-cli\n  - Download: https://bitwarden.com/help/cli/".to_string(),
-                )
-            } else {
-                SecretSpecError::ProviderOperationFailed(e.to_string())
-            }
-        })?;
-
-        // Write base64-encoded JSON to stdin
-        use std::io::Write;
-        if let Some(stdin) = child.stdin.as_mut() {
-            stdin.write_all(encoded_json.as_bytes()).map_err(|e| {
-                SecretSpecError::ProviderOperationFailed(format!("Failed to write to stdin: {}", e))
-            })?;
-        }
-
-        let output = child
-            .wait_with_output()
-            .map_err(|e| SecretSpecError::ProviderOperationFailed(e.to_string()))?;
-
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            return Err(SecretSpecError::ProviderOperationFailed(
-                error_msg.to_string(),
-            ));
-        }
+        self.run_bw(&self.bw_invocation(&args)?.stdin(encoded_json))?;
 
         Ok(())
     }
@@ -2015,16 +2739,8 @@ impl BitwardenProvider {
         // TODO: Research if all item types actually need this encoding or if
         // some could use simpler command formats for better performance
         use base64::{Engine as _, engine::general_purpose};
-        use std::process::Stdio;
         let encoded_json = general_purpose::STANDARD.encode(&template_json);
 
-        let mut cmd = std::process::Command::new("bw");
-
-        // Set server if specified
-        if let Some(server) = &self.config.server {
-            cmd.env("BW_SERVER", server);
-        }
-
         let mut args = vec!["create", "item"];
         let org_id = std::env::var("BITWARDEN_ORGANIZATION")
             .ok()
@@ -2033,39 +2749,7 @@ impl BitwardenProvider {
             args.extend_from_slice(&["--organizationid", org_id]);
         }
 
-        cmd.args(&args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let mut child = cmd.spawn().map_err(|e| {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                SecretSpecError::ProviderOperationFailed(
-                    "Bitwarden CLI (bw) is not installed.\n\nTo install it:\n  - npm: npm install -g @bitwarden/cli\n  - Homebrew: brew install bitwarden-cli\n  - Chocolatey: choco install bitwarden-cli\n  - Download: https://bitwarden.com/help/cli/".to_string(),
-                )
-            } else {
-                SecretSpecError::ProviderOperationFailed(e.to_string())
-            }
-        })?;
-
-        // Write base64-encoded JSON to stdin
-        use std::io::Write;
-        if let Some(stdin) = child.stdin.as_mut() {
-            stdin.write_all(encoded_json.as_bytes()).map_err(|e| {
-                SecretSpecError::ProviderOperationFailed(format!("Failed to write to stdin: {}", e))
-            })?;
-        }
-
-        let output = child
-            .wait_with_output()
-            .map_err(|e| SecretSpecError::ProviderOperationFailed(e.to_string()))?;
-
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            return Err(SecretSpecError::ProviderOperationFailed(
-                error_msg.to_string(),
-            ));
-        }
+        self.run_bw(&self.bw_invocation(&args)?.stdin(encoded_json))?;
 
         Ok(())
     }
@@ -2105,9 +2789,7 @@ impl BitwardenProvider {
                 // Secret created successfully
                 Ok(())
             }
-            Err(SecretSpecError::ProviderOperationFailed(msg))
-                if msg.contains("already exists") =>
-            {
+            Err(SecretSpecError::Conflict(_)) => {
                 // Secret exists, now we need to update it
                 // First list secrets to find the ID
                 let list_args = vec!["secret", "list", project_id];
@@ -2152,6 +2834,150 @@ impl Provider for BitwardenProvider {
         Self::PROVIDER_NAME
     }
 
+    /// Returns the size limit for Bitwarden field values.
+    ///
+    /// Bitwarden rejects encrypted fields over 5000 characters, so larger
+    /// values are split across several items.
+    fn max_value_size(&self) -> Option<usize> {
+        Some(5000)
+    }
+
+    /// Checks that the `bw` or `bws` CLI is installed and authenticated.
+    fn doctor(&self) -> Vec<Check> {
+        match self.config.service {
+            BitwardenService::PasswordManager => {
+                let cli = doctor::check_cli("Bitwarden CLI", self.execute_bw_command(&["--version"]));
+                if cli.status != Status::Pass {
+                    return vec![cli];
+                }
+
+                let api_key = std::env::var_os("BW_CLIENTID").is_some()
+                    && std::env::var_os("BW_CLIENTSECRET").is_some();
+                let auth = match self.status().as_deref() {
+                    Ok("unlocked") => Check::pass("Bitwarden authentication", "Vault is unlocked"),
+                    Ok(status)
+                        if self.config.password_ref.is_some()
+                            && (status == "locked" || api_key) =>
+                    {
+                        Check::pass(
+                            "Bitwarden authentication",
+                            "Vault is unlocked on use with password_ref",
+                        )
+                    }
+                    Ok(_) => Check::fail(
+                        "Bitwarden authentication",
+                        "Not logged in or vault is locked",
+                        Some("Run 'bw login' and 'bw unlock', then export BW_SESSION".to_string()),
+                    ),
+                    Err(e) => Check::from_error("Bitwarden authentication", e),
+                };
+                vec![cli, auth]
+            }
+            BitwardenService::SecretsManager => {
+                let cli = doctor::check_cli(
+                    "Bitwarden Secrets Manager CLI",
+                    self.execute_bws_command(&["--version"]),
+                );
+                if cli.status != Status::Pass {
+                    return vec![cli];
+                }
+
+                let auth = if self.config.access_token.is_some()
+                    || std::env::var("BWS_ACCESS_TOKEN").is_ok()
+                {
+                    Check::pass("Bitwarden Secrets Manager authentication", "Access token is set")
+                } else {
+                    Check::fail(
+                        "Bitwarden Secrets Manager authentication",
+                        "No access token configured",
+                        Some("Set BWS_ACCESS_TOKEN to your machine account access token".to_string()),
+                    )
+                };
+                vec![cli, auth]
+            }
+        }
+    }
+
+    /// Lists the organization members with access to the collection.
+    ///
+    /// Access is managed per collection, so the result covers every project
+    /// and profile stored in the same collection.
+    fn list_access(&self, _project: &str, _profile: &str) -> Result<Vec<AccessEntry>> {
+        let (organization, collection) = self.organization_collection()?;
+        let members = self.org_members(&organization)?;
+        let collection = self.org_collection(&organization, &collection)?;
+
+        let users = collection["users"].as_array().cloned().unwrap_or_default();
+        Ok(users
+            .iter()
+            .map(|user| {
+                let id = user["id"].as_str().unwrap_or_default();
+                let principal = members
+                    .iter()
+                    .find(|member| member.id == id)
+                    .map_or_else(|| id.to_string(), |member| member.email.clone());
+                let mut permissions = vec![if user["readOnly"] == true {
+                    "read-only".to_string()
+                } else {
+                    "read-write".to_string()
+                }];
+                if user["hidePasswords"] == true {
+                    permissions.push("hide-passwords".to_string());
+                }
+                if user["manage"] == true {
+                    permissions.push("manage".to_string());
+                }
+                AccessEntry {
+                    principal,
+                    permissions,
+                }
+            })
+            .collect())
+    }
+
+    /// Builds the read-only collection access entry for an organization member.
+    fn access_policy(
+        &self,
+        _project: &str,
+        _profile: &str,
+        principal: &str,
+    ) -> Result<AccessPolicy> {
+        let (organization, collection) = self.organization_collection()?;
+        let member_id = self.member_id(&organization, principal)?;
+        let document = serde_json::json!({
+            "organizationId": organization,
+            "collectionId": collection,
+            "users": [collection_reader(&member_id)],
+        });
+        Ok(AccessPolicy {
+            principal: principal.to_string(),
+            scope: format!("collection {}", collection),
+            document: serde_json::to_string_pretty(&document)?,
+        })
+    }
+
+    /// Adds an organization member to the collection with read-only access.
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        let policy = self.access_policy(project, profile, principal)?;
+        let (organization, collection_id) = self.organization_collection()?;
+        let member_id = self.member_id(&organization, principal)?;
+
+        let mut collection = self.org_collection(&organization, &collection_id)?;
+        if add_collection_reader(&mut collection, &member_id) {
+            use base64::{Engine as _, engine::general_purpose};
+            let encoded = general_purpose::STANDARD.encode(serde_json::to_string(&collection)?);
+            self.execute_bw_command(&[
+                "edit",
+                "org-collection",
+                &collection_id,
+                &encoded,
+                "--organizationid",
+                &organization,
+            ])?;
+        }
+        Ok(policy)
+    }
+
     /// Retrieves a secret from Bitwarden.
     ///
     /// Searches for an item with the name formatted according to the folder_prefix
@@ -2176,22 +3002,179 @@ impl Provider for BitwardenProvider {
     /// - Item retrieval failures
     /// - JSON parsing errors
     fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
-        eprintln!(
-            "DEBUG: BitwardenProvider.get() called with key='{}', service={:?}",
-            key, self.config.service
-        );
+        Ok(self
+            .get_with_metadata(project, key, profile)?
+            .map(|secret| secret.value))
+    }
+
+    /// Retrieves a secret with the revision date of its item or secret.
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
         match self.config.service {
             BitwardenService::PasswordManager => {
-                eprintln!("DEBUG: Calling get_from_password_manager");
                 self.get_from_password_manager(project, key, profile)
             }
             BitwardenService::SecretsManager => {
-                eprintln!("DEBUG: Calling get_from_secrets_manager");
                 self.get_from_secrets_manager(project, key, profile)
             }
         }
     }
 
+    /// Reads fields of a Password Manager item by its name, in one search.
+    ///
+    /// Secrets Manager secrets have a single value, which is read as a JSON
+    /// object instead.
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        if self.config.service == BitwardenService::SecretsManager {
+            return json_fields(item, self.get(project, item, profile)?, fields);
+        }
+
+        self.ensure_unlocked(project, profile)?;
+        if self.config.sync == BitwardenSync::Always {
+            self.sync_if_due()?;
+        }
+
+        // The search also matches other text, so only use an item with the name
+        let items = self.search_items(item)?;
+        let legacy_item_name = self.format_item_name(project, item, profile);
+        let Some(found) = Self::find_item(&items, item, &legacy_item_name) else {
+            return Ok(HashMap::new());
+        };
+        Ok(fields
+            .iter()
+            .filter_map(|name| {
+                let value = Self::item_field(found, name)?;
+                Some((name.to_string(), SecretString::new(value.into())))
+            })
+            .collect())
+    }
+
+    /// Lists the Password Manager items named after the key, with the
+    /// folders they are in, or the Secrets Manager secrets.
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        if self.config.service == BitwardenService::SecretsManager {
+            let mut args = vec!["secret", "list"];
+            if let Some(project_id) = &self.config.project_id {
+                args.push(project_id);
+            }
+            let secrets: Vec<BitwardenSecret> =
+                serde_json::from_str(&self.execute_bws_command(&args)?)?;
+            let secret_name = format!("{}_{}", project, key);
+            let normalized = normalize_key(key);
+            return Ok(secrets
+                .into_iter()
+                .filter(|secret| {
+                    secret.key == secret_name || normalize_key(&secret.key) == normalized
+                })
+                .map(|secret| ItemMatch {
+                    id: secret.id,
+                    name: secret.key,
+                    location: Some(secret.project_id),
+                })
+                .collect());
+        }
+
+        self.ensure_unlocked(project, profile)?;
+        let legacy_item_name = self.format_item_name(project, key, profile);
+        let normalized = normalize_key(key);
+        let items: Vec<BitwardenItem> = self
+            .search_items(key)?
+            .into_iter()
+            .filter(|item| {
+                item.name == legacy_item_name
+                    || item.name == key
+                    || normalize_key(&item.name) == normalized
+            })
+            .collect();
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let folders: Vec<BitwardenFolder> =
+            serde_json::from_str(&self.execute_bw_command(&["list", "folders"])?)?;
+        Ok(items
+            .into_iter()
+            .map(|item| ItemMatch {
+                location: item.folder_id.as_ref().and_then(|id| {
+                    folders
+                        .iter()
+                        .find(|folder| folder.id.as_deref() == Some(id))
+                        .map(|folder| folder.name.clone())
+                }),
+                id: item.id,
+                name: item.name,
+            })
+            .collect())
+    }
+
+    /// Reads the secret from the item with `bw get item <id>`, or the
+    /// Secrets Manager secret with `bws secret get <id>`, without searching
+    /// the vault.
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        if self.config.service == BitwardenService::SecretsManager {
+            let secret: BitwardenSecret =
+                serde_json::from_str(&self.execute_bws_command(&["secret", "get", id])?)?;
+            return Ok(Some(SecretValue {
+                value: SecretString::new(secret.value.into()),
+                metadata: SecretMetadata {
+                    revised: Some(secret.revision_date),
+                    ..SecretMetadata::default()
+                },
+            }));
+        }
+
+        self.ensure_unlocked(project, profile)?;
+        let item: BitwardenItem = serde_json::from_value(self.get_item_as_template(id)?)?;
+        let metadata = SecretMetadata {
+            revised: item.revision_date.clone(),
+            ..SecretMetadata::default()
+        };
+        Ok(self
+            .extract_value_from_item(&item, key)?
+            .map(|value| SecretValue { value, metadata }))
+    }
+
+    /// Updates the item with the given ID, leaving items with the same name
+    /// untouched.
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        if self.config.service == BitwardenService::SecretsManager {
+            self.execute_bws_command(&["secret", "edit", id, "--value", value.expose_secret()])?;
+            return Ok(());
+        }
+
+        self.ensure_unlocked(project, profile)?;
+        let item_json = self.get_item_as_template(id)?;
+        let item: BitwardenItem = serde_json::from_value(item_json.clone())?;
+        let target_field = std::env::var("BITWARDEN_DEFAULT_FIELD")
+            .ok()
+            .or_else(|| self.config.default_field.clone())
+            .unwrap_or_else(|| item.item_type.default_field_for_hint(key));
+        self.update_item_json(&item, item_json, &target_field, value.expose_secret())
+    }
+
     /// Stores or updates a secret in Bitwarden.
     ///
     /// If an item with the same name exists, it updates the "value" field.
@@ -2224,6 +3207,135 @@ impl Provider for BitwardenProvider {
             }
         }
     }
+
+    /// Updates a secret only if its item is still at the expected revision
+    /// date.
+    ///
+    /// `bw edit` sends the revision date of the local copy of the item as the
+    /// last known revision, and the server refuses the edit if the item was
+    /// changed since. Checking the local copy against `expected` first makes
+    /// the write conditional as a whole. Secrets Manager has no such check.
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        if self.config.service == BitwardenService::SecretsManager {
+            return Err(super::operation_unsupported(
+                "Bitwarden Secrets Manager",
+                "compare-and-set writes",
+            ));
+        }
+
+        self.ensure_unlocked(project, profile)?;
+        let items = self.search_items(key)?;
+        let legacy_item_name = self.format_item_name(project, key, profile);
+        let Some(item) = Self::find_item(&items, key, &legacy_item_name) else {
+            return Err(SecretSpecError::Conflict(format!(
+                "'{}' was deleted since revision {}",
+                key, expected
+            )));
+        };
+        match item.revision_date.as_deref() {
+            Some(revision) if revision == expected => {}
+            Some(revision) => {
+                return Err(SecretSpecError::Conflict(format!(
+                    "'{}' was changed since revision {}; it is now at revision {}",
+                    key, expected, revision
+                )));
+            }
+            None => {
+                return Err(SecretSpecError::ProviderOperationFailed(format!(
+                    "Bitwarden reports no revision date for '{}'",
+                    key
+                )));
+            }
+        }
+
+        self.update_existing_item(item, key, value.expose_secret())
+            .map_err(|e| match e {
+                // The server refused the edit because the item changed after
+                // the local copy was synced
+                SecretSpecError::ProviderOperationFailed(message)
+                    if message.contains("out of date") =>
+                {
+                    SecretSpecError::Conflict(format!(
+                        "'{}' was changed since revision {}",
+                        key, expected
+                    ))
+                }
+                e => e,
+            })
+    }
+
+    /// Moves the item of a secret to the Bitwarden trash, from which it can
+    /// be restored for 30 days.
+    ///
+    /// Secrets Manager has no trash, so its secrets are deleted for good.
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        match self.config.service {
+            BitwardenService::PasswordManager => {
+                self.delete_from_password_manager(project, key, profile, false)
+            }
+            BitwardenService::SecretsManager => self.delete_from_secrets_manager(project, key),
+        }
+    }
+
+    fn allows_delete(&self) -> bool {
+        true
+    }
+
+    /// Deletes the item of a secret for good, also from the trash.
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        match self.config.service {
+            BitwardenService::PasswordManager => {
+                self.delete_from_password_manager(project, key, profile, true)
+            }
+            BitwardenService::SecretsManager => self.delete_from_secrets_manager(project, key),
+        }
+    }
+
+    /// Restores the item of a secret from the Bitwarden trash.
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        if self.config.service == BitwardenService::SecretsManager {
+            return Err(operation_unsupported(
+                self.name(),
+                "restoring deleted secrets",
+            ));
+        }
+        self.ensure_unlocked(project, profile)?;
+
+        let trash = self.search_trash(key)?;
+        let legacy_item_name = self.format_item_name(project, key, profile);
+        let item = Self::find_item(&trash, key, &legacy_item_name).ok_or_else(|| {
+            SecretSpecError::NotFound(format!("No item named '{}' in the Bitwarden trash", key))
+        })?;
+        self.execute_bw_command(&["restore", "item", &item.id])?;
+        Ok(())
+    }
+
+    fn allows_restore(&self) -> bool {
+        self.config.service == BitwardenService::PasswordManager
+    }
+}
+
+impl Drop for BitwardenProvider {
+    /// Switches the user's CLI back to the server it used before.
+    fn drop(&mut self) {
+        let setup = self.server_setup.get_mut().unwrap().take();
+        if let Some(previous) = setup.and_then(|setup| setup.previous_server) {
+            let invocation = Invocation::new("bw").args(["config", "server", &previous]);
+            if let Err(e) = self.run_bw(&invocation) {
+                warning::warn(format!(
+                    "Failed to restore the Bitwarden CLI server to {}: {}",
+                    previous, e
+                ));
+            }
+        }
+    }
 }
 
 impl Default for BitwardenProvider {
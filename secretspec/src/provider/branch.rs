@@ -0,0 +1,109 @@
+//! Per-branch storage with the `{branch}` placeholder.
+//!
+//! Provider URIs may contain `{branch}`, which is replaced with the current
+//! git branch before the URI is parsed, so that preview and feature
+//! deployments each get their own set of secrets:
+//!
+//! ```text
+//! bitwarden://?folder=secretspec/{project}/{branch}
+//! dotenv:.env.{branch}
+//! ```
+//!
+//! The branch is taken from `SECRETSPEC_BRANCH`, then from git, then from
+//! the branch variables CI systems set on detached checkouts. Characters
+//! other than letters, digits, `.`, `_` and `-` become `-`, so
+//! `feature/login` is stored as `feature-login` and never changes the
+//! structure of the URI.
+
+use crate::{Result, SecretSpecError};
+use std::borrow::Cow;
+use std::env;
+use std::process::Command;
+
+/// The placeholder replaced with the current branch.
+const PLACEHOLDER: &str = "{branch}";
+
+/// The placeholder as it appears after URL parsing percent-encoded it.
+const ENCODED_PLACEHOLDER: &str = "%7Bbranch%7D";
+
+/// Variables CI systems set to the branch being built, in order of
+/// preference.
+const CI_VARIABLES: [&str; 3] = ["GITHUB_HEAD_REF", "GITHUB_REF_NAME", "CI_COMMIT_REF_NAME"];
+
+/// Replaces `{branch}` in a provider specification with the current branch.
+///
+/// # Errors
+///
+/// Returns an error if the specification uses `{branch}` and the current
+/// branch can't be determined
+pub(crate) fn expand(spec: &str) -> Result<Cow<'_, str>> {
+    if !spec.contains(PLACEHOLDER) && !spec.contains(ENCODED_PLACEHOLDER) {
+        return Ok(Cow::Borrowed(spec));
+    }
+    let branch = sanitize(&current()?);
+    Ok(Cow::Owned(
+        spec.replace(PLACEHOLDER, &branch)
+            .replace(ENCODED_PLACEHOLDER, &branch),
+    ))
+}
+
+/// Returns the current branch.
+fn current() -> Result<String> {
+    let from_env = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    from_env("SECRETSPEC_BRANCH")
+        .or_else(git_branch)
+        .or_else(|| CI_VARIABLES.iter().find_map(|name| from_env(name)))
+        .ok_or_else(|| {
+            SecretSpecError::ProviderOperationFailed(
+                "The provider URI uses {branch}, but the current git branch is unknown; set SECRETSPEC_BRANCH"
+                    .to_string(),
+            )
+        })
+}
+
+/// Returns the branch checked out in the current directory, or `None` on a
+/// detached HEAD or outside a repository.
+fn git_branch() -> Option<String> {
+    let output = Command::new("git")
+        .args(["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let branch = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!branch.is_empty()).then_some(branch)
+}
+
+/// Makes a branch name safe to use anywhere in a URI.
+fn sanitize(branch: &str) -> String {
+    branch
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(sanitize("feature/login"), "feature-login");
+        assert_eq!(sanitize("release-1.2_rc"), "release-1.2_rc");
+        assert!(matches!(expand("dotenv:.env").unwrap(), Cow::Borrowed(_)));
+
+        // Expansion depends on the checkout the tests run in, but replaces
+        // every placeholder when it succeeds
+        if let Ok(expanded) = expand("lastpass:///secretspec/%7Bbranch%7D?folder={branch}") {
+            assert!(!expanded.contains(PLACEHOLDER));
+            assert!(!expanded.contains(ENCODED_PLACEHOLDER));
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Assembling a provider from middleware layers.
+//!
+//! Cross-cutting concerns like caching, retries, rate limiting, client-side
+//! encryption and auditing are each implemented by a provider wrapper. A
+//! [`ProviderBuilder`] stacks them around a base provider, so programs using
+//! the library can assemble the stack they need:
+//!
+//! ```no_run
+//! use secretspec::{ProviderBuilder, Secrets};
+//! use std::time::Duration;
+//!
+//! let provider = ProviderBuilder::from_spec("onepassword://Production")?
+//!     .retry(3)
+//!     .rate_limit(10.0)
+//!     .cache(Duration::from_secs(60))
+//!     .audit(|event| eprintln!("{} {:?}", event.operation, event.target))
+//!     .build();
+//!
+//! let mut spec = Secrets::load()?;
+//! spec.set_provider_instance(provider);
+//! spec.check()?;
+//! # Ok::<(), secretspec::SecretSpecError>(())
+//! ```
+//!
+//! Layers are applied in the order they are added: the first wraps the base
+//! provider and the last sees each operation first. In the example above,
+//! cache hits are audited but neither rate limited nor retried.
+
+use super::Provider;
+use super::audited::{AuditEvent, AuditSink, AuditedProvider};
+use super::cached::CachedProvider;
+use super::chunked::ChunkedProvider;
+use super::encrypted::{self, EncryptedProvider};
+use super::env_override::EnvOverrideProvider;
+use super::retrying::RetryingProvider;
+use super::throttled::ThrottledProvider;
+use crate::Result;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Middleware that wraps a provider in another.
+///
+/// Any `Fn(Box<dyn Provider>) -> Box<dyn Provider>` is a layer, so custom
+/// wrappers can be added with [`ProviderBuilder::layer`].
+pub trait Layer {
+    /// Wraps the provider, or returns it unchanged.
+    fn layer(&self, inner: Box<dyn Provider>) -> Box<dyn Provider>;
+}
+
+impl<F> Layer for F
+where
+    F: Fn(Box<dyn Provider>) -> Box<dyn Provider>,
+{
+    fn layer(&self, inner: Box<dyn Provider>) -> Box<dyn Provider> {
+        self(inner)
+    }
+}
+
+/// Stacks middleware layers around a base provider.
+pub struct ProviderBuilder {
+    /// The base provider with every layer added so far
+    provider: Box<dyn Provider>,
+}
+
+impl ProviderBuilder {
+    /// Starts a stack from a base provider.
+    pub fn new(base: Box<dyn Provider>) -> Self {
+        Self { provider: base }
+    }
+
+    /// Starts a stack from a provider specification, such as `keyring` or
+    /// `onepassword://Production`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the specification doesn't name a provider or its
+    /// configuration is invalid
+    pub fn from_spec(spec: &str) -> Result<Self> {
+        Ok(Self::new(Box::<dyn Provider>::try_from(spec)?))
+    }
+
+    /// Adds a layer around the stack.
+    pub fn layer(self, layer: impl Layer) -> Self {
+        Self::new(layer.layer(self.provider))
+    }
+
+    /// Caches lookups for `ttl`.
+    pub fn cache(self, ttl: Duration) -> Self {
+        self.layer(move |inner: Box<dyn Provider>| {
+            Box::new(CachedProvider::new(inner, ttl)) as Box<dyn Provider>
+        })
+    }
+
+    /// Retries operations that fail with a transient error, making up to
+    /// `attempts` attempts.
+    pub fn retry(self, attempts: u32) -> Self {
+        self.layer(move |inner: Box<dyn Provider>| {
+            Box::new(RetryingProvider::new(inner, attempts)) as Box<dyn Provider>
+        })
+    }
+
+    /// Limits operations to `rate` per second, shared by every instance of
+    /// the provider in the process.
+    pub fn rate_limit(self, rate: f64) -> Self {
+        self.layer(move |inner: Box<dyn Provider>| ThrottledProvider::wrap(inner, Some(rate)))
+    }
+
+    /// Splits values larger than the provider's size limit across several
+    /// entries.
+    pub fn chunk(self) -> Self {
+        self.layer(ChunkedProvider::wrap)
+    }
+
+    /// Encrypts values to `recipients` with age before they reach the
+    /// stack, decrypting them with the `identity` file or
+    /// `SECRETSPEC_AGE_IDENTITY`, like the `enc+` URI prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no recipients or one isn't an age or
+    /// SSH public key
+    pub fn encrypt(self, recipients: Vec<String>, identity: Option<String>) -> Result<Self> {
+        encrypted::check_recipients(self.provider.name(), &recipients)?;
+        Ok(self.layer(move |inner: Box<dyn Provider>| {
+            Box::new(EncryptedProvider::new(
+                inner,
+                recipients.clone(),
+                identity.clone(),
+            )) as Box<dyn Provider>
+        }))
+    }
+
+    /// Reports every operation to `sink`.
+    pub fn audit(self, sink: impl Fn(&AuditEvent<'_>) + Send + Sync + 'static) -> Self {
+        let sink: AuditSink = Arc::new(sink);
+        self.layer(move |inner: Box<dyn Provider>| {
+            Box::new(AuditedProvider::new(inner, sink.clone())) as Box<dyn Provider>
+        })
+    }
+
+    /// Lets secrets set in the process environment win, like the
+    /// `override+` URI prefix.
+    pub fn env_override(self) -> Self {
+        self.layer(|inner: Box<dyn Provider>| {
+            Box::new(EnvOverrideProvider::new(inner)) as Box<dyn Provider>
+        })
+    }
+
+    /// Returns the assembled provider.
+    pub fn build(self) -> Box<dyn Provider> {
+        self.provider
+    }
+}
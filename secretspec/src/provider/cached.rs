@@ -0,0 +1,156 @@
+//! In-memory caching of lookups.
+//!
+//! The [`CachedProvider`] wrapper keeps the result of each lookup for a fixed
+//! time, so services that read the same secrets repeatedly only reach the
+//! backend once per interval. Missing secrets are cached too. Writes and
+//! deletes made through the wrapper drop the cached entry; changes made
+//! elsewhere show up once the entry expires.
+
+use super::macros::forward_provider;
+use super::{Provider, SecretValue};
+use crate::Result;
+use crate::telemetry;
+use secrecy::SecretString;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Project, profile and key of a cached lookup.
+type CacheKey = (String, String, String);
+
+/// Provider wrapper that caches lookups for a fixed time.
+pub struct CachedProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+    /// How long a lookup is cached
+    ttl: Duration,
+    /// Lookup results and when they were made
+    entries: Mutex<HashMap<CacheKey, (Instant, Option<SecretValue>)>>,
+}
+
+impl CachedProvider {
+    /// Creates a new CachedProvider that keeps lookups for `ttl`.
+    pub fn new(inner: Box<dyn Provider>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the key a lookup is cached under.
+    fn cache_key(project: &str, key: &str, profile: &str) -> CacheKey {
+        (project.to_string(), profile.to_string(), key.to_string())
+    }
+
+    /// Returns the attributes of the cache metrics.
+    fn metric_attributes(&self) -> telemetry::Attributes {
+        vec![("secretspec.provider", self.inner.name().to_string())]
+    }
+
+    /// Drops the cached lookup of a secret.
+    fn invalidate(&self, project: &str, key: &str, profile: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&Self::cache_key(project, key, profile));
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for CachedProvider {
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        Ok(self
+            .get_with_metadata(project, key, profile)?
+            .map(|secret| secret.value))
+    }
+
+    /// Retrieves a secret from the cache, or from the wrapped provider if it
+    /// isn't cached or has expired.
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let cache_key = Self::cache_key(project, key, profile);
+        if let Some((fetched, value)) = self.entries.lock().unwrap().get(&cache_key)
+            && fetched.elapsed() < self.ttl
+        {
+            telemetry::count("secretspec.cache.hits", self.metric_attributes(), 1);
+            return Ok(value.clone());
+        }
+        telemetry::count("secretspec.cache.misses", self.metric_attributes(), 1);
+
+        // Errors aren't cached, so the next lookup tries again
+        let value = self.inner.get_with_metadata(project, key, profile)?;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(cache_key, (Instant::now(), value.clone()));
+        Ok(value)
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.invalidate(project, key, profile);
+        self.inner.set(project, key, value, profile)
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        for (key, _) in values {
+            self.invalidate(project, key, profile);
+        }
+        self.inner.set_many(project, values, profile)
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        self.invalidate(project, key, profile);
+        self.inner
+            .compare_and_set(project, key, value, profile, expected)
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.invalidate(project, key, profile);
+        self.inner.delete(project, key, profile)
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.invalidate(project, key, profile);
+        self.inner.purge(project, key, profile)
+    }
+
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.invalidate(project, key, profile);
+        self.inner.restore(project, key, profile)
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        self.invalidate(project, key, profile);
+        self.inner.set_by_id(project, key, id, value, profile)
+    }
+
+    forward_provider!(inner =>
+        allows_set, check_writable, allows_delete, allows_restore, history, list_keys, allows_list,
+        get_fields, find_items, get_by_id, browse, max_value_size, works_in_sandbox, doctor,
+        list_access, access_policy, grant, leases, renew_lease, revoke_lease, name,
+    );
+}
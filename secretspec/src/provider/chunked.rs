@@ -0,0 +1,474 @@
+//! Transparent chunking for providers with value size limits.
+//!
+//! Some backends cap how large a single stored value may be (for example,
+//! Windows Credential Manager entries or Bitwarden custom fields). The
+//! [`ChunkedProvider`] wrapper splits oversized values across several entries
+//! and stores a small manifest under the original key so that reads can
+//! reassemble them:
+//!
+//! ```text
+//! KEY        -> secretspec:chunked:v1:3:7421
+//! KEY.part1  -> first chunk
+//! KEY.part2  -> second chunk
+//! KEY.part3  -> third chunk
+//! ```
+//!
+//! Values that fit within the limit are stored unchanged, so existing secrets
+//! remain readable and providers without a limit are never wrapped.
+
+use super::macros::forward_provider;
+use super::{Provider, SecretValue};
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+
+/// Prefix identifying a chunk manifest stored under the original key.
+const MANIFEST_PREFIX: &str = "secretspec:chunked:v1:";
+
+/// Provider wrapper that splits values larger than `max_size` into parts.
+///
+/// The wrapper is applied automatically to providers that report a limit via
+/// [`Provider::max_value_size`].
+pub struct ChunkedProvider {
+    /// The wrapped provider that stores the individual chunks
+    inner: Box<dyn Provider>,
+    /// Maximum number of bytes stored in a single entry
+    max_size: usize,
+}
+
+impl ChunkedProvider {
+    /// Creates a new ChunkedProvider around the given provider.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The provider to store chunks in
+    /// * `max_size` - Maximum number of bytes per stored entry
+    pub fn new(inner: Box<dyn Provider>, max_size: usize) -> Self {
+        Self {
+            inner,
+            max_size: max_size.max(MANIFEST_PREFIX.len() + 32),
+        }
+    }
+
+    /// Wraps the provider if it reports a value size limit.
+    ///
+    /// Providers without a limit are returned unchanged.
+    pub fn wrap(inner: Box<dyn Provider>) -> Box<dyn Provider> {
+        match inner.max_value_size() {
+            Some(max_size) => Box::new(Self::new(inner, max_size)),
+            None => inner,
+        }
+    }
+
+    /// Returns the key under which the given chunk is stored (1-based).
+    fn part_key(key: &str, index: usize) -> String {
+        format!("{}.part{}", key, index)
+    }
+
+    /// Returns whether a key names a chunk rather than a secret.
+    fn is_part_key(key: &str) -> bool {
+        key.rsplit_once(".part").is_some_and(|(_, index)| {
+            !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit())
+        })
+    }
+
+    /// Splits a value into chunks of at most `max_size` bytes.
+    ///
+    /// Chunks are always split on UTF-8 character boundaries.
+    fn split(&self, value: &str) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+
+        for c in value.chars() {
+            if current.len() + c.len_utf8() > self.max_size {
+                chunks.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+
+        chunks
+    }
+
+    /// Returns whether a value has to be split into chunks.
+    ///
+    /// Values that look like a manifest are chunked too, so they aren't
+    /// mistaken for one when read.
+    fn needs_chunks(&self, value: &str) -> bool {
+        value.len() > self.max_size || value.starts_with(MANIFEST_PREFIX)
+    }
+
+    /// Writes a value, with `store` writing the entry under the key itself.
+    ///
+    /// Chunks are written before the manifest so that a partially completed
+    /// write never leaves a manifest pointing at missing chunks. Chunks of
+    /// the `previous` value that the new one doesn't use are removed last,
+    /// so no stale secret material is left behind.
+    fn write(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        previous: Option<&SecretString>,
+        store: impl FnOnce(&SecretString) -> Result<()>,
+    ) -> Result<()> {
+        let previous_count = previous
+            .and_then(|stored| Self::parse_manifest(stored.expose_secret()))
+            .map_or(0, |(count, _)| count);
+
+        let raw = value.expose_secret();
+        let count = if self.needs_chunks(raw) {
+            let chunks = self.split(raw);
+            for (index, chunk) in chunks.iter().enumerate() {
+                self.inner.set(
+                    project,
+                    &Self::part_key(key, index + 1),
+                    &SecretString::new(chunk.clone().into()),
+                    profile,
+                )?;
+            }
+            let manifest = format!("{}{}:{}", MANIFEST_PREFIX, chunks.len(), raw.len());
+            store(&SecretString::new(manifest.into()))?;
+            chunks.len()
+        } else {
+            store(value)?;
+            0
+        };
+
+        for index in count + 1..=previous_count {
+            self.remove_part(project, &Self::part_key(key, index), profile)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a chunk that is no longer used, or empties it if the wrapped
+    /// provider can't delete.
+    fn remove_part(&self, project: &str, part_key: &str, profile: &str) -> Result<()> {
+        if self.inner.allows_delete() {
+            self.inner.delete(project, part_key, profile)
+        } else {
+            self.inner
+                .set(project, part_key, &SecretString::from(""), profile)
+        }
+    }
+
+    /// Parses a manifest value into `(part_count, total_length)`.
+    fn parse_manifest(value: &str) -> Option<(usize, usize)> {
+        let rest = value.strip_prefix(MANIFEST_PREFIX)?;
+        let (count, length) = rest.split_once(':')?;
+        Some((count.parse().ok()?, length.parse().ok()?))
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for ChunkedProvider {
+    /// Retrieves a secret, reassembling it from chunks if a manifest is found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a manifest references a missing chunk or the
+    /// reassembled value does not match the recorded length.
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        Ok(self
+            .get_with_metadata(project, key, profile)?
+            .map(|secret| secret.value))
+    }
+
+    /// Retrieves a secret with the metadata of its manifest or only entry.
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let Some(stored) = self.inner.get_with_metadata(project, key, profile)? else {
+            return Ok(None);
+        };
+
+        let Some((count, length)) = Self::parse_manifest(stored.value.expose_secret()) else {
+            return Ok(Some(stored));
+        };
+
+        let mut assembled = String::with_capacity(length);
+        for index in 1..=count {
+            let part_key = Self::part_key(key, index);
+            let part = self
+                .inner
+                .get(project, &part_key, profile)?
+                .ok_or_else(|| {
+                    SecretSpecError::ProviderOperationFailed(format!(
+                        "Secret '{}' is stored in {} chunks but chunk '{}' is missing",
+                        key, count, part_key
+                    ))
+                })?;
+            assembled.push_str(part.expose_secret());
+        }
+
+        if assembled.len() != length {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Secret '{}' is corrupted: expected {} bytes but reassembled {}",
+                key,
+                length,
+                assembled.len()
+            )));
+        }
+
+        Ok(Some(SecretValue {
+            value: SecretString::new(assembled.into()),
+            metadata: stored.metadata,
+        }))
+    }
+
+    /// Stores a secret, splitting it into chunks if it exceeds the size limit.
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        let previous = self.inner.get(project, key, profile)?;
+        self.write(project, key, value, profile, previous.as_ref(), |stored| {
+            self.inner.set(project, key, stored, profile)
+        })
+    }
+
+    /// Stores a secret only if it is still at the expected revision, which
+    /// is checked on the entry under the key itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for values that have to be split into chunks, since
+    /// the chunks would be overwritten before the revision is checked
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        if self.needs_chunks(value.expose_secret()) {
+            return Err(super::operation_unsupported(
+                self.name(),
+                "compare-and-set writes of values split into chunks",
+            ));
+        }
+        let previous = self.inner.get(project, key, profile)?;
+        self.write(project, key, value, profile, previous.as_ref(), |stored| {
+            self.inner
+                .compare_and_set(project, key, stored, profile, expected)
+        })
+    }
+
+    /// Deletes a secret together with any chunks it was split into.
+    ///
+    /// The manifest is removed last so that an interrupted delete can be
+    /// retried.
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let manifest = self.inner.get(project, key, profile)?;
+        if let Some((count, _)) = manifest
+            .as_ref()
+            .and_then(|value| Self::parse_manifest(value.expose_secret()))
+        {
+            for index in 1..=count {
+                self.inner
+                    .delete(project, &Self::part_key(key, index), profile)?;
+            }
+        }
+        self.inner.delete(project, key, profile)
+    }
+
+    /// Purges a secret together with any chunks it was split into.
+    ///
+    /// The chunks are only found while the manifest can be read, so a secret
+    /// already in the trash is purged without its chunks.
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let manifest = self.inner.get(project, key, profile)?;
+        if let Some((count, _)) = manifest
+            .as_ref()
+            .and_then(|value| Self::parse_manifest(value.expose_secret()))
+        {
+            for index in 1..=count {
+                self.inner
+                    .purge(project, &Self::part_key(key, index), profile)?;
+            }
+        }
+        self.inner.purge(project, key, profile)
+    }
+
+    /// Restores a secret together with any chunks it was split into.
+    ///
+    /// The manifest is restored first, since it says how many chunks there
+    /// are.
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.inner.restore(project, key, profile)?;
+        let manifest = self.inner.get(project, key, profile)?;
+        if let Some((count, _)) = manifest
+            .as_ref()
+            .and_then(|value| Self::parse_manifest(value.expose_secret()))
+        {
+            for index in 1..=count {
+                self.inner
+                    .restore(project, &Self::part_key(key, index), profile)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Lists stored keys, leaving out the entries that hold chunks.
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let keys = self.inner.list_keys(project, profile)?;
+        Ok(keys
+            .into_iter()
+            .filter(|key| !Self::is_part_key(key))
+            .collect())
+    }
+
+    /// Reads item fields from the wrapped provider; they are never chunked.
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        self.inner.get_fields(project, item, fields, profile)
+    }
+
+    /// Stores a secret in the item with the given ID, splitting it into
+    /// chunks like [`set`](Provider::set). The chunks are stored by name.
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        let previous = self
+            .inner
+            .get_by_id(project, key, id, profile)?
+            .map(|stored| stored.value);
+        self.write(project, key, value, profile, previous.as_ref(), |stored| {
+            self.inner.set_by_id(project, key, id, stored, profile)
+        })
+    }
+
+    fn max_value_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Stores each value with [`set`](Provider::set), so large values are
+    /// chunked instead of handed whole to the wrapped provider's batch write.
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        for (key, value) in values {
+            self.set(project, key, value, profile)?;
+        }
+        Ok(())
+    }
+
+    forward_provider!(inner =>
+        allows_set, check_writable, allows_delete, allows_restore, history, allows_list, find_items,
+        get_by_id, browse, works_in_sandbox, doctor, list_access, access_policy, grant, leases,
+        renew_lease, revoke_lease, name,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::tests::MockProvider;
+
+    #[test]
+    fn test_small_values_are_stored_unchanged() {
+        let provider = ChunkedProvider::new(Box::new(MockProvider::new()), 64);
+        let value = SecretString::new("short".to_string().into());
+        provider.set("proj", "KEY", &value, "default").unwrap();
+
+        let stored = provider.inner.get("proj", "KEY", "default").unwrap();
+        assert_eq!(stored.unwrap().expose_secret(), "short");
+        assert!(
+            provider
+                .inner
+                .get("proj", "KEY.part1", "default")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_large_values_roundtrip_through_chunks() {
+        let provider = ChunkedProvider::new(Box::new(MockProvider::new()), 64);
+        let large = "ünïcödé-kubeconfig-".repeat(40);
+        let value = SecretString::new(large.clone().into());
+        provider
+            .set("proj", "KUBECONFIG", &value, "default")
+            .unwrap();
+
+        let manifest = provider.inner.get("proj", "KUBECONFIG", "default").unwrap();
+        assert!(
+            manifest
+                .unwrap()
+                .expose_secret()
+                .starts_with(MANIFEST_PREFIX)
+        );
+
+        let part = provider
+            .inner
+            .get("proj", "KUBECONFIG.part1", "default")
+            .unwrap();
+        assert!(part.unwrap().expose_secret().len() <= 64);
+
+        let result = provider.get("proj", "KUBECONFIG", "default").unwrap();
+        assert_eq!(result.unwrap().expose_secret(), large);
+    }
+
+    #[test]
+    fn test_missing_chunk_is_reported() {
+        let provider = ChunkedProvider::new(Box::new(MockProvider::new()), 64);
+        let manifest = SecretString::new(format!("{}2:100", MANIFEST_PREFIX).into());
+        provider
+            .inner
+            .set("proj", "KEY", &manifest, "default")
+            .unwrap();
+
+        let err = provider.get("proj", "KEY", "default").unwrap_err();
+        assert!(err.to_string().contains("KEY.part1"));
+    }
+
+    #[test]
+    fn test_shrinking_values_remove_stale_chunks() {
+        let provider = ChunkedProvider::new(Box::new(MockProvider::new()), 64);
+        let part = |index: usize| {
+            provider
+                .inner
+                .get("proj", &ChunkedProvider::part_key("KEY", index), "default")
+                .unwrap()
+        };
+        let set = |value: String| {
+            provider
+                .set("proj", "KEY", &SecretString::from(value), "default")
+                .unwrap()
+        };
+
+        set("a".repeat(64 * 3));
+        assert!(part(3).is_some());
+
+        // Fewer chunks: the ones past the new count are deleted
+        set("b".repeat(64 * 2));
+        assert!(part(2).is_some());
+        assert!(part(3).is_none());
+        let value = provider.get("proj", "KEY", "default").unwrap().unwrap();
+        assert_eq!(value.expose_secret(), "b".repeat(64 * 2));
+
+        // No chunks at all: the whole series is deleted
+        set("short".to_string());
+        assert!(part(1).is_none());
+        assert!(part(2).is_none());
+        let value = provider.get("proj", "KEY", "default").unwrap().unwrap();
+        assert_eq!(value.expose_secret(), "short");
+    }
+}
@@ -0,0 +1,375 @@
+//! Subprocess execution for CLI-backed providers.
+//!
+//! Providers that wrap a password manager CLI (`op`, `lpass`, `bw`, `bws`)
+//! run it through a [`CommandRunner`] instead of calling [`std::process`]
+//! directly. Regular use goes through [`SystemRunner`]; tests inject a
+//! `FakeRunner` that records every invocation and replays canned output, so
+//! provider logic can be tested without the CLIs installed.
+
+use crate::{Result, SecretSpecError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use zeroize::Zeroizing;
+
+/// A command line to run.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invocation {
+    /// The program to run, looked up on `PATH`
+    pub program: String,
+    /// Arguments passed to the program
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Environment variables set in addition to the inherited environment
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<(String, String)>,
+    /// Data written to the program's standard input
+    ///
+    /// Never serialized, since it usually holds a secret value.
+    #[serde(skip)]
+    pub stdin: Option<Vec<u8>>,
+}
+
+impl Invocation {
+    /// Creates an invocation of a program without arguments.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Appends arguments.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_string()));
+        self
+    }
+
+    /// Sets an environment variable for the program.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Writes data to the program's standard input.
+    pub fn stdin(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.stdin = Some(data.into());
+        self
+    }
+}
+
+impl fmt::Debug for Invocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Invocation")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("env", &self.env)
+            .field(
+                "stdin",
+                &self
+                    .stdin
+                    .as_ref()
+                    .map(|data| format!("[REDACTED {} bytes]", data.len())),
+            )
+            .finish()
+    }
+}
+
+/// The result of a finished command.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandOutput {
+    /// Whether the program exited successfully
+    pub success: bool,
+    /// Everything the program wrote to standard output, zeroed when dropped
+    /// since it may hold secret values
+    #[serde(default)]
+    pub stdout: Zeroizing<String>,
+    /// Everything the program wrote to standard error
+    #[serde(default)]
+    pub stderr: String,
+}
+
+/// Runs provider CLI commands.
+pub trait CommandRunner: Send + Sync {
+    /// Runs a command to completion and captures its output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::NotFound`] if the program is
+    /// not installed, or any other error that prevents it from running.
+    /// A program that runs but fails is not an error; see
+    /// [`CommandOutput::success`].
+    fn run(&self, invocation: &Invocation) -> io::Result<CommandOutput>;
+}
+
+/// Runs commands as real subprocesses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, invocation: &Invocation) -> io::Result<CommandOutput> {
+        let mut cmd = Command::new(&invocation.program);
+        cmd.args(&invocation.args)
+            .envs(invocation.env.iter().map(|(k, v)| (k, v)))
+            .stdin(if invocation.stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = cmd.spawn()?;
+        // Standard input is written from another thread while the output is
+        // read, or a program answering before it read everything would block
+        // on a full pipe, and so would we.
+        let output = thread::scope(|scope| {
+            let writer = match (&invocation.stdin, child.stdin.take()) {
+                (Some(data), Some(mut stdin)) => Some(scope.spawn(move || stdin.write_all(data))),
+                _ => None,
+            };
+            let output = child.wait_with_output()?;
+            if let Some(writer) = writer {
+                match writer.join() {
+                    Ok(Ok(())) => {}
+                    // Programs may exit without reading all of their input
+                    Ok(Err(e)) if e.kind() == io::ErrorKind::BrokenPipe => {}
+                    Ok(Err(e)) => return Err(e),
+                    Err(_) => return Err(io::Error::other("stdin writer panicked")),
+                }
+            }
+            Ok::<_, io::Error>(output)
+        })?;
+
+        // Standard output may hold secret values, so the raw copy is zeroed
+        // too
+        let raw = Zeroizing::new(output.stdout);
+        let stdout = match std::str::from_utf8(&raw) {
+            Ok(stdout) => Zeroizing::new(stdout.to_string()),
+            Err(_) => Zeroizing::new(String::from_utf8_lossy(&raw).into_owned()),
+        };
+        Ok(CommandOutput {
+            success: output.status.success(),
+            stdout,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// Runs a `curl` invocation through `runner` and returns its standard output.
+///
+/// # Arguments
+///
+/// * `purpose` - What curl is needed for, completing the install hint, like
+///   `send notifications`
+/// * `failure` - Prefix of the error when curl fails, like
+///   `LastPass request failed`
+///
+/// # Errors
+///
+/// Returns [`SecretSpecError::CliMissing`] if curl is not installed, or an
+/// error with curl's message if the request fails
+pub(crate) fn curl(
+    runner: &dyn CommandRunner,
+    invocation: &Invocation,
+    purpose: &str,
+    failure: &str,
+) -> Result<Zeroizing<String>> {
+    let output = match runner.run(invocation) {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(SecretSpecError::CliMissing {
+                cli: "curl".to_string(),
+                install_hint: format!("Install curl from your package manager to {}", purpose),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !output.success {
+        return Err(SecretSpecError::ProviderOperationFailed(format!(
+            "{}: {}",
+            failure,
+            output.stderr.trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Quotes a value for a curl config file, which is how URLs and headers
+/// holding credentials are passed on standard input instead of the command
+/// line.
+pub(crate) fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+pub(crate) use fake::FakeRunner;
+
+#[cfg(test)]
+mod fake {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// A command and the output it produced, as stored in a transcript.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub(crate) struct Exchange {
+        /// The program and arguments that were run
+        pub command: Vec<String>,
+        /// The output to replay, or `None` if the program is not installed
+        pub output: Option<CommandOutput>,
+    }
+
+    /// Test runner that replays canned output and records every invocation.
+    ///
+    /// Responses are consumed in order and each must match the program and
+    /// arguments it was scripted for, where a `*` argument matches anything;
+    /// anything else fails the command with a message describing the mismatch.
+    #[derive(Debug, Default)]
+    pub(crate) struct FakeRunner {
+        /// Responses that have not been replayed yet
+        expected: Mutex<VecDeque<Exchange>>,
+        /// Every invocation seen so far
+        calls: Mutex<Vec<Invocation>>,
+    }
+
+    impl FakeRunner {
+        /// Creates a runner without any scripted responses.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Loads the responses to replay from a JSON transcript.
+        ///
+        /// The transcript is an array of `{"command": [...], "output": {...}}`
+        /// objects.
+        pub fn from_transcript(json: &str) -> Self {
+            let exchanges: Vec<Exchange> =
+                serde_json::from_str(json).expect("Invalid command transcript");
+            Self {
+                expected: Mutex::new(exchanges.into()),
+                calls: Mutex::default(),
+            }
+        }
+
+        /// Scripts a successful command that prints `stdout`.
+        pub fn ok(self, command: &[&str], stdout: &str) -> Self {
+            self.push(
+                command,
+                Some(CommandOutput {
+                    success: true,
+                    stdout: Zeroizing::new(stdout.to_string()),
+                    stderr: String::new(),
+                }),
+            )
+        }
+
+        /// Scripts a failing command that prints `stderr`.
+        pub fn fail(self, command: &[&str], stderr: &str) -> Self {
+            self.push(
+                command,
+                Some(CommandOutput {
+                    success: false,
+                    stdout: Zeroizing::default(),
+                    stderr: stderr.to_string(),
+                }),
+            )
+        }
+
+        /// Scripts a command whose program is not installed.
+        pub fn missing(self, command: &[&str]) -> Self {
+            self.push(command, None)
+        }
+
+        fn push(self, command: &[&str], output: Option<CommandOutput>) -> Self {
+            self.expected.lock().unwrap().push_back(Exchange {
+                command: command.iter().map(|s| s.to_string()).collect(),
+                output,
+            });
+            self
+        }
+
+        /// Returns every invocation seen so far.
+        pub fn calls(&self) -> Vec<Invocation> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        /// Returns whether every scripted response was replayed.
+        pub fn is_exhausted(&self) -> bool {
+            self.expected.lock().unwrap().is_empty()
+        }
+    }
+
+    /// Returns whether a command matches a scripted one.
+    fn matches(expected: &[String], actual: &[String]) -> bool {
+        expected.len() == actual.len()
+            && expected
+                .iter()
+                .zip(actual)
+                .all(|(expected, actual)| expected == "*" || expected == actual)
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn run(&self, invocation: &Invocation) -> io::Result<CommandOutput> {
+            self.calls.lock().unwrap().push(invocation.clone());
+
+            let mut command = vec![invocation.program.clone()];
+            command.extend(invocation.args.iter().cloned());
+
+            let expected = self.expected.lock().unwrap().pop_front();
+            match expected {
+                Some(exchange) if matches(&exchange.command, &command) => {
+                    exchange.output.ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::NotFound, "program not installed")
+                    })
+                }
+                Some(exchange) => Err(io::Error::other(format!(
+                    "expected `{}`, got `{}`",
+                    exchange.command.join(" "),
+                    command.join(" ")
+                ))),
+                None => Err(io::Error::other(format!(
+                    "unexpected command `{}`",
+                    command.join(" ")
+                ))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_runner_large_stdin() {
+        // Larger than any pipe buffer, echoed back before it was all read
+        let data = "x".repeat(1 << 20);
+        let output = SystemRunner
+            .run(&Invocation::new("cat").stdin(data.clone()))
+            .unwrap();
+        assert!(output.success);
+        assert_eq!(output.stdout.len(), data.len());
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(r#"https://x/"a"\b"#), r#""https://x/\"a\"\\b""#);
+    }
+
+    #[test]
+    fn test_invocation_debug_redacts_stdin() {
+        let invocation = Invocation::new("op").stdin("hunter2");
+        let debug = format!("{:?}", invocation);
+        assert!(!debug.contains("hunter2"));
+        assert!(debug.contains("REDACTED 7 bytes"));
+    }
+}
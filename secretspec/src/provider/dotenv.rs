@@ -1,11 +1,14 @@
 use super::Provider;
+use crate::doctor::Check;
 use crate::{Result, SecretSpecError};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
 use url::Url;
+use zeroize::Zeroizing;
 
 /// Configuration for the dotenv provider.
 ///
@@ -207,12 +210,82 @@ impl DotEnvProvider {
                     description: Some(format!("{} secret", key)),
                     required: true,
                     default: None,
+                    required_if: None,
+                    required_unless_set: Vec::new(),
+                    group: None,
+                    platforms: Vec::new(),
+                    transform: Vec::new(),
+                    item: None,
+                    fields: BTreeMap::new(),
+                    ttl: None,
+                    generator: None,
+                    hardware_confirm: false,
+                    bitwarden_item_id: None,
+                    kind: None,
                 },
             );
         }
 
         Ok(secrets)
     }
+
+    /// Reads every variable in the .env file.
+    ///
+    /// Returns an empty map if the file doesn't exist. Values are zeroed
+    /// when the map is dropped.
+    fn read_vars(&self) -> Result<HashMap<String, Zeroizing<String>>> {
+        let mut vars = HashMap::new();
+        if self.config.path.exists() {
+            // Use dotenvy for reading to ensure compatibility
+            let env_vars = dotenvy::from_path_iter(&self.config.path)?;
+            for item in env_vars {
+                let (k, v) = item?;
+                vars.insert(k, Zeroizing::new(v));
+            }
+        }
+        Ok(vars)
+    }
+
+    /// Returns the file that writes replace and the directory new contents
+    /// are staged in.
+    fn target(&self) -> (PathBuf, PathBuf) {
+        // Replace the file a symlink points to rather than the symlink itself
+        let target =
+            fs::canonicalize(&self.config.path).unwrap_or_else(|_| self.config.path.clone());
+        let dir = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        (target, dir)
+    }
+
+    /// Replaces the contents of the .env file with the given variables.
+    ///
+    /// The file is written to a temporary sibling and renamed into place, so
+    /// concurrent readers never see a partially written file. Existing file
+    /// permissions are kept; new files are only readable by the owner.
+    fn write_vars(&self, vars: &HashMap<String, Zeroizing<String>>) -> Result<()> {
+        // Save back to file using serde-envfile for proper escaping
+        let content = serde_envfile::to_string(vars)
+            .map(Zeroizing::new)
+            .map_err(|e| {
+                SecretSpecError::ProviderOperationFailed(format!(
+                    "Failed to serialize .env file: {}",
+                    e
+                ))
+            })?;
+
+        let (target, dir) = self.target();
+        let mut staged = tempfile::NamedTempFile::new_in(&dir)?;
+        staged.write_all(content.as_bytes())?;
+        if let Ok(metadata) = fs::metadata(&target) {
+            fs::set_permissions(staged.path(), metadata.permissions())?;
+        }
+        staged
+            .persist(&target)
+            .map_err(|e| SecretSpecError::Io(e.error))?;
+        Ok(())
+    }
 }
 
 impl Provider for DotEnvProvider {
@@ -220,6 +293,10 @@ impl Provider for DotEnvProvider {
         Self::PROVIDER_NAME
     }
 
+    fn works_in_sandbox(&self) -> bool {
+        true
+    }
+
     /// Retrieves a secret value from the .env file.
     ///
     /// Reads the .env file and returns the value for the specified key.
@@ -244,19 +321,8 @@ impl Provider for DotEnvProvider {
     /// standard .env file formats and proper handling of quoted values,
     /// multiline strings, and escape sequences.
     fn get(&self, _project: &str, key: &str, _profile: &str) -> Result<Option<SecretString>> {
-        if !self.config.path.exists() {
-            return Ok(None);
-        }
-
-        // Use dotenvy for reading to ensure compatibility
-        let mut vars = HashMap::new();
-        let env_vars = dotenvy::from_path_iter(&self.config.path)?;
-        for item in env_vars {
-            let (k, v) = item?;
-            vars.insert(k, v);
-        }
-
-        Ok(vars.get(key).map(|v| SecretString::new(v.clone().into())))
+        let vars = self.read_vars()?;
+        Ok(vars.get(key).map(|v| SecretString::from(v.as_str())))
     }
 
     /// Sets a secret value in the .env file.
@@ -282,30 +348,93 @@ impl Provider for DotEnvProvider {
     /// 2. Updates or adds the new key-value pair
     /// 3. Serializes back using serde-envfile for proper escaping
     fn set(&self, _project: &str, key: &str, value: &SecretString, _profile: &str) -> Result<()> {
-        // Load existing vars using dotenvy
-        let mut vars = HashMap::new();
-        if self.config.path.exists() {
-            let env_vars = dotenvy::from_path_iter(&self.config.path)?;
-            for item in env_vars {
-                let (k, v) = item?;
-                vars.insert(k, v);
-            }
-        }
+        let mut vars = self.read_vars()?;
+        vars.insert(
+            key.to_string(),
+            Zeroizing::new(value.expose_secret().to_string()),
+        );
+        self.write_vars(&vars)
+    }
 
-        // Update the value
-        vars.insert(key.to_string(), value.expose_secret().to_string());
+    /// Checks that new contents can be staged next to the .env file, which
+    /// every write does before replacing it.
+    fn check_writable(&self, _project: &str, _profile: &str) -> Result<()> {
+        let (_, dir) = self.target();
+        tempfile::NamedTempFile::new_in(&dir)
+            .map(drop)
+            .map_err(|e| {
+                SecretSpecError::PermissionDenied(format!(
+                    "cannot write to {}: {}",
+                    dir.display(),
+                    e
+                ))
+            })
+    }
 
-        // Save back to file using serde-envfile for proper escaping
-        let content = serde_envfile::to_string(&vars).map_err(|e| {
-            SecretSpecError::ProviderOperationFailed(format!(
-                "Failed to serialize .env file: {}",
-                e
-            ))
-        })?;
-
-        fs::write(&self.config.path, content)?;
+    /// Removes a variable from the .env file.
+    ///
+    /// The file is left untouched if it doesn't contain the variable.
+    fn delete(&self, _project: &str, key: &str, _profile: &str) -> Result<()> {
+        let mut vars = self.read_vars()?;
+        if vars.remove(key).is_some() {
+            self.write_vars(&vars)?;
+        }
         Ok(())
     }
+
+    fn allows_delete(&self) -> bool {
+        true
+    }
+
+    /// Lists every variable in the .env file, sorted by name.
+    fn list_keys(&self, _project: &str, _profile: &str) -> Result<Vec<String>> {
+        let mut keys: Vec<String> = self.read_vars()?.into_keys().collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn allows_list(&self) -> bool {
+        true
+    }
+
+    /// Checks that the .env file exists and is not readable by other users.
+    fn doctor(&self) -> Vec<Check> {
+        const NAME: &str = "Dotenv file";
+        let path = &self.config.path;
+
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(_) => {
+                return vec![Check::warn(
+                    NAME,
+                    format!("{} does not exist", path.display()),
+                    Some("It will be created by 'secretspec set' or 'secretspec check'".into()),
+                )];
+            }
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = metadata.permissions().mode() & 0o777;
+            if mode & 0o077 != 0 {
+                return vec![Check::warn(
+                    NAME,
+                    format!(
+                        "{} is accessible by other users (mode {:o})",
+                        path.display(),
+                        mode
+                    ),
+                    Some(format!("Run 'chmod 600 {}'", path.display())),
+                )];
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = metadata;
+
+        vec![Check::pass(NAME, format!("{} is private", path.display()))]
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,566 @@
+//! Client-side envelope encryption for any provider.
+//!
+//! Prefixing a provider URI with `enc+` wraps it in an [`EncryptedProvider`]
+//! that encrypts values with [age](https://age-encryption.org) before they are
+//! handed to the underlying provider and decrypts them on read, so low-trust
+//! backends such as dotenv files never see plaintext:
+//!
+//! ```text
+//! enc+dotenv://.env.encrypted?key=age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p
+//! ```
+//!
+//! Stored values look like `age:<base64 ciphertext>`. Values are encrypted to
+//! every `key` recipient, which may be an age or SSH public key and may be
+//! repeated, so each team member can decrypt with their own identity file
+//! given by the `identity` parameter or the `SECRETSPEC_AGE_IDENTITY`
+//! environment variable. Recipients can also be listed per profile in the
+//! `[recipients]` table of `secretspec.toml`. Encryption and decryption use
+//! the `age` CLI.
+//!
+//! An identity given as `tpm:<dir>` is an age identity sealed to the
+//! machine's TPM with `secretspec tpm-seal`. It is unsealed once per
+//! command and handed to `age` through a file only the owner can read,
+//! which is removed right after decrypting.
+
+use super::chunked::ChunkedProvider;
+use super::macros::forward_provider;
+use super::{ItemMatch, Provider, SecretValue, json_fields, operation_unsupported};
+use crate::config::is_valid_recipient;
+use crate::doctor::{self, Check};
+use crate::provider::command::{CommandRunner, Invocation, SystemRunner};
+use crate::tpm;
+use crate::{Result, SecretSpecError};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use url::form_urlencoded;
+use zeroize::Zeroizing;
+
+/// Prefix that selects the encryption wrapper in a provider URI.
+pub const SCHEME_PREFIX: &str = "enc+";
+
+/// Prefix of every value stored by the wrapper.
+const VALUE_PREFIX: &str = "age:";
+
+/// Environment variable naming the age identity file used for decryption.
+const IDENTITY_ENV: &str = "SECRETSPEC_AGE_IDENTITY";
+
+/// Provider wrapper that encrypts values before storing them.
+pub struct EncryptedProvider {
+    /// The wrapped provider that stores the ciphertext
+    inner: Box<dyn Provider>,
+    /// age or SSH public keys values are encrypted to
+    recipients: Vec<String>,
+    /// Path of the age identity file, if configured in the URI
+    identity: Option<String>,
+    /// The TPM-sealed identity, once unsealed
+    unsealed: OnceLock<Zeroizing<String>>,
+    /// Runs the `age` CLI
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl EncryptedProvider {
+    /// Creates a new EncryptedProvider around the given provider.
+    ///
+    /// The inner provider is wrapped with [`ChunkedProvider`] if it has a
+    /// value size limit, since ciphertext is larger than the plaintext.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The provider to store ciphertext in
+    /// * `recipients` - The age or SSH public keys to encrypt to
+    /// * `identity` - Path of the age identity file used to decrypt
+    pub fn new(
+        inner: Box<dyn Provider>,
+        recipients: Vec<String>,
+        identity: Option<String>,
+    ) -> Self {
+        Self::with_runner(inner, recipients, identity, Arc::new(SystemRunner))
+    }
+
+    /// Creates an EncryptedProvider that runs the `age` CLI through `runner`.
+    pub(crate) fn with_runner(
+        inner: Box<dyn Provider>,
+        recipients: Vec<String>,
+        identity: Option<String>,
+        runner: Arc<dyn CommandRunner>,
+    ) -> Self {
+        Self {
+            inner: ChunkedProvider::wrap(inner),
+            recipients,
+            identity,
+            unsealed: OnceLock::new(),
+            runner,
+        }
+    }
+
+    /// Creates the wrapper from the part of a URI after `enc+`.
+    ///
+    /// The `key` and `identity` query parameters configure encryption; the
+    /// remaining URI is used to create the underlying provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no valid `key` is given or the underlying provider
+    /// URI is invalid
+    pub fn from_spec(spec: &str) -> Result<Self> {
+        let (inner_spec, recipients, identity) = split_spec(spec)?;
+        check_recipients(&format!("{}{}", SCHEME_PREFIX, spec), &recipients)?;
+        let inner = Box::<dyn Provider>::try_from(inner_spec.as_str())?;
+        Ok(Self::new(inner, recipients, identity))
+    }
+
+    /// Returns the identity file used for decryption.
+    fn identity(&self) -> Result<String> {
+        self.identity
+            .clone()
+            .or_else(|| std::env::var(IDENTITY_ENV).ok())
+            .ok_or_else(|| {
+                SecretSpecError::ProviderOperationFailed(format!(
+                    "No age identity configured for decryption.\n\nSet {} to the path of your age identity file, or add ?identity=/path/to/key.txt (or ?identity=tpm:/path/to/sealed for an identity sealed to the TPM) to the provider URI.",
+                    IDENTITY_ENV
+                ))
+            })
+    }
+
+    /// Returns the identity sealed to the TPM in the directory `sealed`,
+    /// unsealing it on first use.
+    fn unseal(&self, sealed: &str) -> Result<&str> {
+        if let Some(identity) = self.unsealed.get() {
+            return Ok(identity);
+        }
+        let identity = tpm::unseal(Path::new(sealed), self.runner.as_ref())?;
+        Ok(self.unsealed.get_or_init(|| identity))
+    }
+
+    /// Encrypts a value and encodes it for storage.
+    fn encrypt(&self, value: &SecretString) -> Result<SecretString> {
+        let ciphertext = age_encrypt(
+            self.runner.as_ref(),
+            &self.recipients,
+            value.expose_secret().as_bytes(),
+        )?;
+        Ok(SecretString::new(
+            format!("{}{}", VALUE_PREFIX, BASE64.encode(ciphertext)).into(),
+        ))
+    }
+
+    /// Decodes and decrypts a stored value.
+    fn decrypt(&self, key: &str, stored: &SecretString) -> Result<SecretString> {
+        let encoded = stored
+            .expose_secret()
+            .strip_prefix(VALUE_PREFIX)
+            .ok_or_else(|| {
+                SecretSpecError::ProviderOperationFailed(format!(
+                    "{} is stored unencrypted in {}; set it again to encrypt it",
+                    key,
+                    self.inner.name()
+                ))
+            })?;
+        let ciphertext = BASE64.decode(encoded.trim()).map_err(|e| {
+            SecretSpecError::ProviderOperationFailed(format!(
+                "{} has a malformed encrypted value: {}",
+                key, e
+            ))
+        })?;
+        let identity = self.identity()?;
+        let unsealed = match identity.strip_prefix(tpm::IDENTITY_PREFIX) {
+            Some(sealed) => Some(tpm::identity_file(self.unseal(sealed)?)?),
+            None => None,
+        };
+        let identity = match &unsealed {
+            Some(file) => file.path().display().to_string(),
+            None => identity,
+        };
+        let plaintext = execute_age(
+            self.runner.as_ref(),
+            &["--decrypt", "--identity", &identity],
+            &ciphertext,
+        )?;
+        drop(unsealed);
+        Ok(SecretString::from(plaintext.as_str()))
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for EncryptedProvider {
+    /// Retrieves and decrypts a secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored value is not encrypted or cannot be
+    /// decrypted with the configured identity.
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        match self.inner.get(project, key, profile)? {
+            Some(stored) => self.decrypt(key, &stored).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves and decrypts a secret, keeping the metadata of the ciphertext.
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        match self.inner.get_with_metadata(project, key, profile)? {
+            Some(stored) => Ok(Some(SecretValue {
+                value: self.decrypt(key, &stored.value)?,
+                metadata: stored.metadata,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Encrypts a secret and stores the ciphertext in the underlying provider.
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        let encrypted = self.encrypt(value)?;
+        self.inner.set(project, key, &encrypted, profile)
+    }
+
+    /// Encrypts a secret and stores the ciphertext if the underlying
+    /// provider still holds the expected revision.
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        let encrypted = self.encrypt(value)?;
+        self.inner
+            .compare_and_set(project, key, &encrypted, profile, expected)
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        let encrypted = values
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), self.encrypt(value)?)))
+            .collect::<Result<Vec<_>>>()?;
+        self.inner.set_many(project, &encrypted, profile)
+    }
+
+    /// Reads fields of the decrypted JSON value; the fields of the stored
+    /// ciphertext are meaningless.
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        json_fields(item, self.get(project, item, profile)?, fields)
+    }
+
+    /// Items found by name hold plaintext written outside secretspec, which
+    /// can't be adopted into an encrypting provider.
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        let _ = (project, key, profile);
+        Err(operation_unsupported(self.name(), "adopting items"))
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let _ = (project, key, id, profile);
+        Err(operation_unsupported(self.name(), "addressing items by ID"))
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        let _ = (project, key, id, value, profile);
+        Err(operation_unsupported(self.name(), "addressing items by ID"))
+    }
+
+    fn max_value_size(&self) -> Option<usize> {
+        None
+    }
+
+    fn doctor(&self) -> Vec<Check> {
+        let version =
+            execute_age(self.runner.as_ref(), &["--version"], &[]).map(|output| output.to_string());
+        let mut checks = vec![doctor::check_cli("age CLI", version)];
+        if let Err(e) = self.identity() {
+            checks.push(Check::from_error("age identity", &e));
+        }
+        checks.extend(self.inner.doctor());
+        checks
+    }
+
+    forward_provider!(inner =>
+        allows_set, check_writable, delete, allows_delete, purge, restore, allows_restore, history,
+        list_keys, allows_list, browse, works_in_sandbox, list_access, access_policy, grant, leases,
+        renew_lease, revoke_lease, name,
+    );
+}
+
+/// Checks that an encrypting provider has valid recipients.
+///
+/// # Errors
+///
+/// Returns an error naming `uri` if there are no recipients, or the first
+/// recipient that isn't an age or SSH public key
+pub(crate) fn check_recipients(uri: &str, recipients: &[String]) -> Result<()> {
+    if recipients.is_empty() {
+        return Err(SecretSpecError::ProviderOperationFailed(format!(
+            "Encrypted provider '{}' requires a recipient, e.g. ?key=age1... or a [recipients] table in secretspec.toml",
+            uri
+        )));
+    }
+    if let Some(invalid) = recipients.iter().find(|r| !is_valid_recipient(r)) {
+        return Err(SecretSpecError::ProviderOperationFailed(format!(
+            "Invalid recipient '{}'. Recipients must be age (age1...) or SSH (ssh-ed25519, ssh-rsa) public keys",
+            invalid
+        )));
+    }
+    Ok(())
+}
+
+/// Splits the encryption parameters off a provider URI.
+///
+/// Returns the URI of the underlying provider along with every `key` and the
+/// `identity` parameter; all other parameters stay with the provider.
+pub(crate) fn split_spec(spec: &str) -> Result<(String, Vec<String>, Option<String>)> {
+    let Some((base, query)) = spec.split_once('?') else {
+        return Ok((spec.to_string(), Vec::new(), None));
+    };
+
+    let mut recipients = Vec::new();
+    let mut identity = None;
+    let mut remaining = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "key" => recipients.push(value.into_owned()),
+            "identity" => identity = Some(value.into_owned()),
+            _ => {
+                remaining.append_pair(&key, &value);
+            }
+        }
+    }
+
+    let remaining = remaining.finish();
+    let inner = if remaining.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}?{}", base, remaining)
+    };
+    Ok((inner, recipients, identity))
+}
+
+/// Appends recipients to an encrypting provider URI as `key` parameters.
+///
+/// # Arguments
+///
+/// * `spec` - The provider URI, returned unchanged unless it starts with
+///   `enc+` or `gitvault:`, optionally after `override+`
+/// * `recipients` - The public keys to add
+pub(crate) fn with_recipients<'a>(
+    spec: String,
+    recipients: impl IntoIterator<Item = &'a String>,
+) -> String {
+    let wrapped = spec
+        .strip_prefix(super::env_override::SCHEME_PREFIX)
+        .unwrap_or(&spec);
+    if !wrapped.starts_with(SCHEME_PREFIX) && !wrapped.starts_with(super::gitvault::SCHEME_PREFIX) {
+        return spec;
+    }
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for recipient in recipients {
+        serializer.append_pair("key", recipient);
+    }
+    let params = serializer.finish();
+    if params.is_empty() {
+        spec
+    } else if spec.contains('?') {
+        format!("{}&{}", spec, params)
+    } else {
+        format!("{}?{}", spec, params)
+    }
+}
+
+/// Runs the `age` CLI through `runner` with the given input on stdin and
+/// returns its standard output.
+pub(crate) fn execute_age(
+    runner: &dyn CommandRunner,
+    args: &[&str],
+    input: &[u8],
+) -> Result<Zeroizing<String>> {
+    let invocation = Invocation::new("age").args(args).stdin(input);
+    let output = match runner.run(&invocation) {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(SecretSpecError::CliMissing {
+                cli: "age".to_string(),
+                install_hint: "To install it:\n  - macOS: brew install age\n  - Linux: Install the 'age' package or download from https://github.com/FiloSottile/age/releases\n  - Windows: winget install FiloSottile.age\n  - NixOS: nix-env -iA nixpkgs.age".to_string(),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !output.success {
+        return Err(SecretSpecError::ProviderOperationFailed(format!(
+            "age failed: {}",
+            output.stderr.trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Encrypts a value to the recipients with the `age` CLI and returns the
+/// binary ciphertext.
+///
+/// age writes ASCII armor so the ciphertext survives being read as text; the
+/// armor is removed again, so stored values are the same as those of binary
+/// output.
+pub(crate) fn age_encrypt(
+    runner: &dyn CommandRunner,
+    recipients: &[String],
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    let mut args = vec!["--encrypt", "--armor"];
+    for recipient in recipients {
+        args.extend(["--recipient", recipient.as_str()]);
+    }
+    let armored = execute_age(runner, &args, plaintext)?;
+    let body: String = armored
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    BASE64.decode(body).map_err(|e| {
+        SecretSpecError::ProviderOperationFailed(format!("age wrote malformed armor: {}", e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::command::FakeRunner;
+    use crate::provider::tests::MockProvider;
+
+    #[test]
+    fn test_split_spec_extracts_encryption_parameters() {
+        let (inner, recipients, identity) =
+            split_spec("dotenv://.env.enc?key=age1abc&identity=%2Ftmp%2Fkey.txt").unwrap();
+        assert_eq!(inner, "dotenv://.env.enc");
+        assert_eq!(recipients, vec!["age1abc"]);
+        assert_eq!(identity.as_deref(), Some("/tmp/key.txt"));
+
+        let (inner, recipients, _) =
+            split_spec("bitwarden://?org=acme&key=age1abc&type=login&key=ssh-ed25519+AAAA")
+                .unwrap();
+        assert_eq!(inner, "bitwarden://?org=acme&type=login");
+        assert_eq!(recipients, vec!["age1abc", "ssh-ed25519 AAAA"]);
+
+        let (inner, recipients, _) = split_spec("keyring").unwrap();
+        assert_eq!(inner, "keyring");
+        assert!(recipients.is_empty());
+    }
+
+    #[test]
+    fn test_from_spec_requires_recipient() {
+        assert!(EncryptedProvider::from_spec("dotenv://.env").is_err());
+        assert!(EncryptedProvider::from_spec("dotenv://.env?key=ssh-ed25519").is_err());
+
+        let provider = EncryptedProvider::from_spec("dotenv://.env?key=age1abc").unwrap();
+        assert_eq!(provider.name(), "dotenv");
+    }
+
+    #[test]
+    fn test_with_recipients() {
+        let alice = "age1alice".to_string();
+        let bob = "ssh-ed25519 AAAAbob".to_string();
+
+        assert_eq!(
+            with_recipients("enc+dotenv://.env".to_string(), [&alice, &bob]),
+            "enc+dotenv://.env?key=age1alice&key=ssh-ed25519+AAAAbob"
+        );
+        assert_eq!(
+            with_recipients("enc+dotenv://.env?key=age1ci".to_string(), [&alice]),
+            "enc+dotenv://.env?key=age1ci&key=age1alice"
+        );
+        assert_eq!(
+            with_recipients("override+enc+dotenv://.env".to_string(), [&alice]),
+            "override+enc+dotenv://.env?key=age1alice"
+        );
+        assert_eq!(
+            with_recipients("dotenv://.env".to_string(), [&alice]),
+            "dotenv://.env"
+        );
+
+        let spec = with_recipients("enc+dotenv://.env".to_string(), [&alice, &bob]);
+        let provider = EncryptedProvider::from_spec(&spec["enc+".len()..]).unwrap();
+        assert_eq!(provider.recipients, vec![alice, bob]);
+    }
+
+    #[test]
+    fn test_plaintext_values_are_rejected() {
+        let provider = EncryptedProvider::new(
+            Box::new(MockProvider::new()),
+            vec!["age1abc".to_string()],
+            Some("/nonexistent".to_string()),
+        );
+        let plaintext = SecretString::new("hunter2".to_string().into());
+        provider
+            .inner
+            .set("proj", "KEY", &plaintext, "default")
+            .unwrap();
+
+        let err = provider.get("proj", "KEY", "default").unwrap_err();
+        assert!(err.to_string().contains("stored unencrypted"));
+        assert!(!err.to_string().contains("hunter2"));
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt_through_runner() {
+        let runner = Arc::new(
+            FakeRunner::new()
+                .ok(
+                    &["age", "--encrypt", "--armor", "--recipient", "age1abc"],
+                    "-----BEGIN AGE ENCRYPTED FILE-----\nAAEC\n/w==\n-----END AGE ENCRYPTED FILE-----\n",
+                )
+                .ok(&["age", "--decrypt", "--identity", "/tmp/key.txt"], "hunter2"),
+        );
+        let provider = EncryptedProvider::with_runner(
+            Box::new(MockProvider::new()),
+            vec!["age1abc".to_string()],
+            Some("/tmp/key.txt".to_string()),
+            runner.clone(),
+        );
+        provider
+            .set("proj", "KEY", &SecretString::from("hunter2"), "default")
+            .unwrap();
+        let stored = provider
+            .inner
+            .get("proj", "KEY", "default")
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.expose_secret(), "age:AAEC/w==");
+
+        let value = provider.get("proj", "KEY", "default").unwrap().unwrap();
+        assert_eq!(value.expose_secret(), "hunter2");
+        assert!(runner.is_exhausted());
+        let calls = runner.calls();
+        assert_eq!(calls[0].stdin.as_deref(), Some(&b"hunter2"[..]));
+        assert_eq!(calls[1].stdin.as_deref(), Some(&[0, 1, 2, 255][..]));
+    }
+}
@@ -112,6 +112,10 @@ impl Provider for EnvProvider {
         Self::PROVIDER_NAME
     }
 
+    fn works_in_sandbox(&self) -> bool {
+        true
+    }
+
     /// Retrieves a secret value from environment variables.
     ///
     /// This method reads the value directly from the process environment
@@ -0,0 +1,156 @@
+//! Environment overrides on top of any provider.
+//!
+//! Prefixing a provider URI with `override+` wraps it in an
+//! [`EnvOverrideProvider`]. A secret set in the process environment wins;
+//! every other secret is read from the underlying provider:
+//!
+//! ```text
+//! override+onepassword://Production
+//! ```
+//!
+//! This lets a developer point a single secret at a local service with
+//! `DATABASE_URL=postgres://localhost/dev secretspec run -- ...` while the
+//! rest still come from the team store. Writes, deletes and listing go to
+//! the underlying provider, so an overridden secret reads back its override
+//! until the variable is unset.
+
+use super::macros::forward_provider;
+use super::{Provider, SecretValue, json_fields};
+use crate::Result;
+use secrecy::SecretString;
+use std::collections::HashMap;
+use std::env;
+
+/// Prefix that selects the override wrapper in a provider URI.
+pub const SCHEME_PREFIX: &str = "override+";
+
+/// Provider wrapper that lets the process environment override secrets.
+pub struct EnvOverrideProvider {
+    /// The wrapped provider that secrets fall through to
+    inner: Box<dyn Provider>,
+}
+
+impl EnvOverrideProvider {
+    /// Creates a new EnvOverrideProvider around the given provider.
+    pub fn new(inner: Box<dyn Provider>) -> Self {
+        Self { inner }
+    }
+
+    /// Creates the wrapper from the part of a URI after `override+`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying provider URI is invalid
+    pub fn from_spec(spec: &str) -> Result<Self> {
+        Ok(Self::new(Box::<dyn Provider>::try_from(spec)?))
+    }
+
+    /// Returns the value of the environment variable named `key`, if set.
+    fn from_env(key: &str) -> Option<SecretString> {
+        env::var(key).ok().map(|v| SecretString::new(v.into()))
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for EnvOverrideProvider {
+    /// Retrieves a secret from the environment, or else from the underlying
+    /// provider.
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        match Self::from_env(key) {
+            Some(value) => Ok(Some(value)),
+            None => self.inner.get(project, key, profile),
+        }
+    }
+
+    /// Retrieves a secret from the environment, without metadata, or else
+    /// from the underlying provider.
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        match Self::from_env(key) {
+            Some(value) => Ok(Some(value.into())),
+            None => self.inner.get_with_metadata(project, key, profile),
+        }
+    }
+
+    /// Splits an overriding environment variable into fields, or else reads
+    /// the fields from the underlying provider.
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        match Self::from_env(item) {
+            Some(value) => json_fields(item, Some(value), fields),
+            None => self.inner.get_fields(project, item, fields, profile),
+        }
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        match Self::from_env(key) {
+            Some(value) => Ok(Some(value.into())),
+            None => self.inner.get_by_id(project, key, id, profile),
+        }
+    }
+
+    forward_provider!(inner =>
+        set, set_many, allows_set, compare_and_set, check_writable, delete, allows_delete, purge,
+        restore, allows_restore, history, list_keys, allows_list, find_items, set_by_id, browse,
+        max_value_size, works_in_sandbox, doctor, list_access, access_policy, grant, leases,
+        renew_lease, revoke_lease, name,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::tests::MockProvider;
+
+    #[test]
+    fn test_environment_wins() {
+        let provider = EnvOverrideProvider::new(Box::new(MockProvider::new()));
+        let stored = SecretString::new("from-store".to_string().into());
+        for key in ["SECRETSPEC_TEST_OVERRIDDEN", "SECRETSPEC_TEST_STORED"] {
+            provider.set("proj", key, &stored, "default").unwrap();
+        }
+        // SAFETY: the variable is only read by this test
+        unsafe { env::set_var("SECRETSPEC_TEST_OVERRIDDEN", "from-env") };
+
+        let get = |key| {
+            provider
+                .get("proj", key, "default")
+                .unwrap()
+                .map(|v| secrecy::ExposeSecret::expose_secret(&v).to_string())
+        };
+        assert_eq!(
+            get("SECRETSPEC_TEST_OVERRIDDEN").as_deref(),
+            Some("from-env")
+        );
+        assert_eq!(get("SECRETSPEC_TEST_STORED").as_deref(), Some("from-store"));
+        assert_eq!(get("SECRETSPEC_TEST_MISSING"), None);
+
+        unsafe { env::remove_var("SECRETSPEC_TEST_OVERRIDDEN") };
+        assert_eq!(
+            get("SECRETSPEC_TEST_OVERRIDDEN").as_deref(),
+            Some("from-store")
+        );
+    }
+
+    #[test]
+    fn test_from_spec() {
+        let provider = EnvOverrideProvider::from_spec("dotenv://.env").unwrap();
+        assert_eq!(provider.name(), "dotenv");
+        assert!(EnvOverrideProvider::from_spec("nonexistent://x").is_err());
+    }
+}
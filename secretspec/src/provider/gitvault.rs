@@ -0,0 +1,492 @@
+//! Encrypted team store in a git repository.
+//!
+//! A `gitvault://` provider keeps secrets in a git repository that the whole
+//! team can clone, with one JSON file per project and profile:
+//!
+//! ```text
+//! gitvault://git@github.com:org/secrets.git?key=age1...
+//! ```
+//!
+//! ```text
+//! my-app/
+//!   default.json
+//!   production.json
+//! ```
+//!
+//! Values are always encrypted with age before they are written, exactly as
+//! with an `enc+` provider: every `key` parameter and the recipients listed in
+//! the spec's `[recipients]` table can decrypt them. The `branch` parameter
+//! selects the branch to use (default: `main`) and `checkout` the local clone
+//! (default: one per remote in the secretspec data directory).
+//!
+//! The provider clones the repository on first use and fetches it before
+//! reading. Every change is committed and pushed right away. When the push is
+//! rejected because someone else pushed first, the change is applied again on
+//! top of theirs, unless they changed the same key, which is reported as a
+//! conflict instead of silently overwriting their value.
+
+use super::Provider;
+use super::command::{CommandOutput, CommandRunner, Invocation, SystemRunner};
+use super::encrypted::{self, EncryptedProvider};
+use crate::doctor::{self, Check, Status};
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use url::form_urlencoded;
+
+/// Prefix of gitvault provider URIs.
+pub const SCHEME_PREFIX: &str = "gitvault:";
+
+/// Branch used unless the URI names another one.
+const DEFAULT_BRANCH: &str = "main";
+
+/// How often a change is applied again after its push was rejected.
+const PUSH_ATTEMPTS: usize = 3;
+
+/// Configuration for the gitvault provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitVaultConfig {
+    /// The git remote, e.g. `git@github.com:org/secrets.git`
+    pub remote: String,
+    /// The branch secrets are stored on
+    pub branch: String,
+    /// Path of the local clone
+    pub checkout: PathBuf,
+}
+
+impl GitVaultConfig {
+    /// Parses the part of a URI after `gitvault://`, without encryption
+    /// parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no remote is given or the checkout directory can't
+    /// be determined
+    pub(crate) fn parse(spec: &str) -> Result<Self> {
+        let (remote, query) = spec.split_once('?').unwrap_or((spec, ""));
+        if remote.is_empty() {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "gitvault provider requires a remote, e.g. gitvault://git@github.com:org/secrets.git"
+                    .to_string(),
+            ));
+        }
+
+        let mut branch = DEFAULT_BRANCH.to_string();
+        let mut checkout = None;
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "branch" => branch = value.into_owned(),
+                "checkout" => checkout = Some(PathBuf::from(value.as_ref())),
+                _ => {
+                    return Err(SecretSpecError::ProviderOperationFailed(format!(
+                        "Unknown gitvault parameter '{}'. Use key, identity, branch or checkout",
+                        key
+                    )));
+                }
+            }
+        }
+
+        let checkout = match checkout {
+            Some(checkout) => checkout,
+            None => directories::ProjectDirs::from("", "", "secretspec")
+                .ok_or_else(|| {
+                    SecretSpecError::ProviderOperationFailed(
+                        "Could not find the data directory for the gitvault checkout; add ?checkout=<path>"
+                            .to_string(),
+                    )
+                })?
+                .data_local_dir()
+                .join("gitvault")
+                .join(super::short_hash(remote)),
+        };
+
+        Ok(Self {
+            remote: remote.to_string(),
+            branch,
+            checkout,
+        })
+    }
+}
+
+/// Parses a `gitvault://` URI into the configuration, the recipients and
+/// the identity.
+///
+/// # Errors
+///
+/// Returns an error if the URI has no remote or an unknown parameter
+pub(crate) fn parse_spec(spec: &str) -> Result<(GitVaultConfig, Vec<String>, Option<String>)> {
+    let rest = spec.strip_prefix(SCHEME_PREFIX).unwrap_or(spec);
+    let rest = rest.strip_prefix("//").unwrap_or(rest);
+    let (inner_spec, recipients, identity) = encrypted::split_spec(rest)?;
+    Ok((GitVaultConfig::parse(&inner_spec)?, recipients, identity))
+}
+
+/// Creates the provider for a `gitvault://` URI, wrapped in encryption.
+///
+/// # Errors
+///
+/// Returns an error if the URI has no remote or no valid recipient
+pub(crate) fn from_spec(spec: &str) -> Result<Box<dyn Provider>> {
+    let (config, recipients, identity) = parse_spec(spec)?;
+    encrypted::check_recipients(spec, &recipients)?;
+    let provider = GitVaultProvider::new(config);
+    Ok(Box::new(EncryptedProvider::new(
+        Box::new(provider),
+        recipients,
+        identity,
+    )))
+}
+
+/// Provider storing secrets in a git repository.
+///
+/// Values are stored as given; [`from_spec`] wraps the provider in an
+/// [`EncryptedProvider`] so only ciphertext reaches the repository.
+pub struct GitVaultProvider {
+    config: GitVaultConfig,
+    /// Runs the `git` CLI
+    runner: Arc<dyn CommandRunner>,
+    /// Set once the checkout was brought up to date for reading
+    synced: OnceLock<()>,
+}
+
+#[cfg_attr(
+    feature = "native",
+    linkme::distributed_slice(super::PROVIDER_REGISTRY)
+)]
+#[cfg_attr(not(feature = "native"), allow(dead_code))]
+static PROVIDER_REGISTRATION: super::ProviderRegistration = super::ProviderRegistration {
+    info: super::ProviderInfo {
+        name: "gitvault",
+        description: "Encrypted team store in a git repository",
+        examples: &["gitvault://git@github.com:org/secrets.git?key=age1..."],
+    },
+    schemes: &["gitvault"],
+    params: |_| &["key", "identity", "branch", "checkout"],
+    factory: |url| from_spec(url.as_str()),
+    explain: |url| Ok(format!("{:#?}", parse_spec(url.as_str())?.0)),
+};
+
+/// How a push ended.
+enum Push {
+    Pushed,
+    /// Someone else pushed first
+    Rejected,
+}
+
+impl GitVaultProvider {
+    /// Creates a new GitVaultProvider with the given configuration.
+    pub fn new(config: GitVaultConfig) -> Self {
+        Self::with_runner(config, Arc::new(SystemRunner))
+    }
+
+    /// Creates a GitVaultProvider that runs the `git` CLI through `runner`.
+    pub(crate) fn with_runner(config: GitVaultConfig, runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            config,
+            runner,
+            synced: OnceLock::new(),
+        }
+    }
+
+    /// Runs a git command, returning its output whether or not it succeeded.
+    fn run(&self, invocation: Invocation) -> Result<CommandOutput> {
+        match self.runner.run(&invocation) {
+            Ok(output) => Ok(output),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(SecretSpecError::CliMissing {
+                cli: "git".to_string(),
+                install_hint: "To install it:\n  - macOS: xcode-select --install or brew install git\n  - Linux: Install the 'git' package\n  - Windows: winget install Git.Git\n  - NixOS: nix-env -iA nixpkgs.git".to_string(),
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Runs git in the checkout, returning its output whether or not it
+    /// succeeded.
+    fn run_in_checkout(&self, args: &[&str]) -> Result<CommandOutput> {
+        let checkout = self.config.checkout.to_string_lossy();
+        self.run(
+            Invocation::new("git")
+                .args(["-C", checkout.as_ref()])
+                .args(args),
+        )
+    }
+
+    /// Runs git in the checkout and returns its standard output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with git's message if the command fails
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = self.run_in_checkout(args)?;
+        if !output.success {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "git {} failed: {}",
+                args[0],
+                output.stderr.trim()
+            )));
+        }
+        // Git only sees the encrypted vault, never plain values
+        Ok(output.stdout.to_string())
+    }
+
+    /// Clones the repository or brings the checkout up to date with the
+    /// remote branch.
+    ///
+    /// Local commits that weren't pushed are discarded.
+    fn sync(&self) -> Result<()> {
+        let checkout = &self.config.checkout;
+        if !checkout.join(".git").is_dir() {
+            if let Some(parent) = checkout.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let path = checkout.to_string_lossy();
+            let output = self.run(Invocation::new("git").args([
+                "clone",
+                "--quiet",
+                "--",
+                &self.config.remote,
+                path.as_ref(),
+            ]))?;
+            if !output.success {
+                return Err(SecretSpecError::ProviderOperationFailed(format!(
+                    "Could not clone {}: {}",
+                    self.config.remote,
+                    output.stderr.trim()
+                )));
+            }
+        }
+
+        self.git(&["fetch", "--quiet", "origin"])?;
+        let remote_branch = format!("refs/remotes/origin/{}", self.config.branch);
+        let exists = self
+            .run_in_checkout(&["rev-parse", "--verify", "--quiet", &remote_branch])?
+            .success;
+        // A new repository has no branch until the first secret is pushed
+        if exists {
+            self.git(&["reset", "--quiet", "--hard", &remote_branch])?;
+        }
+        Ok(())
+    }
+
+    /// Syncs the checkout once for reading.
+    fn sync_for_read(&self) -> Result<()> {
+        if self.synced.get().is_none() {
+            self.sync()?;
+            let _ = self.synced.set(());
+        }
+        Ok(())
+    }
+
+    /// Returns the path of a profile's file, relative to the checkout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project or profile can't be used as a path
+    /// component
+    fn relative_path(project: &str, profile: &str) -> Result<String> {
+        for part in [project, profile] {
+            if part.is_empty() || part == "." || part == ".." || part.contains(['/', '\\']) {
+                return Err(SecretSpecError::InvalidInput(format!(
+                    "'{}' can't be used as a gitvault path component",
+                    part
+                )));
+            }
+        }
+        Ok(format!("{}/{}.json", project, profile))
+    }
+
+    /// Reads the values stored for a profile.
+    fn read(&self, relative: &str) -> Result<BTreeMap<String, String>> {
+        let path = self.config.checkout.join(relative);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            SecretSpecError::ProviderOperationFailed(format!(
+                "{} in {} is not a JSON object of values: {}",
+                relative, self.config.remote, e
+            ))
+        })
+    }
+
+    /// Writes the values stored for a profile.
+    fn write(&self, relative: &str, values: &BTreeMap<String, String>) -> Result<()> {
+        let path = self.config.checkout.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut content = serde_json::to_string_pretty(values)?;
+        content.push('\n');
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Pushes the checkout's commits to the remote branch.
+    fn push(&self) -> Result<Push> {
+        let refspec = format!("HEAD:refs/heads/{}", self.config.branch);
+        match self.git(&["push", "--quiet", "origin", &refspec]) {
+            Ok(_) => Ok(Push::Pushed),
+            Err(SecretSpecError::ProviderOperationFailed(message)) if is_rejection(&message) => {
+                Ok(Push::Rejected)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets or deletes a key and pushes the change.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if someone else changed the same key since the
+    /// checkout was synced, or the push keeps being rejected
+    fn update(&self, project: &str, profile: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let relative = Self::relative_path(project, profile)?;
+        self.sync()?;
+        let base = self.read(&relative)?.remove(key);
+
+        for _ in 0..PUSH_ATTEMPTS {
+            let mut values = self.read(&relative)?;
+            if values.get(key) != base.as_ref() {
+                return Err(SecretSpecError::ProviderOperationFailed(format!(
+                    "{} in {}/{} was changed in {} while it was being updated. Check the new value and run the command again",
+                    key, project, profile, self.config.remote
+                )));
+            }
+
+            let message = match value {
+                Some(value) => {
+                    values.insert(key.to_string(), value.to_string());
+                    format!("Set {} in {}/{}", key, project, profile)
+                }
+                None if values.remove(key).is_some() => {
+                    format!("Delete {} from {}/{}", key, project, profile)
+                }
+                None => return Ok(()),
+            };
+            self.write(&relative, &values)?;
+
+            self.git(&["add", "--", &relative])?;
+            self.git(&["commit", "--quiet", "-m", &message])?;
+            match self.push()? {
+                Push::Pushed => {
+                    let _ = self.synced.set(());
+                    return Ok(());
+                }
+                // Start over from what the others pushed
+                Push::Rejected => self.sync()?,
+            }
+        }
+
+        Err(SecretSpecError::ProviderOperationFailed(format!(
+            "Could not push to {} after {} attempts because others kept pushing. Run the command again",
+            self.config.remote, PUSH_ATTEMPTS
+        )))
+    }
+}
+
+/// Returns whether git's message reports a push that lost a race.
+///
+/// The remote either refuses a push that isn't based on its branch, or fails
+/// to lock the branch when another push updates it at the same time.
+fn is_rejection(message: &str) -> bool {
+    message.contains("[rejected]")
+        || message.contains("fetch first")
+        || message.contains("non-fast-forward")
+        || message.contains("cannot lock ref")
+}
+
+impl Provider for GitVaultProvider {
+    fn name(&self) -> &'static str {
+        "gitvault"
+    }
+
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        let relative = Self::relative_path(project, profile)?;
+        self.sync_for_read()?;
+        Ok(self.read(&relative)?.remove(key).map(SecretString::from))
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.update(project, profile, key, Some(value.expose_secret()))
+    }
+
+    /// Checks that the remote accepts pushes with `git push --dry-run`.
+    ///
+    /// A repository without commits can't be probed; its first write creates
+    /// the branch.
+    fn check_writable(&self, project: &str, profile: &str) -> Result<()> {
+        Self::relative_path(project, profile)?;
+        self.sync_for_read()?;
+        if !self
+            .run_in_checkout(&["rev-parse", "--verify", "--quiet", "HEAD"])?
+            .success
+        {
+            return Ok(());
+        }
+
+        let refspec = format!("HEAD:refs/heads/{}", self.config.branch);
+        let output = self.run_in_checkout(&["push", "--dry-run", "--quiet", "origin", &refspec])?;
+        if output.success {
+            Ok(())
+        } else {
+            Err(SecretSpecError::PermissionDenied(format!(
+                "cannot push to {}: {}",
+                self.config.remote,
+                output.stderr.trim()
+            )))
+        }
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.update(project, profile, key, None)
+    }
+
+    fn allows_delete(&self) -> bool {
+        true
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let relative = Self::relative_path(project, profile)?;
+        self.sync_for_read()?;
+        Ok(self.read(&relative)?.into_keys().collect())
+    }
+
+    fn allows_list(&self) -> bool {
+        true
+    }
+
+    fn doctor(&self) -> Vec<Check> {
+        let version = self
+            .run(Invocation::new("git").args(["--version"]))
+            .map(|output| output.stdout);
+        let cli = doctor::check_cli("git CLI", version);
+        if cli.status != Status::Pass {
+            return vec![cli];
+        }
+
+        let remote = self.run(Invocation::new("git").args([
+            "ls-remote",
+            "--quiet",
+            "--",
+            &self.config.remote,
+        ]));
+        let remote = match remote {
+            Ok(output) if output.success => Check::pass("gitvault remote", &self.config.remote),
+            Ok(output) => Check::fail(
+                "gitvault remote",
+                format!(
+                    "Could not reach {}: {}",
+                    self.config.remote,
+                    output.stderr.trim()
+                ),
+                Some("Check the remote URL and your git credentials".to_string()),
+            ),
+            Err(e) => Check::from_error("gitvault remote", &e),
+        };
+        vec![cli, remote]
+    }
+}
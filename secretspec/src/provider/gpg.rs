@@ -0,0 +1,338 @@
+//! GPG-encrypted files, one per secret.
+//!
+//! A `gpg://` provider keeps every secret in its own file, encrypted with
+//! GPG to the keys of the people who may read it:
+//!
+//! ```text
+//! gpg://.secrets?recipient=alice@example.com&recipient=0xA1B2C3D4E5F60718
+//! ```
+//!
+//! ```text
+//! .secrets/
+//!   my-app/
+//!     default/
+//!       API_KEY.gpg
+//!     production/
+//!       .gpg-id
+//!       API_KEY.gpg
+//! ```
+//!
+//! As with `pass`, a `.gpg-id` file lists the recipients, one key ID or
+//! email per line. The nearest one wins, looking in the profile's directory,
+//! then the project's and then the store's, so each profile can be shared
+//! with a different group. The `recipient` parameters are used where no
+//! `.gpg-id` exists. Decryption goes through `gpg` and its agent, so the keys
+//! never pass through secretspec.
+
+use super::Provider;
+use super::command::{CommandRunner, Invocation, SystemRunner};
+use crate::doctor::{self, Check};
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+use zeroize::Zeroizing;
+
+/// File listing the recipients of a directory.
+const RECIPIENTS_FILE: &str = ".gpg-id";
+
+/// Extension of encrypted secret files.
+const EXTENSION: &str = "gpg";
+
+/// Configuration for the gpg provider.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpgConfig {
+    /// Directory the encrypted files are stored in
+    pub path: PathBuf,
+    /// Keys values are encrypted to where no `.gpg-id` file lists them
+    pub recipients: Vec<String>,
+}
+
+impl TryFrom<&Url> for GpgConfig {
+    type Error = SecretSpecError;
+
+    /// Creates a GpgConfig from a URL.
+    ///
+    /// # URL Formats
+    ///
+    /// - `gpg:///absolute/path` - Absolute path
+    /// - `gpg://.secrets` - Relative path (authority as directory)
+    /// - `gpg://.secrets?recipient=alice@example.com` - With a default recipient
+    fn try_from(url: &Url) -> std::result::Result<Self, Self::Error> {
+        if url.scheme() != "gpg" {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Invalid scheme '{}' for gpg provider",
+                url.scheme()
+            )));
+        }
+
+        let path = match url.host_str() {
+            Some(host) => format!("{}{}", host, url.path()),
+            None => url.path().to_string(),
+        };
+        let path = path.trim_end_matches('/');
+        if path.is_empty() {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "gpg provider requires a directory, e.g. gpg://.secrets".to_string(),
+            ));
+        }
+
+        let recipients = url
+            .query_pairs()
+            .filter(|(key, _)| key == "recipient")
+            .map(|(_, value)| value.into_owned())
+            .collect();
+        Ok(Self {
+            path: PathBuf::from(path),
+            recipients,
+        })
+    }
+}
+
+/// Provider storing each secret in a GPG-encrypted file.
+pub struct GpgProvider {
+    config: GpgConfig,
+    /// Runs the `gpg` CLI
+    runner: Arc<dyn CommandRunner>,
+}
+
+crate::register_provider! {
+    struct: GpgProvider,
+    config: GpgConfig,
+    name: "gpg",
+    description: "GPG-encrypted files, one per secret",
+    schemes: ["gpg"],
+    examples: ["gpg://.secrets?recipient=alice@example.com"],
+    params: |_| &["recipient"],
+}
+
+impl GpgProvider {
+    /// Creates a new GpgProvider with the given configuration.
+    pub fn new(config: GpgConfig) -> Self {
+        Self::with_runner(config, Arc::new(SystemRunner))
+    }
+
+    /// Creates a GpgProvider that runs the `gpg` CLI through `runner`.
+    pub(crate) fn with_runner(config: GpgConfig, runner: Arc<dyn CommandRunner>) -> Self {
+        Self { config, runner }
+    }
+
+    /// Runs gpg and returns its standard output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if gpg isn't installed or fails
+    fn gpg(&self, invocation: Invocation) -> Result<Zeroizing<String>> {
+        let output = match self.runner.run(&invocation) {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(SecretSpecError::CliMissing {
+                    cli: "GnuPG (gpg)".to_string(),
+                    install_hint: "To install it:\n  - macOS: brew install gnupg\n  - Linux: Install the 'gnupg' package\n  - Windows: winget install GnuPG.Gpg4win\n  - NixOS: nix-env -iA nixpkgs.gnupg".to_string(),
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let stdout = output.stdout;
+        if !output.success {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "gpg failed: {}",
+                output.stderr.trim()
+            )));
+        }
+        Ok(stdout)
+    }
+
+    /// Returns the directory of a profile's files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project or profile can't be used as a path
+    /// component
+    fn profile_dir(&self, project: &str, profile: &str) -> Result<PathBuf> {
+        check_component(project)?;
+        check_component(profile)?;
+        Ok(self.config.path.join(project).join(profile))
+    }
+
+    /// Returns the file a secret is stored in.
+    fn secret_path(&self, project: &str, key: &str, profile: &str) -> Result<PathBuf> {
+        check_component(key)?;
+        Ok(self
+            .profile_dir(project, profile)?
+            .join(format!("{}.{}", key, EXTENSION)))
+    }
+
+    /// Returns the keys a profile's values are encrypted to: those in the
+    /// nearest `.gpg-id` file, or the `recipient` parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither lists a recipient
+    fn recipients(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let dir = self.profile_dir(project, profile)?;
+        for dir in [
+            dir.as_path(),
+            &self.config.path.join(project),
+            &self.config.path,
+        ] {
+            let file = dir.join(RECIPIENTS_FILE);
+            if file.is_file() {
+                let recipients = parse_recipients(&fs::read_to_string(&file)?);
+                if recipients.is_empty() {
+                    return Err(SecretSpecError::ProviderOperationFailed(format!(
+                        "{} lists no recipients",
+                        file.display()
+                    )));
+                }
+                return Ok(recipients);
+            }
+        }
+        if self.config.recipients.is_empty() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "No GPG recipients for {}/{}. Add a {} file listing their key IDs to {}, or ?recipient=<key> to the provider URI",
+                project,
+                profile,
+                RECIPIENTS_FILE,
+                dir.display()
+            )));
+        }
+        Ok(self.config.recipients.clone())
+    }
+}
+
+/// Checks that a project, profile or key can be used as a file name.
+fn check_component(part: &str) -> Result<()> {
+    if part.is_empty() || part.starts_with('.') || part.contains(['/', '\\']) {
+        return Err(SecretSpecError::InvalidInput(format!(
+            "'{}' can't be used as a gpg path component",
+            part
+        )));
+    }
+    Ok(())
+}
+
+/// Parses a `.gpg-id` file: one recipient per line, ignoring blank lines
+/// and `#` comments.
+fn parse_recipients(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Provider for GpgProvider {
+    fn name(&self) -> &'static str {
+        Self::PROVIDER_NAME
+    }
+
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        let path = self.secret_path(project, key, profile)?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let path = path.to_string_lossy();
+        let value = self.gpg(Invocation::new("gpg").args([
+            "--batch",
+            "--quiet",
+            "--decrypt",
+            path.as_ref(),
+        ]))?;
+        Ok(Some(SecretString::from(value.as_str())))
+    }
+
+    /// Encrypts the value to the profile's recipients and replaces the
+    /// secret's file with it.
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        let path = self.secret_path(project, key, profile)?;
+        let mut invocation =
+            Invocation::new("gpg").args(["--batch", "--yes", "--armor", "--encrypt"]);
+        for recipient in self.recipients(project, profile)? {
+            invocation = invocation.args(["--recipient", recipient.as_str()]);
+        }
+        let ciphertext = self.gpg(invocation.stdin(value.expose_secret().as_bytes()))?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir)?;
+        // Staged and renamed, so readers never see a partial file
+        let mut staged = tempfile::NamedTempFile::new_in(dir)?;
+        staged.write_all(ciphertext.as_bytes())?;
+        staged
+            .persist(&path)
+            .map_err(|e| SecretSpecError::Io(e.error))?;
+        Ok(())
+    }
+
+    /// Checks that the profile has recipients and its directory can be
+    /// created.
+    fn check_writable(&self, project: &str, profile: &str) -> Result<()> {
+        self.recipients(project, profile)?;
+        let dir = self.profile_dir(project, profile)?;
+        fs::create_dir_all(&dir).map_err(|e| {
+            SecretSpecError::PermissionDenied(format!("cannot create {}: {}", dir.display(), e))
+        })
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        match fs::remove_file(self.secret_path(project, key, profile)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn allows_delete(&self) -> bool {
+        true
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let dir = self.profile_dir(project, profile)?;
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension == EXTENSION)
+                && let Some(key) = path.file_stem().and_then(|stem| stem.to_str())
+            {
+                keys.push(key.to_string());
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn allows_list(&self) -> bool {
+        true
+    }
+
+    fn doctor(&self) -> Vec<Check> {
+        let version = self
+            .gpg(Invocation::new("gpg").args(["--version"]))
+            .map(|output| output.to_string());
+        vec![doctor::check_cli("GnuPG CLI", version)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipients() {
+        assert_eq!(
+            parse_recipients("# platform team\nalice@example.com\n\n0xA1B2C3D4 # bob\n"),
+            ["alice@example.com", "0xA1B2C3D4"]
+        );
+        assert!(parse_recipients("# nobody yet\n").is_empty());
+    }
+}
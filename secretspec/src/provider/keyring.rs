@@ -1,18 +1,49 @@
 use super::Provider;
+use crate::doctor::Check;
 use crate::{Result, SecretSpecError};
 use keyring::Entry;
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+#[cfg(target_os = "macos")]
+mod macos;
+
+/// Maximum size in bytes of a Windows Credential Manager credential blob.
+const WINDOWS_CREDENTIAL_BLOB_LIMIT: usize = 2560;
+
 /// Configuration for the keyring provider.
 ///
 /// This struct holds configuration options for the keyring provider,
 /// which stores secrets in the system's native keychain service.
-/// Currently, no additional configuration is required as the provider
-/// uses sensible defaults for all platforms.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
-pub struct KeyringConfig {}
+/// The keychain item options only apply on macOS and are ignored on other
+/// platforms, so a project can share one provider URL across platforms.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyringConfig {
+    /// macOS keychain to store items in: `login`, `system` or a file path
+    pub keychain: Option<String>,
+    /// Who may read stored items on macOS
+    #[serde(default)]
+    pub access: KeychainAccess,
+    /// Whether items sync to other devices through iCloud Keychain on macOS
+    #[serde(default)]
+    pub synchronizable: bool,
+}
+
+/// Access policy for macOS keychain items.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeychainAccess {
+    /// The keychain's default access: the app that created an item reads
+    /// it without asking, and other apps prompt
+    #[default]
+    Default,
+    /// The item's access control list trusts only the secretspec binary, so
+    /// every other app, including the one that created the item, prompts
+    AppOnly,
+    /// Every read requires Touch ID or the account password
+    UserPresence,
+}
 
 impl TryFrom<&Url> for KeyringConfig {
     type Error = SecretSpecError;
@@ -20,14 +51,18 @@ impl TryFrom<&Url> for KeyringConfig {
     /// Creates a new KeyringConfig from a URL.
     ///
     /// The URL must have the scheme "keyring" (e.g., "keyring://").
-    /// Currently, no additional parameters are supported in the URL.
+    /// The following query parameters are supported:
+    /// - `keychain`: `login` (default), `system` or a path to a keychain file
+    /// - `access`: `app-only` or `user-presence`, otherwise items get the
+    ///   keychain's default access
+    /// - `synchronizable`: `true` or `false` (default)
     ///
     /// # Examples
     ///
     /// ```ignore
     /// # use url::Url;
     /// # use secretspec::provider::keyring::KeyringConfig;
-    /// let url = Url::parse("keyring://").unwrap();
+    /// let url = Url::parse("keyring://?keychain=login&access=app-only&synchronizable=false").unwrap();
     /// let config: KeyringConfig = (&url).try_into().unwrap();
     /// ```
     fn try_from(url: &Url) -> std::result::Result<Self, Self::Error> {
@@ -38,11 +73,70 @@ impl TryFrom<&Url> for KeyringConfig {
             )));
         }
 
-        Ok(Self::default())
+        let mut config = Self::default();
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "keychain" => config.keychain = Some(value.into_owned()),
+                "access" => {
+                    config.access = match value.as_ref() {
+                        "app-only" => KeychainAccess::AppOnly,
+                        "user-presence" => KeychainAccess::UserPresence,
+                        other => {
+                            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                                "Invalid keyring access '{}'. Expected 'app-only' or 'user-presence'",
+                                other
+                            )));
+                        }
+                    }
+                }
+                "synchronizable" => {
+                    config.synchronizable = value.parse().map_err(|_| {
+                        SecretSpecError::ProviderOperationFailed(format!(
+                            "Invalid keyring synchronizable '{}'. Expected 'true' or 'false'",
+                            value
+                        ))
+                    })?
+                }
+                _ => {} // Ignore unknown parameters
+            }
+        }
+
+        if config.keychain.is_some() && config.uses_data_protection_keychain() {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "The keyring 'keychain' option cannot be combined with 'synchronizable=true' or 'access=user-presence', which use the iCloud-capable data protection keychain".to_string(),
+            ));
+        }
+        if config.synchronizable && config.access == KeychainAccess::AppOnly {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "Keyring access 'app-only' sets an access control list, which only file-based keychains support; it cannot be combined with 'synchronizable=true'".to_string(),
+            ));
+        }
+        if config.synchronizable && config.access == KeychainAccess::UserPresence {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "Keyring items that require user presence cannot be synchronized to other devices"
+                    .to_string(),
+            ));
+        }
+
+        Ok(config)
     }
 }
 
-impl KeyringConfig {}
+impl KeyringConfig {
+    /// Returns whether items live in the data protection keychain.
+    ///
+    /// Only the data protection keychain supports iCloud sync and access
+    /// control policies; everything else uses the file-based keychains.
+    pub fn uses_data_protection_keychain(&self) -> bool {
+        self.synchronizable || self.access == KeychainAccess::UserPresence
+    }
+
+    /// Returns whether any keychain item option differs from the default.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn has_keychain_options(&self) -> bool {
+        self != &Self::default()
+    }
+}
 
 /// Provider for storing secrets in the system keychain.
 ///
@@ -58,8 +152,10 @@ impl KeyringConfig {}
 /// This ensures secrets are properly namespaced by project and profile,
 /// preventing conflicts between different projects or environments.
 pub struct KeyringProvider {
-    #[allow(dead_code)]
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
     config: KeyringConfig,
+    /// Account name entries are stored under; defaults to the current user
+    account: String,
 }
 
 crate::register_provider! {
@@ -68,7 +164,8 @@ crate::register_provider! {
     name: "keyring",
     description: "Uses system keychain (Recommended)",
     schemes: ["keyring"],
-    examples: ["keyring://"],
+    examples: ["keyring://", "keyring://?keychain=login&synchronizable=false"],
+    params: |_| &["keychain", "access", "synchronizable"],
 }
 
 impl KeyringProvider {
@@ -82,7 +179,42 @@ impl KeyringProvider {
     ///
     /// A new instance of KeyringProvider
     pub fn new(config: KeyringConfig) -> Self {
-        Self { config }
+        Self::with_account(config, current_account())
+    }
+
+    /// Creates a KeyringProvider that stores entries under a specific account.
+    pub(crate) fn with_account(config: KeyringConfig, account: impl Into<String>) -> Self {
+        Self {
+            config,
+            account: account.into(),
+        }
+    }
+
+    /// Opens the keychain entry for a secret.
+    ///
+    /// On Windows the credential's target name is the service path itself
+    /// rather than the default `{account}.{service}`, so entries are easy to
+    /// find in Credential Manager and do not depend on how the user name is
+    /// encoded.
+    fn entry(&self, service: &str) -> keyring::Result<Entry> {
+        #[cfg(windows)]
+        return Entry::new_with_target(service, service, &self.account);
+        #[cfg(not(windows))]
+        Entry::new(service, &self.account)
+    }
+}
+
+/// Returns the account name for the current user.
+///
+/// User names that are not valid Unicode would be stored under a lossy
+/// conversion that differs between processes, so they fall back to a fixed
+/// account name instead.
+fn current_account() -> String {
+    let username = whoami::username();
+    if username.is_empty() || username.contains(char::REPLACEMENT_CHARACTER) {
+        "secretspec".to_string()
+    } else {
+        username
     }
 }
 
@@ -91,12 +223,24 @@ impl Provider for KeyringProvider {
         Self::PROVIDER_NAME
     }
 
+    /// Returns the portable size limit for keychain entries.
+    ///
+    /// Windows Credential Manager rejects blobs larger than 2560 bytes and
+    /// stores passwords as UTF-16, which takes at most two bytes per byte of
+    /// UTF-8. Values are chunked at half the blob limit on every platform so
+    /// that stored secrets behave the same wherever the project is used.
+    fn max_value_size(&self) -> Option<usize> {
+        Some(WINDOWS_CREDENTIAL_BLOB_LIMIT / 2)
+    }
+
     /// Retrieves a secret from the system keychain.
     ///
     /// The secret is looked up using a hierarchical key structure:
     /// `secretspec/{project}/{profile}/{key}`
     ///
     /// The current system username is used as the account identifier.
+    /// Entries written before Windows credentials were addressed by their
+    /// service path are still found under their old target name.
     ///
     /// # Arguments
     ///
@@ -112,8 +256,16 @@ impl Provider for KeyringProvider {
     fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
         let service = format!("secretspec/{}/{}/{}", project, profile, key);
 
-        let entry = Entry::new(&service, &whoami::username())?;
-        match entry.get_password() {
+        #[cfg(target_os = "macos")]
+        if self.config.has_keychain_options() {
+            return macos::get(&self.config, &service, &self.account);
+        }
+
+        let mut result = self.entry(&service)?.get_password();
+        if cfg!(windows) && matches!(result, Err(keyring::Error::NoEntry)) {
+            result = Entry::new(&service, &self.account)?.get_password();
+        }
+        match result {
             Ok(password) => Ok(Some(SecretString::new(password.into()))),
             Err(keyring::Error::NoEntry) => Ok(None),
             Err(e) => Err(e.into()),
@@ -142,8 +294,62 @@ impl Provider for KeyringProvider {
     fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
         let service = format!("secretspec/{}/{}/{}", project, profile, key);
 
-        let entry = Entry::new(&service, &whoami::username())?;
-        entry.set_password(value.expose_secret())?;
+        #[cfg(target_os = "macos")]
+        if self.config.has_keychain_options() {
+            return macos::set(&self.config, &service, &self.account, value);
+        }
+
+        self.entry(&service)?.set_password(value.expose_secret())?;
         Ok(())
     }
+
+    /// Removes a secret from the system keychain.
+    ///
+    /// On Windows, entries stored under the old target name are removed too.
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let service = format!("secretspec/{}/{}/{}", project, profile, key);
+
+        #[cfg(target_os = "macos")]
+        if self.config.has_keychain_options() {
+            return macos::delete(&self.config, &service, &self.account);
+        }
+
+        let mut entries = vec![self.entry(&service)?];
+        if cfg!(windows) {
+            entries.push(Entry::new(&service, &self.account)?);
+        }
+        for entry in entries {
+            match entry.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn allows_delete(&self) -> bool {
+        true
+    }
+
+    /// Checks that the system keychain can be queried.
+    ///
+    /// Looks up an entry that never exists, so nothing is read or written.
+    fn doctor(&self) -> Vec<Check> {
+        const NAME: &str = "System keyring";
+
+        let probe = self
+            .entry("secretspec/doctor")
+            .and_then(|entry| entry.get_password());
+        match probe {
+            Ok(_) | Err(keyring::Error::NoEntry) => vec![Check::pass(NAME, "Available")],
+            Err(e) => vec![Check::fail(
+                NAME,
+                format!("Keyring is not available: {}", e),
+                Some(
+                    "On Linux, make sure a Secret Service provider such as gnome-keyring or KWallet is running and unlocked"
+                        .to_string(),
+                ),
+            )],
+        }
+    }
 }
@@ -0,0 +1,270 @@
+//! Keychain Services access for keychain item options on macOS
+//!
+//! The keyring crate stores items with the library's default attributes. When
+//! the provider URL selects a keychain, enables iCloud Keychain sync or asks
+//! for user presence, items are read and written through the SecItem API here
+//! so that those attributes are applied.
+//!
+//! `access=app-only` gives items in file-based keychains an access control
+//! list created with `SecAccessCreate`, whose only trusted application is the
+//! running secretspec binary. The security-framework crates don't bind those
+//! functions, so they are declared here.
+
+use super::{KeychainAccess, KeyringConfig};
+use crate::{Result, SecretSpecError};
+use core_foundation::array::{CFArray, CFArrayRef};
+use core_foundation::base::{CFType, CFTypeRef, OSStatus, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::data::CFData;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::string::{CFString, CFStringRef};
+use secrecy::{ExposeSecret, SecretString};
+use security_framework::access_control::{ProtectionMode, SecAccessControl};
+use security_framework::os::macos::access::SecAccess;
+use security_framework::os::macos::keychain::{SecKeychain, SecPreferencesDomain};
+use security_framework_sys::access_control::kSecAccessControlUserPresence;
+use security_framework_sys::base::{SecAccessRef, errSecItemNotFound, errSecSuccess};
+use security_framework_sys::item::{
+    kSecAttrAccessControl, kSecAttrAccount, kSecAttrService, kSecAttrSynchronizable, kSecClass,
+    kSecClassGenericPassword, kSecMatchSearchList, kSecReturnData, kSecUseDataProtectionKeychain,
+    kSecUseKeychain, kSecValueData,
+};
+use security_framework_sys::keychain_item::{
+    SecItemAdd, SecItemCopyMatching, SecItemDelete, SecItemUpdate,
+};
+use std::ffi::{c_char, c_void};
+use std::ptr;
+
+/// A trusted application entry of an access control list.
+type SecTrustedApplicationRef = *mut c_void;
+
+#[link(name = "Security", kind = "framework")]
+unsafe extern "C" {
+    /// Attribute holding the access control list of a file keychain item.
+    static kSecAttrAccess: CFStringRef;
+
+    /// Creates a trusted application entry; a null path means the caller.
+    fn SecTrustedApplicationCreateFromPath(
+        path: *const c_char,
+        app: *mut SecTrustedApplicationRef,
+    ) -> OSStatus;
+
+    /// Creates an access control list that lets the trusted applications
+    /// read the item without prompting.
+    fn SecAccessCreate(
+        descriptor: CFStringRef,
+        trusted_list: CFArrayRef,
+        access: *mut SecAccessRef,
+    ) -> OSStatus;
+}
+
+/// Path of the system keychain.
+const SYSTEM_KEYCHAIN: &str = "/Library/Keychains/System.keychain";
+
+/// Returned when the binary lacks the entitlements for the data protection keychain.
+const ERR_SEC_MISSING_ENTITLEMENT: OSStatus = -34018;
+
+/// Whether a query searches for existing items or adds a new one.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Purpose {
+    Search,
+    Add,
+}
+
+/// Reads an item, returning `None` if it does not exist.
+pub(super) fn get(
+    config: &KeyringConfig,
+    service: &str,
+    account: &str,
+) -> Result<Option<SecretString>> {
+    let mut query = item_query(config, service, account, Purpose::Search)?;
+    query.push((
+        key(unsafe { kSecReturnData }),
+        CFBoolean::true_value().into_CFType(),
+    ));
+    let query = CFDictionary::from_CFType_pairs(&query);
+
+    let mut result: CFTypeRef = ptr::null();
+    let status = unsafe { SecItemCopyMatching(query.as_concrete_TypeRef(), &mut result) };
+    if status == errSecItemNotFound {
+        return Ok(None);
+    }
+    check(status)?;
+
+    let data = unsafe { CFData::wrap_under_create_rule(result as _) };
+    let value = String::from_utf8(data.bytes().to_vec()).map_err(|_| {
+        SecretSpecError::ProviderOperationFailed(format!(
+            "Keychain item {} is not valid UTF-8",
+            service
+        ))
+    })?;
+    Ok(Some(SecretString::new(value.into())))
+}
+
+/// Creates or updates an item with the configured attributes.
+pub(super) fn set(
+    config: &KeyringConfig,
+    service: &str,
+    account: &str,
+    value: &SecretString,
+) -> Result<()> {
+    let data = CFData::from_buffer(value.expose_secret().as_bytes()).into_CFType();
+
+    let query =
+        CFDictionary::from_CFType_pairs(&item_query(config, service, account, Purpose::Search)?);
+    let update = CFDictionary::from_CFType_pairs(&[(key(unsafe { kSecValueData }), data.clone())]);
+    let status =
+        unsafe { SecItemUpdate(query.as_concrete_TypeRef(), update.as_concrete_TypeRef()) };
+    if status != errSecItemNotFound {
+        return check(status);
+    }
+
+    let mut attributes = item_query(config, service, account, Purpose::Add)?;
+    attributes.push((key(unsafe { kSecValueData }), data));
+    if config.access == KeychainAccess::UserPresence {
+        let access_control = SecAccessControl::create_with_protection(
+            Some(ProtectionMode::AccessibleWhenUnlockedThisDeviceOnly),
+            kSecAccessControlUserPresence,
+        )
+        .map_err(|e| {
+            SecretSpecError::ProviderOperationFailed(format!(
+                "Failed to create keychain access control: {}",
+                e
+            ))
+        })?;
+        attributes.push((
+            key(unsafe { kSecAttrAccessControl }),
+            access_control.into_CFType(),
+        ));
+    }
+    if config.access == KeychainAccess::AppOnly {
+        attributes.push((
+            key(unsafe { kSecAttrAccess }),
+            app_only_access(service)?.into_CFType(),
+        ));
+    }
+    let attributes = CFDictionary::from_CFType_pairs(&attributes);
+    check(unsafe { SecItemAdd(attributes.as_concrete_TypeRef(), ptr::null_mut()) })
+}
+
+/// Creates an access control list trusting only the running binary.
+///
+/// The descriptor is the name macOS shows when another app asks for the item.
+fn app_only_access(service: &str) -> Result<SecAccess> {
+    let mut app: SecTrustedApplicationRef = ptr::null_mut();
+    check(unsafe { SecTrustedApplicationCreateFromPath(ptr::null(), &mut app) })?;
+    let app = unsafe { CFType::wrap_under_create_rule(app as CFTypeRef) };
+    let trusted = CFArray::from_CFTypes(&[app]);
+
+    let descriptor = CFString::new(service);
+    let mut access: SecAccessRef = ptr::null_mut();
+    check(unsafe {
+        SecAccessCreate(
+            descriptor.as_concrete_TypeRef(),
+            trusted.as_concrete_TypeRef(),
+            &mut access,
+        )
+    })?;
+    Ok(unsafe { SecAccess::wrap_under_create_rule(access) })
+}
+
+/// Deletes an item, succeeding if it does not exist.
+pub(super) fn delete(config: &KeyringConfig, service: &str, account: &str) -> Result<()> {
+    let query =
+        CFDictionary::from_CFType_pairs(&item_query(config, service, account, Purpose::Search)?);
+    let status = unsafe { SecItemDelete(query.as_concrete_TypeRef()) };
+    if status == errSecItemNotFound {
+        Ok(())
+    } else {
+        check(status)
+    }
+}
+
+/// Builds the attributes identifying an item and the keychain it lives in.
+///
+/// File keychains are selected with `kSecUseKeychain` when adding and with a
+/// search list when looking items up.
+fn item_query(
+    config: &KeyringConfig,
+    service: &str,
+    account: &str,
+    purpose: Purpose,
+) -> Result<Vec<(CFString, CFType)>> {
+    let mut query = vec![
+        (
+            key(unsafe { kSecClass }),
+            key(unsafe { kSecClassGenericPassword }).into_CFType(),
+        ),
+        (
+            key(unsafe { kSecAttrService }),
+            CFString::new(service).into_CFType(),
+        ),
+        (
+            key(unsafe { kSecAttrAccount }),
+            CFString::new(account).into_CFType(),
+        ),
+    ];
+
+    if config.uses_data_protection_keychain() {
+        query.push((
+            key(unsafe { kSecUseDataProtectionKeychain }),
+            CFBoolean::true_value().into_CFType(),
+        ));
+        query.push((
+            key(unsafe { kSecAttrSynchronizable }),
+            CFBoolean::from(config.synchronizable).into_CFType(),
+        ));
+    } else if let Some(name) = &config.keychain {
+        let keychain = open_keychain(name)?;
+        match purpose {
+            Purpose::Add => query.push((key(unsafe { kSecUseKeychain }), keychain.into_CFType())),
+            Purpose::Search => query.push((
+                key(unsafe { kSecMatchSearchList }),
+                CFArray::from_CFTypes(&[keychain]).into_CFType(),
+            )),
+        }
+    }
+
+    Ok(query)
+}
+
+/// Opens the keychain named by the `keychain` URL option.
+fn open_keychain(name: &str) -> Result<SecKeychain> {
+    let keychain = match name {
+        "login" => SecKeychain::default_for_domain(SecPreferencesDomain::User),
+        "system" => SecKeychain::open(SYSTEM_KEYCHAIN),
+        path => SecKeychain::open(path),
+    };
+    keychain.map_err(|e| {
+        SecretSpecError::ProviderOperationFailed(format!(
+            "Failed to open keychain '{}': {}",
+            name, e
+        ))
+    })
+}
+
+/// Wraps a Core Foundation string constant.
+fn key(name: CFStringRef) -> CFString {
+    unsafe { CFString::wrap_under_get_rule(name) }
+}
+
+/// Converts a Keychain Services status into a result.
+fn check(status: OSStatus) -> Result<()> {
+    if status == errSecSuccess {
+        Ok(())
+    } else {
+        Err(keychain_error(status))
+    }
+}
+
+/// Describes a failed Keychain Services call.
+fn keychain_error(status: OSStatus) -> SecretSpecError {
+    if status == ERR_SEC_MISSING_ENTITLEMENT {
+        return SecretSpecError::ProviderOperationFailed(
+            "This secretspec binary is not entitled to use the data protection keychain.\n\nThe 'synchronizable=true' and 'access=user-presence' keyring options require a signed build of secretspec; remove them to use the login keychain."
+                .to_string(),
+        );
+    }
+    let message = security_framework::base::Error::from(status).to_string();
+    SecretSpecError::ProviderOperationFailed(format!("Keychain error: {}", message))
+}
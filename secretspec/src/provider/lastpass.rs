@@ -1,15 +1,21 @@
+use crate::doctor::{self, Check, Status};
 use crate::provider::Provider;
+use crate::provider::command::{CommandRunner, Invocation, SystemRunner};
 use crate::{Result, SecretSpecError};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::io::Write;
-use std::process::{Command, Stdio};
+use std::sync::Arc;
 use url::Url;
+use zeroize::Zeroizing;
+
+#[cfg(feature = "lastpass-api")]
+mod api;
 
 /// Configuration for the LastPass provider.
 ///
 /// This struct contains the configuration options for interacting with LastPass
-/// through the `lpass` CLI tool.
+/// through the `lpass` CLI tool, or directly with the LastPass API when built
+/// with the `lastpass-api` feature.
 ///
 /// # Examples
 ///
@@ -22,6 +28,7 @@ use url::Url;
 /// // Create a configuration with a folder prefix
 /// let config = LastPassConfig {
 ///     folder_prefix: Some("my-company".to_string()),
+///     ..Default::default()
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,13 +38,24 @@ pub struct LastPassConfig {
     /// Supports placeholders: {project}, {profile}, and {key}.
     /// Defaults to "secretspec/{project}/{profile}/{key}" if not specified.
     pub folder_prefix: Option<String>,
+    /// Whether to talk to the LastPass API directly instead of running `lpass`.
+    ///
+    /// Set by the `lastpass+api` scheme.
+    #[serde(default)]
+    pub api: bool,
+    /// Email address to log in to the API with.
+    ///
+    /// Falls back to `LASTPASS_USERNAME` if not set.
+    pub username: Option<String>,
 }
 
 impl Default for LastPassConfig {
-    /// Creates a default LastPassConfig with no folder prefix.
+    /// Creates a default LastPassConfig with no folder prefix, using `lpass`.
     fn default() -> Self {
         Self {
             folder_prefix: None,
+            api: false,
+            username: None,
         }
     }
 }
@@ -49,7 +67,9 @@ impl TryFrom<&Url> for LastPassConfig {
     ///
     /// Parses a URL in the format `lastpass://[folder]` where the folder
     /// component is optional. The folder can be specified either as the
-    /// authority or the path component of the URL.
+    /// authority or the path component of the URL. The `lastpass+api`
+    /// scheme talks to the API directly and takes the login email as the
+    /// `username` query parameter.
     ///
     /// # Arguments
     ///
@@ -58,7 +78,8 @@ impl TryFrom<&Url> for LastPassConfig {
     /// # Returns
     ///
     /// Returns a `Result` containing the parsed configuration or an error
-    /// if the URL scheme is not `lastpass`.
+    /// if the URL scheme is not `lastpass` or `lastpass+api`, or the API is
+    /// asked for without the `lastpass-api` feature.
     ///
     /// # Examples
     ///
@@ -79,14 +100,31 @@ impl TryFrom<&Url> for LastPassConfig {
     /// let config: LastPassConfig = (&url).try_into().unwrap();
     /// ```
     fn try_from(url: &Url) -> std::result::Result<Self, Self::Error> {
-        if url.scheme() != "lastpass" {
-            return Err(SecretSpecError::ProviderOperationFailed(format!(
-                "Invalid scheme '{}' for lastpass provider",
-                url.scheme()
-            )));
+        let api = match url.scheme() {
+            "lastpass" => false,
+            "lastpass+api" => true,
+            scheme => {
+                return Err(SecretSpecError::ProviderOperationFailed(format!(
+                    "Invalid scheme '{}' for lastpass provider",
+                    scheme
+                )));
+            }
+        };
+        if api && !cfg!(feature = "lastpass-api") {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "lastpass+api:// requires secretspec built with the 'lastpass-api' feature"
+                    .to_string(),
+            ));
         }
 
-        let mut config = Self::default();
+        let mut config = Self {
+            api,
+            username: url
+                .query_pairs()
+                .find(|(name, _)| name == "username")
+                .map(|(_, value)| value.into_owned()),
+            ..Self::default()
+        };
 
         if let Some(host) = url.host_str() {
             config.folder_prefix = Some(host.to_string() + url.path());
@@ -111,6 +149,9 @@ impl TryFrom<&Url> for LastPassConfig {
 ///
 /// After installation, authenticate with: `lpass login <your-email>`
 ///
+/// With the `lastpass-api` feature, `lastpass+api://` talks to the LastPass
+/// API directly instead, needing only curl.
+///
 /// # Examples
 ///
 /// ```ignore
@@ -128,6 +169,11 @@ impl TryFrom<&Url> for LastPassConfig {
 pub struct LastPassProvider {
     #[allow(dead_code)]
     config: LastPassConfig,
+    /// Runs the `lpass` CLI.
+    runner: Arc<dyn CommandRunner>,
+    /// Client of the LastPass API, used instead of `lpass` with `lastpass+api`
+    #[cfg(feature = "lastpass-api")]
+    api: api::Client,
 }
 
 crate::register_provider! {
@@ -135,8 +181,17 @@ crate::register_provider! {
     config: LastPassConfig,
     name: "lastpass",
     description: "LastPass password manager",
-    schemes: ["lastpass"],
-    examples: ["lastpass://", "lastpass://Shared-SecretSpec"],
+    schemes: ["lastpass", "lastpass+api"],
+    examples: ["lastpass://", "lastpass://Shared-SecretSpec", "lastpass+api://?username=alice@example.com"],
+    params: |scheme| if scheme == "lastpass+api" { &["username"] } else { &[] },
+}
+
+/// Returns the error for operations that need the user to log in first.
+fn login_required() -> SecretSpecError {
+    SecretSpecError::AuthRequired {
+        provider: "LastPass".to_string(),
+        fix: "Run 'lpass login <your-email>' first.".to_string(),
+    }
 }
 
 impl LastPassProvider {
@@ -146,7 +201,17 @@ impl LastPassProvider {
     ///
     /// * `config` - The LastPass configuration to use
     pub fn new(config: LastPassConfig) -> Self {
-        Self { config }
+        Self::with_runner(config, Arc::new(SystemRunner))
+    }
+
+    /// Creates a LastPassProvider that runs the `lpass` CLI through `runner`.
+    pub(crate) fn with_runner(config: LastPassConfig, runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            #[cfg(feature = "lastpass-api")]
+            api: api::Client::new(config.username.clone(), runner.clone()),
+            config,
+            runner,
+        }
     }
 
     /// Executes a LastPass CLI command and returns its output.
@@ -169,36 +234,36 @@ impl LastPassProvider {
     /// - Returns an error if the `lpass` CLI is not installed
     /// - Returns an error if the user is not logged in to LastPass
     /// - Returns an error if the command fails for any other reason
-    fn execute_lpass_command(&self, args: &[&str]) -> Result<String> {
-        let mut cmd = Command::new("lpass");
-        cmd.args(args);
+    fn execute_lpass_command(&self, args: &[&str]) -> Result<Zeroizing<String>> {
+        self.run_lpass(&Invocation::new("lpass").args(args))
+    }
 
-        let output = match cmd.output() {
+    /// Runs an `lpass` invocation, translating common failures into helpful errors.
+    fn run_lpass(&self, invocation: &Invocation) -> Result<Zeroizing<String>> {
+        let output = match self.runner.run(invocation) {
             Ok(output) => output,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "LastPass CLI (lpass) is not installed.\n\nTo install it:\n  - macOS: brew install lastpass-cli\n  - Linux: Check your package manager (apt install lastpass-cli, yum install lastpass-cli, etc.)\n  - NixOS: nix-env -iA nixpkgs.lastpass-cli\n\nAfter installation, run 'lpass login <your-email>' to authenticate.".to_string(),
-                ));
+                return Err(SecretSpecError::CliMissing {
+                    cli: "LastPass CLI (lpass)".to_string(),
+                    install_hint: "To install it:\n  - macOS: brew install lastpass-cli\n  - Linux: Check your package manager (apt install lastpass-cli, yum install lastpass-cli, etc.)\n  - NixOS: nix-env -iA nixpkgs.lastpass-cli\n\nAfter installation, run 'lpass login <your-email>' to authenticate.".to_string(),
+                });
             }
             Err(e) => return Err(e.into()),
         };
 
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            if error_msg.contains("Could not find decryption key")
-                || error_msg.contains("Not logged in")
+        if !output.success {
+            let stderr = output.stderr;
+            if stderr.contains("Could not find decryption key") || stderr.contains("Not logged in")
             {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "LastPass authentication required. Please run 'lpass login' first.".to_string(),
-                ));
+                return Err(login_required());
             }
-            return Err(SecretSpecError::ProviderOperationFailed(
-                error_msg.to_string(),
-            ));
+            if stderr.contains("Could not find specified account") {
+                return Err(SecretSpecError::NotFound(stderr));
+            }
+            return Err(SecretSpecError::ProviderOperationFailed(stderr));
         }
 
-        String::from_utf8(output.stdout)
-            .map_err(|e| SecretSpecError::ProviderOperationFailed(e.to_string()))
+        Ok(output.stdout)
     }
 
     /// Formats the item name for storage in LastPass.
@@ -244,10 +309,7 @@ impl LastPassProvider {
     fn check_if_logged_in(&self) -> Result<()> {
         // Check if we're logged in first
         if !self.check_login_status()? {
-            return Err(SecretSpecError::ProviderOperationFailed(
-                "LastPass authentication required. Please run 'lpass login <your-email>' first."
-                    .to_string(),
-            ));
+            return Err(login_required());
         }
         Ok(())
     }
@@ -263,12 +325,7 @@ impl LastPassProvider {
     fn check_login_status(&self) -> Result<bool> {
         match self.execute_lpass_command(&["status"]) {
             Ok(output) => Ok(!output.contains("Not logged in")),
-            Err(SecretSpecError::ProviderOperationFailed(msg))
-                if msg.contains("Not logged in")
-                    || msg.contains("LastPass authentication required") =>
-            {
-                Ok(false)
-            }
+            Err(SecretSpecError::AuthRequired { .. }) => Ok(false),
             Err(e) => Err(e),
         }
     }
@@ -302,6 +359,11 @@ impl Provider for LastPassProvider {
     /// - Returns an error if not logged in to LastPass
     /// - Returns an error if the LastPass CLI fails
     fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        #[cfg(feature = "lastpass-api")]
+        if self.config.api {
+            return self.api.get(&self.format_item_name(project, key, profile));
+        }
+
         self.check_if_logged_in()?;
 
         let item_name = self.format_item_name(project, key, profile);
@@ -315,11 +377,7 @@ impl Provider for LastPassProvider {
                     Ok(Some(SecretString::new(password.to_string().into())))
                 }
             }
-            Err(SecretSpecError::ProviderOperationFailed(msg))
-                if msg.contains("Could not find specified account") =>
-            {
-                Ok(None)
-            }
+            Err(SecretSpecError::NotFound(_)) => Ok(None),
             Err(e) => Err(e),
         }
     }
@@ -353,76 +411,85 @@ impl Provider for LastPassProvider {
     /// GUI prompts. The secret value is passed via stdin to avoid exposing
     /// it in the process list.
     fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        #[cfg(feature = "lastpass-api")]
+        if self.config.api {
+            return self
+                .api
+                .set(&self.format_item_name(project, key, profile), value);
+        }
+
         self.check_if_logged_in()?;
 
         let item_name = self.format_item_name(project, key, profile);
 
-        // Check if item exists
-        if self.get(project, key, profile)?.is_some() {
-            // Update existing item
-            let args = vec![
-                "edit",
+        // Update existing items with `lpass edit`, create new ones with `lpass set`
+        let action = if self.get(project, key, profile)?.is_some() {
+            "edit"
+        } else {
+            "set"
+        };
+
+        let invocation = Invocation::new("lpass")
+            .args([
+                action,
                 "--sync=now",
                 &item_name,
                 "--password",
                 "--non-interactive",
-            ];
-
-            let mut cmd = Command::new("lpass");
-            cmd.args(&args);
-            cmd.env("LPASS_DISABLE_PINENTRY", "1");
+            ])
+            .env("LPASS_DISABLE_PINENTRY", "1")
+            .stdin(value.expose_secret().as_bytes());
+        self.run_lpass(&invocation)?;
 
-            let mut child = cmd
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()?;
+        Ok(())
+    }
 
-            if let Some(stdin) = child.stdin.as_mut() {
-                stdin.write_all(value.expose_secret().as_bytes())?;
-            }
+    /// Removes a secret from LastPass with `lpass rm`.
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        #[cfg(feature = "lastpass-api")]
+        if self.config.api {
+            return self
+                .api
+                .delete(&self.format_item_name(project, key, profile));
+        }
 
-            let output = child.wait_with_output()?;
-            if !output.status.success() {
-                let error_msg = String::from_utf8_lossy(&output.stderr);
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    error_msg.to_string(),
-                ));
-            }
-        } else {
-            // Create new item using lpass set
-            let args = vec![
-                "set",
-                "--sync=now",
-                &item_name,
-                "--password",
-                "--non-interactive",
-            ];
+        self.check_if_logged_in()?;
 
-            let mut cmd = Command::new("lpass");
-            cmd.args(&args);
-            cmd.env("LPASS_DISABLE_PINENTRY", "1");
+        let item_name = self.format_item_name(project, key, profile);
+        match self.execute_lpass_command(&["rm", "--sync=now", &item_name]) {
+            Ok(_) => Ok(()),
+            Err(SecretSpecError::NotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
 
-            let mut child = cmd
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()?;
+    fn allows_delete(&self) -> bool {
+        true
+    }
 
-            if let Some(stdin) = child.stdin.as_mut() {
-                stdin.write_all(value.expose_secret().as_bytes())?;
-            }
+    /// Checks that the `lpass` CLI is installed and logged in, or that the
+    /// API can be reached with `lastpass+api`.
+    fn doctor(&self) -> Vec<Check> {
+        #[cfg(feature = "lastpass-api")]
+        if self.config.api {
+            return self.api.doctor();
+        }
 
-            let output = child.wait_with_output()?;
-            if !output.status.success() {
-                let error_msg = String::from_utf8_lossy(&output.stderr);
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    error_msg.to_string(),
-                ));
-            }
+        let cli = doctor::check_cli("LastPass CLI", self.execute_lpass_command(&["--version"]));
+        if cli.status != Status::Pass {
+            return vec![cli];
         }
 
-        Ok(())
+        let auth = match self.check_login_status() {
+            Ok(true) => Check::pass("LastPass authentication", "Logged in"),
+            Ok(false) => Check::fail(
+                "LastPass authentication",
+                "Not logged in",
+                Some("Run 'lpass login <your-email>'".to_string()),
+            ),
+            Err(e) => Check::from_error("LastPass authentication", &e),
+        };
+        vec![cli, auth]
     }
 }
 
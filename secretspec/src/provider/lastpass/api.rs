@@ -0,0 +1,676 @@
+//! Direct access to the LastPass API
+//!
+//! `lastpass+api://` talks to LastPass the way `lpass` does, for systems
+//! where the CLI is missing or broken. It logs in with the master password,
+//! downloads the encrypted vault and decrypts it locally with a key derived
+//! from the password, which never leaves the machine. Requests are made with
+//! curl, and credentials go to it on standard input.
+//!
+//! Only items in the user's own vault are read; items in shared folders are
+//! encrypted with keys of their own and skipped. Logins that need a push
+//! confirmation in the LastPass app aren't supported, but one-time codes
+//! are.
+
+use crate::doctor::{self, Check};
+use crate::prompt;
+use crate::provider::command::{self, CommandRunner, Invocation, quote};
+use crate::{Result, SecretSpecError};
+use aes::Aes256;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecrypt, BlockDecryptMut, BlockEncryptMut, KeyInit, KeyIvInit};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+use url::form_urlencoded;
+use zeroize::Zeroizing;
+
+/// Address of the LastPass API.
+const BASE_URL: &str = "https://lastpass.com";
+
+/// Login failures that are answered with a one-time code.
+const OTP_CAUSES: [&str; 4] = [
+    "googleauthrequired",
+    "microsoftauthrequired",
+    "otprequired",
+    "yubikeyrestricted",
+];
+
+/// An open session with the API.
+pub(super) struct Session {
+    /// The session cookie
+    id: String,
+    /// Token authorizing changes to the vault
+    token: String,
+    /// Key decrypting the vault, derived from the master password
+    key: Zeroizing<[u8; 32]>,
+}
+
+/// An item of the vault.
+struct Account {
+    /// The item's ID
+    id: String,
+    /// The folder the item is in, empty at the root
+    group: String,
+    /// The item's name
+    name: String,
+    /// The item's URL, hex encoded
+    url: String,
+    /// The item's username
+    username: Zeroizing<String>,
+    /// The item's password, which holds the secret
+    password: Zeroizing<String>,
+    /// The item's notes
+    notes: Zeroizing<String>,
+}
+
+impl Account {
+    /// Returns the name `lpass` shows for the item, like `folder/name`.
+    fn full_name(&self) -> String {
+        if self.group.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}/{}", self.group, self.name)
+        }
+    }
+}
+
+/// Client of the LastPass API, keeping its session for the process.
+pub(super) struct Client {
+    /// Email address to log in with
+    username: Option<String>,
+    /// Runs curl
+    runner: Arc<dyn CommandRunner>,
+    /// The session, opened on first use
+    session: Mutex<Option<Session>>,
+}
+
+impl Client {
+    /// Creates a client logging in as `username`, or `LASTPASS_USERNAME`
+    /// when not given.
+    pub(super) fn new(username: Option<String>, runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            username,
+            runner,
+            session: Mutex::new(None),
+        }
+    }
+
+    /// Returns the password of the item named `name`.
+    pub(super) fn get(&self, name: &str) -> Result<Option<SecretString>> {
+        let session = self.session()?;
+        let session = session.as_ref().expect("session was opened");
+        Ok(self
+            .accounts(session)?
+            .into_iter()
+            .find(|account| account.full_name() == name)
+            .map(|account| account.password.trim().to_string())
+            .filter(|password| !password.is_empty())
+            .map(|password| SecretString::new(password.into())))
+    }
+
+    /// Sets the password of the item named `name`, creating it if it
+    /// doesn't exist.
+    pub(super) fn set(&self, name: &str, value: &SecretString) -> Result<()> {
+        let session = self.session()?;
+        let session = session.as_ref().expect("session was opened");
+        let existing = self
+            .accounts(session)?
+            .into_iter()
+            .find(|account| account.full_name() == name);
+        let (group, item) = name.rsplit_once('/').unwrap_or(("", name));
+        let (id, url, username, notes) = match &existing {
+            Some(account) => (
+                account.id.as_str(),
+                account.url.as_str(),
+                account.username.as_str(),
+                account.notes.as_str(),
+            ),
+            None => ("0", "", "", ""),
+        };
+
+        let key = &session.key;
+        let form = [
+            ("extjs", "1"),
+            ("token", session.token.as_str()),
+            ("method", "cli"),
+            ("aid", id),
+            ("name", &encrypt(item, key)?),
+            ("grouping", &encrypt(group, key)?),
+            ("url", url),
+            ("username", &encrypt(username, key)?),
+            ("password", &encrypt(value.expose_secret(), key)?),
+            ("extra", &encrypt(notes, key)?),
+            ("pwprotect", "off"),
+        ];
+        let response = self.request("show_website.php", &form, Some(session))?;
+        check_result(&response)
+    }
+
+    /// Deletes the item named `name`, if it exists.
+    pub(super) fn delete(&self, name: &str) -> Result<()> {
+        let session = self.session()?;
+        let session = session.as_ref().expect("session was opened");
+        let Some(account) = self
+            .accounts(session)?
+            .into_iter()
+            .find(|account| account.full_name() == name)
+        else {
+            return Ok(());
+        };
+        let form = [
+            ("extjs", "1"),
+            ("token", session.token.as_str()),
+            ("delete", "1"),
+            ("aid", account.id.as_str()),
+        ];
+        let response = self.request("show_website.php", &form, Some(session))?;
+        check_result(&response)
+    }
+
+    /// Checks that curl is installed and a username is configured, without
+    /// logging in.
+    pub(super) fn doctor(&self) -> Vec<Check> {
+        let curl = doctor::check_cli(
+            "curl",
+            self.run(&Invocation::new("curl").args(["--version"])),
+        );
+        let username = match self.username() {
+            Ok(username) => Check::pass("LastPass username", &username),
+            Err(e) => Check::from_error("LastPass username", &e),
+        };
+        vec![curl, username]
+    }
+
+    /// Returns the email address to log in with.
+    fn username(&self) -> Result<String> {
+        self.username
+            .clone()
+            .or_else(|| std::env::var("LASTPASS_USERNAME").ok())
+            .map(|username| username.trim().to_lowercase())
+            .filter(|username| !username.is_empty())
+            .ok_or_else(|| {
+                SecretSpecError::InvalidInput(
+                    "No LastPass username; add ?username=<email> to the provider URI or set LASTPASS_USERNAME".to_string(),
+                )
+            })
+    }
+
+    /// Returns the session, logging in first if none is open.
+    fn session(&self) -> Result<MutexGuard<'_, Option<Session>>> {
+        let mut session = self.session.lock().expect("session lock poisoned");
+        if session.is_none() {
+            *session = Some(self.login()?);
+        }
+        Ok(session)
+    }
+
+    /// Logs in with the master password from `LASTPASS_PASSWORD` or a
+    /// prompt, and a one-time code from `LASTPASS_OTP` or a prompt if the
+    /// account asks for one.
+    fn login(&self) -> Result<Session> {
+        let username = self.username()?;
+        let iterations = self.request("iterations.php", &[("email", &username)], None)?;
+        let iterations: u32 = iterations.trim().parse().map_err(|_| {
+            SecretSpecError::ProviderOperationFailed(
+                "LastPass returned no key iteration count".to_string(),
+            )
+        })?;
+        let password = match std::env::var("LASTPASS_PASSWORD") {
+            Ok(password) => SecretString::new(password.into()),
+            Err(_) => prompt::password(&format!("LastPass master password for {}", username))?,
+        };
+
+        let key = derive_key(&username, password.expose_secret(), iterations);
+        let hash = login_hash(&key, password.expose_secret(), iterations);
+        let iterations = iterations.to_string();
+        let mut form = vec![
+            ("method", "cli"),
+            ("xml", "2"),
+            ("username", username.as_str()),
+            ("hash", hash.as_str()),
+            ("iterations", iterations.as_str()),
+        ];
+        let mut response = self.request("login.php", &form, None)?;
+
+        let otp;
+        if attribute(&response, "cause").is_some_and(|cause| OTP_CAUSES.contains(&cause.as_str())) {
+            otp = match std::env::var("LASTPASS_OTP") {
+                Ok(otp) => SecretString::new(otp.into()),
+                Err(_) => prompt::password("LastPass one-time code")?,
+            };
+            form.push(("otp", otp.expose_secret()));
+            response = self.request("login.php", &form, None)?;
+        }
+
+        match (
+            attribute(&response, "sessionid"),
+            attribute(&response, "token"),
+        ) {
+            (Some(id), Some(token)) => Ok(Session { id, token, key }),
+            _ => Err(login_error(&response)),
+        }
+    }
+
+    /// Downloads and decrypts the items of the vault.
+    fn accounts(&self, session: &Session) -> Result<Vec<Account>> {
+        let blob = self.request(
+            "getaccts.php?mobile=1&b64=1&hash=0.0&hasplugin=3.0.23&requestsrc=cli",
+            &[],
+            Some(session),
+        )?;
+        let blob = Zeroizing::new(STANDARD.decode(blob.trim()).map_err(|_| {
+            SecretSpecError::ProviderOperationFailed(
+                "LastPass returned no vault; the session may have expired".to_string(),
+            )
+        })?);
+        parse_accounts(&blob, &session.key)
+    }
+
+    /// Sends a request to the API and returns the response.
+    ///
+    /// The address, form and session cookie are passed to curl on standard
+    /// input, since they hold the login hash and session.
+    fn request(
+        &self,
+        path: &str,
+        form: &[(&str, &str)],
+        session: Option<&Session>,
+    ) -> Result<Zeroizing<String>> {
+        let mut config = Zeroizing::new(format!(
+            "url = {}\n",
+            quote(&format!("{}/{}", BASE_URL, path))
+        ));
+        if !form.is_empty() {
+            let data = Zeroizing::new(
+                form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(form)
+                    .finish(),
+            );
+            let _ = writeln!(config, "data = {}", quote(&data));
+        }
+        if let Some(session) = session {
+            let _ = writeln!(
+                config,
+                "header = {}",
+                quote(&format!("Cookie: PHPSESSID={}", session.id))
+            );
+        }
+        self.run(
+            &Invocation::new("curl")
+                .args(["--fail", "--silent", "--show-error", "--config", "-"])
+                .stdin(config.as_bytes()),
+        )
+    }
+
+    /// Runs curl and returns its output.
+    fn run(&self, invocation: &Invocation) -> Result<Zeroizing<String>> {
+        command::curl(
+            self.runner.as_ref(),
+            invocation,
+            "use lastpass+api://",
+            "LastPass request failed",
+        )
+    }
+}
+
+/// Returns the error for a failed login.
+fn login_error(response: &str) -> SecretSpecError {
+    match attribute(response, "cause").as_deref() {
+        Some("unknownemail" | "unknownpassword" | "googleauthfailed" | "otpfailed") => {
+            SecretSpecError::AuthRequired {
+                provider: "LastPass".to_string(),
+                fix: "Check the username, master password and one-time code.".to_string(),
+            }
+        }
+        Some("outofbandrequired") => SecretSpecError::ProviderOperationFailed(
+            "LastPass asked for a push confirmation, which lastpass+api:// doesn't support; use a one-time code instead".to_string(),
+        ),
+        _ => SecretSpecError::ProviderOperationFailed(format!(
+            "LastPass login failed: {}",
+            attribute(response, "message").unwrap_or_else(|| "unexpected response".to_string())
+        )),
+    }
+}
+
+/// Checks the response to a change of the vault.
+fn check_result(response: &str) -> Result<()> {
+    if response.contains("<result") && !response.contains("<error") {
+        return Ok(());
+    }
+    Err(SecretSpecError::ProviderOperationFailed(format!(
+        "LastPass rejected the change: {}",
+        attribute(response, "message").unwrap_or_else(|| "unexpected response".to_string())
+    )))
+}
+
+/// Returns the value of the first XML attribute called `name`.
+fn attribute(xml: &str, name: &str) -> Option<String> {
+    let start = xml.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = start + xml[start..].find('"')?;
+    Some(
+        xml[start..end]
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Derives the key decrypting the vault from the master password.
+fn derive_key(username: &str, password: &str, iterations: u32) -> Zeroizing<[u8; 32]> {
+    if iterations == 1 {
+        let mut key = Zeroizing::new([0u8; 32]);
+        key.copy_from_slice(&Sha256::digest(format!("{}{}", username, password)));
+        return key;
+    }
+    pbkdf2(password.as_bytes(), username.as_bytes(), iterations)
+}
+
+/// Returns the hash the API checks the master password with, which can't
+/// be turned back into the key.
+fn login_hash(key: &[u8; 32], password: &str, iterations: u32) -> String {
+    if iterations == 1 {
+        return hex(&Sha256::digest(format!("{}{}", hex(key), password)));
+    }
+    hex(&*pbkdf2(key, password.as_bytes(), 1))
+}
+
+/// PBKDF2 with HMAC-SHA256, producing one 32-byte block.
+fn pbkdf2(password: &[u8], salt: &[u8], iterations: u32) -> Zeroizing<[u8; 32]> {
+    let mac = <Hmac<Sha256> as Mac>::new_from_slice(password).expect("HMAC takes keys of any size");
+    let mut block = mac
+        .clone()
+        .chain_update(salt)
+        .chain_update(1u32.to_be_bytes())
+        .finalize()
+        .into_bytes();
+    let mut output = Zeroizing::new([0u8; 32]);
+    output.copy_from_slice(&block);
+    for _ in 1..iterations {
+        block = mac.clone().chain_update(block).finalize().into_bytes();
+        for (out, byte) in output.iter_mut().zip(block.iter()) {
+            *out ^= byte;
+        }
+    }
+    output
+}
+
+/// Returns the lowercase hex encoding of `bytes`.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// Reads the items of a decoded vault.
+///
+/// The vault is a sequence of chunks, each a four-letter ID and a
+/// size-prefixed payload. Items are `ACCT` chunks whose payload is a
+/// sequence of size-prefixed fields. Chunks after the first `SHAR` belong
+/// to shared folders and are skipped.
+fn parse_accounts(mut blob: &[u8], key: &[u8; 32]) -> Result<Vec<Account>> {
+    let mut accounts = Vec::new();
+    while !blob.is_empty() {
+        let id = take(&mut blob, 4)?;
+        let mut payload = take_field(&mut blob)?;
+        match id {
+            b"SHAR" => break,
+            b"ACCT" => {
+                let mut fields = Vec::new();
+                while !payload.is_empty() {
+                    fields.push(take_field(&mut payload)?);
+                }
+                if fields.len() < 9 {
+                    return Err(malformed());
+                }
+                accounts.push(Account {
+                    id: String::from_utf8_lossy(fields[0]).into_owned(),
+                    name: decrypt(fields[1], key)?.to_string(),
+                    group: decrypt(fields[2], key)?.to_string(),
+                    url: String::from_utf8_lossy(fields[3]).into_owned(),
+                    notes: decrypt(fields[4], key)?,
+                    username: decrypt(fields[7], key)?,
+                    password: decrypt(fields[8], key)?,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(accounts)
+}
+
+/// Returns the error for a vault that doesn't parse.
+fn malformed() -> SecretSpecError {
+    SecretSpecError::ProviderOperationFailed("LastPass returned a malformed vault".to_string())
+}
+
+/// Takes `len` bytes from the front of `data`.
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        return Err(malformed());
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head)
+}
+
+/// Takes a field prefixed with its big-endian 32-bit size from the front
+/// of `data`.
+fn take_field<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let size = take(data, 4)?;
+    let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]);
+    take(data, size as usize)
+}
+
+/// Decrypts a field of the vault: `!`, an IV and AES-256-CBC ciphertext,
+/// or AES-256-ECB ciphertext in old vaults.
+fn decrypt(data: &[u8], key: &[u8; 32]) -> Result<Zeroizing<String>> {
+    if data.is_empty() {
+        return Ok(Zeroizing::new(String::new()));
+    }
+    let invalid = || {
+        SecretSpecError::ProviderOperationFailed(
+            "Could not decrypt the LastPass vault; check the master password".to_string(),
+        )
+    };
+    let plain = if data[0] == b'!' && data.len() % 16 == 1 && data.len() > 32 {
+        let mut buffer = Zeroizing::new(data[17..].to_vec());
+        let plain = cbc::Decryptor::<Aes256>::new(key.into(), data[1..17].into())
+            .decrypt_padded_mut::<Pkcs7>(&mut buffer)
+            .map_err(|_| invalid())?;
+        plain.to_vec()
+    } else {
+        let mut buffer = Zeroizing::new(data.to_vec());
+        let plain = Aes256::new(key.into())
+            .decrypt_padded::<Pkcs7>(&mut buffer)
+            .map_err(|_| invalid())?;
+        plain.to_vec()
+    };
+    String::from_utf8(plain)
+        .map(Zeroizing::new)
+        .map_err(|_| invalid())
+}
+
+/// Encrypts a field for the API with AES-256-CBC and a random IV, as `!`,
+/// the base64 IV, `|` and the base64 ciphertext.
+fn encrypt(plain: &str, key: &[u8; 32]) -> Result<String> {
+    if plain.is_empty() {
+        return Ok(String::new());
+    }
+    let mut iv = [0u8; 16];
+    getrandom::getrandom(&mut iv).map_err(|e| {
+        SecretSpecError::ProviderOperationFailed(format!("Could not generate an IV: {}", e))
+    })?;
+    let mut buffer = Zeroizing::new(vec![0u8; plain.len() + 16]);
+    buffer[..plain.len()].copy_from_slice(plain.as_bytes());
+    let ciphertext = cbc::Encryptor::<Aes256>::new(key.into(), &iv.into())
+        .encrypt_padded_mut::<Pkcs7>(&mut buffer, plain.len())
+        .expect("buffer has room for the padding");
+    Ok(format!(
+        "!{}|{}",
+        STANDARD.encode(iv),
+        STANDARD.encode(ciphertext)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::command::FakeRunner;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    /// Encrypts a field the way it's stored in the vault.
+    fn stored(plain: &str) -> Vec<u8> {
+        let encrypted = encrypt(plain, &KEY).unwrap();
+        let (iv, ciphertext) = encrypted[1..].split_once('|').unwrap();
+        let mut field = vec![b'!'];
+        field.extend(STANDARD.decode(iv).unwrap());
+        field.extend(STANDARD.decode(ciphertext).unwrap());
+        field
+    }
+
+    fn field(data: &[u8]) -> Vec<u8> {
+        let mut field = (data.len() as u32).to_be_bytes().to_vec();
+        field.extend(data);
+        field
+    }
+
+    fn chunk(id: &[u8], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend(field(payload));
+        chunk
+    }
+
+    fn account(id: &str, group: &str, name: &str, password: &str) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for data in [
+            id.as_bytes().to_vec(),
+            stored(name),
+            stored(group),
+            b"".to_vec(),
+            stored("note"),
+            b"0".to_vec(),
+            b"".to_vec(),
+            stored("alice"),
+            stored(password),
+        ] {
+            payload.extend(field(&data));
+        }
+        chunk(b"ACCT", &payload)
+    }
+
+    fn vault() -> String {
+        let mut blob = chunk(b"LPAV", b"42");
+        blob.extend(account("11", "secretspec/app/default", "API_KEY", "sk_1"));
+        blob.extend(chunk(b"SHAR", b""));
+        blob.extend(account("12", "Shared-team", "API_KEY", "sk_shared"));
+        STANDARD.encode(blob)
+    }
+
+    fn client(runner: FakeRunner) -> (Client, Arc<FakeRunner>) {
+        let runner = Arc::new(runner);
+        let client = Client::new(Some("alice@example.com".to_string()), runner.clone());
+        *client.session.lock().unwrap() = Some(Session {
+            id: "s1".to_string(),
+            token: "t1".to_string(),
+            key: Zeroizing::new(KEY),
+        });
+        (client, runner)
+    }
+
+    const CURL: [&str; 6] = [
+        "curl",
+        "--fail",
+        "--silent",
+        "--show-error",
+        "--config",
+        "-",
+    ];
+
+    #[test]
+    fn test_key_derivation() {
+        assert_eq!(
+            hex(&*pbkdf2(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex(&*pbkdf2(b"password", b"salt", 2)),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+        let key = derive_key("alice@example.com", "hunter2", 1);
+        assert_eq!(
+            *key,
+            <[u8; 32]>::from(Sha256::digest("alice@example.comhunter2"))
+        );
+        assert_eq!(login_hash(&key, "hunter2", 1).len(), 64);
+    }
+
+    #[test]
+    fn test_fields_roundtrip() {
+        let field = stored("sk_live");
+        assert_eq!(decrypt(&field, &KEY).unwrap().as_str(), "sk_live");
+        assert!(decrypt(&field, &[8; 32]).is_err());
+        assert_eq!(decrypt(b"", &KEY).unwrap().as_str(), "");
+        assert_eq!(
+            attribute(r#"<ok sessionid="a&amp;b" token="t"/>"#, "sessionid").as_deref(),
+            Some("a&b")
+        );
+    }
+
+    #[test]
+    fn test_get_skips_shared_folders() {
+        let (client, runner) = client(FakeRunner::new().ok(&CURL, &vault()).ok(&CURL, &vault()));
+        let value = client
+            .get("secretspec/app/default/API_KEY")
+            .unwrap()
+            .unwrap();
+        assert_eq!(value.expose_secret(), "sk_1");
+        assert!(client.get("Shared-team/API_KEY").unwrap().is_none());
+        assert!(runner.is_exhausted());
+
+        let config = String::from_utf8(runner.calls()[0].stdin.clone().unwrap()).unwrap();
+        assert!(config.contains("header = \"Cookie: PHPSESSID=s1\""));
+    }
+
+    #[test]
+    fn test_set_updates_the_existing_item() {
+        let (client, runner) = client(
+            FakeRunner::new()
+                .ok(&CURL, &vault())
+                .ok(
+                    &CURL,
+                    r#"<xmlresponse><result action="updated" aid="11"/></xmlresponse>"#,
+                )
+                .ok(&CURL, &vault())
+                .ok(
+                    &CURL,
+                    "<xmlresponse><error message=\"denied\"/></xmlresponse>",
+                ),
+        );
+        client
+            .set(
+                "secretspec/app/default/API_KEY",
+                &SecretString::from("sk_2"),
+            )
+            .unwrap();
+        let config = String::from_utf8(runner.calls()[1].stdin.clone().unwrap()).unwrap();
+        assert!(config.contains("aid=11&"));
+        assert!(config.contains("token=t1&"));
+        assert!(!config.contains("sk_2"));
+
+        assert!(
+            client
+                .set("secretspec/app/default/OTHER", &SecretString::from("x"))
+                .is_err()
+        );
+        let config = String::from_utf8(runner.calls()[3].stdin.clone().unwrap()).unwrap();
+        assert!(config.contains("aid=0&"));
+    }
+}
@@ -6,14 +6,31 @@ use crate::Result;
 pub struct ProviderRegistration {
     pub info: ProviderInfo,
     pub schemes: &'static [&'static str],
+    /// Returns the query parameters understood for a scheme
+    pub params: fn(&str) -> &'static [&'static str],
     pub factory: fn(&url::Url) -> Result<Box<dyn Provider>>,
+    /// Parses the configuration and formats it for `secretspec provider explain`
+    pub explain: fn(&url::Url) -> Result<String>,
 }
 
 /// Distributed slice that collects all provider registrations.
 #[doc(hidden)]
+#[cfg(feature = "native")]
 #[linkme::distributed_slice]
 pub static PROVIDER_REGISTRY: [ProviderRegistration];
 
+/// Without the `native` feature linkme isn't available. On wasm32 the Vault
+/// KV provider is registered in its HTTP form, which the browser can run.
+#[doc(hidden)]
+#[cfg(all(not(feature = "native"), target_arch = "wasm32"))]
+pub static PROVIDER_REGISTRY: [ProviderRegistration; 1] = [super::vault::fetch::REGISTRATION];
+
+/// Without the `native` feature linkme isn't available, and outside wasm32
+/// no providers are registered.
+#[doc(hidden)]
+#[cfg(all(not(feature = "native"), not(target_arch = "wasm32")))]
+pub static PROVIDER_REGISTRY: [ProviderRegistration; 0] = [];
+
 /// Declarative macro for registering providers.
 ///
 /// This macro handles the boilerplate of registering a provider with the global registry.
@@ -28,8 +45,12 @@ pub static PROVIDER_REGISTRY: [ProviderRegistration];
 ///     description: "Uses system keychain (Recommended)",
 ///     schemes: ["keyring"],
 ///     examples: ["keyring://"],
+///     params: |_| &["keychain", "access", "synchronizable"],
 /// }
 /// ```
+///
+/// `params` names a function returning the query parameters understood for
+/// a scheme; providers without it take no parameters.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! register_provider {
@@ -39,14 +60,16 @@ macro_rules! register_provider {
         name: $name:expr,
         description: $description:expr,
         schemes: [$($scheme:expr),* $(,)?],
-        examples: [$($example:expr),* $(,)?] $(,)?
+        examples: [$($example:expr),* $(,)?]
+        $(, params: $params:expr)? $(,)?
     ) => {
         impl $struct_name {
             const PROVIDER_NAME: &'static str = $name;
         }
 
         const _: () = {
-            #[linkme::distributed_slice($crate::provider::PROVIDER_REGISTRY)]
+            #[cfg_attr(feature = "native", linkme::distributed_slice($crate::provider::PROVIDER_REGISTRY))]
+            #[cfg_attr(not(feature = "native"), allow(dead_code))]
             #[doc(hidden)]
             static PROVIDER_REGISTRATION: $crate::provider::ProviderRegistration = $crate::provider::ProviderRegistration {
                 info: $crate::provider::ProviderInfo {
@@ -55,11 +78,271 @@ macro_rules! register_provider {
                     examples: &[$($example,)*],
                 },
                 schemes: &[$($scheme,)*],
+                params: $crate::register_provider!(@params $($params)?),
                 factory: |url| {
                     let config = <$config_type>::try_from(url)?;
                     Ok(Box::new(<$struct_name>::new(config)))
                 },
+                explain: |url| {
+                    let config = <$config_type>::try_from(url)?;
+                    Ok(format!("{:#?}", config))
+                },
             };
         };
     };
+    (@params) => {
+        |_| &[]
+    };
+    (@params $params:expr) => {
+        $params
+    };
+}
+
+/// Implements [`Provider`] methods of a wrapper by passing them to the
+/// provider it wraps.
+///
+/// Wrappers list the methods they leave unchanged after the field holding
+/// the wrapped provider, and deny `clippy::missing_trait_methods` on their
+/// impl, so a method added to [`Provider`] later can't silently fall back to
+/// its default in a wrapper:
+///
+/// ```ignore
+/// #[deny(clippy::missing_trait_methods)]
+/// impl Provider for TimedProvider {
+///     fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+///         // ...
+///     }
+///
+///     forward_provider!(inner => name, set, delete, list_keys);
+/// }
+/// ```
+macro_rules! forward_provider {
+    ($field:ident => $($method:ident),+ $(,)?) => {
+        $($crate::provider::macros::forward_provider!(@method $field $method);)+
+    };
+    (@method $field:ident get) => {
+        fn get(
+            &self,
+            project: &str,
+            key: &str,
+            profile: &str,
+        ) -> $crate::Result<Option<::secrecy::SecretString>> {
+            self.$field.get(project, key, profile)
+        }
+    };
+    (@method $field:ident get_with_metadata) => {
+        fn get_with_metadata(
+            &self,
+            project: &str,
+            key: &str,
+            profile: &str,
+        ) -> $crate::Result<Option<$crate::provider::SecretValue>> {
+            self.$field.get_with_metadata(project, key, profile)
+        }
+    };
+    (@method $field:ident set) => {
+        fn set(
+            &self,
+            project: &str,
+            key: &str,
+            value: &::secrecy::SecretString,
+            profile: &str,
+        ) -> $crate::Result<()> {
+            self.$field.set(project, key, value, profile)
+        }
+    };
+    (@method $field:ident set_many) => {
+        fn set_many(
+            &self,
+            project: &str,
+            values: &[(String, ::secrecy::SecretString)],
+            profile: &str,
+        ) -> $crate::Result<()> {
+            self.$field.set_many(project, values, profile)
+        }
+    };
+    (@method $field:ident allows_set) => {
+        fn allows_set(&self) -> bool {
+            self.$field.allows_set()
+        }
+    };
+    (@method $field:ident compare_and_set) => {
+        fn compare_and_set(
+            &self,
+            project: &str,
+            key: &str,
+            value: &::secrecy::SecretString,
+            profile: &str,
+            expected: &str,
+        ) -> $crate::Result<()> {
+            self.$field.compare_and_set(project, key, value, profile, expected)
+        }
+    };
+    (@method $field:ident check_writable) => {
+        fn check_writable(&self, project: &str, profile: &str) -> $crate::Result<()> {
+            self.$field.check_writable(project, profile)
+        }
+    };
+    (@method $field:ident delete) => {
+        fn delete(&self, project: &str, key: &str, profile: &str) -> $crate::Result<()> {
+            self.$field.delete(project, key, profile)
+        }
+    };
+    (@method $field:ident allows_delete) => {
+        fn allows_delete(&self) -> bool {
+            self.$field.allows_delete()
+        }
+    };
+    (@method $field:ident purge) => {
+        fn purge(&self, project: &str, key: &str, profile: &str) -> $crate::Result<()> {
+            self.$field.purge(project, key, profile)
+        }
+    };
+    (@method $field:ident restore) => {
+        fn restore(&self, project: &str, key: &str, profile: &str) -> $crate::Result<()> {
+            self.$field.restore(project, key, profile)
+        }
+    };
+    (@method $field:ident allows_restore) => {
+        fn allows_restore(&self) -> bool {
+            self.$field.allows_restore()
+        }
+    };
+    (@method $field:ident history) => {
+        fn history(
+            &self,
+            project: &str,
+            key: &str,
+            profile: &str,
+        ) -> $crate::Result<Vec<$crate::provider::SecretMetadata>> {
+            self.$field.history(project, key, profile)
+        }
+    };
+    (@method $field:ident list_keys) => {
+        fn list_keys(&self, project: &str, profile: &str) -> $crate::Result<Vec<String>> {
+            self.$field.list_keys(project, profile)
+        }
+    };
+    (@method $field:ident allows_list) => {
+        fn allows_list(&self) -> bool {
+            self.$field.allows_list()
+        }
+    };
+    (@method $field:ident get_fields) => {
+        fn get_fields(
+            &self,
+            project: &str,
+            item: &str,
+            fields: &[&str],
+            profile: &str,
+        ) -> $crate::Result<::std::collections::HashMap<String, ::secrecy::SecretString>> {
+            self.$field.get_fields(project, item, fields, profile)
+        }
+    };
+    (@method $field:ident find_items) => {
+        fn find_items(
+            &self,
+            project: &str,
+            key: &str,
+            profile: &str,
+        ) -> $crate::Result<Vec<$crate::provider::ItemMatch>> {
+            self.$field.find_items(project, key, profile)
+        }
+    };
+    (@method $field:ident get_by_id) => {
+        fn get_by_id(
+            &self,
+            project: &str,
+            key: &str,
+            id: &str,
+            profile: &str,
+        ) -> $crate::Result<Option<$crate::provider::SecretValue>> {
+            self.$field.get_by_id(project, key, id, profile)
+        }
+    };
+    (@method $field:ident set_by_id) => {
+        fn set_by_id(
+            &self,
+            project: &str,
+            key: &str,
+            id: &str,
+            value: &::secrecy::SecretString,
+            profile: &str,
+        ) -> $crate::Result<()> {
+            self.$field.set_by_id(project, key, id, value, profile)
+        }
+    };
+    (@method $field:ident browse) => {
+        fn browse(&self) -> $crate::Result<Vec<$crate::provider::ItemContainer>> {
+            self.$field.browse()
+        }
+    };
+    (@method $field:ident max_value_size) => {
+        fn max_value_size(&self) -> Option<usize> {
+            self.$field.max_value_size()
+        }
+    };
+    (@method $field:ident works_in_sandbox) => {
+        fn works_in_sandbox(&self) -> bool {
+            self.$field.works_in_sandbox()
+        }
+    };
+    (@method $field:ident doctor) => {
+        fn doctor(&self) -> Vec<$crate::doctor::Check> {
+            self.$field.doctor()
+        }
+    };
+    (@method $field:ident list_access) => {
+        fn list_access(
+            &self,
+            project: &str,
+            profile: &str,
+        ) -> $crate::Result<Vec<$crate::provider::AccessEntry>> {
+            self.$field.list_access(project, profile)
+        }
+    };
+    (@method $field:ident access_policy) => {
+        fn access_policy(
+            &self,
+            project: &str,
+            profile: &str,
+            principal: &str,
+        ) -> $crate::Result<$crate::provider::AccessPolicy> {
+            self.$field.access_policy(project, profile, principal)
+        }
+    };
+    (@method $field:ident grant) => {
+        fn grant(
+            &self,
+            project: &str,
+            profile: &str,
+            principal: &str,
+        ) -> $crate::Result<$crate::provider::AccessPolicy> {
+            self.$field.grant(project, profile, principal)
+        }
+    };
+    (@method $field:ident leases) => {
+        fn leases(&self) -> Vec<$crate::provider::Lease> {
+            self.$field.leases()
+        }
+    };
+    (@method $field:ident renew_lease) => {
+        fn renew_lease(
+            &self,
+            lease: &$crate::provider::Lease,
+        ) -> $crate::Result<$crate::provider::Lease> {
+            self.$field.renew_lease(lease)
+        }
+    };
+    (@method $field:ident revoke_lease) => {
+        fn revoke_lease(&self, lease: &$crate::provider::Lease) -> $crate::Result<()> {
+            self.$field.revoke_lease(lease)
+        }
+    };
+    (@method $field:ident name) => {
+        fn name(&self) -> &'static str {
+            self.$field.name()
+        }
+    };
 }
+pub(crate) use forward_provider;
@@ -21,6 +21,11 @@
 //! - [`OnePasswordProvider`]: OnePassword integration
 //! - [`LastPassProvider`]: LastPass integration
 //! - [`BitwardenProvider`]: Bitwarden password manager
+//! - [`VaultKvProvider`]: HashiCorp Vault KV v2 secrets engine
+//! - [`VaultDynamicProvider`]: HashiCorp Vault dynamic credentials
+//! - [`GitVaultProvider`]: Encrypted team store in a git repository
+//! - [`GpgProvider`]: GPG-encrypted files, one per secret
+//! - [`PaasProvider`]: Heroku, Fly.io, Railway, Vercel and Netlify app variables (write targets)
 //!
 //! ## URI-Based Configuration
 //!
@@ -32,6 +37,11 @@
 //! onepassword://vault/items
 //! lastpass://folder
 //! bitwarden://collection-id
+//! vault://secret
+//! vault+dynamic://database/creds/app-role
+//! gitvault://git@github.com:org/secrets.git?key=age1...
+//! gpg://.secrets?recipient=alice@example.com
+//! fly://my-app
 //! ```
 //!
 //! ## Example
@@ -52,18 +62,42 @@
 //! }
 //! ```
 
+use crate::doctor::Check;
 use crate::{Result, SecretSpecError};
-use secrecy::SecretString;
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fmt;
+use std::time::Duration;
 use url::Url;
 
+pub mod audited;
 pub mod bitwarden;
+pub(crate) mod branch;
+pub mod builder;
+pub mod cached;
+pub mod chunked;
+pub mod command;
 pub mod dotenv;
+pub mod encrypted;
 pub mod env;
+pub mod env_override;
+pub mod gitvault;
+pub mod gpg;
 #[cfg(feature = "keyring")]
 pub mod keyring;
 pub mod lastpass;
+pub mod namespaced;
 pub mod onepassword;
+pub mod paas;
+pub mod pinned;
+pub mod retrying;
+pub(crate) mod session;
+pub mod throttled;
+pub mod timed;
+pub mod traced;
+pub mod vault;
 #[macro_use]
 pub mod macros;
 
@@ -138,6 +172,203 @@ pub fn providers() -> Vec<ProviderInfo> {
         .collect()
 }
 
+/// A principal's access to the secrets stored by a provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessEntry {
+    /// The user, group or account that has access
+    pub principal: String,
+    /// The provider's names for the permissions the principal holds
+    pub permissions: Vec<String>,
+}
+
+/// The minimal policy granting a principal read access to a project's secrets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessPolicy {
+    /// The principal the policy is for
+    pub principal: String,
+    /// What access is granted on, e.g. a vault or collection
+    ///
+    /// This may cover more than one project or profile when the provider
+    /// cannot restrict access more narrowly.
+    pub scope: String,
+    /// The policy in the provider's own format
+    pub document: String,
+}
+
+/// An existing item found by [`Provider::find_items`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemMatch {
+    /// The provider's stable identifier for the item
+    pub id: String,
+    /// The item's name as shown in the provider
+    pub name: String,
+    /// Where the item is kept, e.g. a vault or folder, to tell apart items
+    /// with the same name
+    pub location: Option<String>,
+}
+
+impl fmt::Display for ItemMatch {
+    /// Formats the item like `Stripe API Key in Private (4d1f3a6e)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(location) = &self.location {
+            write!(f, " in {}", location)?;
+        }
+        write!(f, " ({})", self.id)
+    }
+}
+
+/// A vault, folder or other container of items, listed by
+/// [`Provider::browse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemContainer {
+    /// The container's name as shown in the provider
+    pub name: String,
+    /// Names of the items in the container
+    pub items: Vec<String>,
+}
+
+/// A time-limited credential issued by a provider.
+///
+/// Values backed by a lease stop working once it expires, so long-running
+/// commands renew it with [`Provider::renew_lease`] and revoke it with
+/// [`Provider::revoke_lease`] when they no longer need the credential.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    /// The provider's identifier for the lease
+    pub id: String,
+    /// How long the credential stays valid unless the lease is renewed
+    pub ttl: Duration,
+    /// Whether the lease can be extended
+    pub renewable: bool,
+}
+
+/// What a provider records about a stored value.
+///
+/// Fields the provider doesn't track are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecretMetadata {
+    /// When the value was last changed, in the provider's own format
+    pub revised: Option<String>,
+    /// The provider's identifier for this version of the value
+    pub version: Option<String>,
+    /// Who created or last changed the value
+    pub creator: Option<String>,
+    /// Whether this version was deleted, for providers that keep deleted
+    /// versions in their [`history`](Provider::history)
+    pub deleted: bool,
+}
+
+impl SecretMetadata {
+    /// Returns whether the provider recorded nothing about the value.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Returns the identifier compare-and-set writes check: the version if
+    /// the provider numbers versions, otherwise the revision date.
+    pub fn revision(&self) -> Option<&str> {
+        self.version.as_deref().or(self.revised.as_deref())
+    }
+}
+
+impl fmt::Display for SecretMetadata {
+    /// Formats the recorded fields like `revised 2024-03-05, version 3, by alice`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = [
+            self.revised
+                .as_ref()
+                .map(|revised| format!("revised {}", revised)),
+            self.version
+                .as_ref()
+                .map(|version| format!("version {}", version)),
+            self.creator
+                .as_ref()
+                .map(|creator| format!("by {}", creator)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if self.deleted {
+            parts.push("deleted".to_string());
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// A secret value with what the provider records about it.
+#[derive(Debug, Clone)]
+pub struct SecretValue {
+    pub value: SecretString,
+    pub metadata: SecretMetadata,
+}
+
+impl From<SecretString> for SecretValue {
+    fn from(value: SecretString) -> Self {
+        Self {
+            value,
+            metadata: SecretMetadata::default(),
+        }
+    }
+}
+
+/// Returns the error for access-control operations a provider does not support.
+fn access_control_unsupported(provider: &str) -> SecretSpecError {
+    SecretSpecError::ProviderOperationFailed(format!(
+        "Provider '{}' does not support managing access.\n\nAccess control is available for onepassword and bitwarden organization collections.",
+        provider
+    ))
+}
+
+/// Returns the error for a storage operation a provider does not support.
+fn operation_unsupported(provider: &str, operation: &str) -> SecretSpecError {
+    SecretSpecError::ProviderOperationFailed(format!(
+        "Provider '{}' does not support {}",
+        provider, operation
+    ))
+}
+
+/// Returns a short hex hash of `value` for use in file names.
+pub(crate) fn short_hash(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Reads the members of a JSON object stored as a single value.
+///
+/// This is how [`Provider::get_fields`] works for providers without
+/// multi-field items.
+///
+/// # Errors
+///
+/// Returns an error if the value isn't a JSON object
+pub(crate) fn json_fields(
+    item: &str,
+    value: Option<SecretString>,
+    fields: &[&str],
+) -> Result<HashMap<String, SecretString>> {
+    let Some(value) = value else {
+        return Ok(HashMap::new());
+    };
+    let object: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(value.expose_secret()).map_err(|_| {
+            SecretSpecError::InvalidInput(format!(
+                "'{}' is not a JSON object, so its fields can't be read",
+                item
+            ))
+        })?;
+    Ok(fields
+        .iter()
+        .filter_map(|field| {
+            let value = match object.get(*field)? {
+                serde_json::Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            Some((field.to_string(), SecretString::new(value.into())))
+        })
+        .collect())
+}
+
 /// Trait defining the interface for secret storage providers.
 ///
 /// All secret storage backends must implement this trait to integrate with SecretSpec.
@@ -185,6 +416,24 @@ pub trait Provider: Send + Sync {
     /// ```
     fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>>;
 
+    /// Retrieves a secret value together with its revision metadata.
+    ///
+    /// Providers that record when and by whom a value was changed should
+    /// override this, and implement [`get`](Provider::get) in terms of it.
+    ///
+    /// # Returns
+    ///
+    /// The value as returned by [`get`](Provider::get), with empty metadata
+    /// unless the provider overrides this
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        Ok(self.get(project, key, profile)?.map(SecretValue::from))
+    }
+
     /// Stores a secret value in the provider.
     ///
     /// # Arguments
@@ -210,6 +459,29 @@ pub trait Provider: Send + Sync {
     /// ```
     fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()>;
 
+    /// Stores several secrets in one operation.
+    ///
+    /// Providers where every write is expensive, like deployment platforms
+    /// that restart the app on each change, should override this to write
+    /// all values at once. The default sets them one by one.
+    ///
+    /// # Arguments
+    ///
+    /// * `project` - The project namespace
+    /// * `values` - The secret names and their values
+    /// * `profile` - The profile
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        for (key, value) in values {
+            self.set(project, key, value, profile)?;
+        }
+        Ok(())
+    }
+
     /// Returns whether this provider supports setting values.
     ///
     /// By default, providers are assumed to support writing. Read-only providers
@@ -233,12 +505,528 @@ pub trait Provider: Send + Sync {
         true
     }
 
+    /// Stores a secret only if it is still at the expected revision.
+    ///
+    /// `expected` is the [`revision`](SecretMetadata::revision) of the value
+    /// the caller last read. If someone else changed or deleted the secret
+    /// since, nothing is written. The check and the write have to be one
+    /// atomic operation of the backend, so providers without one keep the
+    /// default, which refuses the write.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SecretSpecError::Conflict`] if the secret is at another
+    /// revision, and an error if the provider can't write conditionally
+    fn compare_and_set(
+        &self,
+        _project: &str,
+        _key: &str,
+        _value: &SecretString,
+        _profile: &str,
+        _expected: &str,
+    ) -> Result<()> {
+        Err(operation_unsupported(self.name(), "compare-and-set writes"))
+    }
+
+    /// Checks that secrets can be written for a project and profile.
+    ///
+    /// Commands that write several secrets call this once before the first
+    /// write, so a missing permission fails the whole batch up front instead
+    /// of leaving the target half written. Providers that can probe their
+    /// permissions without writing anything override it; the default only
+    /// checks [`allows_set`](Provider::allows_set).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider is read-only or writes would be
+    /// refused
+    fn check_writable(&self, project: &str, profile: &str) -> Result<()> {
+        let _ = (project, profile);
+        if self.allows_set() {
+            Ok(())
+        } else {
+            Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' is read-only and does not support setting values",
+                self.name()
+            )))
+        }
+    }
+
+    /// Removes a secret from the provider.
+    ///
+    /// Deleting a secret that does not exist succeeds, so deletes can be
+    /// retried safely.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot delete secrets (the default,
+    /// see [`allows_delete`](Provider::allows_delete)) or the delete fails
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let _ = (project, key, profile);
+        Err(operation_unsupported(self.name(), "deleting secrets"))
+    }
+
+    /// Returns whether this provider supports [`delete`](Provider::delete).
+    fn allows_delete(&self) -> bool {
+        false
+    }
+
+    /// Removes a secret for good, bypassing any trash.
+    ///
+    /// Providers whose [`delete`](Provider::delete) moves secrets to a trash
+    /// override this to erase them instead, including from the trash. For
+    /// other providers deleting is already permanent, so the default
+    /// deletes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`delete`](Provider::delete)
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.delete(project, key, profile)
+    }
+
+    /// Brings a deleted secret back from the provider's trash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider has no trash (the default, see
+    /// [`allows_restore`](Provider::allows_restore)) or no deleted secret
+    /// has the key
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let _ = (project, key, profile);
+        Err(operation_unsupported(
+            self.name(),
+            "restoring deleted secrets",
+        ))
+    }
+
+    /// Returns whether [`delete`](Provider::delete) moves secrets to a trash
+    /// that [`restore`](Provider::restore) can bring them back from.
+    fn allows_restore(&self) -> bool {
+        false
+    }
+
+    /// Lists the versions the provider keeps of a secret, newest first.
+    ///
+    /// Deleted versions stay listed with [`deleted`](SecretMetadata::deleted)
+    /// set, so the history shows when a secret was removed.
+    ///
+    /// # Returns
+    ///
+    /// What the provider recorded about each version; empty if the secret
+    /// doesn't exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider doesn't keep versions (the default)
+    /// or reading them fails
+    fn history(&self, project: &str, key: &str, profile: &str) -> Result<Vec<SecretMetadata>> {
+        let _ = (project, key, profile);
+        Err(operation_unsupported(self.name(), "listing versions"))
+    }
+
+    /// Lists the keys stored for a project and profile.
+    ///
+    /// Providers that don't namespace secrets, like dotenv files, return
+    /// every key they hold.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot enumerate secrets (the
+    /// default, see [`allows_list`](Provider::allows_list)) or listing fails
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let _ = (project, profile);
+        Err(operation_unsupported(self.name(), "listing secrets"))
+    }
+
+    /// Returns whether this provider supports [`list_keys`](Provider::list_keys).
+    fn allows_list(&self) -> bool {
+        false
+    }
+
+    /// Reads several fields of one item in a single fetch.
+    ///
+    /// `item` names an item as it is stored, such as the title of a
+    /// 1Password or Bitwarden login, and `fields` the fields to read from it.
+    /// Providers without multi-field items read the secret stored under
+    /// `item` and take the fields from the JSON object it holds.
+    ///
+    /// # Returns
+    ///
+    /// The values of the fields that exist, keyed by field name; empty if
+    /// the item doesn't exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the item fails or its value can't be
+    /// split into fields
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        json_fields(item, self.get(project, item, profile)?, fields)
+    }
+
+    /// Searches the provider for existing items that could hold a secret,
+    /// for `secretspec adopt`.
+    ///
+    /// Providers whose items have stable IDs should override this together
+    /// with [`get_by_id`](Provider::get_by_id) and
+    /// [`set_by_id`](Provider::set_by_id). Names are matched loosely, so
+    /// `Stripe API Key` is a candidate for `STRIPE_API_KEY`.
+    ///
+    /// # Returns
+    ///
+    /// The candidate items, best match first; empty if there is none
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider has no stable item IDs (the default)
+    /// or the search fails
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        let _ = (project, key, profile);
+        Err(operation_unsupported(self.name(), "adopting items"))
+    }
+
+    /// Retrieves a secret from the item with the given ID.
+    ///
+    /// `key` is the secret the item holds, which providers with multi-field
+    /// items use to pick the field.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if the item has no field holding it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider has no stable item IDs (the default),
+    /// the item doesn't exist or retrieving it fails
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let _ = (project, key, id, profile);
+        Err(operation_unsupported(self.name(), "addressing items by ID"))
+    }
+
+    /// Stores a secret in the item with the given ID, leaving items with the
+    /// same name untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider has no stable item IDs (the default),
+    /// the item doesn't exist or updating it fails
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        let _ = (project, key, id, value, profile);
+        Err(operation_unsupported(self.name(), "addressing items by ID"))
+    }
+
+    /// Lists the containers the provider can reach and the names of the
+    /// items in them, whether secretspec stored them or not.
+    ///
+    /// Only names are listed, so browsing never reads a value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider can't be browsed (the default) or
+    /// listing fails
+    fn browse(&self) -> Result<Vec<ItemContainer>> {
+        Err(operation_unsupported(self.name(), "browsing items"))
+    }
+
+    /// Returns the maximum number of bytes a single stored value may hold.
+    ///
+    /// Providers backed by stores with a size cap should override this. Values
+    /// larger than the limit are transparently split across several entries by
+    /// [`ChunkedProvider`](chunked::ChunkedProvider).
+    ///
+    /// # Returns
+    ///
+    /// - `Some(limit)` if the backend enforces a per-value size limit
+    /// - `None` if values of any size can be stored (the default)
+    fn max_value_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns whether this provider works inside a Nix build sandbox.
+    ///
+    /// The sandbox has no network access and no desktop session, so only
+    /// providers that read local files or the environment should override
+    /// this to return `true`.
+    fn works_in_sandbox(&self) -> bool {
+        false
+    }
+
+    /// Runs provider-specific health checks for `secretspec doctor`.
+    ///
+    /// Providers backed by an external CLI should report whether it is
+    /// installed (including its version) and whether the user is signed in.
+    /// Checks must not modify any stored secrets.
+    ///
+    /// # Returns
+    ///
+    /// The findings to display; empty if there is nothing to check (the default)
+    fn doctor(&self) -> Vec<Check> {
+        Vec::new()
+    }
+
+    /// Lists the principals that can access a project's secrets.
+    ///
+    /// Providers with an access-control API should override this together
+    /// with [`access_policy`](Provider::access_policy) and
+    /// [`grant`](Provider::grant).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider does not manage access (the default)
+    fn list_access(&self, project: &str, profile: &str) -> Result<Vec<AccessEntry>> {
+        let _ = (project, profile);
+        Err(access_control_unsupported(self.name()))
+    }
+
+    /// Builds the minimal policy granting a principal read access to a
+    /// project's secrets, without applying it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider does not manage access (the default)
+    /// or the principal is unknown to it
+    fn access_policy(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        let _ = (project, profile, principal);
+        Err(access_control_unsupported(self.name()))
+    }
+
+    /// Grants a principal read access to a project's secrets.
+    ///
+    /// # Returns
+    ///
+    /// The policy that was applied, as built by [`access_policy`](Provider::access_policy)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider does not manage access (the default)
+    /// or the policy cannot be applied
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        let _ = (project, profile, principal);
+        Err(access_control_unsupported(self.name()))
+    }
+
+    /// Returns the leases behind the values this provider has returned.
+    ///
+    /// Providers that issue short-lived credentials should override this
+    /// together with [`renew_lease`](Provider::renew_lease) and
+    /// [`revoke_lease`](Provider::revoke_lease). Values without a lease
+    /// (the default) never expire.
+    fn leases(&self) -> Vec<Lease> {
+        Vec::new()
+    }
+
+    /// Extends a lease returned by [`leases`](Provider::leases).
+    ///
+    /// # Returns
+    ///
+    /// The lease with its new time to live
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider does not issue leases (the default)
+    /// or the lease can no longer be renewed
+    fn renew_lease(&self, lease: &Lease) -> Result<Lease> {
+        let _ = lease;
+        Err(operation_unsupported(self.name(), "renewing leases"))
+    }
+
+    /// Revokes a lease returned by [`leases`](Provider::leases), invalidating
+    /// its credential immediately.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider does not issue leases (the default)
+    /// or the revocation fails
+    fn revoke_lease(&self, lease: &Lease) -> Result<()> {
+        let _ = lease;
+        Err(operation_unsupported(self.name(), "revoking leases"))
+    }
+
     /// Returns the name of this provider.
     ///
     /// This should match the name registered with the provider macro.
     fn name(&self) -> &'static str;
 }
 
+/// A shared provider, so one instance can back several handles.
+#[deny(clippy::missing_trait_methods)]
+impl<P: Provider + ?Sized> Provider for std::sync::Arc<P> {
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        (**self).get(project, key, profile)
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        (**self).get_with_metadata(project, key, profile)
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        (**self).set(project, key, value, profile)
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        (**self).set_many(project, values, profile)
+    }
+
+    fn allows_set(&self) -> bool {
+        (**self).allows_set()
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        (**self).compare_and_set(project, key, value, profile, expected)
+    }
+
+    fn check_writable(&self, project: &str, profile: &str) -> Result<()> {
+        (**self).check_writable(project, profile)
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        (**self).delete(project, key, profile)
+    }
+
+    fn allows_delete(&self) -> bool {
+        (**self).allows_delete()
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        (**self).purge(project, key, profile)
+    }
+
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        (**self).restore(project, key, profile)
+    }
+
+    fn allows_restore(&self) -> bool {
+        (**self).allows_restore()
+    }
+
+    fn history(&self, project: &str, key: &str, profile: &str) -> Result<Vec<SecretMetadata>> {
+        (**self).history(project, key, profile)
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        (**self).list_keys(project, profile)
+    }
+
+    fn allows_list(&self) -> bool {
+        (**self).allows_list()
+    }
+
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        (**self).get_fields(project, item, fields, profile)
+    }
+
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        (**self).find_items(project, key, profile)
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        (**self).get_by_id(project, key, id, profile)
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        (**self).set_by_id(project, key, id, value, profile)
+    }
+
+    fn browse(&self) -> Result<Vec<ItemContainer>> {
+        (**self).browse()
+    }
+
+    fn max_value_size(&self) -> Option<usize> {
+        (**self).max_value_size()
+    }
+
+    fn works_in_sandbox(&self) -> bool {
+        (**self).works_in_sandbox()
+    }
+
+    fn doctor(&self) -> Vec<Check> {
+        (**self).doctor()
+    }
+
+    fn list_access(&self, project: &str, profile: &str) -> Result<Vec<AccessEntry>> {
+        (**self).list_access(project, profile)
+    }
+
+    fn access_policy(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        (**self).access_policy(project, profile, principal)
+    }
+
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        (**self).grant(project, profile, principal)
+    }
+
+    fn leases(&self) -> Vec<Lease> {
+        (**self).leases()
+    }
+
+    fn renew_lease(&self, lease: &Lease) -> Result<Lease> {
+        (**self).renew_lease(lease)
+    }
+
+    fn revoke_lease(&self, lease: &Lease) -> Result<()> {
+        (**self).revoke_lease(lease)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+}
+
 impl TryFrom<String> for Box<dyn Provider> {
     type Error = SecretSpecError;
 
@@ -255,6 +1043,7 @@ impl TryFrom<String> for Box<dyn Provider> {
     ///
     /// - **1password**: Will error suggesting to use `onepassword` instead
     /// - **Bare provider names**: Automatically converted to `provider://`
+    /// - **`enc+` prefix**: Wraps the provider in client-side encryption
     ///
     /// # Examples
     ///
@@ -279,61 +1068,84 @@ impl TryFrom<&str> for Box<dyn Provider> {
     type Error = SecretSpecError;
 
     fn try_from(s: &str) -> Result<Self> {
-        // Parse the scheme from the input string
-        let (scheme, rest) = if let Some(pos) = s.find(':') {
-            let scheme = &s[..pos];
-            let rest = &s[pos + 1..];
-            (scheme, rest)
-        } else {
-            // Just a provider name, no URI components
-            (s, "")
-        };
-
-        // Validate scheme first
-        if scheme == "1password" {
-            return Err(SecretSpecError::ProviderOperationFailed(
-                "Invalid scheme '1password'. Use 'onepassword' instead (e.g., onepassword://vault/path)".to_string()
-            ));
+        let s = &*branch::expand(s)?;
+
+        // Client-side encryption wraps any other provider (e.g., "enc+dotenv://...")
+        if let Some(inner) = s.strip_prefix(encrypted::SCHEME_PREFIX) {
+            return Ok(Box::new(encrypted::EncryptedProvider::from_spec(inner)?));
         }
 
-        // Check if the scheme is registered
-        let is_valid_scheme = PROVIDER_REGISTRY
-            .iter()
-            .any(|reg| reg.schemes.contains(&scheme));
-
-        if !is_valid_scheme {
-            // Check if it's a known provider name to give a better error
-            if PROVIDER_REGISTRY.iter().any(|reg| reg.info.name == scheme) {
-                return Err(SecretSpecError::ProviderOperationFailed(format!(
-                    "Provider '{}' exists but URI parsing failed",
-                    scheme
-                )));
-            } else {
-                return Err(SecretSpecError::ProviderNotFound(scheme.to_string()));
-            }
+        // Environment overrides wrap any other provider (e.g., "override+keyring://")
+        if let Some(inner) = s.strip_prefix(env_override::SCHEME_PREFIX) {
+            return Ok(Box::new(env_override::EnvOverrideProvider::from_spec(
+                inner,
+            )?));
         }
 
-        // Build a proper URL with the correct scheme
-        let url_string = match rest {
-            // Just scheme name (e.g., "keyring")
-            "" | ":" => format!("{}://", scheme),
-            // Standard URI format already has // (e.g., "onepassword://vault/path")
-            s if s.starts_with("//") => format!("{}:{}", scheme, s),
-            // Path only format (e.g., "dotenv:/path/to/.env")
-            s if s.starts_with('/') => format!("{}://{}", scheme, s),
-            // Everything else - assume it's a host or path component
-            s => format!("{}://{}", scheme, s),
-        };
-
-        let proper_url = Url::parse(&url_string).map_err(|e| {
-            SecretSpecError::ProviderOperationFailed(format!(
-                "Invalid provider specification '{}': {}",
-                s, e
-            ))
-        })?;
+        // Remotes like git@github.com:org/secrets.git aren't valid URL authorities
+        if s.starts_with(gitvault::SCHEME_PREFIX) {
+            return gitvault::from_spec(s);
+        }
+
+        Self::try_from(&provider_url(s)?)
+    }
+}
+
+/// Normalizes a provider specification, such as `keyring` or
+/// `dotenv:.env.production`, into the URL of a registered provider.
+fn provider_url(s: &str) -> Result<Url> {
+    // Parse the scheme from the input string
+    let (scheme, rest) = if let Some(pos) = s.find(':') {
+        let scheme = &s[..pos];
+        let rest = &s[pos + 1..];
+        (scheme, rest)
+    } else {
+        // Just a provider name, no URI components
+        (s, "")
+    };
 
-        Self::try_from(&proper_url)
+    // Validate scheme first
+    if scheme == "1password" {
+        return Err(SecretSpecError::ProviderOperationFailed(
+            "Invalid scheme '1password'. Use 'onepassword' instead (e.g., onepassword://vault/path)".to_string()
+        ));
     }
+
+    // Check if the scheme is registered
+    let is_valid_scheme = PROVIDER_REGISTRY
+        .iter()
+        .any(|reg| reg.schemes.contains(&scheme));
+
+    if !is_valid_scheme {
+        // Check if it's a known provider name to give a better error
+        if PROVIDER_REGISTRY.iter().any(|reg| reg.info.name == scheme) {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' exists but URI parsing failed",
+                scheme
+            )));
+        } else {
+            return Err(SecretSpecError::ProviderNotFound(scheme.to_string()));
+        }
+    }
+
+    // Build a proper URL with the correct scheme
+    let url_string = match rest {
+        // Just scheme name (e.g., "keyring")
+        "" | ":" => format!("{}://", scheme),
+        // Standard URI format already has // (e.g., "onepassword://vault/path")
+        s if s.starts_with("//") => format!("{}:{}", scheme, s),
+        // Path only format (e.g., "dotenv:/path/to/.env")
+        s if s.starts_with('/') => format!("{}://{}", scheme, s),
+        // Everything else - assume it's a host or path component
+        s => format!("{}://{}", scheme, s),
+    };
+
+    Url::parse(&url_string).map_err(|e| {
+        SecretSpecError::ProviderOperationFailed(format!(
+            "Invalid provider specification '{}': {}",
+            s, e
+        ))
+    })
 }
 
 impl TryFrom<&Url> for Box<dyn Provider> {
@@ -352,3 +1164,185 @@ impl TryFrom<&Url> for Box<dyn Provider> {
         (registration.factory)(url)
     }
 }
+
+/// Rejects query parameters of a provider specification that the provider
+/// doesn't understand, which creating it would ignore.
+///
+/// Specs of a strict schema check the provider URI with this before using
+/// it.
+///
+/// # Errors
+///
+/// Returns an error if the specification doesn't parse or has an unknown
+/// parameter, suggesting the closest known one
+pub(crate) fn check_strict(spec: &str) -> Result<()> {
+    if let Some(inner) = spec.strip_prefix(encrypted::SCHEME_PREFIX) {
+        let (inner, _, _) = encrypted::split_spec(inner)?;
+        return check_strict(&inner);
+    }
+    if let Some(inner) = spec.strip_prefix(env_override::SCHEME_PREFIX) {
+        return check_strict(inner);
+    }
+    // gitvault rejects unknown parameters when parsing
+    if spec.starts_with(gitvault::SCHEME_PREFIX) {
+        return gitvault::parse_spec(spec).map(drop);
+    }
+
+    let url = provider_url(spec)?;
+    let registration = PROVIDER_REGISTRY
+        .iter()
+        .find(|reg| reg.schemes.contains(&url.scheme()))
+        .ok_or_else(|| SecretSpecError::ProviderNotFound(url.scheme().to_string()))?;
+    check_params(&url, registration)
+}
+
+/// Rejects query parameters of `url` that the provider doesn't understand.
+fn check_params(url: &Url, registration: &ProviderRegistration) -> Result<()> {
+    let scheme = url.scheme();
+    let known = (registration.params)(scheme);
+    let Some((name, _)) = url
+        .query_pairs()
+        .find(|(name, _)| !known.contains(&name.as_ref()))
+    else {
+        return Ok(());
+    };
+
+    let hint = if known.is_empty() {
+        format!("{}:// URIs take no parameters", scheme)
+    } else {
+        crate::suggest::hint(&name, known).unwrap_or_default()
+    };
+    Err(SecretSpecError::InvalidInput(format!(
+        "Unknown parameter '{}' for {}:// URIs, which the provider would ignore. {}",
+        name, scheme, hint
+    )))
+}
+
+/// Shown by [`explain`] in place of credentials.
+const REDACTED: &str = "***";
+
+/// A provider URI parsed into the provider's configuration, as printed by
+/// `secretspec provider explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// Name of the provider
+    pub provider: &'static str,
+    /// The URI the specification was normalized to, before wrappers
+    pub uri: String,
+    /// How many recipients the URI encrypts values to on the client, or
+    /// `None` without client-side encryption
+    pub encryption: Option<usize>,
+    /// Whether secrets set in the process environment override the provider
+    pub env_override: bool,
+    /// The provider's configuration, with credentials redacted
+    pub config: String,
+}
+
+/// Parses a provider specification the way commands do and returns the
+/// resulting configuration.
+///
+/// Unlike creating the provider, query parameters the provider doesn't
+/// understand are reported instead of ignored.
+///
+/// # Errors
+///
+/// Returns an error if the specification doesn't parse or has a query
+/// parameter the provider doesn't understand
+pub fn explain(spec: &str) -> Result<Explanation> {
+    let spec = &*branch::expand(spec)?;
+    if let Some(inner) = spec.strip_prefix(encrypted::SCHEME_PREFIX) {
+        let (inner, recipients, _) = encrypted::split_spec(inner)?;
+        return Ok(Explanation {
+            encryption: Some(recipients.len()),
+            ..explain(&inner)?
+        });
+    }
+    if let Some(inner) = spec.strip_prefix(env_override::SCHEME_PREFIX) {
+        return Ok(Explanation {
+            env_override: true,
+            ..explain(inner)?
+        });
+    }
+    // Remotes like git@github.com:org/secrets.git aren't valid URL authorities
+    if spec.starts_with(gitvault::SCHEME_PREFIX) {
+        let (config, recipients, _) = gitvault::parse_spec(spec)?;
+        return Ok(Explanation {
+            provider: "gitvault",
+            uri: format!("{}//{}", gitvault::SCHEME_PREFIX, config.remote),
+            encryption: Some(recipients.len()),
+            env_override: false,
+            config: format!("{:#?}", config),
+        });
+    }
+
+    let url = provider_url(spec)?;
+    let scheme = url.scheme();
+    let registration = PROVIDER_REGISTRY
+        .iter()
+        .find(|reg| reg.schemes.contains(&scheme))
+        .ok_or_else(|| SecretSpecError::ProviderNotFound(scheme.to_string()))?;
+
+    check_params(&url, registration)?;
+
+    // Tokens can be given in the URI; keep them out of the output
+    let mut config = (registration.explain)(&url)?;
+    let mut shown = url.clone();
+    let mut credentials = Vec::new();
+    if let Some(password) = url.password() {
+        credentials.push(password.to_string());
+        let _ = shown.set_password(Some(REDACTED));
+    } else if scheme.ends_with("+token") && !url.username().is_empty() {
+        credentials.push(url.username().to_string());
+        let _ = shown.set_username(REDACTED);
+    }
+    if url.query_pairs().any(|(name, _)| name == "token") {
+        let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let mut query = shown.query_pairs_mut();
+        query.clear();
+        for (name, value) in pairs {
+            if name == "token" {
+                query.append_pair(&name, REDACTED);
+                credentials.push(value);
+            } else {
+                query.append_pair(&name, &value);
+            }
+        }
+    }
+    for credential in credentials {
+        config = config.replace(&format!("{:?}", credential), &format!("{:?}", REDACTED));
+    }
+
+    Ok(Explanation {
+        provider: registration.info.name,
+        uri: shown.to_string(),
+        encryption: None,
+        env_override: false,
+        config,
+    })
+}
+
+/// Lists the containers and item names a provider can reach, as printed by
+/// `secretspec browse`.
+///
+/// With a `query`, only the items fuzzily matching it are kept, best match
+/// first, and containers left empty are dropped.
+///
+/// # Errors
+///
+/// Returns an error if the specification doesn't parse, the provider can't
+/// be browsed or listing fails
+pub fn browse(spec: &str, query: Option<&str>) -> Result<Vec<ItemContainer>> {
+    let provider = Box::<dyn Provider>::try_from(spec)?;
+    let mut containers = provider.browse()?;
+    if let Some(query) = query {
+        for container in &mut containers {
+            let items: Vec<&str> = container.items.iter().map(String::as_str).collect();
+            container.items = crate::suggest::fuzzy(query, &items)
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+        }
+        containers.retain(|container| !container.items.is_empty());
+    }
+    Ok(containers)
+}
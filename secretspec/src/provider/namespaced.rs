@@ -0,0 +1,161 @@
+//! Name prefixing for shared vaults.
+//!
+//! Teams sharing one Bitwarden organization or Vault mount set a
+//! `namespace` in their global configuration, and the [`NamespacedProvider`]
+//! wrapper puts it in front of the project name of every call. Providers
+//! build item names and paths from the project, so `secretspec/{project}/...`
+//! becomes `secretspec/team-payments/{project}/...` in all of them alike.
+
+use super::macros::forward_provider;
+use super::{AccessEntry, AccessPolicy, ItemMatch, Provider, SecretMetadata, SecretValue};
+use crate::Result;
+use secrecy::SecretString;
+use std::collections::HashMap;
+
+/// Provider wrapper that prefixes project names with a namespace.
+pub struct NamespacedProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+    /// Prefix of the project names handed to the provider
+    namespace: String,
+}
+
+impl NamespacedProvider {
+    /// Wraps the provider if a namespace is set.
+    pub fn wrap(inner: Box<dyn Provider>, namespace: Option<&str>) -> Box<dyn Provider> {
+        match namespace {
+            Some(namespace) => Box::new(Self {
+                inner,
+                namespace: namespace.to_string(),
+            }),
+            None => inner,
+        }
+    }
+
+    /// Returns the project name handed to the wrapped provider.
+    fn project(&self, project: &str) -> String {
+        format!("{}/{}", self.namespace, project)
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for NamespacedProvider {
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        self.inner.get(&self.project(project), key, profile)
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.inner
+            .get_with_metadata(&self.project(project), key, profile)
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.inner.set(&self.project(project), key, value, profile)
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        self.inner.set_many(&self.project(project), values, profile)
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        self.inner
+            .compare_and_set(&self.project(project), key, value, profile, expected)
+    }
+
+    fn check_writable(&self, project: &str, profile: &str) -> Result<()> {
+        self.inner.check_writable(&self.project(project), profile)
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.inner.delete(&self.project(project), key, profile)
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.inner.purge(&self.project(project), key, profile)
+    }
+
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.inner.restore(&self.project(project), key, profile)
+    }
+
+    fn history(&self, project: &str, key: &str, profile: &str) -> Result<Vec<SecretMetadata>> {
+        self.inner.history(&self.project(project), key, profile)
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        self.inner.list_keys(&self.project(project), profile)
+    }
+
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        self.inner
+            .get_fields(&self.project(project), item, fields, profile)
+    }
+
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        self.inner.find_items(&self.project(project), key, profile)
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.inner
+            .get_by_id(&self.project(project), key, id, profile)
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        self.inner
+            .set_by_id(&self.project(project), key, id, value, profile)
+    }
+
+    fn list_access(&self, project: &str, profile: &str) -> Result<Vec<AccessEntry>> {
+        self.inner.list_access(&self.project(project), profile)
+    }
+
+    fn access_policy(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.inner
+            .access_policy(&self.project(project), profile, principal)
+    }
+
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.inner.grant(&self.project(project), profile, principal)
+    }
+
+    forward_provider!(inner =>
+        allows_set, allows_delete, allows_restore, allows_list, browse, max_value_size,
+        works_in_sandbox, doctor, leases, renew_lease, revoke_lease, name,
+    );
+}
@@ -1,9 +1,16 @@
-use crate::provider::Provider;
+use crate::config::normalize_key;
+use crate::doctor::{self, Check, Status};
+use crate::provider::command::{CommandRunner, Invocation, SystemRunner};
+use crate::provider::{
+    AccessEntry, AccessPolicy, ItemContainer, ItemMatch, Provider, SecretMetadata, SecretValue,
+};
 use crate::{Result, SecretSpecError};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::collections::HashMap;
+use std::sync::Arc;
 use url::Url;
+use zeroize::{Zeroize, Zeroizing};
 
 /// Represents a OnePassword item retrieved from the CLI.
 ///
@@ -14,6 +21,24 @@ struct OnePasswordItem {
     /// Collection of fields within the OnePassword item.
     /// Each field represents a piece of data stored in the item.
     fields: Vec<OnePasswordField>,
+    /// When the item was last changed.
+    #[serde(default)]
+    updated_at: Option<String>,
+}
+
+impl OnePasswordItem {
+    /// Returns the field holding the secret: the field labeled "value", or
+    /// else the password or first concealed field.
+    fn value_field(&self) -> Option<&OnePasswordField> {
+        self.fields
+            .iter()
+            .find(|field| field.label.as_deref() == Some("value"))
+            .or_else(|| {
+                self.fields
+                    .iter()
+                    .find(|field| field.field_type == "CONCEALED" || field.id == "password")
+            })
+    }
 }
 
 /// Represents a single field within a OnePassword item.
@@ -70,6 +95,47 @@ struct OnePasswordFieldTemplate {
     value: String,
 }
 
+impl Drop for OnePasswordFieldTemplate {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// Represents a user with access to a vault, as listed by `op vault user list`.
+#[derive(Debug, Deserialize)]
+struct OnePasswordVaultUser {
+    /// The user's email address.
+    email: String,
+    /// The permissions the user holds on the vault.
+    #[serde(default)]
+    permissions: Vec<String>,
+}
+
+/// Summary of a vault as listed by `op vault list`.
+#[derive(Debug, Deserialize)]
+struct OnePasswordVaultSummary {
+    /// The vault's ID.
+    id: String,
+    /// The vault's name.
+    name: String,
+}
+
+/// Summary of an item as listed by `op item list`.
+#[derive(Debug, Deserialize)]
+struct OnePasswordItemSummary {
+    /// The item's ID.
+    #[serde(default)]
+    id: String,
+    /// The item's title.
+    title: String,
+}
+
+/// Vault permission that lets a user read items.
+const READ_PERMISSION: &str = "view_items";
+
+/// Vault permissions `set` needs to create new items and update existing ones.
+const WRITE_PERMISSIONS: &str = "create_items,edit_items";
+
 /// Configuration for the OnePassword provider.
 ///
 /// This struct contains all the necessary configuration options for
@@ -119,6 +185,12 @@ pub struct OnePasswordConfig {
     /// Supports placeholders: {project}, {profile}, and {key}.
     /// Defaults to "secretspec/{project}/{profile}/{key}" if not specified.
     pub folder_prefix: Option<String>,
+    /// Item whose fields are read, from the URI path.
+    ///
+    /// With `onepassword://Shared/Stripe`, keys are the field labels of the
+    /// `Stripe` item in the `Shared` vault instead of items named after the
+    /// key. Such a provider is read-only.
+    pub item: Option<String>,
 }
 
 impl TryFrom<&Url> for OnePasswordConfig {
@@ -167,6 +239,15 @@ impl TryFrom<&Url> for OnePasswordConfig {
             }
         }
 
+        let item = percent_encoding::percent_decode_str(url.path().trim_matches('/'))
+            .decode_utf8()
+            .map_err(|_| {
+                SecretSpecError::ProviderOperationFailed(format!("Invalid item name in '{}'", url))
+            })?;
+        if !item.is_empty() {
+            config.item = Some(item.into_owned());
+        }
+
         Ok(config)
     }
 }
@@ -202,6 +283,10 @@ impl OnePasswordConfig {}
 /// - Fields: project, key, value
 /// - Tags: "automated", {project}
 ///
+/// A URI with a path, like `onepassword://Shared/Stripe`, reads the fields of
+/// that one item instead, so `ref:onepassword://Shared/Stripe#publishable`
+/// takes its `publishable` field.
+///
 /// # Example Usage
 ///
 /// ```ignore
@@ -216,6 +301,8 @@ impl OnePasswordConfig {}
 pub struct OnePasswordProvider {
     /// Configuration for the provider including auth settings and default vault.
     config: OnePasswordConfig,
+    /// Runs the `op` CLI.
+    runner: Arc<dyn CommandRunner>,
 }
 
 crate::register_provider! {
@@ -224,7 +311,15 @@ crate::register_provider! {
     name: "onepassword",
     description: "OnePassword password manager",
     schemes: ["onepassword", "onepassword+token"],
-    examples: ["onepassword://vault", "onepassword://work@Production", "onepassword+token://vault"],
+    examples: ["onepassword://vault", "onepassword://work@Production", "onepassword://vault/item", "onepassword+token://vault"],
+}
+
+/// Returns the error for operations that need the user to sign in first.
+fn signin_required() -> SecretSpecError {
+    SecretSpecError::AuthRequired {
+        provider: "OnePassword".to_string(),
+        fix: "Run 'eval $(op signin)' first.".to_string(),
+    }
 }
 
 impl OnePasswordProvider {
@@ -234,7 +329,12 @@ impl OnePasswordProvider {
     ///
     /// * `config` - The configuration for the provider
     pub fn new(config: OnePasswordConfig) -> Self {
-        Self { config }
+        Self::with_runner(config, Arc::new(SystemRunner))
+    }
+
+    /// Creates a OnePasswordProvider that runs the `op` CLI through `runner`.
+    pub(crate) fn with_runner(config: OnePasswordConfig, runner: Arc<dyn CommandRunner>) -> Self {
+        Self { config, runner }
     }
 
     /// Executes a OnePassword CLI command with proper error handling.
@@ -259,46 +359,47 @@ impl OnePasswordProvider {
     /// - Missing OnePassword CLI installation
     /// - Authentication required
     /// - Command execution failures
-    fn execute_op_command(&self, args: &[&str]) -> Result<String> {
-        let mut cmd = Command::new("op");
+    fn execute_op_command(&self, args: &[&str]) -> Result<Zeroizing<String>> {
+        let mut invocation = Invocation::new("op");
 
         // Set service account token if provided
         if let Some(token) = &self.config.service_account_token {
-            cmd.env("OP_SERVICE_ACCOUNT_TOKEN", token);
+            invocation = invocation.env("OP_SERVICE_ACCOUNT_TOKEN", token);
         }
 
         // Add account if specified
         if let Some(account) = &self.config.account {
-            cmd.arg("--account").arg(account);
+            invocation = invocation.args(["--account", account]);
         }
 
-        cmd.args(args);
+        let invocation = invocation.args(args);
 
-        let output = match cmd.output() {
+        let output = match self.runner.run(&invocation) {
             Ok(output) => output,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "OnePassword CLI (op) is not installed.\n\nTo install it:\n  - macOS: brew install 1password-cli\n  - Linux: Download from https://1password.com/downloads/command-line/\n  - Windows: Download from https://1password.com/downloads/command-line/\n  - NixOS: nix-env -iA nixpkgs.onepassword\n\nAfter installation, run 'eval $(op signin)' to authenticate.".to_string(),
-                ));
+                return Err(SecretSpecError::CliMissing {
+                    cli: "OnePassword CLI (op)".to_string(),
+                    install_hint: "To install it:\n  - macOS: brew install 1password-cli\n  - Linux: Download from https://1password.com/downloads/command-line/\n  - Windows: Download from https://1password.com/downloads/command-line/\n  - NixOS: nix-env -iA nixpkgs.onepassword\n\nAfter installation, run 'eval $(op signin)' to authenticate.".to_string(),
+                });
             }
             Err(e) => return Err(e.into()),
         };
 
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            if error_msg.contains("not currently signed in") {
-                return Err(SecretSpecError::ProviderOperationFailed(
-                    "OnePassword authentication required. Please run 'eval $(op signin)' first."
-                        .to_string(),
-                ));
+        if !output.success {
+            let stderr = output.stderr;
+            if stderr.contains("not currently signed in") || stderr.contains("no account found") {
+                return Err(signin_required());
+            }
+            if stderr.contains("isn't an item") {
+                return Err(SecretSpecError::NotFound(stderr));
             }
-            return Err(SecretSpecError::ProviderOperationFailed(
-                error_msg.to_string(),
-            ));
+            if stderr.contains("You do not have permission") {
+                return Err(SecretSpecError::PermissionDenied(stderr));
+            }
+            return Err(SecretSpecError::ProviderOperationFailed(stderr));
         }
 
-        String::from_utf8(output.stdout)
-            .map_err(|e| SecretSpecError::ProviderOperationFailed(e.to_string()))
+        Ok(output.stdout)
     }
 
     /// Checks if the user is authenticated with OnePassword.
@@ -314,11 +415,7 @@ impl OnePasswordProvider {
     fn whoami(&self) -> Result<bool> {
         match self.execute_op_command(&["whoami"]) {
             Ok(_) => Ok(true),
-            Err(SecretSpecError::ProviderOperationFailed(msg))
-                if msg.contains("not currently signed in") || msg.contains("no account found") =>
-            {
-                Ok(false)
-            }
+            Err(SecretSpecError::AuthRequired { .. }) => Ok(false),
             Err(e) => Err(e),
         }
     }
@@ -367,6 +464,27 @@ impl OnePasswordProvider {
             .replace("{key}", key)
     }
 
+    /// Checks that the user is signed in before an operation.
+    fn require_signin(&self) -> Result<()> {
+        if self.whoami()? {
+            Ok(())
+        } else {
+            Err(signin_required())
+        }
+    }
+
+    /// Rejects writes when the URI addresses the fields of one item.
+    fn require_item_unset(&self) -> Result<()> {
+        match &self.config.item {
+            Some(item) => Err(SecretSpecError::ProviderOperationFailed(format!(
+                "onepassword: fields of item '{}' are read-only; \
+                 drop the item from the provider URI to store secrets",
+                item
+            ))),
+            None => Ok(()),
+        }
+    }
+
     /// Creates a template for a new OnePassword item.
     ///
     /// This template is serialized to JSON and used with `op item create`.
@@ -443,14 +561,13 @@ impl Provider for OnePasswordProvider {
     /// - Item retrieval failures
     /// - JSON parsing errors
     fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
-        // Check authentication status first
-        if !self.whoami()? {
-            return Err(SecretSpecError::ProviderOperationFailed(
-                "OnePassword authentication required. Please run 'eval $(op signin)' first."
-                    .to_string(),
-            ));
+        if let Some(item) = &self.config.item {
+            return Ok(self.get_fields(project, item, &[key], profile)?.remove(key));
         }
 
+        // Check authentication status first
+        self.require_signin()?;
+
         let vault = self.get_vault_name(profile);
         let item_name = self.format_item_name(project, key, profile);
 
@@ -462,32 +579,12 @@ impl Provider for OnePasswordProvider {
         match self.execute_op_command(&args) {
             Ok(output) => {
                 let item: OnePasswordItem = serde_json::from_str(&output)?;
-
-                // Look for the "value" field
-                for field in &item.fields {
-                    if field.label.as_deref() == Some("value") {
-                        return Ok(field
-                            .value
-                            .as_ref()
-                            .map(|v| SecretString::new(v.clone().into())));
-                    }
-                }
-
-                // Fallback: look for password field or first concealed field
-                for field in &item.fields {
-                    if field.field_type == "CONCEALED" || field.id == "password" {
-                        return Ok(field
-                            .value
-                            .as_ref()
-                            .map(|v| SecretString::new(v.clone().into())));
-                    }
-                }
-
-                Ok(None)
-            }
-            Err(SecretSpecError::ProviderOperationFailed(msg)) if msg.contains("isn't an item") => {
-                Ok(None)
+                Ok(item
+                    .value_field()
+                    .and_then(|field| field.value.clone())
+                    .map(|v| SecretString::new(v.into())))
             }
+            Err(SecretSpecError::NotFound(_)) => Ok(None),
             Err(e) => Err(e),
         }
     }
@@ -515,13 +612,10 @@ impl Provider for OnePasswordProvider {
     /// - Item creation/update failures
     /// - Temporary file creation errors
     fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.require_item_unset()?;
+
         // Check authentication status first
-        if !self.whoami()? {
-            return Err(SecretSpecError::ProviderOperationFailed(
-                "OnePassword authentication required. Please run 'eval $(op signin)' first."
-                    .to_string(),
-            ));
-        }
+        self.require_signin()?;
 
         let vault = self.get_vault_name(profile);
         let item_name = self.format_item_name(project, key, profile);
@@ -529,7 +623,7 @@ impl Provider for OnePasswordProvider {
         // First, try to update existing item
         if let Ok(Some(_)) = self.get(project, key, profile) {
             // Item exists, update it
-            let field_assignment = format!("value={}", value.expose_secret());
+            let field_assignment = Zeroizing::new(format!("value={}", value.expose_secret()));
             let args = vec![
                 "item",
                 "edit",
@@ -543,7 +637,7 @@ impl Provider for OnePasswordProvider {
         } else {
             // Item doesn't exist, create it
             let template = self.create_item_template(project, key, value, profile);
-            let template_json = serde_json::to_string(&template)?;
+            let template_json = Zeroizing::new(serde_json::to_string(&template)?);
 
             // Write template to temp file
             use std::io::Write;
@@ -569,6 +663,318 @@ impl Provider for OnePasswordProvider {
 
         Ok(())
     }
+
+    /// Checks that the vault is among those the user can create and edit
+    /// items in, using `op vault list --permission`.
+    fn allows_set(&self) -> bool {
+        self.config.item.is_none()
+    }
+
+    fn check_writable(&self, _project: &str, profile: &str) -> Result<()> {
+        self.require_item_unset()?;
+        self.require_signin()?;
+
+        let vault = self.get_vault_name(profile);
+        let output = self.execute_op_command(&[
+            "vault",
+            "list",
+            "--permission",
+            WRITE_PERMISSIONS,
+            "--format",
+            "json",
+        ])?;
+        let vaults: Vec<OnePasswordVaultSummary> = serde_json::from_str(&output)?;
+        if vaults.iter().any(|v| v.name == vault || v.id == vault) {
+            Ok(())
+        } else {
+            Err(SecretSpecError::PermissionDenied(format!(
+                "cannot create and edit items in 1Password vault '{}'",
+                vault
+            )))
+        }
+    }
+
+    /// Deletes the item holding a secret.
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.require_item_unset()?;
+        self.require_signin()?;
+
+        let vault = self.get_vault_name(profile);
+        let item_name = self.format_item_name(project, key, profile);
+
+        match self.execute_op_command(&["item", "delete", &item_name, "--vault", &vault]) {
+            Ok(_) => Ok(()),
+            Err(SecretSpecError::NotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn allows_delete(&self) -> bool {
+        self.config.item.is_none()
+    }
+
+    /// Lists the keys of the items in the vault whose titles match the item
+    /// name format for the project and profile, or the field labels of the
+    /// item given in the URI.
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        self.require_signin()?;
+
+        if let Some(item) = &self.config.item {
+            let vault = self.get_vault_name(profile);
+            let args = ["item", "get", item, "--vault", &vault, "--format", "json"];
+            let item: OnePasswordItem = serde_json::from_str(&self.execute_op_command(&args)?)?;
+            let mut labels: Vec<String> = item
+                .fields
+                .into_iter()
+                .filter(|field| field.value.is_some())
+                .filter_map(|field| field.label.clone())
+                .filter(|label| !label.is_empty())
+                .collect();
+            labels.sort();
+            return Ok(labels);
+        }
+
+        let vault = self.get_vault_name(profile);
+        let output =
+            self.execute_op_command(&["item", "list", "--vault", &vault, "--format", "json"])?;
+        let items: Vec<OnePasswordItemSummary> = serde_json::from_str(&output)?;
+
+        // Titles are built from the format, so keys sit between its fixed parts
+        let pattern = self.format_item_name(project, "\0", profile);
+        let Some((prefix, suffix)) = pattern.split_once('\0') else {
+            return Ok(Vec::new());
+        };
+        let mut keys: Vec<String> = items
+            .iter()
+            .filter_map(|item| item.title.strip_prefix(prefix)?.strip_suffix(suffix))
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn allows_list(&self) -> bool {
+        true
+    }
+
+    /// Reads fields of an item by its title, in a single `op item get`.
+    ///
+    /// Fields are matched by label or ID, so both `username` and a custom
+    /// field label work.
+    fn get_fields(
+        &self,
+        _project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        self.require_signin()?;
+
+        let vault = self.get_vault_name(profile);
+        let args = ["item", "get", item, "--vault", &vault, "--format", "json"];
+        let output = match self.execute_op_command(&args) {
+            Ok(output) => output,
+            Err(SecretSpecError::NotFound(_)) => return Ok(HashMap::new()),
+            Err(e) => return Err(e),
+        };
+        let item: OnePasswordItem = serde_json::from_str(&output)?;
+
+        Ok(fields
+            .iter()
+            .filter_map(|name| {
+                let field = item
+                    .fields
+                    .iter()
+                    .find(|field| field.label.as_deref() == Some(*name) || field.id == *name)?;
+                let value = field.value.clone()?;
+                Some((name.to_string(), SecretString::new(value.into())))
+            })
+            .collect())
+    }
+
+    /// Lists the items in the vault named after the key: the item secretspec
+    /// would create for it, and items whose title is the key in another
+    /// spelling, like `Stripe API Key`.
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        self.require_signin()?;
+
+        let vault = self.get_vault_name(profile);
+        let output =
+            self.execute_op_command(&["item", "list", "--vault", &vault, "--format", "json"])?;
+        let items: Vec<OnePasswordItemSummary> = serde_json::from_str(&output)?;
+
+        let item_name = self.format_item_name(project, key, profile);
+        let normalized = normalize_key(key);
+        let mut matches: Vec<ItemMatch> = items
+            .into_iter()
+            .filter(|item| item.title == item_name || normalize_key(&item.title) == normalized)
+            .map(|item| ItemMatch {
+                id: item.id,
+                name: item.title,
+                location: Some(vault.clone()),
+            })
+            .collect();
+        matches.sort_by_key(|item| item.name != item_name);
+        Ok(matches)
+    }
+
+    /// Reads the secret from the item with `op item get <id>`.
+    fn get_by_id(
+        &self,
+        _project: &str,
+        _key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.require_signin()?;
+
+        let vault = self.get_vault_name(profile);
+        let output =
+            self.execute_op_command(&["item", "get", id, "--vault", &vault, "--format", "json"])?;
+        let item: OnePasswordItem = serde_json::from_str(&output)?;
+        let metadata = SecretMetadata {
+            revised: item.updated_at.clone(),
+            ..SecretMetadata::default()
+        };
+        Ok(item
+            .value_field()
+            .and_then(|field| field.value.clone())
+            .map(|value| SecretValue {
+                value: SecretString::new(value.into()),
+                metadata,
+            }))
+    }
+
+    /// Updates the field holding the secret with `op item edit <id>`, adding
+    /// a "value" field to items that have none.
+    fn set_by_id(
+        &self,
+        _project: &str,
+        _key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        self.require_signin()?;
+
+        let vault = self.get_vault_name(profile);
+        let output =
+            self.execute_op_command(&["item", "get", id, "--vault", &vault, "--format", "json"])?;
+        let item: OnePasswordItem = serde_json::from_str(&output)?;
+        let field = item
+            .value_field()
+            .map(|field| field.label.as_deref().unwrap_or(&field.id))
+            .unwrap_or("value");
+        let assignment = Zeroizing::new(format!("{}={}", field, value.expose_secret()));
+        self.execute_op_command(&["item", "edit", id, "--vault", &vault, &assignment])?;
+        Ok(())
+    }
+
+    /// Lists the vault of the URI, or every vault the account can reach
+    /// when it names none, with the titles of their items.
+    fn browse(&self) -> Result<Vec<ItemContainer>> {
+        self.require_signin()?;
+
+        let vaults = match &self.config.default_vault {
+            Some(vault) => vec![vault.clone()],
+            None => {
+                let output = self.execute_op_command(&["vault", "list", "--format", "json"])?;
+                let vaults: Vec<OnePasswordVaultSummary> = serde_json::from_str(&output)?;
+                vaults.into_iter().map(|vault| vault.name).collect()
+            }
+        };
+
+        vaults
+            .into_iter()
+            .map(|vault| {
+                let output = self
+                    .execute_op_command(&["item", "list", "--vault", &vault, "--format", "json"])?;
+                let items: Vec<OnePasswordItemSummary> = serde_json::from_str(&output)?;
+                let mut items: Vec<String> = items.into_iter().map(|item| item.title).collect();
+                items.sort();
+                Ok(ItemContainer { name: vault, items })
+            })
+            .collect()
+    }
+
+    /// Lists the users with access to the vault secrets are stored in.
+    ///
+    /// Access is managed per vault, so the result covers every project and
+    /// profile stored in the same vault.
+    fn list_access(&self, _project: &str, profile: &str) -> Result<Vec<AccessEntry>> {
+        self.require_signin()?;
+        let vault = self.get_vault_name(profile);
+        let output =
+            self.execute_op_command(&["vault", "user", "list", &vault, "--format", "json"])?;
+        let users: Vec<OnePasswordVaultUser> = serde_json::from_str(&output)?;
+        Ok(users
+            .into_iter()
+            .map(|user| AccessEntry {
+                principal: user.email,
+                permissions: user.permissions,
+            })
+            .collect())
+    }
+
+    /// Builds the policy granting a user the `view_items` permission on the
+    /// vault secrets are stored in.
+    fn access_policy(
+        &self,
+        _project: &str,
+        profile: &str,
+        principal: &str,
+    ) -> Result<AccessPolicy> {
+        let vault = self.get_vault_name(profile);
+        let document = serde_json::json!({
+            "vault": vault,
+            "user": principal,
+            "permissions": [READ_PERMISSION],
+        });
+        Ok(AccessPolicy {
+            principal: principal.to_string(),
+            scope: format!("vault '{}'", vault),
+            document: serde_json::to_string_pretty(&document)?,
+        })
+    }
+
+    /// Grants a user read access to the vault with `op vault user grant`.
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.require_signin()?;
+        let policy = self.access_policy(project, profile, principal)?;
+        let vault = self.get_vault_name(profile);
+        self.execute_op_command(&[
+            "vault",
+            "user",
+            "grant",
+            "--vault",
+            &vault,
+            "--user",
+            principal,
+            "--permissions",
+            READ_PERMISSION,
+        ])?;
+        Ok(policy)
+    }
+
+    /// Checks that the `op` CLI is installed and signed in.
+    fn doctor(&self) -> Vec<Check> {
+        let cli = doctor::check_cli("OnePassword CLI", self.execute_op_command(&["--version"]));
+        if cli.status != Status::Pass {
+            return vec![cli];
+        }
+
+        let auth = match self.whoami() {
+            Ok(true) => Check::pass("OnePassword authentication", "Signed in"),
+            Ok(false) => Check::fail(
+                "OnePassword authentication",
+                "Not signed in",
+                Some("Run 'eval $(op signin)'".to_string()),
+            ),
+            Err(e) => Check::from_error("OnePassword authentication", &e),
+        };
+        vec![cli, auth]
+    }
 }
 
 impl Default for OnePasswordProvider {
@@ -0,0 +1,703 @@
+//! Deployment platforms as secret targets.
+//!
+//! The `heroku`, `fly`, `railway`, `vercel` and `netlify` providers write to
+//! the variables of a deployed app through the platform's CLI, so
+//! `secretspec sync` can push a profile's resolved secrets instead of someone
+//! pasting them into a dashboard:
+//!
+//! ```text
+//! heroku://my-app                        config vars of a Heroku app
+//! fly://my-app                           secrets of a Fly.io app
+//! railway://web?environment=production   variables of a Railway service
+//! vercel://prj_123?team=team_456         environment variables of a Vercel project
+//! netlify://my-site                      environment variables of a Netlify site
+//! ```
+//!
+//! Heroku, Fly.io and Railway apps have a single set of variables, so the
+//! project and profile are ignored; point each profile at its own app
+//! instead. Every write restarts or redeploys the app, so several values are
+//! always written with a single command. Fly.io never hands secret values
+//! back out: its secrets can be listed and overwritten but not read.
+//!
+//! Vercel and Netlify keep separate variables per environment, so the profile
+//! picks the environment: `production` writes to production, `preview` and
+//! `staging` to preview deploys, `default` and `development` to local
+//! development, and any other profile to the environment of the same name.
+
+use super::command::{CommandRunner, Invocation, SystemRunner};
+use super::{Provider, operation_unsupported};
+use crate::doctor::{self, Check};
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use url::Url;
+use zeroize::Zeroizing;
+
+/// A deployment platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// Config vars of a Heroku app
+    Heroku,
+    /// Secrets of a Fly.io app
+    Fly,
+    /// Variables of a Railway service
+    Railway,
+    /// Environment variables of a Vercel project
+    Vercel,
+    /// Environment variables of a Netlify site
+    Netlify,
+}
+
+impl Platform {
+    /// Returns the platform of a URI scheme.
+    fn from_scheme(scheme: &str) -> Option<Self> {
+        match scheme {
+            "heroku" => Some(Self::Heroku),
+            "fly" => Some(Self::Fly),
+            "railway" => Some(Self::Railway),
+            "vercel" => Some(Self::Vercel),
+            "netlify" => Some(Self::Netlify),
+            _ => None,
+        }
+    }
+
+    /// Returns the provider name, which is also the URI scheme.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Heroku => "heroku",
+            Self::Fly => "fly",
+            Self::Railway => "railway",
+            Self::Vercel => "vercel",
+            Self::Netlify => "netlify",
+        }
+    }
+
+    /// Returns the program of the platform's CLI.
+    fn cli(self) -> &'static str {
+        self.name()
+    }
+
+    /// Returns the environment a profile writes to on platforms that keep
+    /// variables per environment.
+    fn environment_of(self, profile: &str) -> &str {
+        match (self, profile) {
+            (_, "production" | "prod") => "production",
+            (Self::Netlify, "preview" | "staging") => "deploy-preview",
+            (_, "preview" | "staging") => "preview",
+            (Self::Netlify, "default" | "development" | "dev") => "dev",
+            (_, "default" | "development" | "dev") => "development",
+            (_, other) => other,
+        }
+    }
+
+    /// Returns how to install the CLI.
+    fn install_hint(self) -> &'static str {
+        match self {
+            Self::Heroku => {
+                "To install it:\n  - macOS: brew install heroku/brew/heroku\n  - Linux: curl https://cli-assets.heroku.com/install.sh | sh\n  - NixOS: nix-env -iA nixpkgs.heroku"
+            }
+            Self::Fly => {
+                "To install it:\n  - macOS: brew install flyctl\n  - Linux: curl -L https://fly.io/install.sh | sh\n  - NixOS: nix-env -iA nixpkgs.flyctl"
+            }
+            Self::Railway => {
+                "To install it:\n  - macOS: brew install railway\n  - Linux: npm install -g @railway/cli\n  - NixOS: nix-env -iA nixpkgs.railway"
+            }
+            Self::Vercel => {
+                "To install it:\n  - npm install -g vercel\n  - NixOS: nix-env -iA nixpkgs.nodePackages.vercel"
+            }
+            Self::Netlify => {
+                "To install it:\n  - npm install -g netlify-cli\n  - macOS: brew install netlify-cli\n  - NixOS: nix-env -iA nixpkgs.netlify-cli"
+            }
+        }
+    }
+
+    /// Returns how to sign in to the CLI.
+    fn login_hint(self) -> &'static str {
+        match self {
+            Self::Heroku => "Run 'heroku login' or set HEROKU_API_KEY.",
+            Self::Fly => "Run 'fly auth login' or set FLY_API_TOKEN.",
+            Self::Railway => "Run 'railway login' or set RAILWAY_TOKEN.",
+            Self::Vercel => "Run 'vercel login' or set VERCEL_TOKEN.",
+            Self::Netlify => "Run 'netlify login' or set NETLIFY_AUTH_TOKEN.",
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Heroku => "Heroku",
+            Self::Fly => "Fly.io",
+            Self::Railway => "Railway",
+            Self::Vercel => "Vercel",
+            Self::Netlify => "Netlify",
+        })
+    }
+}
+
+/// Configuration for a deployment platform provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaasConfig {
+    /// The platform
+    pub platform: Platform,
+    /// The app (Heroku, Fly.io), service (Railway), project (Vercel) or site
+    /// (Netlify), or `None` for the one linked to the current directory
+    pub app: Option<String>,
+    /// The environment to write to, instead of the linked one (Railway) or
+    /// the one the profile maps to (Vercel, Netlify)
+    pub environment: Option<String>,
+    /// The Vercel team or user owning the project
+    pub team: Option<String>,
+    /// Whether Fly.io secrets are staged for the next deploy instead of
+    /// restarting the app
+    pub stage: bool,
+}
+
+impl TryFrom<&Url> for PaasConfig {
+    type Error = SecretSpecError;
+
+    /// Creates a PaasConfig from a URL like `fly://my-app?stage=true`.
+    fn try_from(url: &Url) -> std::result::Result<Self, Self::Error> {
+        let platform = Platform::from_scheme(url.scheme()).ok_or_else(|| {
+            SecretSpecError::ProviderOperationFailed(format!(
+                "Invalid scheme '{}' for a deployment platform provider",
+                url.scheme()
+            ))
+        })?;
+
+        let app = url
+            .host_str()
+            .map(|host| format!("{}{}", host, url.path()))
+            .unwrap_or_else(|| url.path().to_string());
+        let app = Some(app.trim_matches('/').to_string()).filter(|app| !app.is_empty());
+        if app.is_none() && matches!(platform, Platform::Heroku | Platform::Fly) {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "{} provider requires an app, e.g. {}://my-app",
+                platform,
+                platform.name()
+            )));
+        }
+
+        let mut config = Self {
+            platform,
+            app,
+            environment: None,
+            team: None,
+            stage: false,
+        };
+        for (key, value) in url.query_pairs() {
+            match (platform, key.as_ref()) {
+                (Platform::Railway | Platform::Vercel | Platform::Netlify, "environment") => {
+                    config.environment = Some(value.into_owned())
+                }
+                (Platform::Vercel, "team") => config.team = Some(value.into_owned()),
+                (Platform::Fly, "stage") => config.stage = value == "true",
+                _ => {}
+            }
+        }
+        // The Vercel CLI only picks a project by ID together with its owner
+        if platform == Platform::Vercel && config.app.is_some() && config.team.is_none() {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "vercel provider needs the team or user owning the project, e.g. vercel://prj_123?team=team_456".to_string(),
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// A secret as listed by `fly secrets list --json`.
+#[derive(Deserialize)]
+struct FlySecret {
+    #[serde(alias = "Name")]
+    name: String,
+}
+
+/// Variables of an app or environment. Fly.io values are `None` since they
+/// can't be read.
+type Vars = BTreeMap<String, Option<Zeroizing<String>>>;
+
+/// Provider writing to the variables of a deployed app.
+pub struct PaasProvider {
+    config: PaasConfig,
+    /// Runs the platform's CLI
+    runner: Arc<dyn CommandRunner>,
+    /// The variables of each environment, read once and dropped after every
+    /// write. Platforms without environments use the empty name.
+    vars: Mutex<BTreeMap<String, Vars>>,
+}
+
+/// Registers a platform's scheme.
+macro_rules! register_platform {
+    ($static:ident, $name:literal, $description:literal, $example:literal, $params:expr) => {
+        #[cfg_attr(
+            feature = "native",
+            linkme::distributed_slice(super::PROVIDER_REGISTRY)
+        )]
+        #[cfg_attr(not(feature = "native"), allow(dead_code))]
+        static $static: super::ProviderRegistration = super::ProviderRegistration {
+            info: super::ProviderInfo {
+                name: $name,
+                description: $description,
+                examples: &[$example],
+            },
+            schemes: &[$name],
+            params: |_| $params,
+            factory: |url| Ok(Box::new(PaasProvider::new(PaasConfig::try_from(url)?))),
+            explain: |url| Ok(format!("{:#?}", PaasConfig::try_from(url)?)),
+        };
+    };
+}
+
+register_platform!(
+    HEROKU_REGISTRATION,
+    "heroku",
+    "Heroku config vars (write target)",
+    "heroku://my-app",
+    &[]
+);
+register_platform!(
+    FLY_REGISTRATION,
+    "fly",
+    "Fly.io app secrets (write target)",
+    "fly://my-app",
+    &["stage"]
+);
+register_platform!(
+    RAILWAY_REGISTRATION,
+    "railway",
+    "Railway service variables (write target)",
+    "railway://web?environment=production",
+    &["environment"]
+);
+register_platform!(
+    VERCEL_REGISTRATION,
+    "vercel",
+    "Vercel project environment variables (write target)",
+    "vercel://prj_123?team=team_456",
+    &["environment", "team"]
+);
+register_platform!(
+    NETLIFY_REGISTRATION,
+    "netlify",
+    "Netlify site environment variables (write target)",
+    "netlify://my-site",
+    &["environment"]
+);
+
+impl PaasProvider {
+    /// Creates a new PaasProvider with the given configuration.
+    pub fn new(config: PaasConfig) -> Self {
+        Self::with_runner(config, Arc::new(SystemRunner))
+    }
+
+    /// Creates a PaasProvider that runs the platform's CLI through `runner`.
+    pub(crate) fn with_runner(config: PaasConfig, runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            config,
+            runner,
+            vars: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the environment a profile writes to, if the platform has
+    /// them.
+    fn environment(&self, profile: &str) -> Option<String> {
+        match self.config.platform {
+            Platform::Heroku | Platform::Fly => None,
+            Platform::Railway => self.config.environment.clone(),
+            platform @ (Platform::Vercel | Platform::Netlify) => Some(
+                self.config
+                    .environment
+                    .clone()
+                    .unwrap_or_else(|| platform.environment_of(profile).to_string()),
+            ),
+        }
+    }
+
+    /// Returns an invocation of the platform's CLI.
+    ///
+    /// The Vercel and Netlify CLIs have no flag selecting the project or
+    /// site, so it is passed in their environment variables.
+    fn cli(&self) -> Invocation {
+        let invocation = Invocation::new(self.config.platform.cli());
+        match (self.config.platform, &self.config.app) {
+            (Platform::Vercel, Some(project)) => invocation
+                .env("VERCEL_PROJECT_ID", project)
+                .env("VERCEL_ORG_ID", self.config.team.as_deref().unwrap_or("")),
+            (Platform::Netlify, Some(site)) => invocation.env("NETLIFY_SITE_ID", site),
+            _ => invocation,
+        }
+    }
+
+    /// Returns the arguments selecting the app, service and environment of
+    /// Heroku, Fly.io and Railway.
+    fn target_args(&self) -> Vec<&str> {
+        let app_flag = match self.config.platform {
+            Platform::Railway => "--service",
+            _ => "--app",
+        };
+        let mut args = Vec::new();
+        if let Some(app) = &self.config.app {
+            args.extend([app_flag, app.as_str()]);
+        }
+        if let Some(environment) = &self.config.environment {
+            args.extend(["--environment", environment.as_str()]);
+        }
+        args
+    }
+
+    /// Runs the platform's CLI and returns its standard output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CLI isn't installed, isn't signed in or fails
+    fn run(&self, invocation: Invocation) -> Result<Zeroizing<String>> {
+        let platform = self.config.platform;
+        let output = match self.runner.run(&invocation) {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(SecretSpecError::CliMissing {
+                    cli: format!("{} CLI ({})", platform, platform.cli()),
+                    install_hint: platform.install_hint().to_string(),
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let stdout = output.stdout;
+        if !output.success {
+            let stderr = output.stderr.trim();
+            let lower = stderr.to_lowercase();
+            if ["login", "logged in", "unauthorized"]
+                .iter()
+                .any(|hint| lower.contains(hint))
+            {
+                return Err(SecretSpecError::AuthRequired {
+                    provider: platform.to_string(),
+                    fix: platform.login_hint().to_string(),
+                });
+            }
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "{} {} failed: {}",
+                platform.cli(),
+                invocation.args.first().map_or("", String::as_str),
+                stderr
+            )));
+        }
+        Ok(stdout)
+    }
+
+    /// Reads the variables of an environment.
+    fn read_vars(&self, environment: Option<&str>) -> Result<Vars> {
+        let parse_error = |e: serde_json::Error| {
+            SecretSpecError::ProviderOperationFailed(format!(
+                "Unexpected output from the {} CLI: {}",
+                self.config.platform, e
+            ))
+        };
+        let readable = |vars: BTreeMap<String, String>| {
+            vars.into_iter()
+                .map(|(key, value)| (key, Some(Zeroizing::new(value))))
+                .collect()
+        };
+        let environment = environment.unwrap_or_default();
+        match self.config.platform {
+            Platform::Heroku | Platform::Railway => {
+                let args = match self.config.platform {
+                    Platform::Heroku => vec!["config", "--json"],
+                    _ => vec!["variables", "--json"],
+                };
+                let output = self.run(self.cli().args(args).args(self.target_args()))?;
+                Ok(readable(
+                    serde_json::from_str(&output).map_err(parse_error)?,
+                ))
+            }
+            Platform::Fly => {
+                let output = self.run(
+                    self.cli()
+                        .args(["secrets", "list", "--json"])
+                        .args(self.target_args()),
+                )?;
+                let secrets: Vec<FlySecret> = serde_json::from_str(&output).map_err(parse_error)?;
+                Ok(secrets
+                    .into_iter()
+                    .map(|secret| (secret.name, None))
+                    .collect())
+            }
+            Platform::Netlify => {
+                let output =
+                    self.run(
+                        self.cli()
+                            .args(["env:list", "--json", "--context", environment]),
+                    )?;
+                Ok(readable(
+                    serde_json::from_str(&output).map_err(parse_error)?,
+                ))
+            }
+            Platform::Vercel => {
+                // `vercel env pull` only writes to a file, which is private
+                // to the user and removed once read
+                let file = tempfile::NamedTempFile::new()?;
+                let path = file.path().to_string_lossy();
+                self.run(self.cli().args([
+                    "env",
+                    "pull",
+                    path.as_ref(),
+                    "--environment",
+                    environment,
+                    "--yes",
+                ]))?;
+                let mut vars = Vars::new();
+                for item in dotenvy::from_path_iter(file.path())? {
+                    let (key, value) = item?;
+                    // Variables Vercel adds to every environment
+                    if key != "VERCEL" && !key.starts_with("VERCEL_") {
+                        vars.insert(key, Some(Zeroizing::new(value)));
+                    }
+                }
+                Ok(vars)
+            }
+        }
+    }
+
+    /// Runs `read` on the variables of an environment, reading them on first
+    /// use.
+    fn with_vars<T>(
+        &self,
+        environment: Option<&str>,
+        read: impl FnOnce(&Vars) -> Result<T>,
+    ) -> Result<T> {
+        let mut vars = self.vars.lock().unwrap_or_else(|e| e.into_inner());
+        let name = environment.unwrap_or_default();
+        if !vars.contains_key(name) {
+            let read = self.read_vars(environment)?;
+            vars.insert(name.to_string(), read);
+        }
+        read(&vars[name])
+    }
+
+    /// Drops the cached variables of an environment after a write.
+    fn invalidate(&self, environment: Option<&str>) {
+        self.vars
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(environment.unwrap_or_default());
+    }
+}
+
+/// Formats values for `fly secrets import`, which reads `NAME=VALUE` lines
+/// and takes values spanning several lines in triple quotes.
+fn fly_import(values: &[(String, SecretString)]) -> Zeroizing<String> {
+    let mut input = Zeroizing::new(String::new());
+    for (key, value) in values {
+        let value = value.expose_secret();
+        if value.contains('\n') {
+            input.push_str(&format!("{}=\"\"\"\n{}\n\"\"\"\n", key, value));
+        } else {
+            input.push_str(&format!("{}={}\n", key, value));
+        }
+    }
+    input
+}
+
+impl Provider for PaasProvider {
+    fn name(&self) -> &'static str {
+        self.config.platform.name()
+    }
+
+    /// Reads a variable of the app.
+    ///
+    /// # Errors
+    ///
+    /// Fly.io secrets can't be read, so reading one that is set fails
+    fn get(&self, _project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        let environment = self.environment(profile);
+        self.with_vars(environment.as_deref(), |vars| match vars.get(key) {
+            None => Ok(None),
+            Some(Some(value)) => Ok(Some(SecretString::from(value.as_str()))),
+            Some(None) => Err(SecretSpecError::ProviderOperationFailed(format!(
+                "{} is set on {}, which doesn't let secrets be read back",
+                key, self.config.platform
+            ))),
+        })
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.set_many(project, &[(key.to_string(), value.clone())], profile)
+    }
+
+    /// Writes all values with one command where the platform allows it, so
+    /// the app restarts once.
+    ///
+    /// Fly.io and Vercel read the values from stdin. The Heroku, Railway and
+    /// Netlify CLIs only take them as arguments.
+    fn set_many(
+        &self,
+        _project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        let environment = self.environment(profile);
+        self.invalidate(environment.as_deref());
+        let environment = environment.unwrap_or_default();
+        let pairs: Vec<Zeroizing<String>> = values
+            .iter()
+            .map(|(key, value)| Zeroizing::new(format!("{}={}", key, value.expose_secret())))
+            .collect();
+        let invocation = match self.config.platform {
+            Platform::Heroku => self
+                .cli()
+                .args(["config:set"])
+                .args(pairs.iter().map(|pair| pair.as_str()))
+                .args(self.target_args()),
+            Platform::Railway => {
+                let mut invocation = self.cli().args(["variables"]).args(self.target_args());
+                for pair in &pairs {
+                    invocation = invocation.args(["--set", pair.as_str()]);
+                }
+                invocation
+            }
+            Platform::Fly => {
+                let mut invocation = self
+                    .cli()
+                    .args(["secrets", "import"])
+                    .args(self.target_args());
+                if self.config.stage {
+                    invocation = invocation.args(["--stage"]);
+                }
+                invocation.stdin(fly_import(values).as_bytes())
+            }
+            // Neither CLI sets several variables at once, and changing them
+            // doesn't redeploy anything
+            Platform::Vercel => {
+                for (key, value) in values {
+                    self.run(
+                        self.cli()
+                            .args(["env", "add", key.as_str(), environment.as_str(), "--force"])
+                            .stdin(value.expose_secret().as_bytes()),
+                    )?;
+                }
+                return Ok(());
+            }
+            Platform::Netlify => {
+                for (key, value) in values {
+                    self.run(self.cli().args([
+                        "env:set",
+                        key.as_str(),
+                        value.expose_secret(),
+                        "--context",
+                        environment.as_str(),
+                        "--force",
+                    ]))?;
+                }
+                return Ok(());
+            }
+        };
+        self.run(invocation)?;
+        Ok(())
+    }
+
+    /// Checks that the CLI is signed in and can see the app.
+    fn check_writable(&self, _project: &str, profile: &str) -> Result<()> {
+        self.with_vars(self.environment(profile).as_deref(), |_| Ok(()))
+    }
+
+    fn delete(&self, _project: &str, key: &str, profile: &str) -> Result<()> {
+        let environment = self.environment(profile);
+        let invocation = match self.config.platform {
+            Platform::Heroku => self
+                .cli()
+                .args(["config:unset", key])
+                .args(self.target_args()),
+            Platform::Fly => {
+                let invocation = self
+                    .cli()
+                    .args(["secrets", "unset", key])
+                    .args(self.target_args());
+                if self.config.stage {
+                    invocation.args(["--stage"])
+                } else {
+                    invocation
+                }
+            }
+            Platform::Vercel => self.cli().args([
+                "env",
+                "rm",
+                key,
+                environment.as_deref().unwrap_or_default(),
+                "--yes",
+            ]),
+            Platform::Netlify => self.cli().args([
+                "env:unset",
+                key,
+                "--context",
+                environment.as_deref().unwrap_or_default(),
+                "--force",
+            ]),
+            Platform::Railway => return Err(operation_unsupported("railway", "deleting")),
+        };
+        self.invalidate(environment.as_deref());
+        self.run(invocation)?;
+        Ok(())
+    }
+
+    fn allows_delete(&self) -> bool {
+        self.config.platform != Platform::Railway
+    }
+
+    fn list_keys(&self, _project: &str, profile: &str) -> Result<Vec<String>> {
+        self.with_vars(self.environment(profile).as_deref(), |vars| {
+            Ok(vars.keys().cloned().collect())
+        })
+    }
+
+    fn allows_list(&self) -> bool {
+        true
+    }
+
+    fn doctor(&self) -> Vec<Check> {
+        let platform = self.config.platform;
+        let version = self
+            .run(self.cli().args(["--version"]))
+            .map(|output| output.to_string());
+        let cli = doctor::check_cli(&format!("{} CLI", platform), version);
+        let environment = self.environment("default");
+        let access = match self.with_vars(environment.as_deref(), |vars| Ok(vars.len())) {
+            Ok(count) => Check::pass(
+                format!("{} app", platform),
+                format!(
+                    "{} ({} variables)",
+                    self.config.app.as_deref().unwrap_or("linked app"),
+                    count
+                ),
+            ),
+            Err(e) => Check::from_error(format!("{} app", platform), &e),
+        };
+        vec![cli, access]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fly_import() {
+        let values = [
+            ("API_KEY".to_string(), SecretString::from("sk-123")),
+            (
+                "TLS_KEY".to_string(),
+                SecretString::from("-----BEGIN KEY-----\nabc\n-----END KEY-----"),
+            ),
+        ];
+        assert_eq!(
+            fly_import(&values).as_str(),
+            "API_KEY=sk-123\nTLS_KEY=\"\"\"\n-----BEGIN KEY-----\nabc\n-----END KEY-----\n\"\"\"\n"
+        );
+    }
+}
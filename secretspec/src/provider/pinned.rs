@@ -0,0 +1,209 @@
+//! Addressing adopted items by ID.
+//!
+//! `secretspec adopt` records the IDs of the provider items it takes over in
+//! `secretspec.lock`. The [`PinnedProvider`] wrapper reads and writes those
+//! secrets through [`Provider::get_by_id`] and [`Provider::set_by_id`], so
+//! renaming an item or adding another one with a similar name can't make
+//! secretspec pick the wrong one. Secrets that weren't adopted are still
+//! found by name.
+
+use super::macros::forward_provider;
+use super::{Provider, SecretValue};
+use crate::{Result, SecretSpecError};
+use secrecy::SecretString;
+use std::collections::BTreeMap;
+
+/// Provider wrapper that addresses adopted items by their IDs.
+pub struct PinnedProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+    /// Item IDs, keyed by profile name and then secret name
+    items: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl PinnedProvider {
+    /// Wraps the provider if any items were adopted.
+    pub fn wrap(
+        inner: Box<dyn Provider>,
+        items: BTreeMap<String, BTreeMap<String, String>>,
+    ) -> Box<dyn Provider> {
+        if items.values().all(BTreeMap::is_empty) {
+            inner
+        } else {
+            Box::new(Self { inner, items })
+        }
+    }
+
+    /// Returns the ID of the item a secret was adopted from.
+    fn id(&self, key: &str, profile: &str) -> Option<&str> {
+        self.items.get(profile)?.get(key).map(String::as_str)
+    }
+
+    /// Returns the error for deleting an adopted item, which secretspec
+    /// can only find by name.
+    fn adopted(&self, key: &str) -> SecretSpecError {
+        SecretSpecError::InvalidInput(format!(
+            "'{}' is an adopted {} item; delete it there and remove it from secretspec.lock",
+            key,
+            self.inner.name()
+        ))
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for PinnedProvider {
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        match self.id(key, profile) {
+            Some(id) => Ok(self
+                .inner
+                .get_by_id(project, key, id, profile)?
+                .map(|secret| secret.value)),
+            None => self.inner.get(project, key, profile),
+        }
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        match self.id(key, profile) {
+            Some(id) => self.inner.get_by_id(project, key, id, profile),
+            None => self.inner.get_with_metadata(project, key, profile),
+        }
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        match self.id(key, profile) {
+            Some(id) => self.inner.set_by_id(project, key, id, value, profile),
+            None => self.inner.set(project, key, value, profile),
+        }
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        let (pinned, unpinned): (Vec<_>, Vec<_>) = values
+            .iter()
+            .cloned()
+            .partition(|(key, _)| self.id(key, profile).is_some());
+        for (key, value) in &pinned {
+            self.set(project, key, value, profile)?;
+        }
+        if unpinned.is_empty() {
+            return Ok(());
+        }
+        self.inner.set_many(project, &unpinned, profile)
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        let Some(id) = self.id(key, profile) else {
+            return self
+                .inner
+                .compare_and_set(project, key, value, profile, expected);
+        };
+        let current = self.inner.get_by_id(project, key, id, profile)?;
+        match current.as_ref().and_then(|c| c.metadata.revision()) {
+            Some(revision) if revision == expected => {
+                self.inner.set_by_id(project, key, id, value, profile)
+            }
+            Some(revision) => Err(SecretSpecError::Conflict(format!(
+                "'{}' was changed since revision {}; it is now at revision {}",
+                key, expected, revision
+            ))),
+            None => Err(SecretSpecError::Conflict(format!(
+                "'{}' has no revision to compare with {}",
+                key, expected
+            ))),
+        }
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        if self.id(key, profile).is_some() {
+            return Err(self.adopted(key));
+        }
+        self.inner.delete(project, key, profile)
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        if self.id(key, profile).is_some() {
+            return Err(self.adopted(key));
+        }
+        self.inner.purge(project, key, profile)
+    }
+
+    forward_provider!(inner =>
+        allows_set, check_writable, allows_delete, restore, allows_restore, history, list_keys,
+        allows_list, get_fields, find_items, get_by_id, set_by_id, browse, max_value_size,
+        works_in_sandbox, doctor, list_access, access_policy, grant, leases, renew_lease,
+        revoke_lease, name,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::tests::MockProvider;
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn test_pinned_items_are_addressed_by_id() {
+        let mock = MockProvider::new();
+        mock.set(
+            "legacy",
+            "Stripe Key",
+            &SecretString::from("sk_old"),
+            "shared",
+        )
+        .unwrap();
+        let items = BTreeMap::from([(
+            "default".to_string(),
+            BTreeMap::from([(
+                "STRIPE_KEY".to_string(),
+                "legacy/shared/Stripe Key".to_string(),
+            )]),
+        )]);
+        let provider = PinnedProvider::wrap(Box::new(mock.clone()), items);
+
+        let value = provider
+            .get("app", "STRIPE_KEY", "default")
+            .unwrap()
+            .unwrap();
+        assert_eq!(value.expose_secret(), "sk_old");
+        provider
+            .set(
+                "app",
+                "STRIPE_KEY",
+                &SecretString::from("sk_new"),
+                "default",
+            )
+            .unwrap();
+        let value = mock.get("legacy", "Stripe Key", "shared").unwrap().unwrap();
+        assert_eq!(value.expose_secret(), "sk_new");
+        assert!(mock.get("app", "STRIPE_KEY", "default").unwrap().is_none());
+        assert!(provider.delete("app", "STRIPE_KEY", "default").is_err());
+
+        // Other secrets and profiles are found by name
+        provider
+            .set("app", "OTHER", &SecretString::from("x"), "default")
+            .unwrap();
+        assert!(mock.get("app", "OTHER", "default").unwrap().is_some());
+        assert!(
+            provider
+                .get("app", "STRIPE_KEY", "production")
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Retrying operations that fail transiently.
+//!
+//! The [`RetryingProvider`] wrapper repeats an operation that failed with a
+//! [transient](crate::SecretSpecError::is_transient) error, such as a rate
+//! limit, waiting as long as the provider asked or else backing off
+//! exponentially. Other errors are returned at once.
+
+use super::macros::forward_provider;
+use super::{
+    AccessEntry, AccessPolicy, ItemContainer, ItemMatch, Lease, Provider, SecretMetadata,
+    SecretValue,
+};
+use crate::Result;
+use secrecy::SecretString;
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+/// Wait before the first retry when the provider doesn't say how long.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Longest wait between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Provider wrapper that retries transient failures.
+pub struct RetryingProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+    /// Attempts made per operation, including the first
+    attempts: u32,
+}
+
+impl RetryingProvider {
+    /// Creates a new RetryingProvider making up to `attempts` attempts per
+    /// operation, and at least one.
+    pub fn new(inner: Box<dyn Provider>, attempts: u32) -> Self {
+        Self {
+            inner,
+            attempts: attempts.max(1),
+        }
+    }
+
+    /// Runs an operation, retrying it while it fails transiently.
+    fn retry<T>(&self, operation: impl Fn() -> Result<T>) -> Result<T> {
+        let mut backoff = INITIAL_BACKOFF;
+        for _ in 1..self.attempts {
+            match operation() {
+                Err(e) if e.is_transient() => {
+                    thread::sleep(e.retry_after().unwrap_or(backoff).min(MAX_BACKOFF));
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                result => return result,
+            }
+        }
+        operation()
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for RetryingProvider {
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        self.retry(|| self.inner.get(project, key, profile))
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.retry(|| self.inner.get_with_metadata(project, key, profile))
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.retry(|| self.inner.set(project, key, value, profile))
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        self.retry(|| self.inner.set_many(project, values, profile))
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        self.retry(|| {
+            self.inner
+                .compare_and_set(project, key, value, profile, expected)
+        })
+    }
+
+    fn check_writable(&self, project: &str, profile: &str) -> Result<()> {
+        self.retry(|| self.inner.check_writable(project, profile))
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.retry(|| self.inner.delete(project, key, profile))
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.retry(|| self.inner.purge(project, key, profile))
+    }
+
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.retry(|| self.inner.restore(project, key, profile))
+    }
+
+    fn history(&self, project: &str, key: &str, profile: &str) -> Result<Vec<SecretMetadata>> {
+        self.retry(|| self.inner.history(project, key, profile))
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        self.retry(|| self.inner.list_keys(project, profile))
+    }
+
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        self.retry(|| self.inner.get_fields(project, item, fields, profile))
+    }
+
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        self.retry(|| self.inner.find_items(project, key, profile))
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.retry(|| self.inner.get_by_id(project, key, id, profile))
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        self.retry(|| self.inner.set_by_id(project, key, id, value, profile))
+    }
+
+    fn browse(&self) -> Result<Vec<ItemContainer>> {
+        self.retry(|| self.inner.browse())
+    }
+
+    fn list_access(&self, project: &str, profile: &str) -> Result<Vec<AccessEntry>> {
+        self.retry(|| self.inner.list_access(project, profile))
+    }
+
+    fn access_policy(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.retry(|| self.inner.access_policy(project, profile, principal))
+    }
+
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.retry(|| self.inner.grant(project, profile, principal))
+    }
+
+    fn renew_lease(&self, lease: &Lease) -> Result<Lease> {
+        self.retry(|| self.inner.renew_lease(lease))
+    }
+
+    fn revoke_lease(&self, lease: &Lease) -> Result<()> {
+        self.retry(|| self.inner.revoke_lease(lease))
+    }
+
+    forward_provider!(inner =>
+        allows_set, allows_delete, allows_restore, allows_list, max_value_size, works_in_sandbox,
+        doctor, leases, name,
+    );
+}
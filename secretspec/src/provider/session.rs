@@ -0,0 +1,324 @@
+//! Provider session tokens cached between commands.
+//!
+//! Providers that log in or unlock on first use, like Vault with LDAP or
+//! Kerberos and Bitwarden with `password_ref`, get a short-lived token in
+//! return. Caching it lets separate secretspec invocations reuse the session
+//! instead of unlocking again every time.
+//!
+//! On Linux tokens are kept in the kernel's session keyring, so they never
+//! reach the disk, the kernel drops them when they expire, and they are
+//! gone at the end of the login session. Elsewhere they are kept in the OS
+//! keyring. Entries are keyed per server and user by the provider.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Seconds before expiry at which a cached token is no longer used.
+const EXPIRY_MARGIN: u64 = 60;
+
+/// A token stored in the cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CachedToken {
+    /// The token
+    pub token: String,
+    /// Unix time at which the token expires, or `None` if it never does
+    pub expires_at: Option<u64>,
+}
+
+impl CachedToken {
+    /// Creates a cache entry for a token valid for `ttl`, or forever.
+    pub fn new(token: String, ttl: Option<Duration>) -> Self {
+        Self {
+            token,
+            expires_at: ttl.map(|ttl| now() + ttl.as_secs()),
+        }
+    }
+
+    /// Returns whether the token is still usable.
+    pub fn is_fresh(&self) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| now() + EXPIRY_MARGIN < expires_at)
+    }
+}
+
+/// Returns the current Unix time in seconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Stores session tokens between commands.
+pub(crate) trait TokenCache: Send + Sync {
+    /// Returns the token stored under `key`, if any.
+    fn load(&self, key: &str) -> Option<CachedToken>;
+
+    /// Stores a token under `key`.
+    fn store(&self, key: &str, token: &CachedToken);
+
+    /// Removes the token stored under `key`.
+    fn clear(&self, key: &str);
+}
+
+/// Returns the cache a provider keeps its tokens in, under `service`.
+///
+/// Cache failures are ignored: without a cache the provider simply logs in
+/// or unlocks again.
+pub(crate) fn default_cache(service: &'static str) -> Arc<dyn TokenCache> {
+    #[cfg(target_os = "linux")]
+    return Arc::new(SessionKeyringCache { service });
+    #[cfg(not(target_os = "linux"))]
+    Arc::new(KeyringTokenCache { service })
+}
+
+/// Keeps no tokens, so every command logs in again.
+pub(crate) struct NoTokenCache;
+
+impl TokenCache for NoTokenCache {
+    fn load(&self, _key: &str) -> Option<CachedToken> {
+        None
+    }
+
+    fn store(&self, _key: &str, _token: &CachedToken) {}
+
+    fn clear(&self, _key: &str) {}
+}
+
+/// Caches tokens in the OS keyring.
+#[cfg(not(target_os = "linux"))]
+struct KeyringTokenCache {
+    /// Keyring service that tokens are stored under
+    service: &'static str,
+}
+
+#[cfg(all(not(target_os = "linux"), feature = "keyring"))]
+impl TokenCache for KeyringTokenCache {
+    fn load(&self, key: &str) -> Option<CachedToken> {
+        let entry = keyring::Entry::new(self.service, key).ok()?;
+        serde_json::from_str(&entry.get_password().ok()?).ok()
+    }
+
+    fn store(&self, key: &str, token: &CachedToken) {
+        if let (Ok(entry), Ok(json)) = (
+            keyring::Entry::new(self.service, key),
+            serde_json::to_string(token),
+        ) {
+            let _ = entry.set_password(&json);
+        }
+    }
+
+    fn clear(&self, key: &str) {
+        if let Ok(entry) = keyring::Entry::new(self.service, key) {
+            let _ = entry.delete_credential();
+        }
+    }
+}
+
+/// Without keyring support tokens are only kept for the current command.
+#[cfg(all(not(target_os = "linux"), not(feature = "keyring")))]
+impl TokenCache for KeyringTokenCache {
+    fn load(&self, _key: &str) -> Option<CachedToken> {
+        let _ = self.service;
+        None
+    }
+
+    fn store(&self, _key: &str, _token: &CachedToken) {}
+
+    fn clear(&self, _key: &str) {}
+}
+
+/// Caches tokens in the Linux session keyring.
+///
+/// Processes without a session keyring of their own share the user's
+/// default session keyring. Keys expire with the token they hold.
+#[cfg(target_os = "linux")]
+struct SessionKeyringCache {
+    /// Prefix of the descriptions of the keys
+    service: &'static str,
+}
+
+#[cfg(target_os = "linux")]
+impl SessionKeyringCache {
+    /// Returns the description of the key holding the token stored under `key`.
+    fn description(&self, key: &str) -> String {
+        format!("{}:{}", self.service, key)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl TokenCache for SessionKeyringCache {
+    fn load(&self, key: &str) -> Option<CachedToken> {
+        let id = kernel::search(&self.description(key)).ok()?;
+        let payload = kernel::read(id).ok()?;
+        serde_json::from_slice(&payload).ok()
+    }
+
+    fn store(&self, key: &str, token: &CachedToken) {
+        let Ok(json) = serde_json::to_string(token) else {
+            return;
+        };
+        let json = zeroize::Zeroizing::new(json);
+        let Ok(id) = kernel::add(&self.description(key), json.as_bytes()) else {
+            return;
+        };
+        if let Some(expires_at) = token.expires_at {
+            let _ = kernel::set_timeout(id, expires_at.saturating_sub(now()).max(1));
+        }
+    }
+
+    fn clear(&self, key: &str) {
+        if let Ok(id) = kernel::search(&self.description(key)) {
+            let _ = kernel::invalidate(id);
+        }
+    }
+}
+
+/// Calls into the kernel key management facility, see keyrings(7).
+#[cfg(target_os = "linux")]
+mod kernel {
+    use std::ffi::CString;
+    use std::io;
+    use zeroize::Zeroizing;
+
+    /// Type of keys holding arbitrary user data.
+    const KEY_TYPE: &std::ffi::CStr = c"user";
+
+    /// Converts a key description to a C string.
+    fn c_string(description: &str) -> io::Result<CString> {
+        CString::new(description).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// Converts a syscall return value to a key id.
+    fn check(ret: libc::c_long) -> io::Result<i32> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret as i32)
+        }
+    }
+
+    /// Adds a key to the session keyring, replacing one with the same description.
+    pub(super) fn add(description: &str, payload: &[u8]) -> io::Result<i32> {
+        let description = c_string(description)?;
+        // SAFETY: all pointers are valid for the lengths passed
+        check(unsafe {
+            libc::syscall(
+                libc::SYS_add_key,
+                KEY_TYPE.as_ptr(),
+                description.as_ptr(),
+                payload.as_ptr(),
+                payload.len(),
+                libc::KEY_SPEC_SESSION_KEYRING,
+            )
+        })
+    }
+
+    /// Finds a key in the session keyring and the keyrings linked to it.
+    pub(super) fn search(description: &str) -> io::Result<i32> {
+        let description = c_string(description)?;
+        // SAFETY: the type and description are NUL-terminated strings
+        check(unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                libc::KEYCTL_SEARCH,
+                libc::KEY_SPEC_SESSION_KEYRING,
+                KEY_TYPE.as_ptr(),
+                description.as_ptr(),
+                0,
+            )
+        })
+    }
+
+    /// Reads the payload of a key.
+    pub(super) fn read(id: i32) -> io::Result<Zeroizing<Vec<u8>>> {
+        let mut buffer = Zeroizing::new(vec![0u8; 512]);
+        loop {
+            // SAFETY: the buffer is valid for its length
+            let len = check(unsafe {
+                libc::syscall(
+                    libc::SYS_keyctl,
+                    libc::KEYCTL_READ,
+                    id,
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                )
+            })? as usize;
+            if len <= buffer.len() {
+                buffer.truncate(len);
+                return Ok(buffer);
+            }
+            buffer = Zeroizing::new(vec![0u8; len]);
+        }
+    }
+
+    /// Makes a key expire after `seconds`.
+    pub(super) fn set_timeout(id: i32, seconds: u64) -> io::Result<()> {
+        let seconds = libc::c_uint::try_from(seconds).unwrap_or(libc::c_uint::MAX);
+        // SAFETY: KEYCTL_SET_TIMEOUT takes no pointers
+        check(unsafe { libc::syscall(libc::SYS_keyctl, libc::KEYCTL_SET_TIMEOUT, id, seconds) })
+            .map(drop)
+    }
+
+    /// Removes a key from every keyring it is linked to.
+    pub(super) fn invalidate(id: i32) -> io::Result<()> {
+        // SAFETY: KEYCTL_INVALIDATE takes no pointers
+        check(unsafe { libc::syscall(libc::SYS_keyctl, libc::KEYCTL_INVALIDATE, id) }).map(drop)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_keyring_cache() {
+        let cache = SessionKeyringCache {
+            service: "secretspec-test",
+        };
+        let key = format!("https://vault.test|{}", std::process::id());
+        // Sandboxes may not allow the key management syscalls
+        if kernel::add(&cache.description(&key), b"probe").is_err() {
+            return;
+        }
+
+        let token = CachedToken::new("s.token".to_string(), Some(Duration::from_secs(600)));
+        cache.store(&key, &token);
+        assert_eq!(cache.load(&key), Some(token));
+        cache.clear(&key);
+        assert_eq!(cache.load(&key), None);
+    }
+}
+
+#[cfg(test)]
+pub(crate) use memory::MemoryTokenCache;
+
+#[cfg(test)]
+mod memory {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Test cache that keeps tokens in memory.
+    #[derive(Debug, Default)]
+    pub(crate) struct MemoryTokenCache {
+        pub tokens: Mutex<HashMap<String, CachedToken>>,
+    }
+
+    impl TokenCache for MemoryTokenCache {
+        fn load(&self, key: &str) -> Option<CachedToken> {
+            self.tokens.lock().unwrap().get(key).cloned()
+        }
+
+        fn store(&self, key: &str, token: &CachedToken) {
+            self.tokens
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), token.clone());
+        }
+
+        fn clear(&self, key: &str) {
+            self.tokens.lock().unwrap().remove(key);
+        }
+    }
+}
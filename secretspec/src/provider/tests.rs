@@ -1,5 +1,6 @@
-use crate::Result;
-use crate::provider::Provider;
+use crate::config::normalize_key;
+use crate::provider::{ItemContainer, ItemMatch, Provider, SecretValue};
+use crate::{Result, SecretSpecError};
 use secrecy::{ExposeSecret, SecretString};
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -9,6 +10,9 @@ use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 
 /// Mock provider for testing
+///
+/// Clones share their storage.
+#[derive(Clone)]
 pub struct MockProvider {
     storage: Arc<Mutex<HashMap<String, String>>>,
 }
@@ -37,6 +41,83 @@ impl Provider for MockProvider {
         Ok(())
     }
 
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let mut storage = self.storage.lock().unwrap();
+        storage.remove(&format!("{}/{}/{}", project, profile, key));
+        Ok(())
+    }
+
+    fn allows_delete(&self) -> bool {
+        true
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let storage = self.storage.lock().unwrap();
+        let prefix = format!("{}/{}/", project, profile);
+        let mut keys: Vec<String> = storage
+            .keys()
+            .filter_map(|full_key| full_key.strip_prefix(&prefix))
+            .map(str::to_string)
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn allows_list(&self) -> bool {
+        true
+    }
+
+    /// Finds the entries of any project and profile whose key is the
+    /// secret's name in another spelling, identified by their full path.
+    fn find_items(&self, _project: &str, key: &str, _profile: &str) -> Result<Vec<ItemMatch>> {
+        let storage = self.storage.lock().unwrap();
+        let mut items: Vec<ItemMatch> = storage
+            .keys()
+            .filter_map(|full_key| {
+                let (location, name) = full_key.rsplit_once('/')?;
+                (normalize_key(name) == normalize_key(key)).then(|| ItemMatch {
+                    id: full_key.clone(),
+                    name: name.to_string(),
+                    location: Some(location.to_string()),
+                })
+            })
+            .collect();
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(items)
+    }
+
+    fn get_by_id(
+        &self,
+        _project: &str,
+        _key: &str,
+        id: &str,
+        _profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let storage = self.storage.lock().unwrap();
+        match storage.get(id) {
+            Some(value) => Ok(Some(SecretString::new(value.clone().into()).into())),
+            None => Err(SecretSpecError::NotFound(id.to_string())),
+        }
+    }
+
+    fn set_by_id(
+        &self,
+        _project: &str,
+        _key: &str,
+        id: &str,
+        value: &SecretString,
+        _profile: &str,
+    ) -> Result<()> {
+        let mut storage = self.storage.lock().unwrap();
+        match storage.get_mut(id) {
+            Some(stored) => {
+                *stored = value.expose_secret().to_string();
+                Ok(())
+            }
+            None => Err(SecretSpecError::NotFound(id.to_string())),
+        }
+    }
+
     fn name(&self) -> &'static str {
         "mock"
     }
@@ -83,6 +164,16 @@ fn test_create_from_string_with_plain_names() {
     assert_eq!(provider.name(), "bitwarden");
 }
 
+#[cfg(feature = "keyring")]
+#[test]
+fn test_keyring_chunks_fit_windows_credential_blobs() {
+    let provider = Box::<dyn Provider>::try_from("keyring").unwrap();
+    let max_size = provider.max_value_size().unwrap();
+
+    // Credential Manager stores UTF-16, at most two bytes per UTF-8 byte
+    assert!(max_size * 2 <= 2560);
+}
+
 #[test]
 fn test_create_from_string_with_colon() {
     // Test provider names with colon
@@ -222,6 +313,54 @@ fn test_url_parsing_behavior() {
     assert_eq!(url.path(), "/to/.env");
 }
 
+#[cfg(feature = "keyring")]
+#[test]
+fn test_keyring_config_parsing() {
+    use crate::provider::keyring::{KeychainAccess, KeyringConfig};
+    use std::convert::TryFrom;
+    use url::Url;
+
+    let config = KeyringConfig::try_from(&Url::parse("keyring://").unwrap()).unwrap();
+    assert_eq!(config, KeyringConfig::default());
+    assert!(!config.uses_data_protection_keychain());
+
+    let url = Url::parse("keyring://?keychain=login&synchronizable=false").unwrap();
+    let config = KeyringConfig::try_from(&url).unwrap();
+    assert_eq!(config.keychain.as_deref(), Some("login"));
+    assert_eq!(config.access, KeychainAccess::Default);
+    assert!(!config.synchronizable);
+
+    // app-only sets an access control list on items in file keychains
+    let url = Url::parse("keyring://?keychain=login&access=app-only").unwrap();
+    let config = KeyringConfig::try_from(&url).unwrap();
+    assert_eq!(config.access, KeychainAccess::AppOnly);
+    assert!(!config.uses_data_protection_keychain());
+
+    let url = Url::parse("keyring://?synchronizable=true").unwrap();
+    let config = KeyringConfig::try_from(&url).unwrap();
+    assert!(config.synchronizable);
+    assert!(config.uses_data_protection_keychain());
+
+    let url = Url::parse("keyring://?access=user-presence").unwrap();
+    let config = KeyringConfig::try_from(&url).unwrap();
+    assert_eq!(config.access, KeychainAccess::UserPresence);
+
+    // Invalid values and contradictory combinations are rejected
+    for uri in [
+        "keyring://?access=everyone",
+        "keyring://?synchronizable=yes",
+        "keyring://?keychain=system&synchronizable=true",
+        "keyring://?access=user-presence&synchronizable=true",
+        "keyring://?access=app-only&synchronizable=true",
+    ] {
+        assert!(
+            KeyringConfig::try_from(&Url::parse(uri).unwrap()).is_err(),
+            "{} should be rejected",
+            uri
+        );
+    }
+}
+
 #[test]
 fn test_bitwarden_config_parsing() {
     use crate::provider::bitwarden::{BitwardenConfig, BitwardenService};
@@ -499,6 +638,1961 @@ fn test_bitwarden_environment_variables() {
     }
 }
 
+#[test]
+fn test_access_control() {
+    // Providers without an access-control API reject access operations
+    let provider = MockProvider::new();
+    assert!(provider.list_access("project", "default").is_err());
+    assert!(
+        provider
+            .grant("project", "default", "alice@example.com")
+            .is_err()
+    );
+
+    // OnePassword grants view_items on the configured vault
+    let provider = Box::<dyn Provider>::try_from("onepassword://Engineering").unwrap();
+    let policy = provider
+        .access_policy("project", "production", "alice@example.com")
+        .unwrap();
+    assert_eq!(policy.principal, "alice@example.com");
+    assert_eq!(policy.scope, "vault 'Engineering'");
+    let document: serde_json::Value = serde_json::from_str(&policy.document).unwrap();
+    assert_eq!(document["permissions"], serde_json::json!(["view_items"]));
+
+    // Bitwarden Secrets Manager access is not managed through the CLI
+    let provider = Box::<dyn Provider>::try_from("bws://project-id").unwrap();
+    assert!(
+        provider
+            .access_policy("project", "default", "alice@example.com")
+            .is_err()
+    );
+}
+
+#[test]
+fn test_bitwarden_add_collection_reader() {
+    use crate::provider::bitwarden::add_collection_reader;
+
+    let mut collection = serde_json::json!({
+        "id": "collection-id",
+        "users": [{ "id": "owner", "readOnly": false, "hidePasswords": false, "manage": true }],
+    });
+    assert!(add_collection_reader(&mut collection, "member"));
+    assert_eq!(collection["users"][1]["id"], "member");
+    assert_eq!(collection["users"][1]["readOnly"], true);
+
+    // Existing members keep their permissions
+    assert!(!add_collection_reader(&mut collection, "owner"));
+    assert_eq!(collection["users"][0]["readOnly"], false);
+    assert_eq!(collection["users"].as_array().unwrap().len(), 2);
+
+    // Collections listed without member access gain a users array
+    let mut collection = serde_json::json!({ "id": "collection-id" });
+    assert!(add_collection_reader(&mut collection, "member"));
+    assert_eq!(collection["users"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_onepassword_with_fake_runner() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::onepassword::{OnePasswordConfig, OnePasswordProvider};
+
+    let item = r#"{"id": "abc", "title": "secretspec/app/default/API_KEY", "fields": [
+        {"id": "value", "type": "CONCEALED", "label": "value", "value": "s3cret"}
+    ]}"#;
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(
+                &[
+                    "op",
+                    "item",
+                    "get",
+                    "secretspec/app/default/API_KEY",
+                    "--vault",
+                    "Private",
+                    "--format",
+                    "json",
+                ],
+                item,
+            )
+            .ok(&["op", "whoami"], "alice@example.com")
+            .fail(
+                &["op", "item", "get", "*", "*", "*", "*", "*"],
+                "\"x\" isn't an item",
+            )
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(&["op", "whoami"], "alice@example.com")
+            .fail(
+                &["op", "item", "get", "*", "*", "*", "*", "*"],
+                "\"x\" isn't an item",
+            )
+            .ok(
+                &[
+                    "op",
+                    "item",
+                    "create",
+                    "--vault",
+                    "Private",
+                    "--template",
+                    "*",
+                ],
+                "",
+            ),
+    );
+    let config = OnePasswordConfig {
+        service_account_token: Some("ops_token".to_string()),
+        ..Default::default()
+    };
+    let provider = OnePasswordProvider::with_runner(config, runner.clone());
+
+    let value = provider.get("app", "API_KEY", "default").unwrap().unwrap();
+    assert_eq!(value.expose_secret(), "s3cret");
+    assert!(provider.get("app", "MISSING", "default").unwrap().is_none());
+    provider
+        .set(
+            "app",
+            "NEW_KEY",
+            &SecretString::new("value".into()),
+            "default",
+        )
+        .unwrap();
+    assert!(runner.is_exhausted());
+
+    // The service account token is passed to every invocation
+    let calls = runner.calls();
+    assert!(calls.iter().all(|call| {
+        call.env.contains(&(
+            "OP_SERVICE_ACCOUNT_TOKEN".to_string(),
+            "ops_token".to_string(),
+        ))
+    }));
+
+    // Signed-out users get a sign-in hint instead of a lookup
+    let runner = Arc::new(FakeRunner::new().fail(
+        &["op", "whoami"],
+        "[ERROR] account is not currently signed in",
+    ));
+    let provider = OnePasswordProvider::with_runner(OnePasswordConfig::default(), runner);
+    let err = provider.get("app", "API_KEY", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::AuthRequired { .. }));
+    assert!(err.to_string().contains("op signin"));
+}
+
+#[test]
+fn test_onepassword_get_fields() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::onepassword::{OnePasswordConfig, OnePasswordProvider};
+
+    let item = r#"{"id": "abc", "title": "Prod DB", "fields": [
+        {"id": "username", "type": "STRING", "label": "username", "value": "app"},
+        {"id": "password", "type": "CONCEALED", "label": "password", "value": "hunter2"},
+        {"id": "h7x", "type": "STRING", "label": "host", "value": "db.internal"}
+    ]}"#;
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(
+                &[
+                    "op", "item", "get", "Prod DB", "--vault", "Private", "--format", "json",
+                ],
+                item,
+            ),
+    );
+    let provider = OnePasswordProvider::with_runner(OnePasswordConfig::default(), runner.clone());
+
+    // All fields are read with a single `op item get`
+    let fields = provider
+        .get_fields(
+            "app",
+            "Prod DB",
+            &["username", "password", "host", "port"],
+            "default",
+        )
+        .unwrap();
+    assert!(runner.is_exhausted());
+    assert_eq!(fields["username"].expose_secret(), "app");
+    assert_eq!(fields["password"].expose_secret(), "hunter2");
+    assert_eq!(fields["host"].expose_secret(), "db.internal");
+    assert!(!fields.contains_key("port"));
+}
+
+#[test]
+fn test_onepassword_item_uri_reads_fields() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::onepassword::{OnePasswordConfig, OnePasswordProvider};
+    use url::Url;
+
+    let url = "onepassword://Shared/Stripe%20Keys".parse::<Url>().unwrap();
+    let config = OnePasswordConfig::try_from(&url).unwrap();
+    assert_eq!(config.default_vault.as_deref(), Some("Shared"));
+    assert_eq!(config.item.as_deref(), Some("Stripe Keys"));
+
+    let item = r#"{"id": "s1", "title": "Stripe Keys", "fields": [
+        {"id": "pk", "type": "STRING", "label": "publishable", "value": "pk_live"},
+        {"id": "notesPlain", "type": "STRING", "label": "notesPlain"}
+    ]}"#;
+    let get = [
+        "op",
+        "item",
+        "get",
+        "Stripe Keys",
+        "--vault",
+        "Shared",
+        "--format",
+        "json",
+    ];
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(&get, item)
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(&get, item),
+    );
+    let provider = OnePasswordProvider::with_runner(config, runner.clone());
+
+    // The key is a field of the item, not an item named after the key
+    let value = provider.get("app", "publishable", "default").unwrap();
+    assert_eq!(value.unwrap().expose_secret(), "pk_live");
+    assert_eq!(
+        provider.list_keys("app", "default").unwrap(),
+        vec!["publishable".to_string()]
+    );
+    assert!(runner.is_exhausted());
+
+    // Fields of a single item are read-only
+    assert!(!provider.allows_set());
+    let err = provider
+        .set(
+            "app",
+            "publishable",
+            &SecretString::new("x".into()),
+            "default",
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("read-only"), "{}", err);
+}
+
+#[test]
+fn test_onepassword_items_by_id() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::onepassword::{OnePasswordConfig, OnePasswordProvider};
+
+    let list = r#"[
+        {"id": "k2", "title": "Stripe API Key"},
+        {"id": "k1", "title": "secretspec/app/default/STRIPE_API_KEY"},
+        {"id": "k3", "title": "Old Stripe API Key"}
+    ]"#;
+    let item = r#"{"id": "k2", "title": "Stripe API Key", "updated_at": "2026-10-01T12:00:00Z",
+        "fields": [{"id": "password", "type": "CONCEALED", "label": "password", "value": "sk_live"}]}"#;
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(
+                &[
+                    "op", "item", "list", "--vault", "Private", "--format", "json",
+                ],
+                list,
+            )
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(
+                &[
+                    "op", "item", "get", "k2", "--vault", "Private", "--format", "json",
+                ],
+                item,
+            )
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(
+                &[
+                    "op", "item", "get", "k2", "--vault", "Private", "--format", "json",
+                ],
+                item,
+            )
+            .ok(
+                &[
+                    "op",
+                    "item",
+                    "edit",
+                    "k2",
+                    "--vault",
+                    "Private",
+                    "password=sk_new",
+                ],
+                "",
+            ),
+    );
+    let provider = OnePasswordProvider::with_runner(OnePasswordConfig::default(), runner.clone());
+
+    // The item secretspec would create comes first, and unrelated names are left out
+    let items = provider
+        .find_items("app", "STRIPE_API_KEY", "default")
+        .unwrap();
+    let ids: Vec<&str> = items.iter().map(|item| item.id.as_str()).collect();
+    assert_eq!(ids, ["k1", "k2"]);
+    assert_eq!(items[1].to_string(), "Stripe API Key in Private (k2)");
+
+    let secret = provider
+        .get_by_id("app", "STRIPE_API_KEY", "k2", "default")
+        .unwrap()
+        .unwrap();
+    assert_eq!(secret.value.expose_secret(), "sk_live");
+    assert_eq!(
+        secret.metadata.revised.as_deref(),
+        Some("2026-10-01T12:00:00Z")
+    );
+
+    // The field the value was read from is the one updated
+    provider
+        .set_by_id(
+            "app",
+            "STRIPE_API_KEY",
+            "k2",
+            &SecretString::from("sk_new"),
+            "default",
+        )
+        .unwrap();
+    assert!(runner.is_exhausted());
+}
+
+#[test]
+fn test_onepassword_browse() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::onepassword::{OnePasswordConfig, OnePasswordProvider};
+
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(
+                &["op", "vault", "list", "--format", "json"],
+                r#"[{"id": "v1", "name": "Private"}, {"id": "v2", "name": "Shared"}]"#,
+            )
+            .ok(
+                &[
+                    "op", "item", "list", "--vault", "Private", "--format", "json",
+                ],
+                r#"[{"id": "k2", "title": "Stripe API Key"}, {"id": "k1", "title": "GitHub Token"}]"#,
+            )
+            .ok(
+                &["op", "item", "list", "--vault", "Shared", "--format", "json"],
+                "[]",
+            ),
+    );
+    let provider = OnePasswordProvider::with_runner(OnePasswordConfig::default(), runner.clone());
+
+    let vaults = provider.browse().unwrap();
+    assert!(runner.is_exhausted());
+    assert_eq!(
+        vaults,
+        [
+            ItemContainer {
+                name: "Private".to_string(),
+                items: vec!["GitHub Token".to_string(), "Stripe API Key".to_string()],
+            },
+            ItemContainer {
+                name: "Shared".to_string(),
+                items: Vec::new(),
+            },
+        ]
+    );
+
+    // A vault in the URI is the only one listed
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(
+                &[
+                    "op", "item", "list", "--vault", "Shared", "--format", "json",
+                ],
+                "[]",
+            ),
+    );
+    let config = OnePasswordConfig {
+        default_vault: Some("Shared".to_string()),
+        ..Default::default()
+    };
+    let provider = OnePasswordProvider::with_runner(config, runner.clone());
+    assert_eq!(provider.browse().unwrap().len(), 1);
+    assert!(runner.is_exhausted());
+
+    assert!(MockProvider::new().browse().is_err());
+}
+
+#[test]
+fn test_check_writable() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::onepassword::{OnePasswordConfig, OnePasswordProvider};
+
+    // 1Password lists the vaults the user can create and edit items in
+    let list = [
+        "op",
+        "vault",
+        "list",
+        "--permission",
+        "create_items,edit_items",
+        "--format",
+        "json",
+    ];
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(&list, r#"[{"id": "abc", "name": "Private"}]"#)
+            .ok(&["op", "whoami"], "alice@example.com")
+            .ok(&list, r#"[{"id": "abc", "name": "Private"}]"#),
+    );
+    let provider = OnePasswordProvider::with_runner(OnePasswordConfig::default(), runner.clone());
+    provider.check_writable("app", "default").unwrap();
+    let provider = OnePasswordProvider::with_runner(
+        OnePasswordConfig {
+            default_vault: Some("Production".to_string()),
+            ..Default::default()
+        },
+        runner.clone(),
+    );
+    let err = provider.check_writable("app", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::PermissionDenied(_)));
+    assert!(err.to_string().contains("'Production'"));
+    assert!(runner.is_exhausted());
+
+    // A .env file can't be written where nothing can be staged
+    let temp_dir = TempDir::new().unwrap();
+    let provider = Box::<dyn Provider>::try_from(format!(
+        "dotenv://{}",
+        temp_dir.path().join("missing/.env").display()
+    ))
+    .unwrap();
+    let err = provider.check_writable("app", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::PermissionDenied(_)));
+    let provider = Box::<dyn Provider>::try_from(format!(
+        "dotenv://{}",
+        temp_dir.path().join(".env").display()
+    ))
+    .unwrap();
+    provider.check_writable("app", "default").unwrap();
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+    // Read-only providers fail the default check
+    let provider = Box::<dyn Provider>::try_from("env").unwrap();
+    assert!(provider.check_writable("app", "default").is_err());
+}
+
+#[test]
+fn test_lastpass_api_scheme() {
+    use crate::provider::lastpass::LastPassConfig;
+
+    let url = url::Url::parse("lastpass+api://work?username=alice@example.com").unwrap();
+    if cfg!(feature = "lastpass-api") {
+        let config = LastPassConfig::try_from(&url).unwrap();
+        assert!(config.api);
+        assert_eq!(config.username.as_deref(), Some("alice@example.com"));
+        assert_eq!(config.folder_prefix.as_deref(), Some("work"));
+    } else {
+        let err = LastPassConfig::try_from(&url).unwrap_err();
+        assert!(err.to_string().contains("lastpass-api"));
+    }
+    let url = url::Url::parse("lastpass://work").unwrap();
+    assert!(!LastPassConfig::try_from(&url).unwrap().api);
+}
+
+#[test]
+fn test_lastpass_with_fake_runner() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::lastpass::{LastPassConfig, LastPassProvider};
+
+    let item = "secretspec/app/default/API_KEY";
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["lpass", "status"], "Logged in as alice@example.com.")
+            .ok(&["lpass", "status"], "Logged in as alice@example.com.")
+            .fail(
+                &["lpass", "show", "--sync=now", "--password", item],
+                "Error: Could not find specified account(s).",
+            )
+            .ok(
+                &[
+                    "lpass",
+                    "set",
+                    "--sync=now",
+                    item,
+                    "--password",
+                    "--non-interactive",
+                ],
+                "",
+            ),
+    );
+    let provider = LastPassProvider::with_runner(LastPassConfig::default(), runner.clone());
+    provider
+        .set(
+            "app",
+            "API_KEY",
+            &SecretString::new("s3cret".into()),
+            "default",
+        )
+        .unwrap();
+    assert!(runner.is_exhausted());
+
+    // The value is written to stdin rather than passed as an argument
+    let set = runner.calls().pop().unwrap();
+    assert_eq!(set.stdin.as_deref(), Some(b"s3cret".as_slice()));
+    assert!(
+        set.env
+            .contains(&("LPASS_DISABLE_PINENTRY".to_string(), "1".to_string()))
+    );
+
+    let runner = Arc::new(FakeRunner::new().ok(&["lpass", "status"], "Not logged in."));
+    let provider = LastPassProvider::with_runner(LastPassConfig::default(), runner);
+    let err = provider.get("app", "API_KEY", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::AuthRequired { .. }));
+    assert!(err.to_string().contains("lpass login"));
+}
+
+#[test]
+fn test_bitwarden_with_fake_runner() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+
+    // A missing CLI explains how to install it
+    let runner = Arc::new(FakeRunner::new().missing(&["bw", "status"]));
+    let provider = BitwardenProvider::with_runner(BitwardenConfig::default(), runner);
+    let err = provider.get("app", "API_KEY", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::CliMissing { .. }));
+    assert!(err.to_string().contains("npm install -g @bitwarden/cli"));
+
+    // Transcripts recorded from real sessions can be replayed
+    let transcript = r#"[
+        {"command": ["bw", "status"], "output": {"success": true, "stdout": "{\"status\": \"locked\"}"}}
+    ]"#;
+    let runner = Arc::new(FakeRunner::from_transcript(transcript));
+    let provider = BitwardenProvider::with_runner(BitwardenConfig::default(), runner.clone());
+    let err = provider.get("app", "API_KEY", "default").unwrap_err();
+    assert!(err.to_string().contains("bw unlock"));
+    assert!(runner.is_exhausted());
+}
+
+#[test]
+fn test_bitwarden_items_by_id() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+    use base64::Engine;
+
+    let status = ["bw", "status"];
+    let unlocked = r#"{"status": "unlocked"}"#;
+    let items = r#"[
+        {"id": "a1", "name": "Stripe API Key", "type": 1, "folderId": "f1", "login": {"password": "sk_live"}},
+        {"id": "b2", "name": "STRIPE_API_KEY", "type": 1, "folderId": null, "login": {"password": "sk_test"}},
+        {"id": "c3", "name": "OLD_STRIPE_API_KEY", "type": 1, "login": {"password": "sk_old"}}
+    ]"#;
+    let folders = r#"[{"id": null, "name": "No Folder"}, {"id": "f1", "name": "Payments"}]"#;
+    let item = r#"{"id": "a1", "name": "Stripe API Key", "type": 1, "revisionDate": "2026-10-01T12:00:00.000Z",
+        "login": {"username": null, "password": "sk_live"}}"#;
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&status, unlocked)
+            .ok(
+                &["bw", "list", "items", "--search", "STRIPE_API_KEY"],
+                items,
+            )
+            .ok(&["bw", "list", "folders"], folders)
+            .ok(&status, unlocked)
+            .ok(&["bw", "get", "item", "a1"], item)
+            .ok(&status, unlocked)
+            .ok(&["bw", "get", "item", "a1"], item)
+            .ok(&["bw", "edit", "item", "a1"], ""),
+    );
+    let provider = BitwardenProvider::with_runner(BitwardenConfig::default(), runner.clone());
+
+    // Items with the same name are told apart by their folders
+    let found = provider
+        .find_items("app", "STRIPE_API_KEY", "default")
+        .unwrap();
+    let found: Vec<String> = found.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        found,
+        ["Stripe API Key in Payments (a1)", "STRIPE_API_KEY (b2)"]
+    );
+
+    // Adopted items are read and written without searching the vault
+    let secret = provider
+        .get_by_id("app", "STRIPE_API_KEY", "a1", "default")
+        .unwrap()
+        .unwrap();
+    assert_eq!(secret.value.expose_secret(), "sk_live");
+    assert_eq!(
+        secret.metadata.revised.as_deref(),
+        Some("2026-10-01T12:00:00.000Z")
+    );
+    provider
+        .set_by_id(
+            "app",
+            "STRIPE_API_KEY",
+            "a1",
+            &SecretString::from("sk_new"),
+            "default",
+        )
+        .unwrap();
+    assert!(runner.is_exhausted());
+
+    let calls = runner.calls();
+    let edited = base64::engine::general_purpose::STANDARD
+        .decode(calls.last().unwrap().stdin.as_deref().unwrap())
+        .unwrap();
+    let edited: serde_json::Value = serde_json::from_slice(&edited).unwrap();
+    assert_eq!(edited["login"]["password"], "sk_new");
+}
+
+#[test]
+fn test_bitwarden_sync_modes() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider, BitwardenSync};
+    use crate::provider::command::FakeRunner;
+    use std::time::Duration;
+
+    let parse = |uri: &str| BitwardenConfig::try_from(&url::Url::parse(uri).unwrap());
+    assert_eq!(parse("bitwarden://").unwrap().sync, BitwardenSync::Auto);
+    let config = parse("bitwarden://?sync=always&sync_cooldown=60").unwrap();
+    assert_eq!(config.sync, BitwardenSync::Always);
+    assert_eq!(config.sync_cooldown, Duration::from_secs(60));
+    assert!(parse("bitwarden://?sync=sometimes").is_err());
+    assert!(parse("bitwarden://?sync_cooldown=soon").is_err());
+
+    let status = ["bw", "status"];
+    let unlocked = r#"{"status": "unlocked"}"#;
+    let search = ["bw", "list", "items", "--search", "API_KEY"];
+
+    // `always` syncs before the first read only
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&status, unlocked)
+            .ok(&["bw", "sync", "--last"], "2020-01-15T10:30:00.000Z")
+            .ok(&["bw", "sync"], "Syncing complete.")
+            .ok(&search, "[]")
+            .ok(&status, unlocked)
+            .ok(&search, "[]"),
+    );
+    let provider = BitwardenProvider::with_runner(config, runner.clone());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+
+    // `auto` syncs when a key is missing, then searches again
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&status, unlocked)
+            .ok(&search, "[]")
+            .ok(&["bw", "sync", "--last"], "")
+            .ok(&["bw", "sync"], "Syncing complete.")
+            .ok(&search, "[]"),
+    );
+    let provider = BitwardenProvider::with_runner(BitwardenConfig::default(), runner.clone());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+
+    // No sync within the cooldown of the last one
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&status, unlocked)
+            .ok(&search, "[]")
+            .ok(&["bw", "sync", "--last"], "2999-01-01T00:00:00.000Z"),
+    );
+    let provider = BitwardenProvider::with_runner(BitwardenConfig::default(), runner.clone());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+
+    // `never` leaves the vault alone
+    let runner = Arc::new(FakeRunner::new().ok(&status, unlocked).ok(&search, "[]"));
+    let provider =
+        BitwardenProvider::with_runner(parse("bitwarden://?sync=never").unwrap(), runner.clone());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+}
+
+#[test]
+fn test_bitwarden_matches_normalized_names() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+
+    let status = ["bw", "status"];
+    let unlocked = r#"{"status": "unlocked"}"#;
+    let search = ["bw", "list", "items", "--search", "API_KEY"];
+    let item = |name: &str, password: &str| {
+        format!(
+            r#"{{"id": "{}", "name": "{}", "type": 1, "login": {{"password": "{}"}}}}"#,
+            name, name, password
+        )
+    };
+
+    // The search also finds items that only contain the key, which are skipped
+    let items = format!(
+        "[{}, {}]",
+        item("OLD_API_KEY_BACKUP", "old"),
+        item("Api Key", "current")
+    );
+    let runner = Arc::new(FakeRunner::new().ok(&status, unlocked).ok(&search, &items));
+    let provider = BitwardenProvider::with_runner(BitwardenConfig::default(), runner);
+    let value = provider.get("app", "API_KEY", "default").unwrap().unwrap();
+    assert_eq!(value.expose_secret(), "current");
+
+    let items = format!("[{}]", item("OLD_API_KEY_BACKUP", "old"));
+    let runner = Arc::new(FakeRunner::new().ok(&status, unlocked).ok(&search, &items));
+    let provider = BitwardenProvider::with_runner(BitwardenConfig::default(), runner);
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+}
+
+#[test]
+fn test_bitwarden_trash() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+
+    let config = || BitwardenConfig::try_from(&url::Url::parse("bitwarden://?sync=never").unwrap());
+    let status = ["bw", "status"];
+    let unlocked = r#"{"status": "unlocked"}"#;
+    let search = ["bw", "list", "items", "--search", "API_KEY"];
+    let trash = ["bw", "list", "items", "--trash", "--search", "API_KEY"];
+    let items = r#"[{"id": "item-1", "name": "API_KEY", "type": 1, "login": {}}]"#;
+
+    // Deleting moves the item to the trash, restoring brings it back
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&status, unlocked)
+            .ok(&search, items)
+            .ok(&["bw", "delete", "item", "item-1"], "")
+            .ok(&status, unlocked)
+            .ok(&trash, items)
+            .ok(&["bw", "restore", "item", "item-1"], ""),
+    );
+    let provider = BitwardenProvider::with_runner(config().unwrap(), runner.clone());
+    assert!(provider.allows_restore());
+    provider.delete("app", "API_KEY", "default").unwrap();
+    provider.restore("app", "API_KEY", "default").unwrap();
+    assert!(runner.is_exhausted());
+
+    // Purging also erases an item that is already in the trash
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&status, unlocked)
+            .ok(&search, "[]")
+            .ok(&trash, items)
+            .ok(&["bw", "delete", "item", "item-1", "--permanent"], ""),
+    );
+    let provider = BitwardenProvider::with_runner(config().unwrap(), runner.clone());
+    provider.purge("app", "API_KEY", "default").unwrap();
+    assert!(runner.is_exhausted());
+
+    // An item missing from the trash can't be restored
+    let runner = Arc::new(FakeRunner::new().ok(&status, unlocked).ok(&trash, "[]"));
+    let provider = BitwardenProvider::with_runner(config().unwrap(), runner);
+    assert!(matches!(
+        provider.restore("app", "API_KEY", "default"),
+        Err(SecretSpecError::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_bitwarden_headless_login() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+    use std::env;
+
+    let temp_dir = TempDir::new().unwrap();
+    let env_file = temp_dir.path().join(".env");
+    std::fs::write(&env_file, "BW_PASSWORD=hunter2\n").unwrap();
+    let reference = format!("dotenv://{}#BW_PASSWORD", env_file.display());
+    let mut uri = url::Url::parse("bitwarden://?sync=never").unwrap();
+    uri.query_pairs_mut()
+        .append_pair("password_ref", &reference);
+    let config = BitwardenConfig::try_from(&uri).unwrap();
+    assert_eq!(config.password_ref.as_deref(), Some(reference.as_str()));
+    assert!(
+        BitwardenConfig::try_from(
+            &url::Url::parse("bitwarden://?password_ref=keyring://").unwrap()
+        )
+        .is_err()
+    );
+
+    let search = ["bw", "list", "items", "--search", "API_KEY"];
+    let runner =
+        Arc::new(FakeRunner::new().ok(&["bw", "status"], r#"{"status": "unauthenticated"}"#));
+    let provider = BitwardenProvider::with_runner(config, runner);
+
+    // Without an API key the user has to log in
+    let err = provider.get("app", "API_KEY", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::AuthRequired { .. }));
+    assert!(err.to_string().contains("BW_CLIENTID"));
+
+    unsafe {
+        env::set_var("BW_CLIENTID", "organization.test");
+        env::set_var("BW_CLIENTSECRET", "secret");
+    }
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["bw", "status"], r#"{"status": "unauthenticated"}"#)
+            .ok(&["bw", "login", "--apikey"], "You are logged in!")
+            .ok(
+                &["bw", "unlock", "--passwordenv", "BW_PASSWORD", "--raw"],
+                "session-key\n",
+            )
+            .ok(&search, "[]"),
+    );
+    let provider =
+        BitwardenProvider::with_runner(BitwardenConfig::try_from(&uri).unwrap(), runner.clone());
+    let result = provider.get("app", "API_KEY", "default");
+    unsafe {
+        env::remove_var("BW_CLIENTID");
+        env::remove_var("BW_CLIENTSECRET");
+    }
+    assert!(result.unwrap().is_none());
+    assert!(runner.is_exhausted());
+
+    // The password comes from the reference and the session is used afterwards
+    let calls = runner.calls();
+    assert!(
+        calls[2]
+            .env
+            .contains(&("BW_PASSWORD".to_string(), "hunter2".to_string()))
+    );
+    assert!(
+        calls[3]
+            .env
+            .contains(&("BW_SESSION".to_string(), "session-key".to_string()))
+    );
+}
+
+#[test]
+fn test_bitwarden_session_is_cached() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+    use crate::provider::session::{MemoryTokenCache, TokenCache};
+
+    let temp_dir = TempDir::new().unwrap();
+    let env_file = temp_dir.path().join(".env");
+    std::fs::write(&env_file, "BW_PASSWORD=hunter2\n").unwrap();
+    let mut uri = url::Url::parse("bitwarden://?sync=never").unwrap();
+    uri.query_pairs_mut().append_pair(
+        "password_ref",
+        &format!("dotenv://{}#BW_PASSWORD", env_file.display()),
+    );
+
+    let status = ["bw", "status"];
+    let locked = r#"{"status": "locked", "serverUrl": null, "userEmail": "alice@example.com"}"#;
+    let unlocked = r#"{"status": "unlocked", "serverUrl": null, "userEmail": "alice@example.com"}"#;
+    let unlock = ["bw", "unlock", "--passwordenv", "BW_PASSWORD", "--raw"];
+    let search = ["bw", "list", "items", "--search", "API_KEY"];
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&status, locked)
+            .ok(&unlock, "session-key\n")
+            .ok(&search, "[]")
+            // A second command reuses the cached session without unlocking
+            .ok(&status, locked)
+            .ok(&status, unlocked)
+            .ok(&search, "[]")
+            // A session that no longer unlocks the vault is discarded
+            .ok(&status, locked)
+            .ok(&status, locked)
+            .ok(&unlock, "new-session-key\n")
+            .ok(&search, "[]"),
+    );
+    let cache = Arc::new(MemoryTokenCache::default());
+    let provider = || {
+        BitwardenProvider::with_runner(BitwardenConfig::try_from(&uri).unwrap(), runner.clone())
+            .with_session_cache(cache.clone())
+    };
+
+    assert!(
+        provider()
+            .get("app", "API_KEY", "default")
+            .unwrap()
+            .is_none()
+    );
+    let key = "https://vault.bitwarden.com|alice@example.com";
+    assert_eq!(cache.load(key).unwrap().token, "session-key");
+    assert!(
+        provider()
+            .get("app", "API_KEY", "default")
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        provider()
+            .get("app", "API_KEY", "default")
+            .unwrap()
+            .is_none()
+    );
+    assert!(runner.is_exhausted());
+    assert_eq!(cache.load(key).unwrap().token, "new-session-key");
+
+    let calls = runner.calls();
+    assert!(
+        calls[4]
+            .env
+            .contains(&("BW_SESSION".to_string(), "session-key".to_string()))
+    );
+}
+
+#[test]
+fn test_bitwarden_self_hosted_server() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+
+    let temp_dir = TempDir::new().unwrap();
+    let server = "https://vault.company.com";
+    let config = |isolate: bool| {
+        let mut uri = url::Url::parse("bitwarden://?sync=never").unwrap();
+        uri.query_pairs_mut()
+            .append_pair("server", server)
+            .append_pair("isolate", &isolate.to_string())
+            .append_pair("state_dir", temp_dir.path().to_str().unwrap());
+        BitwardenConfig::try_from(&uri).unwrap()
+    };
+    let status = ["bw", "status"];
+    let unlocked = r#"{"status": "unlocked"}"#;
+    let search = ["bw", "list", "items", "--search", "API_KEY"];
+
+    // The CLI already uses the server
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["bw", "config", "server"], "https://vault.company.com/\n")
+            .ok(&status, unlocked)
+            .ok(&search, "[]"),
+    );
+    let provider = BitwardenProvider::with_runner(config(true), runner.clone());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+    assert!(runner.calls().iter().all(|call| call.env.is_empty()));
+
+    // Another server gets its own CLI data directory
+    let appdata_dir = config(true).server_appdata_dir(server).unwrap();
+    assert!(appdata_dir.starts_with(temp_dir.path()));
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["bw", "config", "server"], "https://vault.bitwarden.com")
+            .ok(&["bw", "config", "server"], "https://vault.bitwarden.com")
+            .ok(
+                &["bw", "config", "server", server],
+                "Saved setting `config`.",
+            )
+            .ok(&status, unlocked)
+            .ok(&search, "[]"),
+    );
+    let provider = BitwardenProvider::with_runner(config(true), runner.clone());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+    let isolated = (
+        "BITWARDENCLI_APPDATA_DIR".to_string(),
+        appdata_dir.to_string_lossy().into_owned(),
+    );
+    let calls = runner.calls();
+    assert!(calls[0].env.is_empty());
+    assert!(calls[1..].iter().all(|call| call.env.contains(&isolated)));
+    assert!(appdata_dir.is_dir());
+
+    // Without isolation the user's CLI is switched and restored on drop
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["bw", "config", "server"], "https://vault.bitwarden.com")
+            .ok(
+                &["bw", "config", "server", server],
+                "Saved setting `config`.",
+            )
+            .ok(&status, unlocked)
+            .ok(&search, "[]")
+            .ok(
+                &["bw", "config", "server", "https://vault.bitwarden.com"],
+                "Saved setting `config`.",
+            ),
+    );
+    let provider = BitwardenProvider::with_runner(config(false), runner.clone());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    drop(provider);
+    assert!(runner.is_exhausted());
+    assert!(runner.calls().iter().all(|call| call.env.is_empty()));
+}
+
+#[test]
+fn test_bws_errors_are_classified() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+    use std::time::Duration;
+
+    let config = BitwardenConfig::try_from(&url::Url::parse("bws://project-id").unwrap()).unwrap();
+    let list = ["bws", "secret", "list", "project-id"];
+    let runner = Arc::new(
+        FakeRunner::new()
+            .fail(
+                &list,
+                "Error: Internal error: Failed to parse IdentityTokenResponse",
+            )
+            .fail(&list, "Error: Resource not found")
+            .fail(&list, "Error: Unauthorized"),
+    );
+    let provider = BitwardenProvider::with_runner(config, runner.clone());
+
+    let err = provider.get("app", "API_KEY", "default").unwrap_err();
+    assert!(err.is_transient());
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(20)));
+
+    let err = provider.get("app", "API_KEY", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::PermissionDenied(_)));
+    assert!(!err.is_transient());
+
+    let err = provider.get("app", "API_KEY", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::AuthRequired { .. }));
+    assert!(runner.is_exhausted());
+}
+
+#[test]
+fn test_bws_revision_metadata() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::chunked::ChunkedProvider;
+    use crate::provider::command::FakeRunner;
+
+    let config = BitwardenConfig::try_from(&url::Url::parse("bws://project-id").unwrap()).unwrap();
+    let secrets = r#"[{
+        "id": "1", "organizationId": "org", "projectId": "project-id",
+        "key": "app_API_KEY", "value": "sk-123", "note": "",
+        "creationDate": "2024-01-01T00:00:00Z", "revisionDate": "2024-03-05T10:00:00Z"
+    }]"#;
+    let runner = Arc::new(FakeRunner::new().ok(&["bws", "secret", "list", "project-id"], secrets));
+    let provider = ChunkedProvider::wrap(Box::new(BitwardenProvider::with_runner(
+        config,
+        runner.clone(),
+    )));
+
+    let secret = provider
+        .get_with_metadata("app", "API_KEY", "default")
+        .unwrap()
+        .unwrap();
+    assert_eq!(secret.value.expose_secret(), "sk-123");
+    assert_eq!(
+        secret.metadata.revised.as_deref(),
+        Some("2024-03-05T10:00:00Z")
+    );
+    assert_eq!(secret.metadata.to_string(), "revised 2024-03-05T10:00:00Z");
+    assert!(runner.is_exhausted());
+
+    // Providers that record nothing return empty metadata
+    let mock = MockProvider::new();
+    mock.set("app", "API_KEY", &SecretString::from("sk-123"), "default")
+        .unwrap();
+    let secret = mock.get_with_metadata("app", "API_KEY", "default").unwrap();
+    assert!(secret.unwrap().metadata.is_empty());
+}
+
+#[test]
+fn test_token_bucket() {
+    use crate::provider::throttled::{ThrottledProvider, TokenBucket};
+    use std::time::Duration;
+
+    // Two requests per second go through at once, then each waits its turn
+    let bucket = TokenBucket::new(2.0);
+    assert_eq!(bucket.reserve(), Duration::ZERO);
+    assert_eq!(bucket.reserve(), Duration::ZERO);
+    let first = bucket.reserve();
+    let second = bucket.reserve();
+    assert!(first > Duration::from_millis(400) && first <= Duration::from_millis(500));
+    assert!(second > Duration::from_millis(900) && second <= Duration::from_secs(1));
+
+    // Slower rates still allow one request without waiting
+    let bucket = TokenBucket::new(0.5);
+    assert_eq!(bucket.reserve(), Duration::ZERO);
+    assert!(bucket.reserve() > Duration::from_millis(1900));
+
+    // Throttled providers behave like the provider they wrap
+    let mock = MockProvider::new();
+    mock.set("app", "API_KEY", &SecretString::from("sk-123"), "default")
+        .unwrap();
+    let provider = ThrottledProvider::wrap(Box::new(mock), Some(100.0));
+    let value = provider.get("app", "API_KEY", "default").unwrap().unwrap();
+    assert_eq!(value.expose_secret(), "sk-123");
+}
+
+#[test]
+fn test_provider_builder() {
+    use crate::provider::builder::ProviderBuilder;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    /// Fails with a rate limit until it has been called `failures` times.
+    struct FlakyProvider {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    impl Provider for FlakyProvider {
+        fn get(&self, _: &str, _: &str, _: &str) -> Result<Option<SecretString>> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(SecretSpecError::RateLimited {
+                    provider: "flaky".to_string(),
+                    retry_after: Some(Duration::ZERO),
+                });
+            }
+            Ok(Some(SecretString::from("value")))
+        }
+
+        fn set(&self, _: &str, _: &str, _: &SecretString, _: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+    }
+
+    // Transient failures are retried up to the number of attempts
+    let flaky = Arc::new(FlakyProvider {
+        failures: 2,
+        calls: AtomicU32::new(0),
+    });
+    let provider = ProviderBuilder::new(Box::new(flaky.clone()))
+        .retry(3)
+        .build();
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_some());
+    let flaky = Arc::new(FlakyProvider {
+        failures: 2,
+        calls: AtomicU32::new(0),
+    });
+    let provider = ProviderBuilder::new(Box::new(flaky.clone()))
+        .retry(2)
+        .build();
+    assert!(
+        provider
+            .get("app", "API_KEY", "default")
+            .unwrap_err()
+            .is_transient()
+    );
+    assert_eq!(flaky.calls.load(Ordering::SeqCst), 2);
+
+    // Lookups are cached until written through the stack, and audited above
+    // the cache
+    let store = MockProvider::new();
+    store
+        .set("app", "API_KEY", &SecretString::from("sk-1"), "default")
+        .unwrap();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    let provider = ProviderBuilder::new(Box::new(store.clone()))
+        .cache(Duration::from_secs(60))
+        .audit(move |event| {
+            recorded.lock().unwrap().push(format!(
+                "{} {} {}",
+                event.provider,
+                event.operation,
+                event.target.unwrap_or("-")
+            ))
+        })
+        .build();
+    let get = || {
+        provider
+            .get("app", "API_KEY", "default")
+            .unwrap()
+            .map(|v| v.expose_secret().to_string())
+    };
+    assert_eq!(get().as_deref(), Some("sk-1"));
+    store
+        .set("app", "API_KEY", &SecretString::from("sk-2"), "default")
+        .unwrap();
+    assert_eq!(get().as_deref(), Some("sk-1"));
+    provider
+        .set("app", "API_KEY", &SecretString::from("sk-3"), "default")
+        .unwrap();
+    assert_eq!(get().as_deref(), Some("sk-3"));
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            "mock get API_KEY",
+            "mock get API_KEY",
+            "mock set API_KEY",
+            "mock get API_KEY"
+        ]
+    );
+
+    // Custom layers are plain functions
+    let provider = ProviderBuilder::new(Box::new(MockProvider::new()))
+        .layer(|_: Box<dyn Provider>| {
+            Box::new(FlakyProvider {
+                failures: 0,
+                calls: AtomicU32::new(0),
+            }) as Box<dyn Provider>
+        })
+        .build();
+    assert_eq!(provider.name(), "flaky");
+    assert!(
+        ProviderBuilder::new(provider)
+            .encrypt(Vec::new(), None)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_compare_and_set() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+    use crate::provider::throttled::ThrottledProvider;
+
+    let status = ["bw", "status"];
+    let unlocked = r#"{"status": "unlocked"}"#;
+    let search = ["bw", "list", "items", "--search", "API_KEY"];
+    let item = r#"{"id": "a1", "name": "API_KEY", "type": 1,
+        "revisionDate": "2024-03-06T09:00:00.000Z", "login": {"password": "sk-456"}}"#;
+    let items = format!("[{}]", item);
+    let config = || BitwardenConfig::try_from(&url::Url::parse("bitwarden://?sync=never").unwrap());
+    let set = |provider: &BitwardenProvider, expected: &str| {
+        provider.compare_and_set(
+            "app",
+            "API_KEY",
+            &SecretString::from("sk-789"),
+            "default",
+            expected,
+        )
+    };
+
+    // Someone else changed the secret after it was read, so nothing is written
+    let runner = Arc::new(FakeRunner::new().ok(&status, unlocked).ok(&search, &items));
+    let provider = BitwardenProvider::with_runner(config().unwrap(), runner.clone());
+    let err = set(&provider, "2024-03-05T10:00:00.000Z").unwrap_err();
+    assert!(matches!(err, SecretSpecError::Conflict(_)));
+    assert!(err.to_string().contains("2024-03-06T09:00:00.000Z"));
+    assert!(runner.is_exhausted());
+
+    // At the expected revision the item is edited, and the server refuses the
+    // edit if it changed after the local copy was synced
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&status, unlocked)
+            .ok(&search, &items)
+            .ok(&["bw", "get", "item", "a1"], item)
+            .ok(&["bw", "edit", "item", "a1"], "")
+            .ok(&status, unlocked)
+            .ok(&search, &items)
+            .ok(&["bw", "get", "item", "a1"], item)
+            .fail(
+                &["bw", "edit", "item", "a1"],
+                "The cipher you are updating is out of date. Please save your work, sync your vault, and try again.",
+            ),
+    );
+    let provider = BitwardenProvider::with_runner(config().unwrap(), runner.clone());
+    set(&provider, "2024-03-06T09:00:00.000Z").unwrap();
+    let err = set(&provider, "2024-03-06T09:00:00.000Z").unwrap_err();
+    assert!(matches!(err, SecretSpecError::Conflict(_)));
+    assert!(runner.is_exhausted());
+
+    // Providers without an atomic conditional write refuse it, including
+    // through wrappers
+    let mock = MockProvider::new();
+    mock.set("app", "API_KEY", &SecretString::from("sk-123"), "default")
+        .unwrap();
+    let provider = ThrottledProvider::wrap(Box::new(mock), Some(100.0));
+    let err = provider
+        .compare_and_set("app", "API_KEY", &SecretString::from("x"), "default", "1")
+        .unwrap_err();
+    assert!(err.to_string().contains("compare-and-set"));
+}
+
+#[test]
+fn test_bws_state_file_per_token() {
+    use crate::provider::bitwarden::{BitwardenConfig, BitwardenProvider};
+    use crate::provider::command::FakeRunner;
+
+    let temp_dir = TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let mut uri = url::Url::parse("bws://project-id?token=0.abc.def:key").unwrap();
+    uri.query_pairs_mut()
+        .append_pair("state_dir", state_dir.to_str().unwrap());
+    let config = BitwardenConfig::try_from(&uri).unwrap();
+    assert_eq!(config.state_dir.as_deref(), Some(state_dir.as_path()));
+
+    let state_file = config.state_file("0.abc.def:key").unwrap();
+    assert_eq!(state_file.parent(), Some(state_dir.as_path()));
+    assert_ne!(state_file, config.state_file("0.other.token:key").unwrap());
+
+    let runner = Arc::new(FakeRunner::new().ok(
+        &[
+            "bws",
+            "secret",
+            "list",
+            "project-id",
+            "--state-file",
+            state_file.to_str().unwrap(),
+        ],
+        "[]",
+    ));
+    let provider = BitwardenProvider::with_runner(config, runner.clone());
+    assert!(provider.get("app", "API_KEY", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+    assert!(state_dir.is_dir());
+}
+
+#[test]
+fn test_vault_dynamic_config_parsing() {
+    use crate::provider::vault::VaultDynamicConfig;
+    use url::Url;
+
+    let url = Url::parse(
+        "vault+dynamic://database/creds/app-role?address=https://vault.example.com:8200",
+    )
+    .unwrap();
+    let config = VaultDynamicConfig::try_from(&url).unwrap();
+    assert_eq!(config.path, "database/creds/app-role");
+    assert_eq!(
+        config.address.as_deref(),
+        Some("https://vault.example.com:8200")
+    );
+
+    assert!(VaultDynamicConfig::try_from(&Url::parse("vault+dynamic://").unwrap()).is_err());
+    assert!(
+        VaultDynamicConfig::try_from(&Url::parse("vault+dynamic://aws/creds/x?role=y").unwrap())
+            .is_err()
+    );
+}
+
+#[test]
+fn test_vault_auth_config_parsing() {
+    use crate::provider::vault::{VaultAuth, VaultDynamicConfig};
+    use url::Url;
+
+    let parse = |uri: &str| VaultDynamicConfig::try_from(&Url::parse(uri).unwrap());
+
+    let config =
+        parse("vault+dynamic://database/creds/app?auth=ldap&username=alice&mount=corp-ldap")
+            .unwrap();
+    assert_eq!(
+        config.auth,
+        VaultAuth::Ldap {
+            username: "alice".to_string(),
+            mount: Some("corp-ldap".to_string()),
+        }
+    );
+
+    let config = parse(
+        "vault+dynamic://database/creds/app?auth=kerberos&service=HTTP/vault.corp&realm=CORP.EXAMPLE.COM",
+    )
+    .unwrap();
+    assert_eq!(config.auth.method(), "kerberos");
+
+    assert_eq!(
+        parse("vault+dynamic://database/creds/app").unwrap().auth,
+        VaultAuth::Token
+    );
+    assert!(parse("vault+dynamic://database/creds/app?auth=ldap").is_err());
+    assert!(parse("vault+dynamic://database/creds/app?auth=radius").is_err());
+}
+
+#[test]
+fn test_vault_kerberos_login_caches_token() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::session::MemoryTokenCache;
+    use crate::provider::vault::{VaultAuth, VaultDynamicConfig, VaultDynamicProvider};
+
+    let login = [
+        "vault",
+        "login",
+        "-format=json",
+        "-no-store",
+        "-method=kerberos",
+        "keytab_path=/etc/app.keytab",
+    ];
+    let read = ["vault", "read", "-format=json", "database/creds/app"];
+    let creds = r#"{"lease_id": "", "data": {"username": "u", "password": "p"}}"#;
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(
+                &login,
+                r#"{"auth": {"client_token": "hvs.first", "lease_duration": 3600}}"#,
+            )
+            .ok(&read, creds)
+            // A second command reuses the cached token without logging in
+            .ok(&read, creds)
+            // A revoked token is discarded and the login retried
+            .fail(&read, "Code: 403. Errors:\n\n* invalid token")
+            .ok(
+                &login,
+                r#"{"auth": {"client_token": "hvs.second", "lease_duration": 3600}}"#,
+            )
+            .ok(&read, creds),
+    );
+    let cache = Arc::new(MemoryTokenCache::default());
+    let config = VaultDynamicConfig {
+        path: "database/creds/app".to_string(),
+        address: Some("https://vault.corp".to_string()),
+        auth: VaultAuth::Kerberos {
+            params: vec![("keytab_path".to_string(), "/etc/app.keytab".to_string())],
+            mount: None,
+        },
+        ..VaultDynamicConfig::default()
+    };
+    let provider = || {
+        VaultDynamicProvider::with_runner(config.clone(), runner.clone())
+            .with_token_cache(cache.clone())
+    };
+
+    assert!(
+        provider()
+            .get("app", "PASSWORD", "default")
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        provider()
+            .get("app", "PASSWORD", "default")
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        provider()
+            .get("app", "PASSWORD", "default")
+            .unwrap()
+            .is_some()
+    );
+    assert!(runner.is_exhausted());
+
+    let tokens: Vec<_> = runner
+        .calls()
+        .iter()
+        .map(|call| {
+            call.env
+                .iter()
+                .find(|(name, _)| name == "VAULT_TOKEN")
+                .map(|(_, value)| value.clone())
+        })
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            None,
+            Some("hvs.first".to_string()),
+            Some("hvs.first".to_string()),
+            Some("hvs.first".to_string()),
+            None,
+            Some("hvs.second".to_string()),
+        ]
+    );
+    assert_eq!(
+        cache.tokens.lock().unwrap()["https://vault.corp|kerberos|"].token,
+        "hvs.second"
+    );
+}
+
+#[test]
+fn test_vault_dynamic_with_fake_runner() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::vault::{VaultDynamicConfig, VaultDynamicProvider};
+    use std::time::Duration;
+
+    let creds = r#"{
+        "lease_id": "database/creds/app-role/abc123",
+        "lease_duration": 3600,
+        "renewable": true,
+        "data": {"username": "v-app-xyz", "password": "hunter2"}
+    }"#;
+    let renewed = r#"{
+        "lease_id": "database/creds/app-role/abc123",
+        "lease_duration": 7200,
+        "renewable": true,
+        "data": null
+    }"#;
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(
+                &["vault", "read", "-format=json", "database/creds/app-role"],
+                creds,
+            )
+            .ok(
+                &[
+                    "vault",
+                    "lease",
+                    "renew",
+                    "-format=json",
+                    "database/creds/app-role/abc123",
+                ],
+                renewed,
+            )
+            .ok(
+                &["vault", "lease", "revoke", "database/creds/app-role/abc123"],
+                "",
+            ),
+    );
+    let config = VaultDynamicConfig {
+        path: "database/creds/app-role".to_string(),
+        ..VaultDynamicConfig::default()
+    };
+    let provider = VaultDynamicProvider::with_runner(config, runner.clone());
+    assert!(provider.leases().is_empty());
+
+    // Both fields come from a single credential
+    let username = provider.get("app", "DB_USERNAME", "default").unwrap();
+    let password = provider.get("app", "PASSWORD", "default").unwrap();
+    assert_eq!(username.unwrap().expose_secret(), "v-app-xyz");
+    assert_eq!(password.unwrap().expose_secret(), "hunter2");
+    assert!(provider.get("app", "DB_HOST", "default").unwrap().is_none());
+    assert!(!provider.allows_set());
+
+    let lease = provider.leases().pop().unwrap();
+    assert_eq!(lease.ttl, Duration::from_secs(3600));
+    assert!(lease.renewable);
+
+    let lease = provider.renew_lease(&lease).unwrap();
+    assert_eq!(lease.ttl, Duration::from_secs(7200));
+    provider.revoke_lease(&lease).unwrap();
+    assert!(runner.is_exhausted());
+
+    // Errors are classified
+    let runner = Arc::new(FakeRunner::new().fail(
+        &["vault", "read", "-format=json", "database/creds/app-role"],
+        "Error reading database/creds/app-role: Error making API request.\n\nCode: 400. Errors:\n\n* missing client token",
+    ));
+    let config = VaultDynamicConfig {
+        path: "database/creds/app-role".to_string(),
+        address: Some("https://vault.example.com".to_string()),
+        ..VaultDynamicConfig::default()
+    };
+    let provider = VaultDynamicProvider::with_runner(config, runner.clone());
+    let err = provider.get("app", "DB_USERNAME", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::AuthRequired { .. }));
+    assert!(runner.calls()[0].env.contains(&(
+        "VAULT_ADDR".to_string(),
+        "https://vault.example.com".to_string()
+    )));
+}
+
+#[test]
+fn test_vault_kv_config_parsing() {
+    use crate::provider::vault::kv::VaultKvConfig;
+    use url::Url;
+
+    let parse = |s: &str| VaultKvConfig::try_from(&Url::parse(s).unwrap());
+
+    let config = parse("vault://secret").unwrap();
+    assert_eq!(config.mount, "secret");
+    assert_eq!(config.prefix, "secretspec");
+
+    let config =
+        parse("vault://kv/teams/payments?address=https://vault:8200&auth=ldap&username=alice")
+            .unwrap();
+    assert_eq!(config.mount, "kv");
+    assert_eq!(config.prefix, "teams/payments");
+    assert_eq!(config.address.as_deref(), Some("https://vault:8200"));
+    assert_eq!(config.auth.method(), "ldap");
+
+    assert!(parse("vault://").is_err());
+    assert!(parse("vault://secret?typo=1").is_err());
+}
+
+#[test]
+fn test_vault_kv_with_fake_runner() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::vault::kv::{VaultKvConfig, VaultKvProvider};
+
+    let path = "secretspec/app/default/API_KEY";
+    let secret = r#"{
+        "data": {
+            "data": {"value": "sk_live_123"},
+            "metadata": {"created_time": "2024-03-05T10:00:00Z", "deletion_time": "", "destroyed": false, "version": 3}
+        }
+    }"#;
+    let metadata = r#"{
+        "data": {
+            "current_version": 3,
+            "versions": {
+                "1": {"created_time": "2024-01-10T16:12:00Z", "deletion_time": "", "destroyed": false},
+                "2": {"created_time": "2024-02-01T09:30:00Z", "deletion_time": "2024-02-02T08:00:00Z", "destroyed": false},
+                "3": {"created_time": "2024-03-05T10:00:00Z", "deletion_time": "", "destroyed": false}
+            }
+        }
+    }"#;
+    let deleted = metadata.replace(
+        r#""3": {"created_time": "2024-03-05T10:00:00Z", "deletion_time": """#,
+        r#""3": {"created_time": "2024-03-05T10:00:00Z", "deletion_time": "2024-03-06T10:00:00Z""#,
+    );
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(
+                &["vault", "kv", "get", "-format=json", "-mount=secret", path],
+                secret,
+            )
+            .ok(
+                &["vault", "kv", "put", "-mount=secret", path, "value=-"],
+                "",
+            )
+            .fail(
+                &["vault", "kv", "put", "-mount=secret", "-cas=3", path, "value=-"],
+                "Error writing data to secret/data/secretspec/app/default/API_KEY: Error making API request.\n\nCode: 400. Errors:\n\n* check-and-set parameter did not match the current version",
+            )
+            .ok(
+                &["vault", "kv", "metadata", "get", "-format=json", "-mount=secret", path],
+                metadata,
+            )
+            .ok(&["vault", "kv", "delete", "-mount=secret", path], "")
+            .ok(
+                &["vault", "kv", "metadata", "get", "-format=json", "-mount=secret", path],
+                &deleted,
+            )
+            .ok(
+                &["vault", "kv", "undelete", "-versions=3", "-mount=secret", path],
+                "",
+            )
+            .ok(
+                &["vault", "kv", "list", "-format=json", "-mount=secret", "secretspec/app/default"],
+                r#"["API_KEY", "nested/"]"#,
+            )
+            .fail(
+                &["vault", "kv", "metadata", "get", "-format=json", "-mount=secret", "*"],
+                "No value found at secret/metadata/secretspec/app/default/MISSING",
+            ),
+    );
+    let config = VaultKvConfig {
+        mount: "secret".to_string(),
+        prefix: "secretspec".to_string(),
+        ..VaultKvConfig::default()
+    };
+    let provider = VaultKvProvider::with_runner(config, runner.clone());
+
+    // Reads report the version and when it was written
+    let value = provider
+        .get_with_metadata("app", "API_KEY", "default")
+        .unwrap()
+        .unwrap();
+    assert_eq!(value.value.expose_secret(), "sk_live_123");
+    assert_eq!(value.metadata.revision(), Some("3"));
+    assert_eq!(
+        value.metadata.to_string(),
+        "revised 2024-03-05T10:00:00Z, version 3"
+    );
+
+    // Values are passed on stdin, never as arguments
+    let secret = SecretString::new("sk_live_456".into());
+    provider.set("app", "API_KEY", &secret, "default").unwrap();
+    assert_eq!(
+        runner.calls()[1].stdin.as_deref(),
+        Some("sk_live_456".as_bytes())
+    );
+
+    let err = provider
+        .compare_and_set("app", "API_KEY", &secret, "default", "3")
+        .unwrap_err();
+    assert!(matches!(err, SecretSpecError::Conflict(_)));
+    assert!(
+        provider
+            .compare_and_set("app", "API_KEY", &secret, "default", "2024-03-05")
+            .is_err()
+    );
+
+    // Versions are listed newest first, marking deleted ones
+    let history = provider.history("app", "API_KEY", "default").unwrap();
+    let versions: Vec<String> = history.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        versions,
+        [
+            "revised 2024-03-05T10:00:00Z, version 3",
+            "revised 2024-02-01T09:30:00Z, version 2, deleted",
+            "revised 2024-01-10T16:12:00Z, version 1",
+        ]
+    );
+
+    // Deleting removes the current version, which restoring undeletes
+    assert!(provider.allows_restore());
+    provider.delete("app", "API_KEY", "default").unwrap();
+    provider.restore("app", "API_KEY", "default").unwrap();
+
+    assert_eq!(provider.list_keys("app", "default").unwrap(), ["API_KEY"]);
+    assert!(
+        provider
+            .history("app", "MISSING", "default")
+            .unwrap()
+            .is_empty()
+    );
+    assert!(runner.is_exhausted());
+}
+
+#[test]
+fn test_vault_fetch_with_fake_transport() {
+    use crate::provider::vault::fetch::{Fetch, VaultFetchProvider, set_token};
+    use crate::provider::vault::kv::VaultKvConfig;
+    use http::{Request, Response};
+
+    /// Answers requests in order and records them.
+    struct FakeFetch {
+        responses: Mutex<Vec<(u16, &'static str)>>,
+        requests: Mutex<Vec<Request<String>>>,
+    }
+
+    impl Fetch for FakeFetch {
+        fn fetch(&self, request: Request<String>) -> Result<Response<String>> {
+            self.requests.lock().unwrap().push(request);
+            let (status, body) = self.responses.lock().unwrap().remove(0);
+            Ok(Response::builder()
+                .status(status)
+                .body(body.to_string())
+                .unwrap())
+        }
+    }
+
+    let fetch = Arc::new(FakeFetch {
+        responses: Mutex::new(vec![
+            (
+                200,
+                r#"{"data": {"data": {"value": "sk_live_123"}, "metadata": {"created_time": "2024-03-05T10:00:00Z", "version": 3}}}"#,
+            ),
+            (404, r#"{"errors": []}"#),
+            (200, "{}"),
+            (
+                400,
+                r#"{"errors": ["check-and-set parameter did not match the current version"]}"#,
+            ),
+            (200, r#"{"data": {"keys": ["API_KEY", "nested/"]}}"#),
+        ]),
+        requests: Mutex::new(Vec::new()),
+    });
+    let config = VaultKvConfig {
+        mount: "secret".to_string(),
+        prefix: "secretspec".to_string(),
+        address: Some("https://vault.example.com:8200".to_string()),
+        namespace: Some("admin".to_string()),
+        ..VaultKvConfig::default()
+    };
+    set_token(SecretString::from("hvs.test"));
+    let provider = VaultFetchProvider::with_fetch(config.clone(), fetch.clone()).unwrap();
+
+    let value = provider
+        .get_with_metadata("app", "API_KEY", "default")
+        .unwrap()
+        .unwrap();
+    assert_eq!(value.value.expose_secret(), "sk_live_123");
+    assert_eq!(value.metadata.revision(), Some("3"));
+    assert!(provider.get("app", "MISSING", "default").unwrap().is_none());
+    provider
+        .set("app", "API_KEY", &SecretString::from("sk_new"), "default")
+        .unwrap();
+    let conflict = provider
+        .compare_and_set("app", "API_KEY", &SecretString::from("x"), "default", "2")
+        .unwrap_err();
+    assert!(matches!(conflict, SecretSpecError::Conflict(_)));
+    assert_eq!(provider.list_keys("app", "default").unwrap(), ["API_KEY"]);
+
+    let requests = fetch.requests.lock().unwrap();
+    let summary: Vec<String> = requests
+        .iter()
+        .map(|r| format!("{} {}", r.method(), r.uri()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            "GET https://vault.example.com:8200/v1/secret/data/secretspec/app/default/API_KEY",
+            "GET https://vault.example.com:8200/v1/secret/data/secretspec/app/default/MISSING",
+            "POST https://vault.example.com:8200/v1/secret/data/secretspec/app/default/API_KEY",
+            "POST https://vault.example.com:8200/v1/secret/data/secretspec/app/default/API_KEY",
+            "GET https://vault.example.com:8200/v1/secret/metadata/secretspec/app/default?list=true",
+        ]
+    );
+    assert_eq!(requests[0].headers()["X-Vault-Token"], "hvs.test");
+    assert_eq!(requests[0].headers()["X-Vault-Namespace"], "admin");
+    assert_eq!(requests[2].body(), r#"{"data":{"value":"sk_new"}}"#);
+    assert_eq!(
+        requests[3].body(),
+        r#"{"data":{"value":"x"},"options":{"cas":2}}"#
+    );
+
+    // The CLI's login methods aren't available in the browser
+    let ldap = VaultKvConfig {
+        auth: crate::provider::vault::VaultAuth::Ldap {
+            username: "ada".to_string(),
+            mount: None,
+        },
+        ..config
+    };
+    assert!(VaultFetchProvider::with_fetch(ldap, fetch.clone()).is_err());
+}
+
+#[test]
+fn test_explain() {
+    use crate::provider::explain;
+
+    let explanation = explain("bitwarden://org@collection?type=card").unwrap();
+    assert_eq!(explanation.provider, "bitwarden");
+    assert_eq!(explanation.encryption, None);
+    assert!(explanation.config.contains("Card"));
+    assert!(explanation.config.contains("\"collection\""));
+
+    // Parameters the provider would ignore are reported
+    let err = explain("bitwarden://org@collection?typo=card").unwrap_err();
+    assert!(matches!(err, SecretSpecError::InvalidInput(_)));
+    assert!(err.to_string().contains("'typo'"));
+    assert!(err.to_string().contains("Did you mean 'type'?"));
+    let err = explain("bitwarden://org@collection?unrelated=1").unwrap_err();
+    assert!(err.to_string().contains("sync_cooldown"));
+    assert!(explain("bws://?sync=never").is_err());
+    assert!(
+        explain("env://?path=.env")
+            .unwrap_err()
+            .to_string()
+            .contains("take no parameters")
+    );
+
+    // Credentials are kept out of the output
+    let explanation = explain("bws://project?token=0.secret").unwrap();
+    assert_eq!(explanation.uri, "bws://project?token=***");
+    assert!(!explanation.config.contains("0.secret"));
+    let explanation = explain("onepassword+token://ops_secret@Vault").unwrap();
+    assert!(!explanation.uri.contains("ops_secret"));
+    assert!(!explanation.config.contains("ops_secret"));
+
+    // Wrappers are explained along with the provider
+    let explanation = explain("enc+dotenv://.env?key=age1a&key=age1b").unwrap();
+    assert_eq!(explanation.provider, "dotenv");
+    assert_eq!(explanation.encryption, Some(2));
+    assert!(!explanation.env_override);
+    let explanation = explain("override+enc+dotenv://.env?key=age1a").unwrap();
+    assert_eq!(explanation.provider, "dotenv");
+    assert_eq!(explanation.encryption, Some(1));
+    assert!(explanation.env_override);
+    let explanation =
+        explain("gitvault://git@github.com:org/secrets.git?key=age1a&branch=dev&checkout=/tmp/gv")
+            .unwrap();
+    assert_eq!(explanation.provider, "gitvault");
+    assert!(explanation.config.contains("\"dev\""));
+}
+
+#[test]
+fn test_gitvault_from_spec() {
+    use crate::provider::gitvault::GitVaultConfig;
+
+    let config = GitVaultConfig::parse(
+        "git@github.com:org/secrets.git?branch=vault&checkout=%2Ftmp%2Fvault",
+    )
+    .unwrap();
+    assert_eq!(config.remote, "git@github.com:org/secrets.git");
+    assert_eq!(config.branch, "vault");
+    assert_eq!(config.checkout, std::path::PathBuf::from("/tmp/vault"));
+    assert_eq!(
+        GitVaultConfig::parse("/srv/secrets.git").unwrap().branch,
+        "main"
+    );
+    assert!(GitVaultConfig::parse("/srv/secrets.git?ref=main").is_err());
+    assert!(GitVaultConfig::parse("").is_err());
+
+    // Values are always encrypted
+    let provider =
+        Box::<dyn Provider>::try_from("gitvault://git@github.com:org/secrets.git?key=age1abc")
+            .unwrap();
+    assert_eq!(provider.name(), "gitvault");
+    let err = Box::<dyn Provider>::try_from("gitvault://git@github.com:org/secrets.git")
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("requires a recipient"));
+
+    let alice = "age1alice".to_string();
+    assert_eq!(
+        crate::provider::encrypted::with_recipients(
+            "gitvault://git@github.com:org/secrets.git".to_string(),
+            [&alice]
+        ),
+        "gitvault://git@github.com:org/secrets.git?key=age1alice"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_gitvault_with_git() {
+    use crate::provider::gitvault::{GitVaultConfig, GitVaultProvider};
+    use std::path::Path;
+    use std::process::Command;
+
+    if Command::new("git").arg("--version").output().is_err() {
+        eprintln!("Skipping gitvault test: git is not installed");
+        return;
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+    fn set_identity(dir: &Path) {
+        git(dir, &["config", "user.name", "Test"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    let remote = temp_dir.path().join("remote.git");
+    git(
+        temp_dir.path(),
+        &["init", "--quiet", "--bare", "remote.git"],
+    );
+    let provider = |name: &str| {
+        GitVaultProvider::new(GitVaultConfig {
+            remote: remote.to_string_lossy().into_owned(),
+            branch: "main".to_string(),
+            checkout: temp_dir.path().join(name),
+        })
+    };
+
+    // The first use clones the empty repository
+    let alice = provider("alice");
+    assert!(alice.list_keys("app", "default").unwrap().is_empty());
+    set_identity(&temp_dir.path().join("alice"));
+    let bob = provider("bob");
+    assert!(bob.get("app", "API_KEY", "default").unwrap().is_none());
+    set_identity(&temp_dir.path().join("bob"));
+
+    alice
+        .set("app", "API_KEY", &SecretString::from("age:one"), "default")
+        .unwrap();
+    let bob = provider("bob");
+    let value = bob.get("app", "API_KEY", "default").unwrap().unwrap();
+    assert_eq!(value.expose_secret(), "age:one");
+    assert!(bob.get("app", "API_KEY", "production").unwrap().is_none());
+
+    // Someone else pushes while bob writes, found through a pre-push hook
+    let carol = temp_dir.path().join("carol");
+    git(
+        temp_dir.path(),
+        &["clone", "--quiet", &remote.to_string_lossy(), "carol"],
+    );
+    set_identity(&carol);
+    let concurrent_push = |file: &str| {
+        let hook = temp_dir.path().join("bob/.git/hooks/pre-push");
+        let marker = temp_dir.path().join("pushed");
+        let _ = std::fs::remove_file(&marker);
+        let script = format!(
+            "#!/bin/sh\nunset GIT_DIR GIT_WORK_TREE GIT_INDEX_FILE\n[ -e {marker} ] && exit 0\ntouch {marker}\ncd {carol} && git pull --quiet origin main && printf '%s' '{file}' > app/default.json && git commit --quiet -am concurrent && git push --quiet origin HEAD:main\n",
+            marker = marker.display(),
+            carol = carol.display(),
+            file = file,
+        );
+        std::fs::write(&hook, script).unwrap();
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+    };
+
+    // A change to another key is applied on top of theirs
+    concurrent_push(r#"{"API_KEY": "age:one", "OTHER": "age:theirs"}"#);
+    bob.set("app", "TOKEN", &SecretString::from("age:two"), "default")
+        .unwrap();
+    let check = provider("alice");
+    assert_eq!(
+        check.list_keys("app", "default").unwrap(),
+        vec!["API_KEY", "OTHER", "TOKEN"]
+    );
+
+    // A change to the same key is a conflict
+    concurrent_push(r#"{"API_KEY": "age:one", "OTHER": "age:theirs", "TOKEN": "age:carol"}"#);
+    let err = bob
+        .set("app", "TOKEN", &SecretString::from("age:bob"), "default")
+        .unwrap_err();
+    assert!(err.to_string().contains("was changed"), "{}", err);
+    let check = provider("alice");
+    let value = check.get("app", "TOKEN", "default").unwrap().unwrap();
+    assert_eq!(value.expose_secret(), "age:carol");
+
+    bob.delete("app", "OTHER", "default").unwrap();
+    let check = provider("alice");
+    assert_eq!(
+        check.list_keys("app", "default").unwrap(),
+        vec!["API_KEY", "TOKEN"]
+    );
+    assert!(bob.get("../app", "TOKEN", "default").is_err());
+}
+
 // Integration tests for all providers
 #[cfg(test)]
 mod integration_tests {
@@ -641,6 +2735,26 @@ mod integration_tests {
         }
     }
 
+    #[test]
+    fn test_all_providers_conformance() {
+        use crate::conformance::ConformanceSuite;
+        use crate::doctor::Status;
+
+        for provider_name in get_test_providers() {
+            let (provider, _temp_dir) = create_provider_with_temp_path(&provider_name);
+            for check in ConformanceSuite::new(provider).run() {
+                assert_ne!(
+                    check.status,
+                    Status::Fail,
+                    "{} failed {}: {}",
+                    provider_name,
+                    check.name,
+                    check.detail
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_provider_special_characters() {
         let test_cases = vec![
@@ -736,6 +2850,11 @@ mod integration_tests {
                 // Found a value, vault is unlocked
                 println!("Bitwarden vault is unlocked and contains data");
             }
+            Err(
+                err @ (SecretSpecError::AuthRequired { .. } | SecretSpecError::CliMissing { .. }),
+            ) => {
+                println!("Got expected authentication error: {}", err);
+            }
             Err(err) => {
                 // Should get authentication error if not unlocked
                 let err_str = err.to_string();
@@ -850,4 +2969,329 @@ mod integration_tests {
             }
         }
     }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_keyring_unicode_account_and_large_values() {
+        use crate::provider::chunked::ChunkedProvider;
+        use crate::provider::keyring::{KeyringConfig, KeyringProvider};
+
+        if !get_test_providers().iter().any(|p| p == "keyring") {
+            println!("Skipping keyring test (keyring not in SECRETSPEC_TEST_PROVIDERS)");
+            return;
+        }
+
+        // Windows Credential Manager stores user names and blobs as UTF-16
+        let provider = ChunkedProvider::wrap(Box::new(KeyringProvider::with_account(
+            KeyringConfig::default(),
+            "tëst-üsér-密码",
+        )));
+        let project_name = generate_test_project_name();
+        let large_value = SecretString::new("🔐é".repeat(1000).into());
+
+        provider
+            .set(&project_name, "LARGE_UNICODE", &large_value, "default")
+            .expect("Should store a large unicode value");
+        let retrieved = provider
+            .get(&project_name, "LARGE_UNICODE", "default")
+            .expect("Should read the value back")
+            .expect("Value should exist");
+        assert_eq!(retrieved.expose_secret(), large_value.expose_secret());
+    }
+}
+
+#[test]
+fn test_gpg_with_fake_runner() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::gpg::{GpgConfig, GpgProvider};
+    use url::Url;
+
+    let config =
+        GpgConfig::try_from(&Url::parse("gpg://.secrets?recipient=alice@example.com").unwrap())
+            .unwrap();
+    assert_eq!(config.path, std::path::PathBuf::from(".secrets"));
+    assert_eq!(config.recipients, ["alice@example.com"]);
+    assert!(GpgConfig::try_from(&Url::parse("gpg://").unwrap()).is_err());
+
+    let temp_dir = TempDir::new().unwrap();
+    let production = temp_dir.path().join("app/production");
+    std::fs::create_dir_all(&production).unwrap();
+    std::fs::write(production.join(".gpg-id"), "ops@example.com\n0xA1B2C3D4\n").unwrap();
+
+    let armored = "-----BEGIN PGP MESSAGE-----\n...\n-----END PGP MESSAGE-----\n";
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(
+                &[
+                    "gpg",
+                    "--batch",
+                    "--yes",
+                    "--armor",
+                    "--encrypt",
+                    "--recipient",
+                    "alice@example.com",
+                ],
+                armored,
+            )
+            // The profile's .gpg-id replaces the default recipients
+            .ok(
+                &[
+                    "gpg",
+                    "--batch",
+                    "--yes",
+                    "--armor",
+                    "--encrypt",
+                    "--recipient",
+                    "ops@example.com",
+                    "--recipient",
+                    "0xA1B2C3D4",
+                ],
+                armored,
+            )
+            .ok(&["gpg", "--batch", "--quiet", "--decrypt", "*"], "s3cret"),
+    );
+    let provider = GpgProvider::with_runner(
+        GpgConfig {
+            path: temp_dir.path().to_path_buf(),
+            recipients: vec!["alice@example.com".to_string()],
+        },
+        runner.clone(),
+    );
+
+    let value = SecretString::new("s3cret".into());
+    provider.set("app", "API_KEY", &value, "default").unwrap();
+    provider
+        .set("app", "API_KEY", &value, "production")
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(production.join("API_KEY.gpg")).unwrap(),
+        armored
+    );
+    // Values only reach gpg on stdin, never on the command line
+    assert!(
+        runner
+            .calls()
+            .iter()
+            .all(|call| !call.args.iter().any(|arg| arg.contains("s3cret")))
+    );
+
+    let value = provider.get("app", "API_KEY", "default").unwrap().unwrap();
+    assert_eq!(value.expose_secret(), "s3cret");
+    assert!(provider.get("app", "MISSING", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+
+    assert_eq!(
+        provider.list_keys("app", "production").unwrap(),
+        ["API_KEY"]
+    );
+    provider.delete("app", "API_KEY", "production").unwrap();
+    assert!(provider.list_keys("app", "production").unwrap().is_empty());
+    assert!(provider.get("app", "../API_KEY", "default").is_err());
+
+    // Without any recipient nothing is written
+    let provider = GpgProvider::with_runner(
+        GpgConfig {
+            path: temp_dir.path().to_path_buf(),
+            recipients: Vec::new(),
+        },
+        Arc::new(FakeRunner::new()),
+    );
+    let err = provider.check_writable("app", "staging").unwrap_err();
+    assert!(err.to_string().contains(".gpg-id"));
+}
+
+#[test]
+fn test_paas_with_fake_runner() {
+    use crate::provider::command::FakeRunner;
+    use crate::provider::paas::{PaasConfig, PaasProvider, Platform};
+    use url::Url;
+
+    let config = PaasConfig::try_from(&Url::parse("fly://my-app?stage=true").unwrap()).unwrap();
+    assert_eq!(config.platform, Platform::Fly);
+    assert_eq!(config.app.as_deref(), Some("my-app"));
+    assert!(config.stage);
+    assert!(PaasConfig::try_from(&Url::parse("heroku://").unwrap()).is_err());
+    // Railway falls back to the service linked to the directory
+    let config =
+        PaasConfig::try_from(&Url::parse("railway://?environment=staging").unwrap()).unwrap();
+    assert_eq!(config.app, None);
+    assert_eq!(config.environment.as_deref(), Some("staging"));
+
+    let values = vec![
+        ("API_KEY".to_string(), SecretString::from("sk-123")),
+        (
+            "DATABASE_URL".to_string(),
+            SecretString::from("postgres://db"),
+        ),
+    ];
+
+    // Heroku writes every value with one config:set, so the app restarts once
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(
+                &["heroku", "config", "--json", "--app", "my-app"],
+                r#"{"API_KEY": "old"}"#,
+            )
+            .ok(
+                &[
+                    "heroku",
+                    "config:set",
+                    "API_KEY=sk-123",
+                    "DATABASE_URL=postgres://db",
+                    "--app",
+                    "my-app",
+                ],
+                "",
+            )
+            .fail(
+                &["heroku", "config", "--json", "--app", "my-app"],
+                "Error: not logged in",
+            ),
+    );
+    let provider = PaasProvider::with_runner(
+        PaasConfig::try_from(&Url::parse("heroku://my-app").unwrap()).unwrap(),
+        runner.clone(),
+    );
+    assert_eq!(provider.name(), "heroku");
+    assert_eq!(
+        provider
+            .get("app", "API_KEY", "default")
+            .unwrap()
+            .unwrap()
+            .expose_secret(),
+        "old"
+    );
+    provider.set_many("app", &values, "default").unwrap();
+    let err = provider.list_keys("app", "default").unwrap_err();
+    assert!(matches!(err, SecretSpecError::AuthRequired { .. }));
+    assert!(runner.is_exhausted());
+
+    // Fly.io takes the values on stdin and never returns them
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(&["fly", "secrets", "import", "--app", "my-app"], "")
+            .ok(
+                &["fly", "secrets", "list", "--json", "--app", "my-app"],
+                r#"[{"Name": "API_KEY", "Digest": "abc"}]"#,
+            ),
+    );
+    let provider = PaasProvider::with_runner(
+        PaasConfig::try_from(&Url::parse("fly://my-app").unwrap()).unwrap(),
+        runner.clone(),
+    );
+    provider.set_many("app", &values, "default").unwrap();
+    let calls = runner.calls();
+    assert_eq!(
+        calls[0].stdin.as_deref(),
+        Some(&b"API_KEY=sk-123\nDATABASE_URL=postgres://db\n"[..])
+    );
+    assert!(calls[0].args.iter().all(|arg| !arg.contains("sk-123")));
+    assert!(provider.get("app", "API_KEY", "default").is_err());
+    assert!(provider.get("app", "OTHER", "default").unwrap().is_none());
+    assert!(runner.is_exhausted());
+
+    let provider = PaasProvider::with_runner(
+        PaasConfig::try_from(&Url::parse("railway://web").unwrap()).unwrap(),
+        Arc::new(FakeRunner::new().ok(
+            &[
+                "railway",
+                "variables",
+                "--service",
+                "web",
+                "--set",
+                "API_KEY=sk-123",
+                "--set",
+                "DATABASE_URL=postgres://db",
+            ],
+            "",
+        )),
+    );
+    provider.set_many("app", &values, "default").unwrap();
+    assert!(!provider.allows_delete());
+
+    // Vercel and Netlify keep variables per environment, picked by profile
+    assert!(PaasConfig::try_from(&Url::parse("vercel://prj_123").unwrap()).is_err());
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(
+                &[
+                    "vercel",
+                    "env",
+                    "pull",
+                    "*",
+                    "--environment",
+                    "preview",
+                    "--yes",
+                ],
+                "",
+            )
+            .ok(
+                &["vercel", "env", "add", "API_KEY", "preview", "--force"],
+                "",
+            )
+            .ok(
+                &["vercel", "env", "add", "DATABASE_URL", "preview", "--force"],
+                "",
+            )
+            .ok(
+                &["vercel", "env", "rm", "API_KEY", "production", "--yes"],
+                "",
+            ),
+    );
+    let provider = PaasProvider::with_runner(
+        PaasConfig::try_from(&Url::parse("vercel://prj_123?team=team_456").unwrap()).unwrap(),
+        runner.clone(),
+    );
+    assert!(provider.list_keys("app", "staging").unwrap().is_empty());
+    provider.set_many("app", &values, "preview").unwrap();
+    provider.delete("app", "API_KEY", "production").unwrap();
+    let calls = runner.calls();
+    assert_eq!(calls[1].stdin.as_deref(), Some(&b"sk-123"[..]));
+    assert!(
+        calls[1]
+            .env
+            .contains(&("VERCEL_PROJECT_ID".to_string(), "prj_123".to_string()))
+    );
+    assert!(runner.is_exhausted());
+
+    let runner = Arc::new(
+        FakeRunner::new()
+            .ok(
+                &["netlify", "env:list", "--json", "--context", "dev"],
+                r#"{"API_KEY": "local"}"#,
+            )
+            .ok(
+                &[
+                    "netlify",
+                    "env:set",
+                    "API_KEY",
+                    "sk-123",
+                    "--context",
+                    "deploy-preview",
+                    "--force",
+                ],
+                "",
+            ),
+    );
+    let provider = PaasProvider::with_runner(
+        PaasConfig::try_from(&Url::parse("netlify://my-site").unwrap()).unwrap(),
+        runner.clone(),
+    );
+    assert_eq!(
+        provider
+            .get("app", "API_KEY", "default")
+            .unwrap()
+            .unwrap()
+            .expose_secret(),
+        "local"
+    );
+    provider
+        .set("app", "API_KEY", &SecretString::from("sk-123"), "staging")
+        .unwrap();
+    assert!(
+        runner.calls()[0]
+            .env
+            .contains(&("NETLIFY_SITE_ID".to_string(), "my-site".to_string()))
+    );
+    assert!(runner.is_exhausted());
 }
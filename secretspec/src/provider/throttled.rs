@@ -0,0 +1,264 @@
+//! Rate limiting shared by every instance of a provider.
+//!
+//! Services like Bitwarden Secrets Manager and 1Password service accounts
+//! reject requests beyond a rate limit. The [`ThrottledProvider`] wrapper
+//! takes a token from a bucket before each operation, and all instances of
+//! the same provider in a process share one bucket, so commands that resolve
+//! references or read from several providers stay under the limit together.
+//!
+//! Rates are set in requests per second with
+//! `secretspec config set defaults.rate_limits.<provider> <rate>`.
+
+use super::macros::forward_provider;
+use super::{
+    AccessEntry, AccessPolicy, ItemContainer, ItemMatch, Lease, Provider, SecretMetadata,
+    SecretValue,
+};
+use crate::Result;
+use crate::doctor::Check;
+use secrecy::SecretString;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A token bucket refilled at a fixed rate.
+///
+/// The bucket holds up to one second's worth of tokens, and at least one,
+/// so short bursts go through without waiting.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    /// Tokens added per second
+    rate: f64,
+    /// Most tokens the bucket holds
+    burst: f64,
+    /// Tokens available, negative when callers are waiting, and when they
+    /// were last counted
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket refilled with `rate` tokens per second.
+    pub(crate) fn new(rate: f64) -> Self {
+        let burst = rate.max(1.0);
+        Self {
+            rate,
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Takes a token, returning how long to wait before using it.
+    ///
+    /// Tokens are handed out in order, so callers that reserve while the
+    /// bucket is empty wait in turn instead of racing for the next token.
+    pub(crate) fn reserve(&self) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let refilled = now.duration_since(state.1).as_secs_f64() * self.rate;
+        let tokens = (state.0 + refilled).min(self.burst) - 1.0;
+        *state = (tokens, now);
+        if tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-tokens / self.rate)
+        }
+    }
+
+    /// Waits until a token is available and takes it.
+    fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Returns the bucket shared by every instance of a provider.
+///
+/// The rate of the first instance wins for the rest of the process.
+fn shared_bucket(provider: &'static str, rate: f64) -> Arc<TokenBucket> {
+    static BUCKETS: OnceLock<Mutex<HashMap<&'static str, Arc<TokenBucket>>>> = OnceLock::new();
+    let mut buckets = BUCKETS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    buckets
+        .entry(provider)
+        .or_insert_with(|| Arc::new(TokenBucket::new(rate)))
+        .clone()
+}
+
+/// Provider wrapper that waits for a token before each operation.
+pub struct ThrottledProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+    /// The bucket shared with other instances of the provider
+    bucket: Arc<TokenBucket>,
+}
+
+impl ThrottledProvider {
+    /// Wraps the provider if a rate limit is set for it.
+    ///
+    /// The provider is returned unchanged without a limit, or with a limit
+    /// that isn't a positive number of requests per second.
+    pub fn wrap(inner: Box<dyn Provider>, rate: Option<f64>) -> Box<dyn Provider> {
+        match rate {
+            Some(rate) if rate > 0.0 && rate.is_finite() => {
+                let bucket = shared_bucket(inner.name(), rate);
+                Box::new(Self { inner, bucket })
+            }
+            _ => inner,
+        }
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for ThrottledProvider {
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        self.bucket.acquire();
+        self.inner.get(project, key, profile)
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.bucket.acquire();
+        self.inner.get_with_metadata(project, key, profile)
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.bucket.acquire();
+        self.inner.set(project, key, value, profile)
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        self.bucket.acquire();
+        self.inner
+            .compare_and_set(project, key, value, profile, expected)
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        self.bucket.acquire();
+        self.inner.set_many(project, values, profile)
+    }
+
+    fn check_writable(&self, project: &str, profile: &str) -> Result<()> {
+        self.bucket.acquire();
+        self.inner.check_writable(project, profile)
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.bucket.acquire();
+        self.inner.delete(project, key, profile)
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.bucket.acquire();
+        self.inner.purge(project, key, profile)
+    }
+
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.bucket.acquire();
+        self.inner.restore(project, key, profile)
+    }
+
+    fn history(&self, project: &str, key: &str, profile: &str) -> Result<Vec<SecretMetadata>> {
+        self.bucket.acquire();
+        self.inner.history(project, key, profile)
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        self.bucket.acquire();
+        self.inner.list_keys(project, profile)
+    }
+
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        self.bucket.acquire();
+        self.inner.get_fields(project, item, fields, profile)
+    }
+
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        self.bucket.acquire();
+        self.inner.find_items(project, key, profile)
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.bucket.acquire();
+        self.inner.get_by_id(project, key, id, profile)
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        self.bucket.acquire();
+        self.inner.set_by_id(project, key, id, value, profile)
+    }
+
+    fn browse(&self) -> Result<Vec<ItemContainer>> {
+        self.bucket.acquire();
+        self.inner.browse()
+    }
+
+    fn doctor(&self) -> Vec<Check> {
+        self.bucket.acquire();
+        self.inner.doctor()
+    }
+
+    fn list_access(&self, project: &str, profile: &str) -> Result<Vec<AccessEntry>> {
+        self.bucket.acquire();
+        self.inner.list_access(project, profile)
+    }
+
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.bucket.acquire();
+        self.inner.grant(project, profile, principal)
+    }
+
+    fn renew_lease(&self, lease: &Lease) -> Result<Lease> {
+        self.bucket.acquire();
+        self.inner.renew_lease(lease)
+    }
+
+    fn revoke_lease(&self, lease: &Lease) -> Result<()> {
+        self.bucket.acquire();
+        self.inner.revoke_lease(lease)
+    }
+
+    forward_provider!(inner =>
+        allows_set, allows_delete, allows_restore, allows_list, max_value_size, works_in_sandbox,
+        access_policy, leases, name,
+    );
+}
@@ -0,0 +1,84 @@
+//! Lookup timing for local usage statistics.
+//!
+//! The [`TimedProvider`] wrapper measures every lookup made through a provider
+//! and adds the counters to the statistics file when it is dropped, so each
+//! command writes the file at most once per provider. See [`crate::stats`].
+
+use super::macros::forward_provider;
+use super::{Provider, SecretValue};
+use crate::Result;
+use crate::stats::{self, ProviderStats};
+use secrecy::SecretString;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Provider wrapper that counts lookups and their latency.
+pub struct TimedProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+    /// Counters collected since the wrapper was created
+    counters: Mutex<ProviderStats>,
+}
+
+impl TimedProvider {
+    /// Wraps the provider if statistics are enabled.
+    ///
+    /// The provider is returned unchanged when recording is disabled.
+    pub fn wrap(inner: Box<dyn Provider>) -> Box<dyn Provider> {
+        if stats::enabled() {
+            Box::new(Self {
+                inner,
+                counters: Mutex::new(ProviderStats::default()),
+            })
+        } else {
+            inner
+        }
+    }
+}
+
+impl Drop for TimedProvider {
+    fn drop(&mut self) {
+        let counters = self.counters.get_mut().unwrap_or_else(|e| e.into_inner());
+        if counters.lookups > 0 {
+            stats::record(self.inner.name(), counters);
+        }
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for TimedProvider {
+    /// Retrieves a secret and counts the lookup.
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        let start = Instant::now();
+        let result = self.inner.get(project, key, profile);
+        let outcome = result.as_ref().ok().map(Option::is_some);
+        self.counters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(start.elapsed(), outcome);
+        result
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let start = Instant::now();
+        let result = self.inner.get_with_metadata(project, key, profile);
+        let outcome = result.as_ref().ok().map(Option::is_some);
+        self.counters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(start.elapsed(), outcome);
+        result
+    }
+
+    forward_provider!(inner =>
+        set, set_many, allows_set, compare_and_set, check_writable, delete, allows_delete, purge,
+        restore, allows_restore, history, list_keys, allows_list, get_fields, find_items, get_by_id,
+        set_by_id, browse, max_value_size, works_in_sandbox, doctor, list_access, access_policy,
+        grant, leases, renew_lease, revoke_lease, name,
+    );
+}
@@ -0,0 +1,217 @@
+//! OpenTelemetry spans and metrics for provider calls.
+//!
+//! The [`TracedProvider`] wrapper records a span for every call made to a
+//! provider, and counts the calls and their latency per provider, operation
+//! and outcome. See [`crate::telemetry`].
+
+use super::macros::forward_provider;
+use super::{AccessPolicy, ItemContainer, ItemMatch, Lease, Provider, SecretMetadata, SecretValue};
+use crate::Result;
+use crate::telemetry;
+use secrecy::SecretString;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Provider wrapper that traces every call.
+pub struct TracedProvider {
+    /// The wrapped provider
+    inner: Box<dyn Provider>,
+}
+
+impl TracedProvider {
+    /// Wraps the provider if telemetry is enabled.
+    ///
+    /// The provider is returned unchanged when telemetry is disabled.
+    pub fn wrap(inner: Box<dyn Provider>) -> Box<dyn Provider> {
+        if telemetry::enabled() {
+            Box::new(Self { inner })
+        } else {
+            inner
+        }
+    }
+
+    /// Runs a call in a span and records it in the metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - Name of the call, such as `get`
+    /// * `target` - The secret, item or principal the call is for, if any
+    /// * `call` - The call to the wrapped provider
+    fn trace<T>(
+        &self,
+        operation: &'static str,
+        target: Option<&str>,
+        call: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let provider = self.inner.name();
+        let mut span = telemetry::span(format!("secretspec.provider.{}", operation));
+        span.attr("secretspec.provider", provider);
+        if let Some(target) = target {
+            span.attr("secretspec.secret", target);
+        }
+
+        let start = Instant::now();
+        let result = call();
+        let elapsed = start.elapsed();
+        if let Err(e) = &result {
+            span.fail(e);
+        }
+        drop(span);
+
+        let attributes = vec![
+            ("secretspec.provider", provider.to_string()),
+            ("secretspec.operation", operation.to_string()),
+        ];
+        let mut outcome = attributes.clone();
+        outcome.push((
+            "secretspec.outcome",
+            if result.is_ok() { "ok" } else { "error" }.to_string(),
+        ));
+        telemetry::count("secretspec.provider.calls", outcome, 1);
+        telemetry::observe("secretspec.provider.duration", attributes, elapsed);
+        result
+    }
+}
+
+#[deny(clippy::missing_trait_methods)]
+impl Provider for TracedProvider {
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        self.trace("get", Some(key), || self.inner.get(project, key, profile))
+    }
+
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.trace("get", Some(key), || {
+            self.inner.get_with_metadata(project, key, profile)
+        })
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.trace("set", Some(key), || {
+            self.inner.set(project, key, value, profile)
+        })
+    }
+
+    fn set_many(
+        &self,
+        project: &str,
+        values: &[(String, SecretString)],
+        profile: &str,
+    ) -> Result<()> {
+        self.trace("set", None, || {
+            self.inner.set_many(project, values, profile)
+        })
+    }
+
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        self.trace("set", Some(key), || {
+            self.inner
+                .compare_and_set(project, key, value, profile, expected)
+        })
+    }
+
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.trace("delete", Some(key), || {
+            self.inner.delete(project, key, profile)
+        })
+    }
+
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.trace("purge", Some(key), || {
+            self.inner.purge(project, key, profile)
+        })
+    }
+
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        self.trace("restore", Some(key), || {
+            self.inner.restore(project, key, profile)
+        })
+    }
+
+    fn history(&self, project: &str, key: &str, profile: &str) -> Result<Vec<SecretMetadata>> {
+        self.trace("history", Some(key), || {
+            self.inner.history(project, key, profile)
+        })
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        self.trace("list", None, || self.inner.list_keys(project, profile))
+    }
+
+    fn get_fields(
+        &self,
+        project: &str,
+        item: &str,
+        fields: &[&str],
+        profile: &str,
+    ) -> Result<HashMap<String, SecretString>> {
+        self.trace("get", Some(item), || {
+            self.inner.get_fields(project, item, fields, profile)
+        })
+    }
+
+    fn find_items(&self, project: &str, key: &str, profile: &str) -> Result<Vec<ItemMatch>> {
+        self.trace("list", None, || {
+            self.inner.find_items(project, key, profile)
+        })
+    }
+
+    fn get_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        self.trace("get", Some(key), || {
+            self.inner.get_by_id(project, key, id, profile)
+        })
+    }
+
+    fn set_by_id(
+        &self,
+        project: &str,
+        key: &str,
+        id: &str,
+        value: &SecretString,
+        profile: &str,
+    ) -> Result<()> {
+        self.trace("set", Some(key), || {
+            self.inner.set_by_id(project, key, id, value, profile)
+        })
+    }
+
+    fn browse(&self) -> Result<Vec<ItemContainer>> {
+        self.trace("list", None, || self.inner.browse())
+    }
+
+    fn grant(&self, project: &str, profile: &str, principal: &str) -> Result<AccessPolicy> {
+        self.trace("grant", Some(principal), || {
+            self.inner.grant(project, profile, principal)
+        })
+    }
+
+    fn renew_lease(&self, lease: &Lease) -> Result<Lease> {
+        self.trace("renew_lease", None, || self.inner.renew_lease(lease))
+    }
+
+    fn revoke_lease(&self, lease: &Lease) -> Result<()> {
+        self.trace("revoke_lease", None, || self.inner.revoke_lease(lease))
+    }
+
+    forward_provider!(inner =>
+        allows_set, check_writable, allows_delete, allows_restore, allows_list, max_value_size,
+        works_in_sandbox, doctor, list_access, access_policy, leases, name,
+    );
+}
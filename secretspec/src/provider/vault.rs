@@ -0,0 +1,315 @@
+//! HashiCorp Vault providers.
+//!
+//! The [`VaultKvProvider`](kv::VaultKvProvider) stores secrets in a KV version 2 secrets engine,
+//! which keeps earlier versions of every secret and reports when each version
+//! was written.
+//!
+//! Vault secrets engines such as `database` and `aws` generate a fresh
+//! credential on every read and attach it to a lease that expires unless it
+//! is renewed. The [`VaultDynamicProvider`] reads one credential per process
+//! through the `vault` CLI and serves its fields as secrets, so a spec can
+//! declare `DB_USERNAME` and `DB_PASSWORD` and receive a matching pair.
+//!
+//! `secretspec run` renews the lease in the background while the command
+//! runs and revokes it when the command exits.
+//!
+//! Both providers authenticate with the CLI's own token by default, or log in
+//! with LDAP or Kerberos; see [`auth`]. In WebAssembly builds, where there is
+//! no CLI, `vault://` URIs are served by the `fetch` provider instead, which
+//! calls Vault's HTTP API.
+
+pub(crate) mod auth;
+mod client;
+#[cfg(any(target_arch = "wasm32", test))]
+pub(crate) mod fetch;
+pub(crate) mod kv;
+
+pub use auth::VaultAuth;
+
+use super::session::{self, TokenCache};
+use super::{Lease, Provider};
+use crate::doctor::Check;
+use crate::provider::command::{CommandRunner, SystemRunner};
+use crate::{Result, SecretSpecError};
+use client::VaultClient;
+use secrecy::SecretString;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use url::Url;
+
+/// Configuration for the Vault dynamic credentials provider.
+///
+/// Parsed from URIs of the form
+/// `vault+dynamic://<mount>/<path>?address=<url>&namespace=<ns>`, e.g.
+/// `vault+dynamic://database/creds/app-role`. Without `address` and
+/// `namespace` the `vault` CLI uses `VAULT_ADDR` and `VAULT_NAMESPACE`.
+///
+/// `auth=ldap&username=<user>` or `auth=kerberos` select how to log in, with
+/// `mount` naming a non-default auth mount and `service`, `realm`,
+/// `keytab_path` and `krb5conf_path` passed on to Kerberos.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultDynamicConfig {
+    /// The credential endpoint to read, e.g. `database/creds/app-role`
+    pub path: String,
+    /// The Vault server address, overriding `VAULT_ADDR`
+    pub address: Option<String>,
+    /// The Vault Enterprise namespace, overriding `VAULT_NAMESPACE`
+    pub namespace: Option<String>,
+    /// How to obtain a Vault token
+    pub auth: VaultAuth,
+}
+
+/// Query parameters understood by vault URIs, including those of
+/// [`VaultAuth::from_params`].
+const PARAMS: &[&str] = &[
+    "address",
+    "namespace",
+    "auth",
+    "username",
+    "mount",
+    "service",
+    "realm",
+    "keytab_path",
+    "krb5conf_path",
+];
+
+impl TryFrom<&Url> for VaultDynamicConfig {
+    type Error = SecretSpecError;
+
+    fn try_from(url: &Url) -> std::result::Result<Self, Self::Error> {
+        if url.scheme() != "vault+dynamic" {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Invalid scheme '{}' for vault dynamic provider",
+                url.scheme()
+            )));
+        }
+
+        let path = format!("{}{}", url.host_str().unwrap_or(""), url.path())
+            .trim_matches('/')
+            .to_string();
+        if path.is_empty() {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "Vault dynamic provider needs a credential path, e.g. vault+dynamic://database/creds/app-role".to_string(),
+            ));
+        }
+
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        if let Some(name) = params.keys().find(|name| !PARAMS.contains(&name.as_str())) {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Unknown vault dynamic provider option '{}'",
+                name
+            )));
+        }
+
+        Ok(Self {
+            path,
+            address: params.get("address").cloned(),
+            namespace: params.get("namespace").cloned(),
+            auth: VaultAuth::from_params(&params)?,
+        })
+    }
+}
+
+/// Response of `vault read` and `vault lease renew`.
+#[derive(Debug, Deserialize)]
+struct VaultResponse {
+    #[serde(default)]
+    lease_id: String,
+    /// Time to live in seconds
+    #[serde(default)]
+    lease_duration: u64,
+    #[serde(default)]
+    renewable: bool,
+    #[serde(default)]
+    data: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl VaultResponse {
+    /// Returns the lease, or `None` if the response isn't leased.
+    fn lease(&self) -> Option<Lease> {
+        (!self.lease_id.is_empty()).then(|| Lease {
+            id: self.lease_id.clone(),
+            ttl: Duration::from_secs(self.lease_duration),
+            renewable: self.renewable,
+        })
+    }
+}
+
+/// A credential read from Vault.
+struct Credential {
+    /// Credential fields, e.g. `username` and `password`
+    fields: HashMap<String, String>,
+    /// The lease the credential was issued under
+    lease: Option<Lease>,
+}
+
+impl Credential {
+    /// Returns the field a secret maps to.
+    ///
+    /// A secret matches a field named like its lowercased name (`PASSWORD`
+    /// matches `password`) or its suffix (`DB_PASSWORD` and `AWS_ACCESS_KEY`
+    /// match `password` and `access_key`). The longest matching field wins.
+    fn field(&self, key: &str) -> Option<&String> {
+        let key = key.to_lowercase();
+        self.fields
+            .iter()
+            .filter(|(name, _)| {
+                key == **name
+                    || key
+                        .strip_suffix(name.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('_'))
+            })
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, value)| value)
+    }
+}
+
+/// Read-only provider serving short-lived credentials from a Vault secrets engine.
+///
+/// The credential is read once, on the first lookup, so all secrets of a spec
+/// come from the same credential. The project and profile are ignored: the
+/// credential endpoint alone decides what is returned.
+///
+/// # Requirements
+///
+/// The Vault CLI (`vault`) must be installed and logged in, or `VAULT_TOKEN`
+/// must be set.
+pub struct VaultDynamicProvider {
+    config: VaultDynamicConfig,
+    /// Runs the `vault` CLI against the configured server.
+    client: VaultClient,
+    /// The credential read by the first lookup
+    credential: Mutex<Option<Credential>>,
+}
+
+crate::register_provider! {
+    struct: VaultDynamicProvider,
+    config: VaultDynamicConfig,
+    name: "vault+dynamic",
+    description: "HashiCorp Vault dynamic credentials",
+    schemes: ["vault+dynamic"],
+    examples: ["vault+dynamic://database/creds/app-role", "vault+dynamic://aws/creds/deploy"],
+    params: |_| PARAMS,
+}
+
+/// Service that login tokens are cached under.
+const TOKEN_CACHE_SERVICE: &str = "secretspec-vault";
+
+impl VaultDynamicProvider {
+    /// Creates a new VaultDynamicProvider with the given configuration.
+    pub fn new(config: VaultDynamicConfig) -> Self {
+        Self::with_runner(config, Arc::new(SystemRunner))
+            .with_token_cache(session::default_cache(TOKEN_CACHE_SERVICE))
+    }
+
+    /// Creates a VaultDynamicProvider that runs the `vault` CLI through `runner`.
+    ///
+    /// Tokens are only kept for the lifetime of the provider.
+    pub(crate) fn with_runner(config: VaultDynamicConfig, runner: Arc<dyn CommandRunner>) -> Self {
+        let client = VaultClient::new(
+            config.address.clone(),
+            config.namespace.clone(),
+            config.auth.clone(),
+            runner,
+        );
+        Self {
+            config,
+            client,
+            credential: Mutex::new(None),
+        }
+    }
+
+    /// Replaces the cache that tokens are stored in between commands.
+    pub(crate) fn with_token_cache(mut self, token_cache: Arc<dyn TokenCache>) -> Self {
+        self.client = self.client.with_token_cache(token_cache);
+        self
+    }
+
+    /// Reads a new credential from the configured endpoint.
+    fn read_credential(&self) -> Result<Option<Credential>> {
+        let output = match self
+            .client
+            .execute(&["read", "-format=json", &self.config.path])
+        {
+            Ok(output) => output,
+            Err(SecretSpecError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let response: VaultResponse = serde_json::from_str(&output)?;
+        let lease = response.lease();
+
+        let fields = response
+            .data
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(name, value)| match value {
+                serde_json::Value::String(s) => Some((name, s)),
+                serde_json::Value::Null => None,
+                other => Some((name, other.to_string())),
+            })
+            .collect();
+        Ok(Some(Credential { fields, lease }))
+    }
+}
+
+impl Provider for VaultDynamicProvider {
+    fn name(&self) -> &'static str {
+        Self::PROVIDER_NAME
+    }
+
+    /// Returns the credential field matching `key`, reading a credential on
+    /// first use.
+    fn get(&self, _project: &str, key: &str, _profile: &str) -> Result<Option<SecretString>> {
+        let mut credential = self.credential.lock().unwrap_or_else(|e| e.into_inner());
+        if credential.is_none() {
+            *credential = self.read_credential()?;
+        }
+
+        Ok(credential
+            .as_ref()
+            .and_then(|credential| credential.field(key))
+            .map(|value| SecretString::new(value.clone().into())))
+    }
+
+    fn set(&self, _project: &str, _key: &str, _value: &SecretString, _profile: &str) -> Result<()> {
+        Err(SecretSpecError::ProviderOperationFailed(
+            "Vault dynamic credentials are generated by Vault and cannot be set".to_string(),
+        ))
+    }
+
+    fn allows_set(&self) -> bool {
+        false
+    }
+
+    fn leases(&self) -> Vec<Lease> {
+        let credential = self.credential.lock().unwrap_or_else(|e| e.into_inner());
+        credential
+            .as_ref()
+            .and_then(|credential| credential.lease.clone())
+            .into_iter()
+            .collect()
+    }
+
+    /// Extends a lease with `vault lease renew`.
+    fn renew_lease(&self, lease: &Lease) -> Result<Lease> {
+        let output = self
+            .client
+            .execute(&["lease", "renew", "-format=json", &lease.id])?;
+        let response: VaultResponse = serde_json::from_str(&output)?;
+        Ok(response.lease().unwrap_or_else(|| lease.clone()))
+    }
+
+    /// Revokes a lease with `vault lease revoke`.
+    fn revoke_lease(&self, lease: &Lease) -> Result<()> {
+        match self.client.execute(&["lease", "revoke", &lease.id]) {
+            Ok(_) | Err(SecretSpecError::NotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn doctor(&self) -> Vec<Check> {
+        self.client.doctor()
+    }
+}
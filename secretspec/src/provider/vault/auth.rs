@@ -0,0 +1,178 @@
+//! Vault authentication methods.
+//!
+//! By default the `vault` CLI uses its own token (`VAULT_TOKEN` or the token
+//! helper written by `vault login`). Other methods log in on first use with
+//! `vault login -no-store`, and the resulting token is cached in the session
+//! keyring until it expires, so users behind LDAP or Kerberos aren't asked
+//! for their password on every command.
+
+use crate::provider::command::Invocation;
+use crate::provider::session::CachedToken;
+use crate::{Result, SecretSpecError, prompt};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How the provider obtains a Vault token.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum VaultAuth {
+    /// Use the CLI's existing token (`auth=token`, the default)
+    #[default]
+    Token,
+    /// Log in with an LDAP username and a password prompted for on the terminal
+    Ldap {
+        /// The LDAP username
+        username: String,
+        /// Mount path of the auth method, if not `ldap`
+        mount: Option<String>,
+    },
+    /// Log in with Kerberos (SPNEGO), from a keytab or the credentials cache
+    Kerberos {
+        /// Parameters passed to `vault login -method=kerberos`, such as
+        /// `username`, `service`, `realm`, `keytab_path` and `krb5conf_path`
+        params: Vec<(String, String)>,
+        /// Mount path of the auth method, if not `kerberos`
+        mount: Option<String>,
+    },
+}
+
+impl VaultAuth {
+    /// Builds the authentication method from provider URI parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for unknown methods or missing required parameters
+    pub(super) fn from_params(params: &HashMap<String, String>) -> Result<Self> {
+        let mount = params.get("mount").cloned();
+        match params.get("auth").map(String::as_str) {
+            None | Some("token") => Ok(Self::Token),
+            Some("ldap") => {
+                let username = params.get("username").cloned().ok_or_else(|| {
+                    SecretSpecError::ProviderOperationFailed(
+                        "Vault LDAP authentication needs a username, e.g. ?auth=ldap&username=alice"
+                            .to_string(),
+                    )
+                })?;
+                Ok(Self::Ldap { username, mount })
+            }
+            Some("kerberos") => {
+                let params = [
+                    "username",
+                    "service",
+                    "realm",
+                    "keytab_path",
+                    "krb5conf_path",
+                ]
+                .iter()
+                .filter_map(|name| {
+                    params
+                        .get(*name)
+                        .map(|value| (name.to_string(), value.clone()))
+                })
+                .collect();
+                Ok(Self::Kerberos { params, mount })
+            }
+            Some(other) => Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Unknown Vault auth method '{}'. Use token, ldap or kerberos.",
+                other
+            ))),
+        }
+    }
+
+    /// Returns the name of the method as used by `vault login -method`.
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Token => "token",
+            Self::Ldap { .. } => "ldap",
+            Self::Kerberos { .. } => "kerberos",
+        }
+    }
+
+    /// Returns who is logging in, if known.
+    fn principal(&self) -> Option<&str> {
+        match self {
+            Self::Token => None,
+            Self::Ldap { username, .. } => Some(username),
+            Self::Kerberos { params, .. } => params
+                .iter()
+                .find(|(name, _)| name == "username")
+                .map(|(_, value)| value.as_str()),
+        }
+    }
+
+    /// Returns the key the token of this method is cached under.
+    pub(super) fn cache_key(&self, address: &str) -> String {
+        format!(
+            "{}|{}|{}",
+            address,
+            self.method(),
+            self.principal().unwrap_or("")
+        )
+    }
+
+    /// Builds the `vault login` invocation, prompting for credentials if needed.
+    ///
+    /// Returns `None` for [`VaultAuth::Token`], which never logs in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a password is needed but no terminal is attached
+    pub(super) fn login(&self) -> Result<Option<Invocation>> {
+        let login = |mount: &Option<String>| {
+            let mut invocation = Invocation::new("vault").args([
+                "login",
+                "-format=json",
+                "-no-store",
+                &format!("-method={}", self.method()),
+            ]);
+            if let Some(mount) = mount {
+                invocation = invocation.args([format!("-path={}", mount)]);
+            }
+            invocation
+        };
+
+        match self {
+            Self::Token => Ok(None),
+            Self::Ldap { username, mount } => {
+                let password = prompt::password(&format!("Vault LDAP password for {}", username))?;
+                // `password=-` makes the CLI read the password from stdin
+                Ok(Some(
+                    login(mount)
+                        .args([format!("username={}", username), "password=-".to_string()])
+                        .stdin(password.expose_secret().as_bytes()),
+                ))
+            }
+            Self::Kerberos { params, mount } => Ok(Some(
+                login(mount).args(
+                    params
+                        .iter()
+                        .map(|(name, value)| format!("{}={}", name, value)),
+                ),
+            )),
+        }
+    }
+}
+
+/// Response of `vault login -format=json`.
+#[derive(Debug, Deserialize)]
+pub(super) struct LoginResponse {
+    auth: LoginAuth,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginAuth {
+    client_token: String,
+    /// Time to live in seconds, 0 for tokens that never expire
+    #[serde(default)]
+    lease_duration: u64,
+}
+
+impl CachedToken {
+    /// Creates a cache entry from a login response.
+    pub(super) fn from_login(response: LoginResponse) -> Self {
+        let ttl = (response.auth.lease_duration > 0)
+            .then(|| Duration::from_secs(response.auth.lease_duration));
+        Self::new(response.auth.client_token, ttl)
+    }
+}
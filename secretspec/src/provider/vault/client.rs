@@ -0,0 +1,225 @@
+//! Running the `vault` CLI against a configured server.
+//!
+//! [`VaultClient`] is shared by the Vault providers. It points each command
+//! at the configured address and namespace, logs in with the configured
+//! [`VaultAuth`] method when needed, and turns the CLI's error messages into
+//! typed errors.
+
+use super::auth::{LoginResponse, VaultAuth};
+use crate::doctor::{self, Check, Status};
+use crate::provider::command::{CommandRunner, Invocation};
+use crate::provider::session::{CachedToken, NoTokenCache, TokenCache};
+use crate::{Result, SecretSpecError};
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
+
+/// Returns the error for operations that need the user to log in first.
+fn login_required(auth: &VaultAuth) -> SecretSpecError {
+    let fix = match auth {
+        VaultAuth::Token => "Run 'vault login' or set VAULT_TOKEN.",
+        VaultAuth::Ldap { .. } => "Check the LDAP username and password.",
+        VaultAuth::Kerberos { .. } => "Run 'kinit' or check the keytab, then try again.",
+    };
+    SecretSpecError::AuthRequired {
+        provider: "Vault".to_string(),
+        fix: fix.to_string(),
+    }
+}
+
+/// Runs `vault` commands with a token for the configured server.
+pub(crate) struct VaultClient {
+    /// The Vault server address, overriding `VAULT_ADDR`
+    address: Option<String>,
+    /// The Vault Enterprise namespace, overriding `VAULT_NAMESPACE`
+    namespace: Option<String>,
+    /// How to obtain a Vault token
+    auth: VaultAuth,
+    /// Runs the `vault` CLI.
+    runner: Arc<dyn CommandRunner>,
+    /// Stores tokens obtained by logging in between commands
+    token_cache: Arc<dyn TokenCache>,
+    /// The token obtained by logging in, once needed
+    token: Mutex<Option<String>>,
+}
+
+impl VaultClient {
+    /// Creates a client that runs the `vault` CLI through `runner`.
+    ///
+    /// Tokens are only kept for the lifetime of the client.
+    pub(crate) fn new(
+        address: Option<String>,
+        namespace: Option<String>,
+        auth: VaultAuth,
+        runner: Arc<dyn CommandRunner>,
+    ) -> Self {
+        Self {
+            address,
+            namespace,
+            auth,
+            runner,
+            token_cache: Arc::new(NoTokenCache),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Replaces the cache that tokens are stored in between commands.
+    pub(crate) fn with_token_cache(mut self, token_cache: Arc<dyn TokenCache>) -> Self {
+        self.token_cache = token_cache;
+        self
+    }
+
+    /// Runs a `vault` command with a token, logging in first if needed.
+    ///
+    /// A cached token that Vault rejects is discarded and the login retried once.
+    pub(crate) fn execute(&self, args: &[&str]) -> Result<Zeroizing<String>> {
+        self.execute_with(args, None)
+    }
+
+    /// Runs a `vault` command like [`execute`](Self::execute), passing
+    /// `input` on stdin so values never appear in the argument list.
+    pub(crate) fn execute_with_input(
+        &self,
+        args: &[&str],
+        input: &[u8],
+    ) -> Result<Zeroizing<String>> {
+        self.execute_with(args, Some(input))
+    }
+
+    fn execute_with(&self, args: &[&str], input: Option<&[u8]>) -> Result<Zeroizing<String>> {
+        let token = self.token()?;
+        match self.run(self.invocation(args, token.as_deref(), input)) {
+            Err(SecretSpecError::AuthRequired { .. }) if token.is_some() => {
+                self.forget_token();
+                let token = self.token()?;
+                self.run(self.invocation(args, token.as_deref(), input))
+            }
+            result => result,
+        }
+    }
+
+    /// Builds a `vault` invocation against the configured server.
+    fn invocation(&self, args: &[&str], token: Option<&str>, input: Option<&[u8]>) -> Invocation {
+        let mut invocation = self.with_server(Invocation::new("vault").args(args), token);
+        if let Some(input) = input {
+            invocation = invocation.stdin(input);
+        }
+        invocation
+    }
+
+    /// Points an invocation at the configured server and namespace.
+    fn with_server(&self, mut invocation: Invocation, token: Option<&str>) -> Invocation {
+        if let Some(address) = &self.address {
+            invocation = invocation.env("VAULT_ADDR", address);
+        }
+        if let Some(namespace) = &self.namespace {
+            invocation = invocation.env("VAULT_NAMESPACE", namespace);
+        }
+        if let Some(token) = token {
+            invocation = invocation.env("VAULT_TOKEN", token);
+        }
+        invocation
+    }
+
+    /// Returns the key the login token is cached under.
+    fn cache_key(&self) -> String {
+        let address = self
+            .address
+            .clone()
+            .or_else(|| std::env::var("VAULT_ADDR").ok())
+            .unwrap_or_else(|| "https://127.0.0.1:8200".to_string());
+        self.auth.cache_key(&address)
+    }
+
+    /// Returns the token to run commands with.
+    ///
+    /// Returns `None` for token authentication, where the CLI uses its own
+    /// token. Other methods reuse a fresh cached token or log in.
+    fn token(&self) -> Result<Option<String>> {
+        let mut token = self.token.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(token) = token.as_ref() {
+            return Ok(Some(token.clone()));
+        }
+
+        let key = self.cache_key();
+        if let Some(cached) = self.token_cache.load(&key)
+            && cached.is_fresh()
+        {
+            *token = Some(cached.token.clone());
+            return Ok(Some(cached.token));
+        }
+
+        let Some(login) = self.auth.login()? else {
+            return Ok(None);
+        };
+        let output = self.run(self.with_server(login, None))?;
+        let cached = CachedToken::from_login(serde_json::from_str::<LoginResponse>(&output)?);
+        self.token_cache.store(&key, &cached);
+        *token = Some(cached.token.clone());
+        Ok(Some(cached.token))
+    }
+
+    /// Discards the login token, so the next command logs in again.
+    fn forget_token(&self) {
+        *self.token.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.token_cache.clear(&self.cache_key());
+    }
+
+    /// Runs a `vault` invocation, translating common failures into typed errors.
+    fn run(&self, invocation: Invocation) -> Result<Zeroizing<String>> {
+        let output = match self.runner.run(&invocation) {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(SecretSpecError::CliMissing {
+                    cli: "Vault CLI (vault)".to_string(),
+                    install_hint: "To install it:\n  - macOS: brew install hashicorp/tap/vault\n  - Linux: https://developer.hashicorp.com/vault/install\n  - NixOS: nix-env -iA nixpkgs.vault\n\nAfter installation, run 'vault login' to authenticate.".to_string(),
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if !output.success {
+            let stderr = output.stderr;
+            if stderr.contains("missing client token")
+                || stderr.contains("invalid token")
+                || stderr.contains("Error authenticating")
+            {
+                return Err(login_required(&self.auth));
+            }
+            if stderr.contains("permission denied") {
+                return Err(SecretSpecError::PermissionDenied(stderr));
+            }
+            if stderr.contains("Code: 429") {
+                return Err(SecretSpecError::RateLimited {
+                    provider: "Vault".to_string(),
+                    retry_after: None,
+                });
+            }
+            if stderr.contains("check-and-set parameter did not match") {
+                return Err(SecretSpecError::Conflict(stderr));
+            }
+            if stderr.contains("No value found at") || stderr.contains("lease not found") {
+                return Err(SecretSpecError::NotFound(stderr));
+            }
+            return Err(SecretSpecError::ProviderOperationFailed(stderr));
+        }
+
+        Ok(output.stdout)
+    }
+
+    /// Checks that the `vault` CLI is installed and has a valid token.
+    pub(crate) fn doctor(&self) -> Vec<Check> {
+        let cli = doctor::check_cli(
+            "Vault CLI",
+            self.run(self.invocation(&["version"], None, None)),
+        );
+        if cli.status != Status::Pass {
+            return vec![cli];
+        }
+
+        let auth = match self.execute(&["token", "lookup", "-format=json"]) {
+            Ok(_) => Check::pass("Vault authentication", "Token is valid"),
+            Err(e) => Check::from_error("Vault authentication", &e),
+        };
+        vec![cli, auth]
+    }
+}
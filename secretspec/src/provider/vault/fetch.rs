@@ -0,0 +1,358 @@
+//! Vault KV over HTTP, for WebAssembly builds.
+//!
+//! There is no `vault` CLI to run in a browser, so on wasm32 the `vault`
+//! scheme is served by [`VaultFetchProvider`], which calls Vault's HTTP API
+//! directly with a blocking `XMLHttpRequest`. Secrets are stored at the same
+//! paths as with [`VaultKvProvider`](super::kv::VaultKvProvider), so a
+//! browser-based tool resolves what the CLI wrote.
+//!
+//! The page supplies the token with [`set_token`], and Vault's CORS settings
+//! must allow the page's origin.
+
+use super::VaultAuth;
+use super::kv::{KvResponse, VaultKvConfig};
+use crate::provider::{Provider, SecretValue};
+use crate::{Result, SecretSpecError};
+use http::{Method, Request, Response, StatusCode};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Registers [`VaultFetchProvider`] for the `vault` scheme in builds without
+/// the `native` feature, which have no linkme-based registry.
+#[cfg(target_arch = "wasm32")]
+pub(crate) const REGISTRATION: crate::provider::ProviderRegistration =
+    crate::provider::ProviderRegistration {
+        info: crate::provider::ProviderInfo {
+            name: "vault",
+            description: "HashiCorp Vault KV v2 secrets engine, over HTTP",
+            examples: &["vault://secret?address=https://vault.example.com"],
+        },
+        schemes: &["vault"],
+        params: |_| super::PARAMS,
+        factory: |url| {
+            let config = VaultKvConfig::try_from(url)?;
+            Ok(Box::new(VaultFetchProvider::new(config)?))
+        },
+        explain: |url| {
+            let config = VaultKvConfig::try_from(url)?;
+            Ok(format!("{:#?}", config))
+        },
+    };
+
+/// The token sent with every request, set by the page.
+static TOKEN: Mutex<Option<SecretString>> = Mutex::new(None);
+
+/// Sets the Vault token that `vault://` providers authenticate with in
+/// WebAssembly builds.
+///
+/// Browser-based tools obtain the token themselves, e.g. by logging in to
+/// Vault with the user's credentials, and set it before resolving secrets.
+pub fn set_token(token: SecretString) {
+    *TOKEN.lock().unwrap_or_else(|e| e.into_inner()) = Some(token);
+}
+
+/// Returns the token set with [`set_token`].
+fn token() -> Option<SecretString> {
+    TOKEN.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Sends HTTP requests and waits for their responses.
+pub(crate) trait Fetch: Send + Sync {
+    /// Sends the request, failing only if no response was received.
+    fn fetch(&self, request: Request<String>) -> Result<Response<String>>;
+}
+
+/// Sends requests with the browser's `XMLHttpRequest` in blocking mode, as
+/// providers are synchronous.
+#[cfg(target_arch = "wasm32")]
+struct XhrFetch;
+
+#[cfg(target_arch = "wasm32")]
+impl Fetch for XhrFetch {
+    fn fetch(&self, request: Request<String>) -> Result<Response<String>> {
+        use web_sys::XmlHttpRequest;
+        use web_sys::wasm_bindgen::JsValue;
+
+        let failed = |e: JsValue| {
+            SecretSpecError::ProviderOperationFailed(format!("Vault request failed: {:?}", e))
+        };
+        let xhr = XmlHttpRequest::new().map_err(failed)?;
+        xhr.open_with_async(request.method().as_str(), &request.uri().to_string(), false)
+            .map_err(failed)?;
+        for (name, value) in request.headers() {
+            let value = value.to_str().map_err(|e| {
+                SecretSpecError::ProviderOperationFailed(format!("Invalid header {}: {}", name, e))
+            })?;
+            xhr.set_request_header(name.as_str(), value)
+                .map_err(failed)?;
+        }
+        if request.body().is_empty() {
+            xhr.send()
+        } else {
+            xhr.send_with_opt_str(Some(request.body()))
+        }
+        .map_err(failed)?;
+
+        let status = xhr.status().map_err(failed)?;
+        if status == 0 {
+            // The browser hides why, but it's usually CORS
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Could not reach Vault at {}; check that its CORS settings allow this page",
+                request.uri()
+            )));
+        }
+        let body = xhr.response_text().map_err(failed)?.unwrap_or_default();
+        Response::builder()
+            .status(status)
+            .body(body)
+            .map_err(|e| SecretSpecError::ProviderOperationFailed(e.to_string()))
+    }
+}
+
+/// Error response of the Vault API.
+#[derive(Debug, Default, Deserialize)]
+struct ErrorResponse {
+    #[serde(default)]
+    errors: Vec<String>,
+}
+
+/// Response of listing the secrets in a folder.
+#[derive(Debug, Deserialize)]
+struct ListResponse {
+    data: ListData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListData {
+    keys: Vec<String>,
+}
+
+/// Provider storing secrets in a HashiCorp Vault KV version 2 secrets engine
+/// through Vault's HTTP API.
+///
+/// Uses the configuration of [`VaultKvProvider`](super::kv::VaultKvProvider),
+/// except that `address` is required and only token authentication is
+/// supported.
+pub struct VaultFetchProvider {
+    config: VaultKvConfig,
+    /// Base URL of the Vault server
+    address: Url,
+    /// Sends the requests
+    fetch: Arc<dyn Fetch>,
+}
+
+impl VaultFetchProvider {
+    /// Creates a VaultFetchProvider that sends requests with the browser's
+    /// `XMLHttpRequest`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration has no valid `address` or logs
+    /// in with LDAP or Kerberos
+    #[cfg(target_arch = "wasm32")]
+    pub fn new(config: VaultKvConfig) -> Result<Self> {
+        Self::with_fetch(config, Arc::new(XhrFetch))
+    }
+
+    /// Creates a VaultFetchProvider that sends requests through `fetch`.
+    pub(crate) fn with_fetch(config: VaultKvConfig, fetch: Arc<dyn Fetch>) -> Result<Self> {
+        if config.auth != VaultAuth::Token {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "In the browser, the vault provider only supports token authentication".to_string(),
+            ));
+        }
+        let address = config.address.as_deref().ok_or_else(|| {
+            SecretSpecError::ProviderOperationFailed(
+                "In the browser, the vault provider needs the server address, e.g. vault://secret?address=https://vault.example.com"
+                    .to_string(),
+            )
+        })?;
+        let address = Url::parse(address).map_err(|e| {
+            SecretSpecError::ProviderOperationFailed(format!(
+                "Invalid Vault address '{}': {}",
+                address, e
+            ))
+        })?;
+        Ok(Self {
+            config,
+            address,
+            fetch,
+        })
+    }
+
+    /// Returns the path of a project and profile's folder within the mount.
+    fn folder(&self, project: &str, profile: &str) -> String {
+        format!("{}/{}/{}", self.config.prefix, project, profile)
+    }
+
+    /// Returns the API URL of `path` under `endpoint` of the mount, e.g.
+    /// `data` or `metadata`.
+    fn url(&self, endpoint: &str, path: &str) -> Result<Url> {
+        let mut url = self.address.clone();
+        url.path_segments_mut()
+            .map_err(|_| {
+                SecretSpecError::ProviderOperationFailed(format!(
+                    "Invalid Vault address '{}'",
+                    self.address
+                ))
+            })?
+            .pop_if_empty()
+            .push("v1")
+            .push(&self.config.mount)
+            .push(endpoint)
+            .extend(path.split('/'));
+        Ok(url)
+    }
+
+    /// Sends a request with the token and namespace, returning the body of
+    /// a successful response.
+    fn send(&self, method: Method, url: Url, body: Option<serde_json::Value>) -> Result<String> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(url.as_str())
+            .header("Content-Type", "application/json");
+        if let Some(token) = token() {
+            request = request.header("X-Vault-Token", token.expose_secret());
+        }
+        if let Some(namespace) = &self.config.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let request = request
+            .body(body)
+            .map_err(|e| SecretSpecError::ProviderOperationFailed(e.to_string()))?;
+
+        let response = self.fetch.fetch(request)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.into_body());
+        }
+        let errors = serde_json::from_str::<ErrorResponse>(response.body())
+            .unwrap_or_default()
+            .errors
+            .join("; ");
+        Err(match status {
+            StatusCode::NOT_FOUND => SecretSpecError::NotFound(errors),
+            StatusCode::FORBIDDEN if token().is_none() => SecretSpecError::ProviderOperationFailed(
+                "Vault needs a token; set one with secretspec::set_vault_token".to_string(),
+            ),
+            StatusCode::FORBIDDEN => SecretSpecError::PermissionDenied(errors),
+            StatusCode::TOO_MANY_REQUESTS => SecretSpecError::RateLimited {
+                provider: "Vault".to_string(),
+                retry_after: None,
+            },
+            _ if errors.contains("check-and-set parameter did not match") => {
+                SecretSpecError::Conflict(errors)
+            }
+            _ => SecretSpecError::ProviderOperationFailed(format!(
+                "Vault returned {}: {}",
+                status, errors
+            )),
+        })
+    }
+
+    /// Writes a new version of a secret, with check-and-set if `cas` is set.
+    fn put(&self, path: &str, value: &SecretString, cas: Option<u64>) -> Result<()> {
+        let mut body = serde_json::json!({ "data": { "value": value.expose_secret() } });
+        if let Some(version) = cas {
+            body["options"] = serde_json::json!({ "cas": version });
+        }
+        self.send(Method::POST, self.url("data", path)?, Some(body))?;
+        Ok(())
+    }
+}
+
+impl Provider for VaultFetchProvider {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        Ok(self
+            .get_with_metadata(project, key, profile)?
+            .map(|secret| secret.value))
+    }
+
+    /// Reads the latest version, reporting its version number and creation
+    /// time.
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let path = format!("{}/{}", self.folder(project, profile), key);
+        let body = match self.send(Method::GET, self.url("data", &path)?, None) {
+            Ok(body) => body,
+            Err(SecretSpecError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let response: KvResponse = serde_json::from_str(&body)?;
+        Ok(response.into_secret())
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        let path = format!("{}/{}", self.folder(project, profile), key);
+        self.put(&path, value, None)
+    }
+
+    /// Writes with Vault's check-and-set, so the write fails unless the
+    /// secret is still at version `expected`.
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        let version = expected.parse::<u64>().map_err(|_| {
+            SecretSpecError::ProviderOperationFailed(format!(
+                "Vault revisions are version numbers, not '{}'",
+                expected
+            ))
+        })?;
+        let path = format!("{}/{}", self.folder(project, profile), key);
+        self.put(&path, value, Some(version)).map_err(|e| match e {
+            SecretSpecError::Conflict(_) => SecretSpecError::Conflict(format!(
+                "'{}' was changed since revision {}",
+                key, expected
+            )),
+            e => e,
+        })
+    }
+
+    /// Deletes the latest version; earlier versions stay.
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let path = format!("{}/{}", self.folder(project, profile), key);
+        self.send(Method::DELETE, self.url("data", &path)?, None)?;
+        Ok(())
+    }
+
+    fn allows_delete(&self) -> bool {
+        true
+    }
+
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let mut url = self.url("metadata", &self.folder(project, profile))?;
+        url.query_pairs_mut().append_pair("list", "true");
+        let body = match self.send(Method::GET, url, None) {
+            Ok(body) => body,
+            Err(SecretSpecError::NotFound(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let response: ListResponse = serde_json::from_str(&body)?;
+        Ok(response
+            .data
+            .keys
+            .into_iter()
+            .filter(|key| !key.ends_with('/'))
+            .collect())
+    }
+
+    fn allows_list(&self) -> bool {
+        true
+    }
+}
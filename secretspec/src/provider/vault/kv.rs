@@ -0,0 +1,414 @@
+//! HashiCorp Vault KV version 2 secrets engine.
+
+use super::client::VaultClient;
+use super::{PARAMS, TOKEN_CACHE_SERVICE, VaultAuth};
+use crate::doctor::Check;
+use crate::provider::command::{CommandRunner, SystemRunner};
+use crate::provider::session::{self, TokenCache};
+use crate::provider::{Provider, SecretMetadata, SecretValue};
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use url::Url;
+
+/// Folder secrets are stored under when the URI doesn't name one.
+const DEFAULT_PREFIX: &str = "secretspec";
+
+/// Configuration for the Vault KV provider.
+///
+/// Parsed from URIs of the form
+/// `vault://<mount>[/<prefix>]?address=<url>&namespace=<ns>`, e.g.
+/// `vault://secret` or `vault://kv/teams/payments`. Secrets are stored at
+/// `<prefix>/<project>/<profile>/<key>` in the KV v2 engine mounted at
+/// `<mount>`, with `secretspec` as the default prefix.
+///
+/// `address`, `namespace` and the login options are those of
+/// [`VaultDynamicConfig`](super::VaultDynamicConfig).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultKvConfig {
+    /// The path the KV v2 engine is mounted at, e.g. `secret`
+    pub mount: String,
+    /// The folder secrets are stored under within the mount
+    pub prefix: String,
+    /// The Vault server address, overriding `VAULT_ADDR`
+    pub address: Option<String>,
+    /// The Vault Enterprise namespace, overriding `VAULT_NAMESPACE`
+    pub namespace: Option<String>,
+    /// How to obtain a Vault token
+    pub auth: VaultAuth,
+}
+
+impl TryFrom<&Url> for VaultKvConfig {
+    type Error = SecretSpecError;
+
+    fn try_from(url: &Url) -> std::result::Result<Self, Self::Error> {
+        if url.scheme() != "vault" {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Invalid scheme '{}' for vault provider",
+                url.scheme()
+            )));
+        }
+
+        let mount = url.host_str().unwrap_or("").to_string();
+        if mount.is_empty() {
+            return Err(SecretSpecError::ProviderOperationFailed(
+                "Vault provider needs the mount of a KV v2 secrets engine, e.g. vault://secret"
+                    .to_string(),
+            ));
+        }
+        let prefix = match url.path().trim_matches('/') {
+            "" => DEFAULT_PREFIX.to_string(),
+            prefix => prefix.to_string(),
+        };
+
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        if let Some(name) = params.keys().find(|name| !PARAMS.contains(&name.as_str())) {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Unknown vault provider option '{}'",
+                name
+            )));
+        }
+
+        Ok(Self {
+            mount,
+            prefix,
+            address: params.get("address").cloned(),
+            namespace: params.get("namespace").cloned(),
+            auth: VaultAuth::from_params(&params)?,
+        })
+    }
+}
+
+/// Response of `vault kv get`, and of reading `<mount>/data/<path>` over HTTP.
+#[derive(Debug, Deserialize)]
+pub(super) struct KvResponse {
+    data: KvData,
+}
+
+impl KvResponse {
+    /// Returns the `value` field of the version with its version number and
+    /// creation time, or `None` if the version was deleted.
+    pub(super) fn into_secret(self) -> Option<SecretValue> {
+        let metadata = &self.data.metadata;
+        let value = self
+            .data
+            .data
+            .as_ref()
+            .and_then(|data| data.get("value"))
+            .and_then(|value| match value {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Null => None,
+                other => Some(other.to_string()),
+            });
+        value.map(|value| SecretValue {
+            value: SecretString::new(value.into()),
+            metadata: metadata.to_metadata(metadata.version.unwrap_or_default()),
+        })
+    }
+}
+
+/// The stored fields of a version and what Vault records about it.
+#[derive(Debug, Deserialize)]
+struct KvData {
+    /// `None` if the version was deleted
+    data: Option<HashMap<String, serde_json::Value>>,
+    metadata: VersionMetadata,
+}
+
+/// What Vault records about one version of a secret.
+#[derive(Debug, Deserialize)]
+struct VersionMetadata {
+    #[serde(default)]
+    created_time: String,
+    /// Empty unless the version was deleted
+    #[serde(default)]
+    deletion_time: String,
+    #[serde(default)]
+    destroyed: bool,
+    /// Only reported by `vault kv get`; `vault kv metadata get` keys the
+    /// versions by number instead
+    #[serde(default)]
+    version: Option<u64>,
+}
+
+impl VersionMetadata {
+    fn to_metadata(&self, version: u64) -> SecretMetadata {
+        SecretMetadata {
+            revised: (!self.created_time.is_empty()).then(|| self.created_time.clone()),
+            version: Some(version.to_string()),
+            deleted: !self.deletion_time.is_empty() || self.destroyed,
+            ..SecretMetadata::default()
+        }
+    }
+}
+
+/// Response of `vault kv metadata get`.
+#[derive(Debug, Deserialize)]
+struct MetadataResponse {
+    data: SecretVersions,
+}
+
+/// The versions Vault keeps of a secret.
+#[derive(Debug, Deserialize)]
+struct SecretVersions {
+    current_version: u64,
+    /// Keyed by version number
+    #[serde(default)]
+    versions: HashMap<String, VersionMetadata>,
+}
+
+/// Provider storing secrets in a HashiCorp Vault KV version 2 secrets engine.
+///
+/// Each secret is a KV entry with a single `value` field. Vault keeps earlier
+/// versions of every entry, so reads report the version and when it was
+/// written, [`compare_and_set`](Provider::compare_and_set) uses Vault's
+/// check-and-set, deleted secrets can be restored, and
+/// [`history`](Provider::history) lists the versions.
+///
+/// # Requirements
+///
+/// The Vault CLI (`vault`) must be installed and logged in, or `VAULT_TOKEN`
+/// must be set.
+pub struct VaultKvProvider {
+    config: VaultKvConfig,
+    /// Runs the `vault` CLI against the configured server.
+    client: VaultClient,
+}
+
+crate::register_provider! {
+    struct: VaultKvProvider,
+    config: VaultKvConfig,
+    name: "vault",
+    description: "HashiCorp Vault KV v2 secrets engine",
+    schemes: ["vault"],
+    examples: ["vault://secret", "vault://kv/teams/payments"],
+    params: |_| PARAMS,
+}
+
+impl VaultKvProvider {
+    /// Creates a new VaultKvProvider with the given configuration.
+    pub fn new(config: VaultKvConfig) -> Self {
+        Self::with_runner(config, Arc::new(SystemRunner))
+            .with_token_cache(session::default_cache(TOKEN_CACHE_SERVICE))
+    }
+
+    /// Creates a VaultKvProvider that runs the `vault` CLI through `runner`.
+    ///
+    /// Tokens are only kept for the lifetime of the provider.
+    pub(crate) fn with_runner(config: VaultKvConfig, runner: Arc<dyn CommandRunner>) -> Self {
+        let client = VaultClient::new(
+            config.address.clone(),
+            config.namespace.clone(),
+            config.auth.clone(),
+            runner,
+        );
+        Self { config, client }
+    }
+
+    /// Replaces the cache that tokens are stored in between commands.
+    pub(crate) fn with_token_cache(mut self, token_cache: Arc<dyn TokenCache>) -> Self {
+        self.client = self.client.with_token_cache(token_cache);
+        self
+    }
+
+    /// Returns the `-mount` option of `vault kv` commands.
+    fn mount(&self) -> String {
+        format!("-mount={}", self.config.mount)
+    }
+
+    /// Returns the path of a project and profile's folder within the mount.
+    fn folder(&self, project: &str, profile: &str) -> String {
+        format!("{}/{}/{}", self.config.prefix, project, profile)
+    }
+
+    /// Returns the path a secret is stored at within the mount.
+    fn path(&self, project: &str, key: &str, profile: &str) -> String {
+        format!("{}/{}", self.folder(project, profile), key)
+    }
+
+    /// Reads the versions Vault keeps of a secret, or `None` if it has none.
+    fn versions(&self, path: &str) -> Result<Option<SecretVersions>> {
+        let output = match self.client.execute(&[
+            "kv",
+            "metadata",
+            "get",
+            "-format=json",
+            &self.mount(),
+            path,
+        ]) {
+            Ok(output) => output,
+            Err(SecretSpecError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let response: MetadataResponse = serde_json::from_str(&output)?;
+        Ok(Some(response.data))
+    }
+
+    /// Writes a new version, passing the value on stdin.
+    fn put(&self, path: &str, value: &SecretString, cas: Option<&str>) -> Result<()> {
+        let mount = self.mount();
+        let cas = cas.map(|version| format!("-cas={}", version));
+        let mut args = vec!["kv", "put", mount.as_str()];
+        args.extend(cas.as_deref());
+        args.extend([path, "value=-"]);
+        self.client
+            .execute_with_input(&args, value.expose_secret().as_bytes())?;
+        Ok(())
+    }
+}
+
+impl Provider for VaultKvProvider {
+    fn name(&self) -> &'static str {
+        Self::PROVIDER_NAME
+    }
+
+    fn get(&self, project: &str, key: &str, profile: &str) -> Result<Option<SecretString>> {
+        Ok(self
+            .get_with_metadata(project, key, profile)?
+            .map(|secret| secret.value))
+    }
+
+    /// Reads the latest version with `vault kv get`, reporting its version
+    /// number and creation time.
+    fn get_with_metadata(
+        &self,
+        project: &str,
+        key: &str,
+        profile: &str,
+    ) -> Result<Option<SecretValue>> {
+        let path = self.path(project, key, profile);
+        let output = match self
+            .client
+            .execute(&["kv", "get", "-format=json", &self.mount(), &path])
+        {
+            Ok(output) => output,
+            Err(SecretSpecError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let response: KvResponse = serde_json::from_str(&output)?;
+        Ok(response.into_secret())
+    }
+
+    fn set(&self, project: &str, key: &str, value: &SecretString, profile: &str) -> Result<()> {
+        self.put(&self.path(project, key, profile), value, None)
+    }
+
+    /// Writes with Vault's check-and-set, so the write fails unless the
+    /// secret is still at version `expected`.
+    fn compare_and_set(
+        &self,
+        project: &str,
+        key: &str,
+        value: &SecretString,
+        profile: &str,
+        expected: &str,
+    ) -> Result<()> {
+        if expected.parse::<u64>().is_err() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Vault revisions are version numbers, not '{}'",
+                expected
+            )));
+        }
+        self.put(&self.path(project, key, profile), value, Some(expected))
+            .map_err(|e| match e {
+                SecretSpecError::Conflict(_) => SecretSpecError::Conflict(format!(
+                    "'{}' was changed since revision {}",
+                    key, expected
+                )),
+                e => e,
+            })
+    }
+
+    /// Deletes the latest version with `vault kv delete`; earlier versions
+    /// stay, and [`restore`](Provider::restore) undeletes it.
+    fn delete(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let path = self.path(project, key, profile);
+        self.client
+            .execute(&["kv", "delete", &self.mount(), &path])?;
+        Ok(())
+    }
+
+    fn allows_delete(&self) -> bool {
+        true
+    }
+
+    /// Removes every version with `vault kv metadata delete`.
+    fn purge(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let path = self.path(project, key, profile);
+        self.client
+            .execute(&["kv", "metadata", "delete", &self.mount(), &path])?;
+        Ok(())
+    }
+
+    /// Undeletes the latest version with `vault kv undelete`.
+    fn restore(&self, project: &str, key: &str, profile: &str) -> Result<()> {
+        let path = self.path(project, key, profile);
+        let deleted = self.versions(&path)?.and_then(|versions| {
+            let current = versions.current_version;
+            versions
+                .versions
+                .get(&current.to_string())
+                .filter(|version| !version.deletion_time.is_empty() && !version.destroyed)
+                .map(|_| current)
+        });
+        let Some(version) = deleted else {
+            return Err(SecretSpecError::NotFound(format!(
+                "No deleted version of '{}' in Vault",
+                key
+            )));
+        };
+
+        let versions = format!("-versions={}", version);
+        self.client
+            .execute(&["kv", "undelete", &versions, &self.mount(), &path])?;
+        Ok(())
+    }
+
+    fn allows_restore(&self) -> bool {
+        true
+    }
+
+    /// Lists the versions with `vault kv metadata get`.
+    fn history(&self, project: &str, key: &str, profile: &str) -> Result<Vec<SecretMetadata>> {
+        let Some(versions) = self.versions(&self.path(project, key, profile))? else {
+            return Ok(Vec::new());
+        };
+        let mut history: Vec<(u64, SecretMetadata)> = versions
+            .versions
+            .iter()
+            .filter_map(|(number, version)| {
+                let number = number.parse().ok()?;
+                Some((number, version.to_metadata(number)))
+            })
+            .collect();
+        history.sort_by(|(a, _), (b, _)| b.cmp(a));
+        Ok(history.into_iter().map(|(_, metadata)| metadata).collect())
+    }
+
+    /// Lists the secrets in the project and profile's folder with
+    /// `vault kv list`.
+    fn list_keys(&self, project: &str, profile: &str) -> Result<Vec<String>> {
+        let folder = self.folder(project, profile);
+        let output =
+            match self
+                .client
+                .execute(&["kv", "list", "-format=json", &self.mount(), &folder])
+            {
+                Ok(output) => output,
+                Err(SecretSpecError::NotFound(_)) => return Ok(Vec::new()),
+                Err(e) => return Err(e),
+            };
+        let keys: Vec<String> = serde_json::from_str(&output)?;
+        Ok(keys.into_iter().filter(|key| !key.ends_with('/')).collect())
+    }
+
+    fn allows_list(&self) -> bool {
+        true
+    }
+
+    fn doctor(&self) -> Vec<Check> {
+        self.client.doctor()
+    }
+}
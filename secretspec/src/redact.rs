@@ -0,0 +1,192 @@
+//! Redaction of secret values in command output
+//!
+//! With `run --redact-output`, the command's stdout and stderr are piped
+//! through a [`Scrubber`] that replaces every occurrence of a resolved value
+//! with `[REDACTED:KEY]`, so an accidental print doesn't end up in CI logs.
+//!
+//! Output is passed on as it arrives. Only a trailing part that could be the
+//! start of a value is held back until the next read shows whether it is.
+
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// Values to redact, with the names of the secrets they belong to.
+pub(crate) struct Redactions {
+    /// Values sorted longest first, so the longest match wins
+    values: Vec<(Zeroizing<Vec<u8>>, String)>,
+}
+
+impl Redactions {
+    /// Collects the non-empty values of resolved secrets.
+    pub(crate) fn new(secrets: &HashMap<String, SecretString>) -> Self {
+        let mut values: Vec<_> = secrets
+            .iter()
+            .filter(|(_, value)| !value.expose_secret().is_empty())
+            .map(|(name, value)| {
+                (
+                    Zeroizing::new(value.expose_secret().as_bytes().to_vec()),
+                    name.clone(),
+                )
+            })
+            .collect();
+        values.sort_by(|(a, a_name), (b, b_name)| {
+            b.len().cmp(&a.len()).then_with(|| a_name.cmp(b_name))
+        });
+        Self { values }
+    }
+}
+
+/// What the output at some position is.
+enum Match<'a> {
+    /// A whole value, replaced by a marker naming its secret
+    Value(usize, &'a str),
+    /// Possibly the start of a value, cut off by the end of the buffer
+    Partial,
+    /// Not part of any value
+    None,
+}
+
+/// Streaming replacement of secret values.
+pub(crate) struct Scrubber {
+    redactions: Arc<Redactions>,
+    /// Output held back because it may be the start of a value
+    pending: Zeroizing<Vec<u8>>,
+}
+
+impl Scrubber {
+    pub(crate) fn new(redactions: Arc<Redactions>) -> Self {
+        Self {
+            redactions,
+            pending: Zeroizing::new(Vec::new()),
+        }
+    }
+
+    /// Returns what the output starting at `rest` is.
+    ///
+    /// A partial match only counts while more output may follow. It wins over
+    /// a whole match of a shorter value, which the rest may extend.
+    fn match_at<'a>(&'a self, rest: &[u8], more: bool) -> Match<'a> {
+        for (value, name) in &self.redactions.values {
+            if rest.starts_with(value) {
+                return Match::Value(value.len(), name);
+            }
+            if more && value.starts_with(rest) {
+                return Match::Partial;
+            }
+        }
+        Match::None
+    }
+
+    /// Scrubs the pending output, returning what can be written.
+    fn drain(&mut self, more: bool) -> Zeroizing<Vec<u8>> {
+        let mut output = Zeroizing::new(Vec::with_capacity(self.pending.len()));
+        let mut position = 0;
+        while position < self.pending.len() {
+            match self.match_at(&self.pending[position..], more) {
+                Match::Value(len, name) => {
+                    output.extend_from_slice(format!("[REDACTED:{}]", name).as_bytes());
+                    position += len;
+                }
+                Match::Partial => break,
+                Match::None => {
+                    output.push(self.pending[position]);
+                    position += 1;
+                }
+            }
+        }
+        let rest = Zeroizing::new(self.pending[position..].to_vec());
+        self.pending = rest;
+        output
+    }
+
+    /// Adds output, returning the part of it that can be written.
+    pub(crate) fn push(&mut self, data: &[u8]) -> Zeroizing<Vec<u8>> {
+        self.pending.extend_from_slice(data);
+        self.drain(true)
+    }
+
+    /// Returns the output held back once no more follows.
+    pub(crate) fn finish(&mut self) -> Zeroizing<Vec<u8>> {
+        self.drain(false)
+    }
+}
+
+/// Copies `input` to `output`, replacing secret values, until `input` ends.
+///
+/// # Errors
+///
+/// Returns an error if reading or writing fails
+pub(crate) fn copy(
+    redactions: Arc<Redactions>,
+    mut input: impl Read,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut scrubber = Scrubber::new(redactions);
+    let mut buffer = Zeroizing::new([0u8; 8192]);
+    loop {
+        let read = match input.read(&mut buffer[..]) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.write_all(&scrubber.push(&buffer[..read]))?;
+        output.flush()?;
+    }
+    output.write_all(&scrubber.finish())?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactions() -> Arc<Redactions> {
+        Arc::new(Redactions::new(&HashMap::from([
+            ("API_KEY".to_string(), SecretString::from("sk-123")),
+            ("TOKEN".to_string(), SecretString::from("sk-123456")),
+            ("EMPTY".to_string(), SecretString::from("")),
+        ])))
+    }
+
+    fn scrub(chunks: &[&str]) -> String {
+        let mut scrubber = Scrubber::new(redactions());
+        let mut output = Vec::new();
+        for chunk in chunks {
+            output.extend_from_slice(&scrubber.push(chunk.as_bytes()));
+        }
+        output.extend_from_slice(&scrubber.finish());
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_scrub() {
+        assert_eq!(scrub(&["key=sk-123\n"]), "key=[REDACTED:API_KEY]\n");
+        assert_eq!(scrub(&["sk-123456 sk-12"]), "[REDACTED:TOKEN] sk-12");
+        assert_eq!(scrub(&["no secrets here"]), "no secrets here");
+        // Values split across reads are still found
+        assert_eq!(
+            scrub(&["key=s", "k-1", "23", "456!"]),
+            "key=[REDACTED:TOKEN]!"
+        );
+        assert_eq!(scrub(&["sk-123", "4"]), "[REDACTED:API_KEY]4");
+    }
+
+    #[test]
+    fn test_push_holds_back_only_partial_matches() {
+        let mut scrubber = Scrubber::new(redactions());
+        assert_eq!(scrubber.push(b"abc sk-").as_slice(), b"abc ");
+        assert_eq!(scrubber.push(b"x").as_slice(), b"sk-x");
+        assert!(scrubber.finish().is_empty());
+    }
+
+    #[test]
+    fn test_copy() {
+        let mut output = Vec::new();
+        copy(redactions(), &b"token: sk-123456\n"[..], &mut output).unwrap();
+        assert_eq!(output, b"token: [REDACTED:TOKEN]\n");
+    }
+}
@@ -0,0 +1,82 @@
+//! References in default values
+//!
+//! A secret's default can point at another secret of the spec or at a key
+//! stored with another provider, so shared values are kept in one place:
+//!
+//! ```toml
+//! [profiles.default]
+//! STRIPE_KEY = { description = "Stripe key", default = "ref:onepassword://Shared/Stripe#publishable" }
+//! CHECKOUT_KEY = { description = "Checkout key", default = "ref:STRIPE_KEY" }
+//! ```
+//!
+//! `onepassword://Shared/Stripe` addresses the `Stripe` item of the `Shared`
+//! vault, whose `publishable` field is read.
+//!
+//! Secret references are resolved recursively: if the referenced secret
+//! isn't set either, its own default is used.
+
+use crate::{Result, SecretSpecError};
+
+/// Prefix marking a default value as a reference.
+pub(crate) const PREFIX: &str = "ref:";
+
+/// What a default value refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Reference<'a> {
+    /// Another secret of the spec, `ref:NAME`
+    Secret(&'a str),
+    /// A key read from another provider, `ref:<provider-uri>#<KEY>`
+    Provider {
+        /// URI of the provider
+        uri: &'a str,
+        /// Key to read from it
+        key: &'a str,
+    },
+}
+
+impl<'a> Reference<'a> {
+    /// Parses a default value.
+    ///
+    /// Returns `None` for plain values that don't start with `ref:`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a provider reference has no `#<KEY>`
+    pub fn parse(value: &'a str) -> Result<Option<Self>> {
+        let Some(target) = value.strip_prefix(PREFIX) else {
+            return Ok(None);
+        };
+        if !target.contains("://") {
+            return Ok(Some(Self::Secret(target)));
+        }
+        match target.rsplit_once('#') {
+            Some((uri, key)) if !key.is_empty() => Ok(Some(Self::Provider { uri, key })),
+            _ => Err(SecretSpecError::InvalidInput(format!(
+                "Reference '{}' has no key. Use ref:<provider-uri>#<KEY>",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Reference::parse("plain value").unwrap(), None);
+        assert_eq!(
+            Reference::parse("ref:DATABASE_URL").unwrap(),
+            Some(Reference::Secret("DATABASE_URL"))
+        );
+        assert_eq!(
+            Reference::parse("ref:onepassword://Shared/Stripe#publishable").unwrap(),
+            Some(Reference::Provider {
+                uri: "onepassword://Shared/Stripe",
+                key: "publishable"
+            })
+        );
+        assert!(Reference::parse("ref:keyring://").is_err());
+    }
+}
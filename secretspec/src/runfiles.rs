@@ -0,0 +1,101 @@
+//! Credential files for `secretspec run`
+//!
+//! Some tools only read credentials from files. The files rendered for them
+//! are written to a directory only the user can enter, under
+//! `$XDG_RUNTIME_DIR` where available so they stay in memory on systems
+//! that mount it as a tmpfs. The command finds them through variables
+//! pointing at their paths, and the directory is removed once it exits.
+//! A secretspec killed before then leaves the directory behind.
+
+use crate::Result;
+use crate::systemd;
+use std::env;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// Returns the directory private files are created in: `$XDG_RUNTIME_DIR`
+/// where available, and otherwise the temporary directory.
+pub(crate) fn private_dir() -> PathBuf {
+    directories::BaseDirs::new()
+        .and_then(|dirs| dirs.runtime_dir().map(PathBuf::from))
+        .unwrap_or_else(env::temp_dir)
+}
+
+/// A private directory of files, removed when dropped.
+pub(crate) struct RunFiles {
+    dir: TempDir,
+    vars: Vec<(String, String)>,
+}
+
+impl RunFiles {
+    /// Creates an empty directory for the files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory can't be created
+    pub(crate) fn new() -> Result<Self> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("secretspec-run-");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(std::fs::Permissions::from_mode(0o700));
+        }
+        let dir = builder.tempdir_in(private_dir())?;
+        Ok(Self {
+            dir,
+            vars: Vec::new(),
+        })
+    }
+
+    /// Writes a file readable only by its owner and points `variables` at it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written
+    pub(crate) fn add(&mut self, file_name: &str, variables: &[&str], content: &str) -> Result<()> {
+        let path = self.dir.path().join(file_name);
+        systemd::write_env_file(&path, content)?;
+        for variable in variables {
+            self.set(variable, &path.display().to_string());
+        }
+        Ok(())
+    }
+
+    /// Sets a variable that goes with the files, like the profile to select
+    /// in one of them.
+    pub(crate) fn set(&mut self, variable: &str, value: &str) {
+        self.vars.push((variable.to_string(), value.to_string()));
+    }
+
+    /// Returns the variables pointing at the files, and those set with them.
+    pub(crate) fn vars(&self) -> &[(String, String)] {
+        &self.vars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_files() {
+        let mut files = RunFiles::new().unwrap();
+        files.add("config", &["APP_CONFIG"], "secret").unwrap();
+        let (variable, path) = files.vars()[0].clone();
+        assert_eq!(variable, "APP_CONFIG");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "secret");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(std::path::Path::new(&path)), 0o600);
+            assert_eq!(mode(files.dir.path()), 0o700);
+        }
+
+        let dir = files.dir.path().to_path_buf();
+        drop(files);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,579 @@
+//! JSON Schema for secretspec.toml
+//!
+//! `secretspec schema` prints this schema so editors with a TOML language
+//! server (Taplo, Even Better TOML) can complete and validate specs:
+//!
+//! ```toml
+//! #:schema ./secretspec.schema.json
+//! [project]
+//! name = "my-app"
+//! schema = 3
+//! ```
+//!
+//! The schema describes the serde types in [`crate::config`] for
+//! [`CURRENT_SCHEMA`], whose number is part of its `$id`.
+
+use crate::config::CURRENT_SCHEMA;
+use serde_json::{Value, json};
+
+/// Pattern of secret names, including patterns like `FEATURE_FLAG_*`.
+const SECRET_NAME: &str = "^[A-Za-z_][A-Za-z0-9_]*\\*?$";
+
+/// Pattern of environment variable names in `[run]`, including patterns
+/// like `LC_*`.
+const ENV_NAME: &str = "^[^=*]+\\*?$";
+
+/// Pattern of notification targets, a URL or `env:NAME`.
+const TARGET: &str = "^(https?://|env:.)";
+
+/// Returns the URL identifying the schema of a spec schema version.
+pub(crate) fn schema_id(version: u32) -> String {
+    format!("https://secretspec.dev/schema/v{}.json", version)
+}
+
+/// Returns the schema of a value that is one string or a list of strings.
+fn one_or_many(description: &str) -> Value {
+    json!({
+        "description": description,
+        "oneOf": [
+            { "type": "string" },
+            { "type": "array", "items": { "type": "string" } }
+        ]
+    })
+}
+
+/// Returns the JSON Schema of secretspec.toml for [`CURRENT_SCHEMA`].
+pub(crate) fn json_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": schema_id(CURRENT_SCHEMA),
+        "title": "secretspec.toml",
+        "description": "Declares the secrets a project needs, per profile",
+        "type": "object",
+        "required": ["project", "profiles"],
+        "additionalProperties": false,
+        "properties": {
+            "project": { "$ref": "#/$defs/project" },
+            "profiles": {
+                "description": "Profiles by name, e.g. default, development or production",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/$defs/profile" }
+            },
+            "recipients": {
+                "description": "Public keys that enc+ and gitvault providers encrypt values to, keyed by profile name and then by team member",
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" }
+                }
+            },
+            "assume_role": {
+                "description": "AWS roles assumed to derive short-lived credentials, keyed by profile name",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/$defs/assume_role" }
+            },
+            "run": { "$ref": "#/$defs/run" },
+            "notify": { "$ref": "#/$defs/notify" },
+            "keys": { "$ref": "#/$defs/keys" }
+        },
+        "$defs": {
+            "project": {
+                "description": "Project metadata",
+                "type": "object",
+                "required": ["schema"],
+                "additionalProperties": false,
+                "properties": {
+                    "name": {
+                        "description": "The name of the project, used to namespace its secrets",
+                        "type": "string"
+                    },
+                    "schema": {
+                        "description": "Spec schema version",
+                        "const": CURRENT_SCHEMA
+                    },
+                    "extends": {
+                        "description": "Relative paths to other projects whose secrets are inherited",
+                        "type": "array",
+                        "items": { "type": "string" }
+                    }
+                }
+            },
+            "profile": {
+                "description": "Secrets by name",
+                "type": "object",
+                "propertyNames": { "pattern": SECRET_NAME },
+                "properties": {
+                    "provider": {
+                        "description": "Provider the profile's secrets are stored in, unless configured for the project in the user configuration",
+                        "type": "string",
+                        "minLength": 1
+                    }
+                },
+                "additionalProperties": { "$ref": "#/$defs/secret" }
+            },
+            "secret": {
+                "type": "object",
+                "required": ["description"],
+                "additionalProperties": false,
+                "properties": {
+                    "description": {
+                        "description": "What the secret is used for",
+                        "type": "string",
+                        "minLength": 1
+                    },
+                    "required": {
+                        "description": "Whether the secret must be set",
+                        "type": "boolean",
+                        "default": true
+                    },
+                    "default": {
+                        "description": "Value used when the secret isn't set; ref:NAME or ref:<provider-uri>#<KEY> reads it from elsewhere",
+                        "type": "string"
+                    },
+                    "required_if": {
+                        "description": "Only require the secret under some conditions",
+                        "type": "object",
+                        "additionalProperties": false,
+                        "required": ["profile"],
+                        "properties": {
+                            "profile": one_or_many("Profiles in which the secret is required")
+                        }
+                    },
+                    "required_unless_set": one_or_many(
+                        "Secrets that make this one optional once all of them are set"
+                    ),
+                    "group": {
+                        "description": "Group selected with --group",
+                        "type": "string",
+                        "minLength": 1
+                    },
+                    "platforms": {
+                        "description": "Platforms the secret is needed on, e.g. macos or macos-aarch64",
+                        "type": "array",
+                        "items": { "type": "string" }
+                    },
+                    "transform": {
+                        "description": "Transforms applied in order to the value: trim, base64d or jsonpath:<path>",
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "pattern": "^(trim|base64d|jsonpath:\\$.*)$"
+                        }
+                    },
+                    "item": {
+                        "description": "Provider item whose fields are read as several secrets in one fetch",
+                        "type": "string",
+                        "minLength": 1
+                    },
+                    "fields": {
+                        "description": "Secrets read from the fields of item, mapping each secret name to its field",
+                        "type": "object",
+                        "propertyNames": { "pattern": "^[A-Za-z_][A-Za-z0-9_]*$" },
+                        "additionalProperties": { "type": "string" }
+                    },
+                    "ttl": {
+                        "description": "How long a written value lives before secretspec gc deletes it, e.g. 24h",
+                        "type": "string",
+                        "pattern": "^[0-9]+[smhdw]$"
+                    },
+                    "generator": {
+                        "description": "Command that generates a new value for secretspec rotate",
+                        "type": "object",
+                        "additionalProperties": false,
+                        "required": ["command"],
+                        "properties": {
+                            "command": {
+                                "description": "Shell command printing the new value on stdout",
+                                "type": "string",
+                                "minLength": 1
+                            },
+                            "timeout": {
+                                "description": "How long the command may run, e.g. 30s",
+                                "type": "string",
+                                "pattern": "^[0-9]+[smhdw]$"
+                            },
+                            "min_length": {
+                                "description": "Fewest characters the value may have",
+                                "type": "integer",
+                                "minimum": 0
+                            },
+                            "multiline": {
+                                "description": "Whether the value may span several lines",
+                                "type": "boolean"
+                            }
+                        }
+                    },
+                    "hardware_confirm": {
+                        "description": "Whether resolving the secret requires a touch on a FIDO2 security key",
+                        "type": "boolean"
+                    },
+                    "bitwarden_item_id": {
+                        "description": "ID of the Bitwarden item holding the secret, read and written by ID instead of found by name",
+                        "type": "string",
+                        "pattern": "^[0-9a-fA-F-]+$"
+                    },
+                    "type": {
+                        "description": "What kind of value the secret holds; passwords are checked against known breaches by analyze --hibp",
+                        "enum": ["password"]
+                    }
+                }
+            },
+            "run": {
+                "description": "How `secretspec run` launches the command",
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "clean_env": {
+                        "description": "Only pass on the variables in allow, as with --clean-env",
+                        "type": "boolean",
+                        "default": false
+                    },
+                    "allow": {
+                        "description": "Variables passed on with a clean environment, by name or a pattern like LC_*",
+                        "type": "array",
+                        "items": { "type": "string", "pattern": ENV_NAME }
+                    },
+                    "deny": {
+                        "description": "Variables never passed on, by name or a pattern like AWS_*",
+                        "type": "array",
+                        "items": { "type": "string", "pattern": ENV_NAME }
+                    },
+                    "harden": {
+                        "description": "Disable core dumps and ptrace access before resolving secrets, as with --harden",
+                        "type": "boolean",
+                        "default": false
+                    },
+                    "kubeconfig": {
+                        "description": "Secrets assembled into a kubeconfig the command finds through KUBECONFIG",
+                        "type": "object",
+                        "required": ["server", "token"],
+                        "additionalProperties": false,
+                        "properties": {
+                            "server": {
+                                "description": "Secret holding the URL of the cluster's API server",
+                                "type": "string"
+                            },
+                            "certificate_authority": {
+                                "description": "Secret holding the cluster's CA certificate as PEM",
+                                "type": "string"
+                            },
+                            "token": {
+                                "description": "Secret holding the bearer token of the cluster user",
+                                "type": "string"
+                            },
+                            "namespace": {
+                                "description": "Namespace the context selects",
+                                "type": "string"
+                            }
+                        }
+                    },
+                    "aws": {
+                        "description": "Secrets written to a shared credentials file the command finds through AWS_SHARED_CREDENTIALS_FILE",
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "profile": {
+                                "description": "Profile the credentials are written under and AWS_PROFILE selects",
+                                "type": "string",
+                                "default": "default"
+                            },
+                            "access_key_id": {
+                                "description": "Secret holding the access key ID",
+                                "type": "string",
+                                "default": "AWS_ACCESS_KEY_ID"
+                            },
+                            "secret_access_key": {
+                                "description": "Secret holding the secret access key",
+                                "type": "string",
+                                "default": "AWS_SECRET_ACCESS_KEY"
+                            },
+                            "session_token": {
+                                "description": "Secret holding the session token, left out when it has no value",
+                                "type": "string",
+                                "default": "AWS_SESSION_TOKEN"
+                            }
+                        }
+                    },
+                    "gcloud": {
+                        "description": "Secret written as application default credentials the command finds through GOOGLE_APPLICATION_CREDENTIALS",
+                        "type": "object",
+                        "required": ["credentials"],
+                        "additionalProperties": false,
+                        "properties": {
+                            "credentials": {
+                                "description": "Secret holding a service account key or authorized user credentials as JSON",
+                                "type": "string"
+                            }
+                        }
+                    }
+                }
+            },
+            "notify": {
+                "description": "Where changes to secrets are announced; values are never sent",
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "profiles": {
+                        "description": "Profiles whose changes are announced; all if omitted",
+                        "type": "array",
+                        "items": { "type": "string" }
+                    },
+                    "slack": {
+                        "description": "Slack incoming webhook URLs, or env:NAME to read one from the environment",
+                        "type": "array",
+                        "items": { "type": "string", "pattern": TARGET }
+                    },
+                    "webhook": {
+                        "description": "URLs that receive a JSON description of each change, or env:NAME",
+                        "type": "array",
+                        "items": { "type": "string", "pattern": TARGET }
+                    },
+                    "email": {
+                        "description": "Email addresses notified through smtp",
+                        "type": "array",
+                        "items": { "type": "string", "pattern": "@" }
+                    },
+                    "smtp": {
+                        "description": "SMTP server URL like smtps://smtp.example.com:465, or env:NAME",
+                        "type": "string",
+                        "pattern": "^(smtps?://|env:)"
+                    },
+                    "from": {
+                        "description": "Sender address of emails",
+                        "type": "string"
+                    }
+                }
+            },
+            "keys": {
+                "description": "Exceptions to the convention that secret names are uppercase",
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "exceptions": {
+                        "description": "Secret names allowed to be lowercase or mixed case",
+                        "type": "array",
+                        "items": { "type": "string", "pattern": "^[A-Za-z_][A-Za-z0-9_]*$" }
+                    }
+                }
+            },
+            "assume_role": {
+                "description": "An AWS role assumed with sts:AssumeRole",
+                "type": "object",
+                "required": ["role_arn"],
+                "additionalProperties": false,
+                "properties": {
+                    "role_arn": {
+                        "description": "ARN of the role to assume",
+                        "type": "string",
+                        "pattern": "^arn:"
+                    },
+                    "session_name": {
+                        "description": "Name of the role session, defaulting to secretspec-<project>",
+                        "type": "string"
+                    },
+                    "duration_seconds": {
+                        "description": "Lifetime of the credentials in seconds",
+                        "type": "integer",
+                        "minimum": 900,
+                        "maximum": 43200
+                    },
+                    "source_profile": {
+                        "description": "AWS CLI profile holding the credentials used to assume the role",
+                        "type": "string"
+                    },
+                    "mfa_serial": {
+                        "description": "ARN or serial number of the MFA device the role requires",
+                        "type": "string"
+                    },
+                    "mfa_secret": {
+                        "description": "Name of the secret holding the MFA device's TOTP seed",
+                        "type": "string"
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// A key of a spec that the schema doesn't declare.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UnknownKey {
+    /// Dotted path of the table holding the key, like `profiles.default.API_KEY`
+    pub table: String,
+    /// The key
+    pub key: String,
+    /// The keys the table declares
+    pub known: Vec<String>,
+}
+
+/// Returns the first key of a spec that [`json_schema`] doesn't declare.
+///
+/// Tables whose keys are names, like `[profiles]`, accept any key.
+pub(crate) fn unknown_key(spec: &Value) -> Option<UnknownKey> {
+    let schema = json_schema();
+    find_unknown_key(spec, &schema, &schema, "")
+}
+
+/// Walks `value` along `schema` looking for an undeclared key.
+fn find_unknown_key(value: &Value, schema: &Value, root: &Value, path: &str) -> Option<UnknownKey> {
+    let schema = match schema["$ref"].as_str() {
+        Some(reference) => &root["$defs"][reference.trim_start_matches("#/$defs/")],
+        None => schema,
+    };
+    let Value::Object(fields) = value else {
+        return None;
+    };
+    for (key, field) in fields {
+        let field_schema = match &schema["properties"][key] {
+            Value::Null => &schema["additionalProperties"],
+            declared => declared,
+        };
+        if field_schema == &Value::Bool(false) {
+            let known = schema["properties"]
+                .as_object()
+                .map(|properties| properties.keys().cloned().collect())
+                .unwrap_or_default();
+            return Some(UnknownKey {
+                table: path.to_string(),
+                key: key.clone(),
+                known,
+            });
+        }
+        let path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        if let Some(unknown) = find_unknown_key(field, field_schema, root, &path) {
+            return Some(unknown);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        AssumeRole, AwsCredentialsConfig, Config, GcloudCredentialsConfig, Generator, KeysConfig,
+        KubeconfigConfig, NotifyConfig, Profile, Project, RequiredIf, RunConfig, Secret,
+        SecretKind,
+    };
+    use std::collections::{BTreeMap, HashMap};
+
+    /// Asserts that every key of `value` is declared by `schema`.
+    fn assert_declared(value: &Value, schema: &Value, root: &Value, path: &str) {
+        let schema = match schema["$ref"].as_str() {
+            Some(reference) => {
+                let name = reference.trim_start_matches("#/$defs/");
+                &root["$defs"][name]
+            }
+            None => schema,
+        };
+        let Value::Object(fields) = value else {
+            return;
+        };
+        for (key, field) in fields {
+            let path = format!("{}.{}", path, key);
+            let field_schema = match &schema["properties"][key] {
+                Value::Null => &schema["additionalProperties"],
+                declared => declared,
+            };
+            assert!(
+                field_schema.is_object(),
+                "{} is missing from the schema",
+                path
+            );
+            assert_declared(field, field_schema, root, &path);
+        }
+    }
+
+    #[test]
+    fn test_schema_covers_config() {
+        let secret = Secret {
+            description: Some("Stripe key".to_string()),
+            required: false,
+            default: Some("ref:STRIPE_TEST_KEY".to_string()),
+            required_if: Some(RequiredIf {
+                profile: vec!["production".to_string()],
+            }),
+            required_unless_set: vec!["STRIPE_TEST_KEY".to_string()],
+            group: Some("payments".to_string()),
+            platforms: vec!["macos".to_string()],
+            transform: vec!["trim".to_string()],
+            item: Some("Stripe".to_string()),
+            fields: BTreeMap::from([("STRIPE_KEY".to_string(), "password".to_string())]),
+            ttl: Some("24h".to_string()),
+            generator: Some(Generator {
+                command: "openssl rand -hex 32".to_string(),
+                timeout: Some("10s".to_string()),
+                min_length: Some(64),
+                multiline: true,
+            }),
+            hardware_confirm: true,
+            bitwarden_item_id: Some("4d1f3a6e-5b2c-4e8f-9a7d-1c2b3a4d5e6f".to_string()),
+            kind: Some(SecretKind::Password),
+        };
+        let config = Config {
+            project: Project {
+                name: "app".to_string(),
+                schema: CURRENT_SCHEMA,
+                extends: Some(vec!["../shared".to_string()]),
+            },
+            profiles: HashMap::from([(
+                "default".to_string(),
+                Profile {
+                    provider: Some("keyring://".to_string()),
+                    secrets: HashMap::from([("STRIPE_KEY".to_string(), secret)]),
+                },
+            )]),
+            recipients: HashMap::from([(
+                "default".to_string(),
+                BTreeMap::from([("alice".to_string(), "age1example".to_string())]),
+            )]),
+            assume_role: HashMap::from([(
+                "production".to_string(),
+                AssumeRole {
+                    role_arn: "arn:aws:iam::123456789012:role/deploy".to_string(),
+                    session_name: Some("deploy".to_string()),
+                    duration_seconds: Some(3600),
+                    source_profile: Some("base".to_string()),
+                    mfa_serial: Some("arn:aws:iam::123456789012:mfa/alice".to_string()),
+                    mfa_secret: Some("AWS_MFA_SEED".to_string()),
+                },
+            )]),
+            run: RunConfig {
+                clean_env: true,
+                allow: vec!["PATH".to_string()],
+                deny: vec!["AWS_*".to_string()],
+                harden: true,
+                kubeconfig: Some(KubeconfigConfig {
+                    server: "KUBE_SERVER".to_string(),
+                    certificate_authority: Some("KUBE_CA".to_string()),
+                    token: "KUBE_TOKEN".to_string(),
+                    namespace: Some("web".to_string()),
+                }),
+                aws: Some(AwsCredentialsConfig::default()),
+                gcloud: Some(GcloudCredentialsConfig {
+                    credentials: "GOOGLE_CREDENTIALS".to_string(),
+                }),
+            },
+            notify: NotifyConfig {
+                profiles: vec!["production".to_string()],
+                slack: vec!["env:SLACK_WEBHOOK_URL".to_string()],
+                webhook: vec!["https://ops.example.com/hook".to_string()],
+                email: vec!["secops@example.com".to_string()],
+                smtp: Some("smtps://smtp.example.com:465".to_string()),
+                from: Some("secretspec@example.com".to_string()),
+            },
+            keys: KeysConfig {
+                exceptions: vec!["npm_config_registry".to_string()],
+            },
+        };
+
+        let schema = json_schema();
+        let value = serde_json::to_value(&config).unwrap();
+        assert_declared(&value, &schema, &schema, "spec");
+        assert_eq!(schema["$id"], schema_id(CURRENT_SCHEMA));
+    }
+}
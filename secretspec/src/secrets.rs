@@ -1,17 +1,109 @@
 //! Core secrets management functionality
 
-use crate::config::{Config, GlobalConfig, Resolved};
+#[cfg(unix)]
+use crate::agent;
+use crate::analyze::{self, Finding, Location, Sample};
+use crate::cargo;
+use crate::cloudsdk;
+use crate::config::{
+    self, AssumeRole, Config, GlobalConfig, ParseError, Profile, Resolved, Secret, SpecFormat,
+};
+use crate::dbfiles;
+use crate::envaudit::{self, EnvFinding};
 use crate::error::{Result, SecretSpecError};
+use crate::explain::Trace;
+use crate::export::{self, ExportFormat};
+use crate::generator;
+use crate::graph::Graph;
+use crate::hardening;
+use crate::hardware;
+use crate::hibp;
+use crate::kubeconfig;
+use crate::lease::LeaseKeeper;
+use crate::lockfile::{LockedSecret, Lockfile};
+use crate::matrix::{Matrix, Presence, ProfileColumn};
+use crate::memory;
+use crate::nix;
+use crate::notify::{self, Action, Change};
+use crate::npmrc;
+use crate::output::{self, Mark, Progress, Rows};
+use crate::plan::{Plan, PlanAction, PlanOperation, PlannedChange};
+use crate::prompt;
+use crate::provider;
 use crate::provider::Provider as ProviderTrait;
+use crate::provider::SecretMetadata;
+use crate::provider::branch;
+use crate::provider::builder::ProviderBuilder;
+use crate::provider::cached::CachedProvider;
+use crate::provider::command::{CommandRunner, SystemRunner};
+use crate::provider::encrypted;
+use crate::provider::namespaced::NamespacedProvider;
+use crate::provider::pinned::PinnedProvider;
+use crate::provider::throttled::ThrottledProvider;
+use crate::provider::timed::TimedProvider;
+use crate::provider::traced::TracedProvider;
+use crate::redact::{self, Redactions};
+use crate::reference::Reference;
+use crate::runfiles::RunFiles;
+use crate::sts;
+use crate::suggest;
+use crate::systemd;
+use crate::telemetry;
+use crate::terraform;
+use crate::transform;
+use crate::ttl;
+use crate::usage::Usage;
 use crate::validation::{ValidatedSecrets, ValidationErrors};
+use crate::warning;
+use crate::watch;
 use colored::Colorize;
 use secrecy::{ExposeSecret, SecretString};
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::TryFrom;
 use std::env;
-use std::io::{self, IsTerminal, Write};
-use std::path::Path;
-use std::process::Command;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// Path of the lockfile written by `check`, relative to `secretspec.toml`
+const LOCKFILE_PATH: &str = "secretspec.lock";
+
+/// Profiles checked at a time by `check --all-profiles` unless
+/// `defaults.concurrency` is set
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Where the active profile was chosen, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProfileSource {
+    /// `--profile`, or a profile set with `set_profile`
+    Flag,
+    /// The `SECRETSPEC_PROFILE` environment variable
+    Env,
+    /// `projects.<project>.profile` in the user configuration
+    ProjectConfig,
+    /// `defaults.profile` in the user configuration
+    UserConfig,
+    /// The spec's `default` profile
+    SpecDefault,
+}
+
+impl ProfileSource {
+    /// Returns where the profile was set, as shown by `profile show`.
+    pub(crate) fn describe(self, project: &str) -> String {
+        match self {
+            ProfileSource::Flag => "--profile".to_string(),
+            ProfileSource::Env => "SECRETSPEC_PROFILE".to_string(),
+            ProfileSource::ProjectConfig => format!("user config projects.{}.profile", project),
+            ProfileSource::UserConfig => "user config defaults.profile".to_string(),
+            ProfileSource::SpecDefault => "spec default".to_string(),
+        }
+    }
+}
 
 /// The main entry point for the secretspec library
 ///
@@ -30,12 +122,30 @@ use std::process::Command;
 pub struct Secrets {
     /// The project-specific configuration
     config: Config,
+    /// Directory of the spec file, where `secretspec.lock` is kept
+    dir: PathBuf,
     /// Optional global user configuration
     global_config: Option<GlobalConfig>,
     /// The provider to use (if set via builder)
     provider: Option<String>,
+    /// A provider assembled by the caller, used instead of a specification
+    provider_instance: Option<Arc<dyn ProviderTrait>>,
     /// The profile to use (if set via builder)
     profile: Option<String>,
+    /// Whether `check` verifies against the lockfile instead of updating it
+    frozen: bool,
+    /// Groups operations are limited to, or empty for all secrets
+    groups: Vec<String>,
+    /// Whether `run` only passes on the variables allowed in `[run]`
+    clean_env: bool,
+    /// Whether `run` hardens the process before resolving secrets
+    harden: bool,
+    /// Whether `run` replaces secret values in the command's output
+    redact_output: bool,
+    /// Whether `get` and `set` accept secrets the spec doesn't declare
+    allow_undeclared: bool,
+    /// Credentials derived from the profile's assumed AWS role, once requested
+    assumed_role: OnceLock<HashMap<String, SecretString>>,
 }
 
 impl Secrets {
@@ -51,7 +161,6 @@ impl Secrets {
     /// # Returns
     ///
     /// A new `Secrets` instance
-    #[cfg(test)]
     pub(crate) fn new(
         config: Config,
         global_config: Option<GlobalConfig>,
@@ -60,16 +169,48 @@ impl Secrets {
     ) -> Self {
         Self {
             config,
+            dir: PathBuf::from("."),
             global_config,
             provider,
+            provider_instance: None,
             profile,
+            frozen: false,
+            groups: Vec::new(),
+            clean_env: false,
+            harden: false,
+            redact_output: false,
+            allow_undeclared: false,
+            assumed_role: OnceLock::new(),
         }
     }
 
+    /// Creates a `Secrets` from a parsed spec, without reading any files
+    ///
+    /// For programs that don't load the spec from disk, like browser-based
+    /// tools built for WebAssembly. No global configuration is read, so the
+    /// provider and profile come from the spec or are set explicitly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use secretspec::{Config, Secrets, SpecFormat};
+    ///
+    /// let config = Config::parse(
+    ///     "[project]\nname = \"app\"\nrevision = \"1.0\"\n\n[profiles.default]\nPORT = { description = \"Port\", default = \"8080\" }\n",
+    ///     SpecFormat::Toml,
+    /// )
+    /// .unwrap();
+    /// let mut spec = Secrets::from_config(config);
+    /// spec.set_provider("env");
+    /// ```
+    pub fn from_config(config: Config) -> Self {
+        Self::new(config, None, None, None)
+    }
+
     /// Loads a `Secrets` using default configuration paths
     ///
     /// This method looks for:
-    /// - `secretspec.toml` in the current directory for project configuration
+    /// - `secretspec.toml`, `secretspec.json` or `secretspec.yaml` in the current directory for project configuration
     /// - User configuration in the system config directory
     ///
     /// # Returns
@@ -79,9 +220,9 @@ impl Secrets {
     /// # Errors
     ///
     /// Returns an error if:
-    /// - No `secretspec.toml` file is found
+    /// - No spec file is found, or there is more than one
     /// - Configuration files are invalid
-    /// - The project revision is unsupported
+    /// - The project schema or revision is unsupported
     ///
     /// # Example
     ///
@@ -93,16 +234,51 @@ impl Secrets {
     /// spec.check().unwrap();
     /// ```
     pub fn load() -> Result<Self> {
-        let project_config = Config::try_from(Path::new("secretspec.toml"))?;
+        Self::load_from(".")
+    }
+
+    /// Loads a `Secrets` from the spec file in `dir`
+    ///
+    /// Like [`load`](Self::load), for programs whose working directory isn't
+    /// the project's. `secretspec.lock` is read and written in `dir` too.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`load`](Self::load)
+    pub fn load_from(dir: impl AsRef<Path>) -> Result<Self> {
+        let project_config = Config::try_from(SpecFormat::find(dir.as_ref())?.as_path())?;
+        let problems = project_config.key_problems();
+        if project_config.is_strict()
+            && let Some(problem) = problems.first()
+        {
+            return Err(ParseError::Validation(problem.clone()).into());
+        }
+        for problem in problems {
+            warning::warn(problem);
+        }
         let global_config = GlobalConfig::load()?;
         Ok(Self {
             config: project_config,
+            dir: dir.as_ref().to_path_buf(),
             global_config,
             provider: None,
+            provider_instance: None,
             profile: None,
+            frozen: false,
+            groups: Vec::new(),
+            clean_env: false,
+            harden: false,
+            redact_output: false,
+            allow_undeclared: false,
+            assumed_role: OnceLock::new(),
         })
     }
 
+    /// Returns the path of `secretspec.lock`, next to the spec file.
+    fn lockfile_path(&self) -> PathBuf {
+        self.dir.join(LOCKFILE_PATH)
+    }
+
     /// Sets the provider to use for secret operations
     ///
     /// This overrides the provider from global configuration.
@@ -122,6 +298,36 @@ impl Secrets {
     /// ```
     pub fn set_provider(&mut self, provider: impl Into<String>) {
         self.provider = Some(provider.into());
+        self.provider_instance = None;
+    }
+
+    /// Sets a provider instance to use for secret operations
+    ///
+    /// This overrides any provider specification, and is how stacks
+    /// assembled with [`ProviderBuilder`](crate::ProviderBuilder) are used.
+    /// The instance is used as is: no rate limits, recipients or other
+    /// configured layers are added to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - The provider to read and write secrets through
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::{ProviderBuilder, Secrets};
+    /// use std::time::Duration;
+    ///
+    /// let mut spec = Secrets::load().unwrap();
+    /// let provider = ProviderBuilder::from_spec("keyring")
+    ///     .unwrap()
+    ///     .cache(Duration::from_secs(30))
+    ///     .build();
+    /// spec.set_provider_instance(provider);
+    /// spec.check().unwrap();
+    /// ```
+    pub fn set_provider_instance(&mut self, provider: Box<dyn ProviderTrait>) {
+        self.provider_instance = Some(Arc::from(provider));
     }
 
     /// Sets the profile to use for secret operations
@@ -143,10 +349,239 @@ impl Secrets {
     /// ```
     pub fn set_profile(&mut self, profile: impl Into<String>) {
         self.profile = Some(profile.into());
+        self.assumed_role = OnceLock::new();
     }
 
-    /// Get a reference to the project configuration (for testing)
-    #[cfg(test)]
+    /// Makes `check` verify secrets against `secretspec.lock` instead of updating it
+    ///
+    /// In frozen mode `check` never prompts for missing secrets and fails if
+    /// any secret was added, removed, moved to another provider, or changed
+    /// value since the lockfile was written.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let mut spec = Secrets::load().unwrap();
+    /// spec.set_frozen(true);
+    /// spec.check().unwrap();
+    /// ```
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    /// Limits `check` and `run` to the secrets in the given groups
+    ///
+    /// An empty list selects all secrets again.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let mut spec = Secrets::load().unwrap();
+    /// spec.set_groups(vec!["payments".to_string()]);
+    /// spec.check().unwrap();
+    /// ```
+    pub fn set_groups(&mut self, groups: Vec<String>) {
+        self.groups = groups;
+    }
+
+    /// Makes `run` start the command from a clean environment
+    ///
+    /// Only the variables allowed in the spec's `[run]` section are passed
+    /// on, besides the secrets themselves.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let mut spec = Secrets::load().unwrap();
+    /// spec.set_clean_env(true);
+    /// spec.run(vec!["env".to_string()]).unwrap();
+    /// ```
+    pub fn set_clean_env(&mut self, clean_env: bool) {
+        self.clean_env = clean_env;
+    }
+
+    /// Makes `run` disable core dumps and ptrace access before resolving
+    /// secrets
+    ///
+    /// See the spec's `[run]` section for the equivalent `harden` setting.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let mut spec = Secrets::load().unwrap();
+    /// spec.set_harden(true);
+    /// spec.run(vec!["./deploy".to_string()]).unwrap();
+    /// ```
+    pub fn set_harden(&mut self, harden: bool) {
+        self.harden = harden;
+    }
+
+    /// Makes `run` replace secret values in the command's stdout and stderr
+    /// with `[REDACTED:KEY]`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let mut spec = Secrets::load().unwrap();
+    /// spec.set_redact_output(true);
+    /// spec.run(vec!["./integration-tests".to_string()]).unwrap();
+    /// ```
+    pub fn set_redact_output(&mut self, redact_output: bool) {
+        self.redact_output = redact_output;
+    }
+
+    /// Lets `get` and `set` read and write secrets the spec doesn't declare
+    ///
+    /// By default an undeclared name is rejected with a suggestion of the
+    /// declared names it is closest to, since it is usually a typo.
+    /// Undeclared secrets are read as they are stored, without defaults or
+    /// transforms.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let mut spec = Secrets::load().unwrap();
+    /// spec.set_allow_undeclared(true);
+    /// spec.set("SCRATCH_TOKEN", Some("abc123".to_string())).unwrap();
+    /// ```
+    pub fn set_allow_undeclared(&mut self, allow_undeclared: bool) {
+        self.allow_undeclared = allow_undeclared;
+    }
+
+    /// Returns the environment `run` starts a command with
+    ///
+    /// Variables of `inherited` are filtered by the spec's `[run]` section
+    /// and the secrets are added on top. Values are zeroed when the map is
+    /// dropped.
+    pub(crate) fn child_env(
+        &self,
+        inherited: impl IntoIterator<Item = (String, String)>,
+        secrets: HashMap<String, SecretString>,
+    ) -> HashMap<String, Zeroizing<String>> {
+        let mut env_vars: HashMap<String, Zeroizing<String>> = inherited
+            .into_iter()
+            .filter(|(name, _)| self.config.run.inherits(name, self.clean_env))
+            .map(|(name, value)| (name, Zeroizing::new(value)))
+            .collect();
+        // Convert SecretString values to regular strings for environment variables
+        for (key, secret) in secrets {
+            env_vars.insert(key, Zeroizing::new(secret.expose_secret().to_string()));
+        }
+        env_vars
+    }
+
+    /// Returns whether a secret is selected by the group filter
+    fn in_groups(&self, secret: &crate::config::Secret) -> bool {
+        self.groups.is_empty()
+            || secret
+                .group
+                .as_ref()
+                .is_some_and(|group| self.groups.contains(group))
+    }
+
+    /// Returns the declared name a secret is looked up under
+    ///
+    /// Names given on the command line or by a program are matched against
+    /// the declarations in their [normalized](config::normalize_key) form, so
+    /// `api_key` reads the secret declared as `API_KEY`. Names that match no
+    /// declaration, or several, are returned unchanged.
+    pub(crate) fn canonical_name(&self, name: &str) -> String {
+        if self.resolve_secret_config(name, None).is_some() {
+            return name.to_string();
+        }
+        let normalized = config::normalize_key(name);
+        let profile = self.resolve_profile(None);
+        let matches: Vec<String> = self
+            .declared_secrets(&profile)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|declared| config::normalize_key(declared) == normalized)
+            .collect();
+        match <[String; 1]>::try_from(matches) {
+            Ok([declared]) => declared,
+            Err(_) => name.to_string(),
+        }
+    }
+
+    /// Returns the names of the secrets declared for a profile
+    ///
+    /// The secrets of the current profile are combined with those inherited
+    /// from the default profile. Declarations reading an item contribute the
+    /// names of their fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profile doesn't exist
+    fn declared_secrets(&self, profile_name: &str) -> Result<BTreeSet<String>> {
+        let profile_config = self.config.profiles.get(profile_name).ok_or_else(|| {
+            SecretSpecError::SecretNotFound(format!("Profile '{}' not found", profile_name))
+        })?;
+
+        let exported = |profile_config: &Profile| {
+            profile_config
+                .secrets
+                .iter()
+                .flat_map(|(name, secret)| secret.exported_names(name))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        let mut names: BTreeSet<String> = exported(profile_config).into_iter().collect();
+        if profile_name != "default"
+            && let Some(default_profile) = self.config.profiles.get("default")
+        {
+            names.extend(exported(default_profile));
+        }
+        Ok(names)
+    }
+
+    /// Returns the names of the profile's secrets selected by the group filter
+    ///
+    /// Secrets limited to other platforms than the current one are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profile doesn't exist, or a group selects no
+    /// secrets, which usually means it is misspelled
+    pub(crate) fn selected_secrets(&self, profile_name: &str) -> Result<BTreeSet<String>> {
+        let mut names = self.declared_secrets(profile_name)?;
+
+        let configs: Vec<_> = names
+            .iter()
+            .filter_map(|name| self.resolve_secret_config(name, Some(profile_name)))
+            .collect();
+        if let Some(group) = self.groups.iter().find(|group| {
+            !configs
+                .iter()
+                .any(|secret| secret.group.as_ref() == Some(*group))
+        }) {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "No secrets in group '{}' of profile '{}'",
+                group, profile_name
+            )));
+        }
+
+        names.retain(|name| {
+            self.resolve_secret_config(name, Some(profile_name))
+                .is_some_and(|secret| {
+                    self.in_groups(&secret) && secret.applies_to(env::consts::OS, env::consts::ARCH)
+                })
+        });
+        Ok(names)
+    }
+
+    /// Get a reference to the project configuration
     pub(crate) fn config(&self) -> &Config {
         &self.config
     }
@@ -160,11 +595,10 @@ impl Secrets {
     /// Resolves the profile to use based on the provided value and configuration
     ///
     /// Profile resolution order:
-    /// 1. Provided profile argument
-    /// 2. Profile set via set_profile()
-    /// 3. SECRETSPEC_PROFILE environment variable
-    /// 4. Global configuration default profile
-    /// 5. "default" profile
+    /// 1. Provided profile argument, or the profile set via set_profile()
+    /// 2. SECRETSPEC_PROFILE environment variable
+    /// 3. Global configuration profile for this project, then the global default profile
+    /// 4. "default" profile
     ///
     /// # Arguments
     ///
@@ -174,16 +608,53 @@ impl Secrets {
     ///
     /// The resolved profile name
     pub(crate) fn resolve_profile(&self, profile: Option<&str>) -> String {
-        profile
-            .map(|p| p.to_string())
-            .or_else(|| self.profile.clone())
-            .or_else(|| env::var("SECRETSPEC_PROFILE").ok())
-            .or_else(|| {
+        self.resolve_profile_with_source(profile).0
+    }
+
+    /// Resolves the profile to use along with where it was chosen
+    ///
+    /// Follows the same order as [`resolve_profile`](Self::resolve_profile).
+    pub(crate) fn resolve_profile_with_source(
+        &self,
+        profile: Option<&str>,
+    ) -> (String, ProfileSource) {
+        self.profile_candidates(profile)
+            .into_iter()
+            .find_map(|(source, name)| name.map(|name| (name, source)))
+            .unwrap_or_else(|| ("default".to_string(), ProfileSource::SpecDefault))
+    }
+
+    /// Returns the profile each source selects, in order of precedence
+    ///
+    /// The first source that selects a profile wins.
+    pub(crate) fn profile_candidates(
+        &self,
+        profile: Option<&str>,
+    ) -> Vec<(ProfileSource, Option<String>)> {
+        let project = self
+            .global_config
+            .as_ref()
+            .and_then(|gc| gc.projects.get(&self.config.project.name));
+        vec![
+            (
+                ProfileSource::Flag,
+                profile
+                    .map(|p| p.to_string())
+                    .or_else(|| self.profile.clone()),
+            ),
+            (ProfileSource::Env, env::var("SECRETSPEC_PROFILE").ok()),
+            (
+                ProfileSource::ProjectConfig,
+                project.and_then(|p| p.profile.clone()),
+            ),
+            (
+                ProfileSource::UserConfig,
                 self.global_config
                     .as_ref()
-                    .and_then(|gc| gc.defaults.profile.clone())
-            })
-            .unwrap_or_else(|| "default".to_string())
+                    .and_then(|gc| gc.defaults.profile.clone()),
+            ),
+            (ProfileSource::SpecDefault, Some("default".to_string())),
+        ]
     }
 
     /// Resolves the configuration for a specific secret
@@ -230,12 +701,36 @@ impl Secrets {
                         .description
                         .clone()
                         .or_else(|| default.description.clone()),
-                    required: current.required,
-                    default: current.default.clone(),
+                    ..current.clone()
                 })
             }
             (Some(secret), None) | (None, Some(secret)) => Some(secret.clone()),
-            (None, None) => None,
+            (None, None) => {
+                // Fields of an item use the configuration of the item's declaration
+                let item = [profile_name.as_str(), "default"]
+                    .iter()
+                    .filter_map(|name| self.config.profiles.get(*name))
+                    .find_map(|profile_config| profile_config.item_declaration(name));
+                if let Some((declaration, _)) = item {
+                    return self.resolve_secret_config(declaration, Some(&profile_name));
+                }
+
+                // Keys matched by a pattern use the most specific pattern's configuration
+                let pattern = self
+                    .config
+                    .profiles
+                    .iter()
+                    .filter(|(name, _)| **name == profile_name || *name == "default")
+                    .flat_map(|(_, profile_config)| profile_config.secrets.keys())
+                    .filter(|pattern| {
+                        Secret::pattern_prefix(pattern).is_some_and(|prefix| {
+                            name.len() > prefix.len() && name.starts_with(prefix)
+                        })
+                    })
+                    .max_by_key(|pattern| pattern.len())?
+                    .clone();
+                self.resolve_secret_config(&pattern, Some(&profile_name))
+            }
         }
     }
 
@@ -243,9 +738,13 @@ impl Secrets {
     ///
     /// Provider resolution order:
     /// 1. Provided provider argument
-    /// 2. Provider set via builder
-    /// 3. Global configuration default provider
-    /// 4. Error if no provider is configured
+    /// 2. Provider instance or specification set via builder (see
+    ///    [`set_provider_instance`](Self::set_provider_instance))
+    /// 3. Global configuration provider for this project and profile (see
+    ///    [`GlobalConfig::project_provider_for`])
+    /// 4. The profile's `provider` in the spec, or the default profile's
+    /// 5. Global default provider (see [`GlobalConfig::default_provider_for`])
+    /// 6. Error if no provider is configured
     ///
     /// # Arguments
     ///
@@ -257,26 +756,233 @@ impl Secrets {
     ///
     /// # Errors
     ///
+    /// Encrypted (`enc+` and `gitvault`) providers also encrypt to the
+    /// recipients listed for the current profile in `secretspec.toml`.
+    ///
+    /// # Errors
+    ///
     /// Returns an error if:
     /// - No provider is configured
     /// - The specified provider is not found
+    /// - The spec is strict and the provider URI has an unknown parameter
     pub(crate) fn get_provider(
         &self,
         provider_arg: Option<String>,
     ) -> Result<Box<dyn ProviderTrait>> {
-        let provider_spec = provider_arg
-            .or_else(|| env::var("SECRETSPEC_PROVIDER").ok())
-            .or_else(|| self.provider.clone())
+        self.profile_provider(provider_arg, &self.resolve_profile(None))
+    }
+
+    /// Gets the provider a profile uses, like [`get_provider`](Self::get_provider)
+    /// does for the current one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no provider is configured for the profile, or it
+    /// cannot be initialized
+    fn profile_provider(
+        &self,
+        provider_arg: Option<String>,
+        profile: &str,
+    ) -> Result<Box<dyn ProviderTrait>> {
+        let namespace = self.namespace()?;
+        let adopted = self.adopted_items(&self.lockfile_path())?;
+        if provider_arg.is_none()
+            && let Some(instance) = &self.provider_instance
+        {
+            let items = self.item_ids(instance.name(), adopted);
+            return Ok(PinnedProvider::wrap(
+                NamespacedProvider::wrap(Box::new(instance.clone()), namespace.as_deref()),
+                items,
+            ));
+        }
+
+        // Expanded here so the agent serves the branch of this checkout
+        let mut provider_spec =
+            branch::expand(&self.profile_provider_spec(provider_arg, profile)?)?.into_owned();
+        if self.config.is_strict() {
+            provider::check_strict(&provider_spec)?;
+        }
+        if let Some(recipients) = self.config.recipients_for(profile) {
+            provider_spec = encrypted::with_recipients(provider_spec, recipients.values());
+        }
+        let builder = ProviderBuilder::from_spec(&provider_spec)?
+            .layer(|p: Box<dyn ProviderTrait>| NamespacedProvider::wrap(p, namespace.as_deref()))
+            .layer(|p: Box<dyn ProviderTrait>| self.throttle(p));
+        #[cfg(unix)]
+        let builder = builder.layer(|p: Box<dyn ProviderTrait>| agent::wrap(p, &provider_spec));
+
+        Ok(builder
+            .layer(|p: Box<dyn ProviderTrait>| {
+                let items = self.item_ids(p.name(), adopted.clone());
+                PinnedProvider::wrap(p, items)
+            })
+            .chunk()
+            .layer(|p: Box<dyn ProviderTrait>| self.cache(p))
+            .layer(TimedProvider::wrap)
+            .layer(TracedProvider::wrap)
+            .build())
+    }
+
+    /// Returns the namespace stored item names are prefixed with, from
+    /// `SECRETSPEC_NAMESPACE` or the global configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the namespace isn't valid
+    pub(crate) fn namespace(&self) -> Result<Option<String>> {
+        let namespace = env::var("SECRETSPEC_NAMESPACE")
+            .ok()
+            .filter(|namespace| !namespace.is_empty())
             .or_else(|| {
                 self.global_config
                     .as_ref()
-                    .and_then(|gc| gc.defaults.provider.clone())
+                    .and_then(|gc| gc.namespace_for(&self.config.project.name))
+                    .map(str::to_string)
+            });
+        match namespace {
+            Some(namespace) if !config::is_valid_namespace(&namespace) => {
+                Err(SecretSpecError::InvalidInput(format!(
+                    "Invalid namespace '{}': use letters, digits, '-', '_' and '.', separated by '/'",
+                    namespace
+                )))
+            }
+            namespace => Ok(namespace),
+        }
+    }
+
+    /// Returns the IDs of the items `secretspec adopt` recorded in the
+    /// lockfile, keyed by profile and then secret name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lockfile exists but can't be read
+    fn adopted_items(&self, path: &Path) -> Result<BTreeMap<String, BTreeMap<String, String>>> {
+        Ok(Lockfile::load(path)?
+            .map(|lockfile| lockfile.items)
+            .unwrap_or_default())
+    }
+
+    /// Returns the IDs of the items secrets are addressed by in `provider`:
+    /// the adopted ones, and for Bitwarden those declared with
+    /// `bitwarden_item_id`, which take precedence
+    fn item_ids(
+        &self,
+        provider: &str,
+        mut items: BTreeMap<String, BTreeMap<String, String>>,
+    ) -> BTreeMap<String, BTreeMap<String, String>> {
+        if provider != "bitwarden" {
+            return items;
+        }
+        for profile in self.config.profiles.keys() {
+            for name in self.declared_secrets(profile).unwrap_or_default() {
+                if let Some(id) = self
+                    .resolve_secret_config(&name, Some(profile))
+                    .and_then(|secret| secret.bitwarden_item_id)
+                {
+                    items.entry(profile.clone()).or_default().insert(name, id);
+                }
+            }
+        }
+        items
+    }
+
+    /// Applies the rate limit set for the provider in the global configuration
+    fn throttle(&self, provider: Box<dyn ProviderTrait>) -> Box<dyn ProviderTrait> {
+        let rate = self
+            .global_config
+            .as_ref()
+            .and_then(|gc| gc.rate_limit_for(provider.name()));
+        ThrottledProvider::wrap(provider, rate)
+    }
+
+    /// Caches lookups for `defaults.cache_ttl` seconds if the global
+    /// configuration sets it
+    fn cache(&self, provider: Box<dyn ProviderTrait>) -> Box<dyn ProviderTrait> {
+        match self
+            .global_config
+            .as_ref()
+            .and_then(|gc| gc.defaults.cache_ttl)
+        {
+            Some(ttl) if ttl > 0 => {
+                Box::new(CachedProvider::new(provider, Duration::from_secs(ttl)))
+            }
+            _ => provider,
+        }
+    }
+
+    /// Resolves the provider specification (name or URI) to use
+    ///
+    /// Follows the same resolution order as [`get_provider`](Self::get_provider).
+    ///
+    /// # Errors
+    ///
+    /// Returns `SecretSpecError::NoProviderConfigured` if no provider is configured
+    pub(crate) fn provider_spec(&self, provider_arg: Option<String>) -> Result<String> {
+        self.profile_provider_spec(provider_arg, &self.resolve_profile(None))
+    }
+
+    /// Resolves the provider specification a profile uses
+    ///
+    /// # Errors
+    ///
+    /// Returns `SecretSpecError::NoProviderConfigured` if no provider is configured
+    fn profile_provider_spec(&self, provider_arg: Option<String>, profile: &str) -> Result<String> {
+        let global_config = self.global_config.as_ref();
+        provider_arg
+            .or_else(|| env::var("SECRETSPEC_PROVIDER").ok())
+            .or_else(|| self.provider.clone())
+            .or_else(|| {
+                global_config
+                    .and_then(|gc| gc.project_provider_for(&self.config.project.name, profile))
+                    .or_else(|| self.config.provider_for(profile))
+                    .or_else(|| global_config.and_then(|gc| gc.default_provider_for(profile)))
+                    .map(|p| p.to_string())
             })
-            .ok_or(SecretSpecError::NoProviderConfigured)?;
+            .ok_or(SecretSpecError::NoProviderConfigured)
+    }
 
-        let provider = Box::<dyn ProviderTrait>::try_from(provider_spec)?;
+    /// Cross-checks a provider override against the provider the spec pins
+    /// the profile to
+    ///
+    /// `--provider` and `SECRETSPEC_PROVIDER` replace the configured
+    /// provider for one invocation. Reading a production profile from a
+    /// different backend than its pinned one usually means reading other
+    /// values than the deployment uses, so this returns a warning naming the
+    /// secrets affected. Overrides of other profiles, and overrides that keep
+    /// the backend, like another vault of the same provider, pass.
+    ///
+    /// # Returns
+    ///
+    /// The warning, or `None` if there is nothing to warn about
+    pub fn provider_override_warning(&self) -> Option<String> {
+        let overriding = self
+            .provider
+            .clone()
+            .or_else(|| env::var("SECRETSPEC_PROVIDER").ok())?;
+        let profile = self.resolve_profile(None);
+        if !config::is_production(&profile) {
+            return None;
+        }
+        let pinned = self.config.provider_for(&profile)?;
 
-        Ok(provider)
+        // Specs that don't parse are reported by the command itself
+        let from = provider::explain(&overriding).ok()?.provider;
+        let to = provider::explain(pinned).ok()?.provider;
+        if from == to {
+            return None;
+        }
+        let secrets: Vec<String> = self.selected_secrets(&profile).ok()?.into_iter().collect();
+        if secrets.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "--provider reads {} from {}, but secretspec.toml pins the {} profile to {} ({})",
+            secrets.join(", "),
+            from,
+            profile,
+            to,
+            pinned
+        ))
     }
 
     /// Sets a secret value in the provider
@@ -310,9 +1016,63 @@ impl Secrets {
     /// spec.set("DATABASE_URL", Some("postgres://localhost".to_string())).unwrap();
     /// ```
     pub fn set(&self, name: &str, value: Option<String>) -> Result<()> {
+        self.set_value(name, value.map(|v| SecretString::new(v.into())))
+    }
+
+    /// Sets a secret value like [`set`](Self::set), taking a value that is
+    /// already a [`SecretString`] so it never exists as a plain `String`
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`set`](Self::set)
+    pub fn set_value(&self, name: &str, value: Option<SecretString>) -> Result<()> {
+        self.store(name, value, None, true)
+    }
+
+    /// Sets a secret value without printing a confirmation
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`set`](Self::set)
+    pub fn set_secret(&self, name: &str, value: SecretString) -> Result<()> {
+        self.store(name, Some(value), None, false)
+    }
+
+    /// Sets a secret value only if it is still at the expected revision
+    ///
+    /// `revision` is the revision `secretspec list` showed for the secret.
+    /// If someone else changed the secret since, nothing is written, so
+    /// concurrent updates aren't silently overwritten. The provider checks
+    /// and writes in one step; providers that can't fail instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SecretSpecError::Conflict`] if the secret was changed or
+    /// deleted since `revision`, an error if the provider doesn't support
+    /// conditional writes, and the errors of [`set`](Self::set)
+    pub fn set_if_revision(
+        &self,
+        name: &str,
+        value: Option<SecretString>,
+        revision: &str,
+    ) -> Result<()> {
+        self.store(name, value, Some(revision), true)
+    }
+
+    /// Stores a secret, checking its revision first if one is expected, and
+    /// prints a confirmation if `announce` is set.
+    fn store(
+        &self,
+        name: &str,
+        value: Option<SecretString>,
+        expected: Option<&str>,
+        announce: bool,
+    ) -> Result<()> {
+        let name = &self.canonical_name(name);
+
         // Check if the secret exists in the spec
         let profile_name = self.resolve_profile(None);
-        let profile_config = self.config.profiles.get(&profile_name).ok_or_else(|| {
+        self.config.profiles.get(&profile_name).ok_or_else(|| {
             SecretSpecError::SecretNotFound(format!(
                 "Profile '{}' is not defined in secretspec.toml. Available profiles: {}",
                 profile_name,
@@ -326,106 +1086,426 @@ impl Secrets {
         })?;
 
         // Check if the secret exists in the profile or is inherited from default
-        if self.resolve_secret_config(name, None).is_none() {
-            // Collect available secrets from both current profile and default
-            let mut available_secrets = profile_config.secrets.keys().cloned().collect::<Vec<_>>();
-            if profile_name != "default" {
-                if let Some(default_profile) = self.config.profiles.get("default") {
-                    for key in default_profile.secrets.keys() {
-                        if !available_secrets.contains(key) {
-                            available_secrets.push(key.clone());
-                        }
-                    }
-                }
-            }
-            available_secrets.sort();
+        if self.resolve_secret_config(name, None).is_none() && !self.allow_undeclared {
+            return Err(self.undeclared(name, &profile_name));
+        }
 
-            return Err(SecretSpecError::SecretNotFound(format!(
-                "Secret '{}' is not defined in profile '{}'. Available secrets: {}",
-                name,
-                profile_name,
-                available_secrets.join(", ")
+        if let Some(item) = self
+            .resolve_secret_config(name, None)
+            .and_then(|secret| secret.item)
+        {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "Secret '{}' is read from item '{}' and cannot be set",
+                name, item
             )));
         }
 
         let backend = self.get_provider(None)?;
         let profile_display = self.resolve_profile(None);
 
-        // Check if the provider supports setting values
-        if !backend.allows_set() {
-            return Err(SecretSpecError::ProviderOperationFailed(format!(
-                "Provider '{}' is read-only and does not support setting values",
-                backend.name()
-            )));
-        }
+        // Check that the value can be saved before asking for it
+        backend.check_writable(&self.config.project.name, &profile_name)?;
 
         let value = if let Some(v) = value {
-            SecretString::new(v.into())
+            v
         } else if io::stdin().is_terminal() {
-            print!("Enter value for {} (profile: {}): ", name, profile_display);
-            io::stdout().flush()?;
-            SecretString::new(rpassword::read_password()?.into())
+            prompt::secret(name, &profile_display)?
         } else {
             // Read from stdin when input is piped
-            let mut buffer = String::new();
-            io::stdin().read_line(&mut buffer)?;
-            SecretString::new(buffer.trim().to_string().into())
+            prompt::from_stdin()?
         };
+        let value = self.seal(name, &profile_name, value)?;
 
-        backend.set(&self.config.project.name, name, &value, &profile_name)?;
-        println!(
-            "{} Secret '{}' saved to {} (profile: {})",
-            "✓".green(),
-            name,
-            backend.name(),
-            profile_display
+        // Only looked up when the change is announced
+        let existed = self.config.notify.covers(&profile_name)
+            && matches!(
+                backend.get(&self.config.project.name, name, &profile_name),
+                Ok(Some(_))
+            );
+        match expected {
+            Some(revision) => backend.compare_and_set(
+                &self.config.project.name,
+                name,
+                &value,
+                &profile_name,
+                revision,
+            )?,
+            None => backend.set(&self.config.project.name, name, &value, &profile_name)?,
+        }
+        self.tag_expiry(backend.as_ref(), name, &profile_name)?;
+        if announce {
+            output::say(format!(
+                "{} Secret '{}' saved to {} (profile: {})",
+                "✓".green(),
+                name,
+                backend.name(),
+                profile_display
+            ));
+        }
+        notify::send(
+            &self.config.notify,
+            &Change {
+                action: if existed {
+                    Action::Changed
+                } else {
+                    Action::Created
+                },
+                project: &self.config.project.name,
+                profile: &profile_name,
+                keys: vec![name.to_string()],
+                provider: backend.name(),
+            },
         );
 
         Ok(())
     }
 
-    /// Retrieves and prints a secret value
+    /// Tags a secret just written with the time it expires, if the spec
+    /// gives it a ttl
     ///
-    /// This method retrieves a secret from the storage backend and prints it
-    /// to stdout. If the secret is not found but has a default value, the
-    /// default is printed.
-    ///
-    /// # Arguments
+    /// # Errors
     ///
-    /// * `name` - The name of the secret to retrieve
-    /// * `provider_arg` - Optional provider to use
-    /// * `profile` - Optional profile to use
+    /// Returns an error if writing the tag fails
+    fn tag_expiry(&self, backend: &dyn ProviderTrait, name: &str, profile: &str) -> Result<()> {
+        let Some(lifetime) = self
+            .resolve_secret_config(name, Some(profile))
+            .and_then(|secret| secret.ttl)
+        else {
+            return Ok(());
+        };
+        let lifetime = ttl::parse(&lifetime).map_err(SecretSpecError::InvalidInput)?;
+        backend.set(
+            &self.config.project.name,
+            &ttl::tag_key(name),
+            &SecretString::new(ttl::tag(lifetime).into()),
+            profile,
+        )
+    }
+
+    /// Seals a value about to be written to the hardware key, if the spec
+    /// gives the secret `hardware_confirm`
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// `Ok(())` if the secret was found and printed
+    /// Returns an error if no hardware key is configured or sealing fails
+    fn seal(&self, name: &str, profile: &str, value: SecretString) -> Result<SecretString> {
+        match self.resolve_secret_config(name, Some(profile)) {
+            Some(secret) if secret.hardware_confirm => hardware::seal(name, &value),
+            _ => Ok(value),
+        }
+    }
+
+    /// Retrieves a secret value, or its default if it isn't set
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - The secret is not defined in the specification
     /// - The secret is not found and has no default value
-    pub fn get(&self, name: &str) -> Result<()> {
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secrecy::ExposeSecret;
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// let url = spec.get("DATABASE_URL").unwrap();
+    /// assert!(url.expose_secret().starts_with("postgres://"));
+    /// ```
+    pub fn get(&self, name: &str) -> Result<SecretString> {
+        let name = &self.canonical_name(name);
+        let mut span = telemetry::span("secretspec.get");
+        span.attr("secretspec.secret", name);
+        let result = self.lookup(name);
+        if let Err(e) = &result {
+            span.fail(e);
+        }
+        result
+    }
+
+    /// Returns the error for a secret the profile doesn't declare, suggesting
+    /// the declared secrets closest to the name.
+    fn undeclared(&self, name: &str, profile_name: &str) -> SecretSpecError {
+        let declared = self.declared_secrets(profile_name).unwrap_or_default();
+        let candidates: Vec<&str> = declared.iter().map(String::as_str).collect();
+        let hint = match suggest::similar(name, &candidates).as_slice() {
+            [] => format!("Available secrets: {}", candidates.join(", ")),
+            similar => format!(
+                "Did you mean {}?",
+                similar
+                    .iter()
+                    .take(3)
+                    .map(|candidate| format!("'{}'", candidate))
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            ),
+        };
+        SecretSpecError::SecretNotDeclared {
+            name: name.to_string(),
+            profile: profile_name.to_string(),
+            hint,
+        }
+    }
+
+    /// Resolves a single secret, the work of [`get`](Self::get).
+    fn lookup(&self, name: &str) -> Result<SecretString> {
         let backend = self.get_provider(None)?;
         let profile_name = self.resolve_profile(None);
-        let secret_config = self
-            .resolve_secret_config(name, None)
-            .ok_or_else(|| SecretSpecError::SecretNotFound(name.to_string()))?;
+        let Some(secret_config) = self.resolve_secret_config(name, None) else {
+            if !self.allow_undeclared {
+                return Err(self.undeclared(name, &profile_name));
+            }
+            return backend
+                .get(&self.config.project.name, name, &profile_name)?
+                .ok_or_else(|| SecretSpecError::SecretNotFound(name.to_string()));
+        };
         let default = secret_config.default.clone();
 
-        match backend.get(&self.config.project.name, name, &profile_name)? {
-            Some(value) => {
-                // Use expose_secret() to access the actual value for printing
-                println!("{}", value.expose_secret());
-                Ok(())
-            }
+        let value = match self.get_value(backend.as_ref(), name, &secret_config, &profile_name)? {
+            Some(value) => value,
             None => {
                 if let Some(default_value) = default {
-                    println!("{}", default_value);
-                    Ok(())
+                    self.resolve_default(
+                        backend.as_ref(),
+                        &default_value,
+                        &HashMap::new(),
+                        &mut vec![name.to_string()],
+                    )?
                 } else {
-                    Err(SecretSpecError::SecretNotFound(name.to_string()))
+                    return Err(SecretSpecError::SecretNotFound(name.to_string()));
+                }
+            }
+        };
+
+        transform::apply(name, &secret_config.transform, value)
+    }
+
+    /// Traces how a secret resolves, without recording its value
+    ///
+    /// Resolves the secret like [`get`](Self::get) does and records the
+    /// profile and why it was chosen, the declaration, the provider and its
+    /// layers, each lookup in order, and the defaults, references and
+    /// transforms applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the secret isn't declared, no provider is
+    /// configured or a lookup fails
+    pub fn explain(&self, name: &str) -> Result<Trace> {
+        let project = &self.config.project.name;
+        let canonical = self.canonical_name(name);
+        let mut trace = Trace::new(&canonical);
+        if canonical != name {
+            trace.step(
+                "alias",
+                format!("'{}' matches the declaration {}", name, canonical),
+            );
+        }
+        let name = canonical.as_str();
+
+        let (profile_name, profile_source) = self.resolve_profile_with_source(None);
+        trace.step(
+            "profile",
+            format!("{} ({})", profile_name, profile_source.describe(project)),
+        );
+        let Some(secret_config) = self.resolve_secret_config(name, None) else {
+            return Err(self.undeclared(name, &profile_name));
+        };
+        trace.step("declared", self.declaration(name, &profile_name));
+
+        if self.config.assume_role_for(&profile_name).is_some()
+            && sts::DERIVED_SECRETS.contains(&name)
+        {
+            trace.source = Some("credentials of the profile's assumed role".to_string());
+            return Ok(trace);
+        }
+
+        let backend = self.get_provider(None)?;
+        match &self.provider_instance {
+            Some(instance) => trace.step(
+                "provider",
+                format!("{} (set by the program)", instance.name()),
+            ),
+            None => {
+                let spec = self.provider_spec(None)?;
+                let source = self.provider_source(&profile_name);
+                trace.step("provider", format!("{} ({})", spec, source));
+                if let Ok(explanation) = provider::explain(&spec) {
+                    if explanation.env_override {
+                        trace.step("layer", "variables set in the environment take precedence");
+                    }
+                    if let Some(recipients) = explanation.encryption {
+                        trace.step(
+                            "layer",
+                            format!("values encrypted to {} recipient(s)", recipients),
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(namespace) = self.namespace()? {
+            trace.step("layer", format!("item names prefixed with '{}'", namespace));
+        }
+        let adopted = self.adopted_items(&self.lockfile_path())?;
+        if let Some(id) = self
+            .item_ids(backend.name(), adopted)
+            .get(&profile_name)
+            .and_then(|items| items.get(name))
+        {
+            trace.step("layer", format!("addressed by item ID {}", id));
+        }
+        #[cfg(unix)]
+        if let Some(socket) = env::var_os(agent::SOCKET_ENV).filter(|socket| !socket.is_empty())
+            && !backend.works_in_sandbox()
+        {
+            trace.step(
+                "layer",
+                format!("read through the agent at {}", Path::new(&socket).display()),
+            );
+        }
+        trace.step("cache", "none; the provider is read on every command");
+
+        let value = match (&secret_config.item, secret_config.fields.get(name)) {
+            (Some(item), Some(field)) => {
+                let found = backend
+                    .get_fields(project, item, &[field], &profile_name)?
+                    .contains_key(field);
+                trace.lookup(
+                    format!("field '{}' of item '{}' in {}", field, item, backend.name()),
+                    found,
+                );
+                found.then(|| format!("field '{}' of item '{}'", field, item))
+            }
+            _ => {
+                let found = backend.get(project, name, &profile_name)?.is_some();
+                trace.lookup(format!("{} in {}", name, backend.name()), found);
+                if found && secret_config.hardware_confirm {
+                    trace.step("layer", "unsealed with the security key");
+                }
+                found.then(|| backend.name().to_string())
+            }
+        };
+        trace.source = match (value, &secret_config.default) {
+            (Some(source), _) => Some(source),
+            (None, Some(default)) => self.explain_default(
+                backend.as_ref(),
+                default,
+                &mut trace,
+                &mut vec![name.to_string()],
+            )?,
+            (None, None) => None,
+        };
+
+        if trace.source.is_some() && !secret_config.transform.is_empty() {
+            trace.step("transform", secret_config.transform.join(", "));
+        }
+        Ok(trace)
+    }
+
+    /// Describes where a secret is declared, for [`explain`](Self::explain)
+    fn declaration(&self, name: &str, profile_name: &str) -> String {
+        let declares = |profile: &str| {
+            self.config
+                .profiles
+                .get(profile)
+                .and_then(|profile_config| match profile_config.secrets.get(name) {
+                    Some(_) => Some(format!("profile {}", profile)),
+                    None => profile_config.item_declaration(name).map(|(item, _)| {
+                        format!("profile {}, as a field of item {}", profile, item)
+                    }),
+                })
+        };
+        match (declares(profile_name), declares("default")) {
+            (Some(current), Some(_)) if profile_name != "default" => {
+                format!("{}, over profile default", current)
+            }
+            (Some(current), _) => current,
+            (None, Some(default)) => format!("{}, inherited", default),
+            (None, None) => format!("profile {}", profile_name),
+        }
+    }
+
+    /// Describes where the provider of a profile is configured, following
+    /// the order of [`profile_provider_spec`](Self::profile_provider_spec)
+    fn provider_source(&self, profile: &str) -> String {
+        let project = &self.config.project.name;
+        let global_config = self.global_config.as_ref();
+        if env::var("SECRETSPEC_PROVIDER").is_ok() {
+            "SECRETSPEC_PROVIDER".to_string()
+        } else if self.provider.is_some() {
+            "--provider".to_string()
+        } else if global_config
+            .is_some_and(|gc| gc.project_provider_for(project, profile).is_some())
+        {
+            format!("user config projects.{}", project)
+        } else if self
+            .config
+            .profiles
+            .get(profile)
+            .is_some_and(|p| p.provider.is_some())
+        {
+            format!("profile {} in the spec", profile)
+        } else if self.config.provider_for(profile).is_some() {
+            "profile default in the spec".to_string()
+        } else {
+            "user config defaults".to_string()
+        }
+    }
+
+    /// Traces the resolution of a default value, following references like
+    /// [`resolve_default`](Self::resolve_default) does
+    ///
+    /// # Returns
+    ///
+    /// Where the value came from, or `None` if a reference points at a value
+    /// that isn't set
+    fn explain_default(
+        &self,
+        backend: &dyn ProviderTrait,
+        default: &str,
+        trace: &mut Trace,
+        chain: &mut Vec<String>,
+    ) -> Result<Option<String>> {
+        let project = &self.config.project.name;
+        let profile_name = self.resolve_profile(None);
+        match Reference::parse(default)? {
+            None => {
+                trace.step("default", "plain value from the spec");
+                Ok(Some("default".to_string()))
+            }
+            Some(Reference::Provider { uri, key }) => {
+                trace.step("default", format!("reference to {} in {}", key, uri));
+                let found = self
+                    .throttle(Box::<dyn ProviderTrait>::try_from(uri)?)
+                    .get(project, key, &profile_name)?
+                    .is_some();
+                trace.lookup(format!("{} in {}", key, uri), found);
+                Ok(found.then(|| format!("{} in {}", key, uri)))
+            }
+            Some(Reference::Secret(other)) => {
+                trace.step("default", format!("reference to {}", other));
+                if chain.iter().any(|visited| visited == other) {
+                    chain.push(other.to_string());
+                    return Err(SecretSpecError::ReferenceCycle(chain.join(" -> ")));
+                }
+                let other_config = self
+                    .resolve_secret_config(other, None)
+                    .ok_or_else(|| SecretSpecError::SecretNotFound(other.to_string()))?;
+                let found = self
+                    .get_value(backend, other, &other_config, &profile_name)?
+                    .is_some();
+                trace.lookup(format!("{} in {}", other, backend.name()), found);
+                if found {
+                    return Ok(Some(format!("{} in {}", other, backend.name())));
                 }
+                let Some(other_default) = &other_config.default else {
+                    return Ok(None);
+                };
+                chain.push(other.to_string());
+                let source = self.explain_default(backend, other_default, trace, chain);
+                chain.pop();
+                source
             }
         }
     }
@@ -437,8 +1517,7 @@ impl Secrets {
     ///
     /// # Arguments
     ///
-    /// * `provider_arg` - Optional provider to use
-    /// * `profile` - Optional profile to use
+    /// * `backend` - The provider to read secrets from and store prompted values in
     /// * `interactive` - Whether to prompt for missing secrets
     ///
     /// # Returns
@@ -452,65 +1531,72 @@ impl Secrets {
     /// - Storage operations fail
     fn ensure_secrets(
         &self,
-        provider_arg: Option<String>,
-        profile: Option<String>,
+        backend: &dyn ProviderTrait,
         interactive: bool,
     ) -> Result<ValidatedSecrets> {
-        let backend = self.get_provider(provider_arg.clone())?;
-        let profile_display = self.resolve_profile(profile.as_deref());
+        let profile_display = self.resolve_profile(None);
 
         // First validate to see what's missing
-        let validation_result = self.validate()?;
+        let validation_result = self.validate_with(backend)?;
 
         match validation_result {
             Ok(valid_secrets) => Ok(valid_secrets),
             Err(validation_errors) => {
                 // If we're in interactive mode and have missing required secrets, prompt for them
                 if interactive && !validation_errors.missing_required.is_empty() {
-                    println!("\nThe following required secrets are missing:");
+                    // Fail before prompting if the answers couldn't be saved
+                    if io::stdin().is_terminal() {
+                        backend.check_writable(&self.config.project.name, &profile_display)?;
+                    }
+                    output::say("\nThe following required secrets are missing:");
                     for secret_name in &validation_errors.missing_required {
+                        // A pattern names no single key to prompt for
+                        if Secret::pattern_prefix(secret_name).is_some() {
+                            continue;
+                        }
                         if let Some(secret_config) =
                             self.resolve_secret_config(secret_name, Some(&profile_display))
                         {
+                            // Fields of an item are stored in the item itself
+                            if secret_config.item.is_some() {
+                                continue;
+                            }
                             let description = secret_config
                                 .description
                                 .as_deref()
                                 .unwrap_or("No description");
-                            println!("\n{} - {}", secret_name.bold(), description);
+                            output::say(format!("\n{} - {}", secret_name.bold(), description));
                             let value = if io::stdin().is_terminal() {
-                                print!(
-                                    "Enter value for {} (profile: {}): ",
-                                    secret_name, profile_display
-                                );
-                                io::stdout().flush()?;
-                                rpassword::read_password()?
+                                prompt::secret(secret_name, &profile_display)?
                             } else {
                                 // When stdin is not a terminal, we can't prompt interactively
                                 return Err(SecretSpecError::RequiredSecretMissing(
                                     validation_errors.missing_required.join(", "),
                                 ));
                             };
+                            let value = self.seal(secret_name, &profile_display, value)?;
 
                             backend.set(
                                 &self.config.project.name,
                                 secret_name,
-                                &SecretString::new(value.into()),
+                                &value,
                                 &profile_display,
                             )?;
-                            println!(
+                            self.tag_expiry(backend, secret_name, &profile_display)?;
+                            output::say(format!(
                                 "{} Secret '{}' saved to {} (profile: {})",
                                 "✓".green(),
                                 secret_name,
                                 backend.name(),
                                 profile_display
-                            );
+                            ));
                         }
                     }
 
-                    println!("\nAll required secrets have been set.");
+                    output::say("\nAll required secrets have been set.");
 
                     // Re-validate to get the updated results
-                    match self.validate()? {
+                    match self.validate_with(backend)? {
                         Ok(valid_secrets) => Ok(valid_secrets),
                         Err(still_errors) => Err(SecretSpecError::RequiredSecretMissing(
                             still_errors.missing_required.join(", "),
@@ -559,12 +1645,12 @@ impl Secrets {
         let provider = self.get_provider(None)?;
         let profile_display = self.resolve_profile(None);
 
-        println!(
+        output::say(format!(
             "Checking secrets in {} using {} (profile: {})...\n",
             self.config.project.name.bold(),
             provider.name().blue(),
             profile_display.cyan()
-        );
+        ));
 
         // First get the initial validation result to display status
         let initial_validation_result = self.validate()?;
@@ -589,66 +1675,58 @@ impl Secrets {
 
         // Display status for each secret
         let profile_name = self.resolve_profile(None);
-        let profile_config = self.config.profiles.get(&profile_name).ok_or_else(|| {
-            SecretSpecError::SecretNotFound(format!("Profile '{}' not found", profile_name))
-        })?;
-
-        // Collect all secrets to display - from current profile and default profile
-        let mut all_secrets_to_display = Vec::new();
-
-        // Add secrets from the current profile
-        for (name, config) in &profile_config.secrets {
-            all_secrets_to_display.push((name.clone(), config.clone()));
-        }
 
-        // If not the default profile, also add secrets from default profile
-        if profile_name != "default" {
-            if let Some(default_profile) = self.config.profiles.get("default") {
-                for (name, config) in &default_profile.secrets {
-                    // Only add if not already in current profile
-                    if !profile_config.secrets.contains_key(name) {
-                        all_secrets_to_display.push((name.clone(), config.clone()));
-                    }
-                }
-            }
-        }
-
-        // Sort by name for consistent display
-        all_secrets_to_display.sort_by(|a, b| a.0.cmp(&b.0));
+        // Collect all secrets to display - from current profile and default
+        // profile, sorted by name for consistent display
+        let all_secrets_to_display: Vec<_> = self
+            .selected_secrets(&profile_name)?
+            .into_iter()
+            .filter_map(|name| {
+                let config = self.resolve_secret_config(&name, None)?;
+                Some((name, config))
+            })
+            .collect();
+        let rows = Rows::new(all_secrets_to_display.iter().map(|(name, _)| name.as_str()));
 
-        for (name, config) in all_secrets_to_display {
-            if secrets_map.contains_key(&name) {
-                if with_defaults.iter().any(|(n, _)| n == &name) {
-                    println!(
-                        "{} {} - {} {}",
-                        "○".yellow(),
+        for (name, config) in &all_secrets_to_display {
+            let description = config.description.as_deref().unwrap_or("No description");
+            let matched = Secret::pattern_prefix(name).map_or(0, |prefix| {
+                secrets_map
+                    .keys()
+                    .filter(|key| key.len() > prefix.len() && key.starts_with(prefix))
+                    .count()
+            });
+            if matched > 0 {
+                rows.print(
+                    Mark::Found,
+                    name,
+                    description,
+                    Some(Mark::Found.paint(&format!("({} matched)", matched))),
+                );
+            } else if secrets_map.contains_key(name) {
+                if with_defaults.iter().any(|(n, _)| n == name) {
+                    rows.print(
+                        Mark::Default,
                         name,
-                        config.description.as_deref().unwrap_or("No description"),
-                        "(has default)".yellow()
+                        description,
+                        Some(Mark::Default.paint("(has default)")),
                     );
                 } else {
-                    println!(
-                        "{} {} - {}",
-                        "✓".green(),
-                        name,
-                        config.description.as_deref().unwrap_or("No description")
-                    );
+                    rows.print(Mark::Found, name, description, None);
                 }
-            } else if missing_required.contains(&name) {
-                println!(
-                    "{} {} - {} {}",
-                    "✗".red(),
+            } else if missing_required.contains(name) {
+                rows.print(
+                    Mark::Missing,
                     name,
-                    config.description.as_deref().unwrap_or("No description"),
-                    "(required)".red()
+                    description,
+                    Some(Mark::Missing.paint("(required)")),
                 );
-            } else if missing_optional.contains(&name) {
-                println!(
-                    "{} {} - {} {}",
-                    "○".blue(),
+            } else if missing_optional.contains(name) {
+                rows.print(
+                    Mark::Optional,
                     name,
-                    config.description.as_deref().unwrap_or("No description"),
-                    "(optional)".blue()
+                    description,
+                    Some(Mark::Optional.paint("(optional)")),
                 );
             }
         }
@@ -656,157 +1734,2064 @@ impl Secrets {
         let found_count = secrets_map.len() - with_defaults.len();
         let missing_count = missing_required.len();
 
-        println!(
+        output::say(format!(
             "\nSummary: {} found, {} missing",
             found_count.to_string().green(),
             missing_count.to_string().red()
-        );
+        ));
 
         // Now ensure all secrets are present (will prompt if needed)
-        self.ensure_secrets(None, None, true)?;
+        let backend = self.get_provider(None)?;
+        let validated = self.ensure_secrets(backend.as_ref(), !self.frozen)?;
+        self.lock(&validated, &self.lockfile_path())?;
 
         Ok(())
     }
 
-    /// Imports secrets from one provider to another
+    /// Fills in the declared secrets that are missing from the provider
     ///
-    /// This method copies all secrets defined in the specification from the
-    /// source provider to the default provider configured in the global settings.
+    /// Secrets the provider already holds are left alone, so this can be run
+    /// again safely, e.g. when setting up a new machine. Values are taken
+    /// from `answers`, a TOML file mapping secret names to values. Missing
+    /// required secrets without a default that it doesn't cover are prompted
+    /// for.
     ///
     /// # Arguments
     ///
-    /// * `from_provider` - The provider specification to import from
-    ///
-    /// # Returns
-    ///
-    /// `Ok(())` if the import completes (even if some secrets were not found)
+    /// * `answers` - Optional path of the answers file
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - The source provider cannot be initialized
-    /// - The target provider cannot be initialized
-    /// - Storage operations fail
-    ///
-    /// # Example
-    ///
-    /// ```no_run
-    /// use secretspec::Secrets;
-    ///
-    /// let spec = Secrets::load().unwrap();
-    /// spec.import("dotenv://.env.production").unwrap();
-    /// ```
-    pub fn import(&self, from_provider: &str) -> Result<()> {
-        // Get the "to" provider from global config (default)
-        let to_provider = self.get_provider(None)?;
-
-        // Resolve profile (checks env var, then global config, then defaults to "default")
-        let profile_display = self.resolve_profile(None);
-
-        // Create the "from" provider
-        let from_provider_instance = Box::<dyn ProviderTrait>::try_from(from_provider.to_string())?;
+    /// - The answers file can't be read or names an undeclared secret
+    /// - A required secret is still missing and no terminal is attached
+    /// - The provider is read-only or storing a value fails
+    pub fn ensure(&self, answers: Option<&Path>) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let project = &self.config.project.name;
+        let profile_name = self.resolve_profile(None);
+        let mut answers = match answers {
+            Some(path) => prompt::answers(path)?,
+            None => HashMap::new(),
+        };
 
-        println!(
-            "Importing secrets from {} to {} (profile: {})...\n",
-            from_provider.blue(),
-            to_provider.name().blue(),
-            profile_display.cyan()
-        );
+        let names = self.selected_secrets(&profile_name)?;
+        if let Some(unknown) = answers.keys().filter(|name| !names.contains(*name)).min() {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "'{}' in the answers file is not declared in profile '{}'",
+                unknown, profile_name
+            )));
+        }
+        let missing_required = match self.validate_with(backend.as_ref())? {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.missing_required,
+        };
+        // Nothing is written when no answers are given and nothing required
+        // is missing
+        if !answers.is_empty() || !missing_required.is_empty() {
+            backend.check_writable(project, &profile_name)?;
+        }
 
-        // Get the profile configuration
-        let profile_config = self.config.profiles.get(&profile_display).ok_or_else(|| {
-            SecretSpecError::SecretNotFound(format!("Profile '{}' not found", profile_display))
-        })?;
+        output::say(format!(
+            "Ensuring secrets in {} using {} (profile: {})...\n",
+            project.bold(),
+            backend.name().blue(),
+            profile_name.cyan()
+        ));
 
-        let mut imported = 0;
-        let mut already_exists = 0;
-        let mut not_found = 0;
+        let mut created = Vec::new();
+        let mut still_missing = Vec::new();
+        for name in &names {
+            let Some(config) = self.resolve_secret_config(name, None) else {
+                continue;
+            };
+            // Patterns and fields of an item can't be set one by one
+            if Secret::pattern_prefix(name).is_some() || config.item.is_some() {
+                continue;
+            }
+            if backend.get(project, name, &profile_name)?.is_some() {
+                output::say(format!(
+                    "{} {} {}",
+                    "✓".green(),
+                    name,
+                    "(already set)".dimmed()
+                ));
+                continue;
+            }
 
-        // Process each secret in the profile
-        for (name, config) in &profile_config.secrets {
-            // First check if the secret exists in the "from" provider
-            match from_provider_instance.get(&self.config.project.name, name, &profile_display)? {
-                Some(value) => {
-                    // Secret exists in "from" provider, check if it exists in "to" provider
-                    match to_provider.get(&self.config.project.name, name, &profile_display)? {
-                        Some(_) => {
-                            println!(
-                                "{} {} - {} {}",
-                                "○".yellow(),
-                                name,
-                                config.description.as_deref().unwrap_or("No description"),
-                                "(already exists in target)".yellow()
-                            );
-                            already_exists += 1;
-                        }
-                        None => {
-                            // Secret doesn't exist in "to" provider, import it
-                            to_provider.set(
-                                &self.config.project.name,
-                                name,
-                                &value,
-                                &profile_display,
-                            )?;
-                            println!(
-                                "{} {} - {}",
-                                "✓".green(),
-                                name,
-                                config.description.as_deref().unwrap_or("No description")
-                            );
-                            imported += 1;
-                        }
-                    }
+            let value = match answers.remove(name) {
+                Some(value) => value,
+                None if !missing_required.contains(name) => {
+                    output::say(format!("{} {} {}", "○".blue(), name, "(skipped)".blue()));
+                    continue;
+                }
+                None if io::stdin().is_terminal() => {
+                    let description = config.description.as_deref().unwrap_or("No description");
+                    output::say(format!("\n{} - {}", name.bold(), description));
+                    prompt::secret(name, &profile_name)?
                 }
                 None => {
-                    // Secret doesn't exist in "from" provider
-                    // Check if it exists in the "to" provider
-                    match to_provider.get(&self.config.project.name, name, &profile_display)? {
-                        Some(_) => {
-                            println!(
-                                "{} {} - {} {}",
-                                "○".blue(),
-                                name,
-                                config.description.as_deref().unwrap_or("No description"),
-                                "(already in target, not in source)".blue()
-                            );
-                            already_exists += 1;
-                        }
-                        None => {
-                            println!(
-                                "{} {} - {} {}",
-                                "✗".red(),
-                                name,
-                                config.description.as_deref().unwrap_or("No description"),
-                                "(not found in source)".red()
-                            );
-                            not_found += 1;
-                        }
-                    }
+                    still_missing.push(name.clone());
+                    continue;
                 }
-            }
+            };
+
+            let value = self.seal(name, &profile_name, value)?;
+            backend.set(project, name, &value, &profile_name)?;
+            self.tag_expiry(backend.as_ref(), name, &profile_name)?;
+            output::say(format!(
+                "{} Secret '{}' saved to {} (profile: {})",
+                "✓".green(),
+                name,
+                backend.name(),
+                profile_name
+            ));
+            created.push(name.clone());
         }
 
-        println!(
-            "\nSummary: {} imported, {} already exists, {} not found in source",
-            imported.to_string().green(),
-            already_exists.to_string().yellow(),
-            not_found.to_string().red()
+        notify::send(
+            &self.config.notify,
+            &Change {
+                action: Action::Created,
+                project,
+                profile: &profile_name,
+                keys: created,
+                provider: backend.name(),
+            },
         );
 
-        if imported > 0 {
-            println!(
-                "\n{} Successfully imported {} secrets from {} to {}",
-                "✓".green(),
-                imported,
-                from_provider,
-                to_provider.name()
-            );
+        if !still_missing.is_empty() {
+            return Err(SecretSpecError::RequiredSecretMissing(
+                still_missing.join(", "),
+            ));
         }
-
         Ok(())
     }
 
-    /// Validates all secrets in the specification
+    /// Lists the declared secrets with what the provider records about them
+    ///
+    /// Each secret of the current profile is shown as set or missing. Set
+    /// secrets show their revision date, version and creator as far as the
+    /// provider reports them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized or reading a
+    /// secret fails
+    pub fn list(&self) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let project = &self.config.project.name;
+        let profile_name = self.resolve_profile(None);
+
+        output::say(format!(
+            "Secrets in {} using {} (profile: {}):\n",
+            project.bold(),
+            backend.name().blue(),
+            profile_name.cyan()
+        ));
+
+        let names = self.selected_secrets(&profile_name)?;
+        let rows = Rows::new(names.iter().map(String::as_str));
+        let mut progress = Progress::new("Listing", names.len());
+        for name in &names {
+            progress.step(name);
+            let Some(config) = self.resolve_secret_config(name, None) else {
+                continue;
+            };
+            let description = config.description.as_deref().unwrap_or("No description");
+            if Secret::pattern_prefix(name).is_some() {
+                rows.print(
+                    Mark::Optional,
+                    name,
+                    description,
+                    Some(Mark::Optional.paint("(pattern)")),
+                );
+                continue;
+            }
+
+            // Fields of an item carry no metadata of their own
+            let metadata = match (&config.item, config.fields.get(name)) {
+                (Some(item), Some(field)) => backend
+                    .get_fields(project, item, &[field], &profile_name)?
+                    .remove(field)
+                    .map(|_| SecretMetadata::default()),
+                _ => backend
+                    .get_with_metadata(project, name, &profile_name)?
+                    .map(|secret| secret.metadata),
+            };
+            match metadata {
+                Some(metadata) if metadata.is_empty() => {
+                    rows.print(Mark::Found, name, description, None)
+                }
+                Some(metadata) => rows.print(
+                    Mark::Found,
+                    name,
+                    description,
+                    Some(format!("({})", metadata).dimmed()),
+                ),
+                None if config.default.is_some() => rows.print(
+                    Mark::Default,
+                    name,
+                    description,
+                    Some(Mark::Default.paint("(has default)")),
+                ),
+                None => rows.print(
+                    Mark::Missing,
+                    name,
+                    description,
+                    Some(Mark::Missing.paint("(not set)")),
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the validated secrets in the lockfile, or verifies them in frozen mode
+    ///
+    /// # Arguments
+    ///
+    /// * `validated` - The secrets resolved by `check`
+    /// * `path` - The lockfile path
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lockfile cannot be read or written, or in frozen
+    /// mode if the lockfile is missing or the secrets have drifted
+    pub(crate) fn lock(&self, validated: &ValidatedSecrets, path: &Path) -> Result<()> {
+        let existing = Lockfile::load(path)?;
+        let profile = &validated.resolved.profile;
+
+        if self.frozen {
+            let lockfile = existing.ok_or_else(|| {
+                SecretSpecError::LockfileDrift(format!(
+                    "{} not found; run 'secretspec check' without --frozen to create it",
+                    path.display()
+                ))
+            })?;
+            let current = self.locked_entries(&lockfile, validated)?;
+            let drift = lockfile.diff(profile, &current);
+            if !drift.is_empty() {
+                let details = drift
+                    .iter()
+                    .map(|d| format!("  - {}", d))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return Err(SecretSpecError::LockfileDrift(format!(
+                    "secrets for profile '{}' differ from {}:\n{}",
+                    profile,
+                    path.display(),
+                    details
+                )));
+            }
+            output::say(format!("{} Secrets match {}", "✓".green(), path.display()));
+            return Ok(());
+        }
+
+        let mut lockfile = match existing {
+            Some(lockfile) => lockfile,
+            None => Lockfile::new()?,
+        };
+        let current = self.locked_entries(&lockfile, validated)?;
+        if lockfile.profiles.get(profile) != Some(&current) {
+            lockfile.profiles.insert(profile.clone(), current);
+            lockfile.save(path)?;
+        }
+        Ok(())
+    }
+
+    /// Computes the lockfile entries for the validated secrets
+    fn locked_entries(
+        &self,
+        lockfile: &Lockfile,
+        validated: &ValidatedSecrets,
+    ) -> Result<BTreeMap<String, LockedSecret>> {
+        // Credentials derived from an assumed role change on every run
+        let derived = self
+            .config
+            .assume_role_for(&validated.resolved.profile)
+            .map_or(&[][..], |_| &sts::DERIVED_SECRETS[..]);
+        let secrets: HashMap<String, SecretString> = validated
+            .resolved
+            .secrets
+            .iter()
+            .filter(|(name, _)| !derived.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        let providers = secrets
+            .keys()
+            .filter(|name| !validated.with_defaults.iter().any(|(n, _)| n == *name))
+            .map(|name| (name.clone(), validated.resolved.provider.clone()))
+            .collect();
+        let mut entries = lockfile.lock_secrets(&secrets, &providers)?;
+
+        // Secrets outside the group filter or for other platforms weren't
+        // checked, so keep their entries
+        if let Some(locked) = lockfile.profiles.get(&validated.resolved.profile) {
+            let profile = &validated.resolved.profile;
+            let declared = self.declared_secrets(profile).unwrap_or_default();
+            let selected = self.selected_secrets(profile).unwrap_or_default();
+            for (name, entry) in locked {
+                if declared.contains(name) && !selected.contains(name) {
+                    entries.entry(name.clone()).or_insert_with(|| entry.clone());
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Takes over existing provider items for the declared secrets
+    ///
+    /// The provider is searched for items matching each secret of the
+    /// current profile that wasn't adopted yet. A single match is taken, and
+    /// the user picks among several. The IDs of the adopted items are
+    /// recorded in `secretspec.lock`, and from then on the secrets are read
+    /// and written by ID instead of found by name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The provider cannot be initialized or has no stable item IDs
+    /// - A secret matches several items but no terminal is attached
+    /// - The lockfile cannot be read or written
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.adopt().unwrap();
+    /// ```
+    pub fn adopt(&self) -> Result<()> {
+        self.adopt_into(&self.lockfile_path())
+    }
+
+    /// Adopts items like [`adopt`](Self::adopt), recording them in the
+    /// lockfile at `path`
+    pub(crate) fn adopt_into(&self, path: &Path) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let project = &self.config.project.name;
+
+        let mut lockfile = match Lockfile::load(path)? {
+            Some(lockfile) => lockfile,
+            None => Lockfile::new()?,
+        };
+        let items = lockfile.items.entry(profile.clone()).or_default();
+        let mut adopted = 0;
+        for name in self.selected_secrets(&profile)? {
+            let declared = self
+                .resolve_secret_config(&name, Some(&profile))
+                .is_some_and(|secret| secret.bitwarden_item_id.is_some());
+            if Secret::pattern_prefix(&name).is_some()
+                || items.contains_key(&name)
+                || (declared && backend.name() == "bitwarden")
+            {
+                continue;
+            }
+            let matches = backend.find_items(project, &name, &profile)?;
+            let item = match matches.as_slice() {
+                [] => {
+                    output::say(format!(
+                        "{} {} {}",
+                        "○".yellow(),
+                        name,
+                        "(no matching item)".yellow()
+                    ));
+                    continue;
+                }
+                [item] => item,
+                _ => {
+                    let options = matches.iter().map(ToString::to_string).collect();
+                    match prompt::select(&format!("Which item holds {}?", name), options)? {
+                        Some(index) => &matches[index],
+                        None => {
+                            output::say(format!(
+                                "{} {} {}",
+                                "○".yellow(),
+                                name,
+                                "(skipped)".yellow()
+                            ));
+                            continue;
+                        }
+                    }
+                }
+            };
+            output::say(format!("{} {} → {}", "✓".green(), name, item));
+            items.insert(name, item.id.clone());
+            adopted += 1;
+        }
+
+        if adopted > 0 {
+            lockfile.save(path)?;
+        }
+        output::say(format!(
+            "\nAdopted {} items from {} (profile: {})",
+            adopted.to_string().green(),
+            backend.name().blue(),
+            profile.cyan()
+        ));
+        Ok(())
+    }
+
+    /// Imports secrets from one provider to another
+    ///
+    /// This method copies all secrets defined in the specification from the
+    /// source provider to the default provider configured in the global settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `from_provider` - The provider specification to import from
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the import completes (even if some secrets were not found)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The source provider cannot be initialized
+    /// - The target provider cannot be initialized
+    /// - The target provider refuses writes
+    /// - Storage operations fail
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.import("dotenv://.env.production").unwrap();
+    /// ```
+    pub fn import(&self, from_provider: &str) -> Result<()> {
+        // Get the "to" provider from global config (default)
+        let to_provider = self.get_provider(None)?;
+
+        // Resolve profile (checks env var, then global config, then defaults to "default")
+        let profile_display = self.resolve_profile(None);
+
+        // Create the "from" provider
+        let from_provider_instance = self.source_provider(from_provider)?;
+
+        output::say(format!(
+            "Importing secrets from {} to {} (profile: {})...\n",
+            from_provider.blue(),
+            to_provider.name().blue(),
+            profile_display.cyan()
+        ));
+
+        // Get the profile configuration
+        let profile_config = self.config.profiles.get(&profile_display).ok_or_else(|| {
+            SecretSpecError::SecretNotFound(format!("Profile '{}' not found", profile_display))
+        })?;
+
+        to_provider.check_writable(&self.config.project.name, &profile_display)?;
+
+        let mut imported = Vec::new();
+        let mut already_exists = 0;
+        let mut not_found = 0;
+
+        // Process each secret in the profile
+        let rows = Rows::new(
+            profile_config
+                .secrets
+                .iter()
+                .filter(|(_, config)| config.item.is_none())
+                .map(|(name, _)| name.as_str()),
+        );
+        let mut progress = Progress::new("Importing", profile_config.secrets.len());
+        for (name, config) in &profile_config.secrets {
+            progress.step(name);
+            // Fields of an item stay in the item
+            if config.item.is_some() {
+                continue;
+            }
+            let description = config.description.as_deref().unwrap_or("No description");
+
+            // First check if the secret exists in the "from" provider
+            match from_provider_instance.get(&self.config.project.name, name, &profile_display)? {
+                Some(value) => {
+                    // Secret exists in "from" provider, check if it exists in "to" provider
+                    match to_provider.get(&self.config.project.name, name, &profile_display)? {
+                        Some(_) => {
+                            rows.print(
+                                Mark::Default,
+                                name,
+                                description,
+                                Some(Mark::Default.paint("(already exists in target)")),
+                            );
+                            already_exists += 1;
+                        }
+                        None => {
+                            // Secret doesn't exist in "to" provider, import it
+                            to_provider.set(
+                                &self.config.project.name,
+                                name,
+                                &value,
+                                &profile_display,
+                            )?;
+                            self.tag_expiry(to_provider.as_ref(), name, &profile_display)?;
+                            rows.print(Mark::Found, name, description, None);
+                            imported.push(name.clone());
+                        }
+                    }
+                }
+                None => {
+                    // Secret doesn't exist in "from" provider
+                    // Check if it exists in the "to" provider
+                    match to_provider.get(&self.config.project.name, name, &profile_display)? {
+                        Some(_) => {
+                            rows.print(
+                                Mark::Optional,
+                                name,
+                                description,
+                                Some(Mark::Optional.paint("(already in target, not in source)")),
+                            );
+                            already_exists += 1;
+                        }
+                        None => {
+                            rows.print(
+                                Mark::Missing,
+                                name,
+                                description,
+                                Some(Mark::Missing.paint("(not found in source)")),
+                            );
+                            not_found += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        output::say(format!(
+            "\nSummary: {} imported, {} already exists, {} not found in source",
+            imported.len().to_string().green(),
+            already_exists.to_string().yellow(),
+            not_found.to_string().red()
+        ));
+
+        if !imported.is_empty() {
+            output::say(format!(
+                "\n{} Successfully imported {} secrets from {} to {}",
+                "✓".green(),
+                imported.len(),
+                from_provider,
+                to_provider.name()
+            ));
+        }
+
+        imported.sort();
+        notify::send(
+            &self.config.notify,
+            &Change {
+                action: Action::Created,
+                project: &self.config.project.name,
+                profile: &profile_display,
+                keys: imported,
+                provider: to_provider.name(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Pushes the resolved secrets of the current profile to another provider
+    ///
+    /// Unlike [`import`](Self::import), which copies stored values, this
+    /// writes what the app would see: defaults, references and transforms
+    /// are applied first. All values are written in one operation, so
+    /// deployment targets like `fly://my-app` restart the app once. With
+    /// `dry_run` the secrets are listed without being written.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - The provider specification to push to
+    /// * `dry_run` - Whether to list the secrets without writing them
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - A required secret is missing
+    /// - The target provider cannot be initialized or refuses writes
+    /// - Writing the values fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.sync("fly://my-app", false).unwrap();
+    /// ```
+    pub fn sync(&self, to: &str, dry_run: bool) -> Result<()> {
+        let project = &self.config.project.name;
+        let validated = self.validate()?.map_err(|errors| {
+            SecretSpecError::RequiredSecretMissing(errors.missing_required.join(", "))
+        })?;
+        let profile = validated.resolved.profile;
+        let mut values: Vec<(String, SecretString)> =
+            validated.resolved.secrets.into_iter().collect();
+        values.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let target = self.source_provider(to)?;
+        target.check_writable(project, &profile)?;
+        let existing: BTreeSet<String> = if target.allows_list() {
+            target.list_keys(project, &profile)?.into_iter().collect()
+        } else {
+            BTreeSet::new()
+        };
+
+        output::say(format!(
+            "Syncing secrets to {} (profile: {})...\n",
+            to.blue(),
+            profile.cyan()
+        ));
+        let rows = Rows::new(values.iter().map(|(name, _)| name.as_str()));
+        for (name, _) in &values {
+            let note = if existing.contains(name) {
+                "(updated)"
+            } else {
+                "(added)"
+            };
+            rows.print(Mark::Found, name, "", Some(Mark::Default.paint(note)));
+        }
+
+        if dry_run {
+            output::say(format!(
+                "\n{} Would push {} secrets to {}",
+                "○".yellow(),
+                values.len(),
+                to
+            ));
+            return Ok(());
+        }
+        target.set_many(project, &values, &profile)?;
+        output::say(format!(
+            "\n{} Synced {} secrets to {} (profile: {})",
+            "✓".green(),
+            values.len(),
+            to,
+            profile
+        ));
+
+        notify::send(
+            &self.config.notify,
+            &Change {
+                action: Action::Changed,
+                project,
+                profile: &profile,
+                keys: values.into_iter().map(|(name, _)| name).collect(),
+                provider: target.name(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Re-encrypts every secret of the current profile
+    ///
+    /// Each stored secret is read and written back through the provider, so
+    /// `enc+` and `gitvault` providers encrypt it to the recipients listed in
+    /// `secretspec.toml`, and `gpg` providers to those in `.gpg-id` files. Run
+    /// this after removing a recipient to revoke their access to the stored
+    /// values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The provider cannot be initialized or is read-only
+    /// - The specified profile doesn't exist
+    /// - A secret cannot be decrypted with the current identity
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.rekey().unwrap();
+    /// ```
+    pub fn rekey(&self) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+
+        if !backend.allows_set() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' is read-only and cannot be rekeyed",
+                backend.name()
+            )));
+        }
+        backend.check_writable(&self.config.project.name, &profile)?;
+
+        let profile_config = self.config.profiles.get(&profile).ok_or_else(|| {
+            SecretSpecError::SecretNotFound(format!("Profile '{}' not found", profile))
+        })?;
+
+        let mut rekeyed = Vec::new();
+        let names: Vec<&str> = profile_config
+            .secrets
+            .iter()
+            .filter(|(_, secret)| secret.item.is_none())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let rows = Rows::new(names.iter().copied());
+        let mut progress = Progress::new("Rekeying", names.len());
+        for name in names {
+            progress.step(name);
+            match backend.get(&self.config.project.name, name, &profile)? {
+                Some(value) => {
+                    backend.set(&self.config.project.name, name, &value, &profile)?;
+                    rows.print(Mark::Found, name, "", None);
+                    rekeyed.push(name.to_string());
+                }
+                None => rows.print(
+                    Mark::Default,
+                    name,
+                    "",
+                    Some(Mark::Default.paint("(not set)")),
+                ),
+            }
+        }
+
+        output::say(format!(
+            "\nRekeyed {} secrets in {} (profile: {})",
+            rekeyed.len().to_string().green(),
+            backend.name().blue(),
+            profile.cyan()
+        ));
+
+        rekeyed.sort();
+        notify::send(
+            &self.config.notify,
+            &Change {
+                action: Action::Rotated,
+                project: &self.config.project.name,
+                profile: &profile,
+                keys: rekeyed,
+                provider: backend.name(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Lists who can access the secrets of the current profile
+    ///
+    /// Only providers with an access-control API (OnePassword vaults and
+    /// Bitwarden organization collections) support this.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized or does not
+    /// manage access
+    pub fn access(&self) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let entries = backend.list_access(&self.config.project.name, &profile)?;
+
+        if entries.is_empty() {
+            output::say(format!(
+                "No principals have access in {}",
+                backend.name().blue()
+            ));
+        }
+        for entry in entries {
+            output::say(format!(
+                "{} {}",
+                entry.principal,
+                format!("({})", entry.permissions.join(", ")).dimmed()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Grants a principal read access to the secrets of the current profile
+    ///
+    /// Prints the minimal policy the provider applies. With `dry_run`, the
+    /// policy is only printed.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The user to grant access to, as named by the provider
+    /// * `dry_run` - Whether to print the policy without applying it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized, does not manage
+    /// access, or does not know the principal
+    pub fn grant(&self, principal: &str, dry_run: bool) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let project = &self.config.project.name;
+
+        let policy = if dry_run {
+            backend.access_policy(project, &profile, principal)?
+        } else {
+            backend.grant(project, &profile, principal)?
+        };
+
+        output::say(&policy.document);
+        if dry_run {
+            output::say(format!(
+                "\n{} Would grant {} read access to {} in {}",
+                "○".yellow(),
+                policy.principal,
+                policy.scope,
+                backend.name().blue()
+            ));
+        } else {
+            output::say(format!(
+                "\n{} Granted {} read access to {} in {}",
+                "✓".green(),
+                policy.principal,
+                policy.scope,
+                backend.name().blue()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replaces a secret with a new value from its generator
+    ///
+    /// The secret's `generator` command is run and its output checked before
+    /// anything is written, so a failing generator leaves the old value in
+    /// place.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the secret to rotate
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The secret isn't declared or has no generator
+    /// - The generator fails, times out or prints an invalid value
+    /// - The provider cannot be initialized or cannot write the value
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.rotate("SESSION_KEY").unwrap();
+    /// ```
+    pub fn rotate(&self, name: &str) -> Result<()> {
+        let name = self.canonical_name(name);
+        let profile = self.resolve_profile(None);
+        let project = &self.config.project.name;
+
+        let Some(secret) = self.resolve_secret_config(&name, None) else {
+            return Err(self.undeclared(&name, &profile));
+        };
+        let Some(generator) = &secret.generator else {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "Secret '{}' has no generator to rotate it with, e.g. generator = {{ command = \"openssl rand -hex 32\" }}",
+                name
+            )));
+        };
+
+        let backend = self.get_provider(None)?;
+        backend.check_writable(project, &profile)?;
+        let value = generator::generate(&name, generator, project, &profile)?;
+        let login = dbfiles::Login::parse(value.expose_secret());
+        let value = self.seal(&name, &profile, value)?;
+        backend.set(project, &name, &value, &profile)?;
+        self.tag_expiry(backend.as_ref(), &name, &profile)?;
+
+        output::say(format!(
+            "{} Secret '{}' rotated in {} (profile: {})",
+            "✓".green(),
+            name,
+            backend.name(),
+            profile
+        ));
+        // Database clients would otherwise keep the old password
+        if let Some(login) = login {
+            for path in dbfiles::refresh(&login)? {
+                output::say(format!("{} Updated {}", "✓".green(), path.display()));
+            }
+        }
+        notify::send(
+            &self.config.notify,
+            &Change {
+                action: Action::Rotated,
+                project,
+                profile: &profile,
+                keys: vec![name],
+                provider: backend.name(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Moves a stored secret to a new name, and optionally another profile
+    ///
+    /// The value is read, written under the new name, read back to verify it,
+    /// and only then deleted under the old name, so a failure part way never
+    /// loses it. Metadata the provider records itself, such as revision
+    /// history, starts over under the new name.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The name the secret is stored under
+    /// * `to` - The name to store it under
+    /// * `to_profile` - The profile to move it to, or `None` to keep the
+    ///   current one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The provider cannot be initialized or cannot write or delete secrets
+    /// - No secret is stored under `from`, or one is already stored under `to`
+    /// - The value read back differs from the one written
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.rename("STRIPE_KEY", "STRIPE_SECRET_KEY", None).unwrap();
+    /// ```
+    pub fn rename(&self, from: &str, to: &str, to_profile: Option<&str>) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let project = &self.config.project.name;
+        let profile = self.resolve_profile(None);
+        let target = to_profile.map_or_else(|| profile.clone(), str::to_string);
+
+        if from == to && profile == target {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "Secret '{}' would be moved onto itself",
+                from
+            )));
+        }
+        if !backend.allows_delete() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' cannot delete secrets, so they can't be moved",
+                backend.name()
+            )));
+        }
+        backend.check_writable(project, &target)?;
+
+        let Some(secret) = backend.get_with_metadata(project, from, &profile)? else {
+            return Err(SecretSpecError::SecretNotFound(format!(
+                "Secret '{}' is not stored in {} (profile: {})",
+                from,
+                backend.name(),
+                profile
+            )));
+        };
+        if backend.get(project, to, &target)?.is_some() {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "Secret '{}' is already stored in {} (profile: {})",
+                to,
+                backend.name(),
+                target
+            )));
+        }
+        if self.resolve_secret_config(to, Some(&target)).is_none() {
+            warning::warn(format!(
+                "'{}' is not declared in profile '{}' of secretspec.toml",
+                to, target
+            ));
+        }
+
+        backend.set(project, to, &secret.value, &target)?;
+        let written = backend.get(project, to, &target)?;
+        if written.as_ref().map(|value| value.expose_secret()) != Some(secret.value.expose_secret())
+        {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "'{}' read back from {} differs from the value written; '{}' was kept",
+                to,
+                backend.name(),
+                from
+            )));
+        }
+        backend.delete(project, from, &profile)?;
+
+        let destination = if target == profile {
+            to.to_string()
+        } else {
+            format!("{} (profile: {})", to, target)
+        };
+        output::say(format!(
+            "{} Moved '{}' to '{}' in {} (profile: {})",
+            "✓".green(),
+            from,
+            destination,
+            backend.name(),
+            profile
+        ));
+        if !secret.metadata.is_empty() {
+            output::say(format!(
+                "{}",
+                format!("  {} was {}", from, secret.metadata).dimmed()
+            ));
+        }
+
+        for (action, key, profile) in [
+            (Action::Created, to, &target),
+            (Action::Deleted, from, &profile),
+        ] {
+            notify::send(
+                &self.config.notify,
+                &Change {
+                    action,
+                    project,
+                    profile,
+                    keys: vec![key.to_string()],
+                    provider: backend.name(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Deletes a stored secret
+    ///
+    /// Providers with a trash, such as Bitwarden, move the secret there so it
+    /// can be brought back with [`restore`](Self::restore); `permanent`
+    /// deletes it for good instead. Other providers always delete for good.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the secret to delete
+    /// * `permanent` - Whether to skip the trash
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized, cannot delete
+    /// secrets, or the deletion fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.delete("LEGACY_API_TOKEN", false).unwrap();
+    /// ```
+    pub fn delete(&self, name: &str, permanent: bool) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let project = &self.config.project.name;
+        let profile = self.resolve_profile(None);
+        let name = self.canonical_name(name);
+
+        if !backend.allows_delete() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' cannot delete secrets",
+                backend.name()
+            )));
+        }
+        let trashed = !permanent && backend.allows_restore();
+        if trashed {
+            backend.delete(project, &name, &profile)?;
+        } else {
+            backend.purge(project, &name, &profile)?;
+        }
+
+        output::say(format!(
+            "{} Secret '{}' {} {} (profile: {})",
+            "✓".green(),
+            name,
+            if trashed {
+                "moved to the trash in"
+            } else {
+                "deleted from"
+            },
+            backend.name(),
+            profile
+        ));
+        notify::send(
+            &self.config.notify,
+            &Change {
+                action: Action::Deleted,
+                project,
+                profile: &profile,
+                keys: vec![name],
+                provider: backend.name(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Brings back a secret [`delete`](Self::delete) moved to the provider's
+    /// trash
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the secret to restore
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized, has no trash,
+    /// or the secret isn't in it
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.restore("LEGACY_API_TOKEN").unwrap();
+    /// ```
+    pub fn restore(&self, name: &str) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let project = &self.config.project.name;
+        let profile = self.resolve_profile(None);
+        let name = self.canonical_name(name);
+
+        if !backend.allows_restore() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' has no trash to restore secrets from",
+                backend.name()
+            )));
+        }
+        backend.restore(project, &name, &profile)?;
+
+        output::say(format!(
+            "{} Secret '{}' restored in {} (profile: {})",
+            "✓".green(),
+            name,
+            backend.name(),
+            profile
+        ));
+        notify::send(
+            &self.config.notify,
+            &Change {
+                action: Action::Created,
+                project,
+                profile: &profile,
+                keys: vec![name],
+                provider: backend.name(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Lists the versions the provider keeps of a secret, newest first
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the secret
+    ///
+    /// # Returns
+    ///
+    /// What the provider recorded about each version; empty if the secret
+    /// was never stored
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized, doesn't keep
+    /// versions, or reading them fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// for version in spec.history("DATABASE_URL").unwrap() {
+    ///     println!("{}", version);
+    /// }
+    /// ```
+    pub fn history(&self, name: &str) -> Result<Vec<SecretMetadata>> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let name = self.canonical_name(name);
+        backend.history(&self.config.project.name, &name, &profile)
+    }
+
+    /// Returns the keys stored for the current profile that the spec doesn't
+    /// declare
+    ///
+    /// A key counts as declared if any profile declares it, reads it as an
+    /// item, or has a pattern matching it, since some providers store every
+    /// profile's secrets together.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized or cannot list
+    /// its secrets
+    pub(crate) fn orphans(&self, backend: &dyn ProviderTrait) -> Result<Vec<String>> {
+        if !backend.allows_list() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' cannot list secrets, so orphans can't be found",
+                backend.name()
+            )));
+        }
+
+        let mut declared = BTreeSet::new();
+        let mut prefixes = Vec::new();
+        for (name, secret) in self
+            .config
+            .profiles
+            .values()
+            .flat_map(|profile| &profile.secrets)
+        {
+            match Secret::pattern_prefix(name) {
+                Some(prefix) => prefixes.push(prefix),
+                None => {
+                    declared.insert(name.as_str());
+                    declared.extend(secret.item.as_deref());
+                    declared.extend(secret.exported_names(name));
+                }
+            }
+        }
+
+        let profile = self.resolve_profile(None);
+        let mut orphans: Vec<String> = backend
+            .list_keys(&self.config.project.name, &profile)?
+            .into_iter()
+            .filter(|key| {
+                // Expiry tags belong to the secret they tag
+                let key = ttl::tagged_key(key).unwrap_or(key);
+                !declared.contains(key) && !prefixes.iter().any(|prefix| key.starts_with(prefix))
+            })
+            .collect();
+        orphans.sort();
+        orphans.dedup();
+        Ok(orphans)
+    }
+
+    /// Deletes the secrets stored for the current profile that the spec no
+    /// longer declares
+    ///
+    /// Each orphaned key is listed, and deleted once the user confirms it.
+    /// With `dry_run` the keys are only listed; with `yes` they are deleted
+    /// without asking.
+    ///
+    /// # Arguments
+    ///
+    /// * `dry_run` - Whether to list the orphans without deleting them
+    /// * `yes` - Whether to delete every orphan without asking
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The provider cannot be initialized, list or delete secrets
+    /// - Confirmation is needed but no terminal is attached
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.prune(true, false).unwrap();
+    /// ```
+    pub fn prune(&self, dry_run: bool, yes: bool) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let project = &self.config.project.name;
+
+        if !dry_run && !backend.allows_delete() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' cannot delete secrets",
+                backend.name()
+            )));
+        }
+        let orphans = self.orphans(backend.as_ref())?;
+        if orphans.is_empty() {
+            output::say(format!(
+                "{} No orphaned secrets in {} (profile: {})",
+                "✓".green(),
+                backend.name().blue(),
+                profile.cyan()
+            ));
+            return Ok(());
+        }
+
+        if dry_run || !yes {
+            output::say(format!(
+                "Secrets in {} (profile: {}) not declared in secretspec.toml:\n",
+                backend.name().blue(),
+                profile.cyan()
+            ));
+            for key in &orphans {
+                output::say(format!("{} {}", "✗".red(), key));
+            }
+            output::say("");
+        }
+        if dry_run {
+            output::say(format!(
+                "{} Would delete {} orphaned secrets",
+                "○".yellow(),
+                orphans.len()
+            ));
+            return Ok(());
+        }
+
+        let mut deleted = Vec::new();
+        for key in orphans {
+            if !yes && !prompt::confirm(&format!("Delete {}?", key), false)? {
+                output::say(format!("{} {} {}", "○".yellow(), key, "(kept)".yellow()));
+                continue;
+            }
+            backend.delete(project, &key, &profile)?;
+            output::say(format!("{} {}", "✓".green(), key));
+            deleted.push(key);
+        }
+
+        output::say(format!(
+            "\nDeleted {} orphaned secrets from {} (profile: {})",
+            deleted.len().to_string().green(),
+            backend.name().blue(),
+            profile.cyan()
+        ));
+        if !deleted.is_empty() {
+            notify::send(
+                &self.config.notify,
+                &Change {
+                    action: Action::Deleted,
+                    project,
+                    profile: &profile,
+                    keys: deleted,
+                    provider: backend.name(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Deletes the secrets of the current profile whose ttl has passed
+    ///
+    /// Secrets declared with a `ttl` are tagged with their expiry when
+    /// written; this finds the tags, deletes each expired secret together
+    /// with its tag, and lists the ones still alive. With `dry_run` nothing
+    /// is deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `dry_run` - Whether to list the expired secrets without deleting them
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized, list, read or
+    /// delete secrets
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// spec.gc(false).unwrap();
+    /// ```
+    pub fn gc(&self, dry_run: bool) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let project = &self.config.project.name;
+
+        if !backend.allows_list() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' cannot list secrets, so expired ones can't be found",
+                backend.name()
+            )));
+        }
+        if !dry_run && !backend.allows_delete() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' cannot delete secrets",
+                backend.name()
+            )));
+        }
+
+        let mut tagged: Vec<String> = backend
+            .list_keys(project, &profile)?
+            .iter()
+            .filter_map(|key| ttl::tagged_key(key))
+            .map(str::to_string)
+            .collect();
+        tagged.sort();
+        tagged.dedup();
+        if tagged.is_empty() {
+            output::say(format!(
+                "{} No secrets with a ttl in {} (profile: {})",
+                "✓".green(),
+                backend.name().blue(),
+                profile.cyan()
+            ));
+            return Ok(());
+        }
+
+        let rows = Rows::new(tagged.iter().map(String::as_str));
+        let mut progress = Progress::new("Collecting", tagged.len());
+        let mut expired = Vec::new();
+        for key in tagged {
+            progress.step(&key);
+            let tag_key = ttl::tag_key(&key);
+            let Some(tag) = backend.get(project, &tag_key, &profile)? else {
+                continue;
+            };
+            let Some(remaining) = ttl::remaining(tag.expose_secret()) else {
+                warning::warn(format!(
+                    "'{}' is not an expiry time, so '{}' was kept",
+                    tag_key, key
+                ));
+                continue;
+            };
+            if remaining > 0 {
+                let note = format!("(expires in {})", ttl::format(remaining.unsigned_abs()));
+                rows.print(Mark::Default, &key, "", Some(Mark::Default.paint(&note)));
+                continue;
+            }
+
+            let note = format!("(expired {} ago)", ttl::format(remaining.unsigned_abs()));
+            if !dry_run {
+                if backend.get(project, &key, &profile)?.is_some() {
+                    backend.delete(project, &key, &profile)?;
+                }
+                backend.delete(project, &tag_key, &profile)?;
+            }
+            rows.print(Mark::Missing, &key, "", Some(Mark::Missing.paint(&note)));
+            expired.push(key);
+        }
+        drop(progress);
+
+        if dry_run {
+            output::say(format!(
+                "\n{} Would delete {} expired secrets",
+                "○".yellow(),
+                expired.len()
+            ));
+            return Ok(());
+        }
+        output::say(format!(
+            "\nDeleted {} expired secrets from {} (profile: {})",
+            expired.len().to_string().green(),
+            backend.name().blue(),
+            profile.cyan()
+        ));
+        if !expired.is_empty() {
+            notify::send(
+                &self.config.notify,
+                &Change {
+                    action: Action::Deleted,
+                    project,
+                    profile: &profile,
+                    keys: expired,
+                    provider: backend.name(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Counts the items each profile has stored in its provider and adds up
+    /// their size
+    ///
+    /// Every profile of the spec is measured, or only the profile set with
+    /// [`set_profile`](Self::set_profile). Profiles without a provider are
+    /// skipped. Values are read to measure them, so this is as slow as
+    /// reading every stored secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a provider cannot be initialized, or listing or
+    /// reading its secrets fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// for usage in spec.usage().unwrap() {
+    ///     println!("{}: {:?} items", usage.profile, usage.items);
+    /// }
+    /// ```
+    pub fn usage(&self) -> Result<Vec<Usage>> {
+        let project = &self.config.project.name;
+        let profiles: Vec<String> = match &self.profile {
+            Some(profile) => vec![profile.clone()],
+            None => {
+                let mut profiles: Vec<String> = self.config.profiles.keys().cloned().collect();
+                profiles.sort();
+                profiles
+            }
+        };
+
+        let mut usage = Vec::new();
+        for profile in profiles {
+            let backend = match self.profile_provider(None, &profile) {
+                Ok(backend) => backend,
+                Err(SecretSpecError::NoProviderConfigured) => continue,
+                Err(e) => return Err(e),
+            };
+            if !backend.allows_list() {
+                usage.push(Usage {
+                    profile,
+                    provider: backend.name(),
+                    items: None,
+                    bytes: None,
+                });
+                continue;
+            }
+
+            let keys = backend.list_keys(project, &profile)?;
+            let mut progress = Progress::new("Measuring", keys.len());
+            let mut bytes = 0;
+            for key in &keys {
+                progress.step(key);
+                if let Some(value) = backend.get(project, key, &profile)? {
+                    bytes += value.expose_secret().len() as u64;
+                }
+            }
+            drop(progress);
+            usage.push(Usage {
+                profile,
+                provider: backend.name(),
+                items: Some(keys.len()),
+                bytes: Some(bytes),
+            });
+        }
+        Ok(usage)
+    }
+
+    /// Checks which secrets are set in every profile
+    ///
+    /// Each profile is checked against the provider it uses, with up to
+    /// `defaults.concurrency` profiles at a time. A profile whose provider
+    /// can't be created or read is recorded with its error instead of
+    /// failing the whole check. Values are only checked for presence.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// let matrix = spec.check_all_profiles();
+    /// assert!(matrix.is_complete());
+    /// ```
+    pub fn check_all_profiles(&self) -> Matrix {
+        let mut profiles: Vec<&String> = self.config.profiles.keys().collect();
+        profiles.sort();
+        let concurrency = self
+            .global_config
+            .as_ref()
+            .and_then(|gc| gc.defaults.concurrency)
+            .unwrap_or(DEFAULT_CONCURRENCY)
+            .max(1);
+
+        let mut columns = Vec::new();
+        for chunk in profiles.chunks(concurrency) {
+            thread::scope(|scope| {
+                let checks: Vec<_> = chunk
+                    .iter()
+                    .map(|profile| (profile, scope.spawn(|| self.profile_column(profile))))
+                    .collect();
+                for (profile, check) in checks {
+                    columns.push(check.join().unwrap_or_else(|_| {
+                        ProfileColumn::failed(
+                            profile,
+                            None,
+                            SecretSpecError::ProviderOperationFailed(
+                                "checking the profile panicked".to_string(),
+                            ),
+                        )
+                    }));
+                }
+            });
+        }
+        Matrix { profiles: columns }
+    }
+
+    /// Checks which secrets of a profile are set, for
+    /// [`check_all_profiles`](Self::check_all_profiles)
+    fn profile_column(&self, profile: &str) -> ProfileColumn {
+        let backend = match self.profile_provider(None, profile) {
+            Ok(backend) => backend,
+            Err(e) => return ProfileColumn::failed(profile, None, e),
+        };
+        let provider = Some(backend.name().to_string());
+        match self.presence(backend.as_ref(), profile) {
+            Ok(secrets) => ProfileColumn {
+                profile: profile.to_string(),
+                provider,
+                error: None,
+                secrets,
+            },
+            Err(e) => ProfileColumn::failed(profile, provider, e),
+        }
+    }
+
+    /// Returns whether each secret of a profile is stored, falls back to its
+    /// default or is missing
+    ///
+    /// Unlike resolving, this never asks for a security key touch: values
+    /// with `hardware_confirm` count as set without being unsealed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profile doesn't exist or the provider can't
+    /// be read
+    fn presence(
+        &self,
+        backend: &dyn ProviderTrait,
+        profile: &str,
+    ) -> Result<BTreeMap<String, Presence>> {
+        let project = &self.config.project.name;
+        let names = self.selected_secrets(profile)?;
+        let role = self.config.assume_role_for(profile);
+        let listed = if names
+            .iter()
+            .any(|name| Secret::pattern_prefix(name).is_some())
+            && backend.allows_list()
+        {
+            backend.list_keys(project, profile)?
+        } else {
+            Vec::new()
+        };
+
+        let mut presence = BTreeMap::new();
+        let mut unset = Vec::new();
+        for name in names {
+            if role.is_some() && sts::DERIVED_SECRETS.contains(&name.as_str()) {
+                continue;
+            }
+            let secret = self
+                .resolve_secret_config(&name, Some(profile))
+                .expect("Secret should exist in config since the profile declares it");
+            let stored = match (
+                Secret::pattern_prefix(&name),
+                &secret.item,
+                secret.fields.get(&name),
+            ) {
+                (Some(prefix), _, _) => listed
+                    .iter()
+                    .any(|key| key.len() > prefix.len() && key.starts_with(prefix)),
+                (None, Some(item), Some(field)) => backend
+                    .get_fields(project, item, &[field], profile)?
+                    .contains_key(field),
+                _ => backend.get(project, &name, profile)?.is_some(),
+            };
+            if stored {
+                presence.insert(name, Presence::Set);
+            } else if secret.default.is_some() {
+                presence.insert(name, Presence::Default);
+            } else {
+                unset.push((name, secret));
+            }
+        }
+        // Conditional requirements depend on which other secrets are set
+        let set: BTreeSet<String> = presence.keys().cloned().collect();
+        for (name, secret) in unset {
+            let state = if secret.is_required(profile, |other| set.contains(other)) {
+                Presence::Missing
+            } else {
+                Presence::Unset
+            };
+            presence.insert(name, state);
+        }
+        Ok(presence)
+    }
+
+    /// Analyzes the strength and reuse of the stored secrets
+    ///
+    /// Reads the values of the current profile, or of every profile unless
+    /// one was set, and reports placeholders like `changeme`, short and
+    /// guessable values, and values stored for several secrets. Defaults
+    /// from the spec are only checked for placeholders. Values are never
+    /// part of the findings.
+    ///
+    /// With `hibp`, secrets declared with `type = "password"` are also
+    /// checked against known breaches with the Have I Been Pwned range API,
+    /// which only receives the first five characters of each password's
+    /// SHA-1 digest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a profile's provider can't be created or read, or
+    /// the Have I Been Pwned API can't be reached
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// for finding in spec.analyze(false).unwrap() {
+    ///     println!("{:?}", finding);
+    /// }
+    /// ```
+    pub fn analyze(&self, hibp: bool) -> Result<Vec<Finding>> {
+        self.analyze_with(hibp.then_some(&SystemRunner as &dyn CommandRunner))
+    }
+
+    /// Analyzes the stored secrets, looking passwords up with `hibp_runner`
+    /// if given, the work of [`analyze`](Self::analyze)
+    pub(crate) fn analyze_with(
+        &self,
+        hibp_runner: Option<&dyn CommandRunner>,
+    ) -> Result<Vec<Finding>> {
+        let samples = self.samples()?;
+        let mut findings = analyze::analyze(&samples);
+        if let Some(runner) = hibp_runner {
+            findings.extend(hibp::breached(&samples, runner)?);
+        }
+        Ok(findings)
+    }
+
+    /// Reads the values [`analyze`](Self::analyze) looks at
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a profile's provider can't be created or read
+    fn samples(&self) -> Result<Vec<Sample>> {
+        let project = &self.config.project.name;
+        let profiles: Vec<String> = match &self.profile {
+            Some(profile) => vec![profile.clone()],
+            None => {
+                let mut profiles: Vec<String> = self.config.profiles.keys().cloned().collect();
+                profiles.sort();
+                profiles
+            }
+        };
+
+        let mut samples = Vec::new();
+        for profile in profiles {
+            let backend = match self.profile_provider(None, &profile) {
+                Ok(backend) => backend,
+                Err(SecretSpecError::NoProviderConfigured) => continue,
+                Err(e) => return Err(e),
+            };
+            // Identifies where values are stored, for telling reuse apart
+            // from profiles sharing a provider
+            let provider = match &self.provider_instance {
+                Some(instance) => instance.name().to_string(),
+                None => self.profile_provider_spec(None, &profile)?,
+            };
+            let names = self.selected_secrets(&profile)?;
+            let mut progress = Progress::new("Analyzing", names.len());
+            for name in names {
+                progress.step(&name);
+                if Secret::pattern_prefix(&name).is_some() {
+                    continue;
+                }
+                let secret = self
+                    .resolve_secret_config(&name, Some(&profile))
+                    .expect("Secret should exist in config since the profile declares it");
+                // Sealed values can only be read with a touch, and their
+                // stored form says nothing about the secret
+                if secret.hardware_confirm {
+                    continue;
+                }
+                let stored = match (&secret.item, secret.fields.get(&name)) {
+                    (Some(item), Some(field)) => backend
+                        .get_fields(project, item, &[field], &profile)?
+                        .remove(field),
+                    _ => backend.get(project, &name, &profile)?,
+                };
+                let location = Location {
+                    profile: profile.clone(),
+                    secret: name,
+                };
+                match (stored, &secret.default) {
+                    (Some(value), _) => samples.push(Sample {
+                        location,
+                        provider: Some(provider.clone()),
+                        kind: secret.kind,
+                        value,
+                    }),
+                    (None, Some(default)) if Reference::parse(default)?.is_none() => {
+                        samples.push(Sample {
+                            location,
+                            provider: None,
+                            kind: secret.kind,
+                            value: SecretString::from(default.as_str()),
+                        })
+                    }
+                    (None, _) => {}
+                }
+            }
+        }
+        Ok(samples)
+    }
+
+    /// Builds the provider secrets are imported from
+    fn source_provider(&self, spec: &str) -> Result<Box<dyn ProviderTrait>> {
+        Ok(ProviderBuilder::from_spec(spec)?
+            .layer(|p: Box<dyn ProviderTrait>| self.throttle(p))
+            .chunk()
+            .layer(TracedProvider::wrap)
+            .build())
+    }
+
+    /// Plans an [`import`](Self::import) without changing anything
+    ///
+    /// The plan creates the declared secrets that are stored in
+    /// `from_provider` but not in the current provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either provider cannot be initialized, the profile
+    /// doesn't exist, or reading a secret fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// let plan = spec.plan_import("dotenv://.env.production").unwrap();
+    /// std::fs::write("plan.json", plan.to_json().unwrap()).unwrap();
+    /// ```
+    pub fn plan_import(&self, from_provider: &str) -> Result<Plan> {
+        let backend = self.get_provider(None)?;
+        let source = self.source_provider(from_provider)?;
+        let profile = self.resolve_profile(None);
+        let project = &self.config.project.name;
+        let profile_config = self.config.profiles.get(&profile).ok_or_else(|| {
+            SecretSpecError::SecretNotFound(format!("Profile '{}' not found", profile))
+        })?;
+
+        let mut changes = Vec::new();
+        for (name, config) in &profile_config.secrets {
+            // Fields of an item stay in the item
+            if config.item.is_some() {
+                continue;
+            }
+            if source.get(project, name, &profile)?.is_some()
+                && backend.get(project, name, &profile)?.is_none()
+            {
+                changes.push(PlannedChange {
+                    action: PlanAction::Create,
+                    key: name.clone(),
+                });
+            }
+        }
+        Ok(Plan::new(
+            PlanOperation::Import,
+            project,
+            &profile,
+            backend.name(),
+            Some(from_provider),
+            changes,
+        ))
+    }
+
+    /// Plans a [`rekey`](Self::rekey) without changing anything
+    ///
+    /// The plan updates every stored secret of the profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized or is
+    /// read-only, the profile doesn't exist, or reading a secret fails
+    pub fn plan_rekey(&self) -> Result<Plan> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let project = &self.config.project.name;
+        if !backend.allows_set() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' is read-only and cannot be rekeyed",
+                backend.name()
+            )));
+        }
+        let profile_config = self.config.profiles.get(&profile).ok_or_else(|| {
+            SecretSpecError::SecretNotFound(format!("Profile '{}' not found", profile))
+        })?;
+
+        let mut changes = Vec::new();
+        for (name, _) in profile_config
+            .secrets
+            .iter()
+            .filter(|(_, secret)| secret.item.is_none())
+        {
+            if backend.get(project, name, &profile)?.is_some() {
+                changes.push(PlannedChange {
+                    action: PlanAction::Update,
+                    key: name.clone(),
+                });
+            }
+        }
+        Ok(Plan::new(
+            PlanOperation::Rekey,
+            project,
+            &profile,
+            backend.name(),
+            None,
+            changes,
+        ))
+    }
+
+    /// Plans a [`prune`](Self::prune) without changing anything
+    ///
+    /// The plan deletes the stored secrets the spec doesn't declare.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider cannot be initialized or cannot list
+    /// the secrets it stores
+    pub fn plan_prune(&self) -> Result<Plan> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let changes = self
+            .orphans(backend.as_ref())?
+            .into_iter()
+            .map(|key| PlannedChange {
+                action: PlanAction::Delete,
+                key,
+            })
+            .collect();
+        Ok(Plan::new(
+            PlanOperation::Prune,
+            &self.config.project.name,
+            &profile,
+            backend.name(),
+            None,
+            changes,
+        ))
+    }
+
+    /// Makes exactly the changes of a reviewed plan
+    ///
+    /// The plan must be for the current project, profile and provider. Every
+    /// change is checked before any is made: secrets to create must still be
+    /// missing from the provider and stored in the source, and secrets to
+    /// update or delete must still be stored. If anything changed since the
+    /// plan was made, nothing is changed and a new plan is needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SecretSpecError::Conflict`] if the plan is for another
+    /// target or the secrets changed since it was made, and an error if a
+    /// provider cannot be initialized or an operation fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::{Plan, Secrets};
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// let plan = Plan::from_json(&std::fs::read_to_string("plan.json").unwrap()).unwrap();
+    /// spec.apply_plan(&plan).unwrap();
+    /// ```
+    pub fn apply_plan(&self, plan: &Plan) -> Result<()> {
+        let backend = self.get_provider(None)?;
+        let profile = self.resolve_profile(None);
+        let project = &self.config.project.name;
+        plan.check_target(project, &profile, backend.name())?;
+
+        let source = match (plan.count(PlanAction::Create), &plan.source) {
+            (0, _) => None,
+            (_, Some(source)) => Some(self.source_provider(source)?),
+            (_, None) => {
+                return Err(SecretSpecError::InvalidInput(
+                    "The plan creates secrets but doesn't name the provider to read them from"
+                        .to_string(),
+                ));
+            }
+        };
+        if plan.count(PlanAction::Create) + plan.count(PlanAction::Update) > 0 {
+            if !backend.allows_set() {
+                return Err(SecretSpecError::ProviderOperationFailed(format!(
+                    "Provider '{}' is read-only",
+                    backend.name()
+                )));
+            }
+            backend.check_writable(project, &profile)?;
+        }
+        if plan.count(PlanAction::Delete) > 0 && !backend.allows_delete() {
+            return Err(SecretSpecError::ProviderOperationFailed(format!(
+                "Provider '{}' cannot delete secrets",
+                backend.name()
+            )));
+        }
+
+        // Check every change before making any
+        let changed = |key: &str, what: &str| {
+            SecretSpecError::Conflict(format!(
+                "Secret '{}' {} since the plan was made; make a new plan",
+                key, what
+            ))
+        };
+        let mut values = HashMap::new();
+        for change in &plan.changes {
+            let stored = backend.get(project, &change.key, &profile)?;
+            match change.action {
+                PlanAction::Create => {
+                    if stored.is_some() {
+                        return Err(changed(&change.key, "was stored"));
+                    }
+                    let value = source
+                        .as_ref()
+                        .and_then(|source| source.get(project, &change.key, &profile).transpose())
+                        .transpose()?
+                        .ok_or_else(|| changed(&change.key, "was removed from the source"))?;
+                    values.insert(change.key.as_str(), value);
+                }
+                PlanAction::Update => {
+                    let value = stored.ok_or_else(|| changed(&change.key, "was deleted"))?;
+                    values.insert(change.key.as_str(), value);
+                }
+                PlanAction::Delete => {
+                    if stored.is_none() {
+                        return Err(changed(&change.key, "was deleted"));
+                    }
+                }
+            }
+        }
+
+        let rows = Rows::new(plan.changes.iter().map(|change| change.key.as_str()));
+        let mut progress = Progress::new("Applying", plan.changes.len());
+        let mut created = Vec::new();
+        let mut updated = Vec::new();
+        let mut deleted = Vec::new();
+        for change in &plan.changes {
+            progress.step(&change.key);
+            let key = change.key.as_str();
+            match change.action {
+                PlanAction::Create => {
+                    backend.set(project, key, &values[key], &profile)?;
+                    self.tag_expiry(backend.as_ref(), key, &profile)?;
+                }
+                PlanAction::Update => backend.set(project, key, &values[key], &profile)?,
+                PlanAction::Delete => backend.delete(project, key, &profile)?,
+            }
+            let (note, applied) = match change.action {
+                PlanAction::Create => (Mark::Found.paint("(created)"), &mut created),
+                PlanAction::Update => (Mark::Default.paint("(updated)"), &mut updated),
+                PlanAction::Delete => (Mark::Missing.paint("(deleted)"), &mut deleted),
+            };
+            rows.print(Mark::Found, key, "", Some(note));
+            applied.push(change.key.clone());
+        }
+        drop(progress);
+
+        output::say(format!(
+            "\nApplied {} changes to {} (profile: {})",
+            plan.changes.len().to_string().green(),
+            backend.name().blue(),
+            profile.cyan()
+        ));
+
+        let update_action = match plan.operation {
+            PlanOperation::Rekey => Action::Rotated,
+            _ => Action::Changed,
+        };
+        for (action, keys) in [
+            (Action::Created, created),
+            (update_action, updated),
+            (Action::Deleted, deleted),
+        ] {
+            if !keys.is_empty() {
+                notify::send(
+                    &self.config.notify,
+                    &Change {
+                        action,
+                        project,
+                        profile: &profile,
+                        keys,
+                        provider: backend.name(),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates all secrets in the specification
     ///
     /// This method checks all secrets defined in the current profile (and default
     /// profile if different) and returns detailed information about their status.
@@ -834,62 +3819,199 @@ impl Secrets {
     /// }
     /// ```
     pub fn validate(&self) -> Result<std::result::Result<ValidatedSecrets, ValidationErrors>> {
-        let backend = self.get_provider(None)?;
+        self.validate_with(self.get_provider(None)?.as_ref())
+    }
+
+    /// Validates all secrets against an already opened provider
+    ///
+    /// See [`validate`](Self::validate).
+    fn validate_with(
+        &self,
+        backend: &dyn ProviderTrait,
+    ) -> Result<std::result::Result<ValidatedSecrets, ValidationErrors>> {
+        let mut span = telemetry::span("secretspec.resolve");
+        span.attr("secretspec.provider", backend.name());
+        span.attr("secretspec.profile", self.resolve_profile(None));
+        let result = self.resolve_all(backend);
+        match &result {
+            Err(e) => span.fail(e),
+            Ok(Err(errors)) => span.fail(errors),
+            Ok(Ok(_)) => {}
+        }
+        result
+    }
+
+    /// Resolves every secret of the profile, the work of
+    /// [`validate_with`](Self::validate_with).
+    fn resolve_all(
+        &self,
+        backend: &dyn ProviderTrait,
+    ) -> Result<std::result::Result<ValidatedSecrets, ValidationErrors>> {
         let mut secrets: HashMap<String, SecretString> = HashMap::new();
         let mut missing_required = Vec::new();
         let mut missing_optional = Vec::new();
         let mut with_defaults = Vec::new();
+        let mut unset = Vec::new();
 
         let profile_name = self.resolve_profile(None);
-        let profile_config = self.config.profiles.get(&profile_name).ok_or_else(|| {
-            SecretSpecError::SecretNotFound(format!("Profile '{}' not found", profile_name))
-        })?;
 
         // Collect all secrets to check - from current profile and default profile
-        let mut all_secrets = HashSet::new();
+        let all_secrets = self.selected_secrets(&profile_name)?;
 
-        // Add secrets from the current profile
-        for name in profile_config.secrets.keys() {
-            all_secrets.insert(name.clone());
-        }
+        // Secrets are resolved after the ones their defaults refer to, and
+        // looping references fail before anything is read
+        let graph = self.dependency_graph(&profile_name)?;
+        let ordered: Vec<&String> = graph
+            .order()?
+            .into_iter()
+            .filter_map(|name| all_secrets.get(name))
+            .collect();
+        // Values read so far, before transforms, for references to reuse
+        let mut fetched: HashMap<String, SecretString> = HashMap::new();
 
-        // If not the default profile, also add secrets from default profile
-        if profile_name != "default" {
-            if let Some(default_profile) = self.config.profiles.get("default") {
-                for name in default_profile.secrets.keys() {
-                    all_secrets.insert(name.clone());
-                }
+        // Credentials derived from an assumed role are never read from the provider
+        let role = self.config.assume_role_for(&profile_name);
+
+        // Patterns like FEATURE_FLAG_* match the keys stored with the provider
+        let patterns: Vec<&String> = all_secrets
+            .iter()
+            .filter(|name| Secret::pattern_prefix(name).is_some())
+            .collect();
+        let listed_keys = match patterns.first() {
+            None => Vec::new(),
+            Some(pattern) if !backend.allows_list() => {
+                return Err(SecretSpecError::InvalidInput(format!(
+                    "Provider '{}' cannot list secrets, which pattern '{}' needs",
+                    backend.name(),
+                    pattern
+                )));
             }
-        }
+            Some(_) => backend
+                .list_keys(&self.config.project.name, &profile_name)?
+                .into_iter()
+                .filter(|key| ttl::tagged_key(key).is_none())
+                .collect(),
+        };
+        // A key belongs to the most specific pattern it matches
+        let owning_pattern = |key: &str| {
+            patterns
+                .iter()
+                .filter(|pattern| {
+                    Secret::pattern_prefix(pattern)
+                        .is_some_and(|prefix| key.len() > prefix.len() && key.starts_with(prefix))
+                })
+                .max_by_key(|pattern| pattern.len())
+                .copied()
+        };
+
+        // Fields are read once per item, keyed by item name
+        let mut item_values: HashMap<String, HashMap<String, SecretString>> = HashMap::new();
 
         // Now check all secrets
-        for name in all_secrets {
+        let mut progress = Progress::new("Resolving", ordered.len());
+        for name in ordered {
+            progress.step(name);
+            let name = name.clone();
+            if role.is_some() && sts::DERIVED_SECRETS.contains(&name.as_str()) {
+                continue;
+            }
             let secret_config = self
                 .resolve_secret_config(&name, None)
                 .expect("Secret should exist in config since we're iterating over it");
-            let required = secret_config.required;
+
+            if Secret::pattern_prefix(&name).is_some() {
+                let mut matched = false;
+                for key in &listed_keys {
+                    // Declared secrets are checked on their own
+                    if all_secrets.contains(key) || owning_pattern(key) != Some(&name) {
+                        continue;
+                    }
+                    if let Some(value) =
+                        backend.get(&self.config.project.name, key, &profile_name)?
+                    {
+                        let value = transform::apply(key, &secret_config.transform, value)?;
+                        secrets.insert(key.clone(), value);
+                        matched = true;
+                    }
+                }
+                if !matched {
+                    unset.push((name, secret_config));
+                }
+                continue;
+            }
+
+            if let Some(item) = &secret_config.item {
+                let values = match item_values.entry(item.clone()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        // Other declarations may read more fields of the same item
+                        let fields: BTreeSet<String> = all_secrets
+                            .iter()
+                            .filter_map(|other| self.resolve_secret_config(other, None))
+                            .filter(|other| other.item.as_ref() == Some(item))
+                            .flat_map(|other| other.fields.into_values())
+                            .collect();
+                        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+                        entry.insert(backend.get_fields(
+                            &self.config.project.name,
+                            item,
+                            &fields,
+                            &profile_name,
+                        )?)
+                    }
+                };
+                match values.get(&secret_config.fields[&name]) {
+                    Some(value) => {
+                        let value =
+                            transform::apply(&name, &secret_config.transform, value.clone())?;
+                        secrets.insert(name, value);
+                    }
+                    None => unset.push((name, secret_config)),
+                }
+                continue;
+            }
+
             let default = secret_config.default.clone();
 
-            match backend.get(&self.config.project.name, &name, &profile_name)? {
+            match self.get_value(backend, &name, &secret_config, &profile_name)? {
                 Some(value) => {
+                    fetched.insert(name.clone(), value.clone());
+                    let value = transform::apply(&name, &secret_config.transform, value)?;
                     secrets.insert(name.clone(), value);
                 }
                 None => {
                     if let Some(default_value) = default {
-                        secrets.insert(
-                            name.clone(),
-                            SecretString::new(default_value.clone().into()),
-                        );
+                        let value = self.resolve_default(
+                            backend,
+                            &default_value,
+                            &fetched,
+                            &mut vec![name.clone()],
+                        )?;
+                        let value = transform::apply(&name, &secret_config.transform, value)?;
+                        secrets.insert(name.clone(), value);
                         with_defaults.push((name.clone(), default_value));
-                    } else if required {
-                        missing_required.push(name.clone());
                     } else {
-                        missing_optional.push(name.clone());
+                        unset.push((name, secret_config));
                     }
                 }
             }
         }
 
+        // Conditional requirements depend on which other secrets are set
+        for (name, secret_config) in unset {
+            if secret_config.is_required(&profile_name, |other| secrets.contains_key(other)) {
+                missing_required.push(name);
+            } else {
+                missing_optional.push(name);
+            }
+        }
+
+        if let Some(role) = role
+            && missing_required.is_empty()
+        {
+            secrets.extend(self.assumed_credentials(role, &secrets)?);
+        }
+
         // Check if there are any missing required secrets
         if !missing_required.is_empty() {
             Ok(Err(ValidationErrors::new(
@@ -900,6 +4022,8 @@ impl Secrets {
                 profile_name.to_string(),
             )))
         } else {
+            let mut locked = memory::Locked::default();
+            secrets.values().for_each(|value| locked.lock(value));
             Ok(Ok(ValidatedSecrets {
                 resolved: Resolved::new(
                     secrets,
@@ -908,14 +4032,148 @@ impl Secrets {
                 ),
                 missing_optional,
                 with_defaults,
+                _locked: locked,
             }))
         }
     }
 
+    /// Reads a secret's stored value, from the fields of its item if it has one
+    ///
+    /// Values of secrets with `hardware_confirm` are unsealed with the
+    /// security key, which asks for a touch.
+    fn get_value(
+        &self,
+        backend: &dyn ProviderTrait,
+        name: &str,
+        secret_config: &Secret,
+        profile_name: &str,
+    ) -> Result<Option<SecretString>> {
+        let project = &self.config.project.name;
+        match (&secret_config.item, secret_config.fields.get(name)) {
+            (Some(item), Some(field)) => Ok(backend
+                .get_fields(project, item, &[field], profile_name)?
+                .remove(field)),
+            _ => match backend.get(project, name, profile_name)? {
+                Some(value) if secret_config.hardware_confirm => {
+                    hardware::open(name, &value).map(Some)
+                }
+                value => Ok(value),
+            },
+        }
+    }
+
+    /// Returns the graph of `ref:` defaults between the secrets a profile
+    /// declares
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profile doesn't exist or a default is a
+    /// malformed reference
+    pub(crate) fn dependency_graph(&self, profile_name: &str) -> Result<Graph> {
+        let defaults: Vec<(String, Option<String>)> = self
+            .declared_secrets(profile_name)?
+            .into_iter()
+            .map(|name| {
+                let default = self
+                    .resolve_secret_config(&name, Some(profile_name))
+                    .and_then(|secret| secret.default);
+                (name, default)
+            })
+            .collect();
+        Graph::new(
+            defaults
+                .iter()
+                .map(|(name, default)| (name.as_str(), default.as_deref())),
+        )
+    }
+
+    /// Resolves a default value, following `ref:` references
+    ///
+    /// Plain defaults are returned as they are. A reference to another secret
+    /// reads it from `backend`, falling back to that secret's own default.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The provider referenced secrets are read from
+    /// * `default` - The default value to resolve
+    /// * `fetched` - Values already read from `backend`, which aren't read again
+    /// * `chain` - The secrets whose defaults are being resolved, to detect cycles
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a reference cycles back to a secret in `chain`
+    /// or points at a value that isn't set
+    fn resolve_default(
+        &self,
+        backend: &dyn ProviderTrait,
+        default: &str,
+        fetched: &HashMap<String, SecretString>,
+        chain: &mut Vec<String>,
+    ) -> Result<SecretString> {
+        let project = &self.config.project.name;
+        let profile_name = self.resolve_profile(None);
+        let unresolved = || SecretSpecError::UnresolvedReference(default.to_string());
+
+        match Reference::parse(default)? {
+            None => Ok(SecretString::new(default.into())),
+            Some(Reference::Provider { uri, key }) => self
+                .throttle(Box::<dyn ProviderTrait>::try_from(uri)?)
+                .get(project, key, &profile_name)?
+                .ok_or_else(unresolved),
+            Some(Reference::Secret(name)) => {
+                if chain.iter().any(|visited| visited == name) {
+                    chain.push(name.to_string());
+                    return Err(SecretSpecError::ReferenceCycle(chain.join(" -> ")));
+                }
+                let secret_config = self
+                    .resolve_secret_config(name, None)
+                    .ok_or_else(|| SecretSpecError::SecretNotFound(name.to_string()))?;
+                if let Some(value) = fetched.get(name) {
+                    return Ok(value.clone());
+                }
+                if let Some(value) = self.get_value(backend, name, &secret_config, &profile_name)? {
+                    return Ok(value);
+                }
+
+                let default = secret_config.default.ok_or_else(unresolved)?;
+                chain.push(name.to_string());
+                let value = self.resolve_default(backend, &default, fetched, chain);
+                chain.pop();
+                value
+            }
+        }
+    }
+
+    /// Returns the credentials of the profile's assumed AWS role
+    ///
+    /// The role is assumed at most once per `Secrets`, since `check` validates
+    /// repeatedly and a TOTP code can't be used twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role to assume
+    /// * `secrets` - Secrets resolved so far, which hold the role's MFA seed
+    fn assumed_credentials(
+        &self,
+        role: &AssumeRole,
+        secrets: &HashMap<String, SecretString>,
+    ) -> Result<HashMap<String, SecretString>> {
+        if let Some(credentials) = self.assumed_role.get() {
+            return Ok(credentials.clone());
+        }
+
+        let mfa_secret = role.mfa_secret.as_ref().and_then(|name| secrets.get(name));
+        let credentials =
+            sts::assume_role(role, &self.config.project.name, mfa_secret, &SystemRunner)?;
+        Ok(self.assumed_role.get_or_init(|| credentials).clone())
+    }
+
     /// Runs a command with secrets injected as environment variables
     ///
     /// This method validates that all required secrets are present, then runs
     /// the specified command with all secrets injected as environment variables.
+    /// Leases behind short-lived credentials are renewed while the command runs
+    /// and revoked once it exits.
     ///
     /// # Arguments
     ///
@@ -925,8 +4183,8 @@ impl Secrets {
     ///
     /// # Returns
     ///
-    /// This method executes the command and exits with the command's exit code.
-    /// It only returns an error if validation fails or the command cannot be started.
+    /// The exit status of the command, once it has exited. The `secretspec run`
+    /// command exits with the same code.
     ///
     /// # Errors
     ///
@@ -941,9 +4199,10 @@ impl Secrets {
     /// use secretspec::Secrets;
     ///
     /// let mut spec = Secrets::load().unwrap();
-    /// spec.run(vec!["npm".to_string(), "start".to_string()]).unwrap();
+    /// let status = spec.run(vec!["npm".to_string(), "start".to_string()]).unwrap();
+    /// assert!(status.success());
     /// ```
-    pub fn run(&self, command: Vec<String>) -> Result<()> {
+    pub fn run(&self, command: Vec<String>) -> Result<ExitStatus> {
         if command.is_empty() {
             return Err(SecretSpecError::Io(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -951,20 +4210,533 @@ impl Secrets {
             )));
         }
 
+        if self.harden || self.config.run.harden {
+            hardening::apply()?;
+        }
+
         // Ensure all secrets are available (will error out if missing)
-        let validation_result = self.ensure_secrets(None, None, false)?;
+        let backend: Arc<dyn ProviderTrait> = Arc::from(self.get_provider(None)?);
+        let validation_result = self.ensure_secrets(backend.as_ref(), false)?;
 
-        let mut env_vars = env::vars().collect::<HashMap<_, _>>();
-        // Convert SecretString values to regular strings for environment variables
-        for (key, secret) in validation_result.resolved.secrets {
-            env_vars.insert(key, secret.expose_secret().to_string());
+        let redactions = self
+            .redact_output
+            .then(|| Arc::new(Redactions::new(&validation_result.resolved.secrets)));
+        // Removed when dropped, after the command has exited
+        let files = self.run_files(&validation_result.resolved.secrets)?;
+        let mut env_vars = self.child_env(env::vars(), validation_result.resolved.secrets);
+        for (name, path) in files.iter().flat_map(RunFiles::vars) {
+            env_vars.insert(name.clone(), Zeroizing::new(path.clone()));
         }
 
         let mut cmd = Command::new(&command[0]);
         cmd.args(&command[1..]);
-        cmd.envs(&env_vars);
+        cmd.env_clear();
+        cmd.envs(env_vars.iter().map(|(name, value)| (name, value.as_str())));
+        drop(env_vars);
+
+        let keeper = LeaseKeeper::start(backend);
+        let status = match redactions {
+            Some(redactions) => run_redacted(cmd, redactions),
+            None => cmd.status(),
+        };
+        if let Some(keeper) = keeper {
+            keeper.revoke();
+        }
+        drop(files);
+        Ok(status?)
+    }
+
+    /// Writes the credential files configured in `[run]` for the command
+    fn run_files(&self, secrets: &HashMap<String, SecretString>) -> Result<Option<RunFiles>> {
+        let run = &self.config.run;
+        if run.kubeconfig.is_none() && run.aws.is_none() && run.gcloud.is_none() {
+            return Ok(None);
+        }
+        let mut files = RunFiles::new()?;
+        if let Some(config) = &run.kubeconfig {
+            let name = format!(
+                "{}-{}",
+                self.config.project.name,
+                self.resolve_profile(None)
+            );
+            let content = kubeconfig::render(config, &name, secrets)?;
+            files.add(kubeconfig::FILE_NAME, &[kubeconfig::VARIABLE], &content)?;
+        }
+        if let Some(config) = &run.aws {
+            let content = cloudsdk::aws_credentials(config, secrets)?;
+            files.add(
+                cloudsdk::AWS_FILE_NAME,
+                &[cloudsdk::AWS_FILE_VARIABLE],
+                &content,
+            )?;
+            files.set(cloudsdk::AWS_PROFILE_VARIABLE, &config.profile);
+        }
+        if let Some(config) = &run.gcloud {
+            let content = cloudsdk::gcloud_credentials(config, secrets)?;
+            files.add(
+                cloudsdk::GCLOUD_FILE_NAME,
+                &cloudsdk::GCLOUD_FILE_VARIABLES,
+                &content,
+            )?;
+        }
+        Ok(Some(files))
+    }
+
+    /// Returns the resolved secrets in a format other tools load
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if required secrets are missing or the values can't be
+    /// represented in `format`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::{ExportFormat, Secrets};
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// print!("{}", spec.export(ExportFormat::Shell).unwrap().as_str());
+    /// ```
+    pub fn export(&self, format: ExportFormat) -> Result<Zeroizing<String>> {
+        let backend = self.get_provider(None)?;
+        let validated = self.ensure_secrets(backend.as_ref(), false)?;
+        export::export(&validated.resolved.secrets, format)
+    }
+
+    /// Compares the environment of a process with the resolved secrets
+    ///
+    /// Reads the environment of the process `pid`, or of this process when
+    /// `None`, as inside `secretspec run`. Reports declared secrets that are
+    /// stale or missing there, and undeclared variables that look like
+    /// secrets or hold the value of one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment of the process can't be read or
+    /// required secrets are missing
+    pub fn env_audit(&self, pid: Option<u32>) -> Result<Vec<EnvFinding>> {
+        let env = match pid {
+            Some(pid) => envaudit::process_env(pid)?,
+            None => env::vars()
+                .map(|(name, value)| (name, Zeroizing::new(value)))
+                .collect(),
+        };
+        let backend = self.get_provider(None)?;
+        let validated = self.ensure_secrets(backend.as_ref(), false)?;
+        let declared = self.selected_secrets(&validated.resolved.profile)?;
+        Ok(envaudit::audit(
+            &env,
+            &declared,
+            &validated.resolved.secrets,
+        ))
+    }
+
+    /// Writes the resolved secrets to an environment file for the systemd
+    /// service running a process
+    ///
+    /// The environment of a running process can't be changed, so the
+    /// service picks the values up from its `EnvironmentFile=` when it is
+    /// next restarted. The file is written to `output`, by default
+    /// `/run/secretspec/<service>.env`, readable only by its owner.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process isn't part of a systemd service,
+    /// required secrets are missing or the file can't be written
+    pub fn env_for_pid(&self, pid: u32, output: Option<&Path>) -> Result<()> {
+        let unit = systemd::unit_of(pid)?;
+        let backend = self.get_provider(None)?;
+        let validated = self.ensure_secrets(backend.as_ref(), false)?;
+        let content = export::export(&validated.resolved.secrets, ExportFormat::Systemd)?;
+
+        let path = output.map_or_else(|| systemd::env_file(&unit), Path::to_path_buf);
+        systemd::write_env_file(&path, &content)?;
+        output::say(format!(
+            "{} Wrote {} secrets for {} to {}",
+            "✓".green(),
+            validated.resolved.secrets.len(),
+            unit.bold(),
+            path.display()
+        ));
+        output::say(format!(
+            "\nLoad them with EnvironmentFile={} (systemctl edit {}), then restart the service:\n  systemctl restart {}",
+            path.display(),
+            unit,
+            unit
+        ));
+        Ok(())
+    }
+
+    /// Returns the resolved secrets as deterministic JSON for Nix shells
+    ///
+    /// With `sandbox`, the provider, AWS roles and `ref:` defaults pointing at
+    /// other providers are checked before anything is read, and anything a
+    /// Nix build sandbox can't reach is refused.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if something can't be reached from the sandbox or
+    /// required secrets are missing
+    pub fn nix(&self, sandbox: bool) -> Result<Zeroizing<String>> {
+        let backend = self.get_provider(None)?;
+        if sandbox {
+            self.check_sandbox(backend.as_ref())?;
+        }
+        let validated = self.ensure_secrets(backend.as_ref(), false)?;
+        nix::json(
+            &self.config.project.name,
+            &validated.resolved.profile,
+            &validated.resolved.secrets,
+        )
+    }
+
+    /// Refuses providers and roles a Nix build sandbox can't reach
+    fn check_sandbox(&self, backend: &dyn ProviderTrait) -> Result<()> {
+        if !backend.works_in_sandbox() {
+            return Err(nix::sandbox_error(&format!(
+                "Provider '{}'",
+                backend.name()
+            )));
+        }
+        let profile_name = self.resolve_profile(None);
+        if self.config.assume_role_for(&profile_name).is_some() {
+            return Err(nix::sandbox_error("Assuming the AWS role"));
+        }
+        for name in self.selected_secrets(&profile_name)? {
+            let default = self
+                .resolve_secret_config(&name, None)
+                .and_then(|secret| secret.default);
+            if let Some(default) = default
+                && let Some(Reference::Provider { uri, .. }) = Reference::parse(&default)?
+                && !Box::<dyn ProviderTrait>::try_from(uri)?.works_in_sandbox()
+            {
+                return Err(nix::sandbox_error(&format!("The default of {}", name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns resolved secrets as the JSON object Terraform's `external`
+    /// data source reads
+    ///
+    /// # Arguments
+    ///
+    /// * `requested` - Names of the secrets to return; all if empty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if required secrets are missing or a requested
+    /// secret isn't set
+    pub fn terraform_output(&self, requested: &[String]) -> Result<Zeroizing<String>> {
+        let backend = self.get_provider(None)?;
+        let validated = self.ensure_secrets(backend.as_ref(), false)?;
+        terraform::output(&validated.resolved.secrets, requested)
+    }
+
+    /// Serves Cargo's credential provider protocol, keeping registry tokens
+    /// in the provider
+    ///
+    /// The token of a registry is the secret named after Cargo's environment
+    /// variable for it, like `CARGO_REGISTRY_TOKEN` for crates.io. `get`
+    /// requests read it, `cargo login` stores it and `cargo logout` deletes
+    /// it. Nothing but the protocol is written to `output`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Cargo's requests, one JSON object per line
+    /// * `output` - Where the responses are written
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading requests or writing responses fails.
+    /// Requests that can't be answered are reported to Cargo.
+    pub fn cargo_credential(&self, input: impl BufRead, output: impl Write) -> Result<()> {
+        cargo::serve(input, output, |name, action| match action {
+            cargo::Action::Get => self.get(name).map(Some),
+            cargo::Action::Login { token: Some(token) } => {
+                self.set_secret(name, token).map(|()| None)
+            }
+            cargo::Action::Login { token: None } => Err(SecretSpecError::InvalidInput(
+                "Pass the token on the command line: cargo login <token>".to_string(),
+            )),
+            cargo::Action::Logout => {
+                let backend = self.get_provider(None)?;
+                if !backend.allows_delete() {
+                    return Err(SecretSpecError::ProviderOperationFailed(format!(
+                        "Provider '{}' cannot delete secrets",
+                        backend.name()
+                    )));
+                }
+                backend.delete(
+                    &self.config.project.name,
+                    &self.canonical_name(name),
+                    &self.resolve_profile(None),
+                )?;
+                Ok(None)
+            }
+            cargo::Action::Unknown => Ok(None),
+        })
+    }
+
+    /// Returns the registry settings of an `.npmrc`, with tokens read from
+    /// the provider
+    ///
+    /// # Arguments
+    ///
+    /// * `registries` - Registries as `[@scope:]URL=SECRET`; the public
+    ///   registry with `NPM_TOKEN` if empty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a registry is malformed or its token isn't set
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use secretspec::Secrets;
+    ///
+    /// let spec = Secrets::load().unwrap();
+    /// let npmrc = spec
+    ///     .npmrc(&["@acme:https://npm.pkg.github.com/=GITHUB_TOKEN".to_string()])
+    ///     .unwrap();
+    /// print!("{}", npmrc.as_str());
+    /// ```
+    pub fn npmrc(&self, registries: &[String]) -> Result<Zeroizing<String>> {
+        let specs = if registries.is_empty() {
+            vec![npmrc::DEFAULT_REGISTRY.to_string()]
+        } else {
+            registries.to_vec()
+        };
+        let mut tokens = Vec::with_capacity(specs.len());
+        for spec in &specs {
+            let registry = npmrc::Registry::parse(spec)?;
+            let token = self.get(&registry.secret)?;
+            tokens.push((registry, token));
+        }
+        npmrc::render(&tokens)
+    }
+
+    /// Writes the PostgreSQL logins of connection URLs to a `.pgpass` file
+    ///
+    /// Lines for the same host, port, database and user are replaced, and
+    /// the rest of the file is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - Secrets holding `postgres://` URLs; every secret holding
+    ///   one if empty
+    /// * `output` - The file to write, by default `PGPASSFILE` or `~/.pgpass`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if required secrets are missing, a named secret
+    /// doesn't hold a URL with a user and password, or the file can't be
+    /// written
+    pub fn pgpass(&self, names: &[String], output: Option<&Path>) -> Result<()> {
+        let logins = self.database_logins(names, dbfiles::Engine::Postgres)?;
+        let path = match output {
+            Some(path) => path.to_path_buf(),
+            None => dbfiles::pgpass_path()?,
+        };
+        let logins: Vec<&dbfiles::Login> = logins.iter().map(|(_, login)| login).collect();
+        dbfiles::write_pgpass(&path, &logins)?;
+        output::say(format!(
+            "{} Wrote {} PostgreSQL login{} to {}",
+            "✓".green(),
+            logins.len(),
+            if logins.len() == 1 { "" } else { "s" },
+            path.display()
+        ));
+        Ok(())
+    }
+
+    /// Writes the MySQL logins of connection URLs to option groups of a
+    /// MySQL option file
+    ///
+    /// A single login goes to the `[client]` group, read by every MySQL
+    /// client. Several go to `[client_<secret>]` groups, selected with
+    /// `--defaults-group-suffix=_<secret>`. Other groups are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - Secrets holding `mysql://` URLs; every secret holding one
+    ///   if empty
+    /// * `output` - The file to write, by default `~/.my.cnf`
+    /// * `group` - The group to write a single login to, instead of `client`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if required secrets are missing, a named secret
+    /// doesn't hold a URL with a user and password, a group is given for
+    /// several logins, or the file can't be written
+    pub fn mycnf(
+        &self,
+        names: &[String],
+        output: Option<&Path>,
+        group: Option<&str>,
+    ) -> Result<()> {
+        let logins = self.database_logins(names, dbfiles::Engine::Mysql)?;
+        if group.is_some() && logins.len() > 1 {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "A group can only be given for one login, but {} secrets hold MySQL URLs: {}",
+                logins.len(),
+                logins
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+        let path = match output {
+            Some(path) => path.to_path_buf(),
+            None => dbfiles::mycnf_path()?,
+        };
+        for (name, login) in &logins {
+            let group = match group {
+                Some(group) => group.to_string(),
+                None if logins.len() == 1 => "client".to_string(),
+                None => format!("client_{}", name.to_lowercase()),
+            };
+            dbfiles::write_option_group(&path, &group, login)?;
+            output::say(format!(
+                "{} Wrote {} to [{}] in {}",
+                "✓".green(),
+                name,
+                group,
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the logins of the resolved secrets holding connection URLs of
+    /// a database engine, in name order
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if required secrets are missing, a named secret
+    /// doesn't hold such a URL, or no secret does
+    fn database_logins(
+        &self,
+        names: &[String],
+        engine: dbfiles::Engine,
+    ) -> Result<Vec<(String, dbfiles::Login)>> {
+        let scheme = match engine {
+            dbfiles::Engine::Postgres => "postgres://",
+            dbfiles::Engine::Mysql => "mysql://",
+        };
+        let backend = self.get_provider(None)?;
+        let validated = self.ensure_secrets(backend.as_ref(), false)?;
+        let secrets = &validated.resolved.secrets;
+
+        let mut logins = Vec::new();
+        if names.is_empty() {
+            for (name, value) in secrets {
+                if let Some(login) = dbfiles::Login::parse(value.expose_secret())
+                    && login.engine == engine
+                {
+                    logins.push((name.clone(), login));
+                }
+            }
+            if logins.is_empty() {
+                return Err(SecretSpecError::InvalidInput(format!(
+                    "No secret holds a {} URL with a user and password",
+                    scheme
+                )));
+            }
+        } else {
+            for name in names {
+                let name = self.canonical_name(name);
+                let value = secrets
+                    .get(&name)
+                    .ok_or_else(|| SecretSpecError::SecretNotFound(name.clone()))?;
+                match dbfiles::Login::parse(value.expose_secret()) {
+                    Some(login) if login.engine == engine => logins.push((name, login)),
+                    _ => {
+                        return Err(SecretSpecError::InvalidInput(format!(
+                            "{} doesn't hold a {} URL with a user and password",
+                            name, scheme
+                        )));
+                    }
+                }
+            }
+        }
+        logins.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(logins)
+    }
+
+    /// Runs a hook whenever the declared secrets change
+    ///
+    /// The secrets are resolved every `interval`. When a value was added,
+    /// removed or changed since the last poll, `hook` is run through the
+    /// shell with the environment `run` would give a command, plus
+    /// `SECRETSPEC_CHANGED` listing the changed secrets. This never returns
+    /// unless the first poll fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the secrets can't be resolved at start. Later
+    /// failures, of polls or of the hook, are printed as warnings.
+    pub fn watch(&self, hook: &str, interval: Duration) -> Result<()> {
+        let mut previous = watch::fingerprint(&self.poll()?);
+        output::say(format!(
+            "Watching {} secrets every {}s",
+            previous.len(),
+            interval.as_secs()
+        ));
+        loop {
+            thread::sleep(interval);
+            let secrets = match self.poll() {
+                Ok(secrets) => secrets,
+                Err(e) => {
+                    warning::warn(format!("Failed to read secrets: {}", e));
+                    continue;
+                }
+            };
+            let current = watch::fingerprint(&secrets);
+            let changed = watch::changed(&previous, &current);
+            previous = current;
+            if changed.is_empty() {
+                continue;
+            }
+
+            output::say(format!("{} Changed: {}", "↻".cyan(), changed.join(", ")));
+            let env_vars = self.child_env(env::vars(), secrets);
+            match watch::run_hook(hook, &env_vars, &changed) {
+                Ok(status) if status.success() => {}
+                Ok(status) => warning::warn(format!("Hook exited with {}", status)),
+                Err(e) => warning::warn(format!("Failed to run hook: {}", e)),
+            }
+        }
+    }
+
+    /// Resolves the secrets from a fresh provider for one `watch` poll
+    fn poll(&self) -> Result<HashMap<String, SecretString>> {
+        let backend = self.get_provider(None)?;
+        Ok(self
+            .ensure_secrets(backend.as_ref(), false)?
+            .resolved
+            .secrets)
+    }
+}
+
+/// Runs a command with its stdout and stderr piped through redaction
+fn run_redacted(mut cmd: Command, redactions: Arc<Redactions>) -> io::Result<ExitStatus> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().map(|pipe| {
+        let redactions = Arc::clone(&redactions);
+        thread::spawn(move || redact::copy(redactions, pipe, io::stdout()))
+    });
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| thread::spawn(move || redact::copy(redactions, pipe, io::stderr())));
 
-        let status = cmd.status()?;
-        std::process::exit(status.code().unwrap_or(1));
+    let status = child.wait()?;
+    for copier in stdout.into_iter().chain(stderr) {
+        copier
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("output redaction panicked")))?;
     }
+    Ok(status)
 }
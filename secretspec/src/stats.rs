@@ -0,0 +1,222 @@
+//! Local usage statistics
+//!
+//! Every secret lookup made through a provider is counted in `stats.toml` in
+//! the user's local data directory, so `secretspec stats` can show which
+//! provider is slowing builds down:
+//!
+//! ```toml
+//! [providers.onepassword]
+//! lookups = 120
+//! found = 118
+//! errors = 0
+//! total_micros = 98123456
+//! ```
+//!
+//! The file only ever holds counters. Nothing is sent over the network, and
+//! setting `SECRETSPEC_NO_STATS` disables recording entirely. Counters are
+//! best-effort: concurrent processes may overwrite each other's updates, and
+//! failing to write the file never fails a command.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Environment variable that disables recording when set.
+const DISABLE_ENV: &str = "SECRETSPEC_NO_STATS";
+
+/// Counters for all providers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Stats {
+    /// Counters keyed by provider name
+    #[serde(default)]
+    pub providers: BTreeMap<String, ProviderStats>,
+}
+
+/// Counters for a single provider.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ProviderStats {
+    /// Number of lookups made
+    #[serde(default)]
+    pub lookups: u64,
+    /// Number of lookups that found a value
+    #[serde(default)]
+    pub found: u64,
+    /// Number of lookups that failed
+    #[serde(default)]
+    pub errors: u64,
+    /// Total time spent in lookups, in microseconds
+    #[serde(default)]
+    pub total_micros: u64,
+}
+
+impl ProviderStats {
+    /// Counts one lookup.
+    ///
+    /// # Arguments
+    ///
+    /// * `elapsed` - How long the lookup took
+    /// * `outcome` - `Some(true)` if a value was found, `Some(false)` if not,
+    ///   `None` if the lookup failed
+    pub fn record(&mut self, elapsed: Duration, outcome: Option<bool>) {
+        self.lookups += 1;
+        match outcome {
+            Some(true) => self.found += 1,
+            Some(false) => {}
+            None => self.errors += 1,
+        }
+        self.total_micros = self
+            .total_micros
+            .saturating_add(u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX));
+    }
+
+    /// Adds another set of counters to these.
+    pub fn merge(&mut self, other: &ProviderStats) {
+        self.lookups += other.lookups;
+        self.found += other.found;
+        self.errors += other.errors;
+        self.total_micros = self.total_micros.saturating_add(other.total_micros);
+    }
+
+    /// Returns the share of lookups that found a value, between 0 and 1.
+    pub fn found_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            0.0
+        } else {
+            self.found as f64 / self.lookups as f64
+        }
+    }
+
+    /// Returns the average time a lookup took.
+    pub fn average_latency(&self) -> Duration {
+        match self.lookups {
+            0 => Duration::ZERO,
+            n => Duration::from_micros(self.total_micros / n),
+        }
+    }
+}
+
+impl Stats {
+    /// Returns the path of the statistics file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data directory cannot be determined
+    pub fn path() -> io::Result<PathBuf> {
+        use directories::ProjectDirs;
+        let dirs = ProjectDirs::from("", "", "secretspec").ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "Could not find data directory")
+        })?;
+        Ok(dirs.data_local_dir().join("stats.toml"))
+    }
+
+    /// Loads statistics, returning empty counters if the file doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes statistics, replacing the file atomically.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content =
+            toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let staged = path.with_extension("toml.tmp");
+        fs::write(&staged, content)?;
+        fs::rename(&staged, path)
+    }
+
+    /// Adds the counters of one provider to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or written
+    pub fn record_at(path: &Path, provider: &str, counters: &ProviderStats) -> io::Result<()> {
+        let mut stats = Self::load(path)?;
+        stats
+            .providers
+            .entry(provider.to_string())
+            .or_default()
+            .merge(counters);
+        stats.save(path)
+    }
+}
+
+/// Returns whether lookups should be recorded.
+///
+/// Recording is disabled by `SECRETSPEC_NO_STATS`, in unit tests, which
+/// must not write to the user's data directory, and without the `native`
+/// feature, as in the browser, where there is none.
+pub(crate) fn enabled() -> bool {
+    !cfg!(test) && cfg!(feature = "native") && std::env::var_os(DISABLE_ENV).is_none()
+}
+
+/// Adds the counters of one provider to the user's statistics file.
+///
+/// Errors are ignored, since statistics must never make a command fail.
+pub(crate) fn record(provider: &str, counters: &ProviderStats) {
+    if let Ok(path) = Stats::path() {
+        let _ = Stats::record_at(&path, provider, counters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_counts_outcomes_and_latency() {
+        let mut counters = ProviderStats::default();
+        counters.record(Duration::from_millis(10), Some(true));
+        counters.record(Duration::from_millis(20), Some(false));
+        counters.record(Duration::from_millis(30), None);
+
+        assert_eq!(counters.lookups, 3);
+        assert_eq!(counters.found, 1);
+        assert_eq!(counters.errors, 1);
+        assert_eq!(counters.average_latency(), Duration::from_millis(20));
+        assert!((counters.found_rate() - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(ProviderStats::default().average_latency(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_record_at_accumulates_across_runs() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("stats.toml");
+
+        let mut counters = ProviderStats::default();
+        counters.record(Duration::from_millis(5), Some(true));
+        Stats::record_at(&path, "keyring", &counters).unwrap();
+        Stats::record_at(&path, "keyring", &counters).unwrap();
+        Stats::record_at(&path, "dotenv", &counters).unwrap();
+
+        let stats = Stats::load(&path).unwrap();
+        assert_eq!(stats.providers["keyring"].lookups, 2);
+        assert_eq!(stats.providers["keyring"].total_micros, 10_000);
+        assert_eq!(stats.providers["dotenv"].found, 1);
+
+        // A missing file has no counters
+        assert_eq!(
+            Stats::load(&dir.path().join("missing.toml")).unwrap(),
+            Stats::default()
+        );
+    }
+}
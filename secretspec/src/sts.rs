@@ -0,0 +1,298 @@
+//! AWS role assumption
+//!
+//! Specs can declare an `[assume_role.<profile>]` table to derive short-lived
+//! AWS credentials instead of storing long-lived keys:
+//!
+//! ```toml
+//! [assume_role.production]
+//! role_arn = "arn:aws:iam::123456789012:role/deploy"
+//! mfa_serial = "arn:aws:iam::123456789012:mfa/alice"
+//! mfa_secret = "AWS_MFA_SEED"
+//! ```
+//!
+//! The role is assumed through `aws sts assume-role` with the AWS CLI's own
+//! credentials, and the MFA code is generated from the TOTP seed stored in
+//! `mfa_secret`. The credentials are exposed as [`DERIVED_SECRETS`].
+
+use crate::config::AssumeRole;
+use crate::provider::command::{CommandRunner, Invocation};
+use crate::totp;
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Names of the secrets derived from an assumed role.
+pub(crate) const DERIVED_SECRETS: [&str; 3] = [
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+];
+
+/// Response of `aws sts assume-role`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssumeRoleResponse {
+    credentials: Credentials,
+}
+
+/// Temporary credentials issued by STS.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+}
+
+/// Returns the session name used when the spec doesn't set one.
+///
+/// Session names may only contain `[\w+=,.@-]` and at most 64 characters.
+pub(crate) fn default_session_name(project: &str) -> String {
+    format!("secretspec-{}", project)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_+=,.@-".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(64)
+        .collect()
+}
+
+/// Returns the MFA code for a stored secret.
+///
+/// The secret usually holds the device's base32 TOTP seed; a 6-digit value
+/// is taken to be a code entered by hand and used as is.
+fn mfa_code(secret: &SecretString) -> Result<String> {
+    let value = secret.expose_secret().trim();
+    if value.len() == 6 && value.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(value.to_string());
+    }
+    totp::now(value)
+        .map_err(|e| SecretSpecError::ProviderOperationFailed(format!("Invalid MFA secret: {}", e)))
+}
+
+/// Assumes a role and returns the derived credentials.
+///
+/// # Arguments
+///
+/// * `role` - The role to assume
+/// * `project` - The project name, used for the default session name
+/// * `mfa_secret` - The value of the role's `mfa_secret`, if it has one
+/// * `runner` - Runs the `aws` CLI
+///
+/// # Errors
+///
+/// Returns an error if the AWS CLI is missing, has no source credentials, or
+/// STS refuses to issue credentials
+pub(crate) fn assume_role(
+    role: &AssumeRole,
+    project: &str,
+    mfa_secret: Option<&SecretString>,
+    runner: &dyn CommandRunner,
+) -> Result<HashMap<String, SecretString>> {
+    let session_name = role
+        .session_name
+        .clone()
+        .unwrap_or_else(|| default_session_name(project));
+    let mut args = vec![
+        "sts".to_string(),
+        "assume-role".to_string(),
+        "--role-arn".to_string(),
+        role.role_arn.clone(),
+        "--role-session-name".to_string(),
+        session_name,
+        "--output".to_string(),
+        "json".to_string(),
+    ];
+    if let Some(duration) = role.duration_seconds {
+        args.extend(["--duration-seconds".to_string(), duration.to_string()]);
+    }
+    if let Some(profile) = &role.source_profile {
+        args.extend(["--profile".to_string(), profile.clone()]);
+    }
+    if let Some(serial) = &role.mfa_serial {
+        let secret = mfa_secret.ok_or_else(|| {
+            SecretSpecError::RequiredSecretMissing(
+                role.mfa_secret
+                    .clone()
+                    .unwrap_or_else(|| "mfa_secret".to_string()),
+            )
+        })?;
+        args.extend([
+            "--serial-number".to_string(),
+            serial.clone(),
+            "--token-code".to_string(),
+            mfa_code(secret)?,
+        ]);
+    }
+
+    let output = match runner.run(&Invocation::new("aws").args(&args)) {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(SecretSpecError::CliMissing {
+                cli: "AWS CLI (aws)".to_string(),
+                install_hint: "To install it:\n  - macOS: brew install awscli\n  - Linux: https://docs.aws.amazon.com/cli/latest/userguide/getting-started-install.html\n  - NixOS: nix-env -iA nixpkgs.awscli2".to_string(),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if !output.success {
+        let stderr = output.stderr;
+        if stderr.contains("Unable to locate credentials") || stderr.contains("ExpiredToken") {
+            return Err(SecretSpecError::AuthRequired {
+                provider: "AWS".to_string(),
+                fix: "Configure credentials to assume the role with, e.g. 'aws configure' or 'aws sso login', or set source_profile.".to_string(),
+            });
+        }
+        if stderr.contains("AccessDenied") {
+            return Err(SecretSpecError::PermissionDenied(stderr));
+        }
+        if stderr.contains("Throttling") {
+            return Err(SecretSpecError::RateLimited {
+                provider: "AWS STS".to_string(),
+                retry_after: None,
+            });
+        }
+        return Err(SecretSpecError::ProviderOperationFailed(stderr));
+    }
+
+    let response: AssumeRoleResponse = serde_json::from_str(&output.stdout)?;
+    let credentials = response.credentials;
+    Ok(DERIVED_SECRETS
+        .iter()
+        .map(|name| name.to_string())
+        .zip([
+            credentials.access_key_id,
+            credentials.secret_access_key,
+            credentials.session_token,
+        ])
+        .map(|(name, value)| (name, SecretString::new(value.into())))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::command::FakeRunner;
+
+    const RESPONSE: &str = r#"{
+        "Credentials": {
+            "AccessKeyId": "ASIAEXAMPLE",
+            "SecretAccessKey": "wJalrXUtnFEMI",
+            "SessionToken": "FwoGZXIvYXdzEXAMPLE",
+            "Expiration": "2026-10-16T13:00:00+00:00"
+        },
+        "AssumedRoleUser": {
+            "AssumedRoleId": "AROAEXAMPLE:secretspec-app",
+            "Arn": "arn:aws:sts::123456789012:assumed-role/deploy/secretspec-app"
+        }
+    }"#;
+
+    fn role() -> AssumeRole {
+        AssumeRole {
+            role_arn: "arn:aws:iam::123456789012:role/deploy".to_string(),
+            session_name: None,
+            duration_seconds: None,
+            source_profile: None,
+            mfa_serial: None,
+            mfa_secret: None,
+        }
+    }
+
+    #[test]
+    fn test_default_session_name() {
+        assert_eq!(default_session_name("my app"), "secretspec-my-app");
+        assert_eq!(default_session_name(&"x".repeat(100)).len(), 64);
+    }
+
+    #[test]
+    fn test_assume_role() {
+        let runner = FakeRunner::new().ok(
+            &[
+                "aws",
+                "sts",
+                "assume-role",
+                "--role-arn",
+                "arn:aws:iam::123456789012:role/deploy",
+                "--role-session-name",
+                "secretspec-app",
+                "--output",
+                "json",
+            ],
+            RESPONSE,
+        );
+        let credentials = assume_role(&role(), "app", None, &runner).unwrap();
+        assert_eq!(
+            credentials["AWS_ACCESS_KEY_ID"].expose_secret(),
+            "ASIAEXAMPLE"
+        );
+        assert_eq!(
+            credentials["AWS_SESSION_TOKEN"].expose_secret(),
+            "FwoGZXIvYXdzEXAMPLE"
+        );
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn test_assume_role_with_mfa() {
+        let role = AssumeRole {
+            source_profile: Some("base".to_string()),
+            mfa_serial: Some("arn:aws:iam::123456789012:mfa/alice".to_string()),
+            mfa_secret: Some("AWS_MFA_SEED".to_string()),
+            ..role()
+        };
+        let runner = FakeRunner::new().ok(
+            &[
+                "aws",
+                "sts",
+                "assume-role",
+                "--role-arn",
+                "arn:aws:iam::123456789012:role/deploy",
+                "--role-session-name",
+                "secretspec-app",
+                "--output",
+                "json",
+                "--profile",
+                "base",
+                "--serial-number",
+                "arn:aws:iam::123456789012:mfa/alice",
+                "--token-code",
+                "*",
+            ],
+            RESPONSE,
+        );
+        let seed = SecretString::new("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".into());
+        assume_role(&role, "app", Some(&seed), &runner).unwrap();
+
+        let code = runner.calls()[0].args.last().unwrap().clone();
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+
+        // Without the seed the MFA secret is reported missing
+        let err = assume_role(&role, "app", None, &FakeRunner::new()).unwrap_err();
+        assert!(
+            matches!(err, SecretSpecError::RequiredSecretMissing(name) if name == "AWS_MFA_SEED")
+        );
+    }
+
+    #[test]
+    fn test_assume_role_errors() {
+        let args = ["aws", "sts", "assume-role", "*", "*", "*", "*", "*", "*"];
+        let runner = FakeRunner::new()
+            .missing(&args)
+            .fail(&args, "Unable to locate credentials. You can configure credentials by running \"aws configure\".")
+            .fail(&args, "An error occurred (AccessDenied) when calling the AssumeRole operation: not authorized");
+
+        let err = assume_role(&role(), "app", None, &runner).unwrap_err();
+        assert!(matches!(err, SecretSpecError::CliMissing { .. }));
+        let err = assume_role(&role(), "app", None, &runner).unwrap_err();
+        assert!(matches!(err, SecretSpecError::AuthRequired { .. }));
+        let err = assume_role(&role(), "app", None, &runner).unwrap_err();
+        assert!(matches!(err, SecretSpecError::PermissionDenied(_)));
+    }
+}
@@ -0,0 +1,154 @@
+//! "Did you mean" suggestions for misspelled names.
+//!
+//! Strict parsing reports spec keys and provider URI parameters that would
+//! otherwise be ignored. Most of them are typos of a known name, like
+//! `colection` for `collection`, so the closest known name is suggested.
+//! Browsing a provider narrows long lists of item names with a looser,
+//! fuzzy match instead.
+
+/// Returns the number of single-character insertions, deletions and
+/// substitutions that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Returns the candidate closest to `name`, if any is close enough to be a
+/// likely typo.
+///
+/// A candidate qualifies within one edit for every three characters of
+/// `name`, and at least one.
+pub(crate) fn closest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    similar(name, candidates).first().copied()
+}
+
+/// Returns every candidate close enough to `name` to be a likely typo,
+/// closest first.
+///
+/// Uses the same limit as [`closest`]. Candidates at the same distance keep
+/// their order.
+pub(crate) fn similar<'a>(name: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    let mut matches: Vec<(usize, &str)> = candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    matches.sort_by_key(|(distance, _)| *distance);
+    matches
+        .into_iter()
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Returns a hint for an unknown name: the closest candidate, or else the
+/// list of candidates.
+///
+/// For example `Did you mean 'collection'?` for `colection`.
+pub(crate) fn hint(name: &str, candidates: &[&str]) -> Option<String> {
+    match closest(name, candidates) {
+        Some(candidate) => Some(format!("Did you mean '{}'?", candidate)),
+        None if candidates.is_empty() => None,
+        None => Some(format!("Expected one of: {}", candidates.join(", "))),
+    }
+}
+
+/// Returns how far apart the characters of `query` are spread in
+/// `candidate`, ignoring case, or `None` if they don't all appear in it in
+/// order.
+///
+/// `0` means `query` appears as is; every character skipped after the
+/// first match adds one.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut started = false;
+    for c in candidate.chars().flat_map(char::to_lowercase) {
+        match query.peek() {
+            None => break,
+            Some(q) if *q == c => {
+                query.next();
+                started = true;
+            }
+            Some(_) if started => score += 1,
+            Some(_) => {}
+        }
+    }
+    query.peek().is_none().then_some(score)
+}
+
+/// Returns the candidates fuzzily matching `query`, like `Stripe API Key`
+/// for `stripekey`, the closest first.
+///
+/// Candidates with the same score keep their order.
+pub(crate) fn fuzzy<'a>(query: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+    let mut matches: Vec<(usize, &str)> = candidates
+        .iter()
+        .filter_map(|candidate| Some((fuzzy_score(query, candidate)?, *candidate)))
+        .collect();
+    matches.sort_by_key(|(score, _)| *score);
+    matches
+        .into_iter()
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(
+            closest("colection", &["collection", "folder"]),
+            Some("collection")
+        );
+        assert_eq!(
+            closest("requried", &["required", "default"]),
+            Some("required")
+        );
+        assert_eq!(closest("xyz", &["required", "default"]), None);
+        assert_eq!(
+            similar(
+                "DATABSE_URL",
+                &["REDIS_URL", "DATABASE_URI", "DATABASE_URL"]
+            ),
+            vec!["DATABASE_URL", "DATABASE_URI"]
+        );
+        assert_eq!(
+            hint("extend", &["name", "schema", "extends"]).as_deref(),
+            Some("Did you mean 'extends'?")
+        );
+        assert_eq!(
+            hint("foo", &["name", "schema"]).as_deref(),
+            Some("Expected one of: name, schema")
+        );
+        assert_eq!(hint("foo", &[]), None);
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        assert_eq!(fuzzy_score("stripe", "Stripe API Key"), Some(0));
+        assert_eq!(fuzzy_score("stripekey", "Stripe API Key"), Some(5));
+        assert_eq!(fuzzy_score("keystripe", "Stripe API Key"), None);
+        assert_eq!(
+            fuzzy(
+                "dburl",
+                &["Database URL", "GitHub Token", "DB_URL", "db/prod/url"]
+            ),
+            vec!["DB_URL", "db/prod/url", "Database URL"]
+        );
+        assert_eq!(fuzzy("", &["a", "b"]), vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Handing rotated secrets to running systemd services
+//!
+//! A process's environment is fixed once it starts, so `secretspec env --pid`
+//! can't change it in place. Instead it finds the systemd service the
+//! process belongs to and writes the secrets to an `EnvironmentFile=` for
+//! it. The service reads the new values on its next restart, without a
+//! redeploy:
+//!
+//! ```ini
+//! # systemctl edit app.service
+//! [Service]
+//! EnvironmentFile=/run/secretspec/app.env
+//! ```
+//!
+//! Only Linux exposes the service of a process, through `/proc/<pid>/cgroup`.
+
+use crate::{Result, SecretSpecError};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory environment files are written to by default.
+///
+/// `/run` is a tmpfs, so the values never reach the disk.
+pub(crate) const ENV_DIR: &str = "/run/secretspec";
+
+/// Returns the service named in the contents of `/proc/<pid>/cgroup`.
+///
+/// Components are searched from the innermost, so a service run by a user's
+/// service manager is preferred over `user@<uid>.service` itself.
+pub(crate) fn unit_from_cgroup(cgroup: &str) -> Option<String> {
+    cgroup
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .flat_map(|path| path.rsplit('/'))
+        .find(|component| component.ends_with(".service"))
+        .map(str::to_string)
+}
+
+/// Returns the systemd service running a process.
+///
+/// # Errors
+///
+/// Returns an error if there is no such process or it doesn't run as part
+/// of a service
+pub(crate) fn unit_of(pid: u32) -> Result<String> {
+    if !cfg!(target_os = "linux") {
+        return Err(SecretSpecError::ProviderOperationFailed(
+            "Finding the service of a process is only supported on Linux".to_string(),
+        ));
+    }
+    let cgroup = match fs::read_to_string(format!("/proc/{}/cgroup", pid)) {
+        Ok(cgroup) => cgroup,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(SecretSpecError::InvalidInput(format!(
+                "No process with PID {}",
+                pid
+            )));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    unit_from_cgroup(&cgroup).ok_or_else(|| {
+        SecretSpecError::InvalidInput(format!("Process {} is not part of a systemd service", pid))
+    })
+}
+
+/// Returns the default environment file of a service.
+pub(crate) fn env_file(unit: &str) -> PathBuf {
+    let name = unit.strip_suffix(".service").unwrap_or(unit);
+    Path::new(ENV_DIR).join(format!("{}.env", name))
+}
+
+/// Replaces `path` with `content`, readable only by the owner.
+///
+/// The file is written to a temporary sibling and renamed into place, so a
+/// service starting meanwhile never reads a partially written file.
+pub(crate) fn write_env_file(path: &Path, content: &str) -> Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+    let mut staged = tempfile::NamedTempFile::new_in(dir)?;
+    staged.write_all(content.as_bytes())?;
+    staged
+        .persist(path)
+        .map_err(|e| SecretSpecError::Io(e.error))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_from_cgroup() {
+        assert_eq!(
+            unit_from_cgroup("0::/system.slice/app.service\n").as_deref(),
+            Some("app.service")
+        );
+        assert_eq!(
+            unit_from_cgroup(
+                "0::/user.slice/user-1000.slice/user@1000.service/app.slice/worker.service\n"
+            )
+            .as_deref(),
+            Some("worker.service")
+        );
+        assert_eq!(
+            unit_from_cgroup(
+                "12:pids:/system.slice/app.service\n1:name=systemd:/system.slice/app.service\n"
+            )
+            .as_deref(),
+            Some("app.service")
+        );
+        assert_eq!(
+            unit_from_cgroup("0::/user.slice/user-1000.slice/session-2.scope\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_env_file() {
+        assert_eq!(
+            env_file("app.service"),
+            PathBuf::from("/run/secretspec/app.env")
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! OpenTelemetry traces and metrics
+//!
+//! With `--otel`, a command records spans for its resolution phases and for
+//! every call made to a provider, along with metrics: calls and their latency
+//! per provider and operation, failures, and cache hits. When the command
+//! finishes, they are sent to an OTLP collector over HTTP:
+//!
+//! - `OTEL_EXPORTER_OTLP_ENDPOINT` - collector URL, `http://localhost:4318`
+//!   by default
+//! - `OTEL_EXPORTER_OTLP_HEADERS` - extra headers as `key=value` pairs
+//!   separated by commas, e.g. for an API key
+//! - `OTEL_SERVICE_NAME` - service name, `secretspec` by default
+//!
+//! Builds with the `otlp` feature, including release binaries, export with
+//! the OpenTelemetry SDK's OTLP exporter, see [`otlp`], which also honours
+//! the other `OTEL_EXPORTER_OTLP_*` variables. Other builds fall back on
+//! [`json`], an experimental sink for debugging against a local collector
+//! that posts OTLP/JSON with curl.
+//!
+//! Spans name secrets but never carry their values. Exporting is best-effort:
+//! failures are reported as warnings and never fail a command.
+
+#[cfg(not(feature = "otlp"))]
+mod json;
+#[cfg(feature = "otlp")]
+mod otlp;
+
+use crate::warning;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Upper bounds of the latency histogram buckets, in milliseconds.
+const BOUNDS_MS: [f64; 11] = [
+    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+/// Attributes of a span or data point, in the order they were added.
+pub(crate) type Attributes = Vec<(&'static str, String)>;
+
+/// A span, finished or not.
+#[derive(Debug, Clone)]
+struct SpanRecord {
+    /// Hex-encoded span ID
+    id: String,
+    /// ID of the enclosing span, or `None` for the command's root span
+    parent: Option<String>,
+    /// Name of the operation
+    name: String,
+    /// When the span started
+    start: SystemTime,
+    /// When the span ended
+    end: SystemTime,
+    /// Attributes describing the operation
+    attributes: Attributes,
+    /// Why the operation failed, if it did
+    error: Option<String>,
+}
+
+impl SpanRecord {
+    /// Starts a span below `parent`.
+    fn start(name: String, parent: Option<String>) -> Self {
+        let now = SystemTime::now();
+        Self {
+            id: random_id::<8>(),
+            parent,
+            name,
+            start: now,
+            end: now,
+            attributes: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+/// Everything recorded by the command so far.
+#[derive(Debug)]
+struct Recorder {
+    /// Hex-encoded trace ID shared by all spans
+    trace_id: String,
+    /// The span covering the whole command, still open
+    root: SpanRecord,
+    /// Finished spans
+    spans: Vec<SpanRecord>,
+    /// Counters by metric name and attributes
+    counters: BTreeMap<(&'static str, Attributes), u64>,
+    /// Recorded latencies in milliseconds, by metric name and attributes
+    histograms: BTreeMap<(&'static str, Attributes), Vec<f64>>,
+}
+
+/// The recorder, or `None` while telemetry is disabled.
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+thread_local! {
+    /// IDs of the spans open on this thread, innermost last.
+    static OPEN: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Locks the recorder, even if a thread panicked while holding it.
+fn recorder() -> std::sync::MutexGuard<'static, Option<Recorder>> {
+    RECORDER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Returns `N` random bytes, hex-encoded.
+fn random_id<const N: usize>() -> String {
+    let mut bytes = [0u8; N];
+    #[cfg(feature = "native")]
+    let random = getrandom::getrandom(&mut bytes).is_ok();
+    #[cfg(not(feature = "native"))]
+    let random = false;
+    if !random {
+        // IDs only need to be unique, so fall back on the clock
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+            .to_le_bytes();
+        for (byte, seed) in bytes.iter_mut().zip(nanos.iter().cycle()) {
+            *byte = *seed;
+        }
+    }
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Starts recording, with a root span named `name` covering the command.
+pub(crate) fn enable(name: impl Into<String>) {
+    *recorder() = Some(Recorder {
+        trace_id: random_id::<16>(),
+        root: SpanRecord::start(name.into(), None),
+        spans: Vec::new(),
+        counters: BTreeMap::new(),
+        histograms: BTreeMap::new(),
+    });
+}
+
+/// Returns whether telemetry is being recorded.
+pub(crate) fn enabled() -> bool {
+    recorder().is_some()
+}
+
+/// An open span, recorded when dropped.
+///
+/// Spans opened while another is open on the same thread are nested in it;
+/// others are nested in the command's root span.
+#[must_use]
+pub(crate) struct Span {
+    /// The span, or `None` if telemetry is disabled
+    record: Option<SpanRecord>,
+}
+
+impl Span {
+    /// Adds an attribute to the span.
+    pub fn attr(&mut self, key: &'static str, value: impl Into<String>) {
+        if let Some(record) = &mut self.record {
+            record.attributes.push((key, value.into()));
+        }
+    }
+
+    /// Marks the span as failed.
+    pub fn fail(&mut self, error: &impl Display) {
+        if let Some(record) = &mut self.record {
+            record.error = Some(error.to_string());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(mut record) = self.record.take() else {
+            return;
+        };
+        OPEN.with(|open| open.borrow_mut().retain(|id| *id != record.id));
+        record.end = SystemTime::now();
+        if let Some(recorder) = &mut *recorder() {
+            recorder.spans.push(record);
+        }
+    }
+}
+
+/// Opens a span named `name`, which does nothing if telemetry is disabled.
+pub(crate) fn span(name: impl Into<String>) -> Span {
+    let Some(root) = recorder().as_ref().map(|recorder| recorder.root.id.clone()) else {
+        return Span { record: None };
+    };
+    let parent = OPEN
+        .with(|open| open.borrow().last().cloned())
+        .unwrap_or(root);
+    let record = SpanRecord::start(name.into(), Some(parent));
+    OPEN.with(|open| open.borrow_mut().push(record.id.clone()));
+    Span {
+        record: Some(record),
+    }
+}
+
+/// Adds `value` to a counter.
+pub(crate) fn count(name: &'static str, attributes: Attributes, value: u64) {
+    if let Some(recorder) = &mut *recorder() {
+        *recorder.counters.entry((name, attributes)).or_default() += value;
+    }
+}
+
+/// Records a duration in a latency histogram.
+pub(crate) fn observe(name: &'static str, attributes: Attributes, elapsed: Duration) {
+    if let Some(recorder) = &mut *recorder() {
+        recorder
+            .histograms
+            .entry((name, attributes))
+            .or_default()
+            .push(elapsed.as_secs_f64() * 1000.0);
+    }
+}
+
+/// Ends the root span and exports everything recorded.
+///
+/// Does nothing if telemetry is disabled. Recording stops afterwards.
+///
+/// # Arguments
+///
+/// * `error` - Why the command failed, if it did
+pub(crate) fn finish(error: Option<&dyn Display>) {
+    let Some(mut recorder) = recorder().take() else {
+        return;
+    };
+    recorder.root.end = SystemTime::now();
+    recorder.root.error = error.map(ToString::to_string);
+    #[cfg(feature = "otlp")]
+    let exported = otlp::export(&recorder);
+    #[cfg(not(feature = "otlp"))]
+    let exported = json::export(&recorder);
+    if let Err(e) = exported {
+        warning::warn(format!("failed to export telemetry: {}", e));
+    }
+}
+
+/// Returns the service name telemetry is reported under.
+fn service_name() -> String {
+    std::env::var("OTEL_SERVICE_NAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "secretspec".to_string())
+}
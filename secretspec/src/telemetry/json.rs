@@ -0,0 +1,314 @@
+//! Experimental OTLP/JSON sink for debugging.
+//!
+//! Builds without the `otlp` feature post the recorded telemetry to the
+//! collector's OTLP/HTTP endpoints with curl, encoding it as JSON by hand.
+//! It is meant for trying `--otel` against a local collector: it only reads
+//! `OTEL_EXPORTER_OTLP_ENDPOINT`, `OTEL_EXPORTER_OTLP_HEADERS` and
+//! `OTEL_SERVICE_NAME`, doesn't retry, and doesn't follow the protocol's
+//! partial-success responses. Build with `otlp` to export for real.
+
+use super::{BOUNDS_MS, Recorder, service_name};
+use crate::Result;
+use crate::provider::command::{self, CommandRunner, Invocation, SystemRunner, quote};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Collector used when `OTEL_EXPORTER_OTLP_ENDPOINT` isn't set.
+const DEFAULT_ENDPOINT: &str = "http://localhost:4318";
+
+/// Where telemetry is sent.
+#[derive(Debug, Clone)]
+struct Collector {
+    /// Base URL of the collector's OTLP/HTTP endpoint
+    endpoint: String,
+    /// Extra request headers
+    headers: Vec<(String, String)>,
+    /// Name of the service the telemetry is from
+    service: String,
+}
+
+impl Collector {
+    /// Reads the collector from the standard OpenTelemetry variables.
+    fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            endpoint: var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string()),
+            headers: var("OTEL_EXPORTER_OTLP_HEADERS")
+                .map(|headers| parse_headers(&headers))
+                .unwrap_or_default(),
+            service: service_name(),
+        }
+    }
+}
+
+/// Parses headers given as `key=value` pairs separated by commas.
+fn parse_headers(headers: &str) -> Vec<(String, String)> {
+    headers
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Converts attributes to OTLP key-value pairs.
+fn key_values(attributes: &[(&'static str, String)]) -> Value {
+    attributes
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+/// Formats a time as nanoseconds since the Unix epoch, as OTLP expects.
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// Builds the OTLP resource describing this process.
+fn resource(collector: &Collector) -> Value {
+    json!({
+        "attributes": key_values(&[
+            ("service.name", collector.service.clone()),
+            ("service.version", env!("CARGO_PKG_VERSION").to_string()),
+        ])
+    })
+}
+
+/// Builds the OTLP scope the telemetry is recorded by.
+fn scope() -> Value {
+    json!({ "name": "secretspec", "version": env!("CARGO_PKG_VERSION") })
+}
+
+/// Builds the OTLP/JSON request exporting the recorded spans.
+fn traces_payload(recorder: &Recorder, collector: &Collector) -> Value {
+    let spans: Vec<Value> = recorder
+        .spans
+        .iter()
+        .chain(std::iter::once(&recorder.root))
+        .map(|span| {
+            let mut value = json!({
+                "traceId": recorder.trace_id,
+                "spanId": span.id,
+                "name": span.name,
+                // SPAN_KIND_INTERNAL, or SPAN_KIND_CLIENT for provider calls
+                "kind": if span.name.starts_with("secretspec.provider.") { 3 } else { 1 },
+                "startTimeUnixNano": nanos(span.start),
+                "endTimeUnixNano": nanos(span.end),
+                "attributes": key_values(&span.attributes),
+                "status": match &span.error {
+                    Some(message) => json!({ "code": 2, "message": message }),
+                    None => json!({ "code": 1 }),
+                },
+            });
+            if let Some(parent) = &span.parent {
+                value["parentSpanId"] = json!(parent);
+            }
+            value
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": resource(collector),
+            "scopeSpans": [{ "scope": scope(), "spans": spans }],
+        }]
+    })
+}
+
+/// Builds the OTLP/JSON request exporting the recorded metrics.
+fn metrics_payload(recorder: &Recorder, collector: &Collector) -> Value {
+    let start = nanos(recorder.root.start);
+    let now = nanos(recorder.root.end);
+    let mut sums: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+    for ((name, attributes), value) in &recorder.counters {
+        sums.entry(name).or_default().push(json!({
+            "attributes": key_values(attributes),
+            "startTimeUnixNano": start,
+            "timeUnixNano": now,
+            "asInt": value.to_string(),
+        }));
+    }
+    let mut histograms: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+    for ((name, attributes), samples) in &recorder.histograms {
+        // Values per bucket, the last one unbounded
+        let mut buckets = [0u64; BOUNDS_MS.len() + 1];
+        for millis in samples {
+            let bucket = BOUNDS_MS
+                .iter()
+                .position(|bound| millis <= bound)
+                .unwrap_or(BOUNDS_MS.len());
+            buckets[bucket] += 1;
+        }
+        histograms.entry(name).or_default().push(json!({
+            "attributes": key_values(attributes),
+            "startTimeUnixNano": start,
+            "timeUnixNano": now,
+            "count": samples.len().to_string(),
+            "sum": samples.iter().sum::<f64>(),
+            "bucketCounts": buckets.iter().map(u64::to_string).collect::<Vec<_>>(),
+            "explicitBounds": BOUNDS_MS,
+        }));
+    }
+
+    // Cumulative temporality, since each command reports from its own start
+    let metrics: Vec<Value> = sums
+        .into_iter()
+        .map(|(name, points)| {
+            json!({
+                "name": name,
+                "unit": "1",
+                "sum": { "dataPoints": points, "aggregationTemporality": 2, "isMonotonic": true },
+            })
+        })
+        .chain(histograms.into_iter().map(|(name, points)| {
+            json!({
+                "name": name,
+                "unit": "ms",
+                "histogram": { "dataPoints": points, "aggregationTemporality": 2 },
+            })
+        }))
+        .collect();
+    json!({
+        "resourceMetrics": [{
+            "resource": resource(collector),
+            "scopeMetrics": [{ "scope": scope(), "metrics": metrics }],
+        }]
+    })
+}
+
+/// Sends the recorded spans and metrics to the collector configured in the
+/// environment.
+pub(super) fn export(recorder: &Recorder) -> Result<()> {
+    export_to(recorder, &Collector::from_env(), &SystemRunner)
+}
+
+/// Sends the recorded spans and metrics to `collector`.
+fn export_to(recorder: &Recorder, collector: &Collector, runner: &dyn CommandRunner) -> Result<()> {
+    post(
+        runner,
+        collector,
+        "traces",
+        &traces_payload(recorder, collector),
+    )?;
+    if !recorder.counters.is_empty() || !recorder.histograms.is_empty() {
+        post(
+            runner,
+            collector,
+            "metrics",
+            &metrics_payload(recorder, collector),
+        )?;
+    }
+    Ok(())
+}
+
+/// POSTs an OTLP/JSON request to one of the collector's signal endpoints.
+///
+/// The URL, headers and payload are passed on standard input, since headers
+/// usually carry an API key.
+fn post(
+    runner: &dyn CommandRunner,
+    collector: &Collector,
+    signal: &str,
+    payload: &Value,
+) -> Result<()> {
+    let mut config = format!(
+        "url = {}\nheader = \"Content-Type: application/json\"\ndata-binary = {}\n",
+        quote(&format!(
+            "{}/v1/{}",
+            collector.endpoint.trim_end_matches('/'),
+            signal
+        )),
+        quote(&payload.to_string())
+    );
+    for (key, value) in &collector.headers {
+        config.push_str(&format!(
+            "header = {}\n",
+            quote(&format!("{}: {}", key, value))
+        ));
+    }
+    let invocation = Invocation::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--config", "-"])
+        .stdin(config);
+    command::curl(runner, &invocation, "export telemetry", "curl failed")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::command::FakeRunner;
+    use crate::telemetry::{Attributes, SpanRecord, random_id};
+
+    fn recorder() -> Recorder {
+        let mut root = SpanRecord::start("secretspec check".to_string(), None);
+        root.error = Some("missing DATABASE_URL".to_string());
+        let mut call =
+            SpanRecord::start("secretspec.provider.get".to_string(), Some(root.id.clone()));
+        call.attributes
+            .push(("secretspec.secret", "DATABASE_URL".to_string()));
+        let attributes: Attributes = vec![("secretspec.provider", "keyring".to_string())];
+        Recorder {
+            trace_id: random_id::<16>(),
+            root,
+            spans: vec![call],
+            counters: BTreeMap::from([(("secretspec.provider.calls", attributes.clone()), 2)]),
+            histograms: BTreeMap::from([(
+                ("secretspec.provider.duration", attributes),
+                vec![42.0],
+            )]),
+        }
+    }
+
+    fn collector() -> Collector {
+        Collector {
+            endpoint: "https://otel.example.com/".to_string(),
+            headers: parse_headers("x-api-key=s3cret, x-team = infra"),
+            service: "deploy".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_payloads() {
+        let recorder = recorder();
+        let traces = traces_payload(&recorder, &collector());
+        let spans = &traces["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(spans.as_array().unwrap().len(), 2);
+        assert_eq!(spans[0]["parentSpanId"], json!(recorder.root.id));
+        assert_eq!(spans[0]["kind"], json!(3));
+        assert_eq!(spans[1]["name"], json!("secretspec check"));
+        assert_eq!(spans[1]["status"]["code"], json!(2));
+        assert!(spans[1].get("parentSpanId").is_none());
+        assert_eq!(recorder.trace_id.len(), 32);
+        assert_eq!(recorder.root.id.len(), 16);
+
+        let metrics = metrics_payload(&recorder, &collector());
+        let metrics = &metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics[0]["name"], json!("secretspec.provider.calls"));
+        assert_eq!(metrics[0]["sum"]["dataPoints"][0]["asInt"], json!("2"));
+        assert_eq!(
+            metrics[1]["histogram"]["dataPoints"][0]["bucketCounts"][3],
+            json!("1")
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let runner = FakeRunner::new()
+            .ok(&["curl", "*", "*", "*", "*", "-"], "")
+            .ok(&["curl", "*", "*", "*", "*", "-"], "");
+        export_to(&recorder(), &collector(), &runner).unwrap();
+
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 2);
+        let config = String::from_utf8(calls[0].stdin.clone().unwrap()).unwrap();
+        assert!(config.contains("url = \"https://otel.example.com/v1/traces\""));
+        assert!(config.contains("header = \"x-api-key: s3cret\""));
+        assert!(config.contains("header = \"x-team: infra\""));
+        assert!(calls[0].args.iter().all(|arg| !arg.contains("s3cret")));
+        let config = String::from_utf8(calls[1].stdin.clone().unwrap()).unwrap();
+        assert!(config.contains("url = \"https://otel.example.com/v1/metrics\""));
+    }
+}
@@ -0,0 +1,170 @@
+//! Exporting with the OpenTelemetry SDK's OTLP exporter.
+//!
+//! Spans are handed to the exporter as recorded, keeping their IDs and
+//! timestamps. Metrics are replayed into an SDK meter provider, which
+//! aggregates them with cumulative temporality and exports them when it shuts
+//! down.
+
+use super::{Attributes, BOUNDS_MS, Recorder, SpanRecord, service_name};
+use crate::{Result, SecretSpecError};
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry::trace::{
+    SpanContext, SpanId, SpanKind, Status, TraceFlags, TraceId, TraceState,
+};
+use opentelemetry::{InstrumentationScope, KeyValue};
+use opentelemetry_otlp::{MetricExporter, SpanExporter};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::{SdkMeterProvider, Temporality};
+use opentelemetry_sdk::trace::{SpanData, SpanEvents, SpanExporter as _, SpanLinks};
+use std::fmt::Display;
+
+/// Returns the error for a failed export.
+fn export_failed(e: impl Display) -> SecretSpecError {
+    SecretSpecError::ProviderOperationFailed(format!("OTLP export failed: {}", e))
+}
+
+/// Converts attributes to OpenTelemetry key-values.
+fn key_values(attributes: &Attributes) -> Vec<KeyValue> {
+    attributes
+        .iter()
+        .map(|(key, value)| KeyValue::new(*key, value.clone()))
+        .collect()
+}
+
+/// Returns the resource describing this process.
+fn resource() -> Resource {
+    Resource::builder()
+        .with_service_name(service_name())
+        .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+/// Returns the scope the telemetry is recorded by.
+fn scope() -> InstrumentationScope {
+    InstrumentationScope::builder("secretspec")
+        .with_version(env!("CARGO_PKG_VERSION"))
+        .build()
+}
+
+/// Converts a recorded span to what the exporter sends.
+fn span_data(trace_id: TraceId, span: &SpanRecord) -> Result<SpanData> {
+    let span_id = SpanId::from_hex(&span.id).map_err(export_failed)?;
+    let parent_span_id = match &span.parent {
+        Some(parent) => SpanId::from_hex(parent).map_err(export_failed)?,
+        None => SpanId::INVALID,
+    };
+    Ok(SpanData {
+        span_context: SpanContext::new(
+            trace_id,
+            span_id,
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        ),
+        parent_span_id,
+        parent_span_is_remote: false,
+        span_kind: if span.name.starts_with("secretspec.provider.") {
+            SpanKind::Client
+        } else {
+            SpanKind::Internal
+        },
+        name: span.name.clone().into(),
+        start_time: span.start,
+        end_time: span.end,
+        attributes: key_values(&span.attributes),
+        dropped_attributes_count: 0,
+        events: SpanEvents::default(),
+        links: SpanLinks::default(),
+        status: match &span.error {
+            Some(message) => Status::error(message.clone()),
+            None => Status::Ok,
+        },
+        instrumentation_scope: scope(),
+    })
+}
+
+/// Converts the recorded spans, the command's root span last.
+fn spans(recorder: &Recorder) -> Result<Vec<SpanData>> {
+    let trace_id = TraceId::from_hex(&recorder.trace_id).map_err(export_failed)?;
+    recorder
+        .spans
+        .iter()
+        .chain(std::iter::once(&recorder.root))
+        .map(|span| span_data(trace_id, span))
+        .collect()
+}
+
+/// Sends the recorded spans and metrics to the collector configured in the
+/// environment.
+pub(super) fn export(recorder: &Recorder) -> Result<()> {
+    let mut exporter = SpanExporter::builder()
+        .with_http()
+        .build()
+        .map_err(export_failed)?;
+    exporter.set_resource(&resource());
+    futures_executor::block_on(exporter.export(spans(recorder)?)).map_err(export_failed)?;
+
+    if recorder.counters.is_empty() && recorder.histograms.is_empty() {
+        return Ok(());
+    }
+    let exporter = MetricExporter::builder()
+        .with_http()
+        .with_temporality(Temporality::Cumulative)
+        .build()
+        .map_err(export_failed)?;
+    let provider = SdkMeterProvider::builder()
+        .with_resource(resource())
+        .with_periodic_exporter(exporter)
+        .build();
+    let meter = provider.meter_with_scope(scope());
+    for ((name, attributes), value) in &recorder.counters {
+        meter
+            .u64_counter(*name)
+            .with_unit("1")
+            .build()
+            .add(*value, &key_values(attributes));
+    }
+    for ((name, attributes), samples) in &recorder.histograms {
+        let histogram = meter
+            .f64_histogram(*name)
+            .with_unit("ms")
+            .with_boundaries(BOUNDS_MS.to_vec())
+            .build();
+        for millis in samples {
+            histogram.record(*millis, &key_values(attributes));
+        }
+    }
+    // Shutting down exports what was recorded
+    provider.shutdown().map_err(export_failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_spans() {
+        let mut root = SpanRecord::start("secretspec check".to_string(), None);
+        root.error = Some("missing DATABASE_URL".to_string());
+        let call = SpanRecord::start("secretspec.provider.get".to_string(), Some(root.id.clone()));
+        let recorder = Recorder {
+            trace_id: super::super::random_id::<16>(),
+            root,
+            spans: vec![call],
+            counters: BTreeMap::new(),
+            histograms: BTreeMap::new(),
+        };
+
+        let spans = spans(&recorder).unwrap();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].parent_span_id, spans[1].span_context.span_id());
+        assert_eq!(spans[0].span_kind, SpanKind::Client);
+        assert_eq!(
+            spans[0].span_context.trace_id(),
+            spans[1].span_context.trace_id()
+        );
+        assert_eq!(spans[1].parent_span_id, SpanId::INVALID);
+        assert!(matches!(spans[1].status, Status::Error { .. }));
+    }
+}
@@ -0,0 +1,146 @@
+//! Terraform `external` data source protocol
+//!
+//! `secretspec terraform-output` lets Terraform read secrets from any
+//! provider:
+//!
+//! ```hcl
+//! data "external" "secrets" {
+//!   program = ["secretspec", "terraform-output"]
+//!   query   = { profile = "production", secrets = "DATABASE_URL,API_KEY" }
+//! }
+//! ```
+//!
+//! Terraform writes the query as a JSON object of strings to stdin and reads
+//! a JSON object of strings from stdout. Errors go to stderr with a non-zero
+//! exit status, which Terraform shows as the reason the data source failed.
+
+use crate::{Result, SecretSpecError};
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::{BTreeMap, HashMap};
+use zeroize::Zeroizing;
+
+/// Settings Terraform passes in the `query` argument.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Query {
+    /// Provider to read from, instead of the configured one
+    pub provider: Option<String>,
+    /// Profile to read
+    pub profile: Option<String>,
+    /// Only read the secrets in these groups
+    pub groups: Vec<String>,
+    /// Secrets to return; all resolved secrets if empty
+    pub secrets: Vec<String>,
+}
+
+/// Splits a comma-separated query value.
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Query {
+    /// Parses the query Terraform writes to stdin.
+    ///
+    /// An empty input is an empty query, as when `query` is omitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input isn't a JSON object of strings or has
+    /// an unknown key
+    pub(crate) fn parse(input: &str) -> Result<Self> {
+        if input.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let values: HashMap<String, String> = serde_json::from_str(input).map_err(|e| {
+            SecretSpecError::InvalidInput(format!(
+                "Terraform query must be a JSON object of strings: {}",
+                e
+            ))
+        })?;
+
+        let mut query = Self::default();
+        for (key, value) in values {
+            match key.as_str() {
+                "provider" => query.provider = Some(value),
+                "profile" => query.profile = Some(value),
+                "group" => query.groups = list(&value),
+                "secrets" => query.secrets = list(&value),
+                _ => {
+                    return Err(SecretSpecError::InvalidInput(format!(
+                        "Unknown Terraform query key '{}'. Use provider, profile, group or secrets.",
+                        key
+                    )));
+                }
+            }
+        }
+        Ok(query)
+    }
+}
+
+/// Formats the requested secrets as the JSON object Terraform reads.
+///
+/// # Errors
+///
+/// Returns an error if a requested secret wasn't resolved
+pub(crate) fn output(
+    secrets: &HashMap<String, SecretString>,
+    requested: &[String],
+) -> Result<Zeroizing<String>> {
+    let selected: BTreeMap<&str, &str> = if requested.is_empty() {
+        secrets
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.expose_secret()))
+            .collect()
+    } else {
+        requested
+            .iter()
+            .map(|name| match secrets.get(name) {
+                Some(value) => Ok((name.as_str(), value.expose_secret())),
+                None => Err(SecretSpecError::SecretNotFound(name.clone())),
+            })
+            .collect::<Result<_>>()?
+    };
+    Ok(Zeroizing::new(serde_json::to_string(&selected)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(Query::parse("").unwrap(), Query::default());
+        assert_eq!(
+            Query::parse(r#"{"profile": "production", "secrets": "DATABASE_URL, API_KEY"}"#)
+                .unwrap(),
+            Query {
+                profile: Some("production".to_string()),
+                secrets: vec!["DATABASE_URL".to_string(), "API_KEY".to_string()],
+                ..Query::default()
+            }
+        );
+        assert!(Query::parse(r#"{"profle": "production"}"#).is_err());
+        assert!(Query::parse(r#"{"secrets": ["API_KEY"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_output() {
+        let secrets: HashMap<String, SecretString> = [("B", "2"), ("A", "1")]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), SecretString::from(value)))
+            .collect();
+        assert_eq!(
+            output(&secrets, &[]).unwrap().as_str(),
+            r#"{"A":"1","B":"2"}"#
+        );
+        assert_eq!(
+            output(&secrets, &["B".to_string()]).unwrap().as_str(),
+            r#"{"B":"2"}"#
+        );
+        assert!(output(&secrets, &["C".to_string()]).is_err());
+    }
+}
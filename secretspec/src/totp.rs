@@ -0,0 +1,181 @@
+//! Time-based one-time passwords (RFC 6238)
+//!
+//! Generates the 6-digit codes that virtual MFA devices show, from the
+//! base32 seed they were enrolled with. AWS and most other services use
+//! HMAC-SHA1 with a 30 second step, which is all this module supports.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds each code is valid for.
+const STEP: u64 = 30;
+
+/// Number of digits in a code.
+const DIGITS: u32 = 6;
+
+/// Returns the code for the current time.
+///
+/// # Errors
+///
+/// Returns an error if the seed is not valid base32
+pub(crate) fn now(seed: &str) -> Result<String, String> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    at(seed, time)
+}
+
+/// Returns the code for a Unix timestamp.
+///
+/// # Errors
+///
+/// Returns an error if the seed is not valid base32
+pub(crate) fn at(seed: &str, time: u64) -> Result<String, String> {
+    let key = base32_decode(seed).ok_or("TOTP seed is not valid base32")?;
+    let mac = hmac_sha1(&key, &(time / STEP).to_be_bytes());
+
+    let offset = (mac[19] & 0x0f) as usize;
+    let code = u32::from_be_bytes([
+        mac[offset] & 0x7f,
+        mac[offset + 1],
+        mac[offset + 2],
+        mac[offset + 3],
+    ]) % 10u32.pow(DIGITS);
+    Ok(format!("{:0width$}", code, width = DIGITS as usize))
+}
+
+/// Decodes RFC 4648 base32, ignoring case, spaces and padding.
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut bits: u64 = 0;
+    let mut count = 0;
+    let mut out = Vec::new();
+
+    for c in input.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u64 - 'A' as u64,
+            c @ '2'..='7' => c as u64 - '2' as u64 + 26,
+            _ => return None,
+        };
+        bits = (bits << 5) | value;
+        count += 5;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+
+    (!out.is_empty()).then_some(out)
+}
+
+/// Computes HMAC-SHA1 (RFC 2104).
+fn hmac_sha1(key: &[u8], message: &[u8]) -> [u8; 20] {
+    const BLOCK: usize = 64;
+
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..20].copy_from_slice(&sha1(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&sha1(&inner));
+    sha1(&outer)
+}
+
+/// Computes SHA-1 (RFC 3174).
+///
+/// SHA-1 is only used here as the HMAC hash that TOTP mandates, where its
+/// collision weaknesses don't apply.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_sha1_and_hmac() {
+        assert_eq!(
+            hex(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        // RFC 2202 test case 2
+        assert_eq!(
+            hex(&hmac_sha1(b"Jefe", b"what do ya want for nothing?")),
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+        );
+    }
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        // The RFC's SHA1 seed "12345678901234567890", base32 encoded
+        let seed = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        assert_eq!(at(seed, 59).unwrap(), "287082");
+        assert_eq!(at(seed, 1111111109).unwrap(), "081804");
+        assert_eq!(at(seed, 2000000000).unwrap(), "279037");
+        // Seeds are often shown lowercase and grouped
+        assert_eq!(
+            at("gezd gnbv gy3t qojq gezd gnbv gy3t qojq", 59).unwrap(),
+            "287082"
+        );
+        assert!(at("not base32!", 59).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! TPM-sealed age identities
+//!
+//! The local encrypted store is an `enc+` provider such as
+//! `enc+dotenv://.env.encrypted`, and its master key is the age identity
+//! that decrypts it. On headless Linux servers with a TPM 2.0 the identity
+//! can be sealed to the TPM instead of kept in a plain file or the OS
+//! keyring:
+//!
+//! ```bash
+//! secretspec tpm-seal key.txt /etc/secretspec/identity.tpm
+//! ```
+//!
+//! ```text
+//! enc+dotenv://.env.encrypted?key=age1...&identity=tpm:/etc/secretspec/identity.tpm
+//! ```
+//!
+//! The sealed directory holds the public and private parts of a TPM data
+//! object under the owner hierarchy's storage key, so it can only be
+//! unsealed by the TPM it was sealed with. Sealing and unsealing use the
+//! `tpm2-tools` CLIs. A sealed object holds at most 128 bytes, which fits an
+//! age X25519 identity but not an SSH key.
+
+use crate::provider::command::{CommandRunner, Invocation};
+use crate::runfiles;
+use crate::{Result, SecretSpecError};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+use zeroize::Zeroizing;
+
+/// Prefix of an `identity` parameter naming a sealed identity.
+pub(crate) const IDENTITY_PREFIX: &str = "tpm:";
+
+/// File holding the public part of the sealed object.
+const PUBLIC: &str = "seal.pub";
+
+/// File holding the private part of the sealed object, encrypted by the TPM.
+const PRIVATE: &str = "seal.priv";
+
+/// Prefix of the secret key line of an age identity file.
+const AGE_SECRET_KEY: &str = "AGE-SECRET-KEY-";
+
+/// Seals the age identity in `identity` to the TPM, writing the sealed
+/// object to the directory `output`.
+///
+/// # Errors
+///
+/// Returns an error if `identity` holds no age secret key, tpm2-tools are
+/// missing or the TPM refuses to seal it
+pub(crate) fn seal(identity: &str, output: &Path, runner: &dyn CommandRunner) -> Result<()> {
+    let key = identity
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with(AGE_SECRET_KEY))
+        .ok_or_else(|| {
+            SecretSpecError::InvalidInput(
+                "Only age identities (AGE-SECRET-KEY-...) can be sealed to the TPM".to_string(),
+            )
+        })?;
+    fs::create_dir_all(output)?;
+    let scratch = tempfile::tempdir()?;
+    let primary = create_primary(scratch.path(), runner)?;
+    run(
+        runner,
+        Invocation::new("tpm2_create")
+            .args(["-Q", "-C", &primary, "-i", "-"])
+            .args(object_args(output))
+            .stdin(key.as_bytes()),
+    )?;
+    Ok(())
+}
+
+/// Unseals the identity in the directory `sealed`.
+///
+/// # Errors
+///
+/// Returns an error if tpm2-tools are missing, the sealed object is missing
+/// or the TPM refuses to unseal it
+pub(crate) fn unseal(sealed: &Path, runner: &dyn CommandRunner) -> Result<Zeroizing<String>> {
+    for part in [PUBLIC, PRIVATE] {
+        if !sealed.join(part).exists() {
+            return Err(SecretSpecError::NotFound(format!(
+                "No sealed identity in {}; create one with 'secretspec tpm-seal'",
+                sealed.display()
+            )));
+        }
+    }
+    let scratch = tempfile::tempdir()?;
+    let primary = create_primary(scratch.path(), runner)?;
+    let object = scratch.path().join("seal.ctx").display().to_string();
+    run(
+        runner,
+        Invocation::new("tpm2_load")
+            .args(["-Q", "-C", &primary])
+            .args(object_args(sealed))
+            .args(["-c", &object]),
+    )?;
+    let identity = run(runner, Invocation::new("tpm2_unseal").args(["-c", &object]))?;
+    Ok(Zeroizing::new(format!("{}\n", identity.trim())))
+}
+
+/// Writes an unsealed identity to a file only the owner can read, removed
+/// when dropped, for `age --identity`.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be written
+pub(crate) fn identity_file(identity: &str) -> Result<NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix("secretspec-identity-")
+        .tempfile_in(runfiles::private_dir())?;
+    file.write_all(identity.as_bytes())?;
+    file.flush()?;
+    Ok(file)
+}
+
+/// Returns the arguments naming the parts of the sealed object in `dir`.
+fn object_args(dir: &Path) -> [String; 4] {
+    [
+        "-u".to_string(),
+        dir.join(PUBLIC).display().to_string(),
+        "-r".to_string(),
+        dir.join(PRIVATE).display().to_string(),
+    ]
+}
+
+/// Creates the owner hierarchy's storage key in `scratch` and returns the
+/// path of its context.
+///
+/// The key is derived from the TPM's seed and the default template, so it
+/// is the same key every time and needn't be persisted.
+fn create_primary(scratch: &Path, runner: &dyn CommandRunner) -> Result<String> {
+    let context = scratch.join("primary.ctx").display().to_string();
+    run(
+        runner,
+        Invocation::new("tpm2_createprimary").args(["-Q", "-C", "o", "-c", &context]),
+    )?;
+    Ok(context)
+}
+
+/// Runs a tpm2-tools command and returns its output.
+fn run(runner: &dyn CommandRunner, invocation: Invocation) -> Result<Zeroizing<String>> {
+    let output = match runner.run(&invocation) {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(SecretSpecError::CliMissing {
+                cli: "TPM2 tools (tpm2-tools)".to_string(),
+                install_hint: "To install it:\n  - Debian/Ubuntu: apt install tpm2-tools\n  - Fedora: dnf install tpm2-tools\n  - NixOS: nix-env -iA nixpkgs.tpm2-tools".to_string(),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !output.success {
+        let stderr = output.stderr.trim();
+        if stderr.contains("Permission denied") {
+            return Err(SecretSpecError::PermissionDenied(format!(
+                "{}: {}\n\nAdd yourself to the tss group to use the TPM",
+                invocation.program, stderr
+            )));
+        }
+        return Err(SecretSpecError::ProviderOperationFailed(format!(
+            "{} failed: {}",
+            invocation.program, stderr
+        )));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::command::FakeRunner;
+
+    const IDENTITY: &str =
+        "# created: 2026-10-16T12:00:00Z\n# public key: age1example\nAGE-SECRET-KEY-1EXAMPLE\n";
+
+    #[test]
+    fn test_seal() {
+        let dir = tempfile::tempdir().unwrap();
+        let sealed = dir.path().join("identity.tpm");
+        let runner = FakeRunner::new()
+            .ok(&["tpm2_createprimary", "-Q", "-C", "o", "-c", "*"], "")
+            .ok(
+                &[
+                    "tpm2_create",
+                    "-Q",
+                    "-C",
+                    "*",
+                    "-i",
+                    "-",
+                    "-u",
+                    "*",
+                    "-r",
+                    "*",
+                ],
+                "",
+            );
+        assert!(seal("not an identity", &sealed, &runner).is_err());
+        seal(IDENTITY, &sealed, &runner).unwrap();
+        assert!(runner.is_exhausted());
+
+        let calls = runner.calls();
+        assert_eq!(
+            calls[1].stdin.as_deref(),
+            Some(b"AGE-SECRET-KEY-1EXAMPLE".as_slice())
+        );
+        assert_eq!(calls[1].args[6], sealed.join(PUBLIC).display().to_string());
+    }
+
+    #[test]
+    fn test_unseal() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = FakeRunner::new();
+        assert!(matches!(
+            unseal(dir.path(), &runner),
+            Err(SecretSpecError::NotFound(_))
+        ));
+
+        fs::write(dir.path().join(PUBLIC), "").unwrap();
+        fs::write(dir.path().join(PRIVATE), "").unwrap();
+        let runner = FakeRunner::new()
+            .ok(&["tpm2_createprimary", "-Q", "-C", "o", "-c", "*"], "")
+            .ok(
+                &[
+                    "tpm2_load",
+                    "-Q",
+                    "-C",
+                    "*",
+                    "-u",
+                    "*",
+                    "-r",
+                    "*",
+                    "-c",
+                    "*",
+                ],
+                "",
+            )
+            .ok(&["tpm2_unseal", "-c", "*"], "AGE-SECRET-KEY-1EXAMPLE");
+        let identity = unseal(dir.path(), &runner).unwrap();
+        assert!(runner.is_exhausted());
+        assert_eq!(identity.as_str(), "AGE-SECRET-KEY-1EXAMPLE\n");
+        let file = identity_file(&identity).unwrap();
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "AGE-SECRET-KEY-1EXAMPLE\n"
+        );
+
+        let runner = FakeRunner::new()
+            .ok(&["tpm2_createprimary", "-Q", "-C", "o", "-c", "*"], "")
+            .fail(
+                &[
+                    "tpm2_load",
+                    "-Q",
+                    "-C",
+                    "*",
+                    "-u",
+                    "*",
+                    "-r",
+                    "*",
+                    "-c",
+                    "*",
+                ],
+                "ERROR: Esys_Load(0x1DF) - tpm:parameter(1):integrity check failed",
+            );
+        assert!(unseal(dir.path(), &runner).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Value transforms
+//!
+//! A secret can list transforms that are applied, in order, to its value
+//! after it is resolved:
+//!
+//! ```toml
+//! [profiles.default]
+//! GCP_SERVICE_ACCOUNT = { description = "Service account key, base64 encoded JSON" }
+//! GCP_PRIVATE_KEY = { description = "Private key", required = false, default = "ref:GCP_SERVICE_ACCOUNT", transform = ["trim", "base64d", "jsonpath:$.private_key"] }
+//! ```
+//!
+//! Combined with `ref:` defaults, one stored item can feed several secrets.
+
+use crate::{Result, SecretSpecError};
+use base64::{Engine as _, engine::general_purpose};
+use secrecy::{ExposeSecret, SecretString};
+use std::str::FromStr;
+use zeroize::{Zeroize, Zeroizing};
+
+/// A step of a secret's `transform` list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Transform {
+    /// `trim`: removes leading and trailing whitespace
+    Trim,
+    /// `base64d`: decodes standard base64 into UTF-8 text
+    Base64Decode,
+    /// `jsonpath:<path>`: selects a value from a JSON document
+    JsonPath(Vec<Segment>),
+}
+
+/// A step of a JSON path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Segment {
+    /// An object member, `.name` or `['name']`
+    Member(String),
+    /// An array element, `[0]`
+    Index(usize),
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(transform: &str) -> std::result::Result<Self, Self::Err> {
+        match transform {
+            "trim" => Ok(Self::Trim),
+            "base64d" => Ok(Self::Base64Decode),
+            _ => match transform.strip_prefix("jsonpath:") {
+                Some(path) => parse_path(path).map(Self::JsonPath),
+                None => Err(format!(
+                    "unknown transform '{}'. Use trim, base64d or jsonpath:<path>",
+                    transform
+                )),
+            },
+        }
+    }
+}
+
+impl Transform {
+    /// Applies the transform to a value.
+    ///
+    /// Intermediate copies of the value are zeroed before they are freed.
+    fn apply(&self, value: &str) -> std::result::Result<Zeroizing<String>, String> {
+        match self {
+            Self::Trim => Ok(Zeroizing::new(value.trim().to_string())),
+            Self::Base64Decode => {
+                let bytes = general_purpose::STANDARD
+                    .decode(value.trim())
+                    .map_err(|e| format!("invalid base64: {}", e))?;
+                String::from_utf8(bytes).map(Zeroizing::new).map_err(|e| {
+                    e.into_bytes().zeroize();
+                    "decoded value is not UTF-8 text".to_string()
+                })
+            }
+            Self::JsonPath(path) => {
+                let mut document: serde_json::Value =
+                    serde_json::from_str(value).map_err(|e| format!("invalid JSON: {}", e))?;
+                let selected = path
+                    .iter()
+                    .try_fold(&document, |current, segment| match segment {
+                        Segment::Member(name) => current.get(name),
+                        Segment::Index(index) => current.get(index),
+                    })
+                    .map(|selected| match selected {
+                        serde_json::Value::String(text) => Zeroizing::new(text.clone()),
+                        other => Zeroizing::new(other.to_string()),
+                    });
+                zeroize_json(&mut document);
+                selected.ok_or_else(|| "path not found".to_string())
+            }
+        }
+    }
+}
+
+/// Zeroes the strings of a JSON document before it is dropped.
+fn zeroize_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => text.zeroize(),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(zeroize_json),
+        serde_json::Value::Object(members) => members.values_mut().for_each(zeroize_json),
+        _ => {}
+    }
+}
+
+/// Parses a JSON path like `$.credentials[0]['private key']`.
+fn parse_path(path: &str) -> std::result::Result<Vec<Segment>, String> {
+    let invalid = || format!("invalid JSON path '{}'", path);
+    let mut rest = path.strip_prefix('$').ok_or_else(invalid)?;
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(Segment::Member(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix("['") {
+            let end = after.find("']").ok_or_else(invalid)?;
+            segments.push(Segment::Member(after[..end].to_string()));
+            rest = &after[end + 2..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(invalid)?;
+            let index = after[..end].parse().map_err(|_| invalid())?;
+            segments.push(Segment::Index(index));
+            rest = &after[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Applies a secret's transforms to its value.
+///
+/// # Errors
+///
+/// Returns an error naming the secret and the transform that failed
+pub(crate) fn apply(
+    name: &str,
+    transforms: &[String],
+    value: SecretString,
+) -> Result<SecretString> {
+    if transforms.is_empty() {
+        return Ok(value);
+    }
+
+    let mut current = Zeroizing::new(value.expose_secret().to_string());
+    for transform in transforms {
+        current = transform
+            .parse::<Transform>()
+            .and_then(|parsed| parsed.apply(&current))
+            .map_err(|e| {
+                SecretSpecError::InvalidInput(format!(
+                    "Secret '{}': transform '{}' failed: {}",
+                    name, transform, e
+                ))
+            })?;
+    }
+    Ok(SecretString::from(current.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(transforms: &[&str], value: &str) -> Result<String> {
+        let transforms: Vec<String> = transforms.iter().map(|t| t.to_string()).collect();
+        apply("KEY", &transforms, SecretString::new(value.into()))
+            .map(|value| value.expose_secret().to_string())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("trim".parse(), Ok(Transform::Trim));
+        assert_eq!(
+            "jsonpath:$.credentials[0]['private key']".parse(),
+            Ok(Transform::JsonPath(vec![
+                Segment::Member("credentials".to_string()),
+                Segment::Index(0),
+                Segment::Member("private key".to_string()),
+            ]))
+        );
+        assert!("upper".parse::<Transform>().is_err());
+        assert!("jsonpath:credentials".parse::<Transform>().is_err());
+        assert!("jsonpath:$.".parse::<Transform>().is_err());
+        assert!("jsonpath:$[x]".parse::<Transform>().is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let document =
+            r#"{"private_key": "-----BEGIN KEY-----", "port": 5432, "hosts": ["a", "b"]}"#;
+        let encoded = general_purpose::STANDARD.encode(document);
+
+        assert_eq!(run(&[], " value\n").unwrap(), " value\n");
+        assert_eq!(run(&["trim"], " value\n").unwrap(), "value");
+        assert_eq!(
+            run(
+                &["trim", "base64d", "jsonpath:$.private_key"],
+                &format!("{}\n", encoded)
+            )
+            .unwrap(),
+            "-----BEGIN KEY-----"
+        );
+        assert_eq!(run(&["jsonpath:$.port"], document).unwrap(), "5432");
+        assert_eq!(run(&["jsonpath:$.hosts[1]"], document).unwrap(), "b");
+        assert_eq!(
+            run(&["jsonpath:$.hosts"], document).unwrap(),
+            r#"["a","b"]"#
+        );
+
+        let err = run(&["jsonpath:$.missing"], document).unwrap_err();
+        assert!(err.to_string().contains("path not found"));
+        assert!(run(&["base64d"], "not base64!").is_err());
+        assert!(run(&["jsonpath:$.a"], "not json").is_err());
+    }
+
+    #[test]
+    fn test_apply_leaves_no_copies() {
+        const SECRET: &str = "s3cret-7d2a";
+        let transforms = ["jsonpath:$.key".to_string(), "trim".to_string()];
+        let encoded = general_purpose::STANDARD.encode(SECRET);
+
+        let hits = crate::memory::lingering(SECRET, || {
+            let document = SecretString::from(r#"{"key": "  s3cret-7d2a  ", "port": 5432}"#);
+            let value = apply("KEY", &transforms, document).unwrap();
+            assert_eq!(value.expose_secret(), SECRET);
+
+            let decoded = apply(
+                "KEY",
+                &["base64d".to_string()],
+                SecretString::from(encoded.as_str()),
+            )
+            .unwrap();
+            assert_eq!(decoded.expose_secret(), SECRET);
+        });
+        assert_eq!(hits, 0);
+    }
+}
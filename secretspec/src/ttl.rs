@@ -0,0 +1,120 @@
+//! Expiry of ephemeral secrets.
+//!
+//! Secrets declared with a `ttl`, such as tokens minted for a CI run, are
+//! tagged with their expiry whenever they are written. The tag is stored
+//! next to the value, so any machine sharing the vault sees it:
+//!
+//! ```text
+//! CI_DEPLOY_TOKEN          -> the value
+//! CI_DEPLOY_TOKEN.expires  -> 1767225600
+//! ```
+//!
+//! `secretspec gc` deletes the values past their expiry, together with their
+//! tags, so shared vaults don't fill up with stale secrets.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Suffix of the key an expiry tag is stored under.
+const TAG_SUFFIX: &str = ".expires";
+
+/// Parses a ttl like `90s`, `30m`, `24h`, `7d` or `2w`.
+pub(crate) fn parse(ttl: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "'{}' is not a duration. Use a number followed by s, m, h, d or w, e.g. 24h",
+            ttl
+        )
+    };
+    let (number, unit) = ttl.split_at(ttl.find(|c: char| !c.is_ascii_digit()).unwrap_or(ttl.len()));
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .filter(|count| *count > 0)
+        .map(|count| Duration::from_secs(count * seconds))
+        .ok_or_else(invalid)
+}
+
+/// Returns the key the expiry tag of a secret is stored under.
+pub(crate) fn tag_key(key: &str) -> String {
+    format!("{}{}", key, TAG_SUFFIX)
+}
+
+/// Returns the secret an expiry tag belongs to, or `None` if the key isn't
+/// a tag.
+pub(crate) fn tagged_key(key: &str) -> Option<&str> {
+    key.strip_suffix(TAG_SUFFIX)
+        .filter(|secret| !secret.is_empty())
+}
+
+/// Returns the tag of a secret written now that lives for `ttl`: the time it
+/// expires, in seconds since the Unix epoch.
+pub(crate) fn tag(ttl: Duration) -> String {
+    (now() + ttl.as_secs()).to_string()
+}
+
+/// Returns how many seconds are left until the expiry in a tag, negative
+/// once it has passed, or `None` if the tag isn't a time.
+pub(crate) fn remaining(tag: &str) -> Option<i64> {
+    let expires: u64 = tag.trim().parse().ok()?;
+    Some(expires as i64 - now() as i64)
+}
+
+/// Formats a number of seconds in its largest whole unit, like `3h`.
+pub(crate) fn format(seconds: u64) -> String {
+    for (unit, size) in [
+        ("w", 7 * 24 * 60 * 60),
+        ("d", 24 * 60 * 60),
+        ("h", 60 * 60),
+        ("m", 60),
+    ] {
+        if seconds >= size {
+            return format!("{}{}", seconds / size, unit);
+        }
+    }
+    format!("{}s", seconds)
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ttl() {
+        assert_eq!(parse("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse("24h"), Ok(Duration::from_secs(86_400)));
+        assert_eq!(parse("2w"), Ok(Duration::from_secs(1_209_600)));
+        assert!(parse("24").is_err());
+        assert!(parse("0h").is_err());
+        assert!(parse("h").is_err());
+        assert!(parse("1.5h").is_err());
+
+        assert_eq!(tag_key("CI_TOKEN"), "CI_TOKEN.expires");
+        assert_eq!(tagged_key("CI_TOKEN.expires"), Some("CI_TOKEN"));
+        assert_eq!(tagged_key("CI_TOKEN"), None);
+        assert_eq!(tagged_key(".expires"), None);
+
+        let left = remaining(&tag(Duration::from_secs(3600))).unwrap();
+        assert!((3590..=3600).contains(&left));
+        assert!(remaining("0").unwrap() < 0);
+        assert_eq!(remaining("soon"), None);
+
+        assert_eq!(format(45), "45s");
+        assert_eq!(format(3 * 3600 + 59), "3h");
+        assert_eq!(format(9 * 86_400), "1w");
+    }
+}
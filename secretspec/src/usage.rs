@@ -0,0 +1,48 @@
+//! How much a project stores in its providers.
+//!
+//! `secretspec usage` counts the items each profile has stored and adds up
+//! the size of their values, so teams notice automation creating keys it
+//! never cleans up before a shared vault hits its quota.
+
+/// What one profile stores in its provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    /// The profile
+    pub profile: String,
+    /// Name of the provider the profile uses
+    pub provider: &'static str,
+    /// Number of items stored, or `None` if the provider can't list them
+    pub items: Option<usize>,
+    /// Total size of the stored values in bytes, or `None` if the provider
+    /// can't list them
+    pub bytes: Option<u64>,
+}
+
+/// Formats a size in bytes with a binary unit, like `1.5 KiB`.
+pub(crate) fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB"] {
+        if size < 1024.0 {
+            return match unit {
+                "B" => format!("{} B", bytes),
+                _ => format!("{:.1} {}", size, unit),
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} GiB", size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
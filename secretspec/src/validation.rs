@@ -1,6 +1,7 @@
 //! Validation results for secret checking
 
 use crate::config::Resolved;
+use crate::memory::Locked;
 use secrecy::SecretString;
 use std::collections::HashMap;
 use std::fmt;
@@ -16,6 +17,9 @@ pub struct ValidatedSecrets {
     pub missing_optional: Vec<String>,
     /// List of secrets using their default values (name, default_value)
     pub with_defaults: Vec<(String, String)>,
+    /// Pages of the resolved values locked into RAM, unlocked on drop after
+    /// the values were zeroed
+    pub(crate) _locked: Locked,
 }
 
 /// Container for validation errors
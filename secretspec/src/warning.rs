@@ -0,0 +1,52 @@
+//! Warnings about problems that don't fail an operation.
+//!
+//! Failing to renew a lease, lock secrets into memory or send a notification
+//! doesn't stop a command, but the user should hear about it. The CLI prints
+//! these warnings to stderr; programs embedding the library can route them
+//! to their own logging with [`set_warning_handler`] instead. Notices the
+//! user has to act on, like touching a security key, go the same way.
+
+use std::sync::RwLock;
+
+/// A function receiving warnings.
+type Handler = Box<dyn Fn(&str) + Send + Sync>;
+
+/// The handler set by the program, or `None` to print to stderr.
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+/// Sends warnings and notices to `handler` instead of printing them to
+/// stderr.
+///
+/// # Example
+///
+/// ```
+/// secretspec::set_warning_handler(|message| {
+///     // e.g. log::warn!("{}", message)
+///     let _ = message;
+/// });
+/// ```
+pub fn set_warning_handler(handler: impl Fn(&str) + Send + Sync + 'static) {
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(handler));
+}
+
+/// Reports a warning to the handler, or to stderr if none is set.
+pub(crate) fn warn(message: impl AsRef<str>) {
+    report(message.as_ref(), "Warning: ");
+}
+
+/// Reports a notice to the handler, or to stderr without the `Warning:`
+/// prefix if none is set.
+pub(crate) fn notice(message: impl AsRef<str>) {
+    report(message.as_ref(), "");
+}
+
+/// Sends a message to the handler, or prints it to stderr after `prefix`.
+fn report(message: &str, prefix: &str) {
+    match &*HANDLER.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(handler) => handler(message),
+        None => {
+            crate::output::clear_progress();
+            eprintln!("{}{}", prefix, message)
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Change detection for `secretspec watch`
+//!
+//! `watch` resolves the declared secrets at an interval and runs a hook when
+//! any of them changes. No provider can push changes, so every provider is
+//! polled. Between polls only SHA-256 digests of the values are kept, never
+//! the values themselves.
+
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::process::{Command, ExitStatus};
+use zeroize::Zeroizing;
+
+/// Environment variable listing the changed secrets for the hook.
+pub(crate) const CHANGED_ENV: &str = "SECRETSPEC_CHANGED";
+
+/// Digests of resolved values by secret name.
+pub(crate) type Fingerprints = HashMap<String, [u8; 32]>;
+
+/// Returns the digests of resolved values.
+pub(crate) fn fingerprint(secrets: &HashMap<String, SecretString>) -> Fingerprints {
+    secrets
+        .iter()
+        .map(|(name, value)| {
+            let digest = Sha256::digest(value.expose_secret().as_bytes());
+            (name.clone(), digest.into())
+        })
+        .collect()
+}
+
+/// Returns the names of secrets that were added, removed or changed, sorted.
+pub(crate) fn changed(previous: &Fingerprints, current: &Fingerprints) -> Vec<String> {
+    let names: BTreeSet<&String> = previous.keys().chain(current.keys()).collect();
+    names
+        .into_iter()
+        .filter(|name| previous.get(*name) != current.get(*name))
+        .cloned()
+        .collect()
+}
+
+/// Runs the hook through the shell with the given environment.
+///
+/// The names of the changed secrets are passed in `SECRETSPEC_CHANGED`,
+/// separated by commas.
+pub(crate) fn run_hook(
+    hook: &str,
+    env: &HashMap<String, Zeroizing<String>>,
+    changed: &[String],
+) -> io::Result<ExitStatus> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", hook]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", hook]);
+        cmd
+    };
+    cmd.env_clear()
+        .envs(env.iter().map(|(name, value)| (name, value.as_str())))
+        .env(CHANGED_ENV, changed.join(","))
+        .status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprints(values: &[(&str, &str)]) -> Fingerprints {
+        fingerprint(
+            &values
+                .iter()
+                .map(|(name, value)| (name.to_string(), SecretString::from(*value)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_changed() {
+        let previous = fingerprints(&[("API_KEY", "one"), ("DB_URL", "postgres://"), ("OLD", "x")]);
+        let current = fingerprints(&[("API_KEY", "two"), ("DB_URL", "postgres://"), ("NEW", "y")]);
+        assert_eq!(changed(&previous, &current), ["API_KEY", "NEW", "OLD"]);
+        assert!(changed(&current, &current).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hook() {
+        let env = HashMap::from([("API_KEY".to_string(), Zeroizing::new("two".to_string()))]);
+        let changed = ["API_KEY".to_string(), "NEW".to_string()];
+        let status = run_hook(
+            r#"[ "$API_KEY" = two ] && [ "$SECRETSPEC_CHANGED" = API_KEY,NEW ]"#,
+            &env,
+            &changed,
+        )
+        .unwrap();
+        assert!(status.success());
+    }
+}